@@ -0,0 +1,26 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rustic_canvas::bench::{self, BenchConfig, ARTIST_COUNTS};
+
+// Checkout throughput of one shared registry as the artist count grows. Each
+// sample is a whole run, so the thread start-up is counted too; run
+// `rustic-canvas bench` for lock wait times alongside.
+fn checkouts(c: &mut Criterion) {
+    let mut group = c.benchmark_group("checkouts");
+    group.sample_size(10);
+    for artists in ARTIST_COUNTS {
+        let config = BenchConfig {
+            artists,
+            ..BenchConfig::default()
+        };
+        group.throughput(Throughput::Elements((artists * config.rounds) as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(artists),
+            &config,
+            |b, config| b.iter(|| bench::run(config)),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, checkouts);
+criterion_main!(benches);
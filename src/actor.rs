@@ -0,0 +1,357 @@
+use crate::{
+    error::RegistryError,
+    i18n::Message,
+    interrupt, logging,
+    queueing::{QueueStats, RequestTiming},
+    registry::Checkout,
+    simulation::{
+        book_returns, finish_work, simulate_task_delay, simulated_clock, start_work, Artist,
+        HandBack, SimulationConfig,
+    },
+    ArtistToolRegistry,
+};
+use std::{
+    sync::{
+        mpsc::{self, Receiver, Sender, SyncSender},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Instant,
+};
+
+// Where the actor sends its one reply to a request.
+pub type Responder<T> = SyncSender<T>;
+
+pub struct CheckoutRequest {
+    pub artist_id: usize,
+    pub tools: Vec<String>,
+    pub reply: Responder<Result<Checkout, RegistryError>>,
+}
+
+pub struct ReturnRequest {
+    pub artist_id: usize,
+    pub tools: Vec<String>,
+    pub reply: Responder<Result<(), RegistryError>>,
+}
+
+type Inspection = Box<dyn FnOnce(&mut ArtistToolRegistry) + Send>;
+
+pub enum Request {
+    Checkout(CheckoutRequest),
+    Return(ReturnRequest),
+    // Anything else, run against the registry on the actor's thread.
+    Inspect(Inspection),
+}
+
+// One thread owns the registry, and with it the shared resources, and
+// handles requests one at a time in the order they arrive. Nothing else
+// touches either, so there is no lock to contend for or order to get wrong.
+pub struct InventoryActor;
+
+impl InventoryActor {
+    // The thread runs until every handle is dropped, then hands the
+    // registry back through the join handle.
+    pub fn spawn(
+        registry: ArtistToolRegistry,
+    ) -> (InventoryHandle, JoinHandle<ArtistToolRegistry>) {
+        let (sender, requests) = mpsc::channel();
+        let thread = thread::spawn(move || Self::serve(registry, requests));
+        (InventoryHandle { sender }, thread)
+    }
+
+    fn serve(mut registry: ArtistToolRegistry, requests: Receiver<Request>) -> ArtistToolRegistry {
+        for request in requests {
+            // A requester that gave up waiting has nothing to be told.
+            match request {
+                Request::Checkout(request) => {
+                    let checkout = registry.tool_registry(request.artist_id, request.tools);
+                    let _ = request.reply.send(checkout);
+                }
+                Request::Return(request) => {
+                    let returned = registry.tool_return(request.artist_id, request.tools);
+                    let _ = request.reply.send(returned);
+                }
+                Request::Inspect(inspect) => inspect(&mut registry),
+            }
+        }
+        registry
+    }
+}
+
+// Cheap to clone; one per thread that talks to the actor.
+#[derive(Clone)]
+pub struct InventoryHandle {
+    sender: Sender<Request>,
+}
+
+impl InventoryHandle {
+    pub fn checkout(
+        &self,
+        artist_id: usize,
+        tools: Vec<String>,
+    ) -> Result<Checkout, RegistryError> {
+        self.ask(|reply| {
+            Request::Checkout(CheckoutRequest {
+                artist_id,
+                tools,
+                reply,
+            })
+        })
+    }
+
+    pub fn return_tools(&self, artist_id: usize, tools: Vec<String>) -> Result<(), RegistryError> {
+        self.ask(|reply| {
+            Request::Return(ReturnRequest {
+                artist_id,
+                tools,
+                reply,
+            })
+        })
+    }
+
+    // Runs `inspect` on the actor's thread and returns what it returned.
+    pub fn with<T: Send + 'static>(
+        &self,
+        inspect: impl FnOnce(&mut ArtistToolRegistry) -> T + Send + 'static,
+    ) -> T {
+        self.ask(|reply| {
+            Request::Inspect(Box::new(move |registry| {
+                let _ = reply.send(inspect(registry));
+            }))
+        })
+    }
+
+    fn ask<T>(&self, request: impl FnOnce(Responder<T>) -> Request) -> T {
+        let (reply, answer) = mpsc::sync_channel(1);
+        self.sender
+            .send(request(reply))
+            .expect("Inventory actor stopped");
+        answer.recv().expect("Inventory actor stopped")
+    }
+}
+
+// Runs every simulated artist on its own thread, like
+// `simulation::run_artists`, but each one talks to the actor instead of
+// locking the registry and resources.
+pub fn run_artists(
+    inventory: &InventoryHandle,
+    config: &SimulationConfig,
+) -> (QueueStats, Vec<RegistryError>) {
+    let mut handles = vec![];
+    let mut results = vec![];
+    let stepped = *config;
+    let clock = inventory.with(move |registry| simulated_clock(registry, &stepped));
+    for id in 0..config.artists {
+        let inventory = inventory.clone();
+        let clock = clock.clone();
+        let config = *config;
+        let handle = thread::spawn(move || {
+            let mut artist = inventory.with(move |registry| Artist::new(id, registry, &config));
+            let mut timings = vec![];
+            let mut errors = vec![];
+            for round in 0..config.rounds {
+                if interrupt::global().requested() {
+                    break;
+                }
+                let return_tools = round + 1 < config.rounds;
+                match artis_task(&inventory, &mut artist, &config, return_tools) {
+                    Ok(timing) => timings.extend(timing),
+                    Err(error) => errors.push(error),
+                }
+                if let Some(clock) = &clock {
+                    clock.tick();
+                }
+            }
+            (timings, errors)
+        });
+        if config.seed.is_some() {
+            results.push(handle.join().expect("Thread panicked"));
+        } else {
+            handles.push(handle);
+        }
+    }
+    results.extend(
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("Thread panicked")),
+    );
+
+    let mut queue_stats = QueueStats::new();
+    let mut errors = vec![];
+    for (timings, refused) in results {
+        for timing in timings {
+            queue_stats.record(timing);
+        }
+        errors.extend(refused);
+    }
+    (queue_stats, errors)
+}
+
+// Runs the artists against `registry` through an actor of its own and puts
+// the registry back once they are done.
+pub fn run(
+    registry: &mut ArtistToolRegistry,
+    config: &SimulationConfig,
+) -> (QueueStats, Vec<RegistryError>) {
+    let placeholder = ArtistToolRegistry::new(&registry.shared_resources);
+    let (inventory, actor) = InventoryActor::spawn(std::mem::replace(registry, placeholder));
+    let result = run_artists(&inventory, config);
+    drop(inventory);
+    *registry = actor.join().expect("Thread panicked");
+    result
+}
+
+// One round of one artist, returning the queue timing of each requested tool.
+pub fn artis_task(
+    inventory: &InventoryHandle,
+    artist: &mut Artist,
+    config: &SimulationConfig,
+    return_tools: bool,
+) -> Result<Vec<RequestTiming>, RegistryError> {
+    let id = artist.id;
+    let _task = tracing::info_span!("artist_task", artist_id = id).entered();
+    let tools_per_artist = config.tools_per_artist;
+    let (range, tools) = inventory.with(move |registry| {
+        let range = tools_per_artist.unwrap_or_else(|| registry.tool_count_range(id));
+        let tools = registry
+            .shared_resources
+            .lock()
+            .map(|resources| resources.tools.clone())
+            .map_err(RegistryError::from);
+        (range, tools)
+    });
+    let tools = artist.pick_tools(&tools?, range);
+    if !config.quiet {
+        tracing::info!(
+            artist_id = id,
+            tools = %tools.join(","),
+            "{}",
+            Message::SelectedTools(id, &tools)
+        );
+    }
+
+    // The actor serves requests in arrival order, so the wait for it starts
+    // when the request is sent and service is indistinguishable from it.
+    let arrival = Instant::now();
+    let checkout = inventory.checkout(id, tools.clone())?;
+    let departure = Instant::now();
+    // Stock is only read here to log what is left, not to change it.
+    let resources = inventory.with(|registry| Arc::clone(&registry.shared_resources));
+    if !config.quiet && !checkout.lent.is_empty() {
+        logging::checked_out(id, &checkout.lent, &*resources.lock()?);
+    }
+    if !config.quiet {
+        for tool in &checkout.queued {
+            tracing::info!(artist_id = id, tool = %tool, "{}", Message::CheckoutQueued(id, tool));
+        }
+        for tool in &checkout.refused {
+            tracing::warn!(artist_id = id, tool = %tool, "{}", Message::LoanCapReached(tool));
+        }
+    }
+    let timings = tools
+        .into_iter()
+        .map(|tool| RequestTiming {
+            tool,
+            arrival,
+            service_start: arrival,
+            departure,
+        })
+        .collect();
+
+    // Whatever was lent goes into a sketch, which is painted over the work
+    // time and hung once finished.
+    let paints = resources.lock()?.paints.clone();
+    let work = artist.plan_work(checkout.lent, &paints, config);
+    let config = *config;
+    let started = work.clone();
+    let artwork = inventory.with(move |registry| start_work(registry, id, &started, &config))?;
+    #[cfg(debug_assertions)]
+    simulate_task_delay(work.task.work_time);
+    let returns = work.returns.clone();
+    inventory.with(move |registry| finish_work(registry, id, &work, artwork, &config))?;
+    let hand_back = if return_tools {
+        artist.hand_back(returns, &config)
+    } else {
+        HandBack::default()
+    };
+    let reorders = artist.reorders_due(&config);
+    inventory.with(move |registry| book_returns(registry, id, &hand_back, &reorders, &config))?;
+    Ok(timings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        chaos::Chaos, resources::TOTAL_ITEMS, tool_limits::ToolCountRange, SharedResources, State,
+    };
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_actor_serves_requests_and_hands_registry_back() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let (inventory, actor) = InventoryActor::spawn(ArtistToolRegistry::new(&resources));
+        let checkout = inventory
+            .checkout(10, vec!["brush".to_string(), "tape".to_string()])
+            .unwrap();
+        assert_eq!(checkout.lent, vec!["brush", "tape"]);
+        assert!(matches!(
+            inventory.return_tools(10, vec!["easel".to_string()]),
+            Err(RegistryError::NotHeld { .. })
+        ));
+        inventory
+            .return_tools(10, vec!["tape".to_string()])
+            .unwrap();
+        assert_eq!(inventory.with(|registry| registry.outstanding(10)), 1);
+
+        let config = SimulationConfig {
+            artists: 4,
+            rounds: 2,
+            seed: Some(7),
+            quiet: true,
+            ..SimulationConfig::default()
+        };
+        let (_, errors) = run_artists(&inventory, &config);
+        drop(inventory);
+        let registry = actor.join().unwrap();
+        let checkouts = registry
+            .history()
+            .filter(|entry| entry.state == Some(State::TakeOut))
+            .count();
+        assert_eq!(checkouts + errors.len(), 1 + 4 * 2);
+        assert_eq!(registry.outstanding(10), 1);
+        assert!(resources.lock().unwrap().stock("brush") < TOTAL_ITEMS);
+    }
+
+    #[test]
+    fn test_actor_artists_lose_tools_to_chaos_like_threaded_ones() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        let config = SimulationConfig {
+            artists: 2,
+            tools_per_artist: Some(ToolCountRange { min: 1, max: 1 }),
+            rounds: 3,
+            seed: Some(1),
+            quiet: true,
+            chaos: Some(Chaos {
+                lose: 1.0,
+                ..Chaos::default()
+            }),
+            ..SimulationConfig::default()
+        };
+        let (_, errors) = run(&mut registry, &config);
+        assert!(errors.is_empty(), "{:?}", errors);
+        let count = |kind| {
+            registry
+                .events
+                .events()
+                .iter()
+                .filter(|event| event.kind == kind)
+                .count()
+        };
+        // The same two rounds' tools are lost and replaced as without the
+        // actor.
+        assert_eq!((count(State::Lost), count(State::New)), (4, 4));
+        assert_eq!(count(State::Return), 0);
+    }
+}
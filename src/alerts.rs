@@ -0,0 +1,250 @@
+use crate::{i18n::Message, units::Amount};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{self, Read, Write},
+    net::TcpStream,
+    time::Duration,
+};
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LowStockAlert {
+    pub at: DateTime<Utc>,
+    pub item: String,
+    pub stock: Amount,
+    pub threshold: Amount,
+    // Whose checkout took the item below its threshold.
+    pub artist_id: usize,
+}
+
+// Stock each item should stay at or above. Items without a threshold never
+// alert.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LowStockThresholds {
+    below: HashMap<String, Amount>,
+}
+
+impl LowStockThresholds {
+    // In the item's own unit; a threshold in the wrong one never alerts.
+    pub fn set(&mut self, item: &str, threshold: impl Into<Amount>) {
+        self.below.insert(item.to_string(), threshold.into());
+    }
+
+    pub fn get(&self, item: &str) -> Option<Amount> {
+        self.below.get(item).copied()
+    }
+
+    // Alerts for the items a checkout of `taken` moved from at or above their
+    // threshold to below it. Items that were already low don't alert again.
+    pub fn crossed(
+        &self,
+        taken: &BTreeMap<String, Amount>,
+        stock: &BTreeMap<String, Amount>,
+        artist_id: usize,
+        at: DateTime<Utc>,
+    ) -> Vec<LowStockAlert> {
+        taken
+            .iter()
+            .filter_map(|(item, taken)| {
+                let threshold = self.get(item)?;
+                let left = *stock.get(item)?;
+                let before = left.checked_add(*taken)?;
+                (left < threshold && before >= threshold).then(|| LowStockAlert {
+                    at,
+                    item: item.clone(),
+                    stock: left,
+                    threshold,
+                    artist_id,
+                })
+            })
+            .collect()
+    }
+}
+
+// Somewhere low-stock alerts are sent.
+pub trait Notifier: Send + Sync {
+    fn notify(&mut self, alert: &LowStockAlert) -> io::Result<()>;
+}
+
+// Prints each alert on standard output.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConsoleNotifier;
+
+impl Notifier for ConsoleNotifier {
+    fn notify(&mut self, alert: &LowStockAlert) -> io::Result<()> {
+        println!("{}", Message::LowStock(alert));
+        Ok(())
+    }
+}
+
+// POSTs each alert as JSON to a plain `http://host[:port]/path` URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebhookNotifier {
+    host: String,
+    path: String,
+}
+
+impl WebhookNotifier {
+    // None unless the URL is plain HTTP.
+    pub fn new(url: &str) -> Option<Self> {
+        let rest = url.strip_prefix("http://")?;
+        let (host, path) = match rest.find('/') {
+            Some(slash) => rest.split_at(slash),
+            None => (rest, "/"),
+        };
+        (!host.is_empty()).then(|| Self {
+            host: host.to_string(),
+            path: path.to_string(),
+        })
+    }
+}
+
+impl Notifier for WebhookNotifier {
+    fn notify(&mut self, alert: &LowStockAlert) -> io::Result<()> {
+        let addr = if self.host.contains(':') {
+            self.host.clone()
+        } else {
+            format!("{}:80", self.host)
+        };
+        let mut stream = TcpStream::connect(addr)?;
+        stream.set_read_timeout(Some(WEBHOOK_TIMEOUT))?;
+        stream.set_write_timeout(Some(WEBHOOK_TIMEOUT))?;
+        let body = serde_json::to_string(alert)?;
+        write!(
+            stream,
+            "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.path,
+            self.host,
+            body.len(),
+            body
+        )?;
+        let mut status = [0; 12];
+        stream.read_exact(&mut status)?;
+        match &status[9..10] {
+            b"2" => Ok(()),
+            _ => Err(io::Error::other(format!(
+                "webhook answered {}",
+                String::from_utf8_lossy(&status[9..])
+            ))),
+        }
+    }
+}
+
+// Hands each alert to a closure, e.g. to queue an order or feed a test.
+pub struct CallbackNotifier<F>(pub F);
+
+impl<F: FnMut(&LowStockAlert) + Send + Sync> Notifier for CallbackNotifier<F> {
+    fn notify(&mut self, alert: &LowStockAlert) -> io::Result<()> {
+        (self.0)(alert);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        units::{Count, Kilograms},
+        ArtistToolRegistry, SharedResources,
+    };
+    use std::{
+        io::BufRead,
+        net::TcpListener,
+        sync::{Arc, Mutex},
+        thread,
+    };
+
+    #[test]
+    fn test_checkout_below_threshold_notifies_once() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        resources.lock().unwrap().low_stock.set("canvas", Count(8));
+        resources
+            .lock()
+            .unwrap()
+            .low_stock
+            .set("red", Kilograms::whole(5));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        let seen = Arc::new(Mutex::new(vec![]));
+        {
+            let seen = Arc::clone(&seen);
+            registry.add_notifier(CallbackNotifier(move |alert: &LowStockAlert| {
+                seen.lock()
+                    .unwrap()
+                    .push((alert.item.clone(), alert.stock, alert.artist_id));
+            }));
+        }
+
+        let canvas = || vec!["canvas".to_string()];
+        registry.tool_registry(1, canvas()).unwrap();
+        registry.tool_registry(2, canvas()).unwrap();
+        assert!(seen.lock().unwrap().is_empty());
+        registry.tool_registry(3, canvas()).unwrap();
+        registry.tool_registry(4, canvas()).unwrap();
+        registry
+            .paint_checkout(5, vec![("red".to_string(), Kilograms::grams(6_500))])
+            .unwrap();
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                ("canvas".to_string(), Count(7).into(), 3),
+                ("red".to_string(), Kilograms::grams(3_500).into(), 5)
+            ]
+        );
+
+        // Back above the threshold, the next dip alerts again.
+        registry.tool_return(3, canvas()).unwrap();
+        registry.tool_return(4, canvas()).unwrap();
+        registry.tool_registry(6, canvas()).unwrap();
+        assert_eq!(seen.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_webhook_posts_alert_as_json() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hooks/stock", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = io::BufReader::new(stream);
+            let mut head = vec![];
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                head.push(line);
+            }
+            let length: usize = head
+                .iter()
+                .find_map(|line| line.strip_prefix("Content-Length: "))
+                .unwrap()
+                .trim()
+                .parse()
+                .unwrap();
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            reader
+                .get_mut()
+                .write_all(b"HTTP/1.1 204 No Content\r\n\r\n")
+                .unwrap();
+            (head[0].clone(), String::from_utf8(body).unwrap())
+        });
+
+        let mut webhook = WebhookNotifier::new(&url).unwrap();
+        let alert = LowStockAlert {
+            at: Utc::now(),
+            item: "canvas".to_string(),
+            stock: Count(1).into(),
+            threshold: Count(2).into(),
+            artist_id: 9,
+        };
+        webhook.notify(&alert).unwrap();
+        let (request_line, body) = server.join().unwrap();
+        assert_eq!(request_line, "POST /hooks/stock HTTP/1.1\r\n");
+        assert!(body.contains(r#""item":"canvas""#), "{}", body);
+        assert_eq!(WebhookNotifier::new("https://example.com/hook"), None);
+    }
+}
@@ -0,0 +1,254 @@
+use crate::{
+    drying::{DryingRacks, RackedArtwork},
+    money::Money,
+    units::Kilograms,
+    ArtistToolRegistry,
+};
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Stage {
+    #[default]
+    Sketch,
+    Painting,
+    Finished,
+}
+
+impl Stage {
+    pub fn next(self) -> Option<Stage> {
+        match self {
+            Stage::Sketch => Some(Stage::Painting),
+            Stage::Painting => Some(Stage::Finished),
+            Stage::Finished => None,
+        }
+    }
+}
+
+// One piece of work, from the first sketch to the gallery wall and on to a
+// buyer. `id` is given when it's hung.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Artwork {
+    pub id: usize,
+    pub artist_id: usize,
+    pub artist_name: Option<String>,
+    pub stage: Stage,
+    pub tools: Vec<String>,
+    pub paints: Vec<(String, Kilograms)>,
+    // Time spent with each tool, summed over the tools.
+    pub tool_time: Duration,
+    pub started: Option<DateTime<Utc>>,
+    pub finished: Option<DateTime<Utc>>,
+    // When it came off the drying rack; a wet piece isn't for sale yet.
+    pub dried: Option<DateTime<Utc>>,
+    pub price: Option<Money>,
+    pub sold: Option<DateTime<Utc>>,
+}
+
+impl Artwork {
+    // Moves the work on a stage, stamping it with `now` when it's finished.
+    // False if it already was.
+    pub fn advance(&mut self, now: DateTime<Utc>) -> bool {
+        let Some(next) = self.stage.next() else {
+            return false;
+        };
+        self.stage = next;
+        if next == Stage::Finished {
+            self.finished = Some(now);
+        }
+        true
+    }
+
+    // Only paint put on while painting counts towards the work.
+    pub fn add_paint(&mut self, color: &str, kg: Kilograms) {
+        if self.stage == Stage::Painting {
+            self.paints.push((color.to_string(), kg));
+        }
+    }
+
+    pub fn work_with_tools(&mut self, time: Duration) {
+        self.tool_time += time * self.tools.len() as u32;
+    }
+}
+
+// Finished artworks in the order they were hung.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Gallery {
+    artworks: Vec<Artwork>,
+}
+
+impl Gallery {
+    // Returns the id the artwork was hung under, counting from 1.
+    pub fn hang(&mut self, mut artwork: Artwork) -> usize {
+        artwork.id = self.artworks.len() + 1;
+        self.artworks.push(artwork);
+        self.artworks.len()
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Artwork> {
+        self.artworks.get(id.checked_sub(1)?)
+    }
+
+    pub fn get_mut(&mut self, id: usize) -> Option<&mut Artwork> {
+        self.artworks.get_mut(id.checked_sub(1)?)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Artwork> {
+        self.artworks.iter()
+    }
+
+    // Dry pieces still for sale, priced or not.
+    pub fn listed(&self) -> impl Iterator<Item = &Artwork> {
+        self.iter()
+            .filter(|artwork| artwork.sold.is_none() && artwork.dried.is_some())
+    }
+
+    pub fn by_artist(&self, artist_id: usize) -> impl Iterator<Item = &Artwork> {
+        self.iter()
+            .filter(move |artwork| artwork.artist_id == artist_id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.artworks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.artworks.is_empty()
+    }
+}
+
+impl ArtistToolRegistry {
+    // A sketch made with tools the artist has just checked out.
+    pub fn start_artwork(&self, artist_id: usize, tools: Vec<String>) -> Artwork {
+        Artwork {
+            artist_id,
+            artist_name: self
+                .profile(artist_id)
+                .map(|profile| profile.name.clone())
+                .filter(|name| !name.is_empty()),
+            tools,
+            started: Some(self.now()),
+            ..Artwork::default()
+        }
+    }
+
+    // Takes the artwork through whatever stages it has left and hangs it in
+    // the gallery. With drying racks it then dries on one, or waits for one
+    // to free up; without, it's dry at once. Returns its gallery id.
+    pub fn finish_artwork(&mut self, mut artwork: Artwork) -> usize {
+        let now = self.now();
+        while artwork.advance(now) {}
+        if self.racks.is_none() {
+            artwork.dried = artwork.finished;
+        }
+        let artist_id = artwork.artist_id;
+        let id = self.gallery.hang(artwork);
+        if let Some(racks) = &mut self.racks {
+            racks.complete(id, artist_id, self.drying_time, now);
+        }
+        self.dry_artworks();
+        id
+    }
+
+    // Gives completed canvases `capacity` racks to dry on, each taking
+    // `drying_time`, before they can be sold.
+    pub fn set_drying_racks(&mut self, capacity: usize, drying_time: chrono::Duration) {
+        self.racks = Some(DryingRacks::new(capacity));
+        self.drying_time = drying_time;
+    }
+
+    // Takes whatever has dried by now off the racks, marking it ready for
+    // sale, and racks the canvases waiting for the space.
+    pub fn dry_artworks(&mut self) -> Vec<RackedArtwork> {
+        let now = self.now();
+        let Some(racks) = &mut self.racks else {
+            return vec![];
+        };
+        let dried = racks.advance(now);
+        for racked in &dried {
+            if let Some(artwork) = self.gallery.get_mut(racked.artwork_id) {
+                artwork.dried = Some(racked.dry_at);
+            }
+        }
+        dried
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        clock::MockClock,
+        error::RegistryError,
+        profiles::{ArtistProfile, Profiles},
+        SharedResources,
+    };
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_artwork_goes_from_sketch_to_gallery() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        registry.set_profiles(Profiles {
+            artists: vec![ArtistProfile {
+                id: 2,
+                name: "Frida".to_string(),
+                ..ArtistProfile::default()
+            }],
+        });
+
+        let mut artwork =
+            registry.start_artwork(2, vec!["brush".to_string(), "palette".to_string()]);
+        assert_eq!(artwork.stage, Stage::Sketch);
+        artwork.add_paint("red", Kilograms::whole(2));
+        assert!(artwork.paints.is_empty());
+        assert!(artwork.advance(registry.now()));
+        artwork.add_paint("red", Kilograms::grams(1_500));
+        artwork.work_with_tools(Duration::from_millis(10));
+        assert_eq!(artwork.tool_time, Duration::from_millis(20));
+
+        assert_eq!(registry.finish_artwork(artwork), 1);
+        registry.finish_artwork(registry.start_artwork(5, vec![]));
+        let hung = registry.gallery.get(1).unwrap();
+        assert_eq!(hung.stage, Stage::Finished);
+        assert_eq!(hung.artist_name.as_deref(), Some("Frida"));
+        assert_eq!(
+            hung.paints,
+            vec![("red".to_string(), Kilograms::grams(1_500))]
+        );
+        assert!(hung.finished >= hung.started);
+        assert_eq!(registry.gallery.len(), 2);
+        assert_eq!(registry.gallery.by_artist(5).count(), 1);
+        assert!(registry.gallery.get(0).is_none());
+    }
+
+    #[test]
+    fn test_wet_artworks_wait_for_a_rack_and_cannot_be_sold() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        let clock = MockClock::new(Utc::now());
+        registry.set_clock(Arc::new(clock.clone()));
+        registry.set_drying_racks(1, chrono::Duration::hours(2));
+
+        let first = registry.finish_artwork(registry.start_artwork(1, vec![]));
+        let second = registry.finish_artwork(registry.start_artwork(2, vec![]));
+        assert_eq!(registry.gallery.listed().count(), 0);
+        assert_eq!(
+            registry.sell_artwork(first),
+            Err(RegistryError::StillDrying(first))
+        );
+
+        clock.advance(chrono::Duration::hours(2));
+        assert_eq!(
+            registry.sell_artwork(first),
+            Err(RegistryError::Unpriced(first))
+        );
+        assert!(registry.gallery.get(second).unwrap().dried.is_none());
+        let stats = registry.racks.as_ref().unwrap().stats();
+        assert_eq!((stats.occupied, stats.queued, stats.max_queued), (1, 0, 1));
+
+        clock.advance(chrono::Duration::hours(2));
+        assert_eq!(registry.dry_artworks().len(), 1);
+        assert_eq!(registry.gallery.listed().count(), 2);
+    }
+}
@@ -0,0 +1,201 @@
+use crate::{
+    error::RegistryError,
+    i18n::Message,
+    interrupt, logging,
+    queueing::{QueueStats, RequestTiming},
+    simulation::{
+        book_returns, finish_work, simulated_clock, start_work, Artist, HandBack, SimulationConfig,
+    },
+    ArtistToolRegistry,
+};
+use std::{io, sync::Arc, time::Instant};
+use tokio::{
+    sync::{Mutex, RwLock},
+    task::JoinSet,
+};
+use tracing::Instrument;
+
+// The registry as the async simulation shares it. Picking tools only reads
+// it; checkouts and returns take it for writing.
+pub type AsyncRegistry = Arc<RwLock<ArtistToolRegistry>>;
+
+// Runs every simulated artist as a task on the current runtime rather than
+// on a thread of its own, so thousands can be in flight at once. Behaves
+// like `simulation::run_artists`, seeded runs included.
+pub async fn run_artists(
+    registry: &AsyncRegistry,
+    config: &SimulationConfig,
+) -> (QueueStats, Vec<RegistryError>) {
+    let queue_stats = Arc::new(Mutex::new(QueueStats::new()));
+    let mut tasks = JoinSet::new();
+    let mut errors = vec![];
+    let clock = simulated_clock(&mut *registry.write().await, config);
+
+    for id in 0..config.artists {
+        let registry = Arc::clone(registry);
+        let clock = clock.clone();
+        let queue_stats = Arc::clone(&queue_stats);
+        let config = *config;
+        let artist = async move {
+            let mut artist = Artist::new(id, &*registry.read().await, &config);
+            let mut errors = vec![];
+            for round in 0..config.rounds {
+                if interrupt::global().requested() {
+                    break;
+                }
+                let return_tools = round + 1 < config.rounds;
+                let task = artis_task(&registry, &mut artist, &queue_stats, &config, return_tools)
+                    .instrument(tracing::info_span!("artist_task", artist_id = id));
+                if let Err(error) = task.await {
+                    errors.push(error);
+                }
+                if let Some(clock) = &clock {
+                    clock.tick();
+                }
+            }
+            errors
+        };
+        if config.seed.is_some() {
+            errors.extend(artist.await);
+        } else {
+            tasks.spawn(artist);
+        }
+    }
+
+    while let Some(artist) = tasks.join_next().await {
+        errors.extend(artist.expect("Task panicked"));
+    }
+    let queue_stats = Arc::try_unwrap(queue_stats)
+        .expect("every artist has finished")
+        .into_inner();
+    (queue_stats, errors)
+}
+
+// Runs the artists against `registry` on a runtime of their own, for
+// callers outside async code.
+pub fn run(
+    registry: &mut ArtistToolRegistry,
+    config: &SimulationConfig,
+) -> io::Result<(QueueStats, Vec<RegistryError>)> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    let placeholder = ArtistToolRegistry::new(&registry.shared_resources);
+    let shared = Arc::new(RwLock::new(std::mem::replace(registry, placeholder)));
+    let (queue_stats, errors) = runtime.block_on(run_artists(&shared, config));
+    *registry = Arc::try_unwrap(shared)
+        .unwrap_or_else(|_| panic!("every artist has finished"))
+        .into_inner();
+    Ok((queue_stats, errors))
+}
+
+pub async fn artis_task(
+    registry: &RwLock<ArtistToolRegistry>,
+    artist: &mut Artist,
+    queue_stats: &Mutex<QueueStats>,
+    config: &SimulationConfig,
+    return_tools: bool,
+) -> Result<(), RegistryError> {
+    let id = artist.id;
+    let tools = {
+        let registry = registry.read().await;
+        let range = config
+            .tools_per_artist
+            .unwrap_or_else(|| registry.tool_count_range(id));
+        let resources = registry.shared_resources.lock()?;
+        artist.pick_tools(&resources.tools, range)
+    };
+    if !config.quiet {
+        tracing::info!(
+            artist_id = id,
+            tools = %tools.join(","),
+            "{}",
+            Message::SelectedTools(id, &tools)
+        );
+    }
+
+    let arrival = Instant::now();
+    let mut writer = registry.write().await;
+    let service_start = Instant::now();
+    let checkout = writer.tool_registry(id, tools.clone())?;
+    if !config.quiet && !checkout.lent.is_empty() {
+        logging::checked_out(id, &checkout.lent, &*writer.shared_resources.lock()?);
+    }
+    drop(writer);
+    let departure = Instant::now();
+    if !config.quiet {
+        for tool in &checkout.queued {
+            tracing::info!(artist_id = id, tool = %tool, "{}", Message::CheckoutQueued(id, tool));
+        }
+        for tool in &checkout.refused {
+            tracing::warn!(artist_id = id, tool = %tool, "{}", Message::LoanCapReached(tool));
+        }
+    }
+
+    let mut stats = queue_stats.lock().await;
+    for tool in tools {
+        stats.record(RequestTiming {
+            tool,
+            arrival,
+            service_start,
+            departure,
+        });
+    }
+    drop(stats);
+
+    // Whatever was lent goes into a sketch, which is painted over the work
+    // time and hung once finished.
+    let work = {
+        let registry = registry.read().await;
+        let resources = registry.shared_resources.lock()?;
+        artist.plan_work(checkout.lent, &resources.paints, config)
+    };
+    let artwork = start_work(&mut *registry.write().await, id, &work, config)?;
+    #[cfg(debug_assertions)]
+    tokio::time::sleep(work.task.work_time).await;
+    finish_work(&mut *registry.write().await, id, &work, artwork, config)?;
+    let hand_back = if return_tools {
+        artist.hand_back(work.returns, config)
+    } else {
+        HandBack::default()
+    };
+    let reorders = artist.reorders_due(config);
+    book_returns(
+        &mut *registry.write().await,
+        id,
+        &hand_back,
+        &reorders,
+        config,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{units::Count, SharedResources, State};
+    use std::sync::Mutex as StdMutex;
+
+    #[test]
+    fn test_thousands_of_artists_run_as_tasks() {
+        let resources = Arc::new(StdMutex::new(SharedResources::default()));
+        {
+            let mut resources = resources.lock().unwrap();
+            for tool in resources.tools.amounts() {
+                resources.set_quantity(&tool.0, Count(100_000)).unwrap();
+            }
+        }
+        let config = SimulationConfig {
+            artists: 2_000,
+            rounds: 2,
+            quiet: true,
+            ..SimulationConfig::default()
+        };
+        let mut registry = ArtistToolRegistry::new(&resources);
+        let (_, errors) = run(&mut registry, &config).unwrap();
+        assert!(errors.is_empty(), "{:?}", errors);
+        let checkouts = registry
+            .history()
+            .filter(|entry| entry.state == Some(State::TakeOut))
+            .count();
+        assert_eq!(checkouts, 2 * 2_000);
+        assert!(registry.history_for_artist(1_999).count() >= 3);
+    }
+}
@@ -0,0 +1,274 @@
+use crate::{registry::HELD_STATES, ArtistToolPreferences, ArtistToolRegistry, State};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::{collections::BTreeMap, fmt};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct AuditLine {
+    pub tool: String,
+    pub in_stock: usize,
+    // Units ever checked out, returned and written off, from the history.
+    pub checked_out: usize,
+    pub returned: usize,
+    pub retired: usize,
+    pub sold: usize,
+    // Units lost and neither found nor retired; they're off the books.
+    pub lost: usize,
+    // Units the history says artists still hold, damaged included.
+    pub held: usize,
+    // Units the loan counter says are out.
+    pub on_loan: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub discrepancy: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AuditReport {
+    pub at: DateTime<Utc>,
+    pub lines: Vec<AuditLine>,
+    // Units each artist has lost and that haven't been found, by artist.
+    pub losses: BTreeMap<usize, usize>,
+}
+
+impl AuditReport {
+    pub fn discrepancies(&self) -> impl Iterator<Item = &AuditLine> {
+        self.lines.iter().filter(|line| line.discrepancy.is_some())
+    }
+}
+
+impl fmt::Display for AuditReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<16} {:>6} {:>6} {:>8} {:>7} {:>5} {:>5} {:>5} {:>7}",
+            "tool", "stock", "out", "returned", "retired", "sold", "lost", "held", "on loan"
+        )?;
+        for line in &self.lines {
+            write!(
+                f,
+                "{:<16} {:>6} {:>6} {:>8} {:>7} {:>5} {:>5} {:>5} {:>7}",
+                line.tool,
+                line.in_stock,
+                line.checked_out,
+                line.returned,
+                line.retired,
+                line.sold,
+                line.lost,
+                line.held,
+                line.on_loan
+            )?;
+            match &line.discrepancy {
+                Some(discrepancy) => writeln!(f, "  ! {}", discrepancy)?,
+                None => writeln!(f)?,
+            }
+        }
+        for (artist_id, lost) in &self.losses {
+            writeln!(f, "artist {} lost {} unit(s)", artist_id, lost)?;
+        }
+        Ok(())
+    }
+}
+
+impl ArtistToolRegistry {
+    // Reconciles the checkout history against stock and the loan counter,
+    // tool by tool, and records an `Audit` entry for `auditor_id` listing
+    // every tool that didn't add up. Lost units were written off when they
+    // were reported, so they count as neither held nor on loan.
+    pub fn audit(&mut self, auditor_id: usize) -> AuditReport {
+        let now = self.now();
+        let on_loan = |state: Option<State>| {
+            state.is_some_and(|state| HELD_STATES.contains(&state) && state != State::Lost)
+        };
+        let mut lines: BTreeMap<String, AuditLine> = BTreeMap::new();
+        let mut losses: BTreeMap<usize, usize> = BTreeMap::new();
+        // Units retired straight from the shelf were never out.
+        let mut shelf_retired: BTreeMap<String, usize> = BTreeMap::new();
+        for entry in &self.artist_tool_preferences {
+            let from = entry.source_state();
+            for &symbol in &entry.preferred_tools {
+                let Some(tool) = self.interner.resolve(symbol).map(str::to_string) else {
+                    continue;
+                };
+                if from == Some(State::Return) && entry.state == Some(State::Retire) {
+                    *shelf_retired.entry(tool.clone()).or_insert(0) += 1;
+                }
+                let line = lines.entry(tool).or_default();
+                if on_loan(from) {
+                    line.held = line.held.saturating_sub(1);
+                }
+                if on_loan(entry.state) {
+                    line.held += 1;
+                }
+                if from == Some(State::Lost) {
+                    line.lost = line.lost.saturating_sub(1);
+                }
+                match entry.state {
+                    Some(State::TakeOut) => line.checked_out += 1,
+                    Some(State::Return) => line.returned += 1,
+                    Some(State::Retire) => line.retired += 1,
+                    Some(State::Sold) => line.sold += 1,
+                    Some(State::Lost) => line.lost += 1,
+                    _ => {}
+                }
+                // Retiring a lost unit doesn't clear the artist who lost it.
+                match (from, entry.state) {
+                    (_, Some(State::Lost)) => *losses.entry(entry.artist_id).or_insert(0) += 1,
+                    (Some(State::Lost), Some(State::Return)) => {
+                        if let Some(lost) = losses.get_mut(&entry.artist_id) {
+                            *lost = lost.saturating_sub(1);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        losses.retain(|_, lost| *lost > 0);
+
+        {
+            let resources = self
+                .shared_resources
+                .lock()
+                .expect("Failed to lock resources");
+            for tool in resources.tools.iter() {
+                lines.entry(tool.name.clone()).or_default().in_stock = tool.quantity.get();
+            }
+            for (tool, count) in resources.loan_caps.loans() {
+                lines.entry(tool).or_default().on_loan = count;
+            }
+        }
+
+        let lines: Vec<AuditLine> = lines
+            .into_iter()
+            .map(|(tool, mut line)| {
+                let retired_while_out =
+                    line.retired - shelf_retired.get(&tool).copied().unwrap_or(0);
+                line.tool = tool;
+                let outstanding = line
+                    .checked_out
+                    .saturating_sub(line.returned + retired_while_out + line.lost);
+                line.discrepancy = if line.held != line.on_loan {
+                    Some(format!(
+                        "history has {} out, loan counter {}",
+                        line.held, line.on_loan
+                    ))
+                } else if outstanding != line.held {
+                    Some(format!(
+                        "{} checked out and not back, but {} held",
+                        outstanding, line.held
+                    ))
+                } else {
+                    None
+                };
+                line
+            })
+            .collect();
+
+        let flagged: Vec<String> = lines
+            .iter()
+            .filter(|line| line.discrepancy.is_some())
+            .map(|line| line.tool.clone())
+            .collect();
+        self.record_event(Some(auditor_id), State::Audit, &flagged, now);
+        self.artist_tool_preferences.push(ArtistToolPreferences {
+            artist_id: auditor_id,
+            preferred_tools: flagged
+                .iter()
+                .map(|tool| self.interner.intern(tool))
+                .collect(),
+            datetime: Some(now),
+            state: Some(State::Audit),
+            ..Default::default()
+        });
+        AuditReport {
+            at: now,
+            lines,
+            losses,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{resources::TOTAL_ITEMS, units::Count, SharedResources};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_audit_reconciles_history_with_stock() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        registry
+            .tool_registry(1, vec!["brush".to_string(), "tape".to_string()])
+            .unwrap();
+        registry.tool_return(1, vec!["tape".to_string()]).unwrap();
+        registry.report_lost(1, "brush").unwrap();
+
+        let report = registry.audit(99);
+        assert_eq!(report.discrepancies().count(), 0);
+        let brush = &report.lines[0];
+        assert_eq!(
+            (
+                brush.tool.as_str(),
+                brush.in_stock,
+                brush.checked_out,
+                brush.lost,
+                brush.held
+            ),
+            ("brush", 9, 1, 1, 0)
+        );
+        assert_eq!(report.losses, BTreeMap::from([(1, 1)]));
+        assert!(report.to_string().lines().count() > report.lines.len());
+
+        // A unit that left the shelf without going through the registry.
+        resources.lock().unwrap().loan_caps.set_on_loan("tape", 1);
+        let report = registry.audit(99);
+        let tape = report
+            .lines
+            .iter()
+            .find(|line| line.tool == "tape")
+            .unwrap();
+        assert!(tape.discrepancy.is_some());
+        let entry = registry.artist_tool_preferences.last().unwrap();
+        assert_eq!((entry.artist_id, entry.state), (99, Some(State::Audit)));
+        assert_eq!(
+            entry.preferred_tools,
+            vec![registry.interner.intern("tape")]
+        );
+    }
+
+    #[test]
+    fn test_lost_units_are_written_off_and_tallied() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        let tools = vec!["brush".to_string(), "brush".to_string(), "tape".to_string()];
+        registry.tool_registry(1, tools).unwrap();
+        registry.tool_registry(2, vec!["tape".to_string()]).unwrap();
+        registry.report_lost(1, "brush").unwrap();
+        registry.report_lost(1, "brush").unwrap();
+        registry.report_lost(1, "tape").unwrap();
+        registry.report_lost(2, "tape").unwrap();
+        assert_eq!(resources.lock().unwrap().loan_caps.on_loan("brush"), 0);
+
+        registry.recover_lost(1, "brush").unwrap();
+        registry.retire(1, "tape").unwrap();
+        registry.recover_lost(2, "tape").unwrap();
+        {
+            let resources = resources.lock().unwrap();
+            assert_eq!(resources.stock("brush"), TOTAL_ITEMS - Count(1));
+            assert_eq!(resources.loan_caps.on_loan("brush"), 0);
+            assert_eq!(resources.stock("tape"), TOTAL_ITEMS - Count(1));
+        }
+
+        let report = registry.audit(99);
+        assert_eq!(report.discrepancies().count(), 0, "{}", report);
+        assert_eq!(report.losses, BTreeMap::from([(1, 2)]));
+        let lost: Vec<(&str, usize)> = report
+            .lines
+            .iter()
+            .filter(|line| line.lost > 0)
+            .map(|line| (line.tool.as_str(), line.lost))
+            .collect();
+        assert_eq!(lost, vec![("brush", 1)]);
+        assert!(report.to_string().contains("artist 1 lost 2 unit(s)"));
+    }
+}
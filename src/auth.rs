@@ -0,0 +1,273 @@
+use crate::{
+    daemon::{error_reply, ok_reply},
+    i18n::Message,
+};
+use chrono::{DateTime, Duration, Utc};
+use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use std::{collections::HashMap, fmt::Write as _};
+
+const TOKEN_LENGTH: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Principal {
+    Manager,
+    Artist(usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Session {
+    pub artist_id: usize,
+    pub expires_at: DateTime<Utc>,
+}
+
+// What the daemon should do with a command line once auth has looked at it.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Gate<'a> {
+    // Auth handled the command itself, or refused it.
+    Reply(String),
+    // An authorised registry command to run.
+    Forward(&'a str),
+}
+
+// Sessions for daemon clients. Artists log in with a PIN the manager set, or
+// use a personal access token the manager issued; either way the token only
+// allows checkouts, returns and paint under the artist's own id. The manager
+// token allows everything, including revoking other tokens.
+pub struct Auth {
+    manager_token: String,
+    session_length: Duration,
+    pins: HashMap<usize, String>,
+    sessions: HashMap<String, Session>,
+}
+
+impl Auth {
+    pub fn new(manager_token: &str, session_length: Duration) -> Self {
+        Self {
+            manager_token: manager_token.to_string(),
+            session_length,
+            pins: HashMap::new(),
+            sessions: HashMap::new(),
+        }
+    }
+
+    pub fn set_pin(&mut self, artist_id: usize, pin: &str) {
+        self.pins.insert(artist_id, pin.to_string());
+    }
+
+    pub fn login(&mut self, artist_id: usize, pin: &str, now: DateTime<Utc>) -> Option<String> {
+        if self.pins.get(&artist_id).map(String::as_str) != Some(pin) {
+            return None;
+        }
+        Some(self.issue(artist_id, self.session_length, now))
+    }
+
+    pub fn issue(&mut self, artist_id: usize, valid_for: Duration, now: DateTime<Utc>) -> String {
+        let token: String = thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(TOKEN_LENGTH)
+            .map(char::from)
+            .collect();
+        self.sessions.insert(
+            token.clone(),
+            Session {
+                artist_id,
+                expires_at: now + valid_for,
+            },
+        );
+        token
+    }
+
+    pub fn revoke(&mut self, token: &str) -> bool {
+        self.sessions.remove(token).is_some()
+    }
+
+    // Revokes every token held by the artist and returns how many there were.
+    pub fn revoke_artist(&mut self, artist_id: usize) -> usize {
+        let before = self.sessions.len();
+        self.sessions
+            .retain(|_, session| session.artist_id != artist_id);
+        before - self.sessions.len()
+    }
+
+    pub fn principal(&mut self, token: &str, now: DateTime<Utc>) -> Option<Principal> {
+        if token == self.manager_token {
+            return Some(Principal::Manager);
+        }
+        self.sessions.retain(|_, session| session.expires_at > now);
+        self.sessions
+            .get(token)
+            .map(|session| Principal::Artist(session.artist_id))
+    }
+
+    // Accepts `login <artist_id> <pin>` unauthenticated and `auth <token>
+    // <command>` for everything else.
+    pub fn gate<'a>(&mut self, line: &'a str, now: DateTime<Utc>) -> Gate<'a> {
+        let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+        match command {
+            "login" => {
+                let mut words = rest.split_whitespace();
+                let artist_id = words.next().and_then(|id| id.parse().ok());
+                let pin = words.next().unwrap_or_default();
+                match artist_id.and_then(|id| self.login(id, pin, now)) {
+                    Some(token) => Gate::Reply(format!("ok: {}\n", token)),
+                    None => Gate::Reply(error_reply(Message::LoginFailed)),
+                }
+            }
+            "auth" => {
+                let (token, command) = rest.trim().split_once(' ').unwrap_or((rest.trim(), ""));
+                match self.principal(token, now) {
+                    Some(principal) => self.authorised(principal, command.trim(), now),
+                    None => Gate::Reply(error_reply(Message::InvalidToken)),
+                }
+            }
+            _ => Gate::Reply(error_reply(Message::AuthRequired)),
+        }
+    }
+
+    fn authorised<'a>(
+        &mut self,
+        principal: Principal,
+        line: &'a str,
+        now: DateTime<Utc>,
+    ) -> Gate<'a> {
+        let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let mut words = rest.split_whitespace();
+        let denied = || Gate::Reply(error_reply(Message::NotAllowed));
+
+        match (principal, command) {
+            (Principal::Manager, "set-pin") => {
+                match (words.next().and_then(|id| id.parse().ok()), words.next()) {
+                    (Some(artist_id), Some(pin)) => {
+                        self.set_pin(artist_id, pin);
+                        Gate::Reply(ok_reply(Message::PinSet(artist_id)))
+                    }
+                    _ => Gate::Reply(error_reply(Message::CommandUsage(
+                        "set-pin <artist_id> <pin>",
+                    ))),
+                }
+            }
+            (Principal::Manager, "issue") => {
+                let artist_id = words.next().and_then(|id| id.parse().ok());
+                let days = words
+                    .next()
+                    .and_then(|days| days.parse().ok())
+                    .unwrap_or(30);
+                match artist_id {
+                    Some(artist_id) => {
+                        let token = self.issue(artist_id, Duration::days(days), now);
+                        Gate::Reply(format!("ok: {}\n", token))
+                    }
+                    None => Gate::Reply(error_reply(Message::CommandUsage(
+                        "issue <artist_id> [days]",
+                    ))),
+                }
+            }
+            (Principal::Manager, "revoke") => match words.next() {
+                Some(target) if target.starts_with("artist:") => {
+                    match target["artist:".len()..].parse() {
+                        Ok(artist_id) => Gate::Reply(ok_reply(Message::TokensRevoked(
+                            self.revoke_artist(artist_id),
+                        ))),
+                        Err(_) => Gate::Reply(error_reply(Message::InvalidArtistId(
+                            &target["artist:".len()..],
+                        ))),
+                    }
+                }
+                Some(token) if self.revoke(token) => Gate::Reply(ok_reply(Message::TokenRevoked)),
+                _ => Gate::Reply(error_reply(Message::NoSuchToken)),
+            },
+            (Principal::Manager, "sessions") => {
+                let mut sessions: Vec<_> = self.sessions.values().collect();
+                sessions.sort_by_key(|session| (session.artist_id, session.expires_at));
+                let mut text = String::new();
+                for session in sessions {
+                    let _ = writeln!(
+                        text,
+                        "artist {} expires {}",
+                        session.artist_id,
+                        session.expires_at.format("%Y-%m-%d %H:%M UTC")
+                    );
+                }
+                Gate::Reply(text)
+            }
+            (Principal::Manager, _) => Gate::Forward(line),
+            (Principal::Artist(_), "status") => Gate::Forward(line),
+            (Principal::Artist(own), "checkout" | "return" | "paint") => {
+                let id = words
+                    .next()
+                    .filter(|word| *word != "atomic")
+                    .or_else(|| words.next());
+                if id.and_then(|id| id.parse().ok()) == Some(own) {
+                    Gate::Forward(line)
+                } else {
+                    denied()
+                }
+            }
+            (Principal::Artist(_), _) => denied(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reply(gate: Gate) -> String {
+        match gate {
+            Gate::Reply(text) => text,
+            Gate::Forward(line) => format!("forward {}", line),
+        }
+    }
+
+    fn token(text: String) -> String {
+        text.trim().trim_start_matches("ok: ").to_string()
+    }
+
+    #[test]
+    fn test_artist_token_is_scoped_to_own_loans() {
+        let now = Utc::now();
+        let mut auth = Auth::new("boss", Duration::hours(8));
+        reply(auth.gate("auth boss set-pin 3 1234", now));
+        assert_eq!(
+            reply(auth.gate("login 3 0000", now)),
+            "error: login failed\n"
+        );
+        let artist = token(reply(auth.gate("login 3 1234", now)));
+
+        let own = format!("auth {} checkout 3 brush", artist);
+        assert_eq!(reply(auth.gate(&own, now)), "forward checkout 3 brush");
+        let other = format!("auth {} checkout 4 brush", artist);
+        assert!(reply(auth.gate(&other, now)).contains("not allowed"));
+        let atomic = format!("auth {} checkout atomic 4 brush", artist);
+        assert!(reply(auth.gate(&atomic, now)).contains("not allowed"));
+        let dump = format!("auth {} dump", artist);
+        assert!(reply(auth.gate(&dump, now)).contains("not allowed"));
+        assert!(reply(auth.gate("status", now)).contains("authentication required"));
+        assert_eq!(reply(auth.gate("auth boss dump", now)), "forward dump");
+    }
+
+    #[test]
+    fn test_tokens_expire_and_can_be_revoked() {
+        let now = Utc::now();
+        let mut auth = Auth::new("boss", Duration::hours(8));
+        let short = token(reply(auth.gate("auth boss issue 5 1", now)));
+        let status = format!("auth {} status", short);
+        assert_eq!(reply(auth.gate(&status, now)), "forward status");
+        let later = now + Duration::days(2);
+        assert!(reply(auth.gate(&status, later)).contains("expired"));
+
+        let long = token(reply(auth.gate("auth boss issue 5 30", now)));
+        assert!(reply(auth.gate("auth boss sessions", now)).contains("artist 5"));
+        let revoke = format!("auth boss revoke {}", long);
+        assert_eq!(reply(auth.gate(&revoke, now)), "ok: revoked\n");
+        let status = format!("auth {} status", long);
+        assert!(reply(auth.gate(&status, now)).contains("invalid"));
+
+        auth.issue(6, Duration::days(1), now);
+        auth.issue(6, Duration::days(1), now);
+        assert_eq!(
+            reply(auth.gate("auth boss revoke artist:6", now)),
+            "ok: revoked 2 token(s)\n"
+        );
+    }
+}
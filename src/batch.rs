@@ -0,0 +1,252 @@
+use crate::{
+    error::RegistryError,
+    registry::{Checkout, CheckoutRequest},
+    ArtistToolRegistry,
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt::Write as _};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum BatchOp {
+    Checkout {
+        artist_id: usize,
+        tools: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchReport {
+    // One entry per operation, in order: the number of items lent, or why the
+    // operation was refused.
+    pub results: Vec<Result<usize, String>>,
+    pub applied: usize,
+    // The checkouts that went through, by artist, as the registry made them.
+    pub checkouts: Vec<(usize, Checkout)>,
+    // Set when an all-or-nothing batch was refused because an item failed.
+    pub rolled_back: bool,
+}
+
+impl BatchReport {
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for (index, result) in self.results.iter().enumerate() {
+            let _ = match result {
+                Ok(lent) if self.rolled_back => {
+                    writeln!(text, "{} ok: {} item(s) not lent", index + 1, lent)
+                }
+                Ok(lent) => writeln!(text, "{} ok: {} item(s) lent", index + 1, lent),
+                Err(error) => writeln!(text, "{} error: {}", index + 1, error),
+            };
+        }
+        let _ = if self.rolled_back {
+            writeln!(text, "rolled back: nothing applied")
+        } else {
+            writeln!(text, "applied {} of {}", self.applied, self.results.len())
+        };
+        text
+    }
+}
+
+// Accepts a JSON array of operations, or a command file with one daemon-style
+// `checkout <artist_id> <tool>, <tool>` per line (`#` starts a comment).
+pub fn parse_batch(text: &str) -> Result<Vec<BatchOp>, String> {
+    if text.trim_start().starts_with('[') {
+        return serde_json::from_str(text).map_err(|error| error.to_string());
+    }
+    let mut ops = vec![];
+    for (number, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let invalid = || {
+            format!(
+                "line {}: expected 'checkout <artist_id> <tools>'",
+                number + 1
+            )
+        };
+        let (command, rest) = line.split_once(' ').ok_or_else(invalid)?;
+        let (id, tools) = rest.trim().split_once(' ').ok_or_else(invalid)?;
+        if command != "checkout" {
+            return Err(invalid());
+        }
+        ops.push(BatchOp::Checkout {
+            artist_id: id.parse().map_err(|_| invalid())?,
+            tools: tools
+                .split(',')
+                .map(str::trim)
+                .filter(|tool| !tool.is_empty())
+                .map(str::to_string)
+                .collect(),
+        });
+    }
+    Ok(ops)
+}
+
+// Each operation is checked against what is left after the ones before it and
+// only applied if every tool in it can be lent, so no operation is ever half
+// applied. With `atomic`, one refused operation refuses the whole batch.
+pub fn run(ops: &[BatchOp], registry: &mut ArtistToolRegistry, atomic: bool) -> BatchReport {
+    let results = check(ops, registry);
+    let rolled_back = atomic && results.iter().any(Result::is_err);
+    let mut checkouts = vec![];
+    if !rolled_back {
+        let requests: Vec<CheckoutRequest> = ops
+            .iter()
+            .zip(&results)
+            .filter(|(_, result)| result.is_ok())
+            .map(
+                |(BatchOp::Checkout { artist_id, tools }, _)| CheckoutRequest {
+                    artist_id: *artist_id,
+                    tools: tools.clone(),
+                },
+            )
+            .collect();
+        let artists: Vec<usize> = requests.iter().map(|request| request.artist_id).collect();
+        checkouts = artists
+            .into_iter()
+            .zip(registry.checkout_batch(requests))
+            .filter_map(|(artist_id, checkout)| Some((artist_id, checkout.ok()?)))
+            .collect();
+    }
+    BatchReport {
+        results,
+        applied: checkouts.len(),
+        checkouts,
+        rolled_back,
+    }
+}
+
+fn check(ops: &[BatchOp], registry: &ArtistToolRegistry) -> Vec<Result<usize, String>> {
+    let resources = registry
+        .shared_resources
+        .lock()
+        .expect("Failed to lock resources");
+    let mut stock: HashMap<&str, usize> = resources
+        .tools
+        .iter()
+        .map(|tool| (tool.name.as_str(), tool.quantity.get()))
+        .collect();
+    let mut on_loan: HashMap<&str, usize> = HashMap::new();
+    let now = registry.now();
+
+    ops.iter()
+        .map(|op| {
+            let BatchOp::Checkout { artist_id, tools } = op;
+            registry
+                .check_tool_count(*artist_id, tools.len())
+                .map_err(|error| error.to_string())?;
+            let mut wanted: HashMap<&str, usize> = HashMap::new();
+            for tool in tools {
+                *wanted.entry(tool.as_str()).or_insert(0) += 1;
+            }
+            for tool in tools {
+                let tool = tool.as_str();
+                let in_stock = stock.get(tool).copied().unwrap_or(0);
+                if in_stock < wanted[tool] {
+                    return Err(format!("'{}' is not available", tool));
+                }
+                let reserved = registry.reservations.held_for_others(*artist_id, tool, now);
+                if in_stock < wanted[tool] + reserved {
+                    return Err(RegistryError::ReservedForOthers(tool.to_string()).to_string());
+                }
+                let lent = *on_loan
+                    .entry(tool)
+                    .or_insert_with(|| resources.loan_caps.on_loan(tool));
+                if resources
+                    .loan_caps
+                    .cap(tool)
+                    .is_some_and(|cap| lent + wanted[tool] > cap)
+                {
+                    return Err(format!("loan cap reached for '{}'", tool));
+                }
+            }
+            for (tool, count) in wanted {
+                *stock.get_mut(tool).expect("checked above") -= count;
+                *on_loan.get_mut(tool).expect("checked above") += count;
+            }
+            Ok(tools.len())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{units::Count, SharedResources};
+    use std::sync::{Arc, Mutex};
+
+    fn registry() -> ArtistToolRegistry {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        resources.lock().unwrap().loan_caps.set_cap("canvas", 1);
+        ArtistToolRegistry::new(&resources)
+    }
+
+    #[test]
+    fn test_parse_command_file_and_json() {
+        let file = "# monday slips\ncheckout 1 brush, tape\n\ncheckout 2 canvas # late\n";
+        let ops = parse_batch(file).unwrap();
+        assert_eq!(
+            ops[0],
+            BatchOp::Checkout {
+                artist_id: 1,
+                tools: vec!["brush".to_string(), "tape".to_string()]
+            }
+        );
+        let json = serde_json::to_string(&ops).unwrap();
+        assert!(json.contains("\"op\":\"checkout\""));
+        assert_eq!(parse_batch(&json).unwrap(), ops);
+        assert!(parse_batch("lend 1 brush").is_err());
+    }
+
+    #[test]
+    fn test_run_reports_per_item_and_skips_refused_items() {
+        let mut registry = registry();
+        let ops = parse_batch(
+            "checkout 1 canvas\ncheckout 2 canvas, brush\ncheckout 3 easel\ncheckout 4 brush\n",
+        )
+        .unwrap();
+        let report = run(&ops, &mut registry, false);
+
+        assert_eq!(report.results[0], Ok(1));
+        assert_eq!(
+            report.results[1],
+            Err("loan cap reached for 'canvas'".to_string())
+        );
+        assert!(report.results[2].is_err());
+        assert_eq!(report.applied, 2);
+        // The refused second slip didn't take a brush.
+        let resources = registry.shared_resources.lock().unwrap();
+        assert_eq!(resources.stock("brush"), Count(9));
+    }
+
+    #[test]
+    fn test_atomic_batch_applies_nothing_on_failure() {
+        let mut registry = registry();
+        let ops = parse_batch("checkout 1 brush\ncheckout 2 easel\n").unwrap();
+        let report = run(&ops, &mut registry, true);
+        assert!(report.rolled_back);
+        assert_eq!(report.applied, 0);
+        assert!(registry.artist_tool_preferences.is_empty());
+        assert!(report.to_text().ends_with("rolled back: nothing applied\n"));
+    }
+
+    #[test]
+    fn test_run_refuses_checkouts_outside_tool_limits() {
+        let mut registry = registry();
+        registry.set_tool_limits(crate::tool_limits::ToolLimits::new(2, 3));
+        let ops = parse_batch(
+            "checkout 1 brush
+checkout 2 brush, tape
+",
+        )
+        .unwrap();
+        let report = run(&ops, &mut registry, false);
+        assert_eq!(
+            report.results[0],
+            Err("artist 1 may check out 2 to 3 tools, not 1".to_string())
+        );
+        assert_eq!(report.results[1], Ok(2));
+    }
+}
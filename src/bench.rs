@@ -0,0 +1,114 @@
+use crate::{
+    lock_stats::{LockSnapshot, LockStats},
+    units::Count,
+    ArtistToolRegistry, SharedResources,
+};
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+// The artist counts measured when none are given.
+pub const ARTIST_COUNTS: [usize; 5] = [1, 10, 100, 1_000, 10_000];
+
+// Each artist is a thread of its own and does next to nothing with its
+// stack, so ten thousand of them fit comfortably.
+const ARTIST_STACK: usize = 256 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BenchConfig {
+    pub artists: usize,
+    // Checkouts each artist makes, returning the tool after each one.
+    pub rounds: usize,
+}
+
+impl Default for BenchConfig {
+    fn default() -> Self {
+        Self {
+            artists: 10,
+            rounds: 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BenchResult {
+    pub artists: usize,
+    pub checkouts: u64,
+    pub elapsed: Duration,
+    // The registry lock, over every checkout and return.
+    pub lock: LockSnapshot,
+}
+
+impl BenchResult {
+    pub fn checkouts_per_sec(&self) -> f64 {
+        self.checkouts as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+
+    // Over every acquisition, so uncontended ones count as no wait at all.
+    pub fn avg_lock_wait_us(&self) -> f64 {
+        self.lock.total_wait_ns as f64 / self.lock.acquisitions.max(1) as f64 / 1_000.0
+    }
+}
+
+// Every artist checks out a brush and hands it back, `rounds` times, against
+// one registry behind one lock, the way the daemon and the threaded
+// simulation share it. There is a brush for everybody, so what's measured is
+// the lock and the registry's bookkeeping rather than the stock running out.
+pub fn run(config: &BenchConfig) -> BenchResult {
+    let resources = Arc::new(Mutex::new(SharedResources::default()));
+    resources
+        .lock()
+        .expect("Failed to lock resources")
+        .restock("brush", Count::of(config.artists))
+        .expect("brushes are counted");
+    let registry = Mutex::new(ArtistToolRegistry::new(&resources));
+    let stats = LockStats::new();
+    let checkouts = AtomicU64::new(0);
+
+    let started = Instant::now();
+    thread::scope(|scope| {
+        for id in 0..config.artists {
+            let (registry, stats, checkouts) = (&registry, &stats, &checkouts);
+            thread::Builder::new()
+                .stack_size(ARTIST_STACK)
+                .spawn_scoped(scope, move || {
+                    let brush = || vec!["brush".to_string()];
+                    for _ in 0..config.rounds {
+                        if stats.lock(registry).tool_registry(id, brush()).is_ok() {
+                            checkouts.fetch_add(1, Ordering::Relaxed);
+                            let _ = stats.lock(registry).tool_return(id, brush());
+                        }
+                    }
+                })
+                .expect("Failed to spawn artist");
+        }
+    });
+    BenchResult {
+        artists: config.artists,
+        checkouts: checkouts.into_inner(),
+        elapsed: started.elapsed(),
+        lock: stats.snapshot(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_artist_gets_every_checkout() {
+        let result = run(&BenchConfig {
+            artists: 20,
+            rounds: 3,
+        });
+        assert_eq!(result.checkouts, 60);
+        assert_eq!(result.lock.acquisitions, 120);
+        assert!(result.checkouts_per_sec() > 0.0);
+        assert!(result.avg_lock_wait_us() >= 0.0);
+    }
+}
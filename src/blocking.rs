@@ -0,0 +1,226 @@
+use crate::{
+    error::{RegistryError, ResourceError},
+    ArtistToolRegistry,
+};
+use std::{
+    sync::{Condvar, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+struct Waiter {
+    ticket: u64,
+    tools: Vec<String>,
+}
+
+// A registry shared between artists who would rather wait for a tool than be
+// refused. Returns made through it wake every waiting checkout. Waiters are
+// served in the order they started waiting: nobody takes a tool that an
+// artist who has waited longer is still waiting for, so a popular tool can't
+// keep going to whoever happens to grab the lock first.
+pub struct BlockingRegistry {
+    registry: Mutex<ArtistToolRegistry>,
+    returned: Condvar,
+    // Only touched with `registry` held.
+    waiting: Mutex<Vec<Waiter>>,
+    next_ticket: Mutex<u64>,
+}
+
+impl BlockingRegistry {
+    pub fn new(registry: ArtistToolRegistry) -> Self {
+        Self {
+            registry: Mutex::new(registry),
+            returned: Condvar::new(),
+            waiting: Mutex::new(vec![]),
+            next_ticket: Mutex::new(0),
+        }
+    }
+
+    pub fn lock(&self) -> Result<MutexGuard<'_, ArtistToolRegistry>, RegistryError> {
+        Ok(self.registry.lock()?)
+    }
+
+    // Call after putting stock back through `lock`, e.g. a finished repair.
+    pub fn notify_returned(&self) {
+        self.returned.notify_all();
+    }
+
+    // Waits until every requested tool can be lent at once, then checks them
+    // all out. Other refusals, like rate limits, are returned straight away.
+    pub fn checkout_blocking(
+        &self,
+        id: usize,
+        tools: Vec<String>,
+        timeout: Duration,
+    ) -> Result<(), RegistryError> {
+        let started = Instant::now();
+        let mut registry = self.registry.lock()?;
+        let ticket = {
+            let mut next = self.next_ticket.lock()?;
+            *next += 1;
+            *next
+        };
+        let mut queued = false;
+        loop {
+            let behind = self
+                .waiting
+                .lock()?
+                .iter()
+                .any(|waiter| waiter.ticket < ticket && shares_a_tool(&waiter.tools, &tools));
+            // Checked before `checkout_all` so that waiting doesn't use up
+            // the artist's rate limit.
+            let available = registry.shared_resources.lock()?.check_all(&tools);
+            match available {
+                Ok(()) if !behind => {
+                    self.leave(ticket, queued)?;
+                    return registry.checkout_all(id, tools);
+                }
+                Ok(()) | Err(ResourceError::Unavailable(_)) => {}
+                Err(error) => {
+                    self.leave(ticket, queued)?;
+                    return Err(error.into());
+                }
+            }
+            if !queued {
+                self.waiting.lock()?.push(Waiter {
+                    ticket,
+                    tools: tools.clone(),
+                });
+                queued = true;
+                let now = registry.now();
+                registry.starvation.kept_waiting(id, now);
+            }
+            let waited = started.elapsed();
+            if waited >= timeout {
+                self.leave(ticket, queued)?;
+                return Err(RegistryError::Timeout {
+                    artist_id: id,
+                    waited,
+                });
+            }
+            registry = self.returned.wait_timeout(registry, timeout - waited)?.0;
+        }
+    }
+
+    // Takes a waiter out of line, letting the ones behind it try again.
+    fn leave(&self, ticket: u64, queued: bool) -> Result<(), RegistryError> {
+        if queued {
+            self.waiting
+                .lock()?
+                .retain(|waiter| waiter.ticket != ticket);
+            self.notify_returned();
+        }
+        Ok(())
+    }
+
+    pub fn tool_return(&self, id: usize, tools: Vec<String>) -> Result<(), RegistryError> {
+        self.registry.lock()?.tool_return(id, tools)?;
+        self.notify_returned();
+        Ok(())
+    }
+}
+
+fn shares_a_tool(a: &[String], b: &[String]) -> bool {
+    a.iter().any(|tool| b.contains(tool))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{units::Count, SharedResources};
+    use std::{
+        sync::{Arc, Mutex},
+        thread,
+    };
+
+    fn studio_with_one_easel() -> BlockingRegistry {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        resources
+            .lock()
+            .unwrap()
+            .restock("easel", Count(1))
+            .unwrap();
+        BlockingRegistry::new(ArtistToolRegistry::new(&resources))
+    }
+
+    #[test]
+    fn test_checkout_blocking_waits_for_a_return() {
+        let studio = Arc::new(studio_with_one_easel());
+        let easel = || vec!["easel".to_string()];
+        studio
+            .checkout_blocking(1, easel(), Duration::ZERO)
+            .unwrap();
+
+        let waiter = {
+            let studio = Arc::clone(&studio);
+            thread::spawn(move || studio.checkout_blocking(2, easel(), Duration::from_secs(5)))
+        };
+        thread::sleep(Duration::from_millis(20));
+        studio.tool_return(1, easel()).unwrap();
+        waiter.join().unwrap().unwrap();
+
+        let registry = studio.lock().unwrap();
+        assert!(registry.held_tools(1).is_empty());
+        assert_eq!(registry.held_tools(2).len(), 1);
+    }
+
+    #[test]
+    fn test_waiters_are_served_in_the_order_they_came() {
+        let studio = Arc::new(studio_with_one_easel());
+        let easel = || vec!["easel".to_string()];
+        studio
+            .checkout_blocking(1, easel(), Duration::ZERO)
+            .unwrap();
+        let wait = |id, timeout| {
+            let studio = Arc::clone(&studio);
+            let waiter = thread::spawn(move || studio.checkout_blocking(id, easel(), timeout));
+            thread::sleep(Duration::from_millis(20));
+            waiter
+        };
+        let first = wait(2, Duration::from_secs(5));
+        let second = wait(3, Duration::from_millis(300));
+        studio.tool_return(1, easel()).unwrap();
+
+        first.join().unwrap().unwrap();
+        assert!(matches!(
+            second.join().unwrap(),
+            Err(RegistryError::Timeout { artist_id: 3, .. })
+        ));
+        let registry = studio.lock().unwrap();
+        assert_eq!(registry.held_tools(2).len(), 1);
+        let report = registry.starvation.report(registry.now());
+        assert_eq!(report.len(), 2);
+        assert_eq!(
+            report
+                .iter()
+                .find(|wait| wait.artist_id == 2)
+                .unwrap()
+                .streak,
+            0
+        );
+        assert_eq!(
+            report
+                .iter()
+                .find(|wait| wait.artist_id == 3)
+                .unwrap()
+                .streak,
+            1
+        );
+    }
+
+    #[test]
+    fn test_checkout_blocking_times_out() {
+        let studio = studio_with_one_easel();
+        let easel = || vec!["easel".to_string()];
+        studio
+            .checkout_blocking(1, easel(), Duration::ZERO)
+            .unwrap();
+        assert!(matches!(
+            studio.checkout_blocking(2, easel(), Duration::from_millis(20)),
+            Err(RegistryError::Timeout { artist_id: 2, .. })
+        ));
+        assert!(matches!(
+            studio.checkout_blocking(2, vec!["tape".to_string(); 11], Duration::from_millis(20)),
+            Err(RegistryError::Timeout { .. })
+        ));
+    }
+}
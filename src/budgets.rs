@@ -0,0 +1,313 @@
+use crate::money::{ExchangeRates, MixedCurrencies, Money, UnknownCurrency};
+use chrono::{DateTime, Datelike, Utc};
+use std::{collections::HashMap, fmt};
+
+pub const WARNING_PERCENT: u64 = 80;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Period {
+    pub year: i32,
+    pub month: u32,
+}
+
+impl Period {
+    pub fn of(datetime: DateTime<Utc>) -> Self {
+        Self {
+            year: datetime.year(),
+            month: datetime.month(),
+        }
+    }
+}
+
+impl fmt::Display for Period {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}", self.year, self.month)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CostKind {
+    Restock,
+    Repair,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Charge {
+    pub department: String,
+    pub kind: CostKind,
+    pub amount: Money,
+    pub base_amount: Money,
+    pub period: Period,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BudgetStatus {
+    WithinBudget,
+    Warning { used_percent: u64 },
+    Overridden { used_percent: u64 },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BudgetExceeded {
+    pub department: String,
+    pub limit: Money,
+    pub would_spend: Money,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BudgetError {
+    Exceeded(BudgetExceeded),
+    UnknownCurrency(UnknownCurrency),
+    MixedCurrencies(MixedCurrencies),
+}
+
+impl From<UnknownCurrency> for BudgetError {
+    fn from(error: UnknownCurrency) -> Self {
+        BudgetError::UnknownCurrency(error)
+    }
+}
+
+impl From<MixedCurrencies> for BudgetError {
+    fn from(error: MixedCurrencies) -> Self {
+        BudgetError::MixedCurrencies(error)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DepartmentSpend {
+    pub department: String,
+    pub restock: Money,
+    pub repair: Money,
+    pub limit: Option<Money>,
+    pub total: Money,
+}
+
+// Monthly spending limits per department or class, kept in the studio's base
+// currency. Departments without a limit are never blocked.
+#[derive(Debug, Clone)]
+pub struct DepartmentBudgets {
+    rates: ExchangeRates,
+    limits: HashMap<String, Money>,
+    charges: Vec<Charge>,
+}
+
+impl DepartmentBudgets {
+    pub fn new(rates: ExchangeRates) -> Self {
+        Self {
+            rates,
+            limits: HashMap::new(),
+            charges: vec![],
+        }
+    }
+
+    pub fn set_limit(&mut self, department: &str, limit: Money) -> Result<(), UnknownCurrency> {
+        let limit = self.rates.to_base(limit)?;
+        self.limits.insert(department.to_string(), limit);
+        Ok(())
+    }
+
+    pub fn spent(&self, department: &str, period: Period) -> Result<Money, MixedCurrencies> {
+        self.charges
+            .iter()
+            .filter(|charge| charge.department == department && charge.period == period)
+            .try_fold(Money::zero(self.rates.base()), |total, charge| {
+                total.checked_add(charge.base_amount)
+            })
+    }
+
+    pub fn limit(&self, department: &str) -> Option<Money> {
+        self.limits.get(department).copied()
+    }
+
+    // Books a cost against `department`. Going over the limit is refused unless
+    // `allow_overrun` is set, in which case the charge is booked and flagged.
+    pub fn charge(
+        &mut self,
+        department: &str,
+        kind: CostKind,
+        amount: Money,
+        at: DateTime<Utc>,
+        allow_overrun: bool,
+    ) -> Result<BudgetStatus, BudgetError> {
+        let status = self.check(department, amount, at, allow_overrun)?;
+        self.charges.push(Charge {
+            department: department.to_string(),
+            kind,
+            amount,
+            base_amount: self.rates.to_base(amount)?,
+            period: Period::of(at),
+        });
+        Ok(status)
+    }
+
+    // What `charge` would say, without booking anything.
+    pub fn check(
+        &self,
+        department: &str,
+        amount: Money,
+        at: DateTime<Utc>,
+        allow_overrun: bool,
+    ) -> Result<BudgetStatus, BudgetError> {
+        let base_amount = self.rates.to_base(amount)?;
+        let would_spend = self
+            .spent(department, Period::of(at))?
+            .checked_add(base_amount)?;
+        let status = match self.limits.get(department).copied() {
+            None => BudgetStatus::WithinBudget,
+            Some(limit) if would_spend.minor_units > limit.minor_units => {
+                if !allow_overrun {
+                    return Err(BudgetError::Exceeded(BudgetExceeded {
+                        department: department.to_string(),
+                        limit,
+                        would_spend,
+                    }));
+                }
+                BudgetStatus::Overridden {
+                    used_percent: used_percent(would_spend, limit),
+                }
+            }
+            Some(limit) => {
+                let used_percent = used_percent(would_spend, limit);
+                if used_percent >= WARNING_PERCENT {
+                    BudgetStatus::Warning { used_percent }
+                } else {
+                    BudgetStatus::WithinBudget
+                }
+            }
+        };
+        Ok(status)
+    }
+
+    pub fn charges(&self) -> &[Charge] {
+        &self.charges
+    }
+
+    // Drops charges booked after the first `len`, as a rollback does.
+    pub fn truncate(&mut self, len: usize) {
+        self.charges.truncate(len);
+    }
+
+    // Every department that spent in `period` or has a limit.
+    pub fn report(&self, period: Period) -> Result<Vec<DepartmentSpend>, MixedCurrencies> {
+        let zero = Money::zero(self.rates.base());
+        let mut spend: HashMap<&str, DepartmentSpend> = HashMap::new();
+        for (department, limit) in &self.limits {
+            spend.insert(
+                department,
+                DepartmentSpend {
+                    department: department.clone(),
+                    restock: zero,
+                    repair: zero,
+                    limit: Some(*limit),
+                    total: zero,
+                },
+            );
+        }
+        for charge in self.charges.iter().filter(|charge| charge.period == period) {
+            let entry = spend
+                .entry(&charge.department)
+                .or_insert_with(|| DepartmentSpend {
+                    department: charge.department.clone(),
+                    restock: zero,
+                    repair: zero,
+                    limit: self.limits.get(&charge.department).copied(),
+                    total: zero,
+                });
+            match charge.kind {
+                CostKind::Restock => {
+                    entry.restock = entry.restock.checked_add(charge.base_amount)?
+                }
+                CostKind::Repair => entry.repair = entry.repair.checked_add(charge.base_amount)?,
+            }
+            entry.total = entry.total.checked_add(charge.base_amount)?;
+        }
+
+        let mut report: Vec<DepartmentSpend> = spend.into_values().collect();
+        report.sort_by(|a, b| a.department.cmp(&b.department));
+        Ok(report)
+    }
+}
+
+fn used_percent(spent: Money, limit: Money) -> u64 {
+    if limit.minor_units <= 0 {
+        return 100;
+    }
+    (spent.minor_units.max(0) * 100 / limit.minor_units) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::money::Currency;
+    use chrono::TimeZone;
+
+    fn october() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 10, 3, 9, 0, 0).unwrap()
+    }
+
+    fn usd(minor_units: i64) -> Money {
+        Money::new(minor_units, Currency::USD)
+    }
+
+    #[test]
+    fn test_charge_warns_then_stops() {
+        let mut budgets = DepartmentBudgets::new(ExchangeRates::new(Currency::USD));
+        budgets.set_limit("ceramics", usd(10_000)).unwrap();
+        let at = october();
+
+        assert_eq!(
+            budgets.charge("ceramics", CostKind::Restock, usd(5_000), at, false),
+            Ok(BudgetStatus::WithinBudget)
+        );
+        assert_eq!(
+            budgets.charge("ceramics", CostKind::Repair, usd(3_500), at, false),
+            Ok(BudgetStatus::Warning { used_percent: 85 })
+        );
+        let Err(BudgetError::Exceeded(exceeded)) =
+            budgets.charge("ceramics", CostKind::Restock, usd(2_000), at, false)
+        else {
+            panic!("expected the charge to exceed the budget");
+        };
+        assert_eq!(exceeded.would_spend, usd(10_500));
+        assert_eq!(budgets.spent("ceramics", Period::of(at)), Ok(usd(8_500)));
+
+        assert_eq!(
+            budgets.charge("ceramics", CostKind::Restock, usd(2_000), at, true),
+            Ok(BudgetStatus::Overridden { used_percent: 105 })
+        );
+    }
+
+    #[test]
+    fn test_report_groups_by_department_and_period_in_base_currency() {
+        let mut rates = ExchangeRates::new(Currency::USD);
+        rates.set_rate(Currency::EUR, 1.1);
+        let mut budgets = DepartmentBudgets::new(rates);
+        let at = october();
+        let next_month = Utc.with_ymd_and_hms(2024, 11, 1, 9, 0, 0).unwrap();
+        let eur = Money::new(1_000, Currency::EUR);
+        budgets
+            .charge("painting", CostKind::Restock, eur, at, false)
+            .unwrap();
+        budgets
+            .charge("painting", CostKind::Repair, usd(250), at, false)
+            .unwrap();
+        budgets
+            .charge("print", CostKind::Restock, usd(400), at, false)
+            .unwrap();
+        budgets
+            .charge("painting", CostKind::Restock, usd(999), next_month, false)
+            .unwrap();
+
+        let report = budgets.report(Period::of(at)).unwrap();
+        assert_eq!(report.len(), 2);
+        assert_eq!(report[0].department, "painting");
+        assert_eq!(report[0].total, usd(1_350));
+        assert_eq!(Period::of(at).to_string(), "2024-10");
+
+        let gbp = Money::new(1, Currency::parse("GBP").unwrap());
+        assert!(matches!(
+            budgets.charge("print", CostKind::Repair, gbp, at, false),
+            Err(BudgetError::UnknownCurrency(_))
+        ));
+    }
+}
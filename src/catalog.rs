@@ -0,0 +1,175 @@
+use crate::{
+    names::normalize,
+    stock::{Tool, DEFAULT_CATEGORY},
+    units::Quantity,
+    SharedResources,
+};
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Labels {
+    category: Option<String>,
+    tags: BTreeSet<String>,
+}
+
+// What kind of tool each tool is, e.g. painting, sculpting or cleanup, and
+// any free-form tags like `beginner-friendly`. Kept apart from the stock so
+// a tool keeps its labels while every unit of it is out. Aliases are other
+// names a tool goes by, keyed by their `names::normalize` form.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Catalog {
+    tools: BTreeMap<String, Labels>,
+    aliases: BTreeMap<String, String>,
+}
+
+impl Catalog {
+    pub fn set_category(&mut self, tool: &str, category: &str) {
+        self.tools.entry(tool.to_string()).or_default().category = Some(category.to_string());
+    }
+
+    pub fn add_tag(&mut self, tool: &str, tag: &str) {
+        self.tools
+            .entry(tool.to_string())
+            .or_default()
+            .tags
+            .insert(tag.to_string());
+    }
+
+    // `stock::DEFAULT_CATEGORY` for tools never given one.
+    pub fn category(&self, tool: &str) -> &str {
+        self.tools
+            .get(tool)
+            .and_then(|labels| labels.category.as_deref())
+            .unwrap_or(DEFAULT_CATEGORY)
+    }
+
+    pub fn has_tag(&self, tool: &str, tag: &str) -> bool {
+        self.tools
+            .get(tool)
+            .is_some_and(|labels| labels.tags.contains(tag))
+    }
+
+    pub fn add_alias(&mut self, alias: &str, tool: &str) {
+        self.aliases.insert(normalize(alias), tool.to_string());
+    }
+
+    // The tool `name` is another name for, however it is spaced or cased.
+    pub fn alias(&self, name: &str) -> Option<&str> {
+        self.aliases.get(&normalize(name)).map(String::as_str)
+    }
+
+    // Every tool given a category, tag or alias.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.tools
+            .keys()
+            .chain(self.aliases.values())
+            .map(String::as_str)
+    }
+
+    // Sorted.
+    pub fn tags(&self, tool: &str) -> Vec<&str> {
+        self.tools
+            .get(tool)
+            .map(|labels| labels.tags.iter().map(String::as_str).collect())
+            .unwrap_or_default()
+    }
+}
+
+impl SharedResources {
+    // Puts a tool in `category`, listed or not.
+    pub fn categorize(&mut self, tool: &str, category: &str) {
+        self.catalog.set_category(tool, category);
+        self.label(tool);
+    }
+
+    pub fn tag(&mut self, tool: &str, tag: &str) {
+        self.catalog.add_tag(tool, tag);
+    }
+
+    // Swaps in a whole catalog, relabelling the listed tools to match.
+    pub fn set_catalog(&mut self, catalog: Catalog) {
+        self.catalog = catalog;
+        let listed: Vec<String> = self.tools.iter().map(|tool| tool.name.clone()).collect();
+        for tool in &listed {
+            self.label(tool);
+        }
+    }
+
+    // Every tool the studio knows to be in `category`, by name in listing
+    // order and then catalog order, including tools that are all out.
+    pub fn tools_in_category(&self, category: &str) -> Vec<String> {
+        let mut tools: Vec<String> = self
+            .tools
+            .iter()
+            .filter(|tool| tool.category == category)
+            .map(|tool| tool.name.clone())
+            .collect();
+        for name in self.catalog.tools.keys() {
+            if self.catalog.category(name) == category && !tools.contains(name) {
+                tools.push(name.clone());
+            }
+        }
+        tools
+    }
+
+    // Tools tagged `tag` with at least one unit on the shelf.
+    pub fn available_with_tag(&self, tag: &str) -> Vec<&Tool> {
+        self.tools
+            .iter()
+            .filter(|tool| !tool.quantity.is_zero() && self.catalog.has_tag(&tool.name, tag))
+            .collect()
+    }
+
+    // Copies a listed tool's category from the catalog, since stock lists a
+    // tool afresh whenever it comes back from having none.
+    pub(crate) fn label(&mut self, tool: &str) {
+        let category = self.catalog.category(tool).to_string();
+        if let Some(tool) = self.tools.get_mut(tool) {
+            tool.category = category;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::units::Count;
+
+    #[test]
+    fn test_tools_keep_their_labels_while_all_out() {
+        let mut resources = SharedResources::default();
+        assert_eq!(
+            resources.tools_in_category("sculpting"),
+            vec!["sculpting tool".to_string()]
+        );
+        resources.categorize("easel", "painting");
+        resources.tag("easel", "beginner-friendly");
+        assert!(resources
+            .tools_in_category("painting")
+            .contains(&"easel".to_string()));
+        assert!(!resources.available_with_tag("beginner-friendly").is_empty());
+        assert!(!resources
+            .available_with_tag("beginner-friendly")
+            .iter()
+            .any(|tool| tool.name == "easel"));
+
+        resources.restock("easel", Count(1)).unwrap();
+        assert_eq!(resources.tools.get("easel").unwrap().category, "painting");
+        resources.take_out_all(&["easel".to_string()]).unwrap();
+        assert!(!resources.tools.contains("easel"));
+        assert!(resources
+            .tools_in_category("painting")
+            .contains(&"easel".to_string()));
+        resources
+            .return_resources(&["easel".to_string()], chrono::Utc::now())
+            .unwrap();
+        assert_eq!(resources.tools.get("easel").unwrap().category, "painting");
+        assert!(resources
+            .available_with_tag("beginner-friendly")
+            .iter()
+            .any(|tool| tool.name == "easel"));
+        assert_eq!(resources.catalog.tags("easel"), vec!["beginner-friendly"]);
+        assert_eq!(resources.catalog.category("tape"), "painting");
+        assert_eq!(resources.catalog.category("easel stand"), DEFAULT_CATEGORY);
+    }
+}
@@ -0,0 +1,114 @@
+use rand::Rng;
+use std::{panic, str::FromStr, sync::Mutex, thread};
+
+// How often each kind of failure strikes a simulated artist, as chances
+// from 0 to 1: a poisoned registry lock before a round, tools kept a round
+// longer than they should be, a tool lost instead of returned, and a
+// supplier late with the replacement for a lost tool.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Chaos {
+    pub poison: f64,
+    pub delay: f64,
+    pub lose: f64,
+    pub supplier: f64,
+}
+
+impl Chaos {
+    pub fn strikes(rate: f64, rng: &mut impl Rng) -> bool {
+        rate > 0.0 && rng.gen_bool(rate.min(1.0))
+    }
+}
+
+// `0.05` for every kind alike, or `poison=0.01,delay=0.2,lose=0.05,supplier=0.5`
+// with unnamed kinds left out.
+impl FromStr for Chaos {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid chaos rates '{}'", text);
+        let rate = |value: &str| match value.trim().parse::<f64>() {
+            Ok(rate) if (0.0..=1.0).contains(&rate) => Ok(rate),
+            _ => Err(invalid()),
+        };
+        if !text.contains('=') {
+            let rate = rate(text)?;
+            return Ok(Self {
+                poison: rate,
+                delay: rate,
+                lose: rate,
+                supplier: rate,
+            });
+        }
+        let mut chaos = Self::default();
+        for pair in text
+            .split(',')
+            .map(str::trim)
+            .filter(|pair| !pair.is_empty())
+        {
+            let (kind, value) = pair.split_once('=').ok_or_else(invalid)?;
+            let slot = match kind.trim() {
+                "poison" => &mut chaos.poison,
+                "delay" => &mut chaos.delay,
+                "lose" => &mut chaos.lose,
+                "supplier" => &mut chaos.supplier,
+                _ => return Err(invalid()),
+            };
+            *slot = rate(value)?;
+        }
+        Ok(chaos)
+    }
+}
+
+// Poisons `mutex` the way a holder that panicked would, without printing
+// a panic message. Nothing is changed under the lock, so the data behind it
+// stays sound.
+pub fn poison<T: Send>(mutex: &Mutex<T>) {
+    thread::scope(|scope| {
+        let _ = scope
+            .spawn(|| {
+                let _guard = mutex.lock();
+                panic::resume_unwind(Box::new("chaos"));
+            })
+            .join();
+    });
+}
+
+// Clears a poisoning left by `poison`. False if the lock wasn't poisoned.
+pub fn recover<T>(mutex: &Mutex<T>) -> bool {
+    let poisoned = mutex.is_poisoned();
+    mutex.clear_poison();
+    poisoned
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_rates_parse_and_poisoning_recovers() {
+        let chaos: Chaos = "lose=0.5, supplier=1".parse().unwrap();
+        assert_eq!(
+            chaos,
+            Chaos {
+                lose: 0.5,
+                supplier: 1.0,
+                ..Chaos::default()
+            }
+        );
+        assert_eq!("0.1".parse::<Chaos>().unwrap().delay, 0.1);
+        for invalid in ["2", "lose=x", "melt=0.1", "lose"] {
+            assert!(invalid.parse::<Chaos>().is_err(), "{}", invalid);
+        }
+        let mut rng = StdRng::seed_from_u64(1);
+        assert!(Chaos::strikes(chaos.supplier, &mut rng));
+        assert!(!Chaos::strikes(chaos.poison, &mut rng));
+
+        let mutex = Mutex::new(3);
+        poison(&mutex);
+        assert!(mutex.lock().is_err());
+        assert!(recover(&mutex));
+        assert_eq!(*mutex.lock().unwrap(), 3);
+        assert!(!recover(&mutex));
+    }
+}
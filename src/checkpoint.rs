@@ -0,0 +1,482 @@
+use crate::{
+    dump::StateDump, i18n::Message, registry::Checkout, units::Kilograms, ArtistToolRegistry,
+    SharedResources,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+const CHECKPOINT_FILE: &str = "checkpoint.json";
+const JOURNAL_FILE: &str = "journal.jsonl";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JournalOp {
+    #[default]
+    Checkout,
+    Return,
+    Paint,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub seq: u64,
+    // Journals written before returns existed only hold checkouts.
+    #[serde(default)]
+    pub op: JournalOp,
+    pub artist_id: usize,
+    // For checkouts, the tools that were lent.
+    pub tools: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub queued: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paints: Vec<(String, Kilograms)>,
+    // When it was applied; older journals didn't say.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub at: Option<DateTime<Utc>>,
+}
+
+impl JournalEntry {
+    // Numbered when it is appended.
+    fn new(artist_id: usize, registry: &ArtistToolRegistry) -> Self {
+        Self {
+            seq: 0,
+            op: JournalOp::default(),
+            artist_id,
+            tools: vec![],
+            queued: vec![],
+            paints: vec![],
+            at: Some(registry.now()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Checkpoint {
+    // Sequence number of the last journaled checkout folded into `state`.
+    seq: u64,
+    // False while the daemon is running; set by a clean shutdown.
+    clean: bool,
+    state: StateDump,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecoveryReport {
+    pub checkpoint_seq: u64,
+    pub checkpoint_at: DateTime<Utc>,
+    pub replayed: usize,
+    // Journal lines that couldn't be read back, typically a torn final write.
+    pub lost_lines: usize,
+    // Journaled operations the restored state refused, so left out.
+    pub diverged: usize,
+    // Bookings the checkpoint's history shows open but didn't save, as
+    // checkpoints written before reservations were kept.
+    pub lost_reservations: usize,
+}
+
+// Journals every checkout the daemon applies and folds the journal into a
+// checkpoint every `every_events` checkouts or `every` elapsed, whichever
+// comes first.
+pub struct Checkpointer {
+    dir: PathBuf,
+    every_events: u64,
+    every: Duration,
+    seq: u64,
+    checkpoint_seq: u64,
+    checkpointed_at: Instant,
+    journal: File,
+}
+
+impl Checkpointer {
+    // Restores the last saved state into `resources` and returns the rebuilt
+    // registry. The report is None after a clean shutdown or on first start.
+    pub fn open(
+        dir: &Path,
+        every_events: u64,
+        every: Duration,
+        resources: &Arc<Mutex<SharedResources>>,
+    ) -> io::Result<(Self, ArtistToolRegistry, Option<RecoveryReport>)> {
+        fs::create_dir_all(dir)?;
+        let checkpoint = match fs::read_to_string(dir.join(CHECKPOINT_FILE)) {
+            Ok(text) => Some(serde_json::from_str::<Checkpoint>(&text)?),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => None,
+            Err(error) => return Err(error),
+        };
+        let journal = match fs::read_to_string(dir.join(JOURNAL_FILE)) {
+            Ok(text) => text,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(error) => return Err(error),
+        };
+
+        let (mut registry, checkpoint_seq, clean, checkpoint_at, lost_reservations) =
+            match &checkpoint {
+                Some(checkpoint) => (
+                    checkpoint.state.restore(resources),
+                    checkpoint.seq,
+                    checkpoint.clean,
+                    checkpoint.state.captured_at,
+                    checkpoint.state.lost_reservations(),
+                ),
+                None => (ArtistToolRegistry::new(resources), 0, true, Utc::now(), 0),
+            };
+
+        let mut seq = checkpoint_seq;
+        let mut replayed = 0;
+        let mut lost_lines = 0;
+        let mut diverged = 0;
+        for line in journal.lines().filter(|line| !line.trim().is_empty()) {
+            match serde_json::from_str::<JournalEntry>(line) {
+                // Entries already in the checkpoint survive a crash between
+                // writing the checkpoint and truncating the journal.
+                Ok(entry) if entry.seq <= checkpoint_seq => {}
+                Ok(entry) if lost_lines == 0 => {
+                    // Checkouts replay as they were applied rather than being
+                    // asked for again, so caps and queues can't change them.
+                    let at = entry.at.unwrap_or_else(|| registry.now());
+                    let applied = match entry.op {
+                        JournalOp::Checkout => registry.replay_checkout(
+                            entry.artist_id,
+                            &entry.tools,
+                            &entry.queued,
+                            at,
+                        ),
+                        JournalOp::Return => registry.tool_return(entry.artist_id, entry.tools),
+                        JournalOp::Paint => registry.paint_checkout(entry.artist_id, entry.paints),
+                    };
+                    if let Err(error) = applied {
+                        tracing::warn!(
+                            seq = entry.seq,
+                            error = %error,
+                            "{}",
+                            Message::JournalDiverged(entry.seq, &error)
+                        );
+                        diverged += 1;
+                    }
+                    seq = entry.seq;
+                    replayed += 1;
+                }
+                _ => lost_lines += 1,
+            }
+        }
+
+        let report = (!clean || replayed > 0 || lost_lines > 0 || lost_reservations > 0).then_some(
+            RecoveryReport {
+                checkpoint_seq,
+                checkpoint_at,
+                replayed,
+                lost_lines,
+                diverged,
+                lost_reservations,
+            },
+        );
+
+        let mut checkpointer = Self {
+            dir: dir.to_path_buf(),
+            every_events: every_events.max(1),
+            every,
+            seq,
+            checkpoint_seq: seq,
+            checkpointed_at: Instant::now(),
+            journal: OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(dir.join(JOURNAL_FILE))?,
+        };
+        checkpointer.write_checkpoint(&registry, false)?;
+        Ok((checkpointer, registry, report))
+    }
+
+    // Call after each checkout has been applied to `registry`, with what it
+    // lent and queued.
+    pub fn record(
+        &mut self,
+        artist_id: usize,
+        checkout: &Checkout,
+        registry: &ArtistToolRegistry,
+    ) -> io::Result<()> {
+        self.append(
+            JournalEntry {
+                op: JournalOp::Checkout,
+                tools: checkout.lent.clone(),
+                queued: checkout.queued.clone(),
+                ..JournalEntry::new(artist_id, registry)
+            },
+            registry,
+        )
+    }
+
+    // Call after each accepted return has been applied to `registry`.
+    pub fn record_return(
+        &mut self,
+        artist_id: usize,
+        tools: &[String],
+        registry: &ArtistToolRegistry,
+    ) -> io::Result<()> {
+        self.append(
+            JournalEntry {
+                op: JournalOp::Return,
+                tools: tools.to_vec(),
+                ..JournalEntry::new(artist_id, registry)
+            },
+            registry,
+        )
+    }
+
+    // Call after each accepted paint checkout has been applied to `registry`.
+    pub fn record_paint(
+        &mut self,
+        artist_id: usize,
+        paints: &[(String, Kilograms)],
+        registry: &ArtistToolRegistry,
+    ) -> io::Result<()> {
+        self.append(
+            JournalEntry {
+                op: JournalOp::Paint,
+                paints: paints.to_vec(),
+                ..JournalEntry::new(artist_id, registry)
+            },
+            registry,
+        )
+    }
+
+    fn append(&mut self, mut entry: JournalEntry, registry: &ArtistToolRegistry) -> io::Result<()> {
+        self.seq += 1;
+        entry.seq = self.seq;
+        writeln!(self.journal, "{}", serde_json::to_string(&entry)?)?;
+        self.journal.sync_data()?;
+
+        if self.seq - self.checkpoint_seq >= self.every_events
+            || self.checkpointed_at.elapsed() >= self.every
+        {
+            self.write_checkpoint(registry, false)?;
+        }
+        Ok(())
+    }
+
+    // Folds everything into a checkpoint now. For changes the journal has no
+    // entry for, like undos, repairs and write-offs, so a crash can't lose
+    // them.
+    pub fn checkpoint(&mut self, registry: &ArtistToolRegistry) -> io::Result<()> {
+        self.write_checkpoint(registry, false)
+    }
+
+    pub fn shutdown(&mut self, registry: &ArtistToolRegistry) -> io::Result<()> {
+        self.write_checkpoint(registry, true)
+    }
+
+    fn write_checkpoint(&mut self, registry: &ArtistToolRegistry, clean: bool) -> io::Result<()> {
+        let checkpoint = Checkpoint {
+            seq: self.seq,
+            clean,
+            state: StateDump::capture(registry),
+        };
+        // Write to a temporary file and rename so a crash never leaves a
+        // half-written checkpoint behind.
+        let path = self.dir.join(CHECKPOINT_FILE);
+        let temporary = path.with_extension("json.tmp");
+        fs::write(&temporary, serde_json::to_string(&checkpoint)?)?;
+        fs::rename(&temporary, &path)?;
+
+        self.journal = OpenOptions::new()
+            .write(true)
+            .truncate(true)
+            .open(self.dir.join(JOURNAL_FILE))?;
+        self.checkpoint_seq = self.seq;
+        self.checkpointed_at = Instant::now();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::units::Count;
+    use std::{env, process};
+
+    fn state_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("rustic-canvas-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn resources() -> Arc<Mutex<SharedResources>> {
+        Arc::new(Mutex::new(SharedResources::default()))
+    }
+
+    fn checkout(checkpointer: &mut Checkpointer, registry: &mut ArtistToolRegistry, id: usize) {
+        let checkout = registry
+            .tool_registry(id, vec!["brush".to_string()])
+            .unwrap();
+        checkpointer.record(id, &checkout, registry).unwrap();
+    }
+
+    #[test]
+    fn test_clean_restart_restores_without_report() {
+        let dir = state_dir("checkpoint-clean");
+        let (mut checkpointer, mut registry, report) =
+            Checkpointer::open(&dir, 100, Duration::from_secs(60), &resources()).unwrap();
+        assert_eq!(report, None);
+        checkout(&mut checkpointer, &mut registry, 1);
+        checkpointer.shutdown(&registry).unwrap();
+
+        let fresh = resources();
+        let (_, registry, report) =
+            Checkpointer::open(&dir, 100, Duration::from_secs(60), &fresh).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(report, None);
+        assert_eq!(registry.artist_tool_preferences.len(), 1);
+        assert_eq!(fresh.lock().unwrap().stock("brush"), Count(9));
+    }
+
+    #[test]
+    fn test_crash_recovers_checkpoint_plus_journal_tail() {
+        let dir = state_dir("checkpoint-crash");
+        {
+            let (mut checkpointer, mut registry, _) =
+                Checkpointer::open(&dir, 2, Duration::from_secs(60), &resources()).unwrap();
+            for id in 1..=3 {
+                checkout(&mut checkpointer, &mut registry, id);
+            }
+            // Dropped without shutdown: checkpoint after #2, journal holds #3.
+        }
+        let mut journal = OpenOptions::new()
+            .append(true)
+            .open(dir.join(JOURNAL_FILE))
+            .unwrap();
+        write!(journal, "{{\"seq\":4,\"artist_id\"").unwrap();
+
+        let fresh = resources();
+        let (_, registry, report) =
+            Checkpointer::open(&dir, 2, Duration::from_secs(60), &fresh).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let report = report.unwrap();
+        assert_eq!(report.checkpoint_seq, 2);
+        assert_eq!(report.replayed, 1);
+        assert_eq!(report.lost_lines, 1);
+        assert_eq!(registry.artist_tool_preferences.len(), 3);
+        assert_eq!(fresh.lock().unwrap().stock("brush"), Count(7));
+    }
+
+    #[test]
+    fn test_reservations_survive_a_restart() {
+        let dir = state_dir("checkpoint-reservations");
+        let tools = vec!["brush".to_string()];
+        let booking = {
+            let (mut checkpointer, mut registry, _) =
+                Checkpointer::open(&dir, 100, Duration::from_secs(60), &resources()).unwrap();
+            let now = registry.now();
+            let booking = registry
+                .reserve(1, tools.clone(), now, now + chrono::Duration::hours(1))
+                .unwrap();
+            checkpointer.shutdown(&registry).unwrap();
+            booking
+        };
+        let (mut checkpointer, mut registry, report) =
+            Checkpointer::open(&dir, 100, Duration::from_secs(60), &resources()).unwrap();
+        assert_eq!(report, None);
+        let now = registry.now();
+        let next = registry
+            .reserve(2, tools.clone(), now, now + chrono::Duration::hours(1))
+            .unwrap();
+        assert_ne!(next, booking);
+        registry.claim_reservation(booking).unwrap();
+        assert_eq!(registry.held_tools(1).len(), 1);
+        checkpointer.shutdown(&registry).unwrap();
+
+        // A checkpoint from before reservations were saved still shows the
+        // open booking in its history, and recovery says it is gone.
+        let path = dir.join(CHECKPOINT_FILE);
+        let mut checkpoint: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        checkpoint["state"]
+            .as_object_mut()
+            .unwrap()
+            .remove("reservations");
+        fs::write(&path, checkpoint.to_string()).unwrap();
+        let (_, _, report) =
+            Checkpointer::open(&dir, 100, Duration::from_secs(60), &resources()).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(report.unwrap().lost_reservations, 1);
+    }
+
+    #[test]
+    fn test_journaled_returns_replay_after_crash() {
+        let dir = state_dir("checkpoint-return");
+        {
+            let (mut checkpointer, mut registry, _) =
+                Checkpointer::open(&dir, 100, Duration::from_secs(60), &resources()).unwrap();
+            checkout(&mut checkpointer, &mut registry, 1);
+            let tools = vec!["brush".to_string()];
+            registry.tool_return(1, tools.clone()).unwrap();
+            checkpointer.record_return(1, &tools, &registry).unwrap();
+        }
+        // A journal line from before returns were journaled still reads as a
+        // checkout.
+        let mut journal = OpenOptions::new()
+            .append(true)
+            .open(dir.join(JOURNAL_FILE))
+            .unwrap();
+        writeln!(
+            journal,
+            "{{\"seq\":3,\"artist_id\":2,\"tools\":[\"tape\"]}}"
+        )
+        .unwrap();
+
+        let fresh = resources();
+        let (_, registry, report) =
+            Checkpointer::open(&dir, 100, Duration::from_secs(60), &fresh).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(report.unwrap().replayed, 3);
+        assert!(registry.held_tools(1).is_empty());
+        assert_eq!(registry.held_tools(2).len(), 1);
+        assert_eq!(fresh.lock().unwrap().stock("brush"), Count(10));
+    }
+
+    #[test]
+    fn test_checkouts_replay_as_applied() {
+        let dir = state_dir("checkpoint-outcome");
+        {
+            let shared = resources();
+            {
+                let mut resources = shared.lock().unwrap();
+                resources.loan_caps.policy = crate::loan_caps::CapPolicy::Queue;
+                resources.loan_caps.set_cap("brush", 1);
+            }
+            let (mut checkpointer, mut registry, _) =
+                Checkpointer::open(&dir, 100, Duration::from_secs(60), &shared).unwrap();
+            checkout(&mut checkpointer, &mut registry, 1);
+            checkout(&mut checkpointer, &mut registry, 2);
+        }
+        let mut journal = OpenOptions::new()
+            .append(true)
+            .open(dir.join(JOURNAL_FILE))
+            .unwrap();
+        writeln!(
+            journal,
+            "{{\"seq\":3,\"artist_id\":3,\"tools\":[\"easel\"]}}"
+        )
+        .unwrap();
+
+        // No cap this time, yet artist 2 is still waiting rather than lent a
+        // brush, and the easel nobody has is reported, not dropped silently.
+        let fresh = resources();
+        let (_, registry, report) =
+            Checkpointer::open(&dir, 100, Duration::from_secs(60), &fresh).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        let report = report.unwrap();
+        assert_eq!((report.replayed, report.diverged), (3, 1));
+        assert_eq!(registry.held_tools(1).len(), 1);
+        assert!(registry.held_tools(2).is_empty());
+        let resources = fresh.lock().unwrap();
+        assert_eq!(resources.stock("brush"), Count(9));
+        assert_eq!(resources.loan_caps.queued().count(), 1);
+    }
+}
@@ -0,0 +1,119 @@
+use chrono::{DateTime, Duration, Utc};
+use std::sync::{Arc, Mutex};
+
+// Where the registry gets the time it stamps entries with.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+// Stands still until moved. Clones share one time, so a test can keep a
+// clone to move the clock it gave the registry.
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    now: Arc<Mutex<DateTime<Utc>>>,
+}
+
+impl MockClock {
+    pub fn new(start: DateTime<Utc>) -> Self {
+        Self {
+            now: Arc::new(Mutex::new(start)),
+        }
+    }
+
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.now.lock().expect("Failed to lock clock") = now;
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.now.lock().expect("Failed to lock clock") += by;
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().expect("Failed to lock clock")
+    }
+}
+
+// Simulated time: moves `step` forward each time a simulation step ends,
+// however long the step really took.
+#[derive(Debug, Clone)]
+pub struct SimulatedClock {
+    clock: MockClock,
+    step: Duration,
+}
+
+impl SimulatedClock {
+    pub fn new(start: DateTime<Utc>, step: Duration) -> Self {
+        Self {
+            clock: MockClock::new(start),
+            step,
+        }
+    }
+
+    pub fn tick(&self) {
+        self.clock.advance(self.step);
+    }
+}
+
+impl Clock for SimulatedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.clock.now()
+    }
+}
+
+// `90s`, `30m`, `2h` or `1d`; a unit without a number is one of it, so
+// `hour` is an hour.
+pub fn parse_span(text: &str) -> Option<Duration> {
+    let digits = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let count = match &text[..digits] {
+        "" => 1,
+        count => count.parse().ok()?,
+    };
+    match &text[digits..] {
+        "s" | "sec" | "second" => Some(Duration::seconds(count)),
+        "m" | "min" | "minute" => Some(Duration::minutes(count)),
+        "h" | "hour" => Some(Duration::hours(count)),
+        "d" | "day" => Some(Duration::days(count)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArtistToolRegistry, SharedResources};
+
+    #[test]
+    fn test_registry_stamps_entries_with_its_clock() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        let start = Utc::now() - Duration::days(30);
+        let clock = MockClock::new(start);
+        registry.set_clock(Arc::new(clock.clone()));
+
+        registry
+            .tool_registry(1, vec!["brush".to_string()])
+            .unwrap();
+        clock.advance(Duration::hours(2));
+        registry.tool_return(1, vec!["brush".to_string()]).unwrap();
+        let stamps: Vec<_> = registry.history().map(|entry| entry.datetime).collect();
+        assert_eq!(stamps, vec![Some(start), Some(start + Duration::hours(2))]);
+
+        let simulated = SimulatedClock::new(start, Duration::minutes(15));
+        simulated.tick();
+        simulated.tick();
+        assert_eq!(simulated.now(), start + Duration::minutes(30));
+    }
+}
@@ -0,0 +1,511 @@
+use crate::{
+    budgets::{BudgetError, BudgetStatus, CostKind, DepartmentBudgets, DepartmentSpend, Period},
+    error::RegistryError,
+    i18n::Message,
+    money::{Currency, ExchangeRates, MixedCurrencies, Money, UnknownCurrency},
+    units::{Amount, Count},
+    ArtistToolRegistry,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::{collections::HashMap, fs, io, path::Path};
+
+// What the studio pays for its stock: each unit of a tool, each kilogram of
+// paint. Items without a cost are free as far as the budget goes.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CostBook {
+    tools: HashMap<String, Money>,
+    paints: HashMap<String, Money>,
+    // Money in hand before the first restock.
+    pub opening: Option<Money>,
+    // Monthly restock and repair limits, by department.
+    pub departments: HashMap<String, Money>,
+    // What the ledger and budgets count in; the ledger's own if unset.
+    pub currency: Option<Currency>,
+    // How much one unit of each other currency is worth in `currency`.
+    pub rates: HashMap<Currency, f64>,
+}
+
+// The file `CostBook::load` reads, amounts written the way `Money` displays:
+//
+//   currency = "USD"
+//   budget = "500.00 USD"
+//
+//   [rates]
+//   EUR = 1.1
+//
+//   [tools]
+//   brush = "4.50 USD"
+//
+//   [paints]
+//   red = "3.00 USD"
+//
+//   [departments]
+//   painting = "200.00 USD"
+//
+// A department is a tool's catalog category; paints cost against
+// `stock::DEFAULT_CATEGORY` unless the catalog files them elsewhere.
+#[derive(Deserialize)]
+struct CostFile {
+    currency: Option<String>,
+    budget: Option<String>,
+    #[serde(default)]
+    rates: HashMap<String, f64>,
+    #[serde(default)]
+    tools: HashMap<String, String>,
+    #[serde(default)]
+    paints: HashMap<String, String>,
+    #[serde(default)]
+    departments: HashMap<String, String>,
+}
+
+fn parse_currency(code: &str) -> io::Result<Currency> {
+    Currency::parse(code).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unknown currency {:?}", code),
+        )
+    })
+}
+
+fn parse_money(item: &str, text: &str) -> io::Result<Money> {
+    Money::parse(text).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} costs {:?}", item, text),
+        )
+    })
+}
+
+impl CostBook {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let file: CostFile = toml::from_str(text)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error.to_string()))?;
+        let mut book = CostBook {
+            opening: file
+                .budget
+                .map(|text| parse_money("budget", &text))
+                .transpose()?,
+            currency: file.currency.as_deref().map(parse_currency).transpose()?,
+            ..CostBook::default()
+        };
+        for (code, rate) in &file.rates {
+            book.rates.insert(parse_currency(code)?, *rate);
+        }
+        for (tool, text) in &file.tools {
+            book.set_tool(tool, parse_money(tool, text)?);
+        }
+        for (color, text) in &file.paints {
+            book.set_paint(color, parse_money(color, text)?);
+        }
+        for (department, text) in &file.departments {
+            book.departments
+                .insert(department.clone(), parse_money(department, text)?);
+        }
+        Ok(book)
+    }
+
+    pub fn set_tool(&mut self, tool: &str, unit: Money) {
+        self.tools.insert(tool.to_string(), unit);
+    }
+
+    pub fn set_paint(&mut self, color: &str, per_kg: Money) {
+        self.paints.insert(color.to_string(), per_kg);
+    }
+
+    // What `quantity` units of a tool, or kilograms of a paint, cost, to the
+    // nearest cent.
+    pub fn cost_of(&self, item: &str, quantity: impl Into<Amount>) -> Option<Money> {
+        let (each, times) = match quantity.into() {
+            Amount::Count(count) => (self.tools.get(item)?, count.0 as f64),
+            Amount::Kilograms(kg) => (self.paints.get(item)?, kg.get()),
+        };
+        Some(Money::new(
+            (each.minor_units as f64 * times).round() as i64,
+            each.currency,
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CashFlow {
+    Restock,
+    Repair,
+    Loss,
+    Revenue,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BudgetLine {
+    pub at: DateTime<Utc>,
+    pub flow: CashFlow,
+    // In the budget's currency, always positive; `flow` says which way.
+    pub amount: Money,
+    pub memo: String,
+}
+
+// The studio's money over a run: what it started with and every cost and
+// sale since, in the ledger's base currency.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StudioBudget {
+    opening: Money,
+    lines: Vec<BudgetLine>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProfitLoss {
+    pub revenue: Money,
+    pub restocks: Money,
+    pub repairs: Money,
+    pub losses: Money,
+    pub profit: Money,
+    pub balance: Money,
+}
+
+impl StudioBudget {
+    pub fn new(opening: Money) -> Self {
+        Self {
+            opening,
+            lines: vec![],
+        }
+    }
+
+    pub fn opening(&self) -> Money {
+        self.opening
+    }
+
+    pub fn lines(&self) -> &[BudgetLine] {
+        &self.lines
+    }
+
+    // Nothing to report: no opening balance and nothing booked.
+    pub fn is_empty(&self) -> bool {
+        self.opening.minor_units == 0 && self.lines.is_empty()
+    }
+
+    pub fn book(&mut self, flow: CashFlow, amount: Money, at: DateTime<Utc>, memo: String) {
+        self.lines.push(BudgetLine {
+            at,
+            flow,
+            amount,
+            memo,
+        });
+    }
+
+    fn total(&self, flow: CashFlow) -> Result<Money, MixedCurrencies> {
+        self.lines
+            .iter()
+            .filter(|line| line.flow == flow)
+            .try_fold(Money::zero(self.opening.currency), |sum, line| {
+                sum.checked_add(line.amount)
+            })
+    }
+
+    // Money in hand now. Losses are stock written down, not cash spent, so
+    // they count against profit but not the balance.
+    pub fn balance(&self) -> Result<Money, MixedCurrencies> {
+        self.opening
+            .checked_add(self.total(CashFlow::Revenue)?)?
+            .checked_sub(self.total(CashFlow::Restock)?)?
+            .checked_sub(self.total(CashFlow::Repair)?)
+    }
+
+    pub fn summary(&self) -> Result<ProfitLoss, MixedCurrencies> {
+        let revenue = self.total(CashFlow::Revenue)?;
+        let restocks = self.total(CashFlow::Restock)?;
+        let repairs = self.total(CashFlow::Repair)?;
+        let losses = self.total(CashFlow::Loss)?;
+        Ok(ProfitLoss {
+            revenue,
+            restocks,
+            repairs,
+            losses,
+            profit: revenue
+                .checked_sub(restocks)?
+                .checked_sub(repairs)?
+                .checked_sub(losses)?,
+            balance: self.balance()?,
+        })
+    }
+}
+
+impl ArtistToolRegistry {
+    // Prices stock from `costs`, takes its currency and rates, and starts the
+    // budget and department spend over. Fails if the opening balance or a
+    // department's limit is in a currency there's no rate for.
+    pub fn set_costs(&mut self, costs: CostBook) -> Result<(), UnknownCurrency> {
+        let base = costs.currency.unwrap_or(self.ledger.rates().base());
+        let mut rates = ExchangeRates::new(base);
+        for (currency, base_per_unit) in &costs.rates {
+            rates.set_rate(*currency, *base_per_unit);
+        }
+        let opening = match costs.opening {
+            Some(opening) => rates.to_base(opening)?,
+            None => Money::zero(base),
+        };
+        let mut departments = DepartmentBudgets::new(rates.clone());
+        for (department, limit) in &costs.departments {
+            departments.set_limit(department, *limit)?;
+        }
+        self.ledger.set_rates(rates);
+        self.budget = StudioBudget::new(opening);
+        self.departments = departments;
+        self.costs = costs;
+        Ok(())
+    }
+
+    // Lets restocks and repairs go ahead past a department's limit, each one
+    // logged, rather than refusing them.
+    pub fn allow_over_budget(&mut self, allowed: bool) {
+        self.over_budget = allowed;
+    }
+
+    // This month's restock and repair spend, by department.
+    pub fn department_spend(&self) -> Result<Vec<DepartmentSpend>, MixedCurrencies> {
+        self.departments.report(Period::of(self.now()))
+    }
+
+    // Totals `costs` by the department each item is in, refused if any would
+    // go past its limit, unless overruns are allowed. Nothing is booked
+    // until `charge_departments` is given what this returns. Costs are
+    // totalled in the base currency; those the ledger can't convert are let
+    // through, as `book` leaves them out.
+    pub(crate) fn check_departments(
+        &self,
+        costs: &[(&str, Money)],
+    ) -> Result<Vec<(String, Money)>, RegistryError> {
+        let mut charges: Vec<(String, Money)> = vec![];
+        {
+            let resources = self
+                .shared_resources
+                .lock()
+                .map_err(|poisoned| self.recover(poisoned))?;
+            for (item, cost) in costs {
+                let department = resources.catalog.category(item);
+                let cost = self.ledger.rates().to_base(*cost).unwrap_or(*cost);
+                match charges
+                    .iter_mut()
+                    .find(|(name, total)| name == department && total.currency == cost.currency)
+                {
+                    Some((_, total)) => {
+                        *total =
+                            total
+                                .checked_add(cost)
+                                .map_err(|MixedCurrencies(left, right)| {
+                                    RegistryError::MixedCurrencies(left, right)
+                                })?
+                    }
+                    None => charges.push((department.to_string(), cost)),
+                }
+            }
+        }
+        let now = self.now();
+        for (department, cost) in &charges {
+            match self
+                .departments
+                .check(department, *cost, now, self.over_budget)
+            {
+                Err(BudgetError::Exceeded(exceeded)) => {
+                    return Err(RegistryError::OverBudget(exceeded))
+                }
+                Err(BudgetError::MixedCurrencies(MixedCurrencies(left, right))) => {
+                    return Err(RegistryError::MixedCurrencies(left, right))
+                }
+                Ok(_) | Err(BudgetError::UnknownCurrency(_)) => {}
+            }
+        }
+        Ok(charges)
+    }
+
+    // Books what `check_departments` let through, warning of departments
+    // at `budgets::WARNING_PERCENT` of their limit or past it.
+    pub(crate) fn charge_departments(&mut self, kind: CostKind, charges: Vec<(String, Money)>) {
+        let now = self.now();
+        for (department, cost) in charges {
+            match self
+                .departments
+                .charge(&department, kind, cost, now, self.over_budget)
+            {
+                Ok(BudgetStatus::Warning { used_percent }) => tracing::warn!(
+                    department = %department,
+                    used_percent,
+                    "{}",
+                    Message::BudgetWarning(&department, used_percent)
+                ),
+                Ok(BudgetStatus::Overridden { used_percent }) => tracing::warn!(
+                    department = %department,
+                    used_percent,
+                    "{}",
+                    Message::BudgetOverrun(&department, used_percent)
+                ),
+                Ok(BudgetStatus::WithinBudget) | Err(_) => {}
+            }
+        }
+    }
+
+    pub fn costs(&self) -> &CostBook {
+        &self.costs
+    }
+
+    // Books `amount` in the budget's currency. Amounts the ledger can't
+    // convert are left out; `record_ledger` already reports them.
+    pub(crate) fn book(&mut self, flow: CashFlow, amount: Money, memo: String) {
+        let now = self.now();
+        if let Ok(amount) = self.ledger.rates().to_base(amount) {
+            self.budget.book(flow, amount, now, memo);
+        }
+    }
+
+    // Lost and damaged units are written down at what they cost.
+    pub(crate) fn book_loss(&mut self, tool: &str) {
+        if let Some(cost) = self.costs.cost_of(tool, Count(1)) {
+            self.book(CashFlow::Loss, cost, format!("wrote down {}", tool));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{deliveries::Delivery, units::Kilograms, SharedResources};
+    use chrono::Duration;
+    use std::sync::{Arc, Mutex};
+
+    fn usd(minor_units: i64) -> Money {
+        Money::new(minor_units, Currency::USD)
+    }
+
+    #[test]
+    fn test_budget_tracks_costs_losses_and_revenue() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        let costs = CostBook::parse(
+            r#"
+            budget = "100.00 USD"
+
+            [tools]
+            brush = "4.00 USD"
+
+            [paints]
+            red = "1.50 USD"
+            "#,
+        )
+        .unwrap();
+        assert!(CostBook::parse("[tools]\nbrush = \"cheap\"").is_err());
+        assert_eq!(costs.cost_of("red", Kilograms::whole(2)), Some(usd(300)));
+        assert_eq!(costs.cost_of("red", Kilograms::grams(250)), Some(usd(38)));
+        assert_eq!(costs.cost_of("red", Count(2)), None);
+        assert_eq!(costs.cost_of("easel", Count(1)), None);
+        registry.set_costs(costs).unwrap();
+
+        registry.restock("brush", Count(2)).unwrap();
+        registry.restock("red", Kilograms::whole(4)).unwrap();
+        registry
+            .receive_delivery(Delivery {
+                supplier: "Acme".to_string(),
+                items: vec![("tape".to_string(), Count(1).into())],
+                cost: usd(250),
+            })
+            .unwrap();
+        let brush = vec!["brush".to_string()];
+        registry.tool_registry(1, brush.clone()).unwrap();
+        let ticket = registry.return_damaged(1, "brush").unwrap();
+        registry
+            .start_repair(ticket, Duration::hours(1), Some(usd(150)))
+            .unwrap();
+        registry.tool_registry(2, brush).unwrap();
+        registry.report_lost(2, "brush").unwrap();
+        registry.sell_stock(3, "tape", 1, usd(2_000)).unwrap();
+
+        let summary = registry.budget.summary().unwrap();
+        assert_eq!(summary.revenue, usd(2_000));
+        // 2 × 4.00 + 4 × 1.50 + 2.50
+        assert_eq!(summary.restocks, usd(1_650));
+        assert_eq!(summary.repairs, usd(150));
+        assert_eq!(summary.losses, usd(800));
+        assert_eq!(summary.profit, usd(-600));
+        assert_eq!(summary.balance, usd(10_200));
+    }
+
+    #[test]
+    fn test_departments_warn_stop_and_report() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        resources.lock().unwrap().categorize("brush", "painting");
+        let mut registry = ArtistToolRegistry::new(&resources);
+        let costs = CostBook::parse(
+            "[tools]\nbrush = \"4.00 USD\"\n[departments]\npainting = \"10.00 USD\"\n",
+        )
+        .unwrap();
+        registry.set_costs(costs).unwrap();
+        let brushes = |registry: &ArtistToolRegistry| {
+            registry.shared_resources.lock().unwrap().stock("brush")
+        };
+        let before = brushes(&registry);
+
+        // 8.00 of 10.00 warns; another 4.00 would go over, so is refused.
+        registry.restock("brush", Count(2)).unwrap();
+        assert!(matches!(
+            registry.restock("brush", Count(1)),
+            Err(RegistryError::OverBudget(_))
+        ));
+        assert_eq!(brushes(&registry), before + Count(2));
+        registry
+            .tool_registry(1, vec!["brush".to_string()])
+            .unwrap();
+        let ticket = registry.return_damaged(1, "brush").unwrap();
+        assert!(registry
+            .start_repair(ticket, Duration::hours(1), Some(usd(300)))
+            .is_err());
+
+        registry.allow_over_budget(true);
+        registry
+            .start_repair(ticket, Duration::hours(1), Some(usd(300)))
+            .unwrap();
+        let spend = registry.department_spend().unwrap();
+        let painting = spend
+            .iter()
+            .find(|spend| spend.department == "painting")
+            .unwrap();
+        assert_eq!(
+            (painting.restock, painting.repair, painting.limit),
+            (usd(800), usd(300), Some(usd(1_000)))
+        );
+    }
+
+    #[test]
+    fn test_costs_file_sets_base_currency_and_rates() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        resources.lock().unwrap().categorize("brush", "painting");
+        let mut registry = ArtistToolRegistry::new(&resources);
+        let costs = CostBook::parse(
+            r#"
+            currency = "EUR"
+            budget = "10.00 USD"
+
+            [rates]
+            USD = 0.9
+
+            [tools]
+            brush = "4.00 USD"
+
+            [departments]
+            painting = "10.00 EUR"
+            "#,
+        )
+        .unwrap();
+        assert!(CostBook::parse("currency = \"EURO\"").is_err());
+        registry.set_costs(costs).unwrap();
+        let eur = |minor_units| Money::new(minor_units, Currency::EUR);
+        assert_eq!(registry.ledger.rates().base(), Currency::EUR);
+        assert_eq!(registry.budget.opening(), eur(900));
+
+        registry.restock("brush", Count(2)).unwrap();
+        let spend = registry.department_spend().unwrap();
+        assert_eq!(spend[0].total, eur(720));
+        assert_eq!(registry.budget.balance(), Ok(eur(180)));
+    }
+}
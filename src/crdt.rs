@@ -0,0 +1,403 @@
+use crate::{events, State};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+// Stock a branch starts with is counted under this replica, so branches
+// seeded from the same inventory don't double it when they merge.
+pub const ORIGIN: &str = "origin";
+
+// Counter that every replica may bump up or down independently; merging takes
+// the per-replica maximum, so merges commute and can be repeated safely.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PnCounter {
+    increments: BTreeMap<String, u64>,
+    decrements: BTreeMap<String, u64>,
+}
+
+impl PnCounter {
+    pub fn increment(&mut self, replica: &str, by: u64) {
+        *self.increments.entry(replica.to_string()).or_insert(0) += by;
+    }
+
+    pub fn decrement(&mut self, replica: &str, by: u64) {
+        *self.decrements.entry(replica.to_string()).or_insert(0) += by;
+    }
+
+    pub fn value(&self) -> i64 {
+        let up: u64 = self.increments.values().sum();
+        let down: u64 = self.decrements.values().sum();
+        up as i64 - down as i64
+    }
+
+    pub fn merge(&mut self, other: &PnCounter) {
+        for (replica, &count) in &other.increments {
+            let mine = self.increments.entry(replica.clone()).or_insert(0);
+            *mine = (*mine).max(count);
+        }
+        for (replica, &count) in &other.decrements {
+            let mine = self.decrements.entry(replica.clone()).or_insert(0);
+            *mine = (*mine).max(count);
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GSet<T: Ord>(BTreeSet<T>);
+
+impl<T: Ord + Clone> GSet<T> {
+    pub fn new() -> Self {
+        Self(BTreeSet::new())
+    }
+
+    pub fn insert(&mut self, value: T) {
+        self.0.insert(value);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.0.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn merge(&mut self, other: &GSet<T>) {
+        self.0.extend(other.0.iter().cloned());
+    }
+}
+
+impl<T: Ord + Clone> Default for GSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum EventKind {
+    Restock,
+    TakeOut,
+    Return,
+    // Units the studio retired, sold or sent away from the shelf.
+    Remove,
+}
+
+// (replica, seq) identifies an event uniquely, so the same event arriving
+// from two branches is stored once.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct InventoryEvent {
+    pub replica: String,
+    pub seq: u64,
+    pub at: DateTime<Utc>,
+    pub kind: EventKind,
+    pub item: String,
+    pub quantity: u64,
+    pub artist_id: Option<usize>,
+}
+
+// A conflict found while merging, e.g. two offline branches both lending out
+// the last unit. Reported for a manager to resolve instead of failing the merge.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditItem {
+    pub item: String,
+    pub shortfall: u64,
+    pub takes: Vec<InventoryEvent>,
+}
+
+// Inventory one branch studio can change while offline and merge later:
+// shelf stock and units on loan, each as a counter per item.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrdtInventory {
+    pub replica: String,
+    seq: u64,
+    stock: BTreeMap<String, PnCounter>,
+    #[serde(default)]
+    lent: BTreeMap<String, PnCounter>,
+    events: GSet<InventoryEvent>,
+}
+
+impl CrdtInventory {
+    pub fn seeded(replica: &str, stock: &[(String, usize)], on_loan: &[(String, usize)]) -> Self {
+        let mut inventory = Self {
+            replica: replica.to_string(),
+            seq: 0,
+            stock: BTreeMap::new(),
+            lent: BTreeMap::new(),
+            events: GSet::new(),
+        };
+        for (item, quantity) in stock {
+            inventory
+                .stock
+                .entry(item.clone())
+                .or_default()
+                .increment(ORIGIN, *quantity as u64);
+        }
+        for (item, count) in on_loan {
+            inventory
+                .lent
+                .entry(item.clone())
+                .or_default()
+                .increment(ORIGIN, *count as u64);
+        }
+        inventory
+    }
+
+    pub fn quantity(&self, item: &str) -> i64 {
+        self.stock.get(item).map(PnCounter::value).unwrap_or(0)
+    }
+
+    pub fn on_loan(&self, item: &str) -> i64 {
+        self.lent.get(item).map(PnCounter::value).unwrap_or(0)
+    }
+
+    // Every item with a shelf counter, and what it comes to.
+    pub fn items(&self) -> impl Iterator<Item = (&str, i64)> {
+        self.stock
+            .iter()
+            .map(|(item, counter)| (item.as_str(), counter.value()))
+    }
+
+    // Every item with a loan counter, and how many units are out.
+    pub fn loans(&self) -> impl Iterator<Item = (&str, i64)> {
+        self.lent
+            .iter()
+            .map(|(item, counter)| (item.as_str(), counter.value()))
+    }
+
+    pub fn events(&self) -> impl Iterator<Item = &InventoryEvent> {
+        self.events.iter()
+    }
+
+    pub fn restock(&mut self, item: &str, quantity: u64, at: DateTime<Utc>) {
+        let replica = self.replica.clone();
+        self.stock
+            .entry(item.to_string())
+            .or_default()
+            .increment(&replica, quantity);
+        self.log(EventKind::Restock, item, quantity, None, at);
+    }
+
+    // Lends one unit if this branch still sees one in stock.
+    pub fn take_out(&mut self, item: &str, artist_id: usize, at: DateTime<Utc>) -> bool {
+        if self.quantity(item) <= 0 {
+            return false;
+        }
+        self.lend(item, artist_id, at);
+        true
+    }
+
+    fn lend(&mut self, item: &str, artist_id: usize, at: DateTime<Utc>) {
+        let replica = self.replica.clone();
+        self.stock
+            .entry(item.to_string())
+            .or_default()
+            .decrement(&replica, 1);
+        self.lent
+            .entry(item.to_string())
+            .or_default()
+            .increment(&replica, 1);
+        self.log(EventKind::TakeOut, item, 1, Some(artist_id), at);
+    }
+
+    pub fn give_back(&mut self, item: &str, artist_id: usize, at: DateTime<Utc>) {
+        let replica = self.replica.clone();
+        self.stock
+            .entry(item.to_string())
+            .or_default()
+            .increment(&replica, 1);
+        self.lent
+            .entry(item.to_string())
+            .or_default()
+            .decrement(&replica, 1);
+        self.log(EventKind::Return, item, 1, Some(artist_id), at);
+    }
+
+    pub fn remove(&mut self, item: &str, quantity: u64, at: DateTime<Utc>) {
+        let replica = self.replica.clone();
+        self.stock
+            .entry(item.to_string())
+            .or_default()
+            .decrement(&replica, quantity);
+        self.log(EventKind::Remove, item, quantity, None, at);
+    }
+
+    // Follows a change the registry has already made to its own stock, so
+    // the branch's counters keep up with the shelves. Paint isn't tracked.
+    pub fn record(&mut self, event: &events::EventRecord) {
+        let counts = || {
+            event
+                .quantities
+                .iter()
+                .filter_map(|(item, quantity)| Some((item, quantity.count()?.get() as u64)))
+        };
+        match (event.kind, event.artist_id) {
+            (State::TakeOut, Some(artist_id)) => {
+                for item in &event.items {
+                    self.lend(item, artist_id, event.at);
+                }
+            }
+            (State::Return, Some(artist_id)) => {
+                for item in &event.items {
+                    // A found unit stopped counting as lent when it was lost.
+                    if event.from == Some(State::Lost) {
+                        let replica = self.replica.clone();
+                        self.lent
+                            .entry(item.clone())
+                            .or_default()
+                            .increment(&replica, 1);
+                    }
+                    self.give_back(item, artist_id, event.at);
+                }
+            }
+            (State::New | State::Fill | State::TransferIn, None) => {
+                for (item, count) in counts() {
+                    self.restock(item, count, event.at);
+                }
+            }
+            (State::Retire | State::Sold | State::TransferOut, None) => {
+                for (item, count) in counts() {
+                    self.remove(item, count, event.at);
+                }
+            }
+            // A lent unit retired or lost never comes back to the shelf; one
+            // retired after it was lost already stopped counting.
+            (State::Retire, Some(_)) if event.from == Some(State::Lost) => {}
+            (State::Retire | State::Lost, Some(_)) => {
+                let replica = self.replica.clone();
+                for item in &event.items {
+                    self.lent
+                        .entry(item.clone())
+                        .or_default()
+                        .decrement(&replica, 1);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Folds in another branch's state. Returns an audit item for every tool
+    // that ends up with more units lent out than exist.
+    pub fn merge(&mut self, other: &CrdtInventory) -> Vec<AuditItem> {
+        for (item, counter) in &other.stock {
+            self.stock.entry(item.clone()).or_default().merge(counter);
+        }
+        for (item, counter) in &other.lent {
+            self.lent.entry(item.clone()).or_default().merge(counter);
+        }
+        self.events.merge(&other.events);
+        self.audit()
+    }
+
+    pub fn audit(&self) -> Vec<AuditItem> {
+        self.stock
+            .iter()
+            .filter(|(_, counter)| counter.value() < 0)
+            .map(|(item, counter)| AuditItem {
+                item: item.clone(),
+                shortfall: counter.value().unsigned_abs(),
+                takes: self
+                    .events
+                    .iter()
+                    .filter(|event| event.item == *item && event.kind == EventKind::TakeOut)
+                    .cloned()
+                    .collect(),
+            })
+            .collect()
+    }
+
+    fn log(
+        &mut self,
+        kind: EventKind,
+        item: &str,
+        quantity: u64,
+        artist_id: Option<usize>,
+        at: DateTime<Utc>,
+    ) {
+        self.seq += 1;
+        self.events.insert(InventoryEvent {
+            replica: self.replica.clone(),
+            seq: self.seq,
+            at,
+            kind,
+            item: item.to_string(),
+            quantity,
+            artist_id,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArtistToolRegistry, SharedResources};
+    use std::sync::{Arc, Mutex};
+
+    fn stock() -> Vec<(String, usize)> {
+        vec![("canvas".to_string(), 1), ("brush".to_string(), 5)]
+    }
+
+    #[test]
+    fn test_merge_is_commutative_and_idempotent() {
+        let now = Utc::now();
+        let mut north = CrdtInventory::seeded("north", &stock(), &[]);
+        let mut south = CrdtInventory::seeded("south", &stock(), &[]);
+        assert!(north.take_out("brush", 1, now));
+        south.restock("brush", 3, now);
+        south.give_back("canvas", 2, now);
+
+        let mut left = north.clone();
+        left.merge(&south);
+        let mut right = south.clone();
+        right.merge(&north);
+        assert_eq!(left.quantity("brush"), 7);
+        assert_eq!(left.quantity("canvas"), 2);
+        assert_eq!(left.stock, right.stock);
+        assert_eq!(left.events, right.events);
+
+        let before = left.clone();
+        left.merge(&south);
+        assert_eq!(left.stock, before.stock);
+        assert_eq!(left.events.len(), 3);
+    }
+
+    #[test]
+    fn test_concurrent_takes_of_last_unit_become_audit_item() {
+        let now = Utc::now();
+        let mut north = CrdtInventory::seeded("north", &stock(), &[]);
+        let mut south = CrdtInventory::seeded("south", &stock(), &[]);
+        assert!(north.take_out("canvas", 1, now));
+        assert!(south.take_out("canvas", 2, now));
+        assert!(!north.take_out("canvas", 3, now));
+
+        let audit = north.merge(&south);
+        assert_eq!(audit.len(), 1);
+        assert_eq!(audit[0].item, "canvas");
+        assert_eq!(audit[0].shortfall, 1);
+        let artists: Vec<_> = audit[0].takes.iter().map(|take| take.artist_id).collect();
+        assert_eq!(artists, vec![Some(1), Some(2)]);
+    }
+
+    #[test]
+    fn test_recorded_loss_stops_counting_as_lent_once() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        registry.branch = Some(CrdtInventory::seeded("north", &stock(), &[]));
+        for id in 1..=3 {
+            registry
+                .tool_registry(id, vec!["brush".to_string()])
+                .unwrap();
+        }
+        registry.report_lost(1, "brush").unwrap();
+        registry.retire(1, "brush").unwrap();
+        registry.report_lost(2, "brush").unwrap();
+        registry.recover_lost(2, "brush").unwrap();
+
+        let branch = registry.branch.as_ref().unwrap();
+        assert_eq!(branch.on_loan("brush"), 1);
+        assert_eq!(branch.quantity("brush"), 3);
+    }
+}
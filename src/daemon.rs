@@ -0,0 +1,771 @@
+use crate::{
+    auth::{Auth, Gate},
+    batch,
+    budgets::DepartmentSpend,
+    checkpoint::Checkpointer,
+    dump::StateDump,
+    expiry::EXPIRY_WARNING,
+    i18n::Message,
+    lock_stats::REGISTRY_LOCK,
+    money::{MixedCurrencies, Money},
+    overdue::Overdue,
+    registry::Checkout,
+    serials::Item,
+    units::Kilograms,
+    wear::WearLevel,
+    ArtistToolRegistry,
+};
+use chrono::Utc;
+use std::{
+    fmt::{self, Write as _},
+    fs,
+    io::{self, BufRead, BufReader, Read, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+pub const DEFAULT_SOCKET: &str = "rustic-canvas.sock";
+// How long a client gets to send its command before the daemon moves on to
+// the next connection.
+pub const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub enum Reply {
+    Continue(String),
+    Shutdown(String),
+}
+
+// Keeps one registry resident and serves one text command per connection:
+//
+//   checkout [atomic] <artist_id> <tool>[, <tool>...]
+//   return <artist_id> <tool>[, <tool>...]
+//   paint <artist_id> <color> <kg>[, <color> <kg>...]
+//   batch [atomic] <JSON array of operations>
+//   damaged <artist_id> <tool>
+//   repairs
+//   items [<artist_id>]
+//   timeline <serial> <tool>
+//   overdue
+//   wear
+//   budgets
+//   retire <admin_id> <count> <tool>
+//   sell <admin_id> <count> <amount> <currency> <tool>
+//   sweep <admin_id>
+//   status
+//   audit
+//   dump
+//   events [<artist_id>]
+//   shutdown
+//
+// With a checkpointer, every checkout, return and paint checkout is journaled
+// before it is acknowledged, and every other change is checkpointed.
+// With auth, commands must carry a token (see `Auth::gate`). A client that
+// fails, by hanging up, sending something unreadable or going quiet past
+// `CLIENT_TIMEOUT`, is logged and dropped; the daemon keeps serving.
+pub fn serve(
+    socket: &Path,
+    registry: Arc<Mutex<ArtistToolRegistry>>,
+    mut checkpoints: Option<Checkpointer>,
+    mut auth: Option<Auth>,
+) -> io::Result<()> {
+    if socket.exists() {
+        fs::remove_file(socket)?;
+    }
+    let listener = UnixListener::bind(socket)?;
+
+    for stream in listener.incoming() {
+        let stop = stream.and_then(|stream| {
+            serve_client(stream, &registry, checkpoints.as_mut(), auth.as_mut())
+        });
+        match stop {
+            Ok(true) => break,
+            Ok(false) => {}
+            Err(error) => tracing::warn!(
+                error = %error,
+                "{}",
+                Message::ClientFailed(&error.to_string())
+            ),
+        }
+    }
+
+    if let Some(checkpoints) = &mut checkpoints {
+        checkpoints.shutdown(&lock(&registry))?;
+    }
+    fs::remove_file(socket)
+}
+
+// Reads and answers one client's command. True once the daemon should shut
+// down.
+fn serve_client(
+    mut stream: UnixStream,
+    registry: &Mutex<ArtistToolRegistry>,
+    checkpoints: Option<&mut Checkpointer>,
+    auth: Option<&mut Auth>,
+) -> io::Result<bool> {
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    let mut line = String::new();
+    if let Err(error) = BufReader::new(&stream).read_line(&mut line) {
+        // Not worth an answer if the client has gone quiet or away.
+        if error.kind() == io::ErrorKind::InvalidData {
+            let _ = stream.write_all(error_reply(&error).as_bytes());
+        }
+        return Err(error);
+    }
+
+    let gate = match auth {
+        Some(auth) => auth.gate(line.trim(), Utc::now()),
+        None => Gate::Forward(line.trim()),
+    };
+    let reply = match gate {
+        Gate::Forward(command) => handle_command(command, registry, checkpoints),
+        Gate::Reply(text) => Reply::Continue(text),
+    };
+    let (text, stop) = match reply {
+        Reply::Continue(text) => (text, false),
+        Reply::Shutdown(text) => (text, true),
+    };
+    // A client that hangs up early shouldn't take the daemon down.
+    let _ = stream.write_all(text.as_bytes());
+    Ok(stop)
+}
+
+// Sends one command to a running daemon and returns its reply.
+pub fn send(socket: &Path, command: &str) -> io::Result<String> {
+    let mut stream = UnixStream::connect(socket)?;
+    stream.write_all(command.as_bytes())?;
+    stream.write_all(b"\n")?;
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply)?;
+    Ok(reply)
+}
+
+pub fn handle_command(
+    line: &str,
+    registry: &Mutex<ArtistToolRegistry>,
+    mut checkpoints: Option<&mut Checkpointer>,
+) -> Reply {
+    let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+    let reply = match command {
+        "checkout" if rest.trim_start().starts_with("atomic ") => {
+            match parse_artist_tools(&rest.trim_start()["atomic ".len()..]) {
+                Ok((artist_id, tools)) => {
+                    let mut registry = lock(registry);
+                    match registry.checkout_all(artist_id, tools.clone()) {
+                        Ok(()) => {
+                            let journaled = match checkpoints {
+                                Some(checkpoints) => {
+                                    // Names resolve the same way they just did.
+                                    let checkout = Checkout {
+                                        lent: registry
+                                            .resolve_tools(tools.clone())
+                                            .unwrap_or(tools.clone()),
+                                        ..Checkout::default()
+                                    };
+                                    checkpoints.record(artist_id, &checkout, &registry)
+                                }
+                                None => Ok(()),
+                            };
+                            match journaled {
+                                Ok(()) => ok_reply(Message::ArtistHolds(artist_id, tools.len())),
+                                Err(error) => error_reply(Message::NotJournaled(
+                                    "checkout",
+                                    error.to_string(),
+                                )),
+                            }
+                        }
+                        Err(error) => error_reply(error),
+                    }
+                }
+                Err(error) => error,
+            }
+        }
+        "checkout" => match parse_artist_tools(rest) {
+            Ok((artist_id, tools)) => {
+                let mut registry = lock(registry);
+                let checkout = match registry.tool_registry(artist_id, tools) {
+                    Ok(checkout) => checkout,
+                    Err(error) => return Reply::Continue(error_reply(error)),
+                };
+                let journaled = match checkpoints {
+                    Some(checkpoints) => checkpoints.record(artist_id, &checkout, &registry),
+                    None => Ok(()),
+                };
+                match journaled {
+                    Ok(()) => ok_reply(Message::ArtistHolds(artist_id, checkout.lent.len())),
+                    Err(error) => error_reply(Message::NotJournaled("checkout", error.to_string())),
+                }
+            }
+            Err(error) => error,
+        },
+        "batch" => {
+            let (atomic, json) = match rest.trim().strip_prefix("atomic") {
+                Some(json) => (true, json),
+                None => (false, rest),
+            };
+            match batch::parse_batch(json) {
+                Ok(ops) => {
+                    let mut registry = lock(registry);
+                    let report = batch::run(&ops, &mut registry, atomic);
+                    let mut journaled = Ok(());
+                    if let Some(checkpoints) = checkpoints.as_mut() {
+                        for (artist_id, checkout) in &report.checkouts {
+                            journaled = journaled
+                                .and_then(|()| checkpoints.record(*artist_id, checkout, &registry));
+                        }
+                    }
+                    match journaled {
+                        Ok(()) => report.to_text(),
+                        Err(error) => {
+                            report.to_text()
+                                + &error_reply(Message::NotJournaled("batch", error.to_string()))
+                        }
+                    }
+                }
+                Err(error) => error_reply(error),
+            }
+        }
+        "return" => match parse_artist_tools(rest) {
+            Ok((artist_id, tools)) => {
+                let mut registry = lock(registry);
+                match registry.tool_return(artist_id, tools.clone()) {
+                    Ok(()) => {
+                        let journaled = match checkpoints {
+                            Some(checkpoints) => {
+                                checkpoints.record_return(artist_id, &tools, &registry)
+                            }
+                            None => Ok(()),
+                        };
+                        match journaled {
+                            Ok(()) => ok_reply(Message::ArtistReturned(artist_id, tools.len())),
+                            Err(error) => {
+                                error_reply(Message::NotJournaled("return", error.to_string()))
+                            }
+                        }
+                    }
+                    Err(error) => error_reply(error),
+                }
+            }
+            Err(error) => error,
+        },
+        "paint" => match parse_artist_paints(rest) {
+            Ok((artist_id, paints)) => {
+                let mut registry = lock(registry);
+                let kg: Kilograms = paints.iter().map(|&(_, kg)| kg).sum();
+                match registry.paint_checkout(artist_id, paints.clone()) {
+                    Ok(()) => {
+                        let journaled = match checkpoints {
+                            Some(checkpoints) => {
+                                checkpoints.record_paint(artist_id, &paints, &registry)
+                            }
+                            None => Ok(()),
+                        };
+                        match journaled {
+                            Ok(()) => ok_reply(Message::ArtistTookPaint(artist_id, kg)),
+                            Err(error) => {
+                                error_reply(Message::NotJournaled("paint", error.to_string()))
+                            }
+                        }
+                    }
+                    Err(error) => error_reply(error),
+                }
+            }
+            Err(error) => error,
+        },
+        // Hands back one damaged unit, which goes to the repair queue.
+        "damaged" => {
+            let (id, tool) = rest.trim().split_once(' ').unwrap_or((rest.trim(), ""));
+            match id.parse() {
+                Ok(artist_id) if !tool.trim().is_empty() => {
+                    let mut registry = lock(registry);
+                    match registry.return_damaged(artist_id, tool.trim()) {
+                        Ok(ticket) => checkpointed(
+                            checkpoints,
+                            &registry,
+                            ok_reply(Message::RepairTicketOpened(ticket)),
+                        ),
+                        Err(error) => error_reply(error),
+                    }
+                }
+                Ok(_) => error_reply(Message::NoToolGiven),
+                Err(_) => error_reply(Message::InvalidArtistId(id)),
+            }
+        }
+        "repairs" => repairs(&lock(registry)),
+        // Reverses an operator's slip: the artist's last checkout or return.
+        "undo" => match rest.trim().parse() {
+            Ok(artist_id) => {
+                let mut registry = lock(registry);
+                match registry.undo_last(artist_id) {
+                    Ok(()) => checkpointed(
+                        checkpoints,
+                        &registry,
+                        ok_reply(Message::UndidLast(artist_id)),
+                    ),
+                    Err(error) => error_reply(error),
+                }
+            }
+            Err(_) => error_reply(Message::InvalidArtistId(rest.trim())),
+        },
+        "items" => match rest.trim() {
+            "" => items(&lock(registry).items()),
+            id => match id.parse() {
+                Ok(artist_id) => items(&lock(registry).items_held(artist_id)),
+                Err(_) => error_reply(Message::InvalidArtistId(id)),
+            },
+        },
+        "timeline" => {
+            let unit = rest
+                .trim()
+                .split_once(' ')
+                .and_then(|(serial, tool)| Some((serial.parse().ok()?, tool.trim())));
+            match unit {
+                Some((serial, tool)) => match lock(registry).tool_timeline(tool, serial) {
+                    Some(timeline) => timeline.to_text(),
+                    None => error_reply(Message::NoSuchUnit(tool, serial)),
+                },
+                None => error_reply(Message::CommandUsage("timeline <serial> <tool>")),
+            }
+        }
+        "overdue" => overdue(&lock(registry).overdue()),
+        "wear" => wear(&lock(registry).wear_levels()),
+        "budgets" => match lock(registry).department_spend() {
+            Ok(spend) => budgets(&spend),
+            Err(MixedCurrencies(left, right)) => error_reply(Message::MixedCurrencies(left, right)),
+        },
+        "retire" => match parse_disposal(rest, false) {
+            Some((admin_id, count, _, tool)) => {
+                let mut registry = lock(registry);
+                match registry.retire_stock(admin_id, &tool, count) {
+                    Ok(()) => checkpointed(
+                        checkpoints,
+                        &registry,
+                        ok_reply(Message::StockRetired(count, &tool)),
+                    ),
+                    Err(error) => error_reply(error),
+                }
+            }
+            None => error_reply(Message::CommandUsage("retire <admin_id> <count> <tool>")),
+        },
+        "sell" => match parse_disposal(rest, true) {
+            Some((admin_id, count, Some(price), tool)) => {
+                let mut registry = lock(registry);
+                match registry.sell_stock(admin_id, &tool, count, price) {
+                    Ok(()) => checkpointed(
+                        checkpoints,
+                        &registry,
+                        ok_reply(Message::StockSold(count, &tool, price)),
+                    ),
+                    Err(error) => error_reply(error),
+                }
+            }
+            _ => error_reply(Message::CommandUsage(
+                "sell <admin_id> <count> <amount> <currency> <tool>",
+            )),
+        },
+        // Takes expired paint batches out of stock.
+        "sweep" => match rest.trim().parse() {
+            Ok(admin_id) => {
+                let mut registry = lock(registry);
+                let now = registry.now();
+                match registry.sweep_expired_paints(admin_id, now) {
+                    Ok(expired) => {
+                        let kg: Kilograms = expired.iter().map(|batch| batch.kg).sum();
+                        checkpointed(
+                            checkpoints,
+                            &registry,
+                            ok_reply(Message::BatchesExpired(expired.len(), kg)),
+                        )
+                    }
+                    Err(error) => error_reply(error),
+                }
+            }
+            Err(_) => error_reply(Message::InvalidAdminId(rest.trim())),
+        },
+        "status" => status(&lock(registry)),
+        "audit" => match lock(registry).audit_shards() {
+            Ok(audit) => audit.to_string(),
+            Err(error) => error_reply(error),
+        },
+        "dump" => match StateDump::capture(&lock(registry)).to_json() {
+            Ok(json) => json + "\n",
+            Err(error) => error_reply(error),
+        },
+        // The event log as JSON Lines, optionally just one artist's events.
+        "events" => {
+            let registry = lock(registry);
+            let artist_id = match rest.trim() {
+                "" => None,
+                id => match id.parse() {
+                    Ok(id) => Some(id),
+                    Err(_) => return Reply::Continue(error_reply(Message::InvalidArtistId(id))),
+                },
+            };
+            let mut lines = String::new();
+            for event in registry.events.records() {
+                if artist_id.is_some_and(|id| event.artist_id != Some(id)) {
+                    continue;
+                }
+                match serde_json::to_string(&event) {
+                    Ok(json) => lines.push_str(&(json + "\n")),
+                    Err(error) => return Reply::Continue(error_reply(error)),
+                }
+            }
+            lines
+        }
+        "shutdown" => return Reply::Shutdown(ok_reply(Message::ShuttingDown)),
+        "" => error_reply(Message::EmptyCommand),
+        other => error_reply(Message::UnknownCommand(other)),
+    };
+    Reply::Continue(reply)
+}
+
+// Replies `ok` once the change is in a checkpoint, for the commands the
+// journal doesn't cover.
+fn checkpointed(
+    checkpoints: Option<&mut Checkpointer>,
+    registry: &ArtistToolRegistry,
+    ok: String,
+) -> String {
+    match checkpoints.map(|checkpoints| checkpoints.checkpoint(registry)) {
+        Some(Err(error)) => error_reply(Message::NotCheckpointed(error.to_string())),
+        _ => ok,
+    }
+}
+
+// Replies open with `ok:` or `error:` whatever the language, so clients can
+// tell them apart; the rest reads in the operator's.
+pub(crate) fn ok_reply(message: Message) -> String {
+    format!("ok: {}\n", message)
+}
+
+pub(crate) fn error_reply(reason: impl fmt::Display) -> String {
+    format!("error: {}\n", reason)
+}
+
+fn parse_artist_tools(rest: &str) -> Result<(usize, Vec<String>), String> {
+    let (id, tools) = rest.trim().split_once(' ').unwrap_or((rest.trim(), ""));
+    let artist_id = id
+        .parse()
+        .map_err(|_| error_reply(Message::InvalidArtistId(id)))?;
+    let tools: Vec<String> = tools
+        .split(',')
+        .map(str::trim)
+        .filter(|tool| !tool.is_empty())
+        .map(str::to_string)
+        .collect();
+    if tools.is_empty() {
+        return Err(error_reply(Message::NoToolsGiven));
+    }
+    Ok((artist_id, tools))
+}
+
+// `<admin_id> <count> [<amount> <currency>] <tool>`, the price only when
+// `priced`. Tool names may contain spaces, so the tool comes last.
+fn parse_disposal(rest: &str, priced: bool) -> Option<(usize, usize, Option<Money>, String)> {
+    let mut words = rest.split_whitespace();
+    let admin_id = words.next()?.parse().ok()?;
+    let count = words.next()?.parse().ok()?;
+    let price = match priced {
+        true => Some(Money::parse(&format!(
+            "{} {}",
+            words.next()?,
+            words.next()?
+        ))?),
+        false => None,
+    };
+    let tool = words.collect::<Vec<_>>().join(" ");
+    (!tool.is_empty()).then_some((admin_id, count, price, tool))
+}
+
+fn parse_artist_paints(rest: &str) -> Result<(usize, Vec<(String, Kilograms)>), String> {
+    let (id, paints) = rest.trim().split_once(' ').unwrap_or((rest.trim(), ""));
+    let artist_id = id
+        .parse()
+        .map_err(|_| error_reply(Message::InvalidArtistId(id)))?;
+    let mut parsed = vec![];
+    for paint in paints.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (color, kg) = paint
+            .rsplit_once(' ')
+            .and_then(|(color, kg)| Some((color.trim(), kg.parse().ok()?)))
+            .ok_or_else(|| error_reply(Message::ExpectedPaint(paint)))?;
+        parsed.push((color.to_string(), kg));
+    }
+    if parsed.is_empty() {
+        return Err(error_reply(Message::NoPaintsGiven));
+    }
+    Ok((artist_id, parsed))
+}
+
+pub fn status(registry: &ArtistToolRegistry) -> String {
+    let dump = StateDump::capture(registry);
+    let mut text = String::new();
+    for (name, quantity) in &dump.tools {
+        let _ = writeln!(text, "tool  {:<16} {}", name, quantity);
+    }
+    for (name, quantity) in &dump.paints {
+        let _ = writeln!(text, "paint {:<16} {}", name, quantity);
+    }
+    let _ = writeln!(text, "entries {}", dump.entries.len());
+    let resources = registry
+        .shared_resources
+        .lock()
+        .unwrap_or_else(|poisoned| registry.recovered(poisoned));
+    for (tier, waits) in resources.loan_caps.wait_stats(registry.now()) {
+        let _ = writeln!(
+            text,
+            "queue {:<6} served {} avg {}s max {}s waiting {} oldest {}s",
+            format!("{:?}", tier).to_lowercase(),
+            waits.served,
+            waits.avg_wait.num_seconds(),
+            waits.max_wait.num_seconds(),
+            waits.waiting,
+            waits.oldest_waiting.num_seconds()
+        );
+    }
+    for batch in resources.batches.expiring(registry.now(), EXPIRY_WARNING) {
+        let _ = writeln!(
+            text,
+            "expiring {:<16} {} {} kg on {}",
+            batch.color,
+            batch.batch,
+            batch.kg,
+            batch.expires.format("%Y-%m-%d")
+        );
+    }
+    text
+}
+
+// One line per open repair ticket, oldest first.
+pub fn repairs(registry: &ArtistToolRegistry) -> String {
+    let mut text = String::new();
+    for job in registry.repairs.jobs() {
+        let progress = match job.ready_at() {
+            Some(ready) => format!("ready {}", ready.format("%Y-%m-%d %H:%M")),
+            None => "waiting".to_string(),
+        };
+        let cost = job.cost.map(|cost| cost.to_string()).unwrap_or_default();
+        let _ = writeln!(
+            text,
+            "repair {:<4} {:<16} artist {:<4} {} {}",
+            job.ticket, job.tool, job.artist_id, progress, cost
+        );
+    }
+    text
+}
+
+// One line per numbered unit, by tool and serial.
+pub fn items(items: &[Item]) -> String {
+    let mut text = String::new();
+    for item in items {
+        let whereabouts = match item.artist_id {
+            None => "on shelf".to_string(),
+            Some(artist_id) => format!("{:?} artist {}", item.state, artist_id),
+        };
+        let _ = writeln!(text, "item {:<20} {}", item.to_string(), whereabouts);
+    }
+    text
+}
+
+// One line per overdue unit, latest first.
+pub fn overdue(overdue: &[Overdue]) -> String {
+    let mut text = String::new();
+    for unit in overdue {
+        let _ = writeln!(
+            text,
+            "overdue artist {:<4} {:<16} due {} late {}h",
+            unit.artist_id,
+            unit.tool,
+            unit.due.format("%Y-%m-%d %H:%M"),
+            unit.late.num_hours()
+        );
+    }
+    text
+}
+
+// One line per stocked tool, most worn first.
+pub fn wear(levels: &[WearLevel]) -> String {
+    let mut text = String::new();
+    for level in levels {
+        let _ = writeln!(
+            text,
+            "wear {:<16} {:>3}{}",
+            level.tool,
+            level.durability,
+            if level.worn_out { " worn out" } else { "" }
+        );
+    }
+    text
+}
+
+// One line per department that spent this month or has a limit.
+pub fn budgets(spend: &[DepartmentSpend]) -> String {
+    let mut text = String::new();
+    for department in spend {
+        let limit = department
+            .limit
+            .map(|limit| limit.to_string())
+            .unwrap_or_else(|| "no limit".to_string());
+        let _ = writeln!(
+            text,
+            "budget {:<16} restock {} repair {} of {}",
+            department.department, department.restock, department.repair, limit
+        );
+    }
+    text
+}
+
+fn lock(registry: &Mutex<ArtistToolRegistry>) -> std::sync::MutexGuard<'_, ArtistToolRegistry> {
+    REGISTRY_LOCK.lock(registry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SharedResources;
+    use std::{env, process, thread, time::Duration};
+
+    fn registry() -> Arc<Mutex<ArtistToolRegistry>> {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        Arc::new(Mutex::new(ArtistToolRegistry::new(&resources)))
+    }
+
+    fn reply_text(reply: Reply) -> String {
+        match reply {
+            Reply::Continue(text) | Reply::Shutdown(text) => text,
+        }
+    }
+
+    #[test]
+    fn test_handle_command_checkout_and_errors() {
+        let registry = registry();
+        let reply = reply_text(handle_command(
+            "checkout 3 brush, sculpting tool",
+            &registry,
+            None,
+        ));
+        assert_eq!(reply, "ok: artist 3 holds 2 new item(s)\n");
+        assert!(
+            reply_text(handle_command("checkout x brush", &registry, None)).starts_with("error")
+        );
+        assert!(reply_text(handle_command("checkout 3", &registry, None)).starts_with("error"));
+        assert_eq!(
+            reply_text(handle_command("return 3 brush", &registry, None)),
+            "ok: artist 3 returned 1 item(s)\n"
+        );
+        assert_eq!(
+            reply_text(handle_command("return 3 brush", &registry, None)),
+            "error: artist 3 did not check out brush\n"
+        );
+        assert_eq!(
+            reply_text(handle_command(
+                "checkout atomic 4 brush, easel",
+                &registry,
+                None
+            )),
+            "error: not available: easel\n"
+        );
+        assert_eq!(
+            reply_text(handle_command(
+                "checkout atomic 4 brush, tape",
+                &registry,
+                None
+            )),
+            "ok: artist 4 holds 2 new item(s)\n"
+        );
+        assert_eq!(
+            reply_text(handle_command("paint 3 red 2, white 0.5", &registry, None)),
+            "ok: artist 3 took 2.5 kg of paint\n"
+        );
+        assert_eq!(
+            reply_text(handle_command("paint 3 red lots", &registry, None)),
+            "error: expected '<color> <kg>', got 'red lots'\n"
+        );
+        assert!(reply_text(handle_command("fly", &registry, None)).contains("unknown command"));
+        assert!(reply_text(handle_command("status", &registry, None)).contains("brush"));
+        assert!(!reply_text(handle_command("audit", &registry, None)).contains('!'));
+        let events = reply_text(handle_command("events 4", &registry, None));
+        assert_eq!(events.lines().count(), 1);
+        assert!(events.contains("\"kind\":\"TakeOut\""));
+        assert_eq!(
+            reply_text(handle_command("damaged 4 tape", &registry, None)),
+            "ok: repair ticket 1\n"
+        );
+        assert!(reply_text(handle_command("repairs", &registry, None)).contains("tape"));
+        assert!(reply_text(handle_command("items", &registry, None)).contains("tape #1"));
+        assert!(
+            reply_text(handle_command("wear", &registry, None)).contains("brush            100")
+        );
+        assert!(!reply_text(handle_command("budgets", &registry, None)).starts_with("error"));
+        assert_eq!(
+            reply_text(handle_command("retire 0 2 sculpting tool", &registry, None)),
+            "ok: retired 2 sculpting tool\n"
+        );
+        assert_eq!(
+            reply_text(handle_command("sell 0 1 12.50 USD roller", &registry, None)),
+            "ok: sold 1 roller for 12.50 USD\n"
+        );
+        assert!(reply_text(handle_command("sell 0 1 roller", &registry, None)).contains("usage"));
+        assert!(matches!(
+            handle_command("shutdown", &registry, None),
+            Reply::Shutdown(_)
+        ));
+    }
+
+    #[test]
+    fn test_undo_survives_a_crash() {
+        let dir = env::temp_dir().join(format!("rustic-canvas-undo-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let open = || {
+            let resources = Arc::new(Mutex::new(SharedResources::default()));
+            let (checkpoints, registry, _) =
+                Checkpointer::open(&dir, 100, Duration::from_secs(60), &resources).unwrap();
+            (checkpoints, Mutex::new(registry))
+        };
+        {
+            let (mut checkpoints, registry) = open();
+            for command in ["checkout 1 brush", "undo 1"] {
+                let reply = reply_text(handle_command(command, &registry, Some(&mut checkpoints)));
+                assert!(reply.starts_with("ok"), "{}", reply);
+            }
+            // Dropped without a clean shutdown.
+        }
+        let (_, registry) = open();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(registry.lock().unwrap().held_tools(1).is_empty());
+        // The undo itself isn't taken for a return that could be undone.
+        assert!(reply_text(handle_command("undo 1", &registry, None)).starts_with("error"));
+    }
+
+    #[test]
+    fn test_serve_over_unix_socket() {
+        let socket = env::temp_dir().join(format!("rustic-canvas-test-{}.sock", process::id()));
+        let registry = registry();
+        let server = {
+            let socket = socket.clone();
+            let registry = Arc::clone(&registry);
+            thread::spawn(move || serve(&socket, registry, None, None))
+        };
+        // Probe until the listener accepts; the probe itself is an empty command.
+        while UnixStream::connect(&socket).is_err() {
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        // A client sending garbage is answered and dropped without stopping
+        // the daemon.
+        let mut garbage = UnixStream::connect(&socket).unwrap();
+        garbage.write_all(b"\xff\xfe status\n").unwrap();
+        let mut reply = String::new();
+        garbage.read_to_string(&mut reply).unwrap();
+        assert!(reply.starts_with("error"));
+        assert!(send(&socket, "checkout 1 canvas")
+            .unwrap()
+            .starts_with("ok"));
+        assert!(send(&socket, "dump").unwrap().contains("\"canvas\""));
+        assert!(send(&socket, "shutdown").unwrap().starts_with("ok"));
+        server.join().unwrap().unwrap();
+
+        assert!(!socket.exists());
+        assert_eq!(registry.lock().unwrap().artist_tool_preferences.len(), 1);
+    }
+}
@@ -0,0 +1,126 @@
+use crate::{money::Money, units::Amount};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+// Supplies arriving from a supplier: units of tools or kilograms of paint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Delivery {
+    pub supplier: String,
+    pub items: Vec<(String, Amount)>,
+    pub cost: Money,
+}
+
+// A delivery as it was put on the shelves.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FillEntry {
+    pub at: DateTime<Utc>,
+    pub delivery: Delivery,
+}
+
+// The most the storeroom holds of each item. Units out on loan count, since
+// they come back to the same shelf. Items without a capacity are unlimited.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StorageCapacity {
+    max: HashMap<String, Amount>,
+}
+
+impl StorageCapacity {
+    // In the item's own unit; a capacity in the wrong one never refuses.
+    pub fn set(&mut self, item: &str, max: impl Into<Amount>) {
+        self.max.insert(item.to_string(), max.into());
+    }
+
+    pub fn get(&self, item: &str) -> Option<Amount> {
+        self.max.get(item).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::{RegistryError, ResourceError},
+        money::Currency,
+        resources::{TOTAL_ITEMS, TOTAL_WEIGHT_KG},
+        units::{Count, Kilograms},
+        ArtistToolRegistry, SharedResources, State,
+    };
+    use std::sync::{Arc, Mutex};
+
+    fn delivery(items: &[(&str, Amount)]) -> Delivery {
+        Delivery {
+            supplier: "Brushworks".to_string(),
+            items: items
+                .iter()
+                .map(|&(item, quantity)| (item.to_string(), quantity))
+                .collect(),
+            cost: Money::new(12_000, Currency::USD),
+        }
+    }
+
+    #[test]
+    fn test_delivery_restocks_and_books_the_cost() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        registry
+            .receive_delivery(delivery(&[
+                ("brush", Count(5).into()),
+                ("red", Kilograms::grams(2_500).into()),
+                ("easel", Count(2).into()),
+            ]))
+            .unwrap();
+
+        {
+            let resources = resources.lock().unwrap();
+            assert_eq!(resources.stock("brush"), TOTAL_ITEMS + Count(5));
+            assert_eq!(resources.stock("easel"), Count(2));
+            assert_eq!(
+                resources.paints.quantity("red"),
+                TOTAL_WEIGHT_KG + Kilograms::grams(2_500)
+            );
+        }
+        assert_eq!(registry.fills.len(), 1);
+        assert_eq!(registry.fills[0].delivery.supplier, "Brushworks");
+        let event = registry.events.last().unwrap();
+        assert_eq!((event.kind, event.artist_id), (State::Fill, None));
+        assert_eq!(event.quantities["brush"], Count(5).into());
+        let entry = registry.ledger.entries().last().unwrap();
+        assert_eq!(entry.amount, Money::new(12_000, Currency::USD));
+        assert!(entry.memo.contains("Brushworks"));
+    }
+
+    #[test]
+    fn test_delivery_over_capacity_is_refused_whole() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        resources.lock().unwrap().capacity.set("brush", Count(12));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        registry
+            .tool_registry(1, vec!["brush".to_string()])
+            .unwrap();
+
+        // Nine on the shelf and one on loan leave room for two more.
+        assert_eq!(
+            registry.receive_delivery(delivery(&[
+                ("tape", Count(4).into()),
+                ("brush", Count(2).into()),
+                ("brush", Count(1).into()),
+            ])),
+            Err(RegistryError::Resource(ResourceError::OverCapacity {
+                item: "brush".to_string(),
+                holding: TOTAL_ITEMS.into(),
+                delivered: Count(3).into(),
+                capacity: Count(12).into(),
+            }))
+        );
+        assert_eq!(resources.lock().unwrap().stock("tape"), TOTAL_ITEMS);
+        assert!(registry.fills.is_empty());
+        // Brushes don't come by the kilogram.
+        assert!(matches!(
+            registry.receive_delivery(delivery(&[("brush", Kilograms::whole(1).into())])),
+            Err(RegistryError::Resource(ResourceError::WrongUnit { .. }))
+        ));
+        registry
+            .receive_delivery(delivery(&[("brush", Count(2).into())]))
+            .unwrap();
+    }
+}
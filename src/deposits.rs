@@ -0,0 +1,171 @@
+use crate::money::{ExchangeRates, Money, UnknownCurrency};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeldDeposit {
+    pub artist_id: usize,
+    pub tool: String,
+    pub amount: Money,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Forfeit {
+    pub deducted: Money,
+    pub refunded: Money,
+}
+
+// Totals are expressed in the studio's base currency.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DepositStatement {
+    pub held: Vec<HeldDeposit>,
+    pub held_total: Money,
+    pub forfeited_total: Money,
+}
+
+// Deposits for high-value tools: held on checkout, released on return, and
+// drawn down when a tool comes back damaged or not at all.
+#[derive(Debug, Clone, Default)]
+pub struct Deposits {
+    required: HashMap<String, Money>,
+    held: Vec<HeldDeposit>,
+    forfeited: HashMap<usize, Vec<Money>>,
+}
+
+impl Deposits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn require(&mut self, tool: &str, amount: Money) {
+        self.required.insert(tool.to_string(), amount);
+    }
+
+    pub fn required(&self, tool: &str) -> Option<Money> {
+        self.required.get(tool).copied()
+    }
+
+    // Takes the deposit for `tool` if it needs one and returns the amount held.
+    pub fn hold(&mut self, artist_id: usize, tool: &str) -> Option<Money> {
+        let amount = self.required(tool)?;
+        self.held.push(HeldDeposit {
+            artist_id,
+            tool: tool.to_string(),
+            amount,
+        });
+        Some(amount)
+    }
+
+    pub fn release(&mut self, artist_id: usize, tool: &str) -> Option<Money> {
+        self.take_held(artist_id, tool)
+            .map(|deposit| deposit.amount)
+    }
+
+    // Keeps up to `deduction` and refunds the rest. A deduction in another
+    // currency is converted into the deposit's first; one with no rate to
+    // convert it by fails and leaves the deposit held.
+    pub fn forfeit(
+        &mut self,
+        artist_id: usize,
+        tool: &str,
+        deduction: Money,
+        rates: &ExchangeRates,
+    ) -> Result<Option<Forfeit>, UnknownCurrency> {
+        let Some(pos) = self.position(artist_id, tool) else {
+            return Ok(None);
+        };
+        let deduction = rates.convert(deduction, self.held[pos].amount.currency)?;
+        let deposit = self.held.remove(pos);
+        let deducted = deduction.min(deposit.amount);
+        self.forfeited.entry(artist_id).or_default().push(deducted);
+        // Both are in the deposit's currency by now.
+        let refunded = Money::new(
+            deposit.amount.minor_units - deducted.minor_units,
+            deposit.amount.currency,
+        );
+        Ok(Some(Forfeit { deducted, refunded }))
+    }
+
+    pub fn statement(
+        &self,
+        artist_id: usize,
+        rates: &ExchangeRates,
+    ) -> Result<DepositStatement, UnknownCurrency> {
+        let held: Vec<HeldDeposit> = self
+            .held
+            .iter()
+            .filter(|deposit| deposit.artist_id == artist_id)
+            .cloned()
+            .collect();
+        let held_total = rates.sum(held.iter().map(|deposit| deposit.amount))?;
+        let forfeited = self.forfeited.get(&artist_id).into_iter().flatten();
+        let forfeited_total = rates.sum(forfeited.copied())?;
+        Ok(DepositStatement {
+            held,
+            held_total,
+            forfeited_total,
+        })
+    }
+
+    fn take_held(&mut self, artist_id: usize, tool: &str) -> Option<HeldDeposit> {
+        let pos = self.position(artist_id, tool)?;
+        Some(self.held.remove(pos))
+    }
+
+    fn position(&self, artist_id: usize, tool: &str) -> Option<usize> {
+        self.held
+            .iter()
+            .position(|deposit| deposit.artist_id == artist_id && deposit.tool == tool)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::money::Currency;
+
+    #[test]
+    fn test_hold_and_release_deposit() {
+        let rates = ExchangeRates::new(Currency::USD);
+        let mut deposits = Deposits::new();
+        deposits.require("sculpting tool", Money::new(5_000, Currency::USD));
+        assert_eq!(deposits.hold(1, "brush"), None);
+        assert!(deposits.hold(1, "sculpting tool").is_some());
+        assert_eq!(
+            deposits.statement(1, &rates).unwrap().held_total,
+            Money::new(5_000, Currency::USD)
+        );
+
+        assert!(deposits.release(1, "sculpting tool").is_some());
+        assert_eq!(deposits.release(1, "sculpting tool"), None);
+        let statement = deposits.statement(1, &rates).unwrap();
+        assert!(statement.held.is_empty());
+        assert_eq!(statement.held_total, Money::zero(Currency::USD));
+    }
+
+    #[test]
+    fn test_forfeit_deducts_from_deposit_and_reports_in_base() {
+        let mut rates = ExchangeRates::new(Currency::USD);
+        rates.set_rate(Currency::EUR, 1.1);
+        let mut deposits = Deposits::new();
+        deposits.require("roller", Money::new(2_000, Currency::EUR));
+        deposits.hold(2, "roller");
+
+        // A deduction with no rate into the deposit's currency keeps it held.
+        let gbp = Currency::parse("GBP").unwrap();
+        assert_eq!(
+            deposits.forfeit(2, "roller", Money::new(1_000, gbp), &rates),
+            Err(UnknownCurrency(gbp))
+        );
+        // One in dollars comes off the euro deposit at the going rate.
+        let forfeit = deposits
+            .forfeit(2, "roller", Money::new(1_650, Currency::USD), &rates)
+            .unwrap()
+            .unwrap();
+        assert_eq!(forfeit.deducted, Money::new(1_500, Currency::EUR));
+        assert_eq!(forfeit.refunded, Money::new(500, Currency::EUR));
+
+        let statement = deposits.statement(2, &rates).unwrap();
+        assert_eq!(statement.held_total, Money::zero(Currency::USD));
+        assert_eq!(statement.forfeited_total, Money::new(1_650, Currency::USD));
+    }
+}
@@ -0,0 +1,174 @@
+use chrono::{DateTime, Duration, Utc};
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RackedArtwork {
+    pub artwork_id: usize,
+    pub artist_id: usize,
+    pub racked_at: DateTime<Utc>,
+    pub dry_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct WaitingArtwork {
+    artwork_id: usize,
+    artist_id: usize,
+    completed_at: DateTime<Utc>,
+    drying_time: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RackOutcome {
+    Racked { dry_at: DateTime<Utc> },
+    // Position in the queue, 1 being next in line for a rack.
+    Queued { position: usize },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DryingStats {
+    pub capacity: usize,
+    pub occupied: usize,
+    pub queued: usize,
+    pub max_queued: usize,
+    pub racked_total: usize,
+    pub avg_wait_for_rack: Duration,
+}
+
+// Finished canvases need a rack to dry on before they count as done. When all
+// racks are taken they wait in completion order for the next free one.
+#[derive(Debug, Clone)]
+pub struct DryingRacks {
+    capacity: usize,
+    drying: Vec<RackedArtwork>,
+    waiting: VecDeque<WaitingArtwork>,
+    max_queued: usize,
+    racked_total: usize,
+    total_wait: Duration,
+}
+
+impl DryingRacks {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            drying: vec![],
+            waiting: VecDeque::new(),
+            max_queued: 0,
+            racked_total: 0,
+            total_wait: Duration::zero(),
+        }
+    }
+
+    pub fn complete(
+        &mut self,
+        artwork_id: usize,
+        artist_id: usize,
+        drying_time: Duration,
+        now: DateTime<Utc>,
+    ) -> RackOutcome {
+        let waiting = WaitingArtwork {
+            artwork_id,
+            artist_id,
+            completed_at: now,
+            drying_time,
+        };
+        if self.drying.len() < self.capacity {
+            return RackOutcome::Racked {
+                dry_at: self.rack(waiting, now).dry_at,
+            };
+        }
+        self.waiting.push_back(waiting);
+        self.max_queued = self.max_queued.max(self.waiting.len());
+        RackOutcome::Queued {
+            position: self.waiting.len(),
+        }
+    }
+
+    // Takes everything that has dried by `now` off the racks, in the order it
+    // dried. Each freed rack goes to the next waiting canvas at the moment it
+    // was freed, so a long gap between calls doesn't distort waiting times.
+    pub fn advance(&mut self, now: DateTime<Utc>) -> Vec<RackedArtwork> {
+        let mut dried = vec![];
+        while let Some(pos) = self
+            .drying
+            .iter()
+            .enumerate()
+            .filter(|(_, racked)| racked.dry_at <= now)
+            .min_by_key(|(_, racked)| racked.dry_at)
+            .map(|(pos, _)| pos)
+        {
+            let done = self.drying.swap_remove(pos);
+            if let Some(next) = self.waiting.pop_front() {
+                self.rack(next, done.dry_at.max(next.completed_at));
+            }
+            dried.push(done);
+        }
+        dried
+    }
+
+    pub fn drying(&self) -> &[RackedArtwork] {
+        &self.drying
+    }
+
+    pub fn stats(&self) -> DryingStats {
+        DryingStats {
+            capacity: self.capacity,
+            occupied: self.drying.len(),
+            queued: self.waiting.len(),
+            max_queued: self.max_queued,
+            racked_total: self.racked_total,
+            avg_wait_for_rack: if self.racked_total == 0 {
+                Duration::zero()
+            } else {
+                self.total_wait / self.racked_total as i32
+            },
+        }
+    }
+
+    fn rack(&mut self, waiting: WaitingArtwork, at: DateTime<Utc>) -> RackedArtwork {
+        let racked = RackedArtwork {
+            artwork_id: waiting.artwork_id,
+            artist_id: waiting.artist_id,
+            racked_at: at,
+            dry_at: at + waiting.drying_time,
+        };
+        self.drying.push(racked);
+        self.racked_total += 1;
+        self.total_wait += at - waiting.completed_at;
+        racked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_full_racks_queue_completed_canvases() {
+        let start = Utc::now();
+        let mut racks = DryingRacks::new(1);
+        let hours = Duration::hours;
+        assert_eq!(
+            racks.complete(1, 10, hours(4), start),
+            RackOutcome::Racked {
+                dry_at: start + hours(4)
+            }
+        );
+        assert_eq!(
+            racks.complete(2, 11, hours(2), start + hours(1)),
+            RackOutcome::Queued { position: 1 }
+        );
+        assert!(racks.advance(start + hours(3)).is_empty());
+        assert_eq!(racks.stats().queued, 1);
+
+        // The second canvas got the rack at hour 4 and was dry at hour 6.
+        let dried = racks.advance(start + hours(7));
+        let ids: Vec<_> = dried.iter().map(|artwork| artwork.artwork_id).collect();
+        assert_eq!(ids, vec![1, 2]);
+        assert_eq!(dried[1].racked_at, start + hours(4));
+
+        let stats = racks.stats();
+        assert_eq!((stats.occupied, stats.queued, stats.max_queued), (0, 0, 1));
+        assert_eq!(stats.racked_total, 2);
+        assert_eq!(stats.avg_wait_for_rack, Duration::minutes(90));
+    }
+}
@@ -0,0 +1,227 @@
+use crate::{
+    crdt::CrdtInventory,
+    loan_caps::QueuedCheckout,
+    reservations::Reservations,
+    serials::{Item, SerialBook},
+    units::{Count, Kilograms},
+    ArtistToolPreferences, ArtistToolRegistry, SharedResources, State,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DumpEntry {
+    pub artist_id: usize,
+    pub tools: Vec<String>,
+    pub datetime: Option<DateTime<Utc>>,
+    pub state: Option<State>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<State>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paints: Vec<(String, Kilograms)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub serials: Vec<usize>,
+}
+
+// Point-in-time copy of inventory and registry history, for persisting or
+// inspecting a live instance.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateDump {
+    pub captured_at: DateTime<Utc>,
+    pub tools: Vec<(String, Count)>,
+    pub paints: Vec<(String, Kilograms)>,
+    pub on_loan: Vec<(String, usize)>,
+    pub queued: Vec<QueuedCheckout>,
+    pub entries: Vec<DumpEntry>,
+    // Undone entries, each with the entry that reversed it, by index.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub undone: Vec<(usize, usize)>,
+    // Set on a branch forked with `sync --fork`, for merging it back.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<CrdtInventory>,
+    // Every numbered unit. Older dumps without it are numbered again from
+    // their history.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub units: Vec<Item>,
+    // Open bookings. Dumps from before they were kept have none, though
+    // their history still shows them; see `lost_reservations`.
+    #[serde(default)]
+    pub reservations: Reservations,
+}
+
+impl StateDump {
+    pub fn capture(registry: &ArtistToolRegistry) -> Self {
+        let resources = registry
+            .shared_resources
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let tools = resources.tools.amounts();
+        let paints = resources.paints.amounts();
+        let on_loan = resources.loan_caps.loans();
+        let queued = resources.loan_caps.queued().cloned().collect();
+        drop(resources);
+
+        let entries = registry
+            .artist_tool_preferences
+            .iter()
+            .map(|preferences| DumpEntry {
+                artist_id: preferences.artist_id,
+                tools: preferences
+                    .preferred_tools
+                    .iter()
+                    .filter_map(|&symbol| registry.interner.resolve(symbol))
+                    .map(str::to_string)
+                    .collect(),
+                datetime: preferences.datetime,
+                state: preferences.state,
+                from: preferences.from,
+                paints: preferences
+                    .paints
+                    .iter()
+                    .filter_map(|&(symbol, kg)| {
+                        Some((registry.interner.resolve(symbol)?.to_string(), kg))
+                    })
+                    .collect(),
+                due: preferences.due,
+                serials: preferences.serials.clone(),
+            })
+            .collect();
+        Self {
+            captured_at: Utc::now(),
+            tools,
+            paints,
+            on_loan,
+            queued,
+            entries,
+            undone: registry.undone.clone(),
+            branch: registry.branch.clone(),
+            units: registry.items(),
+            reservations: registry.reservations.clone(),
+        }
+    }
+
+    // Puts inventory back into `resources` and rebuilds the registry history.
+    // Loan caps, deposits, the ledger and repair tickets aren't part of the dump.
+    pub fn restore(&self, resources: &Arc<Mutex<SharedResources>>) -> ArtistToolRegistry {
+        {
+            let mut resources = resources
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            resources.tools = self.tools.iter().cloned().collect();
+            resources.paints = self.paints.iter().cloned().collect();
+            for (tool, count) in &self.on_loan {
+                resources.loan_caps.set_on_loan(tool, *count);
+            }
+            for queued in &self.queued {
+                resources.loan_caps.enqueue(queued.clone());
+            }
+        }
+        let mut registry = ArtistToolRegistry::new(resources);
+        for entry in &self.entries {
+            let preferred_tools = entry
+                .tools
+                .iter()
+                .map(|tool| registry.interner.intern(tool))
+                .collect();
+            registry
+                .artist_tool_preferences
+                .push(ArtistToolPreferences {
+                    artist_id: entry.artist_id,
+                    preferred_tools,
+                    datetime: entry.datetime,
+                    // Dumps from before paint draws had a state of their own
+                    // recorded them as the only `Fill` entries.
+                    state: match entry.state {
+                        Some(State::Fill) => Some(State::Paint),
+                        state => state,
+                    },
+                    from: entry.from,
+                    paints: entry
+                        .paints
+                        .iter()
+                        .map(|(color, kg)| (registry.interner.intern(color), *kg))
+                        .collect(),
+                    due: entry.due,
+                    serials: entry.serials.clone(),
+                });
+        }
+        registry.units = if self.units.is_empty() {
+            let interner = &registry.interner;
+            SerialBook::replay(&mut registry.artist_tool_preferences, |entry| {
+                entry
+                    .preferred_tools
+                    .iter()
+                    .filter_map(|&symbol| interner.resolve(symbol))
+                    .map(str::to_string)
+                    .collect()
+            })
+        } else {
+            SerialBook::from_items(self.units.clone())
+        };
+        registry.undone = self.undone.clone();
+        registry.branch = self.branch.clone();
+        registry.reservations = self.reservations.clone();
+        registry
+    }
+
+    // Bookings the history still shows open that the dump holds no
+    // reservation for, so restoring it can't honour them.
+    pub fn lost_reservations(&self) -> usize {
+        let history = |matches: fn(&DumpEntry) -> bool| {
+            self.entries.iter().filter(|entry| matches(entry)).count()
+        };
+        let booked = history(|entry| entry.state == Some(State::Reserved));
+        let closed = history(|entry| entry.from == Some(State::Reserved));
+        booked
+            .saturating_sub(closed)
+            .saturating_sub(self.reservations.open().len())
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capture_resolves_tool_names() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        registry
+            .tool_registry(4, vec!["canvas".to_string()])
+            .unwrap();
+
+        let dump = StateDump::capture(&registry);
+        assert_eq!(dump.entries[0].tools, vec!["canvas"]);
+        assert_eq!(dump.tools[2], ("canvas".to_string(), Count(9)));
+
+        let json: serde_json::Value = serde_json::from_str(&dump.to_json().unwrap()).unwrap();
+        assert_eq!(json["entries"][0]["state"], "TakeOut");
+    }
+
+    #[test]
+    fn test_restore_round_trips_state() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        registry
+            .tool_registry(4, vec!["canvas".to_string(), "tape".to_string()])
+            .unwrap();
+        let dump = StateDump::capture(&registry);
+
+        let json = dump.to_json().unwrap();
+        let parsed: StateDump = serde_json::from_str(&json).unwrap();
+        let fresh = Arc::new(Mutex::new(SharedResources::default()));
+        let restored = parsed.restore(&fresh);
+
+        let again = StateDump::capture(&restored);
+        assert_eq!(again.tools, dump.tools);
+        assert_eq!(again.on_loan, dump.on_loan);
+        assert_eq!(again.entries, dump.entries);
+    }
+}
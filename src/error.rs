@@ -0,0 +1,303 @@
+use crate::{
+    budgets::BudgetExceeded,
+    money::Currency,
+    tool_limits::ToolCountError,
+    units::{Amount, Count, Kilograms},
+    State,
+};
+use chrono::Duration;
+use std::{fmt, sync::PoisonError, time};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UnavailableTools {
+    // Not stocked, or fewer units left than requested.
+    pub missing: Vec<String>,
+    // In stock but at their loan cap.
+    pub capped: Vec<String>,
+}
+
+impl fmt::Display for UnavailableTools {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = vec![];
+        if !self.missing.is_empty() {
+            parts.push(format!("not available: {}", self.missing.join(", ")));
+        }
+        if !self.capped.is_empty() {
+            parts.push(format!("loan cap reached: {}", self.capped.join(", ")));
+        }
+        write!(f, "{}", parts.join("; "))
+    }
+}
+
+// Why the shared stock couldn't serve a request. Nothing is taken when one
+// of these is returned.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResourceError {
+    // Never stocked, or every unit has been written off.
+    ToolNotFound(String),
+    // Stocked, but every unit left is out on loan.
+    OutOfStock(String),
+    // A tool name that, spacing and case aside, fits more than one tool.
+    AmbiguousName {
+        name: String,
+        candidates: Vec<String>,
+    },
+    Unavailable(UnavailableTools),
+    UnknownPaint(String),
+    PaintUnderStock {
+        color: String,
+        requested_kg: Kilograms,
+        available_kg: Kilograms,
+    },
+    // Kilograms of a tool, or a count of paint.
+    WrongUnit {
+        item: String,
+        quantity: Amount,
+    },
+    // A delivery that would leave more of an item than the storeroom holds.
+    OverCapacity {
+        item: String,
+        holding: Amount,
+        delivered: Amount,
+        capacity: Amount,
+    },
+    // A paint mix that adds up to nothing.
+    EmptyMix,
+    // A paint whose color isn't known, so it can't be mixed.
+    NoColorValue(String),
+    // Taking more units off the shelf than it holds, or giving back more
+    // than are out on loan: a bookkeeping bug rather than a short stock.
+    Miscounted {
+        tool: String,
+        counted: Count,
+        removed: Count,
+        on_loan: bool,
+    },
+}
+
+impl fmt::Display for ResourceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResourceError::ToolNotFound(tool) => write!(f, "no tool called '{}'", tool),
+            ResourceError::OutOfStock(tool) => write!(f, "every '{}' is out on loan", tool),
+            ResourceError::AmbiguousName { name, candidates } => {
+                write!(f, "'{}' could be any of {}", name, candidates.join(", "))
+            }
+            ResourceError::Unavailable(tools) => write!(f, "{}", tools),
+            ResourceError::UnknownPaint(color) => write!(f, "no paint called '{}'", color),
+            ResourceError::PaintUnderStock {
+                color,
+                requested_kg,
+                available_kg,
+            } => write!(
+                f,
+                "only {} kg of {} left, {} kg requested",
+                available_kg, color, requested_kg
+            ),
+            ResourceError::WrongUnit { item, quantity } => write!(
+                f,
+                "'{}' isn't measured in {}, so {} of it makes no sense",
+                item,
+                quantity.unit(),
+                quantity
+            ),
+            ResourceError::OverCapacity {
+                item,
+                holding,
+                delivered,
+                capacity,
+            } => write!(
+                f,
+                "{} more '{}' would exceed its storage capacity of {}, {} already held",
+                delivered, item, capacity, holding
+            ),
+            ResourceError::EmptyMix => write!(f, "a mix needs some paint in it"),
+            ResourceError::NoColorValue(color) => {
+                write!(f, "no color value known for '{}'", color)
+            }
+            ResourceError::Miscounted {
+                tool,
+                counted,
+                removed,
+                on_loan,
+            } => write!(
+                f,
+                "{} '{}' counted {}, so {} can't be taken away",
+                counted,
+                tool,
+                if *on_loan { "on loan" } else { "on the shelf" },
+                removed
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegistryError {
+    Resource(ResourceError),
+    // Another thread panicked while holding the shared resources.
+    LockPoisoned,
+    RateLimited {
+        artist_id: usize,
+        retry_after: Duration,
+    },
+    ToolCount(ToolCountError),
+    // The checkout would leave the artist holding more than they may.
+    HoldingCap {
+        artist_id: usize,
+        holding: usize,
+        requested: usize,
+        cap: usize,
+    },
+    // Tools in a return the artist doesn't hold, one per missing unit.
+    NotHeld {
+        artist_id: usize,
+        tools: Vec<String>,
+    },
+    // The artist has no checkout or return left to undo.
+    NothingToUndo(usize),
+    InvalidStateTransition {
+        from: State,
+        to: State,
+    },
+    // Enough units are in stock, but some are booked for another artist.
+    ReservedForOthers(String),
+    // Fewer units on the shelf than a retirement or sale asked for.
+    NotOnShelf {
+        tool: String,
+        on_shelf: usize,
+        requested: usize,
+        on_loan: usize,
+    },
+    UnknownReservation(usize),
+    ReservationExpired(usize),
+    UnknownRepair(usize),
+    UnknownArtwork(usize),
+    // The artwork can't be sold until it has a price.
+    Unpriced(usize),
+    AlreadySold(usize),
+    // The artwork is still on, or waiting for, a drying rack.
+    StillDrying(usize),
+    // A price in a currency the ledger has no exchange rate for.
+    UnknownCurrency(Currency),
+    // Two amounts in different currencies summed without converting one.
+    MixedCurrencies(Currency, Currency),
+    // A reservation window that ends before it starts.
+    EmptyWindow,
+    // A blocking checkout gave up waiting for returns.
+    Timeout {
+        artist_id: usize,
+        waited: time::Duration,
+    },
+    // A restock or repair would take its department past its monthly limit.
+    OverBudget(BudgetExceeded),
+}
+
+impl RegistryError {
+    // Refused for want of units rather than for breaking a rule: everything
+    // out, or booked for someone else.
+    pub fn is_shortage(&self) -> bool {
+        matches!(
+            self,
+            RegistryError::Resource(ResourceError::OutOfStock(_) | ResourceError::Unavailable(_))
+                | RegistryError::ReservedForOthers(_)
+        )
+    }
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegistryError::Resource(error) => write!(f, "{}", error),
+            RegistryError::LockPoisoned => write!(f, "shared resources are poisoned"),
+            RegistryError::RateLimited { retry_after, .. } => write!(
+                f,
+                "rate limited, retry in {}s",
+                retry_after.num_seconds().max(1)
+            ),
+            RegistryError::ToolCount(error) => write!(f, "{}", error),
+            RegistryError::HoldingCap {
+                artist_id,
+                holding,
+                requested,
+                cap,
+            } => write!(
+                f,
+                "artist {} already holds {} tools, {} more would exceed the limit of {}",
+                artist_id, holding, requested, cap
+            ),
+            RegistryError::NotHeld { artist_id, tools } => write!(
+                f,
+                "artist {} did not check out {}",
+                artist_id,
+                tools.join(", ")
+            ),
+            RegistryError::NothingToUndo(id) => {
+                write!(f, "artist {} has nothing left to undo", id)
+            }
+            RegistryError::InvalidStateTransition { from, to } => {
+                write!(f, "a {:?} tool can't become {:?}", from, to)
+            }
+            RegistryError::ReservedForOthers(tool) => {
+                write!(f, "'{}' is reserved for another artist", tool)
+            }
+            RegistryError::UnknownReservation(id) => write!(f, "no open reservation {}", id),
+            RegistryError::ReservationExpired(id) => write!(f, "reservation {} has expired", id),
+            RegistryError::NotOnShelf {
+                tool,
+                on_shelf,
+                requested,
+                on_loan,
+            } => write!(
+                f,
+                "only {} '{}' on the shelf, {} requested ({} checked out)",
+                on_shelf, tool, requested, on_loan
+            ),
+            RegistryError::UnknownRepair(ticket) => write!(f, "no open repair ticket {}", ticket),
+            RegistryError::UnknownArtwork(id) => write!(f, "no artwork {} in the gallery", id),
+            RegistryError::StillDrying(id) => write!(f, "artwork {} is still drying", id),
+            RegistryError::Unpriced(id) => write!(f, "artwork {} has no price", id),
+            RegistryError::AlreadySold(id) => write!(f, "artwork {} is already sold", id),
+            RegistryError::UnknownCurrency(currency) => {
+                write!(f, "no exchange rate for {}", currency)
+            }
+            RegistryError::MixedCurrencies(left, right) => {
+                write!(f, "can't add {} to {} without converting", right, left)
+            }
+            RegistryError::EmptyWindow => write!(f, "a reservation must end after it starts"),
+            RegistryError::OverBudget(exceeded) => write!(
+                f,
+                "{} would spend {} this month, over its budget of {}",
+                exceeded.department, exceeded.would_spend, exceeded.limit
+            ),
+            RegistryError::Timeout { artist_id, waited } => write!(
+                f,
+                "artist {} gave up after waiting {} ms",
+                artist_id,
+                waited.as_millis()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ResourceError {}
+
+impl std::error::Error for RegistryError {}
+
+impl From<ResourceError> for RegistryError {
+    fn from(error: ResourceError) -> Self {
+        RegistryError::Resource(error)
+    }
+}
+
+impl From<ToolCountError> for RegistryError {
+    fn from(error: ToolCountError) -> Self {
+        RegistryError::ToolCount(error)
+    }
+}
+
+impl<T> From<PoisonError<T>> for RegistryError {
+    fn from(_: PoisonError<T>) -> Self {
+        RegistryError::LockPoisoned
+    }
+}
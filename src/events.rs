@@ -0,0 +1,556 @@
+use crate::{
+    error::ResourceError,
+    interner::{Interner, Symbol},
+    segment_log::{SegmentedLog, Timestamped},
+    units::{Amount, Kilograms, Quantity},
+    SharedResources, State,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, io, path::Path, sync::mpsc};
+
+// Saved logs are split into compressed segments of about this size.
+const SEGMENT_BYTES: u64 = 1 << 20;
+
+// One change to the inventory, with its items by name, as it is recorded,
+// saved and streamed to subscribers. Restocks are logged as `New`, supplier
+// deliveries as `Fill`, units the studio retires or sells from the shelf as
+// `Retire` or `Sold`, and expired paint as `Expired`, all without an artist;
+// paint an artist draws is `Paint`, and everything else uses the state the
+// items moved to. Artworks sold from the
+// gallery are `Sold` with the artist who made them, and don't move stock.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventRecord {
+    pub at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artist_id: Option<usize>,
+    pub kind: State,
+    // The state the units moved out of, for history entries.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<State>,
+    pub items: Vec<String>,
+    // Shelf stock of each distinct item once the change was applied.
+    pub stock: BTreeMap<String, Amount>,
+    // Units restocked, delivered or taken off the shelf, or kilograms of
+    // paint used; for every other kind each listed item is one unit.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub quantities: BTreeMap<String, Amount>,
+    // Serial numbers of the units of each tool that moved.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub serials: BTreeMap<String, Vec<usize>>,
+}
+
+impl EventRecord {
+    pub fn new(
+        at: DateTime<Utc>,
+        artist_id: Option<usize>,
+        kind: State,
+        items: Vec<String>,
+        resources: &SharedResources,
+    ) -> Self {
+        let stock = items
+            .iter()
+            .map(|item| (item.clone(), resources.amount_of(item)))
+            .collect();
+        Self {
+            at,
+            artist_id,
+            kind,
+            from: None,
+            items,
+            stock,
+            quantities: BTreeMap::new(),
+            serials: BTreeMap::new(),
+        }
+    }
+}
+
+impl Timestamped for EventRecord {
+    fn at(&self) -> DateTime<Utc> {
+        self.at
+    }
+}
+
+// An event as the log keeps it: the same as its record, with every item
+// name interned into the log's symbol table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InventoryEvent {
+    pub at: DateTime<Utc>,
+    pub artist_id: Option<usize>,
+    pub kind: State,
+    pub from: Option<State>,
+    pub items: Vec<Symbol>,
+    pub stock: BTreeMap<Symbol, Amount>,
+    pub quantities: BTreeMap<Symbol, Amount>,
+    pub serials: BTreeMap<Symbol, Vec<usize>>,
+}
+
+// Append-only record of every inventory mutation made through the registry.
+// Subscribers get a copy of each event as it is appended.
+#[derive(Debug, Default)]
+pub struct EventLog {
+    events: Vec<InventoryEvent>,
+    names: Interner,
+    subscribers: Vec<mpsc::Sender<EventRecord>>,
+}
+
+impl EventLog {
+    pub fn append(&mut self, record: EventRecord) {
+        self.publish(&record);
+        let event = self.intern(record);
+        self.events.push(event);
+    }
+
+    fn intern(&mut self, record: EventRecord) -> InventoryEvent {
+        let names = &mut self.names;
+        let mut keyed = |map: BTreeMap<String, Amount>| {
+            map.into_iter()
+                .map(|(item, amount)| (names.intern(&item), amount))
+                .collect()
+        };
+        let stock = keyed(record.stock);
+        let quantities = keyed(record.quantities);
+        InventoryEvent {
+            at: record.at,
+            artist_id: record.artist_id,
+            kind: record.kind,
+            from: record.from,
+            items: record.items.iter().map(|item| names.intern(item)).collect(),
+            stock,
+            quantities,
+            serials: record
+                .serials
+                .into_iter()
+                .map(|(tool, serials)| (names.intern(&tool), serials))
+                .collect(),
+        }
+    }
+
+    // The event with its item names spelled out again.
+    pub fn record(&self, event: &InventoryEvent) -> EventRecord {
+        let named = |&symbol: &Symbol| self.names.resolve(symbol).map(str::to_string);
+        let keyed = |map: &BTreeMap<Symbol, Amount>| {
+            map.iter()
+                .filter_map(|(symbol, amount)| Some((named(symbol)?, *amount)))
+                .collect()
+        };
+        EventRecord {
+            at: event.at,
+            artist_id: event.artist_id,
+            kind: event.kind,
+            from: event.from,
+            items: event.items.iter().filter_map(named).collect(),
+            stock: keyed(&event.stock),
+            quantities: keyed(&event.quantities),
+            serials: event
+                .serials
+                .iter()
+                .filter_map(|(symbol, serials)| Some((named(symbol)?, serials.clone())))
+                .collect(),
+        }
+    }
+
+    // Every event by name, oldest first.
+    pub fn records(&self) -> impl DoubleEndedIterator<Item = EventRecord> + '_ {
+        self.events.iter().map(|event| self.record(event))
+    }
+
+    pub fn last(&self) -> Option<EventRecord> {
+        self.events.last().map(|event| self.record(event))
+    }
+
+    // The symbol `name` is logged under; None if no event mentions it.
+    pub fn symbol(&self, name: &str) -> Option<Symbol> {
+        self.names.get(name)
+    }
+
+    pub fn name(&self, symbol: Symbol) -> Option<&str> {
+        self.names.resolve(symbol)
+    }
+
+    // Streams every event appended from now on. Dropping the receiver
+    // unsubscribes.
+    pub fn subscribe(&mut self) -> mpsc::Receiver<EventRecord> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    fn publish(&mut self, record: &EventRecord) {
+        self.subscribers
+            .retain(|subscriber| subscriber.send(record.clone()).is_ok());
+    }
+
+    pub fn events(&self) -> &[InventoryEvent] {
+        &self.events
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    // Drops every event after the first `len`. Subscribers aren't told.
+    pub(crate) fn truncate(&mut self, len: usize) {
+        self.events.truncate(len);
+    }
+
+    // Events at or after `from` and before `until`.
+    pub fn between(
+        &self,
+        from: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> impl Iterator<Item = &InventoryEvent> {
+        self.events
+            .iter()
+            .filter(move |event| from <= event.at && event.at < until)
+    }
+
+    pub fn for_artist(&self, artist_id: usize) -> impl Iterator<Item = &InventoryEvent> {
+        self.events
+            .iter()
+            .filter(move |event| event.artist_id == Some(artist_id))
+    }
+
+    // One JSON object per line, oldest first.
+    pub fn to_json_lines(&self) -> serde_json::Result<String> {
+        let mut lines = String::new();
+        for record in self.records() {
+            lines.push_str(&serde_json::to_string(&record)?);
+            lines.push('\n');
+        }
+        Ok(lines)
+    }
+
+    pub fn from_json_lines(lines: &str) -> serde_json::Result<Self> {
+        let records = lines
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<serde_json::Result<Vec<EventRecord>>>()?;
+        Ok(Self::from_records(records))
+    }
+
+    fn from_records(records: Vec<EventRecord>) -> Self {
+        let mut log = Self::default();
+        for record in records {
+            let event = log.intern(record);
+            log.events.push(event);
+        }
+        log
+    }
+
+    // Writes the log to the directory `path` as compressed segments indexed
+    // by time, replacing any log saved there before.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut log = SegmentedLog::create(path.as_ref(), SEGMENT_BYTES)?;
+        self.records().try_for_each(|record| log.append(&record))
+    }
+
+    // Reads a log written by `save`, or an older single JSON-lines file.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        if !path.is_dir() {
+            return Ok(Self::from_json_lines(&fs::read_to_string(path)?)?);
+        }
+        let log = SegmentedLog::open(path, SEGMENT_BYTES)?;
+        Ok(Self::from_records(log.all()?))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StockMismatch {
+    pub item: String,
+    pub recorded: Amount,
+    pub replayed: Amount,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventReplay {
+    pub applied: usize,
+    // Events the replayed stock couldn't serve, by position in the log.
+    pub refused: Vec<(usize, ResourceError)>,
+    // Items whose replayed stock differs from the last recorded one.
+    pub mismatches: Vec<StockMismatch>,
+}
+
+impl EventReplay {
+    pub fn matches(&self) -> bool {
+        self.refused.is_empty() && self.mismatches.is_empty()
+    }
+}
+
+// Re-applies a recorded log to `resources`, which should hold the stock the
+// recorded run started from, then compares every item's stock against the
+// last value the log recorded for it.
+pub fn replay(log: &EventLog, resources: &mut SharedResources) -> EventReplay {
+    let mut report = EventReplay::default();
+    let mut recorded: BTreeMap<String, Amount> = BTreeMap::new();
+    for (index, event) in log.records().enumerate() {
+        let applied = match event.kind {
+            State::TakeOut => resources.take_out_all(&event.items),
+            State::Return => {
+                // A found unit was written off when it was lost, so it counts
+                // as on loan again until it is back on the shelf.
+                if event.from == Some(State::Lost) {
+                    for tool in &event.items {
+                        let on_loan = resources.loan_caps.on_loan(tool);
+                        resources.loan_caps.set_on_loan(tool, on_loan + 1);
+                    }
+                }
+                resources.return_resources(&event.items, event.at).map(drop)
+            }
+            State::New => event
+                .quantities
+                .iter()
+                .try_for_each(|(item, quantity)| resources.restock(item, *quantity)),
+            State::Fill | State::TransferIn => {
+                let delivered: Vec<(String, Amount)> =
+                    event.quantities.clone().into_iter().collect();
+                resources.receive(&delivered)
+            }
+            State::Paint => paint_amounts(&event).and_then(|paints| resources.take_paints(&paints)),
+            State::Expired if event.artist_id.is_none() => paint_amounts(&event).map(|paints| {
+                for (color, kg) in paints {
+                    if let Some(paint) = resources.paints.get_mut(&color) {
+                        paint.weight_kg = paint.weight_kg.saturating_sub(kg);
+                    }
+                }
+            }),
+            State::Retire | State::Sold | State::TransferOut if event.artist_id.is_none() => {
+                event.quantities.iter().try_for_each(|(tool, quantity)| {
+                    let count = quantity.count().ok_or_else(|| ResourceError::WrongUnit {
+                        item: tool.clone(),
+                        quantity: *quantity,
+                    })?;
+                    let on_shelf = resources.stock(tool);
+                    resources.set_quantity(tool, on_shelf.saturating_sub(count))
+                })
+            }
+            // Lost units were released when they were reported.
+            State::Retire if event.from == Some(State::Lost) => Ok(()),
+            State::Retire | State::Lost => {
+                for tool in &event.items {
+                    let on_loan = resources.loan_caps.on_loan(tool);
+                    resources
+                        .loan_caps
+                        .set_on_loan(tool, on_loan.saturating_sub(1));
+                }
+                Ok(())
+            }
+            // Damage, repairs, reservations, audits and overdue notices don't
+            // move stock.
+            _ => Ok(()),
+        };
+        match applied {
+            Ok(()) => report.applied += 1,
+            Err(error) => report.refused.push((index, error)),
+        }
+        recorded.extend(event.stock);
+    }
+    for (item, recorded) in recorded {
+        let replayed = resources.amount_of(&item);
+        if replayed != recorded {
+            report.mismatches.push(StockMismatch {
+                item,
+                recorded,
+                replayed,
+            });
+        }
+    }
+    report
+}
+
+// The kilograms of each color an event used or expired.
+fn paint_amounts(event: &EventRecord) -> Result<Vec<(String, Kilograms)>, ResourceError> {
+    event
+        .quantities
+        .iter()
+        .map(|(color, quantity)| {
+            let kg = quantity
+                .kilograms()
+                .ok_or_else(|| ResourceError::WrongUnit {
+                    item: color.clone(),
+                    quantity: *quantity,
+                })?;
+            Ok((color.clone(), kg))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        deliveries::Delivery,
+        money::{Currency, Money},
+        resources::TOTAL_ITEMS,
+        simulation::{self, SimulationConfig},
+        units::Count,
+        ArtistToolRegistry,
+    };
+    use chrono::Duration;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_registry_logs_every_mutation() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        let started = Utc::now();
+        registry
+            .tool_registry(1, vec!["brush".to_string(), "tape".to_string()])
+            .unwrap();
+        registry.report_damage(1, "brush").unwrap();
+        registry.restock("brush", Count(2)).unwrap();
+        registry.tool_return(1, vec!["tape".to_string()]).unwrap();
+        registry
+            .paint_checkout(2, vec![("red".to_string(), Kilograms::whole(3))])
+            .unwrap();
+
+        let kinds: Vec<State> = registry.events.events().iter().map(|e| e.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                State::TakeOut,
+                State::Damage,
+                State::New,
+                State::Return,
+                State::Paint
+            ]
+        );
+        // The log keeps names once and events by symbol.
+        let brush = registry.events.symbol("brush").unwrap();
+        assert_eq!(registry.events.events()[1].items, vec![brush]);
+        let records: Vec<EventRecord> = registry.events.records().collect();
+        let restock = &records[2];
+        assert_eq!(restock.artist_id, None);
+        assert_eq!(restock.stock["brush"], (TOTAL_ITEMS + Count(1)).into());
+        assert_eq!(records[4].stock["red"], Kilograms::whole(7).into());
+
+        assert_eq!(registry.events.for_artist(1).count(), 3);
+        let later = Utc::now() + Duration::seconds(1);
+        assert_eq!(registry.events.between(started, later).count(), 5);
+        assert_eq!(registry.events.between(later, later).count(), 0);
+
+        let lines = registry.events.to_json_lines().unwrap();
+        assert_eq!(lines.lines().count(), 5);
+        let first: EventRecord = serde_json::from_str(lines.lines().next().unwrap()).unwrap();
+        assert_eq!(first, records[0]);
+    }
+
+    #[test]
+    fn test_replay_reproduces_a_recorded_run() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let registry = Arc::new(Mutex::new(ArtistToolRegistry::new(&resources)));
+        let config = SimulationConfig {
+            artists: 4,
+            rounds: 3,
+            ..SimulationConfig::default()
+        };
+        simulation::run_artists(&resources, &registry, &config);
+        let mut registry = registry.lock().unwrap();
+        registry.restock("kiln", Count(2)).unwrap();
+        registry
+            .receive_delivery(Delivery {
+                supplier: "Pigment House".to_string(),
+                items: vec![
+                    ("blue".to_string(), Kilograms::grams(4_500).into()),
+                    ("tape".to_string(), Count(2).into()),
+                ],
+                cost: Money::new(4_000, Currency::USD),
+            })
+            .unwrap();
+        registry
+            .paint_checkout(1, vec![("blue".to_string(), Kilograms::grams(4_250))])
+            .unwrap();
+        let dir = std::env::temp_dir().join(format!("rustic-canvas-events-{}", std::process::id()));
+        registry.events.save(&dir).unwrap();
+        let log = EventLog::load(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(log.records().eq(registry.events.records()));
+
+        let report = replay(&log, &mut SharedResources::default());
+        assert!(report.matches(), "{:?}", report);
+        assert_eq!(report.applied, log.len());
+
+        // Starting from different stock shows up as a mismatch.
+        let mut fewer = SharedResources::default();
+        fewer.set_quantity("kiln", Count(1)).unwrap();
+        let report = replay(&log, &mut fewer);
+        assert_eq!(
+            report.mismatches,
+            vec![StockMismatch {
+                item: "kiln".to_string(),
+                recorded: Count(2).into(),
+                replayed: Count(3).into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_replay_releases_a_lost_unit_once() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        for id in 1..=3 {
+            registry
+                .tool_registry(id, vec!["brush".to_string()])
+                .unwrap();
+        }
+        registry.report_lost(1, "brush").unwrap();
+        registry.retire(1, "brush").unwrap();
+        registry.report_lost(2, "brush").unwrap();
+        registry.recover_lost(2, "brush").unwrap();
+        assert_eq!(registry.events.events()[4].from, Some(State::Lost));
+
+        let mut replayed = SharedResources::default();
+        let report = replay(&registry.events, &mut replayed);
+        assert!(report.matches(), "{:?}", report);
+        assert_eq!(replayed.loan_caps.on_loan("brush"), 1);
+        assert_eq!(
+            replayed.loan_caps.on_loan("brush"),
+            resources.lock().unwrap().loan_caps.on_loan("brush")
+        );
+    }
+
+    #[test]
+    fn test_replay_matches_live_stock_after_a_loss_and_a_retirement() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        registry
+            .tool_registry(1, vec!["brush".to_string(), "tape".to_string()])
+            .unwrap();
+        registry
+            .tool_registry(2, vec!["brush".to_string()])
+            .unwrap();
+        registry.report_lost(1, "brush").unwrap();
+        registry.return_damaged(2, "brush").unwrap();
+        registry.retire(2, "brush").unwrap();
+        registry.tool_return(1, vec!["tape".to_string()]).unwrap();
+
+        let mut replayed = SharedResources::default();
+        let report = replay(&registry.events, &mut replayed);
+        assert!(report.matches(), "{:?}", report);
+        assert_eq!(replayed, *resources.lock().unwrap());
+    }
+
+    #[test]
+    fn test_subscribers_see_new_events_until_they_hang_up() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        registry.restock("brush", Count(1)).unwrap();
+        let live = registry.events.subscribe();
+        let gone = registry.events.subscribe();
+        drop(gone);
+
+        registry
+            .tool_registry(1, vec!["brush".to_string(), "tape".to_string()])
+            .unwrap();
+        let event = live.try_recv().unwrap();
+        assert_eq!((event.kind, event.artist_id), (State::TakeOut, Some(1)));
+        assert!(live.try_recv().is_err());
+        assert_eq!(registry.events.subscribers.len(), 1);
+    }
+}
@@ -0,0 +1,370 @@
+use crate::{inventory::ShardedInventory, units::Count};
+use rand::{rngs::StdRng, seq::index, Rng, SeedableRng};
+use std::{
+    sync::{mpsc, Arc, Mutex, RwLock},
+    thread,
+    time::{Duration, Instant},
+};
+
+// The same checkout/return workload run against each synchronization
+// strategy, so their throughput and latency can be compared side by side.
+pub trait StockStrategy: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn checkout(&self, tools: &[usize]) -> bool;
+    fn give_back(&self, tools: &[usize]);
+}
+
+pub struct MutexStock(Mutex<Vec<usize>>);
+
+impl MutexStock {
+    pub fn new(tools: usize, quantity: usize) -> Self {
+        Self(Mutex::new(vec![quantity; tools]))
+    }
+}
+
+impl StockStrategy for MutexStock {
+    fn name(&self) -> &'static str {
+        "mutex"
+    }
+
+    fn checkout(&self, tools: &[usize]) -> bool {
+        let mut stock = self.0.lock().expect("Failed to lock stock");
+        take_all(&mut stock, tools)
+    }
+
+    fn give_back(&self, tools: &[usize]) {
+        let mut stock = self.0.lock().expect("Failed to lock stock");
+        for &tool in tools {
+            stock[tool] += 1;
+        }
+    }
+}
+
+pub struct RwLockStock(RwLock<Vec<usize>>);
+
+impl RwLockStock {
+    pub fn new(tools: usize, quantity: usize) -> Self {
+        Self(RwLock::new(vec![quantity; tools]))
+    }
+}
+
+impl StockStrategy for RwLockStock {
+    fn name(&self) -> &'static str {
+        "rwlock"
+    }
+
+    // Checks availability under a shared lock first so failing requests never
+    // take the exclusive lock.
+    fn checkout(&self, tools: &[usize]) -> bool {
+        {
+            let stock = self.0.read().expect("Failed to lock stock");
+            if tools.iter().any(|&tool| stock[tool] == 0) {
+                return false;
+            }
+        }
+        let mut stock = self.0.write().expect("Failed to lock stock");
+        take_all(&mut stock, tools)
+    }
+
+    fn give_back(&self, tools: &[usize]) {
+        let mut stock = self.0.write().expect("Failed to lock stock");
+        for &tool in tools {
+            stock[tool] += 1;
+        }
+    }
+}
+
+pub struct ShardedStock(Vec<Mutex<usize>>);
+
+impl ShardedStock {
+    pub fn new(tools: usize, quantity: usize) -> Self {
+        Self((0..tools).map(|_| Mutex::new(quantity)).collect())
+    }
+}
+
+impl StockStrategy for ShardedStock {
+    fn name(&self) -> &'static str {
+        "sharded"
+    }
+
+    // Locks shards in ascending index order so concurrent checkouts can't deadlock.
+    fn checkout(&self, tools: &[usize]) -> bool {
+        let mut sorted = tools.to_vec();
+        sorted.sort_unstable();
+        let mut guards: Vec<_> = sorted
+            .iter()
+            .map(|&tool| self.0[tool].lock().expect("Failed to lock shard"))
+            .collect();
+        if guards.iter().any(|quantity| **quantity == 0) {
+            return false;
+        }
+        for quantity in guards.iter_mut() {
+            **quantity -= 1;
+        }
+        true
+    }
+
+    fn give_back(&self, tools: &[usize]) {
+        for &tool in tools {
+            *self.0[tool].lock().expect("Failed to lock shard") += 1;
+        }
+    }
+}
+
+// The studio's real per-tool inventory, keyed by name, to show what the
+// string lookups and loan bookkeeping cost on top of bare sharded counters.
+pub struct InventoryStock {
+    inventory: ShardedInventory,
+    names: Vec<String>,
+}
+
+impl InventoryStock {
+    pub fn new(tools: usize, quantity: usize) -> Self {
+        let inventory = ShardedInventory::default();
+        let names: Vec<String> = (0..tools).map(|tool| format!("tool {}", tool)).collect();
+        for name in &names {
+            inventory.restock(name, Count::of(quantity));
+        }
+        Self { inventory, names }
+    }
+
+    fn names(&self, tools: &[usize]) -> Vec<String> {
+        tools.iter().map(|&tool| self.names[tool].clone()).collect()
+    }
+}
+
+impl StockStrategy for InventoryStock {
+    fn name(&self) -> &'static str {
+        "per-tool"
+    }
+
+    fn checkout(&self, tools: &[usize]) -> bool {
+        self.inventory.take_out_all(&self.names(tools)).is_ok()
+    }
+
+    fn give_back(&self, tools: &[usize]) {
+        self.inventory.return_resources(&self.names(tools));
+    }
+}
+
+enum ActorRequest {
+    Checkout(Vec<usize>, mpsc::Sender<bool>),
+    GiveBack(Vec<usize>),
+}
+
+// A single thread owns the stock; callers talk to it over a channel.
+pub struct ActorStock(mpsc::Sender<ActorRequest>);
+
+impl ActorStock {
+    pub fn new(tools: usize, quantity: usize) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut stock = vec![quantity; tools];
+            for request in receiver {
+                match request {
+                    ActorRequest::Checkout(tools, reply) => {
+                        let _ = reply.send(take_all(&mut stock, &tools));
+                    }
+                    ActorRequest::GiveBack(tools) => {
+                        for tool in tools {
+                            stock[tool] += 1;
+                        }
+                    }
+                }
+            }
+        });
+        Self(sender)
+    }
+}
+
+impl StockStrategy for ActorStock {
+    fn name(&self) -> &'static str {
+        "actor"
+    }
+
+    fn checkout(&self, tools: &[usize]) -> bool {
+        let (reply, response) = mpsc::channel();
+        self.0
+            .send(ActorRequest::Checkout(tools.to_vec(), reply))
+            .expect("Inventory actor stopped");
+        response.recv().expect("Inventory actor stopped")
+    }
+
+    fn give_back(&self, tools: &[usize]) {
+        self.0
+            .send(ActorRequest::GiveBack(tools.to_vec()))
+            .expect("Inventory actor stopped");
+    }
+}
+
+fn take_all(stock: &mut [usize], tools: &[usize]) -> bool {
+    if tools.iter().any(|&tool| stock[tool] == 0) {
+        return false;
+    }
+    for &tool in tools {
+        stock[tool] -= 1;
+    }
+    true
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExperimentConfig {
+    pub artists: usize,
+    pub operations_per_artist: usize,
+    pub tools: usize,
+    pub quantity: usize,
+    pub seed: u64,
+}
+
+impl Default for ExperimentConfig {
+    fn default() -> Self {
+        Self {
+            artists: 8,
+            operations_per_artist: 2_000,
+            tools: 10,
+            quantity: 10,
+            seed: 42,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExperimentResult {
+    pub strategy: &'static str,
+    pub operations: usize,
+    pub failed_checkouts: usize,
+    pub elapsed: Duration,
+    pub avg_latency_us: f64,
+    pub p99_latency_us: f64,
+}
+
+impl ExperimentResult {
+    pub fn throughput_per_sec(&self) -> f64 {
+        self.operations as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+// Every artist gets its own seeded list of tool sets to check out, so every
+// strategy sees exactly the same requests.
+pub fn workload(config: &ExperimentConfig) -> Vec<Vec<Vec<usize>>> {
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let max_tools = config.tools.min(crate::registry::MAX_ALLOWED_TOOLS);
+    let min_tools = config.tools.min(crate::registry::MIN_REQUIRED_TOOLS);
+    (0..config.artists)
+        .map(|_| {
+            (0..config.operations_per_artist)
+                .map(|_| {
+                    let count = rng.gen_range(min_tools..=max_tools);
+                    index::sample(&mut rng, config.tools, count).into_vec()
+                })
+                .collect()
+        })
+        .collect()
+}
+
+pub fn run(strategy: Arc<dyn StockStrategy>, workload: &[Vec<Vec<usize>>]) -> ExperimentResult {
+    let started = Instant::now();
+    let handles: Vec<_> = workload
+        .iter()
+        .cloned()
+        .map(|requests| {
+            let strategy = Arc::clone(&strategy);
+            thread::spawn(move || {
+                let mut latencies = Vec::with_capacity(requests.len());
+                let mut failed = 0;
+                for tools in requests {
+                    let op_started = Instant::now();
+                    if strategy.checkout(&tools) {
+                        strategy.give_back(&tools);
+                    } else {
+                        failed += 1;
+                    }
+                    latencies.push(op_started.elapsed().as_secs_f64() * 1_000_000.0);
+                }
+                (latencies, failed)
+            })
+        })
+        .collect();
+
+    let mut latencies = vec![];
+    let mut failed_checkouts = 0;
+    for handle in handles {
+        let (artist_latencies, failed) = handle.join().expect("Thread panicked");
+        latencies.extend(artist_latencies);
+        failed_checkouts += failed;
+    }
+    let elapsed = started.elapsed();
+
+    latencies.sort_by(f64::total_cmp);
+    let operations = latencies.len();
+    let p99_index = ((operations as f64 * 0.99).ceil() as usize).clamp(1, operations.max(1)) - 1;
+    ExperimentResult {
+        strategy: strategy.name(),
+        operations,
+        failed_checkouts,
+        elapsed,
+        avg_latency_us: latencies.iter().sum::<f64>() / operations.max(1) as f64,
+        p99_latency_us: latencies.get(p99_index).copied().unwrap_or(0.0),
+    }
+}
+
+pub fn run_all(config: &ExperimentConfig) -> Vec<ExperimentResult> {
+    let workload = workload(config);
+    let strategies: Vec<Arc<dyn StockStrategy>> = vec![
+        Arc::new(MutexStock::new(config.tools, config.quantity)),
+        Arc::new(RwLockStock::new(config.tools, config.quantity)),
+        Arc::new(ShardedStock::new(config.tools, config.quantity)),
+        Arc::new(InventoryStock::new(config.tools, config.quantity)),
+        Arc::new(ActorStock::new(config.tools, config.quantity)),
+    ];
+    strategies
+        .into_iter()
+        .map(|strategy| run(strategy, &workload))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_workload_is_deterministic_per_seed() {
+        let config = ExperimentConfig {
+            artists: 2,
+            operations_per_artist: 5,
+            ..ExperimentConfig::default()
+        };
+        assert_eq!(workload(&config), workload(&config));
+        let other = ExperimentConfig { seed: 7, ..config };
+        assert_ne!(workload(&config), workload(&other));
+    }
+
+    #[test]
+    fn test_strategies_agree_on_checkout_semantics() {
+        let strategies: Vec<Box<dyn StockStrategy>> = vec![
+            Box::new(MutexStock::new(3, 1)),
+            Box::new(RwLockStock::new(3, 1)),
+            Box::new(ShardedStock::new(3, 1)),
+            Box::new(InventoryStock::new(3, 1)),
+            Box::new(ActorStock::new(3, 1)),
+        ];
+        for strategy in strategies {
+            assert!(strategy.checkout(&[0, 2]), "{}", strategy.name());
+            assert!(!strategy.checkout(&[1, 2]), "{}", strategy.name());
+            strategy.give_back(&[0, 2]);
+            assert!(strategy.checkout(&[1, 2]), "{}", strategy.name());
+        }
+    }
+
+    #[test]
+    fn test_run_all_completes_every_operation() {
+        let config = ExperimentConfig {
+            artists: 3,
+            operations_per_artist: 50,
+            ..ExperimentConfig::default()
+        };
+        let results = run_all(&config);
+        assert_eq!(results.len(), 5);
+        assert!(results.iter().all(|result| result.operations == 150));
+    }
+}
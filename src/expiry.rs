@@ -0,0 +1,120 @@
+use crate::units::{Kilograms, Quantity};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+// How far ahead reports warn about paint that is about to expire.
+pub const EXPIRY_WARNING: Duration = Duration::days(14);
+
+// Kilograms of one color from the same production batch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PaintBatch {
+    pub color: String,
+    pub batch: String,
+    pub kg: Kilograms,
+    pub expires: DateTime<Utc>,
+}
+
+// Which part of each color's stock expires when. Paint drawn from a color
+// comes out of its soonest-expiring batch first; kilograms in no batch
+// never expire.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PaintBatches {
+    batches: Vec<PaintBatch>,
+}
+
+impl PaintBatches {
+    pub fn add(&mut self, batch: PaintBatch) {
+        self.batches.push(batch);
+        self.batches.sort_by_key(|batch| batch.expires);
+    }
+
+    pub fn batches(&self) -> &[PaintBatch] {
+        &self.batches
+    }
+
+    pub fn consume(&mut self, color: &str, mut kg: Kilograms) {
+        for batch in self.batches.iter_mut().filter(|batch| batch.color == color) {
+            let used = batch.kg.min(kg);
+            batch.kg -= used;
+            kg -= used;
+        }
+        self.batches.retain(|batch| !batch.kg.is_zero());
+    }
+
+    // Removes and returns every batch that has expired by `now`.
+    pub fn take_expired(&mut self, now: DateTime<Utc>) -> Vec<PaintBatch> {
+        let (expired, usable) = self
+            .batches
+            .drain(..)
+            .partition(|batch| batch.expires <= now);
+        self.batches = usable;
+        expired
+    }
+
+    // Batches still usable at `now` that expire within `within`, soonest first.
+    pub fn expiring(&self, now: DateTime<Utc>, within: Duration) -> Vec<&PaintBatch> {
+        self.batches
+            .iter()
+            .filter(|batch| batch.expires > now && batch.expires <= now + within)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{resources::TOTAL_WEIGHT_KG, ArtistToolRegistry, SharedResources, State};
+    use std::sync::{Arc, Mutex};
+
+    fn batch(color: &str, name: &str, kg: f64, expires: DateTime<Utc>) -> PaintBatch {
+        PaintBatch {
+            color: color.to_string(),
+            batch: name.to_string(),
+            kg: Kilograms::new(kg).unwrap(),
+            expires,
+        }
+    }
+
+    #[test]
+    fn test_sweep_removes_expired_batches_from_stock() {
+        let now = Utc::now();
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        {
+            let mut resources = resources.lock().unwrap();
+            resources.add_batch(batch("red", "R2", 3.0, now + Duration::days(5)));
+            resources.add_batch(batch("red", "R1", 4.0, now - Duration::days(1)));
+            resources.add_batch(batch("blue", "B1", 2.0, now + Duration::days(60)));
+        }
+        let mut registry = ArtistToolRegistry::new(&resources);
+        // Paint comes out of the batch that expires first.
+        registry
+            .paint_checkout(1, vec![("red".to_string(), Kilograms::grams(500))])
+            .unwrap();
+
+        let expired = registry.sweep_expired_paints(99, now).unwrap();
+        assert_eq!(
+            expired,
+            vec![batch("red", "R1", 3.5, now - Duration::days(1))]
+        );
+        {
+            let resources = resources.lock().unwrap();
+            let red = resources.paints.get("red").unwrap();
+            assert_eq!(red.weight_kg, TOTAL_WEIGHT_KG - Kilograms::whole(4));
+            assert_eq!(red.batch.as_deref(), Some("R2"));
+            let expiring = resources.batches.expiring(now, EXPIRY_WARNING);
+            assert_eq!(
+                expiring,
+                vec![&batch("red", "R2", 3.0, now + Duration::days(5))]
+            );
+        }
+        let entry = registry.artist_tool_preferences.last().unwrap();
+        assert_eq!((entry.artist_id, entry.state), (99, Some(State::Expired)));
+        assert_eq!(registry.paint_usage(99), vec![]);
+        let event = registry.events.last().unwrap();
+        assert_eq!(
+            (event.artist_id, event.quantities["red"]),
+            (None, Kilograms::grams(3_500).into())
+        );
+        assert!(registry.sweep_expired_paints(99, now).unwrap().is_empty());
+    }
+}
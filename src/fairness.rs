@@ -0,0 +1,96 @@
+use chrono::{DateTime, Duration, Utc};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ArtistWait {
+    pub artist_id: usize,
+    // Checkouts refused, or left waiting, in a row right now and at worst.
+    pub streak: usize,
+    pub longest_streak: usize,
+    // Longest stretch from the first unanswered request to being served,
+    // counting one still going on.
+    pub longest_wait: Duration,
+    pub waiting_since: Option<DateTime<Utc>>,
+}
+
+// How long each artist has gone without getting what they asked for. A run
+// is fair when every artist's longest wait stays bounded, however popular
+// the tools they want.
+#[derive(Debug, Clone, Default)]
+pub struct Starvation {
+    artists: BTreeMap<usize, ArtistWait>,
+}
+
+impl Starvation {
+    fn artist(&mut self, artist_id: usize) -> &mut ArtistWait {
+        self.artists.entry(artist_id).or_insert_with(|| ArtistWait {
+            artist_id,
+            ..Default::default()
+        })
+    }
+
+    // The artist asked and didn't get everything: refused, or queued.
+    pub fn kept_waiting(&mut self, artist_id: usize, now: DateTime<Utc>) {
+        let artist = self.artist(artist_id);
+        artist.streak += 1;
+        artist.longest_streak = artist.longest_streak.max(artist.streak);
+        artist.waiting_since.get_or_insert(now);
+    }
+
+    pub fn served(&mut self, artist_id: usize, now: DateTime<Utc>) {
+        let artist = self.artist(artist_id);
+        if let Some(since) = artist.waiting_since.take() {
+            artist.longest_wait = artist.longest_wait.max(now - since);
+        }
+        artist.streak = 0;
+    }
+
+    // Every artist who has had to wait, longest wait first, with waits still
+    // going on counted up to `now`.
+    pub fn report(&self, now: DateTime<Utc>) -> Vec<ArtistWait> {
+        let mut waits: Vec<ArtistWait> = self
+            .artists
+            .values()
+            .filter(|artist| artist.longest_streak > 0)
+            .map(|artist| ArtistWait {
+                longest_wait: artist.waiting_since.map_or(artist.longest_wait, |since| {
+                    artist.longest_wait.max(now - since)
+                }),
+                ..*artist
+            })
+            .collect();
+        waits.sort_by(|a, b| {
+            b.longest_wait
+                .cmp(&a.longest_wait)
+                .then_with(|| a.artist_id.cmp(&b.artist_id))
+        });
+        waits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_counts_streaks_and_open_waits() {
+        let start = Utc::now();
+        let minutes = Duration::minutes;
+        let mut starvation = Starvation::default();
+        starvation.kept_waiting(1, start);
+        starvation.kept_waiting(1, start + minutes(2));
+        starvation.served(1, start + minutes(5));
+        starvation.kept_waiting(1, start + minutes(6));
+        starvation.kept_waiting(2, start + minutes(1));
+        starvation.served(3, start);
+
+        let report = starvation.report(start + minutes(10));
+        assert_eq!(report.len(), 2);
+        assert_eq!(
+            (report[0].artist_id, report[0].longest_wait),
+            (2, minutes(9))
+        );
+        assert_eq!(report[1].longest_wait, minutes(5));
+        assert_eq!((report[1].streak, report[1].longest_streak), (1, 2));
+    }
+}
@@ -0,0 +1,114 @@
+use rand::Rng;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FatigueModel {
+    pub base_duration: Duration,
+    // Extra fraction of `base_duration` added per consecutive task.
+    pub slowdown_per_task: f64,
+    pub base_damage_probability: f64,
+    pub damage_increase_per_task: f64,
+    // An artist takes a break after this many tasks in a row.
+    pub tasks_before_break: usize,
+}
+
+impl Default for FatigueModel {
+    fn default() -> Self {
+        Self {
+            base_duration: Duration::from_millis(10),
+            slowdown_per_task: 0.15,
+            base_damage_probability: 0.01,
+            damage_increase_per_task: 0.01,
+            tasks_before_break: 4,
+        }
+    }
+}
+
+impl FatigueModel {
+    pub fn effort(&self, consecutive_tasks: usize) -> TaskEffort {
+        let tired = consecutive_tasks as f64;
+        TaskEffort {
+            duration: self
+                .base_duration
+                .mul_f64(1.0 + self.slowdown_per_task * tired),
+            damage_probability: (self.base_damage_probability
+                + self.damage_increase_per_task * tired)
+                .clamp(0.0, 1.0),
+            needs_break: consecutive_tasks + 1 >= self.tasks_before_break,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TaskEffort {
+    pub duration: Duration,
+    pub damage_probability: f64,
+    pub needs_break: bool,
+}
+
+impl TaskEffort {
+    pub fn rolls_damage(&self, rng: &mut impl Rng) -> bool {
+        rng.gen_bool(self.damage_probability)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ArtistFatigue {
+    consecutive_tasks: usize,
+}
+
+impl ArtistFatigue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn consecutive_tasks(&self) -> usize {
+        self.consecutive_tasks
+    }
+
+    // Returns the effort for the next task; the artist rests automatically once
+    // the model says a break is due.
+    pub fn record_task(&mut self, model: &FatigueModel) -> TaskEffort {
+        let effort = model.effort(self.consecutive_tasks);
+        if effort.needs_break {
+            self.take_break();
+        } else {
+            self.consecutive_tasks += 1;
+        }
+        effort
+    }
+
+    pub fn take_break(&mut self) {
+        self.consecutive_tasks = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_consecutive_tasks_get_slower_and_riskier() {
+        let model = FatigueModel::default();
+        let mut fatigue = ArtistFatigue::new();
+        let first = fatigue.record_task(&model);
+        let second = fatigue.record_task(&model);
+        assert!(second.duration > first.duration);
+        assert!(second.damage_probability > first.damage_probability);
+        assert_eq!(fatigue.consecutive_tasks(), 2);
+    }
+
+    #[test]
+    fn test_break_resets_fatigue() {
+        let model = FatigueModel {
+            tasks_before_break: 2,
+            ..FatigueModel::default()
+        };
+        let mut fatigue = ArtistFatigue::new();
+        fatigue.record_task(&model);
+        let tired = fatigue.record_task(&model);
+        assert!(tired.needs_break);
+        assert_eq!(fatigue.consecutive_tasks(), 0);
+        assert_eq!(fatigue.record_task(&model).duration, model.base_duration);
+    }
+}
@@ -0,0 +1,75 @@
+use crate::{ArtistToolPreferences, ArtistToolRegistry};
+use chrono::{DateTime, Utc};
+
+// Read-only views over the registry's entries, oldest first. Each can be
+// narrowed further with ordinary iterator adapters.
+impl ArtistToolRegistry {
+    pub fn history(&self) -> impl Iterator<Item = &ArtistToolPreferences> {
+        self.artist_tool_preferences.iter()
+    }
+
+    pub fn history_for_artist(&self, id: usize) -> impl Iterator<Item = &ArtistToolPreferences> {
+        self.history().filter(move |entry| entry.artist_id == id)
+    }
+
+    // Entries that moved units of `tool`. Paint entries aren't included.
+    pub fn history_for_tool(&self, tool: &str) -> impl Iterator<Item = &ArtistToolPreferences> {
+        let symbol = self.interner.get(tool);
+        self.history().filter(move |entry| {
+            symbol.is_some_and(|symbol| entry.preferred_tools.contains(&symbol))
+        })
+    }
+
+    // Entries recorded at or after `from` and before `to`. Entries without a
+    // time are left out.
+    pub fn history_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> impl Iterator<Item = &ArtistToolPreferences> {
+        self.history().filter(move |entry| {
+            entry
+                .datetime
+                .is_some_and(|datetime| from <= datetime && datetime < to)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ArtistToolRegistry, SharedResources, State};
+    use chrono::{Duration, Utc};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_history_filters_by_artist_tool_and_time() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        let start = Utc::now();
+        registry
+            .tool_registry(1, vec!["brush".to_string(), "tape".to_string()])
+            .unwrap();
+        registry.tool_registry(2, vec!["tape".to_string()]).unwrap();
+        registry.tool_return(1, vec!["brush".to_string()]).unwrap();
+        let end = Utc::now() + Duration::seconds(1);
+
+        let states: Vec<_> = registry
+            .history_for_artist(1)
+            .map(|entry| entry.state)
+            .collect();
+        assert_eq!(states, vec![Some(State::TakeOut), Some(State::Return)]);
+        let artists: Vec<_> = registry
+            .history_for_tool("tape")
+            .map(|entry| entry.artist_id)
+            .collect();
+        assert_eq!(artists, vec![1, 2]);
+        assert_eq!(registry.history_for_tool("easel").count(), 0);
+        assert_eq!(registry.history_between(start, end).count(), 3);
+        assert_eq!(
+            registry
+                .history_between(end, end + Duration::days(1))
+                .count(),
+            0
+        );
+    }
+}
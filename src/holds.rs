@@ -0,0 +1,122 @@
+use crate::{
+    error::{RegistryError, ResourceError, UnavailableTools},
+    ArtistToolRegistry, State,
+};
+use chrono::Duration;
+
+// How long a hold keeps its tools back unless the caller picks a timeout.
+pub const DEFAULT_HOLD_TIMEOUT: Duration = Duration::minutes(5);
+
+// A claim on tools sitting on the shelf, set aside for one artist until the
+// hold is committed, released or times out. It is a reservation starting
+// now, so it shows up among the registry's reservations under this number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HoldToken(usize);
+
+impl HoldToken {
+    pub fn reservation(self) -> usize {
+        self.0
+    }
+}
+
+impl ArtistToolRegistry {
+    // First half of a two-phase checkout: sets the tools aside for `id` so
+    // nobody else can take them, without lending anything yet. Every unit
+    // has to be on the shelf and not held for anyone already. Once `timeout`
+    // passes the hold stops counting and the tools are free again.
+    pub fn hold(
+        &mut self,
+        id: usize,
+        tools: Vec<String>,
+        timeout: Duration,
+    ) -> Result<HoldToken, RegistryError> {
+        let now = self.now();
+        if timeout <= Duration::zero() {
+            return Err(RegistryError::EmptyWindow);
+        }
+        let tools = self.resolve_tools(tools)?;
+        self.check_tool_count(id, tools.len())?;
+        self.check_holding(id, tools.len())?;
+        {
+            let resources = self
+                .shared_resources
+                .lock()
+                .map_err(|poisoned| self.recover(poisoned))?;
+            let mut unavailable = UnavailableTools::default();
+            for tool in &tools {
+                let wanted = tools.iter().filter(|name| *name == tool).count();
+                let free = resources
+                    .stock(tool)
+                    .get()
+                    .saturating_sub(self.reservations.held(tool, now));
+                if free < wanted && !unavailable.missing.contains(tool) {
+                    unavailable.missing.push(tool.clone());
+                }
+            }
+            if !unavailable.missing.is_empty() {
+                return Err(ResourceError::Unavailable(unavailable).into());
+            }
+        }
+        let reservation = self.reservations.add(id, tools.clone(), now, now + timeout);
+        self.push_entry(id, &tools, None, State::Reserved, now);
+        Ok(HoldToken(reservation))
+    }
+
+    // Lends everything the hold set aside. Fails with `ReservationExpired`
+    // once the hold has timed out.
+    pub fn commit(&mut self, token: HoldToken) -> Result<(), RegistryError> {
+        self.claim_reservation(token.0)
+    }
+
+    // Puts the held tools back up for anyone, lending nothing.
+    pub fn release(&mut self, token: HoldToken) -> Result<(), RegistryError> {
+        self.cancel_reservation(token.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{clock::MockClock, units::Count, SharedResources};
+    use chrono::Utc;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_hold_keeps_tools_until_commit_release_or_timeout() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        resources
+            .lock()
+            .unwrap()
+            .restock("easel", Count(2))
+            .unwrap();
+        let mut registry = ArtistToolRegistry::new(&resources);
+        let clock = MockClock::new(Utc::now());
+        registry.set_clock(Arc::new(clock.clone()));
+        let easel = || vec!["easel".to_string()];
+
+        let first = registry.hold(1, easel(), DEFAULT_HOLD_TIMEOUT).unwrap();
+        let second = registry.hold(2, easel(), Duration::minutes(1)).unwrap();
+        // Both easels are spoken for, though still on the shelf.
+        assert!(registry.hold(3, easel(), DEFAULT_HOLD_TIMEOUT).is_err());
+        assert!(matches!(
+            registry.tool_registry(3, easel()),
+            Err(RegistryError::ReservedForOthers(_))
+        ));
+        assert_eq!(resources.lock().unwrap().stock("easel"), Count(2));
+
+        registry.commit(first).unwrap();
+        assert_eq!(registry.held_tools(1).len(), 1);
+        registry.release(second).unwrap();
+        let third = registry.hold(3, easel(), Duration::minutes(1)).unwrap();
+
+        clock.advance(Duration::minutes(2));
+        let fourth = registry.hold(4, easel(), DEFAULT_HOLD_TIMEOUT).unwrap();
+        assert_eq!(
+            registry.commit(third),
+            Err(RegistryError::ReservationExpired(third.reservation()))
+        );
+        registry.commit(fourth).unwrap();
+        assert_eq!(resources.lock().unwrap().stock("easel"), Count(0));
+        assert_eq!(registry.audit(0).discrepancies().count(), 0);
+    }
+}
@@ -0,0 +1,1211 @@
+use crate::{
+    alerts::LowStockAlert,
+    budgets::DepartmentSpend,
+    checkpoint::RecoveryReport,
+    costs::ProfitLoss,
+    crdt::AuditItem,
+    drying::DryingStats,
+    error::{RegistryError, ResourceError},
+    events::{EventReplay, StockMismatch},
+    money::{Currency, Money},
+    recovery::PoisonRecovery,
+    rounds::RoundStats,
+    scenario::StepOutcome,
+    script::{Action, FiredRule},
+    search::SearchKind,
+    stocktake::{Adjustment, AdjustmentReason},
+    templates::TEMPLATES,
+    tool_limits::ToolCountError,
+    trace::ReplayReport,
+    units::{Amount, Count, Kilograms},
+    watch::{Metric, MetricChange, RunSummary},
+};
+use chrono::Duration;
+use std::{
+    env, fmt,
+    sync::{OnceLock, PoisonError, RwLock},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Locale {
+    English,
+    Spanish,
+}
+
+impl Locale {
+    // Accepts POSIX locale tags such as `es_ES.UTF-8`, `es`, or `en_US`.
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let language = tag
+            .split(['_', '-', '.', '@'])
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        match language.as_str() {
+            "en" | "c" | "posix" => Some(Locale::English),
+            "es" => Some(Locale::Spanish),
+            _ => None,
+        }
+    }
+
+    pub fn from_env() -> Self {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|key| env::var(key).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| Self::from_tag(&value))
+            .unwrap_or(Locale::English)
+    }
+}
+
+static LOCALE: OnceLock<Locale> = OnceLock::new();
+static PINNED: OnceLock<Locale> = OnceLock::new();
+static CHOSEN: RwLock<Option<Locale>> = RwLock::new(None);
+
+// The locale `--lang` picks, ahead of the studio file's and the
+// environment's for as long as the process runs.
+pub fn pin_locale(locale: Locale) {
+    let _ = PINNED.set(locale);
+}
+
+// The studio file's locale, ahead of the environment's; a running daemon
+// switches when the file changes.
+pub fn set_locale(locale: Locale) {
+    *CHOSEN.write().unwrap_or_else(PoisonError::into_inner) = Some(locale);
+}
+
+pub fn locale() -> Locale {
+    let chosen = *CHOSEN.read().unwrap_or_else(PoisonError::into_inner);
+    match PINNED.get().copied().or(chosen) {
+        Some(locale) => locale,
+        // Tests check English replies whatever the machine is set to.
+        None if cfg!(test) => Locale::English,
+        None => *LOCALE.get_or_init(Locale::from_env),
+    }
+}
+
+pub enum Message<'a> {
+    ToolNotFound(&'a str),
+    LockFailed,
+    LoanCapReached(&'a str),
+    CheckoutQueued(usize, &'a str),
+    QueuedCheckoutServed(usize, &'a str),
+    RateLimited(usize, Duration),
+    ToolCountRejected(&'a ToolCountError),
+    CheckoutFailed(&'a RegistryError),
+    SaleFailed(&'a RegistryError),
+    UnknownCurrency(Currency),
+    MixedCurrencies(Currency, Currency),
+    SelectedTools(usize, &'a [String]),
+    CheckedOut(usize, &'a [String]),
+    Returned(usize, &'a [String]),
+    UsedPaint(usize, &'a str, Kilograms),
+    LockRecovered(&'a PoisonRecovery),
+    ChaosPoisoned(usize),
+    ChaosRecovered(usize),
+    ChaosReturnDelayed(usize, &'a [String]),
+    ChaosToolLost(usize, &'a str),
+    TiredArtistDamaged(usize, &'a str),
+    ArtistTookBreak(usize),
+    ChaosSupplierDelayed(&'a [String]),
+    SearchHit(SearchKind, &'a str),
+    TraceSaved(&'a str),
+    StateDumped(&'a str),
+    StudioReloaded(&'a str),
+    StudioReloadRefused(&'a str, String),
+    EventsSaved(&'a str),
+    CsvExported(&'a str),
+    ReportWritten(&'a str),
+    Serving(&'a str),
+    FeatureNotBuilt(&'a str),
+    RuntimeFailed(String),
+    DashboardFailed(String),
+    Interrupted,
+    InterruptHandlerFailed(String),
+    FinalInventory(&'a [(String, Count)]),
+    LowStock(&'a LowStockAlert),
+    NotifyFailed(&'a str, String),
+    EventRefused(usize, &'a ResourceError),
+    StockMismatch(&'a StockMismatch),
+    EventReplaySummary(&'a EventReplay),
+    JobFailed(&'a str, String),
+    Recovered(&'a RecoveryReport),
+    SyncPlan(usize, bool),
+    BranchForked(&'a str, &'a str),
+    BranchMerged(usize, bool),
+    MergeConflict(&'a AuditItem),
+    NotABranch,
+    RuleFired(&'a FiredRule),
+    StocktakePrompt(&'a str, Amount),
+    StocktakeAdjustment(&'a Adjustment),
+    StocktakeConfirm(usize),
+    StocktakeCancelled,
+    StocktakeMatches,
+    StocktakeRefused(&'a ResourceError),
+    StocktakeApplied(usize),
+    RunSummary(&'a RunSummary),
+    StudioSummary(&'a str, usize, &'a RunSummary),
+    RoundSummary(&'a RoundStats),
+    ClientFailed(&'a str),
+    JournalDiverged(u64, &'a RegistryError),
+    MetricChanged(&'a MetricChange),
+    NoMetricChanges,
+    WatchingFiles,
+    StudioInitialized(&'a str, &'a str),
+    UnknownTemplate(&'a str),
+    FileError(&'a str, String),
+    InvalidFlag(&'a str, &'a str),
+    UnknownArgument(&'a str),
+    ReplaySummary(&'a ReplayReport),
+    QueueSummaryHeader,
+    ToolStatsHeader,
+    ArtistStatsHeader,
+    StarvationHeader,
+    RevenueHeader,
+    ProfitLoss(&'a ProfitLoss),
+    DepartmentSpend(&'a DepartmentSpend),
+    DryingSummary(&'a DryingStats),
+    BudgetWarning(&'a str, u64),
+    BudgetOverrun(&'a str, u64),
+    ExperimentHeader,
+    BenchHeader,
+    Finished,
+    InteractiveBanner,
+    ScenarioStep(&'a StepOutcome),
+    ScenarioSummary(usize, usize),
+    // Daemon and interactive replies, without their `ok:` or `error:`.
+    ArtistHolds(usize, usize),
+    ArtistReturned(usize, usize),
+    ArtistTookPaint(usize, Kilograms),
+    RepairTicketOpened(usize),
+    UndidLast(usize),
+    StockRetired(usize, &'a str),
+    StockSold(usize, &'a str, Money),
+    BatchesExpired(usize, Kilograms),
+    ShuttingDown,
+    NotJournaled(&'a str, String),
+    NotCheckpointed(String),
+    NoToolGiven,
+    NoToolsGiven,
+    NoPaintsGiven,
+    ExpectedPaint(&'a str),
+    InvalidArtistId(&'a str),
+    InvalidAdminId(&'a str),
+    InvalidAuditorId(&'a str),
+    CommandUsage(&'a str),
+    NoSuchUnit(&'a str, usize),
+    EmptyCommand,
+    UnknownCommand(&'a str),
+    LoginFailed,
+    InvalidToken,
+    AuthRequired,
+    NotAllowed,
+    PinSet(usize),
+    TokensRevoked(usize),
+    TokenRevoked,
+    NoSuchToken,
+    NoHistory(usize),
+    InteractiveHelp,
+    Usage,
+}
+
+impl Message<'_> {
+    pub fn render(&self, locale: Locale) -> String {
+        match (self, locale) {
+            (Message::ToolNotFound(tool), Locale::English) => {
+                format!("Warning: Tool '{}' not found in resources.", tool)
+            }
+            (Message::ToolNotFound(tool), Locale::Spanish) => {
+                format!(
+                    "Advertencia: la herramienta '{}' no está en los recursos.",
+                    tool
+                )
+            }
+            (Message::LockFailed, Locale::English) => {
+                "Error: Unable to lock shared resources.".to_string()
+            }
+            (Message::LockFailed, Locale::Spanish) => {
+                "Error: no se pudieron bloquear los recursos compartidos.".to_string()
+            }
+            (Message::QueuedCheckoutServed(id, tool), Locale::English) => {
+                format!("Artist {} got queued '{}' (loan returned).", id, tool)
+            }
+            (Message::QueuedCheckoutServed(id, tool), Locale::Spanish) => {
+                format!("Artista {} recibe '{}' (préstamo devuelto).", id, tool)
+            }
+            (Message::LoanCapReached(tool), Locale::English) => {
+                format!("Warning: Loan cap reached for '{}'.", tool)
+            }
+            (Message::LoanCapReached(tool), Locale::Spanish) => {
+                format!(
+                    "Advertencia: se alcanzó el límite de préstamo de '{}'.",
+                    tool
+                )
+            }
+            (Message::CheckoutQueued(id, tool), Locale::English) => {
+                format!("Artist {} queued for '{}' until a unit is free.", id, tool)
+            }
+            (Message::CheckoutQueued(id, tool), Locale::Spanish) => {
+                format!(
+                    "Artista {} en espera de '{}' hasta que quede una libre.",
+                    id, tool
+                )
+            }
+            (Message::ToolCountRejected(error), Locale::English) => format!(
+                "Artist {} asked for {} tool(s); allowed {} to {}.",
+                error.artist_id, error.requested, error.allowed.min, error.allowed.max
+            ),
+            (Message::ToolCountRejected(error), Locale::Spanish) => format!(
+                "El artista {} pidió {} herramienta(s); se permiten de {} a {}.",
+                error.artist_id, error.requested, error.allowed.min, error.allowed.max
+            ),
+            (Message::RateLimited(id, retry_after), Locale::English) => {
+                format!(
+                    "Warning: Artist {} is rate limited; retry in {} ms.",
+                    id,
+                    retry_after.num_milliseconds()
+                )
+            }
+            (Message::RateLimited(id, retry_after), Locale::Spanish) => {
+                format!(
+                    "Advertencia: el artista {} superó el límite; reintente en {} ms.",
+                    id,
+                    retry_after.num_milliseconds()
+                )
+            }
+            // Reuses the matching warning where there is one, so each reason
+            // reads the same however it was reported.
+            (Message::CheckoutFailed(error), locale) => match error {
+                RegistryError::Resource(ResourceError::ToolNotFound(tool)) => {
+                    Message::ToolNotFound(tool).render(locale)
+                }
+                RegistryError::RateLimited {
+                    artist_id,
+                    retry_after,
+                } => Message::RateLimited(*artist_id, *retry_after).render(locale),
+                RegistryError::ToolCount(error) => Message::ToolCountRejected(error).render(locale),
+                RegistryError::LockPoisoned => Message::LockFailed.render(locale),
+                other => match locale {
+                    Locale::English => format!("Error: Checkout refused: {}.", other),
+                    Locale::Spanish => format!("Error: préstamo rechazado: {}.", other),
+                },
+            },
+            (Message::SaleFailed(error), Locale::English) => {
+                format!("Error: Sale refused: {}.", error)
+            }
+            (Message::SaleFailed(error), Locale::Spanish) => {
+                format!("Error: venta rechazada: {}.", error)
+            }
+            (Message::UnknownCurrency(currency), Locale::English) => {
+                format!("Error: No exchange rate configured for {}.", currency)
+            }
+            (Message::MixedCurrencies(left, right), Locale::English) => {
+                format!("Error: Can't total {} with {} without converting.", left, right)
+            }
+            (Message::MixedCurrencies(left, right), Locale::Spanish) => {
+                format!("Error: no se puede totalizar {} con {} sin convertir.", left, right)
+            }
+            (Message::UnknownCurrency(currency), Locale::Spanish) => {
+                format!(
+                    "Error: no hay tipo de cambio configurado para {}.",
+                    currency
+                )
+            }
+            (Message::SelectedTools(id, tools), Locale::English) => {
+                format!("Artist {}: Selected tools: {:#?}", id, tools)
+            }
+            (Message::SelectedTools(id, tools), Locale::Spanish) => {
+                format!("Artista {}: herramientas seleccionadas: {:#?}", id, tools)
+            }
+            (Message::CheckedOut(id, tools), Locale::English) => {
+                format!("Artist {}: Checked out {}", id, tools.join(", "))
+            }
+            (Message::CheckedOut(id, tools), Locale::Spanish) => {
+                format!("Artista {}: se llevó {}", id, tools.join(", "))
+            }
+            (Message::Returned(id, tools), Locale::English) => {
+                format!("Artist {}: Returned {}", id, tools.join(", "))
+            }
+            (Message::Returned(id, tools), Locale::Spanish) => {
+                format!("Artista {}: devolvió {}", id, tools.join(", "))
+            }
+            (Message::LockRecovered(recovery), Locale::English) => format!(
+                "Warning: Shared resources were poisoned; put back the stock of {} item(s) and the loan count of {} tool(s) from the registry's records.",
+                recovery.stock.len(),
+                recovery.on_loan.len()
+            ),
+            (Message::LockRecovered(recovery), Locale::Spanish) => format!(
+                "Advertencia: los recursos compartidos estaban envenenados; se restauró el stock de {} artículo(s) y los préstamos de {} herramienta(s) según los registros.",
+                recovery.stock.len(),
+                recovery.on_loan.len()
+            ),
+            (Message::ChaosPoisoned(id), Locale::English) => {
+                format!("Chaos: artist {} poisoned the registry lock", id)
+            }
+            (Message::ChaosPoisoned(id), Locale::Spanish) => {
+                format!("Caos: el artista {} envenenó el cerrojo del registro", id)
+            }
+            (Message::ChaosRecovered(id), Locale::English) => {
+                format!("Chaos: artist {} cleared the poisoned lock", id)
+            }
+            (Message::ChaosRecovered(id), Locale::Spanish) => {
+                format!("Caos: el artista {} limpió el cerrojo envenenado", id)
+            }
+            (Message::ChaosReturnDelayed(id, tools), Locale::English) => {
+                format!("Chaos: artist {} keeps {} another round", id, tools.join(", "))
+            }
+            (Message::ChaosReturnDelayed(id, tools), Locale::Spanish) => {
+                format!("Caos: el artista {} se queda {} otra ronda", id, tools.join(", "))
+            }
+            (Message::ChaosToolLost(id, tool), Locale::English) => {
+                format!("Chaos: artist {} lost one {}", id, tool)
+            }
+            (Message::ChaosToolLost(id, tool), Locale::Spanish) => {
+                format!("Caos: el artista {} perdió una unidad de {}", id, tool)
+            }
+            (Message::TiredArtistDamaged(id, tool), Locale::English) => {
+                format!("Artist {} is tired and damaged a {}", id, tool)
+            }
+            (Message::TiredArtistDamaged(id, tool), Locale::Spanish) => {
+                format!("El artista {} está cansado y dañó una unidad de {}", id, tool)
+            }
+            (Message::ArtistTookBreak(id), Locale::English) => {
+                format!("Artist {} takes a break", id)
+            }
+            (Message::ArtistTookBreak(id), Locale::Spanish) => {
+                format!("El artista {} se toma un descanso", id)
+            }
+            (Message::ChaosSupplierDelayed(tools), Locale::English) => {
+                format!("Chaos: the supplier is late replacing {}", tools.join(", "))
+            }
+            (Message::ChaosSupplierDelayed(tools), Locale::Spanish) => {
+                format!("Caos: el proveedor se retrasa en reponer {}", tools.join(", "))
+            }
+            (Message::UsedPaint(id, color, kg), Locale::English) => {
+                format!("Artist {}: Used {} kg of {}", id, kg, color)
+            }
+            (Message::UsedPaint(id, color, kg), Locale::Spanish) => {
+                format!("Artista {}: ha usado {} kg de {}", id, kg, color)
+            }
+            (Message::SearchHit(kind, name), _) => {
+                format!("{}: {}", kind_label(*kind, locale), name)
+            }
+            (Message::TraceSaved(path), Locale::English) => format!("Trace written to {}.", path),
+            (Message::TraceSaved(path), Locale::Spanish) => format!("Traza guardada en {}.", path),
+            (Message::Serving(addr), Locale::English) => format!("Serving the API on http://{}.", addr),
+            (Message::Serving(addr), Locale::Spanish) => {
+                format!("Sirviendo la API en http://{}.", addr)
+            }
+            (Message::FeatureNotBuilt(feature), Locale::English) => format!(
+                "Error: built without the '{}' feature; rebuild with --features {}.",
+                feature, feature
+            ),
+            (Message::FeatureNotBuilt(feature), Locale::Spanish) => format!(
+                "Error: compilado sin la función '{}'; recompila con --features {}.",
+                feature, feature
+            ),
+            (Message::RuntimeFailed(error), Locale::English) => {
+                format!("Error: the async runtime failed to start: {}", error)
+            }
+            (Message::RuntimeFailed(error), Locale::Spanish) => {
+                format!("Error: no se pudo iniciar el entorno asíncrono: {}", error)
+            }
+            (Message::DashboardFailed(error), Locale::English) => {
+                format!("Error: the dashboard failed: {}", error)
+            }
+            (Message::DashboardFailed(error), Locale::Spanish) => {
+                format!("Error: el panel falló: {}", error)
+            }
+            (Message::Interrupted, Locale::English) => {
+                "Interrupted: artists stopped after their current checkout.".to_string()
+            }
+            (Message::Interrupted, Locale::Spanish) => {
+                "Interrumpido: los artistas pararon tras su préstamo en curso.".to_string()
+            }
+            (Message::InterruptHandlerFailed(error), Locale::English) => {
+                format!("Error: could not install the Ctrl+C handler: {}", error)
+            }
+            (Message::InterruptHandlerFailed(error), Locale::Spanish) => {
+                format!("Error: no se pudo instalar el manejador de Ctrl+C: {}", error)
+            }
+            (Message::FinalInventory(stock), Locale::English) => {
+                format!("Final inventory: {}", stock_list(stock))
+            }
+            (Message::FinalInventory(stock), Locale::Spanish) => {
+                format!("Inventario final: {}", stock_list(stock))
+            }
+            (Message::LowStock(alert), Locale::English) => format!(
+                "Low stock: {} '{}' left (threshold {}) after artist {}'s checkout.",
+                alert.stock, alert.item, alert.threshold, alert.artist_id
+            ),
+            (Message::LowStock(alert), Locale::Spanish) => format!(
+                "Existencias bajas: quedan {} de '{}' (umbral {}) tras el préstamo del artista {}.",
+                alert.stock, alert.item, alert.threshold, alert.artist_id
+            ),
+            (Message::NotifyFailed(item, error), Locale::English) => {
+                format!("Error: the low-stock alert for '{}' wasn't sent: {}", item, error)
+            }
+            (Message::NotifyFailed(item, error), Locale::Spanish) => {
+                format!("Error: no se envió el aviso de existencias bajas de '{}': {}", item, error)
+            }
+            (Message::EventsSaved(path), Locale::English) => {
+                format!("Event log written to {}.", path)
+            }
+            (Message::EventsSaved(path), Locale::Spanish) => {
+                format!("Registro de eventos guardado en {}.", path)
+            }
+            (Message::CsvExported(path), Locale::English) => format!("CSV written to {}.", path),
+            (Message::CsvExported(path), Locale::Spanish) => format!("CSV guardado en {}.", path),
+            (Message::ReportWritten(path), Locale::English) => format!("Report written to {}.", path),
+            (Message::ReportWritten(path), Locale::Spanish) => {
+                format!("Informe guardado en {}.", path)
+            }
+            (Message::EventRefused(index, error), Locale::English) => {
+                format!("Error: event {} could not be replayed: {}.", index + 1, error)
+            }
+            (Message::EventRefused(index, error), Locale::Spanish) => {
+                format!("Error: no se pudo reproducir el evento {}: {}.", index + 1, error)
+            }
+            (Message::StockMismatch(mismatch), Locale::English) => format!(
+                "Mismatch: '{}' was recorded at {} but replays to {}.",
+                mismatch.item, mismatch.recorded, mismatch.replayed
+            ),
+            (Message::StockMismatch(mismatch), Locale::Spanish) => format!(
+                "Discrepancia: '{}' se registró con {} pero la reproducción da {}.",
+                mismatch.item, mismatch.recorded, mismatch.replayed
+            ),
+            (Message::EventReplaySummary(report), Locale::English) => format!(
+                "Replayed {} events: {}.",
+                report.applied,
+                if report.matches() {
+                    "final inventory matches the log"
+                } else {
+                    "final inventory does not match the log"
+                }
+            ),
+            (Message::EventReplaySummary(report), Locale::Spanish) => format!(
+                "Reproducidos {} eventos: {}.",
+                report.applied,
+                if report.matches() {
+                    "el inventario final coincide con el registro"
+                } else {
+                    "el inventario final no coincide con el registro"
+                }
+            ),
+            (Message::StateDumped(path), Locale::English) => format!("State dumped to {}.", path),
+            (Message::StateDumped(path), Locale::Spanish) => {
+                format!("Estado volcado en {}.", path)
+            }
+            (Message::StudioReloaded(path), Locale::English) => {
+                format!("Reloaded thresholds and capacities from {}.", path)
+            }
+            (Message::StudioReloaded(path), Locale::Spanish) => {
+                format!("Umbrales y capacidades recargados desde {}.", path)
+            }
+            (Message::StudioReloadRefused(path, error), Locale::English) => {
+                format!("Error: kept the old settings, {} can't be applied: {}", path, error)
+            }
+            (Message::StudioReloadRefused(path, error), Locale::Spanish) => format!(
+                "Error: se mantienen los ajustes anteriores, {} no se puede aplicar: {}",
+                path, error
+            ),
+            (Message::JobFailed(job, error), Locale::English) => {
+                format!("Error: job '{}' failed: {}", job, error)
+            }
+            (Message::JobFailed(job, error), Locale::Spanish) => {
+                format!("Error: la tarea '{}' falló: {}", job, error)
+            }
+            (Message::Recovered(report), Locale::English) => format!(
+                "Recovered after an unclean shutdown: checkpoint #{} from {}, plus {} journaled checkout(s); {}{}",
+                report.checkpoint_seq,
+                report.checkpoint_at.format("%Y-%m-%d %H:%M:%S UTC"),
+                report.replayed,
+                match (report.lost_lines, report.diverged) {
+                    (0, 0) => "nothing lost.".to_string(),
+                    (lost, 0) => format!("{} unreadable journal line(s) lost.", lost),
+                    (lost, diverged) => format!(
+                        "{} unreadable journal line(s) lost, {} operation(s) could not be re-applied.",
+                        lost, diverged
+                    ),
+                },
+                match report.lost_reservations {
+                    0 => String::new(),
+                    lost => format!(" {} reservation(s) were not saved and are gone.", lost),
+                }
+            ),
+            (Message::Recovered(report), Locale::Spanish) => format!(
+                "Recuperado tras un cierre inesperado: punto de control #{} del {}, más {} préstamo(s) del diario; {}{}",
+                report.checkpoint_seq,
+                report.checkpoint_at.format("%Y-%m-%d %H:%M:%S UTC"),
+                report.replayed,
+                match (report.lost_lines, report.diverged) {
+                    (0, 0) => "no se perdió nada.".to_string(),
+                    (lost, 0) => format!("se perdieron {} línea(s) ilegibles del diario.", lost),
+                    (lost, diverged) => format!(
+                        "se perdieron {} línea(s) ilegibles del diario y no se pudo volver a aplicar {} operación(es).",
+                        lost, diverged
+                    ),
+                },
+                match report.lost_reservations {
+                    0 => String::new(),
+                    lost => format!(" {} reserva(s) no se guardaron y se han perdido.", lost),
+                }
+            ),
+            (Message::SyncPlan(count, true), Locale::English) => {
+                format!("Dry run: {} operation(s) would be applied.", count)
+            }
+            (Message::SyncPlan(count, false), Locale::English) => {
+                format!("Applying {} operation(s).", count)
+            }
+            (Message::SyncPlan(count, true), Locale::Spanish) => {
+                format!("Simulación: se aplicarían {} operación(es).", count)
+            }
+            (Message::SyncPlan(count, false), Locale::Spanish) => {
+                format!("Aplicando {} operación(es).", count)
+            }
+            (Message::BranchForked(replica, path), Locale::English) => {
+                format!("Branch '{}' written to {}.", replica, path)
+            }
+            (Message::BranchForked(replica, path), Locale::Spanish) => {
+                format!("Sucursal '{}' guardada en {}.", replica, path)
+            }
+            (Message::BranchMerged(conflicts, true), Locale::English) => {
+                format!("Dry run: merging would leave {} conflict(s) to audit.", conflicts)
+            }
+            (Message::BranchMerged(conflicts, false), Locale::English) => {
+                format!("Branches merged; {} conflict(s) to audit.", conflicts)
+            }
+            (Message::BranchMerged(conflicts, true), Locale::Spanish) => {
+                format!("Simulación: la fusión dejaría {} conflicto(s) por revisar.", conflicts)
+            }
+            (Message::BranchMerged(conflicts, false), Locale::Spanish) => {
+                format!("Sucursales fusionadas; {} conflicto(s) por revisar.", conflicts)
+            }
+            (Message::MergeConflict(item), Locale::English) => format!(
+                "'{}': {} more lent out than exist, by artists {}",
+                item.item,
+                item.shortfall,
+                conflict_artists(item)
+            ),
+            (Message::MergeConflict(item), Locale::Spanish) => format!(
+                "'{}': {} prestado(s) de más, por los artistas {}",
+                item.item,
+                item.shortfall,
+                conflict_artists(item)
+            ),
+            (Message::NotABranch, Locale::English) => {
+                "Error: both states must be branches started with --fork.".to_string()
+            }
+            (Message::NotABranch, Locale::Spanish) => {
+                "Error: ambos estados deben ser sucursales creadas con --fork.".to_string()
+            }
+            (Message::StocktakePrompt(item, recorded), Locale::English) => {
+                format!("Counted {} (recorded {}, blank to skip):", item, recorded)
+            }
+            (Message::StocktakePrompt(item, recorded), Locale::Spanish) => {
+                format!("Recuento de {} (registrado {}, vacío para omitir):", item, recorded)
+            }
+            (Message::StocktakeAdjustment(adjustment), _) => format!(
+                "{}: {} -> {} ({:+}, {})",
+                adjustment.item,
+                adjustment.recorded,
+                adjustment.counted,
+                adjustment.delta(),
+                reason_label(adjustment.reason, locale)
+            ),
+            (Message::StocktakeConfirm(count), Locale::English) => {
+                format!("Apply {} adjustment(s)? [y/N]", count)
+            }
+            (Message::StocktakeConfirm(count), Locale::Spanish) => {
+                format!("¿Aplicar {} ajuste(s)? [s/N]", count)
+            }
+            (Message::StocktakeCancelled, Locale::English) => "No changes made.".to_string(),
+            (Message::StocktakeCancelled, Locale::Spanish) => "No se hizo ningún cambio.".to_string(),
+            (Message::StocktakeMatches, Locale::English) => {
+                "Counts match the registry; nothing to adjust.".to_string()
+            }
+            (Message::StocktakeMatches, Locale::Spanish) => {
+                "El recuento coincide con el registro; nada que ajustar.".to_string()
+            }
+            (Message::StocktakeRefused(error), Locale::English) => {
+                format!("Error: Stocktake refused: {}.", error)
+            }
+            (Message::StocktakeRefused(error), Locale::Spanish) => {
+                format!("Error: recuento rechazado: {}.", error)
+            }
+            (Message::StocktakeApplied(count), Locale::English) => {
+                format!("Applied {} adjustment(s).", count)
+            }
+            (Message::StocktakeApplied(count), Locale::Spanish) => {
+                format!("Aplicados {} ajuste(s).", count)
+            }
+            (Message::RunSummary(summary), _) => summary
+                .metrics()
+                .iter()
+                .map(|(metric, value)| format!("{}: {}", metric_label(*metric, locale), value))
+                .collect::<Vec<_>>()
+                .join(", "),
+            (Message::StudioSummary(name, artists, summary), Locale::English) => format!(
+                "Studio {} ({} artists): {}",
+                name,
+                artists,
+                Message::RunSummary(summary).render(locale)
+            ),
+            (Message::StudioSummary(name, artists, summary), Locale::Spanish) => format!(
+                "Estudio {} ({} artistas): {}",
+                name,
+                artists,
+                Message::RunSummary(summary).render(locale)
+            ),
+            (Message::RoundSummary(stats), Locale::English) => format!(
+                "Round {}: {} checked out, {} working, {} returned, {} idle, {} failed, {} kg paint used, {} on loan",
+                stats.round,
+                stats.checkouts,
+                stats.working,
+                stats.returns,
+                stats.idle,
+                stats.failed,
+                stats.paint_kg,
+                stats.on_loan
+            ),
+            (Message::RoundSummary(stats), Locale::Spanish) => format!(
+                "Ronda {}: {} préstamos, {} trabajando, {} devoluciones, {} sin hacer nada, {} fallidos, {} kg de pintura usados, {} prestados",
+                stats.round,
+                stats.checkouts,
+                stats.working,
+                stats.returns,
+                stats.idle,
+                stats.failed,
+                stats.paint_kg,
+                stats.on_loan
+            ),
+            (Message::JournalDiverged(seq, error), Locale::English) => {
+                format!("Journal entry #{} could not be re-applied: {}", seq, error)
+            }
+            (Message::JournalDiverged(seq, error), Locale::Spanish) => {
+                format!("No se pudo volver a aplicar la entrada #{} del diario: {}", seq, error)
+            }
+            (Message::ClientFailed(error), Locale::English) => {
+                format!("Dropped a daemon client: {}", error)
+            }
+            (Message::ClientFailed(error), Locale::Spanish) => {
+                format!("Cliente del demonio descartado: {}", error)
+            }
+            (Message::MetricChanged(change), _) => format!(
+                "  {}: {} -> {} ({:+})",
+                metric_label(change.metric, locale),
+                change.before,
+                change.after,
+                change.delta()
+            ),
+            (Message::NoMetricChanges, Locale::English) => {
+                "  No change from the previous run.".to_string()
+            }
+            (Message::NoMetricChanges, Locale::Spanish) => {
+                "  Sin cambios respecto a la ejecución anterior.".to_string()
+            }
+            (Message::WatchingFiles, Locale::English) => {
+                "Watching for changes... (Ctrl-C to stop)".to_string()
+            }
+            (Message::WatchingFiles, Locale::Spanish) => {
+                "Esperando cambios... (Ctrl-C para salir)".to_string()
+            }
+            (Message::StudioInitialized(template, path), Locale::English) => {
+                format!("Wrote the '{}' studio to {}.", template, path)
+            }
+            (Message::StudioInitialized(template, path), Locale::Spanish) => {
+                format!("Estudio '{}' guardado en {}.", template, path)
+            }
+            (Message::UnknownTemplate(name), Locale::English) => format!(
+                "Unknown template '{}'. Available: {}.",
+                name,
+                TEMPLATES.join(", ")
+            ),
+            (Message::UnknownTemplate(name), Locale::Spanish) => format!(
+                "Plantilla desconocida '{}'. Disponibles: {}.",
+                name,
+                TEMPLATES.join(", ")
+            ),
+            (Message::RuleFired(fired), Locale::English) => match &fired.action {
+                Action::Reorder(quantity) => format!(
+                    "Rule on line {}: reordered {} {} (stock was {}).",
+                    fired.line, quantity, fired.item, fired.stock
+                ),
+                Action::Log(text) => format!("Rule on line {}: {}", fired.line, text),
+            },
+            (Message::RuleFired(fired), Locale::Spanish) => match &fired.action {
+                Action::Reorder(quantity) => format!(
+                    "Regla de la línea {}: pedidos {} {} (había {}).",
+                    fired.line, quantity, fired.item, fired.stock
+                ),
+                Action::Log(text) => format!("Regla de la línea {}: {}", fired.line, text),
+            },
+            (Message::FileError(path, error), Locale::English) => {
+                format!("Error: could not use '{}': {}", path, error)
+            }
+            (Message::FileError(path, error), Locale::Spanish) => {
+                format!("Error: no se pudo usar '{}': {}", path, error)
+            }
+            (Message::InvalidFlag(flag, value), Locale::English) => {
+                format!("Error: invalid value '{}' for {}.", value, flag)
+            }
+            (Message::InvalidFlag(flag, value), Locale::Spanish) => {
+                format!("Error: valor '{}' no válido para {}.", value, flag)
+            }
+            (Message::UnknownArgument(arg), Locale::English) => {
+                format!("Error: unexpected argument '{}'.", arg)
+            }
+            (Message::UnknownArgument(arg), Locale::Spanish) => {
+                format!("Error: argumento inesperado '{}'.", arg)
+            }
+            (Message::ReplaySummary(report), Locale::English) => format!(
+                "Replayed {} events at {}x: scheduled {:?}, took {:?}, avg lag {:?}, max lag {:?} ({})",
+                report.events,
+                report.speed,
+                report.scheduled,
+                report.actual,
+                report.avg_lag,
+                report.max_lag,
+                if report.kept_up { "kept up" } else { "fell behind" }
+            ),
+            (Message::ReplaySummary(report), Locale::Spanish) => format!(
+                "Reproducidos {} eventos a {}x: previsto {:?}, real {:?}, retraso medio {:?}, máximo {:?} ({})",
+                report.events,
+                report.speed,
+                report.scheduled,
+                report.actual,
+                report.avg_lag,
+                report.max_lag,
+                if report.kept_up { "al día" } else { "con retraso" }
+            ),
+            (Message::QueueSummaryHeader, Locale::English) => format!(
+                "{:<16} {:>5} {:>10} {:>10} {:>6} {:>10} {:>8}",
+                "tool", "reqs", "avg ms", "p95 ms", "util", "req/s", "little"
+            ),
+            (Message::QueueSummaryHeader, Locale::Spanish) => format!(
+                "{:<16} {:>5} {:>10} {:>10} {:>6} {:>10} {:>8}",
+                "herramienta", "sol", "media ms", "p95 ms", "uso", "sol/s", "little"
+            ),
+            (Message::ToolStatsHeader, Locale::English) => format!(
+                "{:<16} {:>9} {:>6} {:>12}",
+                "tool", "checkouts", "util", "avg hold ms"
+            ),
+            (Message::ToolStatsHeader, Locale::Spanish) => format!(
+                "{:<16} {:>9} {:>6} {:>12}",
+                "herramienta", "préstamos", "uso", "media ms"
+            ),
+            (Message::ArtistStatsHeader, Locale::English) => format!(
+                "{:<8} {:>9} {:>8} {:>8}",
+                "artist", "checkouts", "returns", "paint kg"
+            ),
+            (Message::ArtistStatsHeader, Locale::Spanish) => format!(
+                "{:<8} {:>9} {:>8} {:>8}",
+                "artista", "préstamos", "devol.", "kg pint."
+            ),
+            (Message::StarvationHeader, Locale::English) => format!(
+                "{:<8} {:>11} {:>15} {:>8}",
+                "artist", "max waits", "longest wait ms", "waiting"
+            ),
+            (Message::StarvationHeader, Locale::Spanish) => format!(
+                "{:<8} {:>11} {:>15} {:>8}",
+                "artista", "máx. esperas", "espera máx. ms", "esperando"
+            ),
+            (Message::RevenueHeader, Locale::English) => {
+                format!("{:<8} {:>6} {:>14}", "artist", "sold", "revenue")
+            }
+            (Message::RevenueHeader, Locale::Spanish) => {
+                format!("{:<8} {:>6} {:>14}", "artista", "obras", "ingresos")
+            }
+            (Message::ProfitLoss(summary), Locale::English) => format!(
+                "Profit and loss: revenue {}, restocks {}, repairs {}, losses {}; profit {}, balance {}.",
+                summary.revenue,
+                summary.restocks,
+                summary.repairs,
+                summary.losses,
+                summary.profit,
+                summary.balance
+            ),
+            (Message::ProfitLoss(summary), Locale::Spanish) => format!(
+                "Pérdidas y ganancias: ingresos {}, reposiciones {}, reparaciones {}, pérdidas {}; beneficio {}, saldo {}.",
+                summary.revenue,
+                summary.restocks,
+                summary.repairs,
+                summary.losses,
+                summary.profit,
+                summary.balance
+            ),
+            (Message::DepartmentSpend(spend), Locale::English) => format!(
+                "Department {}: restocks {}, repairs {}, total {}{}.",
+                spend.department,
+                spend.restock,
+                spend.repair,
+                spend.total,
+                spend
+                    .limit
+                    .map(|limit| format!(" of {}", limit))
+                    .unwrap_or_default()
+            ),
+            (Message::DepartmentSpend(spend), Locale::Spanish) => format!(
+                "Departamento {}: reposiciones {}, reparaciones {}, total {}{}.",
+                spend.department,
+                spend.restock,
+                spend.repair,
+                spend.total,
+                spend
+                    .limit
+                    .map(|limit| format!(" de {}", limit))
+                    .unwrap_or_default()
+            ),
+            (Message::DryingSummary(stats), Locale::English) => format!(
+                "Drying racks: {} of {} in use, {} waiting (at most {}), {} racked, average wait {} min.",
+                stats.occupied,
+                stats.capacity,
+                stats.queued,
+                stats.max_queued,
+                stats.racked_total,
+                stats.avg_wait_for_rack.num_minutes()
+            ),
+            (Message::DryingSummary(stats), Locale::Spanish) => format!(
+                "Secaderos: {} de {} ocupados, {} en espera (como mucho {}), {} secados, espera media {} min.",
+                stats.occupied,
+                stats.capacity,
+                stats.queued,
+                stats.max_queued,
+                stats.racked_total,
+                stats.avg_wait_for_rack.num_minutes()
+            ),
+            (Message::BudgetWarning(department, percent), Locale::English) => format!(
+                "Warning: '{}' has used {}% of this month's budget.",
+                department, percent
+            ),
+            (Message::BudgetWarning(department, percent), Locale::Spanish) => format!(
+                "Advertencia: '{}' ha gastado el {}% del presupuesto del mes.",
+                department, percent
+            ),
+            (Message::BudgetOverrun(department, percent), Locale::English) => format!(
+                "Warning: '{}' is over budget, at {}% of this month's limit.",
+                department, percent
+            ),
+            (Message::BudgetOverrun(department, percent), Locale::Spanish) => format!(
+                "Advertencia: '{}' supera su presupuesto, con el {}% del límite del mes.",
+                department, percent
+            ),
+            (Message::BenchHeader, Locale::English) => format!(
+                "{:<8} {:>10} {:>14} {:>12} {:>14} {:>14}",
+                "artists", "checkouts", "checkouts/s", "contended", "avg wait us", "max wait us"
+            ),
+            (Message::BenchHeader, Locale::Spanish) => format!(
+                "{:<8} {:>10} {:>14} {:>12} {:>14} {:>14}",
+                "artistas", "préstamos", "préstamos/s", "esperas", "espera med us", "espera máx us"
+            ),
+            (Message::ExperimentHeader, Locale::English) => format!(
+                "{:<8} {:>8} {:>8} {:>12} {:>10} {:>10}",
+                "strategy", "ops", "failed", "ops/s", "avg us", "p99 us"
+            ),
+            (Message::ExperimentHeader, Locale::Spanish) => format!(
+                "{:<8} {:>8} {:>8} {:>12} {:>10} {:>10}",
+                "método", "ops", "fallos", "ops/s", "media us", "p99 us"
+            ),
+            (Message::Finished, Locale::English) => "End".to_string(),
+            (Message::Finished, Locale::Spanish) => "Fin".to_string(),
+            (Message::InteractiveBanner, Locale::English) => {
+                "Type 'help' for the commands, 'quit' to leave.".to_string()
+            }
+            (Message::InteractiveBanner, Locale::Spanish) => {
+                "Escribe 'help' para ver las órdenes y 'quit' para salir.".to_string()
+            }
+            (Message::ScenarioStep(outcome), locale) => {
+                let seconds = outcome.at.num_seconds();
+                let at = format!(
+                    "+{:02}:{:02}:{:02}",
+                    seconds / 3600,
+                    seconds / 60 % 60,
+                    seconds % 60
+                );
+                let artist = match locale {
+                    Locale::English => "artist",
+                    Locale::Spanish => "artista",
+                };
+                let line = format!("{} {} {}: {}", at, artist, outcome.artist_id, outcome.action);
+                match (&outcome.result, locale) {
+                    (Err(error), Locale::English) => format!("{} failed: {}", line, error),
+                    (Err(error), Locale::Spanish) => format!("{} falló: {}", line, error),
+                    (Ok(()), Locale::English) if !outcome.held_back.is_empty() => {
+                        format!("{} (held back: {})", line, outcome.held_back.join(", "))
+                    }
+                    (Ok(()), Locale::Spanish) if !outcome.held_back.is_empty() => {
+                        format!("{} (retenidas: {})", line, outcome.held_back.join(", "))
+                    }
+                    (Ok(()), _) => line,
+                }
+            }
+            (Message::ScenarioSummary(steps, failed), Locale::English) => {
+                format!("Scenario finished: {} step(s), {} failed.", steps, failed)
+            }
+            (Message::ScenarioSummary(steps, failed), Locale::Spanish) => {
+                format!("Escenario terminado: {} paso(s), {} fallidos.", steps, failed)
+            }
+            (Message::ArtistHolds(id, count), Locale::English) => {
+                format!("artist {} holds {} new item(s)", id, count)
+            }
+            (Message::ArtistHolds(id, count), Locale::Spanish) => {
+                format!("el artista {} tiene {} artículo(s) nuevo(s)", id, count)
+            }
+            (Message::ArtistReturned(id, count), Locale::English) => {
+                format!("artist {} returned {} item(s)", id, count)
+            }
+            (Message::ArtistReturned(id, count), Locale::Spanish) => {
+                format!("el artista {} devolvió {} artículo(s)", id, count)
+            }
+            (Message::ArtistTookPaint(id, kg), Locale::English) => {
+                format!("artist {} took {} kg of paint", id, kg)
+            }
+            (Message::ArtistTookPaint(id, kg), Locale::Spanish) => {
+                format!("el artista {} tomó {} kg de pintura", id, kg)
+            }
+            (Message::RepairTicketOpened(ticket), Locale::English) => {
+                format!("repair ticket {}", ticket)
+            }
+            (Message::RepairTicketOpened(ticket), Locale::Spanish) => {
+                format!("orden de reparación {}", ticket)
+            }
+            (Message::UndidLast(id), Locale::English) => {
+                format!("undid artist {}'s last checkout or return", id)
+            }
+            (Message::UndidLast(id), Locale::Spanish) => {
+                format!(
+                    "se deshizo el último préstamo o devolución del artista {}",
+                    id
+                )
+            }
+            (Message::StockRetired(count, tool), Locale::English) => {
+                format!("retired {} {}", count, tool)
+            }
+            (Message::StockRetired(count, tool), Locale::Spanish) => {
+                format!("retirado(s) {} {}", count, tool)
+            }
+            (Message::StockSold(count, tool, price), Locale::English) => {
+                format!("sold {} {} for {}", count, tool, price)
+            }
+            (Message::StockSold(count, tool, price), Locale::Spanish) => {
+                format!("vendido(s) {} {} por {}", count, tool, price)
+            }
+            (Message::BatchesExpired(batches, kg), Locale::English) => {
+                format!("{} batch(es), {} kg expired", batches, kg)
+            }
+            (Message::BatchesExpired(batches, kg), Locale::Spanish) => {
+                format!("{} lote(s), {} kg caducados", batches, kg)
+            }
+            (Message::ShuttingDown, Locale::English) => "shutting down".to_string(),
+            (Message::ShuttingDown, Locale::Spanish) => "cerrando".to_string(),
+            (Message::NotJournaled(command, error), Locale::English) => {
+                format!("{} applied but not journaled: {}", command, error)
+            }
+            (Message::NotJournaled(command, error), Locale::Spanish) => {
+                format!("{} aplicado pero sin anotar en el diario: {}", command, error)
+            }
+            (Message::NotCheckpointed(error), Locale::English) => {
+                format!("applied but not checkpointed: {}", error)
+            }
+            (Message::NotCheckpointed(error), Locale::Spanish) => {
+                format!("aplicado pero sin punto de control: {}", error)
+            }
+            (Message::NoToolGiven, Locale::English) => "no tool given".to_string(),
+            (Message::NoToolGiven, Locale::Spanish) => "falta la herramienta".to_string(),
+            (Message::NoToolsGiven, Locale::English) => "no tools given".to_string(),
+            (Message::NoToolsGiven, Locale::Spanish) => "faltan las herramientas".to_string(),
+            (Message::NoPaintsGiven, Locale::English) => "no paints given".to_string(),
+            (Message::NoPaintsGiven, Locale::Spanish) => "faltan las pinturas".to_string(),
+            (Message::ExpectedPaint(paint), Locale::English) => {
+                format!("expected '<color> <kg>', got '{}'", paint)
+            }
+            (Message::ExpectedPaint(paint), Locale::Spanish) => {
+                format!("se esperaba '<color> <kg>', no '{}'", paint)
+            }
+            (Message::InvalidArtistId(id), Locale::English) => {
+                format!("invalid artist id '{}'", id)
+            }
+            (Message::InvalidArtistId(id), Locale::Spanish) => {
+                format!("id de artista no válido '{}'", id)
+            }
+            (Message::InvalidAdminId(id), Locale::English) => format!("invalid admin id '{}'", id),
+            (Message::InvalidAdminId(id), Locale::Spanish) => {
+                format!("id de administrador no válido '{}'", id)
+            }
+            (Message::InvalidAuditorId(id), Locale::English) => {
+                format!("invalid auditor id '{}'", id)
+            }
+            (Message::InvalidAuditorId(id), Locale::Spanish) => {
+                format!("id de auditor no válido '{}'", id)
+            }
+            (Message::CommandUsage(syntax), Locale::English) => format!("usage: {}", syntax),
+            (Message::CommandUsage(syntax), Locale::Spanish) => format!("uso: {}", syntax),
+            (Message::NoSuchUnit(tool, serial), Locale::English) => {
+                format!("no unit '{}' #{} has been numbered", tool, serial)
+            }
+            (Message::NoSuchUnit(tool, serial), Locale::Spanish) => {
+                format!("no hay ninguna unidad '{}' n.º {} numerada", tool, serial)
+            }
+            (Message::EmptyCommand, Locale::English) => "empty command".to_string(),
+            (Message::EmptyCommand, Locale::Spanish) => "orden vacía".to_string(),
+            (Message::UnknownCommand(command), Locale::English) => {
+                format!("unknown command '{}'", command)
+            }
+            (Message::UnknownCommand(command), Locale::Spanish) => {
+                format!("orden desconocida '{}'", command)
+            }
+            (Message::LoginFailed, Locale::English) => "login failed".to_string(),
+            (Message::LoginFailed, Locale::Spanish) => "inicio de sesión fallido".to_string(),
+            (Message::InvalidToken, Locale::English) => "invalid or expired token".to_string(),
+            (Message::InvalidToken, Locale::Spanish) => {
+                "token no válido o caducado".to_string()
+            }
+            (Message::AuthRequired, Locale::English) => "authentication required".to_string(),
+            (Message::AuthRequired, Locale::Spanish) => "se requiere autenticación".to_string(),
+            (Message::NotAllowed, Locale::English) => "not allowed for this token".to_string(),
+            (Message::NotAllowed, Locale::Spanish) => {
+                "no permitido con este token".to_string()
+            }
+            (Message::PinSet(id), Locale::English) => format!("pin set for artist {}", id),
+            (Message::PinSet(id), Locale::Spanish) => {
+                format!("pin establecido para el artista {}", id)
+            }
+            (Message::TokensRevoked(count), Locale::English) => {
+                format!("revoked {} token(s)", count)
+            }
+            (Message::TokensRevoked(count), Locale::Spanish) => {
+                format!("revocado(s) {} token(s)", count)
+            }
+            (Message::TokenRevoked, Locale::English) => "revoked".to_string(),
+            (Message::TokenRevoked, Locale::Spanish) => "revocado".to_string(),
+            (Message::NoSuchToken, Locale::English) => "no such token".to_string(),
+            (Message::NoSuchToken, Locale::Spanish) => "no existe ese token".to_string(),
+            (Message::NoHistory(id), Locale::English) => format!("no history for artist {}", id),
+            (Message::NoHistory(id), Locale::Spanish) => {
+                format!("el artista {} no tiene historial", id)
+            }
+            (Message::InteractiveHelp, Locale::English) => "\
+checkout <artist_id> <tool>...   lend tools to an artist
+return <artist_id> <tool>...     take tools back
+stock                            tools and paints on the shelf
+history <artist_id>              everything an artist has done
+audit [<auditor_id>]             reconcile history against stock
+undo <artist_id>                 reverse the artist's last checkout or return
+help                             this list
+quit                             leave
+Any daemon command (paint, damaged, repairs, sell, ...) works too.
+"
+            .to_string(),
+            (Message::InteractiveHelp, Locale::Spanish) => "\
+checkout <artist_id> <tool>...   presta herramientas a un artista
+return <artist_id> <tool>...     recoge herramientas
+stock                            herramientas y pinturas en la estantería
+history <artist_id>              todo lo que ha hecho un artista
+audit [<auditor_id>]             cuadra el historial con el stock
+undo <artist_id>                 deshace el último préstamo o devolución del artista
+help                             esta lista
+quit                             salir
+También funciona cualquier orden del demonio (paint, damaged, repairs, sell, ...).
+"
+            .to_string(),
+            (Message::Usage, Locale::English) => "Usage: rustic-canvas [--lang en|es] [--log-format text|json] [find <query> [--studio PATH] | init --template NAME [--out PATH] | daemon [--socket PATH] [--studio PATH] [--schedule JOBS.toml] [--dump-dir DIR] [--state-dir DIR [--checkpoint-every N] [--checkpoint-secs N]] [--manager-token TOKEN [--session-hours N]] [--tool-limits LIMITS.toml] [--rate-limit N/WINDOW] [--notify URL] [--loan-days N] [--costs COSTS.toml [--over-budget]] [--wear N [--retire-worn]] | serve [--addr HOST:PORT] [--studio PATH] [--tool-limits LIMITS.toml] [--rate-limit N/WINDOW] [--notify URL] [--simulate [--simulate-after SECS] [--artists N] [--rounds N] [--seed N]] | ctl [--socket PATH] <command> | ctl batch [--atomic] FILE | sync --from STATE --to STATE [--dry-run] [--fork NAME | --merge] | stocktake --state STATE [--counts CSV] [--yes] [--audit-log PATH] | timeline --state STATE --tool TOOL --serial N [--json] | experiment [--artists N] [--ops N] [--tools N] [--seed N] | bench [--artists N,...] [--rounds N] | simulate [SCENARIO] [--watch] [--studio PATH] [--studios PATH,... [--assign ID=NAME,...]] [--tool-limits LIMITS.toml] [--queue] [--rate-limit N/WINDOW] [--profiles PROFILES.toml] [--costs COSTS.toml [--over-budget]] [--drying-racks N [--drying-hours H]] [--speed X] [--artists N] [--tools-per-artist MIN..MAX] [--rounds N] [--import-csv SHEET.csv] [--stock ITEM=N,...] [--state STATE] [--db DB.sqlite] [--seed N] [--pool-size N] [--time-step SECS] [--strategy random|weighted|least-contended|round-robin] [--events LOG] [--dump-dir DIR] [--chaos RATES] [--fatigue] [--tui] [--threaded | --async | --actor] | replay LOG [--studio PATH] [--import-csv SHEET.csv] [--stock ITEM=N,...] | report [--studio PATH] [--import-csv SHEET.csv] [--stock ITEM=N,...] [--tool-limits LIMITS.toml] [--profiles PROFILES.toml] [--costs COSTS.toml [--over-budget]] [--drying-racks N [--drying-hours H]] [--history-csv PATH] [--inventory-csv PATH] [--out REPORT.md|REPORT.html [--format markdown|html]] [--db DB.sqlite] [--state STATE | [--artists N] [--rounds N] [--seed N] [--strategy NAME]] | script <file> [--speed X] | run-scenario SCENARIO.toml [--studio PATH] [--import-csv SHEET.csv] [--stock ITEM=N,...] [--tool-limits LIMITS.toml] [--queue] [--rate-limit N/WINDOW] [--events LOG] | interactive [--studio PATH] [--import-csv SHEET.csv] [--stock ITEM=N,...] [--tool-limits LIMITS.toml] [--state STATE] | replay-bench <trace> [--speed X] [--tolerance-ms N] | --record-trace <trace> | --studio PATH | --tool-limits LIMITS.toml]".to_string(),
+            (Message::Usage, Locale::Spanish) => "Uso: rustic-canvas [--lang en|es] [--log-format text|json] [find <consulta> [--studio RUTA] | init --template NOMBRE [--out RUTA] | daemon [--socket RUTA] [--studio RUTA] [--schedule TAREAS.toml] [--dump-dir DIR] [--state-dir DIR [--checkpoint-every N] [--checkpoint-secs N]] [--manager-token TOKEN [--session-hours N]] [--tool-limits LIMITES.toml] [--rate-limit N/VENTANA] [--notify URL] [--loan-days N] [--costs COSTES.toml [--over-budget]] [--wear N [--retire-worn]] | serve [--addr HOST:PUERTO] [--studio RUTA] [--tool-limits LIMITES.toml] [--rate-limit N/VENTANA] [--notify URL] [--simulate [--simulate-after SEGUNDOS] [--artists N] [--rounds N] [--seed N]] | ctl [--socket RUTA] <orden> | ctl batch [--atomic] FICHERO | sync --from ESTADO --to ESTADO [--dry-run] [--fork NOMBRE | --merge] | stocktake --state ESTADO [--counts CSV] [--yes] [--audit-log RUTA] | timeline --state ESTADO --tool HERRAMIENTA --serial N [--json] | experiment [--artists N] [--ops N] [--tools N] [--seed N] | bench [--artists N,...] [--rounds N] | simulate [ESCENARIO] [--watch] [--studio RUTA] [--studios RUTA,... [--assign ID=NOMBRE,...]] [--tool-limits LIMITES.toml] [--queue] [--rate-limit N/VENTANA] [--profiles PERFILES.toml] [--costs COSTES.toml [--over-budget]] [--drying-racks N [--drying-hours H]] [--speed X] [--artists N] [--tools-per-artist MIN..MAX] [--rounds N] [--import-csv HOJA.csv] [--stock ARTICULO=N,...] [--state ESTADO] [--db BD.sqlite] [--seed N] [--pool-size N] [--time-step SEGUNDOS] [--strategy random|weighted|least-contended|round-robin] [--events REGISTRO] [--dump-dir DIR] [--chaos TASAS] [--fatigue] [--tui] [--threaded | --async | --actor] | replay REGISTRO [--studio RUTA] [--import-csv HOJA.csv] [--stock ARTICULO=N,...] | report [--studio RUTA] [--import-csv HOJA.csv] [--stock ARTICULO=N,...] [--tool-limits LIMITES.toml] [--profiles PERFILES.toml] [--costs COSTES.toml [--over-budget]] [--drying-racks N [--drying-hours H]] [--history-csv RUTA] [--inventory-csv RUTA] [--out INFORME.md|INFORME.html [--format markdown|html]] [--db BD.sqlite] [--state ESTADO | [--artists N] [--rounds N] [--seed N] [--strategy NOMBRE]] | script <fichero> [--speed X] | run-scenario ESCENARIO.toml [--studio RUTA] [--import-csv HOJA.csv] [--stock ARTICULO=N,...] [--tool-limits LIMITES.toml] [--queue] [--rate-limit N/VENTANA] [--events REGISTRO] | interactive [--studio RUTA] [--import-csv HOJA.csv] [--stock ARTICULO=N,...] [--tool-limits LIMITES.toml] [--state ESTADO] | replay-bench <traza> [--speed X] [--tolerance-ms N] | --record-trace <traza> | --studio RUTA | --tool-limits LIMITES.toml]".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Message<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.render(locale()))
+    }
+}
+
+fn reason_label(reason: AdjustmentReason, locale: Locale) -> &'static str {
+    match (reason, locale) {
+        (AdjustmentReason::Shrinkage, Locale::English) => "shrinkage",
+        (AdjustmentReason::Miscount, Locale::English) => "miscount",
+        (AdjustmentReason::Shrinkage, Locale::Spanish) => "merma",
+        (AdjustmentReason::Miscount, Locale::Spanish) => "error de recuento",
+    }
+}
+
+fn metric_label(metric: Metric, locale: Locale) -> &'static str {
+    match (metric, locale) {
+        (Metric::Checkouts, Locale::English) => "checkouts",
+        (Metric::ItemsLent, Locale::English) => "items lent",
+        (Metric::ToolsInStock, Locale::English) => "tools in stock",
+        (Metric::PaintKg, Locale::English) => "paint (kg)",
+        (Metric::QueuedCheckouts, Locale::English) => "queued checkouts",
+        (Metric::RulesFired, Locale::English) => "rules fired",
+        (Metric::Artworks, Locale::English) => "artworks",
+        (Metric::Checkouts, Locale::Spanish) => "préstamos",
+        (Metric::ItemsLent, Locale::Spanish) => "artículos prestados",
+        (Metric::ToolsInStock, Locale::Spanish) => "herramientas en stock",
+        (Metric::PaintKg, Locale::Spanish) => "pintura (kg)",
+        (Metric::QueuedCheckouts, Locale::Spanish) => "préstamos en cola",
+        (Metric::RulesFired, Locale::Spanish) => "reglas disparadas",
+        (Metric::Artworks, Locale::Spanish) => "obras",
+    }
+}
+
+fn kind_label(kind: SearchKind, locale: Locale) -> &'static str {
+    match (kind, locale) {
+        (SearchKind::Tool, Locale::English) => "Tool",
+        (SearchKind::Paint, Locale::English) => "Paint",
+        (SearchKind::Artist, Locale::English) => "Artist",
+        (SearchKind::Tool, Locale::Spanish) => "Herramienta",
+        (SearchKind::Paint, Locale::Spanish) => "Pintura",
+        (SearchKind::Artist, Locale::Spanish) => "Artista",
+    }
+}
+
+fn stock_list(stock: &[(String, Count)]) -> String {
+    stock
+        .iter()
+        .map(|(item, quantity)| format!("{} {}", item, quantity))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// Who took out the units a merge found oversold.
+fn conflict_artists(item: &AuditItem) -> String {
+    item.takes
+        .iter()
+        .filter_map(|take| take.artist_id)
+        .map(|id| id.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locale_from_tag() {
+        assert_eq!(Locale::from_tag("es_ES.UTF-8"), Some(Locale::Spanish));
+        assert_eq!(Locale::from_tag("en-GB"), Some(Locale::English));
+        assert_eq!(Locale::from_tag("C"), Some(Locale::English));
+        assert_eq!(Locale::from_tag("fr_FR"), None);
+    }
+
+    #[test]
+    fn test_messages_render_per_locale() {
+        let message = Message::ToolNotFound("brush");
+        assert_eq!(
+            message.render(Locale::English),
+            "Warning: Tool 'brush' not found in resources."
+        );
+        assert!(message.render(Locale::Spanish).starts_with("Advertencia"));
+        assert_eq!(
+            Message::SearchHit(SearchKind::Paint, "red").render(Locale::Spanish),
+            "Pintura: red"
+        );
+        assert_eq!(
+            Message::ArtistHolds(3, 2).render(Locale::Spanish),
+            "el artista 3 tiene 2 artículo(s) nuevo(s)"
+        );
+        assert!(Message::InteractiveHelp
+            .render(Locale::Spanish)
+            .contains("esta lista"));
+    }
+}
@@ -0,0 +1,194 @@
+use crate::{
+    daemon::{self, error_reply, Reply},
+    i18n::Message,
+    lock_stats::REGISTRY_LOCK,
+    ArtistToolRegistry,
+};
+use std::{
+    fmt::Write as _,
+    io::{self, BufRead, Write},
+    sync::Mutex,
+};
+
+// Splits `brush sculpting tool tape` into tools, taking the longest run of
+// words that names something in stock each time, so tools with spaces in
+// their names need no quoting. Lists with commas are split on the commas.
+fn split_tools(registry: &ArtistToolRegistry, words: &str) -> Vec<String> {
+    if words.contains(',') {
+        return words
+            .split(',')
+            .map(str::trim)
+            .filter(|tool| !tool.is_empty())
+            .map(str::to_string)
+            .collect();
+    }
+    let resources = registry
+        .shared_resources
+        .lock()
+        .unwrap_or_else(|poisoned| registry.recovered(poisoned));
+    let words: Vec<&str> = words.split_whitespace().collect();
+    let mut tools = vec![];
+    let mut at = 0;
+    while at < words.len() {
+        let length = (1..=words.len() - at)
+            .rev()
+            .find(|&length| {
+                let name = words[at..at + length].join(" ");
+                resources.tools.iter().any(|tool| tool.name == name)
+            })
+            .unwrap_or(1);
+        tools.push(words[at..at + length].join(" "));
+        at += length;
+    }
+    tools
+}
+
+fn stock(registry: &ArtistToolRegistry) -> String {
+    let resources = registry
+        .shared_resources
+        .lock()
+        .unwrap_or_else(|poisoned| registry.recovered(poisoned));
+    let mut text = String::new();
+    for tool in resources.tools.iter() {
+        let _ = writeln!(text, "tool  {:<16} {}", tool.name, tool.quantity);
+    }
+    for paint in resources.paints.iter() {
+        let _ = writeln!(text, "paint {:<16} {} kg", paint.color, paint.weight_kg);
+    }
+    text
+}
+
+fn history(registry: &ArtistToolRegistry, artist_id: usize) -> String {
+    let mut text = String::new();
+    for entry in registry.history_for_artist(artist_id) {
+        let items: Vec<&str> = entry
+            .preferred_tools
+            .iter()
+            .chain(entry.paints.iter().map(|(symbol, _)| symbol))
+            .filter_map(|&symbol| registry.interner.resolve(symbol))
+            .collect();
+        let _ = writeln!(
+            text,
+            "{} {:<10} {}",
+            entry
+                .datetime
+                .map(|datetime| datetime.format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            entry
+                .state
+                .map(|state| format!("{:?}", state))
+                .unwrap_or_default(),
+            items.join(", ")
+        );
+    }
+    if text.is_empty() {
+        text = format!("{}\n", Message::NoHistory(artist_id));
+    }
+    text
+}
+
+pub enum Step {
+    Continue(String),
+    Quit,
+}
+
+// Runs one line typed at the prompt against the registry.
+pub fn execute(line: &str, registry: &Mutex<ArtistToolRegistry>) -> Step {
+    let line = line.trim();
+    let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+    let reply = match command {
+        "quit" | "exit" => return Step::Quit,
+        "help" | "?" => Message::InteractiveHelp.to_string(),
+        "stock" => stock(&REGISTRY_LOCK.lock(registry)),
+        "history" => match rest.trim().parse() {
+            Ok(artist_id) => history(&REGISTRY_LOCK.lock(registry), artist_id),
+            Err(_) => error_reply(Message::InvalidArtistId(rest.trim())),
+        },
+        "audit" => match rest.trim() {
+            "" => REGISTRY_LOCK.lock(registry).audit(0).to_string(),
+            id => match id.parse() {
+                Ok(auditor_id) => REGISTRY_LOCK.lock(registry).audit(auditor_id).to_string(),
+                Err(_) => error_reply(Message::InvalidAuditorId(id)),
+            },
+        },
+        // The daemon takes comma-separated tools; everything else is its
+        // syntax already.
+        "checkout" | "return" if !rest.trim_start().starts_with("atomic ") => {
+            let (id, words) = rest.trim().split_once(' ').unwrap_or((rest.trim(), ""));
+            let tools = split_tools(&REGISTRY_LOCK.lock(registry), words);
+            forward(
+                &format!("{} {} {}", command, id, tools.join(", ")),
+                registry,
+            )
+        }
+        "" => String::new(),
+        _ => forward(line, registry),
+    };
+    Step::Continue(reply)
+}
+
+fn forward(line: &str, registry: &Mutex<ArtistToolRegistry>) -> String {
+    match daemon::handle_command(line, registry, None) {
+        Reply::Continue(text) | Reply::Shutdown(text) => text,
+    }
+}
+
+// Reads commands from `input` until it ends or `quit` is typed, writing each
+// reply to `output` after a `> ` prompt.
+pub fn run(
+    registry: &Mutex<ArtistToolRegistry>,
+    input: impl BufRead,
+    mut output: impl Write,
+) -> io::Result<()> {
+    let mut lines = input.lines();
+    loop {
+        write!(output, "> ")?;
+        output.flush()?;
+        let Some(line) = lines.next().transpose()? else {
+            writeln!(output)?;
+            return Ok(());
+        };
+        match execute(&line, registry) {
+            Step::Continue(reply) => output.write_all(reply.as_bytes())?,
+            Step::Quit => return Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SharedResources;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_session_checks_out_returns_and_reports() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let registry = Mutex::new(ArtistToolRegistry::new(&resources));
+        let input = "checkout 3 brush sculpting tool\nreturn 3 brush\nhistory 3\nstock\naudit\nquit\nstock\n";
+        let mut output = vec![];
+        run(&registry, input.as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.contains("ok: artist 3 holds 2 new item(s)\n"));
+        assert!(output.contains("ok: artist 3 returned 1 item(s)\n"));
+        assert!(output.contains("TakeOut    brush, sculpting tool\n"));
+        assert!(output.contains(&format!(
+            "tool  {:<16} {}\n",
+            "sculpting tool",
+            resources.lock().unwrap().stock("sculpting tool")
+        )));
+        assert!(output.contains("on loan\n"));
+        // Nothing after `quit` runs.
+        assert_eq!(output.matches("tool  brush").count(), 1);
+        assert!(matches!(
+            execute("history x", &registry),
+            Step::Continue(reply) if reply.starts_with("error")
+        ));
+        assert!(matches!(
+            execute("undo 3", &registry),
+            Step::Continue(reply) if reply.starts_with("ok")
+        ));
+        assert_eq!(registry.lock().unwrap().held_tools(3).len(), 2);
+    }
+}
@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Index of an interned tool or paint name. Cheap to copy and compare.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Symbol(u32);
+
+// Symbol table so registry history and the event log store each name once
+// instead of cloning it into every entry.
+#[derive(Debug, Default, Clone)]
+pub struct Interner {
+    names: Vec<String>,
+    lookup: HashMap<String, Symbol>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn intern(&mut self, name: &str) -> Symbol {
+        if let Some(&symbol) = self.lookup.get(name) {
+            return symbol;
+        }
+        let symbol = Symbol(u32::try_from(self.names.len()).expect("too many interned names"));
+        self.names.push(name.to_string());
+        self.lookup.insert(name.to_string(), symbol);
+        symbol
+    }
+
+    pub fn get(&self, name: &str) -> Option<Symbol> {
+        self.lookup.get(name).copied()
+    }
+
+    // None for a symbol another interner handed out.
+    pub fn resolve(&self, symbol: Symbol) -> Option<&str> {
+        self.names.get(symbol.0 as usize).map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_intern_reuses_symbols() {
+        let mut interner = Interner::new();
+        let brush = interner.intern("brush");
+        let tape = interner.intern("tape");
+        assert_eq!(interner.intern("brush"), brush);
+        assert_ne!(brush, tape);
+        assert_eq!(interner.len(), 2);
+        assert_eq!(interner.resolve(tape), Some("tape"));
+        assert_eq!(interner.get("rags"), None);
+        assert_eq!(Interner::new().resolve(tape), None);
+    }
+}
@@ -0,0 +1,65 @@
+use signal_hook::{
+    consts::{SIGINT, SIGTERM},
+    flag,
+};
+use std::{
+    io,
+    os::raw::c_int,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, OnceLock,
+    },
+};
+
+// What the process exits with when a second signal cuts shutdown short.
+pub const FORCED_EXIT_CODE: i32 = 130;
+
+// Set once the process is asked to stop. Long-running loops check it between
+// steps, so a checkout in progress always completes before they give up.
+#[derive(Debug, Clone, Default)]
+pub struct Interrupt {
+    flag: Arc<AtomicBool>,
+}
+
+impl Interrupt {
+    // Sets the flag on SIGINT or SIGTERM. A second one ends the process
+    // straight away, for when stopping cleanly is taking too long.
+    pub fn install(&self) -> io::Result<()> {
+        self.install_on(&[SIGINT, SIGTERM])
+    }
+
+    fn install_on(&self, signals: &[c_int]) -> io::Result<()> {
+        for &signal in signals {
+            // Checked before the flag is set, so only a repeat signal exits.
+            flag::register_conditional_shutdown(signal, FORCED_EXIT_CODE, Arc::clone(&self.flag))?;
+            flag::register(signal, Arc::clone(&self.flag))?;
+        }
+        Ok(())
+    }
+
+    pub fn requested(&self) -> bool {
+        self.flag.load(Ordering::SeqCst)
+    }
+}
+
+// The one the process installs at startup and the simulations watch.
+pub fn global() -> &'static Interrupt {
+    static INTERRUPT: OnceLock<Interrupt> = OnceLock::new();
+    INTERRUPT.get_or_init(Interrupt::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use signal_hook::{consts::SIGUSR2, low_level};
+
+    #[test]
+    fn test_signal_sets_only_its_own_flag() {
+        let interrupt = Interrupt::default();
+        interrupt.install_on(&[SIGUSR2]).unwrap();
+        assert!(!interrupt.requested());
+        low_level::raise(SIGUSR2).unwrap();
+        assert!(interrupt.requested());
+        assert!(!global().requested());
+    }
+}
@@ -0,0 +1,353 @@
+use crate::{
+    error::{RegistryError, ResourceError, UnavailableTools},
+    events::EventLog,
+    units::Count,
+    ArtistToolRegistry, SharedResources,
+};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, MutexGuard, RwLock,
+    },
+    thread,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ToolStock {
+    pub quantity: usize,
+    pub on_loan: usize,
+    pub cap: Option<usize>,
+}
+
+impl ToolStock {
+    // `Err(true)` when the loan cap, rather than the stock, is what's short.
+    fn can_lend(&self, count: usize) -> Result<(), bool> {
+        if self.quantity < count {
+            Err(false)
+        } else if self.cap.is_some_and(|cap| self.on_loan + count > cap) {
+            Err(true)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+// Tool stock with a lock per tool, so checkouts of disjoint tools never wait
+// on each other. The outer lock is only taken for writing when a tool is
+// added; every checkout and return shares it. `version` counts changes, and
+// is bumped under the shard locks a change holds.
+#[derive(Debug, Default)]
+pub struct ShardedInventory {
+    shards: RwLock<BTreeMap<String, Mutex<ToolStock>>>,
+    version: AtomicU64,
+}
+
+// A shard whose shelf stock isn't what the journal last recorded for it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ShardDiscrepancy {
+    pub tool: String,
+    pub journal: usize,
+    pub shard: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ShardAudit {
+    // The inventory version every shard was read at.
+    pub version: u64,
+    pub shards: usize,
+    pub on_shelf: usize,
+    pub on_loan: usize,
+    pub discrepancies: Vec<ShardDiscrepancy>,
+}
+
+impl fmt::Display for ShardAudit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} shard(s) at version {}: {} on the shelf, {} on loan",
+            self.shards, self.version, self.on_shelf, self.on_loan
+        )?;
+        for discrepancy in &self.discrepancies {
+            writeln!(
+                f,
+                "! {}: journal says {}, shard holds {}",
+                discrepancy.tool, discrepancy.journal, discrepancy.shard
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl ShardedInventory {
+    pub fn from_resources(resources: &SharedResources) -> Self {
+        let inventory = Self::default();
+        for tool in resources.tools.iter() {
+            inventory.update(&tool.name, |stock| stock.quantity = tool.quantity.get());
+        }
+        // Tools whose every unit is out are no longer listed in `tools`.
+        for (tool, on_loan) in resources.loan_caps.loans() {
+            inventory.update(&tool, |stock| stock.on_loan = on_loan);
+        }
+        for (tool, _) in inventory.snapshot() {
+            let cap = resources.loan_caps.cap(&tool);
+            inventory.update(&tool, |stock| stock.cap = cap);
+        }
+        inventory
+    }
+
+    pub fn restock(&self, tool: &str, quantity: Count) {
+        self.update(tool, |stock| stock.quantity += quantity.get());
+    }
+
+    // Applies `change` to one tool's stock, adding the tool if it's new.
+    fn update(&self, tool: &str, change: impl FnOnce(&mut ToolStock)) {
+        {
+            let shards = self.shards.read().expect("Failed to lock inventory");
+            if let Some(shard) = shards.get(tool) {
+                change(&mut shard.lock().expect("Failed to lock shard"));
+                self.version.fetch_add(1, Ordering::SeqCst);
+                return;
+            }
+        }
+        let mut shards = self.shards.write().expect("Failed to lock inventory");
+        change(
+            shards
+                .entry(tool.to_string())
+                .or_default()
+                .get_mut()
+                .expect("Failed to lock shard"),
+        );
+        self.version.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub fn stock(&self, tool: &str) -> usize {
+        self.get(tool).map(|stock| stock.quantity).unwrap_or(0)
+    }
+
+    pub fn get(&self, tool: &str) -> Option<ToolStock> {
+        let shards = self.shards.read().expect("Failed to lock inventory");
+        let stock = *shards.get(tool)?.lock().expect("Failed to lock shard");
+        Some(stock)
+    }
+
+    // All-or-nothing, like `SharedResources::take_out_all`. Only the shards
+    // of the requested tools are locked, in name order so that two
+    // checkouts can't deadlock.
+    pub fn take_out_all(&self, tools: &[String]) -> Result<(), ResourceError> {
+        let shards = self.shards.read().expect("Failed to lock inventory");
+        let mut wanted: BTreeMap<&str, usize> = BTreeMap::new();
+        for tool in tools {
+            *wanted.entry(tool).or_insert(0) += 1;
+        }
+
+        let mut unavailable = UnavailableTools::default();
+        let mut guards: Vec<(MutexGuard<'_, ToolStock>, usize)> = vec![];
+        for (tool, count) in wanted {
+            let Some(shard) = shards.get(tool) else {
+                unavailable.missing.push(tool.to_string());
+                continue;
+            };
+            let stock = shard.lock().expect("Failed to lock shard");
+            match stock.can_lend(count) {
+                Ok(()) => guards.push((stock, count)),
+                Err(false) => unavailable.missing.push(tool.to_string()),
+                Err(true) => unavailable.capped.push(tool.to_string()),
+            }
+        }
+        if !unavailable.missing.is_empty() || !unavailable.capped.is_empty() {
+            return Err(ResourceError::Unavailable(unavailable));
+        }
+        for (stock, count) in &mut guards {
+            stock.quantity -= *count;
+            stock.on_loan += *count;
+        }
+        self.version.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    pub fn return_resources(&self, tools: &[String]) {
+        for tool in tools {
+            self.update(tool, |stock| {
+                stock.quantity += 1;
+                stock.on_loan = stock.on_loan.saturating_sub(1);
+            });
+        }
+    }
+
+    // A consistent view of every tool: all shards are held at once, so no
+    // checkout can land halfway through the snapshot.
+    pub fn snapshot(&self) -> Vec<(String, ToolStock)> {
+        self.versioned_snapshot().1
+    }
+
+    // The snapshot and the version it was taken at, read while every shard
+    // is held, so the two agree.
+    fn versioned_snapshot(&self) -> (u64, Vec<(String, ToolStock)>) {
+        let shards = self.shards.read().expect("Failed to lock inventory");
+        let guards: Vec<_> = shards
+            .iter()
+            .map(|(tool, shard)| (tool, shard.lock().expect("Failed to lock shard")))
+            .collect();
+        let version = self.version.load(Ordering::SeqCst);
+        let stock = guards
+            .iter()
+            .map(|(tool, stock)| (tool.to_string(), **stock))
+            .collect();
+        (version, stock)
+    }
+
+    // Checks every shard against the last shelf stock `journal` recorded for
+    // its tool. The shards are read at one version, then split between
+    // `workers` threads that each search the journal for their own tools;
+    // tools the journal never mentions aren't checked.
+    pub fn audit(&self, journal: &EventLog, workers: usize) -> ShardAudit {
+        let (version, shards) = self.versioned_snapshot();
+        let per_worker = shards.len().div_ceil(workers.max(1)).max(1);
+        let discrepancies = thread::scope(|scope| {
+            let workers: Vec<_> = shards
+                .chunks(per_worker)
+                .map(|chunk| scope.spawn(move || verify(chunk, journal)))
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().expect("Audit worker panicked"))
+                .collect()
+        });
+        ShardAudit {
+            version,
+            shards: shards.len(),
+            on_shelf: shards.iter().map(|(_, stock)| stock.quantity).sum(),
+            on_loan: shards.iter().map(|(_, stock)| stock.on_loan).sum(),
+            discrepancies,
+        }
+    }
+}
+
+fn verify(shards: &[(String, ToolStock)], journal: &EventLog) -> Vec<ShardDiscrepancy> {
+    shards
+        .iter()
+        .filter_map(|(tool, stock)| {
+            let symbol = journal.symbol(tool)?;
+            let recorded = journal
+                .events()
+                .iter()
+                .rev()
+                .find_map(|event| event.stock.get(&symbol))?
+                .count()?
+                .get();
+            (recorded != stock.quantity).then(|| ShardDiscrepancy {
+                tool: tool.clone(),
+                journal: recorded,
+                shard: stock.quantity,
+            })
+        })
+        .collect()
+}
+
+impl ArtistToolRegistry {
+    // Shards the live stock, read under one resources lock, and audits it
+    // against the event log, with a worker per available core.
+    pub fn audit_shards(&self) -> Result<ShardAudit, RegistryError> {
+        let inventory = {
+            let resources = self
+                .shared_resources
+                .lock()
+                .map_err(|poisoned| self.recover(poisoned))?;
+            ShardedInventory::from_resources(&resources)
+        };
+        let workers = thread::available_parallelism().map_or(1, usize::from);
+        Ok(inventory.audit(&self.events, workers))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::TOTAL_ITEMS;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_take_out_all_is_all_or_nothing() {
+        let mut resources = SharedResources::default();
+        resources.loan_caps.set_cap("easel", 1);
+        resources.restock("easel", Count(3)).unwrap();
+        let inventory = ShardedInventory::from_resources(&resources);
+        let tools = |names: &[&str]| {
+            names
+                .iter()
+                .map(|name| name.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        inventory.take_out_all(&tools(&["brush", "easel"])).unwrap();
+        assert_eq!(
+            inventory.take_out_all(&tools(&["brush", "easel", "kiln"])),
+            Err(ResourceError::Unavailable(UnavailableTools {
+                missing: vec!["kiln".to_string()],
+                capped: vec!["easel".to_string()],
+            }))
+        );
+        assert_eq!(inventory.stock("brush"), TOTAL_ITEMS.get() - 1);
+
+        inventory.return_resources(&tools(&["easel", "kiln"]));
+        assert_eq!(inventory.get("easel").unwrap().on_loan, 0);
+        assert_eq!(inventory.stock("kiln"), 1);
+    }
+
+    #[test]
+    fn test_concurrent_checkouts_keep_counts_consistent() {
+        let inventory = Arc::new(ShardedInventory::from_resources(&SharedResources::default()));
+        let handles: Vec<_> = ["brush", "tape", "rags", "brush"]
+            .into_iter()
+            .map(|tool| {
+                let inventory = Arc::clone(&inventory);
+                thread::spawn(move || {
+                    let tools = vec![tool.to_string(), "canvas".to_string()];
+                    for _ in 0..500 {
+                        if inventory.take_out_all(&tools).is_ok() {
+                            inventory.return_resources(&tools);
+                        }
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        for (_, stock) in inventory.snapshot() {
+            assert_eq!((stock.quantity, stock.on_loan), (TOTAL_ITEMS.get(), 0));
+        }
+    }
+
+    #[test]
+    fn test_audit_checks_every_shard_against_the_journal() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = crate::ArtistToolRegistry::new(&resources);
+        registry
+            .tool_registry(1, vec!["brush".to_string(), "tape".to_string()])
+            .unwrap();
+        registry.tool_return(1, vec!["tape".to_string()]).unwrap();
+        let audit = registry.audit_shards().unwrap();
+        assert!(audit.discrepancies.is_empty(), "{}", audit);
+        assert_eq!(audit.on_loan, 1);
+
+        // Stock that moved without going through the registry shows up, on
+        // whichever worker checked it.
+        let inventory = ShardedInventory::from_resources(&resources.lock().unwrap());
+        inventory.restock("brush", Count(2));
+        let before = inventory.audit(&registry.events, 1).version;
+        let audit = inventory.audit(&registry.events, 4);
+        assert_eq!(audit.version, before);
+        assert_eq!(
+            audit.discrepancies,
+            vec![ShardDiscrepancy {
+                tool: "brush".to_string(),
+                journal: TOTAL_ITEMS.get() - 1,
+                shard: TOTAL_ITEMS.get() + 1,
+            }]
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use crate::{
+    money::{ExchangeRates, Money, UnknownCurrency},
+    spreadsheet,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum LedgerEvent {
+    Purchase,
+    Sale,
+    Penalty,
+    DepositHeld,
+    DepositReleased,
+    Depreciation,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountCodes {
+    pub cash: String,
+    pub inventory: String,
+    pub deposits_held: String,
+    pub sales_revenue: String,
+    pub penalty_income: String,
+    pub depreciation_expense: String,
+    pub accumulated_depreciation: String,
+}
+
+impl Default for AccountCodes {
+    fn default() -> Self {
+        Self {
+            cash: "1000".to_string(),
+            inventory: "1200".to_string(),
+            accumulated_depreciation: "1290".to_string(),
+            deposits_held: "2100".to_string(),
+            sales_revenue: "4000".to_string(),
+            penalty_income: "4100".to_string(),
+            depreciation_expense: "5100".to_string(),
+        }
+    }
+}
+
+impl AccountCodes {
+    fn accounts_for(&self, event: LedgerEvent) -> (&str, &str) {
+        match event {
+            LedgerEvent::Purchase => (&self.inventory, &self.cash),
+            LedgerEvent::Sale => (&self.cash, &self.sales_revenue),
+            LedgerEvent::Penalty => (&self.deposits_held, &self.penalty_income),
+            LedgerEvent::DepositHeld => (&self.cash, &self.deposits_held),
+            LedgerEvent::DepositReleased => (&self.deposits_held, &self.cash),
+            LedgerEvent::Depreciation => {
+                (&self.depreciation_expense, &self.accumulated_depreciation)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LedgerEntry {
+    pub datetime: DateTime<Utc>,
+    pub event: LedgerEvent,
+    pub debit_account: String,
+    pub credit_account: String,
+    pub amount: Money,
+    pub base_amount: Money,
+    pub memo: String,
+}
+
+// Double-entry journal of every money-relevant event, in the order recorded.
+// Each entry keeps its original amount alongside the base-currency value.
+#[derive(Debug, Clone)]
+pub struct Ledger {
+    codes: AccountCodes,
+    rates: ExchangeRates,
+    entries: Vec<LedgerEntry>,
+}
+
+impl Ledger {
+    pub fn new(codes: AccountCodes, rates: ExchangeRates) -> Self {
+        Self {
+            codes,
+            rates,
+            entries: vec![],
+        }
+    }
+
+    pub fn rates(&self) -> &ExchangeRates {
+        &self.rates
+    }
+
+    // Entries already recorded keep the base amounts they were booked at.
+    pub fn set_rates(&mut self, rates: ExchangeRates) {
+        self.rates = rates;
+    }
+
+    pub fn record(
+        &mut self,
+        event: LedgerEvent,
+        amount: Money,
+        datetime: DateTime<Utc>,
+        memo: impl Into<String>,
+    ) -> Result<(), UnknownCurrency> {
+        let base_amount = self.rates.to_base(amount)?;
+        let (debit, credit) = self.codes.accounts_for(event);
+        self.entries.push(LedgerEntry {
+            datetime,
+            event,
+            debit_account: debit.to_string(),
+            credit_account: credit.to_string(),
+            amount,
+            base_amount,
+            memo: memo.into(),
+        });
+        Ok(())
+    }
+
+    pub fn entries(&self) -> &[LedgerEntry] {
+        &self.entries
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "datetime,event,debit_account,credit_account,amount,currency,base_amount,base_currency,memo\n",
+        );
+        for entry in &self.entries {
+            csv.push_str(&format!(
+                "{},{:?},{},{},{},{},{},{},{}\n",
+                entry.datetime.to_rfc3339(),
+                entry.event,
+                spreadsheet::field(&entry.debit_account),
+                spreadsheet::field(&entry.credit_account),
+                entry.amount.decimal(),
+                entry.amount.currency,
+                entry.base_amount.decimal(),
+                entry.base_amount.currency,
+                spreadsheet::field(&entry.memo),
+            ));
+        }
+        csv
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(&self.entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::money::Currency;
+
+    fn usd(minor_units: i64) -> Money {
+        Money::new(minor_units, Currency::USD)
+    }
+
+    #[test]
+    fn test_record_uses_configured_accounts() {
+        let codes = AccountCodes {
+            cash: "CASH".to_string(),
+            ..AccountCodes::default()
+        };
+        let mut ledger = Ledger::new(codes, ExchangeRates::new(Currency::USD));
+        ledger
+            .record(LedgerEvent::DepositHeld, usd(5_000), Utc::now(), "artist 1")
+            .unwrap();
+        ledger
+            .record(LedgerEvent::Purchase, usd(1_200), Utc::now(), "brushes")
+            .unwrap();
+
+        let entries = ledger.entries();
+        assert_eq!(entries[0].debit_account, "CASH");
+        assert_eq!(entries[0].credit_account, "2100");
+        assert_eq!(entries[1].debit_account, "1200");
+        assert_eq!(entries[1].credit_account, "CASH");
+    }
+
+    #[test]
+    fn test_export_csv_and_json_in_base_currency() {
+        let mut rates = ExchangeRates::new(Currency::USD);
+        rates.set_rate(Currency::EUR, 1.1);
+        let mut ledger = Ledger::new(AccountCodes::default(), rates);
+        let amount = Money::new(750, Currency::EUR);
+        ledger
+            .record(LedgerEvent::Penalty, amount, Utc::now(), "roller, damaged")
+            .unwrap();
+
+        let csv = ledger.to_csv();
+        let row = csv.lines().nth(1).unwrap();
+        assert!(row.contains(",Penalty,2100,4100,7.50,EUR,8.25,USD,\"roller, damaged\""));
+
+        let json: serde_json::Value = serde_json::from_str(&ledger.to_json().unwrap()).unwrap();
+        assert_eq!(json[0]["event"], "Penalty");
+        assert_eq!(json[0]["base_amount"]["minor_units"], 825);
+        assert_eq!(json[0]["base_amount"]["currency"], "USD");
+    }
+}
@@ -0,0 +1,95 @@
+// Inventory, checkout registry and studio simulation for shared art supplies.
+// The `rustic-canvas` binary is a command-line driver over this crate.
+
+pub mod actor;
+pub mod alerts;
+pub mod artwork;
+#[cfg(feature = "async")]
+pub mod async_simulation;
+pub mod audit;
+pub mod auth;
+pub mod batch;
+pub mod bench;
+pub mod blocking;
+pub mod budgets;
+pub mod catalog;
+pub mod chaos;
+pub mod checkpoint;
+pub mod clock;
+pub mod costs;
+pub mod crdt;
+pub mod daemon;
+pub mod deliveries;
+pub mod deposits;
+pub mod drying;
+pub mod dump;
+pub mod error;
+pub mod events;
+pub mod experiment;
+pub mod expiry;
+pub mod fairness;
+pub mod fatigue;
+pub mod history;
+pub mod holds;
+pub mod i18n;
+pub mod interactive;
+pub mod interner;
+pub mod interrupt;
+pub mod inventory;
+pub mod ledger;
+pub mod loan_caps;
+pub mod lock_stats;
+pub mod logging;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod mixing;
+pub mod money;
+pub mod names;
+pub mod observer;
+pub mod overdue;
+pub mod profiles;
+pub mod queueing;
+pub mod rate_limit;
+pub mod recovery;
+pub mod registry;
+pub mod reload;
+pub mod repairs;
+pub mod reservations;
+pub mod resources;
+pub mod rounds;
+pub mod run_report;
+pub mod sales;
+pub mod scenario;
+pub mod scheduler;
+pub mod script;
+pub mod search;
+pub mod segment_log;
+pub mod selection;
+pub mod serials;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod signal_dump;
+pub mod simulation;
+pub mod snapshot;
+pub mod spreadsheet;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod stats;
+pub mod stock;
+pub mod stocktake;
+pub mod studios;
+pub mod sync;
+pub mod templates;
+pub mod timeline;
+pub mod tool_limits;
+pub mod trace;
+#[cfg(feature = "tui")]
+pub mod tui;
+pub mod undo;
+pub mod units;
+pub mod watch;
+pub mod wear;
+pub mod worker_pool;
+
+pub use registry::{ArtistToolPreferences, ArtistToolRegistry, State};
+pub use resources::SharedResources;
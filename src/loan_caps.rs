@@ -0,0 +1,289 @@
+use crate::tool_limits::PriorityTier;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CapPolicy {
+    Fail,
+    Queue,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueuedCheckout {
+    pub artist_id: usize,
+    pub tool: String,
+    #[serde(default)]
+    pub priority: PriorityTier,
+    #[serde(default = "Utc::now")]
+    pub queued_at: DateTime<Utc>,
+}
+
+// Waiting raises a queued checkout's priority by one level per `step`, up to
+// `max_boost` levels. With a boost above the gap between the lowest and
+// highest tier, anyone who waits long enough gets ahead of new high-priority
+// arrivals, so nobody waits forever.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Aging {
+    pub step: Duration,
+    pub max_boost: u32,
+}
+
+impl Default for Aging {
+    fn default() -> Self {
+        Self {
+            step: Duration::minutes(5),
+            max_boost: 3,
+        }
+    }
+}
+
+impl Aging {
+    pub fn effective_priority(&self, queued: &QueuedCheckout, now: DateTime<Utc>) -> u32 {
+        let waited = (now - queued.queued_at).max(Duration::zero());
+        let steps = if self.step > Duration::zero() {
+            (waited.num_milliseconds() / self.step.num_milliseconds().max(1)) as u32
+        } else {
+            0
+        };
+        queued.priority.rank() + steps.min(self.max_boost)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WaitStats {
+    pub served: usize,
+    pub avg_wait: Duration,
+    pub max_wait: Duration,
+    pub waiting: usize,
+    // How long the longest-waiting checkout still in the queue has waited.
+    pub oldest_waiting: Duration,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct WaitTotals {
+    served: usize,
+    total: Duration,
+    max: Duration,
+}
+
+// Limits how many units of a tool may be on loan at once, independent of how
+// many are in stock, so a few can be kept back for walk-ins.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoanCaps {
+    pub policy: CapPolicy,
+    caps: HashMap<String, usize>,
+    on_loan: HashMap<String, usize>,
+    queue: VecDeque<QueuedCheckout>,
+    aging: Aging,
+    waits: BTreeMap<PriorityTier, WaitTotals>,
+}
+
+impl LoanCaps {
+    pub fn new(policy: CapPolicy) -> Self {
+        Self {
+            policy,
+            caps: HashMap::new(),
+            on_loan: HashMap::new(),
+            queue: VecDeque::new(),
+            aging: Aging::default(),
+            waits: BTreeMap::new(),
+        }
+    }
+
+    pub fn set_aging(&mut self, aging: Aging) {
+        self.aging = aging;
+    }
+
+    pub fn set_cap(&mut self, tool: &str, cap: usize) {
+        self.caps.insert(tool.to_string(), cap);
+    }
+
+    pub fn cap(&self, tool: &str) -> Option<usize> {
+        self.caps.get(tool).copied()
+    }
+
+    pub fn on_loan(&self, tool: &str) -> usize {
+        self.on_loan.get(tool).copied().unwrap_or(0)
+    }
+
+    // Counts one more unit as lent out, or returns false if the cap is reached.
+    pub fn try_lend(&mut self, tool: &str) -> bool {
+        let on_loan = self.on_loan(tool);
+        if self.cap(tool).is_some_and(|cap| on_loan >= cap) {
+            return false;
+        }
+        self.on_loan.insert(tool.to_string(), on_loan + 1);
+        true
+    }
+
+    // Records a checkout that hit the cap. Returns true if it was queued.
+    pub fn defer(
+        &mut self,
+        artist_id: usize,
+        tool: &str,
+        priority: PriorityTier,
+        now: DateTime<Utc>,
+    ) -> bool {
+        match self.policy {
+            CapPolicy::Fail => false,
+            CapPolicy::Queue => {
+                self.queue.push_back(QueuedCheckout {
+                    artist_id,
+                    tool: tool.to_string(),
+                    priority,
+                    queued_at: now,
+                });
+                true
+            }
+        }
+    }
+
+    // Gives a unit back and hands its slot to the queued checkout for it with
+    // the highest effective priority, the earliest one on a tie.
+    pub fn release(&mut self, tool: &str, now: DateTime<Utc>) -> Option<QueuedCheckout> {
+        if let Some(count) = self.on_loan.get_mut(tool) {
+            *count = count.saturating_sub(1);
+        }
+        let pos = self
+            .queue
+            .iter()
+            .enumerate()
+            .filter(|(_, queued)| queued.tool == tool)
+            .max_by_key(|(pos, queued)| {
+                (
+                    self.aging.effective_priority(queued, now),
+                    std::cmp::Reverse(*pos),
+                )
+            })
+            .map(|(pos, _)| pos)?;
+        let next = self.queue.remove(pos)?;
+        self.try_lend(tool);
+
+        let waited = (now - next.queued_at).max(Duration::zero());
+        let totals = self.waits.entry(next.priority).or_default();
+        totals.served += 1;
+        totals.total += waited;
+        totals.max = totals.max.max(waited);
+        Some(next)
+    }
+
+    // Per tier: how long served checkouts waited and how long the ones still
+    // queued have been waiting so far.
+    pub fn wait_stats(&self, now: DateTime<Utc>) -> BTreeMap<PriorityTier, WaitStats> {
+        let mut tiers: BTreeMap<PriorityTier, WaitStats> = self
+            .waits
+            .iter()
+            .map(|(tier, totals)| {
+                (
+                    *tier,
+                    WaitStats {
+                        served: totals.served,
+                        avg_wait: totals.total / totals.served.max(1) as i32,
+                        max_wait: totals.max,
+                        waiting: 0,
+                        oldest_waiting: Duration::zero(),
+                    },
+                )
+            })
+            .collect();
+        for queued in &self.queue {
+            let stats = tiers.entry(queued.priority).or_insert(WaitStats {
+                served: 0,
+                avg_wait: Duration::zero(),
+                max_wait: Duration::zero(),
+                waiting: 0,
+                oldest_waiting: Duration::zero(),
+            });
+            stats.waiting += 1;
+            stats.oldest_waiting = stats
+                .oldest_waiting
+                .max((now - queued.queued_at).max(Duration::zero()));
+        }
+        tiers
+    }
+
+    pub fn queued(&self) -> impl Iterator<Item = &QueuedCheckout> {
+        self.queue.iter()
+    }
+
+    // Used when restoring saved state; bypasses the cap and the queue policy.
+    pub fn set_on_loan(&mut self, tool: &str, count: usize) {
+        self.on_loan.insert(tool.to_string(), count);
+    }
+
+    pub fn enqueue(&mut self, queued: QueuedCheckout) {
+        self.queue.push_back(queued);
+    }
+
+    // Units currently lent out per tool, sorted by name.
+    pub fn loans(&self) -> Vec<(String, usize)> {
+        let mut loans: Vec<_> = self
+            .on_loan
+            .iter()
+            .filter(|(_, &count)| count > 0)
+            .map(|(tool, &count)| (tool.clone(), count))
+            .collect();
+        loans.sort();
+        loans
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_try_lend_respects_cap() {
+        let mut caps = LoanCaps::new(CapPolicy::Fail);
+        caps.set_cap("canvas", 1);
+        assert!(caps.try_lend("canvas"));
+        assert!(!caps.try_lend("canvas"));
+        assert!(caps.try_lend("brush"));
+        assert!(!caps.defer(1, "canvas", PriorityTier::Normal, Utc::now()));
+        assert_eq!(caps.queued().count(), 0);
+    }
+
+    #[test]
+    fn test_release_serves_queue_in_order() {
+        let mut caps = LoanCaps::new(CapPolicy::Queue);
+        caps.set_cap("canvas", 1);
+        assert!(caps.try_lend("canvas"));
+        let now = Utc::now();
+        assert!(caps.defer(1, "canvas", PriorityTier::Normal, now));
+        assert!(caps.defer(2, "canvas", PriorityTier::Normal, now));
+
+        let next = caps.release("canvas", now).unwrap();
+        assert_eq!(next.artist_id, 1);
+        assert_eq!(caps.on_loan("canvas"), 1);
+        assert_eq!(caps.queued().count(), 1);
+    }
+
+    #[test]
+    fn test_aging_lets_long_waits_pass_new_high_priority() {
+        let mut caps = LoanCaps::new(CapPolicy::Queue);
+        caps.set_cap("canvas", 1);
+        assert!(caps.try_lend("canvas"));
+        let start = Utc::now();
+        let minutes = Duration::minutes;
+        caps.defer(1, "canvas", PriorityTier::Low, start);
+        caps.defer(2, "canvas", PriorityTier::High, start + minutes(4));
+
+        // Four minutes in, the high-priority arrival still goes first.
+        let next = caps.release("canvas", start + minutes(4)).unwrap();
+        assert_eq!(next.artist_id, 2);
+
+        // After fifteen minutes the low-priority wait is boosted by three
+        // levels, past the two separating it from a fresh high arrival.
+        caps.defer(3, "canvas", PriorityTier::High, start + minutes(15));
+        let next = caps.release("canvas", start + minutes(15)).unwrap();
+        assert_eq!(next.artist_id, 1);
+
+        let stats = caps.wait_stats(start + minutes(20));
+        assert_eq!(stats[&PriorityTier::Low].served, 1);
+        assert_eq!(stats[&PriorityTier::Low].max_wait, minutes(15));
+        let high = stats[&PriorityTier::High];
+        assert_eq!((high.served, high.waiting), (1, 1));
+        assert_eq!(high.oldest_waiting, minutes(5));
+    }
+}
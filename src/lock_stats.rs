@@ -0,0 +1,113 @@
+use serde::Serialize;
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, MutexGuard, TryLockError,
+    },
+    time::Instant,
+};
+
+// Counts how often a lock is taken, how often callers had to wait for it, and
+// for how long.
+#[derive(Debug, Default)]
+pub struct LockStats {
+    acquisitions: AtomicU64,
+    contended: AtomicU64,
+    total_wait_ns: AtomicU64,
+    max_wait_ns: AtomicU64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct LockSnapshot {
+    pub acquisitions: u64,
+    pub contended: u64,
+    pub total_wait_ns: u64,
+    pub max_wait_ns: u64,
+}
+
+// The registry lock taken by daemon commands and scheduled jobs.
+pub static REGISTRY_LOCK: LockStats = LockStats::new();
+
+impl LockStats {
+    pub const fn new() -> Self {
+        Self {
+            acquisitions: AtomicU64::new(0),
+            contended: AtomicU64::new(0),
+            total_wait_ns: AtomicU64::new(0),
+            max_wait_ns: AtomicU64::new(0),
+        }
+    }
+
+    // A lock poisoned by a panicking holder is taken anyway: the dump and
+    // the signal handler go through here and must still be able to report.
+    pub fn lock<'a, T>(&self, mutex: &'a Mutex<T>) -> MutexGuard<'a, T> {
+        self.acquisitions.fetch_add(1, Ordering::Relaxed);
+        match mutex.try_lock() {
+            Ok(guard) => return guard,
+            Err(TryLockError::Poisoned(poisoned)) => return poisoned.into_inner(),
+            Err(TryLockError::WouldBlock) => {}
+        }
+        let started = Instant::now();
+        let guard = mutex
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let waited = u64::try_from(started.elapsed().as_nanos()).unwrap_or(u64::MAX);
+        self.contended.fetch_add(1, Ordering::Relaxed);
+        self.total_wait_ns.fetch_add(waited, Ordering::Relaxed);
+        self.max_wait_ns.fetch_max(waited, Ordering::Relaxed);
+        guard
+    }
+
+    pub fn snapshot(&self) -> LockSnapshot {
+        LockSnapshot {
+            acquisitions: self.acquisitions.load(Ordering::Relaxed),
+            contended: self.contended.load(Ordering::Relaxed),
+            total_wait_ns: self.total_wait_ns.load(Ordering::Relaxed),
+            max_wait_ns: self.max_wait_ns.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{sync::Arc, thread, time::Duration};
+
+    #[test]
+    fn test_lock_counts_contention() {
+        let stats = Arc::new(LockStats::new());
+        let mutex = Arc::new(Mutex::new(0));
+
+        let held = stats.lock(&mutex);
+        let waiter = {
+            let (stats, mutex) = (Arc::clone(&stats), Arc::clone(&mutex));
+            thread::spawn(move || *stats.lock(&mutex) += 1)
+        };
+        thread::sleep(Duration::from_millis(20));
+        drop(held);
+        waiter.join().unwrap();
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.acquisitions, 2);
+        assert_eq!(snapshot.contended, 1);
+        assert!(snapshot.max_wait_ns > 0);
+        assert_eq!(*mutex.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_lock_takes_a_poisoned_mutex() {
+        let stats = LockStats::new();
+        let mutex = Arc::new(Mutex::new(0));
+        let poisoner = Arc::clone(&mutex);
+        let _ = thread::spawn(move || {
+            let _guard = poisoner.lock().unwrap();
+            panic!("poison the lock");
+        })
+        .join();
+        assert!(mutex.is_poisoned());
+
+        *stats.lock(&mutex) += 1;
+        assert_eq!(*stats.lock(&mutex), 1);
+        assert_eq!(stats.snapshot().contended, 0);
+    }
+}
@@ -0,0 +1,307 @@
+use crate::{i18n::Message, SharedResources};
+use chrono::Utc;
+use serde_json::{Map, Value};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, PoisonError, RwLock,
+    },
+};
+use tracing::{
+    field::{Field, Visit},
+    level_filters::LevelFilter,
+    span,
+    subscriber::Interest,
+    Event, Metadata, Subscriber,
+};
+
+// The most detailed level written, everything until the studio file's
+// `log_level` says otherwise; a running daemon may change it.
+static LEVEL: RwLock<LevelFilter> = RwLock::new(LevelFilter::TRACE);
+
+pub fn set_level(level: LevelFilter) {
+    *LEVEL.write().unwrap_or_else(PoisonError::into_inner) = level;
+}
+
+pub fn level() -> LevelFilter {
+    *LEVEL.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    // Just the message, as the commands have always printed it.
+    #[default]
+    Text,
+    // One JSON object per line, with the event's fields and its span's.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("unknown log format '{}'", text)),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Fields(Map<String, Value>);
+
+impl Visit for Fields {
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{:?}", value).into());
+    }
+}
+
+struct SpanData {
+    name: &'static str,
+    fields: Fields,
+    references: usize,
+}
+
+thread_local! {
+    // Spans entered on this thread, innermost last.
+    static ENTERED: RefCell<Vec<u64>> = const { RefCell::new(vec![]) };
+}
+
+// Writes every event as a line to `sink`: the message alone in text, or a
+// JSON object with the level, time, innermost span and every field.
+pub struct StudioSubscriber {
+    format: LogFormat,
+    sink: Box<dyn Fn(&str) + Send + Sync>,
+    spans: Mutex<HashMap<u64, SpanData>>,
+    next_id: AtomicU64,
+}
+
+impl StudioSubscriber {
+    pub fn new(format: LogFormat, sink: impl Fn(&str) + Send + Sync + 'static) -> Self {
+        Self {
+            format,
+            sink: Box::new(sink),
+            spans: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    pub fn stdout(format: LogFormat) -> Self {
+        Self::new(format, |line| println!("{}", line))
+    }
+
+    fn line(&self, event: &Event<'_>) -> String {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let message = match fields.0.remove("message") {
+            Some(Value::String(message)) => message,
+            Some(other) => other.to_string(),
+            None => String::new(),
+        };
+        if self.format == LogFormat::Text {
+            return message;
+        }
+        let mut line = Map::new();
+        line.insert("timestamp".to_string(), Utc::now().to_rfc3339().into());
+        line.insert(
+            "level".to_string(),
+            event.metadata().level().as_str().into(),
+        );
+        let current = ENTERED.with(|entered| entered.borrow().last().copied());
+        let spans = self.spans.lock().expect("Failed to lock spans");
+        if let Some(span) = current.and_then(|id| spans.get(&id)) {
+            let mut described = span.fields.0.clone();
+            described.insert("name".to_string(), span.name.into());
+            line.insert("span".to_string(), Value::Object(described));
+        }
+        line.insert("message".to_string(), message.into());
+        line.extend(fields.0);
+        Value::Object(line).to_string()
+    }
+}
+
+impl Subscriber for StudioSubscriber {
+    // Asked again for every event, since the level can change.
+    fn register_callsite(&self, _: &'static Metadata<'static>) -> Interest {
+        Interest::sometimes()
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        *metadata.level() <= level()
+    }
+
+    fn new_span(&self, attributes: &span::Attributes<'_>) -> span::Id {
+        let mut fields = Fields::default();
+        attributes.record(&mut fields);
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.spans.lock().expect("Failed to lock spans").insert(
+            id,
+            SpanData {
+                name: attributes.metadata().name(),
+                fields,
+                references: 1,
+            },
+        );
+        span::Id::from_u64(id)
+    }
+
+    fn record(&self, id: &span::Id, values: &span::Record<'_>) {
+        if let Some(span) = self
+            .spans
+            .lock()
+            .expect("Failed to lock spans")
+            .get_mut(&id.into_u64())
+        {
+            values.record(&mut span.fields);
+        }
+    }
+
+    fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        (self.sink)(&self.line(event));
+    }
+
+    fn enter(&self, id: &span::Id) {
+        ENTERED.with(|entered| entered.borrow_mut().push(id.into_u64()));
+    }
+
+    fn exit(&self, id: &span::Id) {
+        ENTERED.with(|entered| {
+            let mut entered = entered.borrow_mut();
+            if let Some(at) = entered.iter().rposition(|&span| span == id.into_u64()) {
+                entered.remove(at);
+            }
+        });
+    }
+
+    fn clone_span(&self, id: &span::Id) -> span::Id {
+        if let Some(span) = self
+            .spans
+            .lock()
+            .expect("Failed to lock spans")
+            .get_mut(&id.into_u64())
+        {
+            span.references += 1;
+        }
+        id.clone()
+    }
+
+    fn try_close(&self, id: span::Id) -> bool {
+        let mut spans = self.spans.lock().expect("Failed to lock spans");
+        let Some(span) = spans.get_mut(&id.into_u64()) else {
+            return false;
+        };
+        span.references -= 1;
+        if span.references > 0 {
+            return false;
+        }
+        spans.remove(&id.into_u64());
+        true
+    }
+}
+
+// Sends every event from here on to stdout in `format`. Only the first call
+// has any effect.
+pub fn init(format: LogFormat) {
+    let _ = tracing::subscriber::set_global_default(StudioSubscriber::stdout(format));
+}
+
+// Shelf stock of each tool, as `brush=9,palette=4`.
+fn remaining(tools: &[String], resources: &SharedResources) -> String {
+    let mut seen: Vec<&str> = vec![];
+    for tool in tools {
+        if !seen.contains(&tool.as_str()) {
+            seen.push(tool);
+        }
+    }
+    seen.iter()
+        .map(|tool| format!("{}={}", tool, resources.stock(tool)))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+pub fn checked_out(artist_id: usize, tools: &[String], resources: &SharedResources) {
+    tracing::info!(
+        artist_id,
+        tools = %tools.join(","),
+        remaining = %remaining(tools, resources),
+        "{}",
+        Message::CheckedOut(artist_id, tools)
+    );
+}
+
+pub fn returned(artist_id: usize, tools: &[String], resources: &SharedResources) {
+    tracing::info!(
+        artist_id,
+        tools = %tools.join(","),
+        remaining = %remaining(tools, resources),
+        "{}",
+        Message::Returned(artist_id, tools)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn capture(format: LogFormat, run: impl FnOnce()) -> Vec<String> {
+        let lines = Arc::new(Mutex::new(vec![]));
+        let sink = Arc::clone(&lines);
+        let subscriber = StudioSubscriber::new(format, move |line| {
+            sink.lock().unwrap().push(line.to_string())
+        });
+        tracing::subscriber::with_default(subscriber, run);
+        let lines = lines.lock().unwrap().clone();
+        lines
+    }
+
+    #[test]
+    fn test_events_carry_their_fields_and_span() {
+        let resources = SharedResources::default();
+        let tools = vec!["brush".to_string(), "brush".to_string()];
+        let lines = capture(LogFormat::Json, || {
+            let _task = tracing::info_span!("artist_task", artist_id = 3).entered();
+            checked_out(3, &tools, &resources);
+        });
+        let line: Value = serde_json::from_str(&lines[0]).unwrap();
+        assert_eq!(line["level"], "INFO");
+        assert_eq!(line["span"]["name"], "artist_task");
+        assert_eq!(line["span"]["artist_id"], 3);
+        assert_eq!(line["artist_id"], 3);
+        assert_eq!(line["tools"], "brush,brush");
+        assert_eq!(
+            line["remaining"],
+            format!("brush={}", resources.stock("brush"))
+        );
+        assert_eq!(line["message"], Message::CheckedOut(3, &tools).to_string());
+
+        let lines = capture(LogFormat::Text, || returned(3, &tools, &resources));
+        assert_eq!(lines, vec![Message::Returned(3, &tools).to_string()]);
+        assert_eq!("json".parse(), Ok(LogFormat::Json));
+        assert!("yaml".parse::<LogFormat>().is_err());
+    }
+}
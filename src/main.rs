@@ -1,225 +1,1680 @@
-use chrono::{DateTime, Utc};
-use rand::{seq::SliceRandom, thread_rng, Rng};
+use chrono::Utc;
+use clap::{
+    error::{ContextKind, ContextValue, ErrorKind},
+    value_parser, Arg, ArgAction, ArgMatches, Command,
+};
+use rustic_canvas::{
+    alerts, auth, batch,
+    bench::{self, BenchConfig},
+    chaos::Chaos,
+    checkpoint,
+    costs::CostBook,
+    daemon, dump,
+    error::RegistryError,
+    events,
+    experiment::{self, ExperimentConfig},
+    fatigue::FatigueModel,
+    i18n::{self, Locale, Message},
+    interactive, interrupt,
+    loan_caps::CapPolicy,
+    logging::{self, LogFormat},
+    money::{MixedCurrencies, UnknownCurrency},
+    profiles::Profiles,
+    rate_limit::RateLimiter,
+    reload, rounds,
+    run_report::{ReportFormat, RunReport},
+    sales::Pricing,
+    scenario::Scenario,
+    scheduler, script,
+    selection::Strategy,
+    signal_dump, simulation,
+    stats::Stats,
+    stocktake,
+    studios::{Studio, Studios},
+    sync, templates,
+    tool_limits::{ToolCountRange, ToolLimits},
+    trace::{self, Trace},
+    units::Amount,
+    watch,
+    wear::{WearPolicy, WornOut},
+    ArtistToolRegistry, SharedResources,
+};
 use std::{
+    env,
+    io::{self, Write},
+    path::Path,
+    process,
     sync::{Arc, Mutex},
     thread,
+    time::Duration,
 };
 
-const TOTAL_ARTISTS: usize = 1;
-const TOTAL_ITEMS: usize = 10;
-const TOTAL_WEIGHT_KG: usize = 10;
-const MIN_REQUIRED_TOOLS: usize = 2;
-const MAX_ALLOWED_TOOLS: usize = 5;
-
-#[derive(Debug)]
-struct SharedResources {
-    tools: Vec<(String, usize)>,
-    paints: Vec<(String, usize)>,
-}
-
-impl SharedResources {
-    fn default() -> Self {
-        Self {
-            tools: vec![
-                ("brush".to_string(), TOTAL_ITEMS),
-                ("palette".to_string(), TOTAL_ITEMS),
-                ("canvas".to_string(), TOTAL_ITEMS),
-                ("eraser".to_string(), TOTAL_ITEMS),
-                ("sponges".to_string(), TOTAL_ITEMS),
-                ("roller".to_string(), TOTAL_ITEMS),
-                ("sculpting tool".to_string(), TOTAL_ITEMS),
-                ("water container".to_string(), TOTAL_ITEMS),
-                ("rags".to_string(), TOTAL_ITEMS),
-                ("tape".to_string(), TOTAL_ITEMS),
-            ],
-            paints: vec![
-                ("red".to_string(), TOTAL_WEIGHT_KG),
-                ("blue".to_string(), TOTAL_WEIGHT_KG),
-                ("green".to_string(), TOTAL_WEIGHT_KG),
-                ("yellow".to_string(), TOTAL_WEIGHT_KG),
-                ("black".to_string(), TOTAL_WEIGHT_KG),
-                ("white".to_string(), TOTAL_WEIGHT_KG),
-                ("purple".to_string(), TOTAL_WEIGHT_KG),
-                ("orange".to_string(), TOTAL_WEIGHT_KG),
-                ("pink".to_string(), TOTAL_WEIGHT_KG),
-                ("brown".to_string(), TOTAL_WEIGHT_KG),
-            ],
-        }
-    }
-
-    fn take_out_resources(&mut self, tools: Vec<String>) {
-        for tool in tools {
-            if let Some(pos) = self.tools.iter().position(|(name, _)| *name == tool) {
-                let (_, quantity) = &mut self.tools[pos];
-                *quantity -= 1;
-                if *quantity == 0 {
-                    self.tools.remove(pos);
+fn main() {
+    let resources = SharedResources::default();
+    let shared_resources = Arc::new(Mutex::new(resources));
+    let artist_tool_registry = Arc::new(Mutex::new(ArtistToolRegistry::new(&shared_resources)));
+
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    // `--lang es` picks the language over LANG and the studio file.
+    if let Some(at) = args.iter().position(|arg| arg == "--lang") {
+        let value = args.get(at + 1).cloned().unwrap_or_default();
+        match Locale::from_tag(&value) {
+            Some(locale) => {
+                i18n::pin_locale(locale);
+            }
+            None => {
+                println!("{}", Message::InvalidFlag("--lang", &value));
+                process::exit(2);
+            }
+        }
+        args.drain(at..(at + 2).min(args.len()));
+    }
+    // Taken out before the command is read, so every command logs the same
+    // way.
+    let mut log_format = LogFormat::default();
+    if let Some(at) = args.iter().position(|arg| arg == "--log-format") {
+        let value = args.get(at + 1).cloned().unwrap_or_default();
+        match value.parse() {
+            Ok(format) => log_format = format,
+            Err(_) => {
+                println!("{}", Message::InvalidFlag("--log-format", &value));
+                process::exit(2);
+            }
+        }
+        args.drain(at..(at + 2).min(args.len()));
+    }
+    logging::init(log_format);
+
+    if let Some((command, query)) = args.split_first() {
+        if command == "find" {
+            // Categories and tags come from the studio file, as they do for
+            // the HTTP and dashboard search.
+            if !load_studio(query, &artist_tool_registry) {
+                return;
+            }
+            let mut words = Vec::new();
+            let mut iter = query.iter();
+            while let Some(word) = iter.next() {
+                if word == "--studio" {
+                    iter.next();
+                } else {
+                    words.push(word.as_str());
+                }
+            }
+            let registry = artist_tool_registry
+                .lock()
+                .expect("Failed to lock registry");
+            for hit in registry.search(&words.join(" ")) {
+                println!("{}", Message::SearchHit(hit.kind, &hit.name));
+            }
+            return;
+        }
+        if command == "bench" {
+            run_bench(query);
+            return;
+        }
+        if command == "experiment" {
+            run_experiment(query);
+            return;
+        }
+        if command == "init" {
+            run_init(query);
+            return;
+        }
+        if command == "daemon" {
+            run_daemon(query, &shared_resources, &artist_tool_registry);
+            return;
+        }
+        if command == "serve" {
+            run_serve(query, &shared_resources, &artist_tool_registry);
+            return;
+        }
+        if command == "sync" {
+            run_sync(query);
+            return;
+        }
+        if command == "stocktake" {
+            run_stocktake(query);
+            return;
+        }
+        if command == "timeline" {
+            run_timeline(query);
+            return;
+        }
+        if command == "simulate" {
+            run_simulate(query);
+            return;
+        }
+        if command == "run-scenario" {
+            run_scenario(query);
+            return;
+        }
+        if command == "script" {
+            run_script(query, &artist_tool_registry);
+            return;
+        }
+        if command == "ctl" {
+            run_ctl(query);
+            return;
+        }
+        if command == "replay" {
+            run_replay(query);
+            return;
+        }
+        if command == "report" {
+            run_report(query);
+            return;
+        }
+        if command == "interactive" {
+            run_interactive(query);
+            return;
+        }
+        if command == "replay-bench" {
+            run_replay_bench(query);
+            return;
+        }
+        if command == "help" || command == "--help" {
+            println!("{}", Message::Usage);
+            return;
+        }
+    }
+
+    if !load_studio(&args, &artist_tool_registry) || !load_tool_limits(&args, &artist_tool_registry)
+    {
+        return;
+    }
+    let (queue_stats, errors) = simulation::run_artists(
+        &shared_resources,
+        &artist_tool_registry,
+        &simulation::SimulationConfig::default(),
+    );
+    for error in &errors {
+        println!("{}", Message::CheckoutFailed(error));
+    }
+
+    println!("{}", Message::QueueSummaryHeader);
+    for metrics in queue_stats
+        .lock()
+        .expect("Failed to lock queue stats")
+        .summary()
+    {
+        println!(
+            "{:<16} {:>5} {:>10.3} {:>10.3} {:>6.2} {:>10.1} {:>8.4}",
+            metrics.tool,
+            metrics.requests,
+            metrics.avg_wait_ms,
+            metrics.p95_wait_ms,
+            metrics.utilization,
+            metrics.throughput_per_sec,
+            metrics.littles_law_error(),
+        );
+    }
+
+    if let Some(path) = flag_value::<String>(&args, "--record-trace") {
+        let registry = artist_tool_registry
+            .lock()
+            .expect("Failed to lock registry");
+        match Trace::from_registry(&registry).save(&path) {
+            Ok(()) => println!("{}", Message::TraceSaved(&path)),
+            Err(error) => println!("{}", Message::FileError(&path, error.to_string())),
+        }
+    }
+
+    println!("{}", Message::Finished);
+}
+
+fn run_init(args: &[String]) {
+    let Some(name) = flag_value::<String>(args, "--template") else {
+        println!("{}", Message::Usage);
+        return;
+    };
+    let Some(studio) = templates::template(&name) else {
+        println!("{}", Message::UnknownTemplate(&name));
+        return;
+    };
+    let out = flag_value(args, "--out").unwrap_or(templates::DEFAULT_STUDIO.to_string());
+    match studio
+        .to_toml()
+        .map_err(|error| error.to_string())
+        .and_then(|text| std::fs::write(&out, text).map_err(|error| error.to_string()))
+    {
+        Ok(()) => println!("{}", Message::StudioInitialized(&name, &out)),
+        Err(error) => println!("{}", Message::FileError(&out, error)),
+    }
+}
+
+fn run_daemon(
+    args: &[String],
+    resources: &Arc<Mutex<SharedResources>>,
+    registry: &Arc<Mutex<ArtistToolRegistry>>,
+) {
+    let socket = flag_value(args, "--socket").unwrap_or(daemon::DEFAULT_SOCKET.to_string());
+
+    if !load_studio(args, registry) {
+        return;
+    }
+
+    let checkpoints = match flag_value::<String>(args, "--state-dir") {
+        Some(dir) => {
+            let every_events = flag_value(args, "--checkpoint-every").unwrap_or(100);
+            let every = Duration::from_secs(flag_value(args, "--checkpoint-secs").unwrap_or(60));
+            match checkpoint::Checkpointer::open(Path::new(&dir), every_events, every, resources) {
+                Ok((checkpoints, mut restored, report)) => {
+                    if let Some(report) = report {
+                        println!("{}", Message::Recovered(&report));
+                    }
+                    // A checkpoint holds stock and history; the studio file's
+                    // settings carry over.
+                    let mut registry = registry.lock().expect("Failed to lock registry");
+                    restored.set_loan_period(registry.loan_period());
+                    if let Some(limits) = registry.tool_limits() {
+                        restored.set_tool_limits(limits.clone());
+                    }
+                    *registry = restored;
+                    Some(checkpoints)
+                }
+                Err(error) => {
+                    println!("{}", Message::FileError(&dir, error.to_string()));
+                    return;
+                }
+            }
+        }
+        None => None,
+    };
+
+    if !load_tool_limits(args, registry)
+        || !set_rate_limit(args, registry)
+        || !add_notifiers(args, registry)
+        || !load_costs(args, registry)
+    {
+        return;
+    }
+    if let Some(days) = flag_value(args, "--loan-days") {
+        registry
+            .lock()
+            .expect("Failed to lock registry")
+            .set_loan_period(chrono::Duration::days(days));
+    }
+    if let Some(per_checkout) = flag_value(args, "--wear") {
+        let worn_out = if args.iter().any(|arg| arg == "--retire-worn") {
+            WornOut::Retire
+        } else {
+            WornOut::Repair
+        };
+        registry
+            .lock()
+            .expect("Failed to lock registry")
+            .set_wear_policy(WearPolicy {
+                per_checkout,
+                worn_out,
+                ..WearPolicy::default()
+            });
+    }
+
+    let auth = flag_value::<String>(args, "--manager-token").map(|token| {
+        let hours = flag_value(args, "--session-hours").unwrap_or(8);
+        auth::Auth::new(&token, chrono::Duration::hours(hours))
+    });
+
+    let dump_dir = flag_value(args, "--dump-dir").unwrap_or(".".to_string());
+    if let Err(error) =
+        signal_dump::install(Path::new(&dump_dir).to_path_buf(), Arc::clone(registry))
+    {
+        println!("{}", Message::FileError(&dump_dir, error.to_string()));
+    }
+    if let Some(jobs) = flag_value::<String>(args, "--schedule") {
+        match scheduler::load_jobs(Path::new(&jobs)) {
+            Ok(loaded) => scheduler::spawn(
+                scheduler::Scheduler::new(loaded, Utc::now()),
+                Arc::clone(registry),
+            ),
+            Err(error) => {
+                println!("{}", Message::FileError(&jobs, error.to_string()));
+                return;
+            }
+        }
+    }
+    // Edits to the studio file apply as they're saved; see
+    // `reload::StudioWatcher`.
+    if let Some(path) = studio_path(args) {
+        match reload::StudioWatcher::open(path.clone().into(), pinned(args)) {
+            Ok(watcher) => reload::spawn(watcher, Arc::clone(registry)),
+            Err(error) => {
+                println!("{}", Message::FileError(&path, error.to_string()));
+                return;
+            }
+        }
+    }
+    if let Err(error) = daemon::serve(Path::new(&socket), Arc::clone(registry), checkpoints, auth) {
+        println!("{}", Message::FileError(&socket, error.to_string()));
+    }
+}
+
+fn run_sync(args: &[String]) {
+    let (Some(from), Some(to)) = (
+        flag_value::<String>(args, "--from"),
+        flag_value::<String>(args, "--to"),
+    ) else {
+        println!("{}", Message::Usage);
+        return;
+    };
+    let dry_run = args.iter().any(|arg| arg == "--dry-run");
+
+    let source = match sync::load_state(Path::new(&from)) {
+        Ok(state) => state,
+        Err(error) => return println!("{}", Message::FileError(&from, error.to_string())),
+    };
+    // `--fork NAME` starts an offline branch of FROM in TO.
+    if let Some(replica) = flag_value::<String>(args, "--fork") {
+        let branch = sync::fork(&source, &replica);
+        match sync::save_state(Path::new(&to), &branch) {
+            Ok(()) => println!("{}", Message::BranchForked(&replica, &to)),
+            Err(error) => println!("{}", Message::FileError(&to, error.to_string())),
+        }
+        return;
+    }
+    let mut target = match sync::load_state(Path::new(&to)) {
+        Ok(state) => state,
+        Err(error) => return println!("{}", Message::FileError(&to, error.to_string())),
+    };
+
+    // `--merge` folds branch FROM into branch TO, conflicts and all,
+    // instead of copying FROM over TO.
+    if args.iter().any(|arg| arg == "--merge") {
+        let Some(audit) = sync::merge(&mut target, &source) else {
+            return println!("{}", Message::NotABranch);
+        };
+        println!("{}", Message::BranchMerged(audit.len(), dry_run));
+        for item in &audit {
+            println!("  {}", Message::MergeConflict(item));
+        }
+        if !dry_run {
+            if let Err(error) = sync::save_state(Path::new(&to), &target) {
+                println!("{}", Message::FileError(&to, error.to_string()));
+            }
+        }
+        return;
+    }
+    let ops = sync::diff(&source, &target);
+    println!("{}", Message::SyncPlan(ops.len(), dry_run));
+    for op in &ops {
+        println!("  {}", op);
+    }
+    if !dry_run && !ops.is_empty() {
+        sync::apply(&mut target, &ops);
+        if let Err(error) = sync::save_state(Path::new(&to), &target) {
+            println!("{}", Message::FileError(&to, error.to_string()));
+        }
+    }
+}
+
+// Prints the life of one numbered unit from a saved state, as text or
+// with `--json` as JSON.
+fn run_timeline(args: &[String]) {
+    let (Some(state), Some(tool), Some(serial)) = (
+        flag_value::<String>(args, "--state"),
+        flag_value::<String>(args, "--tool"),
+        flag_value::<usize>(args, "--serial"),
+    ) else {
+        println!("{}", Message::Usage);
+        return;
+    };
+    let resources = Arc::new(Mutex::new(SharedResources::default()));
+    let registry = match ArtistToolRegistry::load(Path::new(&state), &resources) {
+        Ok(registry) => registry,
+        Err(error) => return println!("{}", Message::FileError(&state, error.to_string())),
+    };
+    let Some(timeline) = registry.tool_timeline(&tool, serial) else {
+        return println!("{}", Message::NoSuchUnit(&tool, serial));
+    };
+    if args.iter().any(|arg| arg == "--json") {
+        // A timeline is plain strings and numbers, so it always serializes.
+        println!("{}", timeline.to_json().expect("timeline serializes"));
+    } else {
+        print!("{}", timeline.to_text());
+    }
+}
+
+fn run_stocktake(args: &[String]) {
+    let Some(state_path) = flag_value::<String>(args, "--state") else {
+        println!("{}", Message::Usage);
+        return;
+    };
+    let audit_log = flag_value(args, "--audit-log").unwrap_or("stocktake-audit.jsonl".to_string());
+    let mut state = match sync::load_state(Path::new(&state_path)) {
+        Ok(state) => state,
+        Err(error) => return println!("{}", Message::FileError(&state_path, error.to_string())),
+    };
+
+    let counts = match flag_value::<String>(args, "--counts") {
+        Some(path) => {
+            match std::fs::read_to_string(&path)
+                .map_err(|error| error.to_string())
+                .and_then(|csv| stocktake::parse_counts(&csv))
+            {
+                Ok(counts) => counts,
+                Err(error) => return println!("{}", Message::FileError(&path, error)),
+            }
+        }
+        None => prompt_counts(&state),
+    };
+
+    let adjustments = match stocktake::propose(&state, &counts) {
+        Ok(adjustments) => adjustments,
+        Err(error) => return println!("{}", Message::StocktakeRefused(&error)),
+    };
+    if adjustments.is_empty() {
+        println!("{}", Message::StocktakeMatches);
+        return;
+    }
+    for adjustment in &adjustments {
+        println!("{}", Message::StocktakeAdjustment(adjustment));
+    }
+    if !args.iter().any(|arg| arg == "--yes") {
+        print!("{} ", Message::StocktakeConfirm(adjustments.len()));
+        let _ = io::stdout().flush();
+        let mut answer = String::new();
+        let _ = io::stdin().read_line(&mut answer);
+        if !matches!(answer.trim(), "y" | "Y" | "yes" | "s" | "si" | "sí") {
+            println!("{}", Message::StocktakeCancelled);
+            return;
+        }
+    }
+
+    stocktake::apply(&mut state, &adjustments);
+    if let Err(error) = sync::save_state(Path::new(&state_path), &state) {
+        return println!("{}", Message::FileError(&state_path, error.to_string()));
+    }
+    if let Err(error) = stocktake::log_audit(Path::new(&audit_log), &adjustments, Utc::now()) {
+        println!("{}", Message::FileError(&audit_log, error.to_string()));
+    }
+    println!("{}", Message::StocktakeApplied(adjustments.len()));
+}
+
+// Asks for a count of every recorded item; a blank answer skips the item.
+fn prompt_counts(state: &dump::StateDump) -> Vec<(String, Amount)> {
+    let mut counts = vec![];
+    let tools = state
+        .tools
+        .iter()
+        .map(|(tool, count)| (tool, Amount::from(*count)));
+    let paints = state
+        .paints
+        .iter()
+        .map(|(paint, kg)| (paint, Amount::from(*kg)));
+    for (item, recorded) in tools.chain(paints) {
+        loop {
+            print!("{} ", Message::StocktakePrompt(item, recorded));
+            let _ = io::stdout().flush();
+            let mut answer = String::new();
+            if io::stdin().read_line(&mut answer).unwrap_or(0) == 0 {
+                return counts;
+            }
+            match answer.trim() {
+                "" => break,
+                answer => {
+                    if let Ok(counted) = answer.parse() {
+                        counts.push((item.clone(), counted));
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    counts
+}
+
+// `simulate [SCENARIO] [--watch]`: runs a scenario script, or the random
+// artist simulation without one, in a fresh studio. With `--watch` it runs
+// again whenever the scenario, studio or limits file changes and shows how
+// the summary moved.
+fn run_simulate(args: &[String]) {
+    let matches = match simulate_command().try_get_matches_from(args) {
+        Ok(matches) => matches,
+        Err(error) => {
+            println!("{}", flag_error(&error));
+            process::exit(2);
+        }
+    };
+    let scenario = matches.get_one::<String>("scenario").cloned();
+    if let Some(list) = matches.get_one::<String>("studios") {
+        simulate_studios(args, &matches, list);
+        return;
+    }
+    let watch = matches.get_flag("watch");
+    let paths = scenario
+        .iter()
+        .chain(
+            ["studio", "tool-limits", "profiles", "costs"]
+                .iter()
+                .filter_map(|id| matches.get_one(id)),
+        )
+        .map(std::path::PathBuf::from)
+        .collect();
+    let mut watcher = watch::FileWatcher::new(paths);
+
+    // Ctrl+C lets the artists finish what they are doing and the run save
+    // and report as usual; a second one exits at once.
+    let interrupt = interrupt::global();
+    if let Err(error) = interrupt.install() {
+        println!("{}", Message::InterruptHandlerFailed(error.to_string()));
+    }
+    let mut previous: Option<watch::RunSummary> = None;
+    loop {
+        if let Some(summary) = simulate_once(args, &matches) {
+            println!("{}", Message::RunSummary(&summary));
+            if let Some(previous) = previous {
+                let changes = summary.changes_since(&previous);
+                if changes.is_empty() {
+                    println!("{}", Message::NoMetricChanges);
+                }
+                for change in &changes {
+                    println!("{}", Message::MetricChanged(change));
+                }
+            }
+            previous = Some(summary);
+        }
+        if !watch || interrupt.requested() {
+            return;
+        }
+        println!("{}", Message::WatchingFiles);
+        while !watcher.changed() {
+            if interrupt.requested() {
+                return;
+            }
+            thread::sleep(watch::POLL_INTERVAL);
+        }
+    }
+}
+
+// Every flag `simulate` takes. Flags the other commands share, like
+// `--studio` or `--stock`, are checked again by the loaders that read them,
+// so here they are only taken as text; the rest are parsed once, up front.
+fn simulate_command() -> Command {
+    let value = |id: &'static str| Arg::new(id).long(id).action(ArgAction::Set);
+    let switch = |id: &'static str| Arg::new(id).long(id).action(ArgAction::SetTrue);
+    Command::new("simulate")
+        .no_binary_name(true)
+        .disable_help_flag(true)
+        .arg(Arg::new("scenario"))
+        .args(
+            [
+                "studio",
+                "studios",
+                "assign",
+                "tool-limits",
+                "rate-limit",
+                "profiles",
+                "costs",
+                "stock",
+                "import-csv",
+                "state",
+                "db",
+                "events",
+                "dump-dir",
+            ]
+            .map(value),
+        )
+        .arg(value("artists").value_parser(value_parser!(usize)))
+        .arg(value("rounds").value_parser(value_parser!(usize)))
+        .arg(value("pool-size").value_parser(value_parser!(usize)))
+        .arg(value("drying-racks").value_parser(value_parser!(usize)))
+        .arg(value("drying-hours").value_parser(value_parser!(i64)))
+        .arg(value("seed").value_parser(value_parser!(u64)))
+        .arg(value("time-step").value_parser(value_parser!(i64)))
+        .arg(value("speed").value_parser(value_parser!(f64)))
+        .arg(value("tools-per-artist").value_parser(parsed::<ToolCountRange>))
+        .arg(value("strategy").value_parser(parsed::<Strategy>))
+        .arg(value("chaos").value_parser(parsed::<Chaos>))
+        .args(
+            [
+                "watch",
+                "queue",
+                "over-budget",
+                "fatigue",
+                "tui",
+                "threaded",
+                "async",
+                "actor",
+            ]
+            .map(switch),
+        )
+}
+
+// A clap value parser for any of the crate's `FromStr` types. The message
+// is never shown: `flag_error` reports the flag and value instead.
+fn parsed<T: std::str::FromStr>(text: &str) -> Result<T, String> {
+    text.parse().map_err(|_| text.to_string())
+}
+
+// What went wrong with the command line, in the same words the hand-read
+// flags use.
+fn flag_error(error: &clap::Error) -> Message<'_> {
+    let text = |kind| match error.get(kind) {
+        Some(ContextValue::String(text)) => text.as_str(),
+        _ => "",
+    };
+    // clap names a flag with its value placeholder, `--rounds <rounds>`.
+    let arg = text(ContextKind::InvalidArg);
+    let flag = arg.split_whitespace().next().unwrap_or(arg);
+    match error.kind() {
+        ErrorKind::UnknownArgument => Message::UnknownArgument(flag),
+        _ => Message::InvalidFlag(flag, text(ContextKind::InvalidValue)),
+    }
+}
+
+// The random artist simulation as the `simulate` flags set it up.
+fn simulation_config(matches: &ArgMatches) -> simulation::SimulationConfig {
+    let defaults = simulation::SimulationConfig::default();
+    simulation::SimulationConfig {
+        artists: matches
+            .get_one("artists")
+            .copied()
+            .unwrap_or(defaults.artists),
+        tools_per_artist: matches.get_one("tools-per-artist").copied(),
+        rounds: matches
+            .get_one("rounds")
+            .copied()
+            .unwrap_or(defaults.rounds),
+        seed: matches.get_one("seed").copied(),
+        quiet: matches.get_flag("tui"),
+        pool_size: matches.get_one("pool-size").copied(),
+        strategy: matches.get_one("strategy").copied().unwrap_or_default(),
+        time_step: matches
+            .get_one("time-step")
+            .copied()
+            .map(chrono::Duration::seconds),
+        chaos: matches.get_one("chaos").copied(),
+        fatigue: matches.get_flag("fatigue").then(FatigueModel::default),
+    }
+}
+
+// `simulate --studios A.toml,B.toml [--assign ID=NAME,...]`: runs the random
+// artist simulation in every studio at once, each with its own stock, and
+// reports on each. Artists not assigned are spread over the studios in turn.
+fn simulate_studios(args: &[String], matches: &ArgMatches, list: &str) {
+    let mut studios = Studios::default();
+    for path in list
+        .split(',')
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+    {
+        let config = match templates::StudioConfig::load(Path::new(path)) {
+            Ok(config) => config,
+            Err(error) => {
+                println!("{}", Message::FileError(path, error.to_string()));
+                return;
+            }
+        };
+        if studios.add(Studio::from_config(&config)).is_err() {
+            println!("{}", Message::InvalidFlag("--studios", path));
+            return;
+        }
+    }
+    let assignments = matches
+        .get_one::<String>("assign")
+        .cloned()
+        .unwrap_or_default();
+    for pair in assignments
+        .split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+    {
+        let assigned = pair
+            .split_once('=')
+            .and_then(|(id, name)| Some((id.trim().parse().ok()?, name.trim())))
+            .is_some_and(|(id, name)| studios.assign(id, name).is_ok());
+        if !assigned {
+            println!("{}", Message::InvalidFlag("--assign", pair));
+            return;
+        }
+    }
+    // Limits, profiles and costs apply to every studio alike.
+    for studio in studios.iter() {
+        if !load_tool_limits(args, &studio.registry)
+            || !set_rate_limit(args, &studio.registry)
+            || !load_profiles(args, &studio.registry)
+            || !load_costs(args, &studio.registry)
+            || !set_drying(args, &studio.registry)
+        {
+            return;
+        }
+    }
+    let errors = simulation::run_studios(&mut studios, &simulation_config(matches));
+    for ((name, summary), errors) in studios.summaries().iter().zip(&errors) {
+        for error in errors {
+            println!("{}", Message::CheckoutFailed(error));
+        }
+        let artists = studios.artists(name).len();
+        println!("{}", Message::StudioSummary(name, artists, summary));
+    }
+}
+
+fn simulate_once(args: &[String], matches: &ArgMatches) -> Option<watch::RunSummary> {
+    let resources = Arc::new(Mutex::new(SharedResources::default()));
+    let registry = Arc::new(Mutex::new(ArtistToolRegistry::new(&resources)));
+    if !load_studio(args, &registry) || !load_stock(args, &resources) {
+        return None;
+    }
+    // `--queue` has checkouts wait for units to come back, highest priority
+    // tier first, instead of failing.
+    if matches.get_flag("queue") {
+        resources
+            .lock()
+            .expect("Failed to lock resources")
+            .loan_caps
+            .policy = CapPolicy::Queue;
+    }
+    // `--state FILE` resumes an earlier run and saves this one back to it.
+    let state = matches.get_one::<String>("state");
+    if let Some(path) = state.filter(|path| Path::new(path).exists()) {
+        match ArtistToolRegistry::load(Path::new(path), &resources) {
+            Ok(loaded) => *registry.lock().expect("Failed to lock registry") = loaded,
+            Err(error) => {
+                println!("{}", Message::FileError(path, error.to_string()));
+                return None;
+            }
+        }
+    }
+    // `--db FILE` does the same through SQLite.
+    if !load_database(args, &resources, &registry).unwrap_or(true)
+        || !load_tool_limits(args, &registry)
+        || !set_rate_limit(args, &registry)
+        || !load_profiles(args, &registry)
+        || !load_costs(args, &registry)
+        || !set_drying(args, &registry)
+    {
+        return None;
+    }
+    let mut rules_fired = 0;
+    match matches.get_one::<String>("scenario") {
+        Some(path) => {
+            let script = match std::fs::read_to_string(path)
+                .map_err(|error| error.to_string())
+                .and_then(|text| script::Script::parse(&text).map_err(|error| error.to_string()))
+            {
+                Ok(script) => script,
+                Err(error) => {
+                    println!("{}", Message::FileError(path, error));
+                    return None;
                 }
+            };
+            let speed = matches.get_one("speed").copied().unwrap_or(1.0);
+            let scripted = Arc::clone(&registry);
+            for fired in with_dashboard(matches, &registry, move || script.run(&scripted, speed))? {
+                println!("{}", Message::RuleFired(&fired));
+                rules_fired += 1;
+            }
+        }
+        None => {
+            let config = simulation_config(matches);
+            let errors = if matches.get_flag("async") {
+                run_async(&registry, &config)?
+            } else if matches.get_flag("actor") {
+                let mut registry = registry.lock().expect("Failed to lock registry");
+                rustic_canvas::actor::run(&mut registry, &config).1
+            } else if matches.get_flag("threaded") {
+                let (shared, simulated) = (Arc::clone(&resources), Arc::clone(&registry));
+                with_dashboard(matches, &registry, move || {
+                    simulation::run_artists(&shared, &simulated, &config)
+                })?
+                .1
             } else {
-                println!("Warning: Tool '{}' not found in resources.", tool);
+                // Each artist checks out, works, returns or idles a round at a
+                // time, and every round gets a line of its own.
+                let simulated = Arc::clone(&registry);
+                let (rounds, errors) = with_dashboard(matches, &registry, move || {
+                    rounds::run_rounds(&simulated, &config)
+                })?;
+                for stats in &rounds {
+                    println!("{}", Message::RoundSummary(stats));
+                }
+                errors
+            };
+            for error in &errors {
+                println!("{}", Message::CheckoutFailed(error));
             }
         }
     }
+    let interrupted = interrupt::global().requested();
+    if interrupted {
+        println!("{}", Message::Interrupted);
+        // Without `--state` to save to, leave a dump of where things stood.
+        if state.is_none() {
+            let dir = matches
+                .get_one::<String>("dump-dir")
+                .map_or(".", String::as_str);
+            match signal_dump::write_dump(Path::new(&dir), &registry) {
+                Ok(path) => println!("{}", Message::StateDumped(&path.display().to_string())),
+                Err(error) => println!("{}", Message::FileError(dir, error.to_string())),
+            }
+        }
+    }
+    let registry = registry.lock().expect("Failed to lock registry");
+    if let Some(path) = state {
+        match registry.save(Path::new(path)) {
+            Ok(()) => println!("{}", Message::StateDumped(path)),
+            Err(error) => println!("{}", Message::FileError(path, error.to_string())),
+        }
+    }
+    save_database(args, &registry);
+    if let Some(path) = matches.get_one::<String>("events") {
+        match registry.events.save(path) {
+            Ok(()) => println!("{}", Message::EventsSaved(path)),
+            Err(error) => println!("{}", Message::FileError(path, error.to_string())),
+        }
+    }
+    print_budget(&registry);
+    if let Some(racks) = &registry.racks {
+        println!("{}", Message::DryingSummary(&racks.stats()));
+    }
+    if interrupted {
+        let stock = resources
+            .lock()
+            .expect("Failed to lock resources")
+            .tools
+            .amounts();
+        println!("{}", Message::FinalInventory(&stock));
+    }
+    Some(watch::RunSummary::capture(&registry, rules_fired))
 }
 
-enum State {
-    TakeOut,
-    Return,
-    Fill,
-    Change,
-    New,
-    Retire,
-    Damage,
-    Lost,
-    Audit,
-    Reserved,
-    Repair,
-    Expired,
-    Sold,
+// Runs `work` under the terminal dashboard when `--tui` is given, or
+// straight away otherwise. None if the dashboard couldn't run.
+#[cfg(feature = "tui")]
+fn with_dashboard<T: Send + 'static>(
+    matches: &ArgMatches,
+    registry: &Arc<Mutex<ArtistToolRegistry>>,
+    work: impl FnOnce() -> T + Send + 'static,
+) -> Option<T> {
+    if !matches.get_flag("tui") {
+        return Some(work());
+    }
+    let dump_dir = matches
+        .get_one::<String>("dump-dir")
+        .map_or(".", String::as_str);
+    match rustic_canvas::tui::run(registry, Path::new(dump_dir), work) {
+        Ok(result) => Some(result),
+        Err(error) => {
+            println!("{}", Message::DashboardFailed(error.to_string()));
+            None
+        }
+    }
 }
 
-struct ArtistToolPreferences {
-    artist_id: usize,
-    preferred_tools: Vec<String>,
-    datetime: Option<DateTime<Utc>>,
-    state: Option<State>,
+#[cfg(not(feature = "tui"))]
+fn with_dashboard<T>(
+    matches: &ArgMatches,
+    _registry: &Arc<Mutex<ArtistToolRegistry>>,
+    work: impl FnOnce() -> T,
+) -> Option<T> {
+    if matches.get_flag("tui") {
+        println!("{}", Message::FeatureNotBuilt("tui"));
+        return None;
+    }
+    Some(work())
 }
 
-impl ArtistToolPreferences {
-    fn default() -> Self {
-        Self {
-            artist_id: 0,
-            preferred_tools: vec![],
-            datetime: None,
-            state: None,
+// Loads the registry from the `--db` database. None when the flag isn't
+// given or the database has nothing saved yet; Some(false) if it couldn't be
+// read.
+#[cfg(feature = "sqlite")]
+fn load_database(
+    args: &[String],
+    resources: &Arc<Mutex<SharedResources>>,
+    registry: &Mutex<ArtistToolRegistry>,
+) -> Option<bool> {
+    let path = flag_value::<String>(args, "--db")?;
+    match rustic_canvas::sqlite::CachedStore::open(Path::new(&path))
+        .and_then(|store| store.load(resources))
+    {
+        Ok(Some(loaded)) => {
+            *registry.lock().expect("Failed to lock registry") = loaded;
+            Some(true)
+        }
+        Ok(None) => None,
+        Err(error) => {
+            println!("{}", Message::FileError(&path, error.to_string()));
+            Some(false)
         }
     }
 }
 
-struct ArtistToolRegistry {
-    artist_tool_preferences: Vec<ArtistToolPreferences>,
-    shared_resources: Arc<Mutex<SharedResources>>,
+#[cfg(not(feature = "sqlite"))]
+fn load_database(
+    args: &[String],
+    _resources: &Arc<Mutex<SharedResources>>,
+    _registry: &Mutex<ArtistToolRegistry>,
+) -> Option<bool> {
+    flag_value::<String>(args, "--db")?;
+    println!("{}", Message::FeatureNotBuilt("sqlite"));
+    Some(false)
+}
+
+#[cfg(feature = "sqlite")]
+fn save_database(args: &[String], registry: &ArtistToolRegistry) {
+    let Some(path) = flag_value::<String>(args, "--db") else {
+        return;
+    };
+    match rustic_canvas::sqlite::CachedStore::open(Path::new(&path))
+        .and_then(|mut store| store.save(registry))
+    {
+        Ok(()) => println!("{}", Message::StateDumped(&path)),
+        Err(error) => println!("{}", Message::FileError(&path, error.to_string())),
+    }
 }
 
-impl ArtistToolRegistry {
-    fn new(resources: &Arc<Mutex<SharedResources>>) -> Self {
-        Self {
-            artist_tool_preferences: vec![],
-            shared_resources: Arc::clone(resources),
+#[cfg(not(feature = "sqlite"))]
+fn save_database(_args: &[String], _registry: &ArtistToolRegistry) {}
+
+// Runs the artists as tokio tasks. None if the runtime couldn't start.
+#[cfg(feature = "async")]
+fn run_async(
+    registry: &Mutex<ArtistToolRegistry>,
+    config: &simulation::SimulationConfig,
+) -> Option<Vec<RegistryError>> {
+    let mut registry = registry.lock().expect("Failed to lock registry");
+    match rustic_canvas::async_simulation::run(&mut registry, config) {
+        Ok((_, errors)) => Some(errors),
+        Err(error) => {
+            println!("{}", Message::RuntimeFailed(error.to_string()));
+            None
         }
     }
+}
 
-    fn tool_registry(&mut self, id: usize, tools: Vec<String>) {
-        self.artist_tool_preferences.push(ArtistToolPreferences {
-            artist_id: id,
-            datetime: Some(Utc::now()),
-            state: Some(State::TakeOut),
-            preferred_tools: tools.clone(),
-        });
+#[cfg(not(feature = "async"))]
+fn run_async(
+    _registry: &Mutex<ArtistToolRegistry>,
+    _config: &simulation::SimulationConfig,
+) -> Option<Vec<RegistryError>> {
+    println!("{}", Message::FeatureNotBuilt("async"));
+    None
+}
+
+// A prompt for running the studio by hand. `--state FILE` picks up where the
+// last session left off and saves this one back on the way out.
+fn run_interactive(args: &[String]) {
+    let resources = Arc::new(Mutex::new(SharedResources::default()));
+    let registry = Arc::new(Mutex::new(ArtistToolRegistry::new(&resources)));
+    if !load_studio(args, &registry) || !load_stock(args, &resources) {
+        return;
+    }
+    let state = flag_value::<String>(args, "--state");
+    if let Some(path) = state.as_deref().filter(|path| Path::new(path).exists()) {
+        match ArtistToolRegistry::load(Path::new(path), &resources) {
+            Ok(loaded) => *registry.lock().expect("Failed to lock registry") = loaded,
+            Err(error) => return println!("{}", Message::FileError(path, error.to_string())),
+        }
+    }
+    if !load_tool_limits(args, &registry) {
+        return;
+    }
+    println!("{}", Message::InteractiveBanner);
+    if let Err(error) = interactive::run(&registry, io::stdin().lock(), io::stdout()) {
+        println!("{}", Message::FileError("stdin", error.to_string()));
+    }
+    if let Some(path) = &state {
+        match registry
+            .lock()
+            .expect("Failed to lock registry")
+            .save(Path::new(path))
+        {
+            Ok(()) => println!("{}", Message::StateDumped(path)),
+            Err(error) => println!("{}", Message::FileError(path, error.to_string())),
+        }
+    }
+}
+
+fn run_script(args: &[String], registry: &Mutex<ArtistToolRegistry>) {
+    let Some(path) = args.first() else {
+        println!("{}", Message::Usage);
+        return;
+    };
+    let script = match std::fs::read_to_string(path)
+        .map_err(|error| error.to_string())
+        .and_then(|text| script::Script::parse(&text).map_err(|error| error.to_string()))
+    {
+        Ok(script) => script,
+        Err(error) => return println!("{}", Message::FileError(path, error)),
+    };
+    let speed = flag_value(args, "--speed").unwrap_or(1.0);
+    for fired in script.run(registry, speed) {
+        println!("{}", Message::RuleFired(&fired));
+    }
+    println!("{}", Message::Finished);
+}
+
+// `run-scenario FILE`: plays a scenario's steps in simulated time against a
+// fresh studio, the same way every run.
+fn run_scenario(args: &[String]) {
+    let Some(path) = args.first() else {
+        println!("{}", Message::Usage);
+        return;
+    };
+    let scenario = match Scenario::load(Path::new(path)) {
+        Ok(scenario) => scenario,
+        Err(error) => return println!("{}", Message::FileError(path, error.to_string())),
+    };
+    let resources = Arc::new(Mutex::new(SharedResources::default()));
+    let registry = Mutex::new(ArtistToolRegistry::new(&resources));
+    if !load_studio(args, &registry)
+        || !load_stock(args, &resources)
+        || !load_tool_limits(args, &registry)
+        || !set_rate_limit(args, &registry)
+    {
+        return;
+    }
+    if args.iter().any(|arg| arg == "--queue") {
+        resources
+            .lock()
+            .expect("Failed to lock resources")
+            .loan_caps
+            .policy = CapPolicy::Queue;
+    }
+    let mut registry = registry.into_inner().expect("Failed to lock registry");
+    let outcomes = scenario.run(&mut registry);
+    for outcome in &outcomes {
+        println!("{}", Message::ScenarioStep(outcome));
+    }
+    let failed = outcomes
+        .iter()
+        .filter(|outcome| outcome.result.is_err())
+        .count();
+    println!("{}", Message::ScenarioSummary(outcomes.len(), failed));
+    if let Some(path) = flag_value::<String>(args, "--events") {
+        match registry.events.save(&path) {
+            Ok(()) => println!("{}", Message::EventsSaved(&path)),
+            Err(error) => println!("{}", Message::FileError(&path, error.to_string())),
+        }
+    }
+    let stock = resources
+        .lock()
+        .expect("Failed to lock resources")
+        .tools
+        .amounts();
+    println!("{}", Message::FinalInventory(&stock));
+}
 
-        // Lock shared resources and update them
-        if let Ok(mut update_resources) = self.shared_resources.lock() {
-            update_resources.take_out_resources(tools);
+fn run_ctl(args: &[String]) {
+    let socket = flag_value(args, "--socket").unwrap_or(daemon::DEFAULT_SOCKET.to_string());
+    let mut words = vec![];
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--socket" {
+            iter.next();
         } else {
-            println!("Error: Unable to lock shared resources.");
+            words.push(arg.as_str());
         }
     }
+    // `ctl batch [--atomic] FILE` sends a command file or JSON file as one batch.
+    let mut command = words.join(" ");
+    if let ["batch", rest @ ..] = words.as_slice() {
+        let atomic = rest.contains(&"--atomic");
+        if let Some(path) = rest.iter().find(|word| **word != "--atomic") {
+            let ops = match std::fs::read_to_string(path)
+                .map_err(|error| error.to_string())
+                .and_then(|text| batch::parse_batch(&text))
+            {
+                Ok(ops) => ops,
+                Err(error) => return println!("{}", Message::FileError(path, error)),
+            };
+            let json = serde_json::to_string(&ops).expect("batch operations serialize");
+            command = format!("batch {}{}", if atomic { "atomic " } else { "" }, json);
+        }
+    }
+    match daemon::send(Path::new(&socket), &command) {
+        Ok(reply) => print!("{}", reply),
+        Err(error) => println!("{}", Message::FileError(&socket, error.to_string())),
+    }
 }
 
-fn artis_task(
-    artist_tool_registry: Arc<Mutex<ArtistToolRegistry>>,
-    id: usize,
-    resources: Arc<Mutex<SharedResources>>,
-) {
-    let artist_tools: (usize, Vec<String>);
+// Runs a simulation, or reads the history saved with `--state`, and prints
+// tool utilization and artist activity, busiest first.
+fn run_report(args: &[String]) {
+    let resources = Arc::new(Mutex::new(SharedResources::default()));
+    let registry = Arc::new(Mutex::new(ArtistToolRegistry::new(&resources)));
+    if !load_studio(args, &registry)
+        || !load_stock(args, &resources)
+        || !load_tool_limits(args, &registry)
+        || !load_profiles(args, &registry)
+        || !load_costs(args, &registry)
+        || !set_drying(args, &registry)
     {
-        let resources = resources.lock().expect("Failed to lock resources");
-        artist_tools = tools_usage(id, &resources.tools);
+        return;
+    }
+    // A `--db` database with a saved run is reported on as it stands.
+    let from_database = match load_database(args, &resources, &registry) {
+        Some(false) => return,
+        loaded => loaded.is_some(),
+    };
+    match flag_value::<String>(args, "--state") {
+        _ if from_database => {}
+        Some(path) => match ArtistToolRegistry::load(Path::new(&path), &resources) {
+            Ok(loaded) => *registry.lock().expect("Failed to lock registry") = loaded,
+            Err(error) => {
+                println!("{}", Message::FileError(&path, error.to_string()));
+                return;
+            }
+        },
+        None => {
+            let defaults = simulation::SimulationConfig::default();
+            let config = simulation::SimulationConfig {
+                artists: flag_value(args, "--artists").unwrap_or(defaults.artists),
+                rounds: flag_value(args, "--rounds").unwrap_or(defaults.rounds),
+                seed: flag_value(args, "--seed"),
+                quiet: true,
+                strategy: flag_value(args, "--strategy").unwrap_or_default(),
+                ..defaults
+            };
+            let (_, errors) = simulation::run_artists(&resources, &registry, &config);
+            for error in &errors {
+                println!("{}", Message::CheckoutFailed(error));
+            }
+            // The run ends with everything the artists made sold at list
+            // price.
+            let mut registry = registry.lock().expect("Failed to lock registry");
+            let pricing = Pricing::standard(registry.ledger.rates().base());
+            let sale = registry.sell_gallery(&pricing);
+            if let Err(error) = sale {
+                println!("{}", Message::SaleFailed(&error));
+            }
+        }
     }
 
-    let mut registry = artist_tool_registry
+    let stats = Stats::compute(
+        &registry.lock().expect("Failed to lock registry"),
+        Utc::now(),
+    );
+    println!("{}", Message::ToolStatsHeader);
+    for tool in &stats.tools {
+        let avg_hold = tool
+            .avg_hold
+            .map(|hold| hold.num_milliseconds().to_string())
+            .unwrap_or_else(|| "-".to_string());
+        println!(
+            "{:<16} {:>9} {:>6.2} {:>12}",
+            tool.tool, tool.checkouts, tool.utilization, avg_hold
+        );
+    }
+    println!("{}", Message::ArtistStatsHeader);
+    for artist in &stats.artists {
+        println!(
+            "{:<8} {:>9} {:>8} {:>8}",
+            artist.artist_id, artist.checkouts, artist.returns, artist.paint_kg
+        );
+    }
+    // Artists left waiting in a row, worst first; none at all means nobody
+    // was starved of a tool.
+    if !stats.starvation.is_empty() {
+        println!("{}", Message::StarvationHeader);
+        for wait in &stats.starvation {
+            println!(
+                "{:<8} {:>11} {:>15} {:>8}",
+                wait.artist_id,
+                wait.longest_streak,
+                wait.longest_wait.num_milliseconds(),
+                wait.streak
+            );
+        }
+    }
+    let revenue = registry
         .lock()
-        .expect("Failed to lock registry");
-    registry.tool_registry(artist_tools.0, artist_tools.1);
+        .expect("Failed to lock registry")
+        .revenue_by_artist();
+    if !revenue.is_empty() {
+        println!("{}", Message::RevenueHeader);
+        for line in &revenue {
+            println!(
+                "{:<8} {:>6} {:>14}",
+                line.artist_id,
+                line.sold,
+                line.revenue.to_string()
+            );
+        }
+    }
+    let registry = registry.lock().expect("Failed to lock registry");
+    print_budget(&registry);
+    if let Some(racks) = &registry.racks {
+        println!("{}", Message::DryingSummary(&racks.stats()));
+    }
+    if let Some(path) = flag_value::<String>(args, "--out") {
+        let format = match flag_value::<String>(args, "--format") {
+            None => ReportFormat::for_path(Path::new(&path)),
+            Some(format) => match format.parse() {
+                Ok(format) => format,
+                Err(_) => return println!("{}", Message::InvalidFlag("--format", &format)),
+            },
+        };
+        match RunReport::compute(&registry, Utc::now()).write(Path::new(&path), format) {
+            Ok(()) => println!("{}", Message::ReportWritten(&path)),
+            Err(error) => println!("{}", Message::FileError(&path, error.to_string())),
+        }
+    }
+    if let Some(path) = flag_value::<String>(args, "--history-csv") {
+        match registry.export_csv(Path::new(&path)) {
+            Ok(()) => println!("{}", Message::CsvExported(&path)),
+            Err(error) => println!("{}", Message::FileError(&path, error.to_string())),
+        }
+    }
+    if let Some(path) = flag_value::<String>(args, "--inventory-csv") {
+        let exported = resources
+            .lock()
+            .expect("Failed to lock resources")
+            .export_csv(Path::new(&path));
+        match exported {
+            Ok(()) => println!("{}", Message::CsvExported(&path)),
+            Err(error) => println!("{}", Message::FileError(&path, error.to_string())),
+        }
+    }
+}
 
-    #[cfg(debug_assertions)]
-    simulate_task_delay();
+// Re-applies an event log written by `simulate --events` to the studio it
+// started from and checks that the stock ends up where the log says.
+fn run_replay(args: &[String]) {
+    let Some(path) = args.first() else {
+        println!("{}", Message::Usage);
+        return;
+    };
+    let log = match events::EventLog::load(path) {
+        Ok(log) => log,
+        Err(error) => {
+            println!("{}", Message::FileError(path, error.to_string()));
+            return;
+        }
+    };
+    let resources = Arc::new(Mutex::new(SharedResources::default()));
+    let registry = Mutex::new(ArtistToolRegistry::new(&resources));
+    if !load_studio(args, &registry) || !load_stock(args, &resources) {
+        return;
+    }
+    let mut resources = resources.lock().expect("Failed to lock resources");
+    let report = events::replay(&log, &mut resources);
+    for (index, error) in &report.refused {
+        println!("{}", Message::EventRefused(*index, error));
+    }
+    for mismatch in &report.mismatches {
+        println!("{}", Message::StockMismatch(mismatch));
+    }
+    println!("{}", Message::EventReplaySummary(&report));
 }
 
-fn tools_usage(id: usize, tools: &Vec<(String, usize)>) -> (usize, Vec<String>) {
-    let mut rng = thread_rng();
-    let tool_count = rng.gen_range(MIN_REQUIRED_TOOLS..=MAX_ALLOWED_TOOLS);
-    let selected_tools: Vec<_> = tools.choose_multiple(&mut rng, tool_count).collect();
+#[cfg(feature = "server")]
+fn run_serve(
+    args: &[String],
+    resources: &Arc<Mutex<SharedResources>>,
+    registry: &Arc<Mutex<ArtistToolRegistry>>,
+) {
+    if !load_studio(args, registry)
+        || !load_tool_limits(args, registry)
+        || !set_rate_limit(args, registry)
+        || !add_notifiers(args, registry)
+    {
+        return;
+    }
+    let addr =
+        flag_value(args, "--addr").unwrap_or(rustic_canvas::server::DEFAULT_ADDR.to_string());
+    // `--simulate` runs artists against the served registry, `--simulate-after`
+    // seconds in, so clients subscribed to /events can watch them live.
+    if args.iter().any(|arg| arg == "--simulate") {
+        let defaults = simulation::SimulationConfig::default();
+        let config = simulation::SimulationConfig {
+            artists: flag_value(args, "--artists").unwrap_or(defaults.artists),
+            tools_per_artist: flag_value(args, "--tools-per-artist"),
+            rounds: flag_value(args, "--rounds").unwrap_or(defaults.rounds),
+            seed: flag_value(args, "--seed"),
+            ..defaults
+        };
+        let after = Duration::from_secs(flag_value(args, "--simulate-after").unwrap_or(0));
+        let (resources, registry) = (Arc::clone(resources), Arc::clone(registry));
+        thread::spawn(move || {
+            thread::sleep(after);
+            let (_, errors) = simulation::run_artists(&resources, &registry, &config);
+            for error in &errors {
+                println!("{}", Message::CheckoutFailed(error));
+            }
+        });
+    }
+    println!("{}", Message::Serving(&addr));
+    if let Err(error) = rustic_canvas::server::serve(&addr, Arc::clone(registry)) {
+        println!("{}", Message::FileError(&addr, error.to_string()));
+    }
+}
 
-    let string_values: Vec<String> = selected_tools.iter().map(|&(ref s, _)| s.clone()).collect();
-    println!("Artist {}: Selected tools: {:#?}", id, string_values);
-    (id, string_values)
+#[cfg(not(feature = "server"))]
+fn run_serve(
+    _args: &[String],
+    _resources: &Arc<Mutex<SharedResources>>,
+    _registry: &Arc<Mutex<ArtistToolRegistry>>,
+) {
+    println!("{}", Message::FeatureNotBuilt("server"));
 }
 
-fn simulate_task_delay() {
-    thread::sleep(std::time::Duration::from_millis(10));
+fn run_replay_bench(args: &[String]) {
+    let Some(path) = args.first() else {
+        println!("{}", Message::Usage);
+        return;
+    };
+    let trace = match Trace::load(path) {
+        Ok(trace) => trace,
+        Err(error) => {
+            println!("{}", Message::FileError(path, error.to_string()));
+            return;
+        }
+    };
+    let speed = flag_value(args, "--speed").unwrap_or(1.0);
+    let tolerance = Duration::from_millis(flag_value(args, "--tolerance-ms").unwrap_or(50));
+
+    let resources = Arc::new(Mutex::new(SharedResources::default()));
+    let registry = Mutex::new(ArtistToolRegistry::new(&resources));
+    let report = trace::replay(&trace, &registry, speed, tolerance);
+    println!("{}", Message::ReplaySummary(&report));
 }
 
-fn main() {
-    let resources = SharedResources::default();
-    let shared_resources = Arc::new(Mutex::new(resources));
-    let artist_tool_registry = Arc::new(Mutex::new(ArtistToolRegistry::new(&shared_resources)));
+fn run_bench(args: &[String]) {
+    let counts = match flag_value::<String>(args, "--artists") {
+        None => bench::ARTIST_COUNTS.to_vec(),
+        Some(list) => match list
+            .split(',')
+            .map(|count| {
+                count
+                    .trim()
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&count| count > 0)
+            })
+            .collect::<Option<Vec<_>>>()
+        {
+            Some(counts) => counts,
+            None => return println!("{}", Message::InvalidFlag("--artists", &list)),
+        },
+    };
+    let rounds = flag_value(args, "--rounds").unwrap_or(BenchConfig::default().rounds);
+
+    println!("{}", Message::BenchHeader);
+    for artists in counts {
+        let result = bench::run(&BenchConfig { artists, rounds });
+        println!(
+            "{:<8} {:>10} {:>14.0} {:>12} {:>14.2} {:>14.2}",
+            result.artists,
+            result.checkouts,
+            result.checkouts_per_sec(),
+            result.lock.contended,
+            result.avg_lock_wait_us(),
+            result.lock.max_wait_ns as f64 / 1_000.0,
+        );
+    }
+}
 
-    let mut handles = vec![];
+fn run_experiment(args: &[String]) {
+    let defaults = ExperimentConfig::default();
+    let config = ExperimentConfig {
+        artists: flag_value(args, "--artists").unwrap_or(defaults.artists),
+        operations_per_artist: flag_value(args, "--ops").unwrap_or(defaults.operations_per_artist),
+        tools: flag_value(args, "--tools").unwrap_or(defaults.tools),
+        seed: flag_value(args, "--seed").unwrap_or(defaults.seed),
+        ..defaults
+    };
 
-    for id in 0..TOTAL_ARTISTS {
-        let resources_arc_clone = Arc::clone(&shared_resources);
-        let artist_tool_registry_arc_clone = Arc::clone(&artist_tool_registry);
-        let handle = thread::spawn(move || {
-            artis_task(artist_tool_registry_arc_clone, id, resources_arc_clone)
-        });
-        handles.push(handle)
+    println!("{}", Message::ExperimentHeader);
+    for result in experiment::run_all(&config) {
+        println!(
+            "{:<8} {:>8} {:>8} {:>12.0} {:>10.2} {:>10.2}",
+            result.strategy,
+            result.operations,
+            result.failed_checkouts,
+            result.throughput_per_sec(),
+            result.avg_latency_us,
+            result.p99_latency_us,
+        );
+    }
+}
+
+// `--studio FILE`, or `studio.toml` in the working directory if there is one.
+fn studio_path(args: &[String]) -> Option<String> {
+    flag_value::<String>(args, "--studio").or_else(|| {
+        Path::new(templates::DEFAULT_STUDIO)
+            .exists()
+            .then(|| templates::DEFAULT_STUDIO.to_string())
+    })
+}
+
+// Replaces the inventory with the studio file, if any, and takes up its
+// settings; false if the file couldn't be used.
+fn load_studio(args: &[String], registry: &Mutex<ArtistToolRegistry>) -> bool {
+    let Some(path) = studio_path(args) else {
+        return true;
+    };
+    match templates::StudioConfig::load(Path::new(&path)) {
+        Ok(studio) => {
+            let mut registry = registry.lock().expect("Failed to lock registry");
+            *registry
+                .shared_resources
+                .lock()
+                .expect("Failed to lock resources") = studio.resources();
+            reload::settle(&studio, pinned(args), &mut registry);
+            true
+        }
+        Err(error) => {
+            println!("{}", Message::FileError(&path, error.to_string()));
+            false
+        }
+    }
+}
+
+// Settings given by flag, which the studio file may not change.
+fn pinned(args: &[String]) -> reload::Pinned {
+    reload::Pinned {
+        loan_period: args.iter().any(|arg| arg == "--loan-days"),
+        quotas: args.iter().any(|arg| arg == "--tool-limits"),
+    }
+}
+
+// Low-stock alerts are printed, and also posted to `--notify URL` if given;
+// false if the URL isn't plain HTTP.
+fn add_notifiers(args: &[String], registry: &Mutex<ArtistToolRegistry>) -> bool {
+    let mut registry = registry.lock().expect("Failed to lock registry");
+    registry.add_notifier(alerts::ConsoleNotifier);
+    let Some(url) = flag_value::<String>(args, "--notify") else {
+        return true;
+    };
+    match alerts::WebhookNotifier::new(&url) {
+        Some(webhook) => {
+            registry.add_notifier(webhook);
+            true
+        }
+        None => {
+            println!("{}", Message::InvalidFlag("--notify", &url));
+            false
+        }
     }
+}
 
-    for handle in handles {
-        handle.join().expect("Thread panicked");
+// Seeds stock from a `--import-csv SHEET` spreadsheet, then overrides levels
+// with `--stock brush=20,red=5`, if given; false if either couldn't be used.
+fn load_stock(args: &[String], resources: &Mutex<SharedResources>) -> bool {
+    if let Some(path) = flag_value::<String>(args, "--import-csv") {
+        let imported = resources
+            .lock()
+            .expect("Failed to lock resources")
+            .import_csv(Path::new(&path));
+        if let Err(error) = imported {
+            println!("{}", Message::FileError(&path, error.to_string()));
+            return false;
+        }
+    }
+    let Some(list) = flag_value::<String>(args, "--stock") else {
+        return true;
+    };
+    let mut levels = vec![];
+    for pair in list
+        .split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+    {
+        match pair
+            .split_once('=')
+            .and_then(|(item, quantity)| Some((item.trim(), quantity.trim().parse().ok()?)))
+        {
+            Some((item, quantity)) => levels.push((item, quantity, pair)),
+            None => {
+                println!("{}", Message::InvalidFlag("--stock", pair));
+                return false;
+            }
+        }
     }
+    let mut resources = resources.lock().expect("Failed to lock resources");
+    for (item, quantity, pair) in levels {
+        let set = resources
+            .measure(item, quantity)
+            .and_then(|quantity| resources.set_quantity(item, quantity));
+        if set.is_err() {
+            println!("{}", Message::InvalidFlag("--stock", pair));
+            return false;
+        }
+    }
+    true
+}
 
-    println!("End");
+// Applies `--tool-limits FILE` if given; false if the file couldn't be used.
+fn load_tool_limits(args: &[String], registry: &Mutex<ArtistToolRegistry>) -> bool {
+    let Some(path) = flag_value::<String>(args, "--tool-limits") else {
+        return true;
+    };
+    match ToolLimits::load(Path::new(&path)) {
+        Ok(limits) => {
+            registry
+                .lock()
+                .expect("Failed to lock registry")
+                .set_tool_limits(limits);
+            true
+        }
+        Err(error) => {
+            println!("{}", Message::FileError(&path, error.to_string()));
+            false
+        }
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+// Applies `--rate-limit N/WINDOW` if given, e.g. `3/hour` checkouts per
+// artist; false if it couldn't be read.
+fn set_rate_limit(args: &[String], registry: &Mutex<ArtistToolRegistry>) -> bool {
+    let Some(limit) = flag_value::<String>(args, "--rate-limit") else {
+        return true;
+    };
+    match limit.parse::<RateLimiter>() {
+        Ok(limiter) => {
+            registry
+                .lock()
+                .expect("Failed to lock registry")
+                .set_rate_limiter(limiter);
+            true
+        }
+        Err(_) => {
+            println!("{}", Message::InvalidFlag("--rate-limit", &limit));
+            false
+        }
+    }
+}
 
-    #[test]
-    fn test_shared_resources_initialization() {
-        let resources = SharedResources::default();
-        assert_eq!(resources.tools.len(), TOTAL_ITEMS);
-        assert_eq!(resources.paints.len(), TOTAL_WEIGHT_KG);
+// Applies `--profiles FILE` if given; false if the file couldn't be used.
+fn load_profiles(args: &[String], registry: &Mutex<ArtistToolRegistry>) -> bool {
+    let Some(path) = flag_value::<String>(args, "--profiles") else {
+        return true;
+    };
+    match Profiles::load(Path::new(&path)) {
+        Ok(profiles) => {
+            registry
+                .lock()
+                .expect("Failed to lock registry")
+                .set_profiles(profiles);
+            true
+        }
+        Err(error) => {
+            println!("{}", Message::FileError(&path, error.to_string()));
+            false
+        }
     }
+}
 
-    #[test]
-    fn test_take_out_resources() {
-        let mut resources = SharedResources::default();
-        let initial_tool_count = resources.tools[0].1;
-        resources.take_out_resources(vec!["brush".to_string()]);
-        assert_eq!(resources.tools[0].1, initial_tool_count - 1);
+// Applies `--costs FILE` if given, letting departments overspend with
+// `--over-budget`; false if the file couldn't be used.
+fn load_costs(args: &[String], registry: &Mutex<ArtistToolRegistry>) -> bool {
+    let Some(path) = flag_value::<String>(args, "--costs") else {
+        return true;
+    };
+    let costs = match CostBook::load(Path::new(&path)) {
+        Ok(costs) => costs,
+        Err(error) => {
+            println!("{}", Message::FileError(&path, error.to_string()));
+            return false;
+        }
+    };
+    let mut registry = registry.lock().expect("Failed to lock registry");
+    registry.allow_over_budget(args.iter().any(|arg| arg == "--over-budget"));
+    match registry.set_costs(costs) {
+        Ok(()) => true,
+        Err(UnknownCurrency(currency)) => {
+            println!("{}", Message::UnknownCurrency(currency));
+            false
+        }
     }
+}
 
-    #[test]
-    fn test_tool_registry() {
-        let resources = Arc::new(Mutex::new(SharedResources::default()));
-        let mut registry = ArtistToolRegistry::new(&resources);
-        let tools = vec!["brush".to_string(), "palette".to_string()];
-        registry.tool_registry(1, tools);
-        assert_eq!(registry.artist_tool_preferences.len(), 1);
+// The profit and loss, if anything was booked, then this month's spend by
+// department.
+fn print_budget(registry: &ArtistToolRegistry) {
+    if !registry.budget.is_empty() {
+        match registry.budget.summary() {
+            Ok(summary) => println!("{}", Message::ProfitLoss(&summary)),
+            Err(MixedCurrencies(left, right)) => {
+                println!("{}", Message::MixedCurrencies(left, right))
+            }
+        }
     }
+    match registry.department_spend() {
+        Ok(spend) => {
+            for spend in &spend {
+                println!("{}", Message::DepartmentSpend(spend));
+            }
+        }
+        Err(MixedCurrencies(left, right)) => {
+            println!("{}", Message::MixedCurrencies(left, right))
+        }
+    }
+}
 
-    #[test]
-    fn test_tools_usage() {
-        let tools = vec![
-            ("brush".to_string(), TOTAL_ITEMS),
-            ("palette".to_string(), TOTAL_ITEMS),
-        ];
-        let (id, selected_tools) = tools_usage(1, &tools);
-        assert!(
-            selected_tools.len() >= MIN_REQUIRED_TOOLS && selected_tools.len() <= MAX_ALLOWED_TOOLS
-        );
+// `--drying-racks N` gives finished canvases N racks to dry on for
+// `--drying-hours H` (4 by default) before they can be sold; false if there
+// are no racks to dry on at all.
+fn set_drying(args: &[String], registry: &Mutex<ArtistToolRegistry>) -> bool {
+    let Some(capacity) = flag_value::<usize>(args, "--drying-racks") else {
+        return true;
+    };
+    if capacity == 0 {
+        println!("{}", Message::InvalidFlag("--drying-racks", "0"));
+        return false;
+    }
+    let hours = flag_value::<i64>(args, "--drying-hours").unwrap_or(4);
+    registry
+        .lock()
+        .expect("Failed to lock registry")
+        .set_drying_racks(capacity, chrono::Duration::hours(hours));
+    true
+}
+
+// The value given after `flag`, or None if the flag isn't there. A flag
+// with no value, or one that doesn't parse, ends the run with an error
+// rather than quietly falling back on the default.
+fn flag_value<T: std::str::FromStr>(args: &[String], flag: &str) -> Option<T> {
+    let pos = args.iter().position(|arg| arg == flag)?;
+    let value = args.get(pos + 1).map(String::as_str);
+    match value.map(str::parse) {
+        Some(Ok(value)) => Some(value),
+        _ => {
+            let value = value.unwrap_or_default();
+            println!("{}", Message::InvalidFlag(flag, value));
+            process::exit(2);
+        }
     }
 }
@@ -0,0 +1,168 @@
+use crate::{lock_stats::LockSnapshot, ArtistToolRegistry, State};
+use std::fmt::Write;
+
+// What `GET /metrics` serves, in the Prometheus text format.
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+fn label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn family(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+// Checkout counters, shelf stock per tool and how long callers have waited
+// for the registry lock.
+pub fn render(registry: &ArtistToolRegistry, lock: LockSnapshot) -> String {
+    let mut out = String::new();
+    let checkouts = registry
+        .history()
+        .filter(|entry| entry.state == Some(State::TakeOut))
+        .count();
+    family(
+        &mut out,
+        "rustic_canvas_checkouts_total",
+        "counter",
+        "Checkouts the registry lent tools for.",
+    );
+    let _ = writeln!(out, "rustic_canvas_checkouts_total {}", checkouts);
+    family(
+        &mut out,
+        "rustic_canvas_failed_checkouts_total",
+        "counter",
+        "Checkouts the registry refused.",
+    );
+    let _ = writeln!(
+        out,
+        "rustic_canvas_failed_checkouts_total {}",
+        registry.failed_checkouts()
+    );
+
+    family(
+        &mut out,
+        "rustic_canvas_tool_stock",
+        "gauge",
+        "Units of each tool on the shelf.",
+    );
+    let tools = registry
+        .shared_resources
+        .lock()
+        .expect("Failed to lock resources")
+        .tools
+        .amounts();
+    for (tool, quantity) in &tools {
+        let _ = writeln!(
+            out,
+            "rustic_canvas_tool_stock{{tool=\"{}\"}} {}",
+            label(tool),
+            quantity
+        );
+    }
+
+    family(
+        &mut out,
+        "rustic_canvas_registry_lock_acquisitions_total",
+        "counter",
+        "Times the registry lock was taken.",
+    );
+    let _ = writeln!(
+        out,
+        "rustic_canvas_registry_lock_acquisitions_total {}",
+        lock.acquisitions
+    );
+    family(
+        &mut out,
+        "rustic_canvas_registry_lock_contended_total",
+        "counter",
+        "Times a caller had to wait for the registry lock.",
+    );
+    let _ = writeln!(
+        out,
+        "rustic_canvas_registry_lock_contended_total {}",
+        lock.contended
+    );
+    family(
+        &mut out,
+        "rustic_canvas_registry_lock_wait_seconds_total",
+        "counter",
+        "Time spent waiting for the registry lock.",
+    );
+    let _ = writeln!(
+        out,
+        "rustic_canvas_registry_lock_wait_seconds_total {}",
+        lock.total_wait_ns as f64 / 1e9
+    );
+    family(
+        &mut out,
+        "rustic_canvas_registry_lock_max_wait_seconds",
+        "gauge",
+        "Longest single wait for the registry lock.",
+    );
+    let _ = writeln!(
+        out,
+        "rustic_canvas_registry_lock_max_wait_seconds {}",
+        lock.max_wait_ns as f64 / 1e9
+    );
+    #[cfg(feature = "sqlite")]
+    render_db_cache(&mut out, crate::sqlite::DB_CACHE.snapshot());
+    out
+}
+
+#[cfg(feature = "sqlite")]
+fn render_db_cache(out: &mut String, cache: crate::sqlite::CacheSnapshot) {
+    family(
+        out,
+        "rustic_canvas_db_cache_hits_total",
+        "counter",
+        "Database reads answered from the read cache.",
+    );
+    let _ = writeln!(out, "rustic_canvas_db_cache_hits_total {}", cache.hits);
+    family(
+        out,
+        "rustic_canvas_db_cache_misses_total",
+        "counter",
+        "Database reads the read cache had to pass on.",
+    );
+    let _ = writeln!(out, "rustic_canvas_db_cache_misses_total {}", cache.misses);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{resources::TOTAL_ITEMS, units::Count, SharedResources};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_render_reports_checkouts_stock_and_lock_wait() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        registry
+            .tool_registry(1, vec!["brush".to_string()])
+            .unwrap();
+        assert!(registry.tool_registry(1, vec!["kiln".to_string()]).is_err());
+        let lock = LockSnapshot {
+            acquisitions: 4,
+            contended: 1,
+            total_wait_ns: 1_500_000,
+            max_wait_ns: 1_500_000,
+        };
+
+        let text = render(&registry, lock);
+        assert!(text.contains("# TYPE rustic_canvas_checkouts_total counter\n"));
+        assert!(text.contains("\nrustic_canvas_checkouts_total 1\n"));
+        assert!(text.contains("\nrustic_canvas_failed_checkouts_total 1\n"));
+        assert!(text.contains(&format!(
+            "\nrustic_canvas_tool_stock{{tool=\"brush\"}} {}\n",
+            TOTAL_ITEMS - Count(1)
+        )));
+        assert!(text.contains("\nrustic_canvas_registry_lock_wait_seconds_total 0.0015\n"));
+        assert_eq!(label("a \"b\"\\"), "a \\\"b\\\"\\\\");
+        #[cfg(feature = "sqlite")]
+        assert!(text.contains("# TYPE rustic_canvas_db_cache_hits_total counter\n"));
+    }
+}
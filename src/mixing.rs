@@ -0,0 +1,163 @@
+use crate::{
+    error::ResourceError,
+    units::{Kilograms, Quantity},
+    SharedResources,
+};
+use std::fmt;
+
+// How close a blend must come to a named color, as a squared distance over
+// the three channels, to be stocked under that name rather than its hex code.
+pub const NAMED_MATCH_DISTANCE: u32 = 40 * 40;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+const NAMED: [(&str, Rgb); 10] = [
+    ("red", Rgb(255, 0, 0)),
+    ("blue", Rgb(0, 0, 255)),
+    ("green", Rgb(0, 128, 0)),
+    ("yellow", Rgb(255, 255, 0)),
+    ("black", Rgb(0, 0, 0)),
+    ("white", Rgb(255, 255, 255)),
+    ("purple", Rgb(128, 0, 128)),
+    ("orange", Rgb(255, 165, 0)),
+    ("pink", Rgb(255, 192, 203)),
+    ("brown", Rgb(139, 69, 19)),
+];
+
+impl Rgb {
+    // The value of a named paint, or of a mixture stocked as `#rrggbb`.
+    pub fn of(color: &str) -> Option<Rgb> {
+        if let Some(hex) = color.strip_prefix('#') {
+            if hex.len() != 6 {
+                return None;
+            }
+            let channel = |at: usize| u8::from_str_radix(hex.get(at..at + 2)?, 16).ok();
+            return Some(Rgb(channel(0)?, channel(2)?, channel(4)?));
+        }
+        NAMED
+            .iter()
+            .find(|(name, _)| *name == color)
+            .map(|&(_, rgb)| rgb)
+    }
+
+    fn distance(self, other: Rgb) -> u32 {
+        let channel = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+        channel(self.0, other.0) + channel(self.1, other.1) + channel(self.2, other.2)
+    }
+
+    // The named color this is close enough to pass for, else its hex code.
+    pub fn name(self) -> String {
+        NAMED
+            .iter()
+            .filter(|&&(_, named)| self.distance(named) <= NAMED_MATCH_DISTANCE)
+            .min_by_key(|&&(_, named)| self.distance(named))
+            .map(|(name, _)| name.to_string())
+            .unwrap_or_else(|| self.to_string())
+    }
+}
+
+impl fmt::Display for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.0, self.1, self.2)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MixedPaint {
+    pub color: String,
+    pub rgb: Rgb,
+    pub kg: Kilograms,
+    pub components: Vec<(String, Kilograms)>,
+}
+
+impl SharedResources {
+    // Blends the given kilograms of each paint into one new paint, stocked
+    // under the name of the blended color. Channels are averaged by weight,
+    // which is closer to mixing light than pigment but names blends well
+    // enough. Nothing is taken unless every component has enough left.
+    pub fn mix_paints(
+        &mut self,
+        components: &[(String, Kilograms)],
+    ) -> Result<MixedPaint, ResourceError> {
+        let kg: Kilograms = components.iter().map(|&(_, kg)| kg).sum();
+        if kg.is_zero() {
+            return Err(ResourceError::EmptyMix);
+        }
+        let mut sums = [0.0; 3];
+        for (color, weight) in components {
+            let rgb = Rgb::of(color).ok_or_else(|| ResourceError::NoColorValue(color.clone()))?;
+            for (sum, channel) in sums.iter_mut().zip([rgb.0, rgb.1, rgb.2]) {
+                *sum += channel as f64 * weight.get();
+            }
+        }
+        self.take_paints(components)?;
+        let average = |sum: f64| (sum / kg.get()).round() as u8;
+        let rgb = Rgb(average(sums[0]), average(sums[1]), average(sums[2]));
+        let color = rgb.name();
+        self.paints.add(&color, kg);
+        Ok(MixedPaint {
+            color,
+            rgb,
+            kg,
+            components: components.to_vec(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::TOTAL_WEIGHT_KG;
+
+    fn request(color: &str, kg: f64) -> (String, Kilograms) {
+        (color.to_string(), Kilograms::new(kg).unwrap())
+    }
+
+    #[test]
+    fn test_mix_paints_stocks_the_blend() {
+        let mut resources = SharedResources::default();
+        let orange = resources
+            .mix_paints(&[request("red", 2.0), request("yellow", 2.0)])
+            .unwrap();
+        assert_eq!(orange.color, "orange");
+        assert_eq!(orange.rgb, Rgb(255, 128, 0));
+        assert_eq!(
+            resources.paints.quantity("orange"),
+            TOTAL_WEIGHT_KG + Kilograms::whole(4)
+        );
+        assert_eq!(
+            resources.paints.quantity("red"),
+            TOTAL_WEIGHT_KG - Kilograms::whole(2)
+        );
+
+        let gray = resources
+            .mix_paints(&[request("blue", 0.5), request("yellow", 0.5)])
+            .unwrap();
+        assert_eq!(gray.color, "#808080");
+        assert_eq!(Rgb::of(&gray.color), Some(gray.rgb));
+        let darker = resources
+            .mix_paints(&[request("#808080", 0.5), request("black", 0.5)])
+            .unwrap();
+        assert_eq!(darker.color, "#404040");
+        assert_eq!(resources.paints.quantity("#808080"), Kilograms::grams(500));
+
+        assert_eq!(
+            resources.mix_paints(&[request("white", 1.0), request("red", 20.0)]),
+            Err(ResourceError::PaintUnderStock {
+                color: "red".to_string(),
+                requested_kg: Kilograms::whole(20),
+                available_kg: TOTAL_WEIGHT_KG - Kilograms::whole(2),
+            })
+        );
+        assert_eq!(resources.paints.quantity("white"), TOTAL_WEIGHT_KG);
+        assert_eq!(
+            resources.mix_paints(&[request("red", 0.0)]),
+            Err(ResourceError::EmptyMix)
+        );
+        assert_eq!(
+            resources.mix_paints(&[request("ochre", 1.0)]),
+            Err(ResourceError::NoColorValue("ochre".to_string()))
+        );
+    }
+}
@@ -0,0 +1,304 @@
+use serde::{Serialize, Serializer};
+use std::{collections::HashMap, fmt};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Currency([u8; 3]);
+
+impl Currency {
+    pub const USD: Currency = Currency(*b"USD");
+    pub const EUR: Currency = Currency(*b"EUR");
+
+    // What the ledger and budgets count in until a costs file names another.
+    pub const DEFAULT: Currency = Currency::USD;
+
+    // Parses an ISO 4217 style code such as `eur` or `GBP`.
+    pub fn parse(code: &str) -> Option<Self> {
+        let bytes: [u8; 3] = code.as_bytes().try_into().ok()?;
+        if !bytes.iter().all(u8::is_ascii_alphabetic) {
+            return None;
+        }
+        Some(Currency(bytes.map(|b| b.to_ascii_uppercase())))
+    }
+
+    pub fn code(&self) -> &str {
+        std::str::from_utf8(&self.0).expect("currency codes are ASCII")
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+impl Serialize for Currency {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.code())
+    }
+}
+
+// An amount in hundredths of its currency's unit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub struct Money {
+    pub minor_units: i64,
+    pub currency: Currency,
+}
+
+impl Money {
+    pub fn new(minor_units: i64, currency: Currency) -> Self {
+        Self {
+            minor_units,
+            currency,
+        }
+    }
+
+    pub fn zero(currency: Currency) -> Self {
+        Self::new(0, currency)
+    }
+
+    // Reads what `Display` writes, e.g. `12.05 USD`, with at most two
+    // decimals.
+    pub fn parse(text: &str) -> Option<Self> {
+        let (amount, code) = text.trim().split_once(' ')?;
+        let currency = Currency::parse(code.trim())?;
+        let (sign, amount) = match amount.strip_prefix('-') {
+            Some(amount) => (-1, amount),
+            None => (1, amount),
+        };
+        let (units, cents) = amount.split_once('.').unwrap_or((amount, ""));
+        if units.is_empty()
+            || cents.len() > 2
+            || !units
+                .bytes()
+                .chain(cents.bytes())
+                .all(|b| b.is_ascii_digit())
+        {
+            return None;
+        }
+        let cents: i64 = format!("{:0<2}", cents).parse().ok()?;
+        let minor_units = units
+            .parse::<i64>()
+            .ok()?
+            .checked_mul(100)?
+            .checked_add(cents)?;
+        Some(Money::new(sign * minor_units, currency))
+    }
+
+    // Shares the amount out in `parts` that add back up to it, the odd cents
+    // going to the first.
+    pub fn split(self, parts: usize) -> Vec<Money> {
+        let Ok(count) = i64::try_from(parts) else {
+            return vec![];
+        };
+        (0..count)
+            .map(|part| {
+                let odd = i64::from(part < self.minor_units.rem_euclid(count));
+                Money::new(self.minor_units.div_euclid(count) + odd, self.currency)
+            })
+            .collect()
+    }
+
+    // Sums of two currencies mean nothing until one is converted, so these
+    // refuse them rather than pick a side.
+    pub fn checked_add(self, other: Money) -> Result<Money, MixedCurrencies> {
+        self.same_currency(other)?;
+        Ok(Money::new(
+            self.minor_units + other.minor_units,
+            self.currency,
+        ))
+    }
+
+    pub fn checked_sub(self, other: Money) -> Result<Money, MixedCurrencies> {
+        self.same_currency(other)?;
+        Ok(Money::new(
+            self.minor_units - other.minor_units,
+            self.currency,
+        ))
+    }
+
+    fn same_currency(self, other: Money) -> Result<(), MixedCurrencies> {
+        if self.currency == other.currency {
+            Ok(())
+        } else {
+            Err(MixedCurrencies(self.currency, other.currency))
+        }
+    }
+
+    pub fn min(self, other: Money) -> Money {
+        debug_assert_eq!(self.currency, other.currency);
+        Money::new(self.minor_units.min(other.minor_units), self.currency)
+    }
+
+    // The amount without its currency code, e.g. `-12.05`.
+    pub fn decimal(&self) -> String {
+        let sign = if self.minor_units < 0 { "-" } else { "" };
+        let abs = self.minor_units.unsigned_abs();
+        format!("{}{}.{:02}", sign, abs / 100, abs % 100)
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.decimal(), self.currency)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnknownCurrency(pub Currency);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MixedCurrencies(pub Currency, pub Currency);
+
+// Static conversion table into the studio's base currency.
+#[derive(Debug, Clone)]
+pub struct ExchangeRates {
+    base: Currency,
+    rates: HashMap<Currency, f64>,
+}
+
+impl ExchangeRates {
+    pub fn new(base: Currency) -> Self {
+        Self {
+            base,
+            rates: HashMap::new(),
+        }
+    }
+
+    pub fn base(&self) -> Currency {
+        self.base
+    }
+
+    // `base_per_unit` is how much one unit of `currency` is worth in the base currency.
+    pub fn set_rate(&mut self, currency: Currency, base_per_unit: f64) {
+        self.rates.insert(currency, base_per_unit);
+    }
+
+    pub fn to_base(&self, money: Money) -> Result<Money, UnknownCurrency> {
+        if money.currency == self.base {
+            return Ok(money);
+        }
+        let rate = self
+            .rates
+            .get(&money.currency)
+            .ok_or(UnknownCurrency(money.currency))?;
+        let minor_units = (money.minor_units as f64 * rate).round() as i64;
+        Ok(Money::new(minor_units, self.base))
+    }
+
+    // Totals `amounts` in the base currency, converting each first.
+    pub fn sum(&self, amounts: impl IntoIterator<Item = Money>) -> Result<Money, UnknownCurrency> {
+        amounts
+            .into_iter()
+            .try_fold(Money::zero(self.base), |total, amount| {
+                let amount = self.to_base(amount)?;
+                Ok(Money::new(
+                    total.minor_units + amount.minor_units,
+                    self.base,
+                ))
+            })
+    }
+
+    // Converts through the base currency, so both ends need a rate unless
+    // one of them is the base.
+    pub fn convert(&self, money: Money, to: Currency) -> Result<Money, UnknownCurrency> {
+        if money.currency == to {
+            return Ok(money);
+        }
+        let base = self.to_base(money)?;
+        if to == self.base {
+            return Ok(base);
+        }
+        let rate = self.rates.get(&to).ok_or(UnknownCurrency(to))?;
+        let minor_units = (base.minor_units as f64 / rate).round() as i64;
+        Ok(Money::new(minor_units, to))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_currency_parse_and_display() {
+        assert_eq!(Currency::parse("eur"), Some(Currency::EUR));
+        assert_eq!(Currency::parse("EURO"), None);
+        assert_eq!(Currency::parse("E1R"), None);
+        assert_eq!(Money::new(-1_205, Currency::USD).to_string(), "-12.05 USD");
+        assert_eq!(
+            Money::parse("-12.05 USD"),
+            Some(Money::new(-1_205, Currency::USD))
+        );
+        assert_eq!(
+            Money::parse("7.5 eur"),
+            Some(Money::new(750, Currency::EUR))
+        );
+        assert_eq!(Money::parse("1.005 USD"), None);
+        assert_eq!(Money::parse("12"), None);
+    }
+
+    #[test]
+    fn test_split_adds_back_up() {
+        let shares = Money::new(1_001, Currency::USD).split(3);
+        assert_eq!(
+            shares
+                .iter()
+                .map(|share| share.minor_units)
+                .collect::<Vec<_>>(),
+            vec![334, 334, 333]
+        );
+        assert_eq!(
+            Money::new(-5, Currency::USD).split(2)[0].minor_units
+                + Money::new(-5, Currency::USD).split(2)[1].minor_units,
+            -5
+        );
+        assert!(Money::new(5, Currency::USD).split(0).is_empty());
+    }
+
+    #[test]
+    fn test_checked_ops_refuse_mixed_currencies() {
+        let usd = Money::new(1_000, Currency::USD);
+        let eur = Money::new(250, Currency::EUR);
+        assert_eq!(
+            usd.checked_sub(Money::new(250, Currency::USD)),
+            Ok(Money::new(750, Currency::USD))
+        );
+        assert_eq!(
+            usd.checked_add(eur),
+            Err(MixedCurrencies(Currency::USD, Currency::EUR))
+        );
+
+        let mut rates = ExchangeRates::new(Currency::USD);
+        rates.set_rate(Currency::EUR, 1.1);
+        assert_eq!(rates.sum([usd, eur]), Ok(Money::new(1_275, Currency::USD)));
+        assert_eq!(
+            ExchangeRates::new(Currency::EUR).sum([eur, usd]),
+            Err(UnknownCurrency(Currency::USD))
+        );
+    }
+
+    #[test]
+    fn test_convert_to_base_currency() {
+        let gbp = Currency::parse("GBP").unwrap();
+        let mut rates = ExchangeRates::new(Currency::EUR);
+        rates.set_rate(gbp, 1.17);
+
+        let converted = rates.to_base(Money::new(1_000, gbp)).unwrap();
+        assert_eq!(converted, Money::new(1_170, Currency::EUR));
+        assert_eq!(
+            rates.to_base(Money::new(5, Currency::EUR)),
+            Ok(Money::new(5, Currency::EUR))
+        );
+        assert_eq!(
+            rates.to_base(Money::new(5, Currency::USD)),
+            Err(UnknownCurrency(Currency::USD))
+        );
+        assert_eq!(
+            rates.convert(Money::new(1_170, Currency::EUR), gbp),
+            Ok(Money::new(1_000, gbp))
+        );
+        assert_eq!(
+            rates.convert(Money::new(5, gbp), Currency::USD),
+            Err(UnknownCurrency(Currency::USD))
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use crate::{
+    error::{RegistryError, ResourceError},
+    ArtistToolRegistry, SharedResources,
+};
+use std::collections::BTreeSet;
+
+// Lowercase letters and digits only, so "Sculpting Tool", "sculpting-tool"
+// and "sculptingtool" all come out the same.
+pub fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+impl SharedResources {
+    // The tool a request for `name` means: the tool of that exact name, else
+    // the one it is an alias of, else the one it matches once normalized.
+    // Tools the studio knows of count whether or not any are on the shelf. A
+    // name that fits nothing comes back as it is, to fail the usual way.
+    pub fn resolve_tool(&self, name: &str) -> Result<String, ResourceError> {
+        let known: BTreeSet<String> = self
+            .tools
+            .iter()
+            .map(|tool| tool.name.clone())
+            .chain(self.catalog.names().map(str::to_string))
+            .chain(self.loan_caps.loans().into_iter().map(|(tool, _)| tool))
+            .collect();
+        if known.contains(name) {
+            return Ok(name.to_string());
+        }
+        if let Some(tool) = self.catalog.alias(name) {
+            return Ok(tool.to_string());
+        }
+        let wanted = normalize(name);
+        let mut candidates: Vec<String> = known
+            .into_iter()
+            .filter(|tool| normalize(tool) == wanted)
+            .collect();
+        match candidates.len() {
+            0 => Ok(name.to_string()),
+            1 => Ok(candidates.remove(0)),
+            _ => Err(ResourceError::AmbiguousName {
+                name: name.to_string(),
+                candidates,
+            }),
+        }
+    }
+}
+
+impl ArtistToolRegistry {
+    pub(crate) fn resolve_tools(&self, tools: Vec<String>) -> Result<Vec<String>, RegistryError> {
+        let resources = self
+            .shared_resources
+            .lock()
+            .map_err(|poisoned| self.recover(poisoned))?;
+        tools
+            .iter()
+            .map(|tool| resources.resolve_tool(tool).map_err(RegistryError::from))
+            .collect()
+    }
+
+    pub(crate) fn resolve_tool(&self, tool: &str) -> Result<String, RegistryError> {
+        Ok(self.resolve_tools(vec![tool.to_string()])?.remove(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        money::{Currency, Money},
+        units::Count,
+    };
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_variants_and_aliases_resolve_to_the_tool() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        for variant in ["sculpting-tool", "sculptingtool", "Sculpting Tool"] {
+            assert_eq!(
+                resources.lock().unwrap().resolve_tool(variant),
+                Ok("sculpting tool".to_string())
+            );
+        }
+        let checkout = registry
+            .tool_registry(1, vec!["Paintbrush".to_string(), "Rags".to_string()])
+            .unwrap();
+        assert_eq!(checkout.lent, vec!["brush", "rags"]);
+        registry
+            .tool_return(1, vec!["paint-brush".to_string(), "rag".to_string()])
+            .unwrap();
+        assert_eq!(
+            resources.lock().unwrap().resolve_tool("easel"),
+            Ok("easel".to_string())
+        );
+
+        // Repairs, losses, write-offs, bookings and shelf sales resolve names the
+        // same way.
+        let brushes = resources.lock().unwrap().stock("brush");
+        registry
+            .tool_registry(1, vec!["brush".to_string(), "rags".to_string()])
+            .unwrap();
+        registry.report_damage(1, "Paintbrush").unwrap();
+        registry.send_to_repair(1, "paint-brush").unwrap();
+        registry.finish_repair(1, "Paintbrush").unwrap();
+        assert_eq!(resources.lock().unwrap().stock("brush"), brushes);
+        registry.report_lost(1, "Rag").unwrap();
+        registry.recover_lost(1, "rag").unwrap();
+        registry.tool_registry(1, vec!["rags".to_string()]).unwrap();
+        registry.report_damage(1, "Rag").unwrap();
+        registry.retire(1, "rag").unwrap();
+        assert!(registry.held_tools(1).is_empty());
+        let now = registry.now();
+        let booking = registry
+            .reserve(
+                3,
+                vec!["Paintbrush".to_string()],
+                now,
+                now + chrono::Duration::hours(1),
+            )
+            .unwrap();
+        assert_eq!(
+            registry.reservations.get(booking).unwrap().tools,
+            vec!["brush"]
+        );
+        let brushes = resources.lock().unwrap().stock("brush");
+        registry.retire_stock(0, "Paintbrush", 1).unwrap();
+        registry
+            .sell_stock(0, "paint-brush", 1, Money::new(500, Currency::USD))
+            .unwrap();
+        assert_eq!(resources.lock().unwrap().stock("brush"), brushes - Count(2));
+
+        resources
+            .lock()
+            .unwrap()
+            .restock("sculpting-tool", Count(1))
+            .unwrap();
+        assert_eq!(
+            registry.tool_registry(2, vec!["SculptingTool".to_string()]),
+            Err(RegistryError::Resource(ResourceError::AmbiguousName {
+                name: "SculptingTool".to_string(),
+                candidates: vec!["sculpting tool".to_string(), "sculpting-tool".to_string()],
+            }))
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use crate::{
+    alerts::LowStockAlert,
+    events::EventRecord,
+    units::{Amount, Kilograms},
+    ArtistToolRegistry, State,
+};
+use chrono::{DateTime, Utc};
+use std::{collections::BTreeMap, sync::mpsc};
+
+// What happened in the registry, for consumers that react to it as it
+// happens. Kinds nobody has asked for a shape of yet come through as the
+// logged event itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegistryEvent {
+    CheckedOut {
+        at: DateTime<Utc>,
+        artist_id: usize,
+        tools: Vec<String>,
+    },
+    Returned {
+        at: DateTime<Utc>,
+        artist_id: usize,
+        tools: Vec<String>,
+    },
+    Damaged {
+        at: DateTime<Utc>,
+        artist_id: usize,
+        tools: Vec<String>,
+    },
+    Lost {
+        at: DateTime<Utc>,
+        artist_id: usize,
+        tools: Vec<String>,
+    },
+    // Kilograms per color.
+    PaintUsed {
+        at: DateTime<Utc>,
+        artist_id: usize,
+        paints: BTreeMap<String, Kilograms>,
+    },
+    // Units or kilograms put on the shelf by a restock, a delivery or a
+    // transfer in.
+    Restocked {
+        at: DateTime<Utc>,
+        items: BTreeMap<String, Amount>,
+    },
+    RateLimited {
+        at: DateTime<Utc>,
+        artist_id: usize,
+        tools: Vec<String>,
+    },
+    LowStock(LowStockAlert),
+    Other(EventRecord),
+}
+
+impl From<&EventRecord> for RegistryEvent {
+    fn from(event: &EventRecord) -> Self {
+        let at = event.at;
+        let tools = event.items.clone();
+        match (event.kind, event.artist_id) {
+            (State::TakeOut, Some(artist_id)) => Self::CheckedOut {
+                at,
+                artist_id,
+                tools,
+            },
+            (State::Return, Some(artist_id)) => Self::Returned {
+                at,
+                artist_id,
+                tools,
+            },
+            (State::Damage, Some(artist_id)) => Self::Damaged {
+                at,
+                artist_id,
+                tools,
+            },
+            (State::Lost, Some(artist_id)) => Self::Lost {
+                at,
+                artist_id,
+                tools,
+            },
+            (State::Paint, Some(artist_id)) => Self::PaintUsed {
+                at,
+                artist_id,
+                paints: event
+                    .quantities
+                    .iter()
+                    .filter_map(|(color, quantity)| Some((color.clone(), quantity.kilograms()?)))
+                    .collect(),
+            },
+            (State::New | State::Fill | State::TransferIn, None) => Self::Restocked {
+                at,
+                items: event.quantities.clone(),
+            },
+            (State::RateLimited, Some(artist_id)) => Self::RateLimited {
+                at,
+                artist_id,
+                tools,
+            },
+            _ => Self::Other(event.clone()),
+        }
+    }
+}
+
+impl ArtistToolRegistry {
+    // Streams everything logged from now on, and every low-stock alert right
+    // after the event that raised it. Events are sent as they are logged, so
+    // receivers never need the registry or its lock. Dropping the receiver
+    // unsubscribes.
+    pub fn subscribe(&mut self) -> mpsc::Receiver<RegistryEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.observers.push(sender);
+        receiver
+    }
+
+    pub(crate) fn publish(&mut self, event: RegistryEvent) {
+        self.observers
+            .retain(|observer| observer.send(event.clone()).is_ok());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{units::Count, SharedResources};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_subscribers_see_checkouts_returns_and_alerts() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        let events = registry.subscribe();
+        let dropped = registry.subscribe();
+        drop(dropped);
+        let brushes = resources.lock().unwrap().stock("brush");
+        resources.lock().unwrap().low_stock.set("brush", brushes);
+
+        registry
+            .tool_registry(1, vec!["brush".to_string()])
+            .unwrap();
+        registry.tool_return(1, vec!["brush".to_string()]).unwrap();
+        registry.restock("easel", Count(2)).unwrap();
+        assert_eq!(registry.observers.len(), 1);
+
+        let received: Vec<RegistryEvent> = events.try_iter().collect();
+        assert_eq!(received.len(), 4);
+        assert!(matches!(
+            &received[0],
+            RegistryEvent::CheckedOut { artist_id: 1, tools, .. } if tools == &["brush"]
+        ));
+        assert!(matches!(
+            &received[1],
+            RegistryEvent::LowStock(alert) if alert.item == "brush" && alert.artist_id == 1
+        ));
+        assert!(matches!(
+            &received[2],
+            RegistryEvent::Returned { artist_id: 1, .. }
+        ));
+        assert!(matches!(
+            &received[3],
+            RegistryEvent::Restocked { items, .. } if items["easel"] == Count(2).into()
+        ));
+    }
+}
@@ -0,0 +1,144 @@
+use crate::{interner::Symbol, ArtistToolRegistry, State};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+pub const DEFAULT_LOAN_PERIOD: Duration = Duration::days(7);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Overdue {
+    pub artist_id: usize,
+    pub tool: String,
+    pub due: DateTime<Utc>,
+    pub late: Duration,
+}
+
+impl ArtistToolRegistry {
+    // Units still out past their due time, latest first.
+    pub fn overdue(&self) -> Vec<Overdue> {
+        self.overdue_at(self.now())
+    }
+
+    pub fn overdue_at(&self, now: DateTime<Utc>) -> Vec<Overdue> {
+        let mut overdue: Vec<Overdue> = self
+            .units_due()
+            .into_iter()
+            .filter_map(|((artist_id, symbol), due)| {
+                Some((artist_id, self.interner.resolve(symbol)?, due))
+            })
+            .flat_map(|(artist_id, tool, due)| {
+                due.into_iter()
+                    .filter(move |&due| due < now)
+                    .map(move |due| Overdue {
+                        artist_id,
+                        tool: tool.to_string(),
+                        due,
+                        late: now - due,
+                    })
+            })
+            .collect();
+        overdue.sort_by(|a, b| {
+            b.late
+                .cmp(&a.late)
+                .then_with(|| a.artist_id.cmp(&b.artist_id))
+                .then_with(|| a.tool.cmp(&b.tool))
+        });
+        overdue
+    }
+
+    // Logs an `Overdue` event per artist for units that have gone overdue
+    // since the last sweep, and returns them as reminders. Units already
+    // logged aren't reported again.
+    pub fn sweep_overdue(&mut self) -> Vec<Overdue> {
+        let now = self.now();
+        let mut reminders = self.overdue_at(now);
+        reminders.retain(|overdue| {
+            let symbol = self.interner.intern(&overdue.tool);
+            self.flagged_overdue
+                .insert((overdue.artist_id, symbol, overdue.due))
+        });
+        let mut by_artist: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+        for overdue in &reminders {
+            by_artist
+                .entry(overdue.artist_id)
+                .or_default()
+                .push(overdue.tool.clone());
+        }
+        for (artist_id, tools) in by_artist {
+            self.record_event(Some(artist_id), State::Overdue, &tools, now);
+        }
+        reminders
+    }
+
+    // When each unit an artist has out is due back, oldest loan first. Units
+    // recorded without a due time are due a loan period after they left.
+    fn units_due(&self) -> HashMap<(usize, Symbol), VecDeque<DateTime<Utc>>> {
+        let mut out: HashMap<(usize, Symbol), VecDeque<DateTime<Utc>>> = HashMap::new();
+        for entry in self.history() {
+            for &symbol in &entry.preferred_tools {
+                let key = (entry.artist_id, symbol);
+                if entry.source_state() == Some(State::TakeOut) {
+                    if let Some(due) = out.get_mut(&key) {
+                        due.pop_front();
+                    }
+                }
+                if entry.state == Some(State::TakeOut) {
+                    let due = entry
+                        .due
+                        .or_else(|| entry.datetime.map(|taken| taken + self.loan_period()));
+                    if let Some(due) = due {
+                        out.entry(key).or_default().push_back(due);
+                    }
+                }
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{clock::MockClock, SharedResources};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_sweep_flags_each_overdue_unit_once() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        let start = Utc::now();
+        let clock = MockClock::new(start);
+        registry.set_clock(Arc::new(clock.clone()));
+        registry.set_loan_period(Duration::days(1));
+
+        let checkout = registry
+            .tool_registry(1, vec!["brush".to_string(), "tape".to_string()])
+            .unwrap();
+        assert_eq!(checkout.due, Some(start + Duration::days(1)));
+        clock.advance(Duration::hours(12));
+        registry
+            .tool_registry(2, vec!["brush".to_string()])
+            .unwrap();
+        registry.tool_return(1, vec!["tape".to_string()]).unwrap();
+        assert!(registry.sweep_overdue().is_empty());
+
+        clock.advance(Duration::hours(15));
+        assert_eq!(
+            registry.overdue(),
+            vec![Overdue {
+                artist_id: 1,
+                tool: "brush".to_string(),
+                due: start + Duration::days(1),
+                late: Duration::hours(3),
+            }]
+        );
+        assert_eq!(registry.sweep_overdue().len(), 1);
+        let event = registry.events.last().unwrap();
+        assert_eq!((event.kind, event.artist_id), (State::Overdue, Some(1)));
+
+        clock.advance(Duration::days(1));
+        let reminders = registry.sweep_overdue();
+        assert_eq!(reminders.len(), 1);
+        assert_eq!(reminders[0].artist_id, 2);
+        assert_eq!(registry.overdue().len(), 2);
+    }
+}
@@ -0,0 +1,269 @@
+use crate::{
+    selection::SelectionStrategy,
+    stock::{Paint, Stock, Tool},
+    units::Kilograms,
+};
+use rand::{seq::SliceRandom, Rng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::{fs, io, path::Path, time::Duration};
+
+// How likely an artist is to reach for each favorite tool in stock before
+// picking the rest the usual way.
+pub const FAVORITE_CHANCE: f64 = 0.8;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SkillLevel {
+    Novice,
+    #[default]
+    Intermediate,
+    Expert,
+}
+
+impl SkillLevel {
+    // Kilograms of paint a round of work uses; practice wastes less.
+    pub fn paint_per_round(self) -> Kilograms {
+        match self {
+            SkillLevel::Novice => Kilograms::whole(3),
+            SkillLevel::Intermediate => Kilograms::whole(2),
+            SkillLevel::Expert => Kilograms::whole(1),
+        }
+    }
+}
+
+fn default_speed() -> f64 {
+    1.0
+}
+
+// Who an artist is, as far as the simulation cares. `speed` scales how fast
+// they work: 2.0 finishes a round in half the usual time.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArtistProfile {
+    pub id: usize,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub skill: SkillLevel,
+    #[serde(default)]
+    pub favorite_tools: Vec<String>,
+    // Any tool of these categories will do, e.g. "cleanup".
+    #[serde(default)]
+    pub favorite_categories: Vec<String>,
+    #[serde(default)]
+    pub favorite_colors: Vec<String>,
+    #[serde(default = "default_speed")]
+    pub speed: f64,
+}
+
+impl Default for ArtistProfile {
+    fn default() -> Self {
+        Self {
+            id: 0,
+            name: String::new(),
+            skill: SkillLevel::default(),
+            favorite_tools: vec![],
+            favorite_categories: vec![],
+            favorite_colors: vec![],
+            speed: default_speed(),
+        }
+    }
+}
+
+impl ArtistProfile {
+    pub fn work_time(&self, base: Duration) -> Duration {
+        base.div_f64(self.speed)
+    }
+
+    // One of the artist's favorite colors with enough paint left for a round,
+    // and how much of it they'll use. None without such a color.
+    pub fn pick_paint(
+        &self,
+        paints: &Stock<Paint>,
+        rng: &mut impl Rng,
+    ) -> Option<(String, Kilograms)> {
+        let kg = self.skill.paint_per_round();
+        let available: Vec<&String> = self
+            .favorite_colors
+            .iter()
+            .filter(|color| paints.quantity(color) >= kg)
+            .collect();
+        available.choose(rng).map(|color| (color.to_string(), kg))
+    }
+}
+
+// Artist profiles, one per artist id:
+//
+//   [[artists]]
+//   id = 3
+//   name = "Frida"
+//   skill = "expert"
+//   favorite_tools = ["brush", "palette knife"]
+//   favorite_categories = ["sculpting"]
+//   favorite_colors = ["red"]
+//   speed = 1.5
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Profiles {
+    #[serde(default)]
+    pub artists: Vec<ArtistProfile>,
+}
+
+impl Profiles {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let profiles: Self = toml::from_str(&fs::read_to_string(path)?)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error.to_string()))?;
+        if let Some(artist) = profiles
+            .artists
+            .iter()
+            .find(|artist| !(artist.speed.is_finite() && artist.speed > 0.0))
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("artist {} has speed {}", artist.id, artist.speed),
+            ));
+        }
+        Ok(profiles)
+    }
+
+    pub fn get(&self, artist_id: usize) -> Option<&ArtistProfile> {
+        self.artists.iter().find(|artist| artist.id == artist_id)
+    }
+}
+
+// Wraps another strategy so the artist's favorite tools come first: each one
+// in stock is taken with `FAVORITE_CHANCE`, then so is one tool in stock of
+// each favorite category, and `inner` picks the rest from what's left.
+pub struct Favoring {
+    pub favorites: Vec<String>,
+    pub categories: Vec<String>,
+    pub inner: Box<dyn SelectionStrategy>,
+}
+
+impl SelectionStrategy for Favoring {
+    fn select(&self, tools: &Stock<Tool>, count: usize, rng: &mut dyn RngCore) -> Vec<String> {
+        let mut chosen: Vec<String> = vec![];
+        for favorite in &self.favorites {
+            if chosen.len() == count {
+                break;
+            }
+            if tools.contains(favorite)
+                && !chosen.contains(favorite)
+                && rng.gen_bool(FAVORITE_CHANCE)
+            {
+                chosen.push(favorite.clone());
+            }
+        }
+        for category in &self.categories {
+            if chosen.len() == count {
+                break;
+            }
+            let candidates: Vec<&String> = tools
+                .iter()
+                .filter(|tool| tool.category == *category && !chosen.contains(&tool.name))
+                .map(|tool| &tool.name)
+                .collect();
+            if let Some(&tool) = candidates.choose(rng) {
+                if rng.gen_bool(FAVORITE_CHANCE) {
+                    chosen.push(tool.clone());
+                }
+            }
+        }
+        let rest: Stock<Tool> = tools
+            .iter()
+            .filter(|tool| !chosen.contains(&tool.name))
+            .map(|tool| (tool.name.clone(), tool.quantity))
+            .collect();
+        let remaining = count - chosen.len();
+        chosen.extend(self.inner.select(&rest, remaining, rng));
+        chosen
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{selection::Random, units::Count, SharedResources};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_profile_biases_tools_paint_and_pace() {
+        let profiles: Profiles = toml::from_str(
+            r#"
+            [[artists]]
+            id = 3
+            name = "Frida"
+            skill = "expert"
+            favorite_tools = ["brush"]
+            favorite_colors = ["red", "teal"]
+            speed = 2.0
+
+            [[artists]]
+            id = 4
+            "#,
+        )
+        .unwrap();
+        let frida = profiles.get(3).unwrap();
+        assert_eq!(frida.skill, SkillLevel::Expert);
+        assert_eq!(
+            frida.work_time(Duration::from_millis(10)),
+            Duration::from_millis(5)
+        );
+        assert_eq!(profiles.get(4).unwrap().speed, 1.0);
+        assert!(profiles.get(5).is_none());
+
+        let rng = &mut StdRng::seed_from_u64(1);
+        let tools: Stock<Tool> = ["brush", "tape", "easel", "palette"]
+            .into_iter()
+            .map(|name| (name.to_string(), Count(2)))
+            .collect();
+        let favoring = Favoring {
+            favorites: frida.favorite_tools.clone(),
+            categories: vec![],
+            inner: Box::new(Random),
+        };
+        let picks: Vec<Vec<String>> = (0..50).map(|_| favoring.select(&tools, 2, rng)).collect();
+        assert!(picks
+            .iter()
+            .all(|pick| pick.len() == 2 && pick[0] != pick[1]));
+        let with_brush = picks
+            .iter()
+            .filter(|pick| pick.contains(&"brush".to_string()))
+            .count();
+        assert!(with_brush > 35);
+
+        let studio = SharedResources::default();
+        let cleaning = Favoring {
+            favorites: vec![],
+            categories: vec!["cleanup".to_string()],
+            inner: Box::new(Random),
+        };
+        let with_cleanup = (0..50)
+            .filter(|_| {
+                cleaning
+                    .select(&studio.tools, 1, rng)
+                    .iter()
+                    .all(|tool| studio.catalog.category(tool) == "cleanup")
+            })
+            .count();
+        assert!(with_cleanup > 35);
+
+        let paints: Stock<Paint> = [
+            ("red".to_string(), Kilograms::whole(5)),
+            ("blue".to_string(), Kilograms::whole(5)),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            frida.pick_paint(&paints, rng),
+            Some(("red".to_string(), Kilograms::whole(1)))
+        );
+        let novice = ArtistProfile {
+            skill: SkillLevel::Novice,
+            favorite_colors: vec!["blue".to_string()],
+            ..ArtistProfile::default()
+        };
+        let low: Stock<Paint> = [("blue".to_string(), Kilograms::grams(2_500))]
+            .into_iter()
+            .collect();
+        assert_eq!(novice.pick_paint(&low, rng), None);
+    }
+}
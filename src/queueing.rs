@@ -0,0 +1,177 @@
+use std::{collections::BTreeMap, time::Instant};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestTiming {
+    pub tool: String,
+    pub arrival: Instant,
+    pub service_start: Instant,
+    pub departure: Instant,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolQueueMetrics {
+    pub tool: String,
+    pub requests: usize,
+    pub avg_wait_ms: f64,
+    pub p95_wait_ms: f64,
+    pub utilization: f64,
+    pub throughput_per_sec: f64,
+    // Time-averaged number of requests in the system, measured directly...
+    pub observed_in_system: f64,
+    // ...and as predicted by Little's law (arrival rate times time in system).
+    pub littles_law_in_system: f64,
+}
+
+impl ToolQueueMetrics {
+    // Relative gap between the measured and predicted queue length.
+    pub fn littles_law_error(&self) -> f64 {
+        if self.littles_law_in_system == 0.0 {
+            return 0.0;
+        }
+        (self.observed_in_system - self.littles_law_in_system).abs() / self.littles_law_in_system
+    }
+}
+
+// Arrival, service, and departure times of checkout requests, one sample per
+// requested tool, for capacity analysis after a run.
+#[derive(Debug, Default)]
+pub struct QueueStats {
+    samples: Vec<RequestTiming>,
+}
+
+impl QueueStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, timing: RequestTiming) {
+        self.samples.push(timing);
+    }
+
+    pub fn summary(&self) -> Vec<ToolQueueMetrics> {
+        let mut by_tool: BTreeMap<&str, Vec<&RequestTiming>> = BTreeMap::new();
+        for sample in &self.samples {
+            by_tool.entry(&sample.tool).or_default().push(sample);
+        }
+        by_tool
+            .into_iter()
+            .map(|(tool, samples)| tool_metrics(tool, &samples))
+            .collect()
+    }
+}
+
+fn tool_metrics(tool: &str, samples: &[&RequestTiming]) -> ToolQueueMetrics {
+    let start = samples.iter().map(|s| s.arrival).min().expect("non-empty");
+    let end = samples
+        .iter()
+        .map(|s| s.departure)
+        .max()
+        .expect("non-empty");
+    let window = end.duration_since(start).as_secs_f64();
+
+    let mut waits: Vec<f64> = samples
+        .iter()
+        .map(|s| s.service_start.duration_since(s.arrival).as_secs_f64() * 1000.0)
+        .collect();
+    waits.sort_by(f64::total_cmp);
+    let busy: f64 = samples
+        .iter()
+        .map(|s| s.departure.duration_since(s.service_start).as_secs_f64())
+        .sum();
+    let time_in_system: f64 = samples
+        .iter()
+        .map(|s| s.departure.duration_since(s.arrival).as_secs_f64())
+        .sum();
+
+    let requests = samples.len();
+    let per_window = |value: f64| if window > 0.0 { value / window } else { 0.0 };
+    let arrival_rate = per_window(requests as f64);
+    ToolQueueMetrics {
+        tool: tool.to_string(),
+        requests,
+        avg_wait_ms: waits.iter().sum::<f64>() / requests as f64,
+        p95_wait_ms: percentile(&waits, 0.95),
+        utilization: per_window(busy).min(1.0),
+        throughput_per_sec: arrival_rate,
+        observed_in_system: per_window(integrate_in_system(samples, start)),
+        littles_law_in_system: arrival_rate * (time_in_system / requests as f64),
+    }
+}
+
+// Sweeps arrivals and departures to integrate the number of requests in the
+// system over time, independently of the per-request sojourn times.
+fn integrate_in_system(samples: &[&RequestTiming], start: Instant) -> f64 {
+    let mut events: Vec<(f64, i64)> = samples
+        .iter()
+        .flat_map(|s| {
+            [
+                (s.arrival.duration_since(start).as_secs_f64(), 1),
+                (s.departure.duration_since(start).as_secs_f64(), -1),
+            ]
+        })
+        .collect();
+    events.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+
+    let mut area = 0.0;
+    let mut in_system = 0;
+    let mut last = 0.0;
+    for (at, delta) in events {
+        area += in_system as f64 * (at - last);
+        in_system += delta;
+        last = at;
+    }
+    area
+}
+
+fn percentile(sorted: &[f64], fraction: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (fraction * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn timing(base: Instant, tool: &str, arrival: u64, start: u64, end: u64) -> RequestTiming {
+        RequestTiming {
+            tool: tool.to_string(),
+            arrival: base + Duration::from_millis(arrival),
+            service_start: base + Duration::from_millis(start),
+            departure: base + Duration::from_millis(end),
+        }
+    }
+
+    #[test]
+    fn test_summary_computes_wait_and_utilization() {
+        let base = Instant::now();
+        let mut stats = QueueStats::new();
+        stats.record(timing(base, "brush", 0, 0, 100));
+        stats.record(timing(base, "brush", 0, 100, 200));
+        stats.record(timing(base, "tape", 50, 50, 60));
+
+        let summary = stats.summary();
+        assert_eq!(summary.len(), 2);
+        let brush = &summary[0];
+        assert_eq!(brush.tool, "brush");
+        assert_eq!(brush.requests, 2);
+        assert!((brush.avg_wait_ms - 50.0).abs() < 1e-6);
+        assert!((brush.p95_wait_ms - 100.0).abs() < 1e-6);
+        assert!((brush.utilization - 1.0).abs() < 1e-6);
+        assert!((brush.throughput_per_sec - 10.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_littles_law_holds_for_consistent_samples() {
+        let base = Instant::now();
+        let mut stats = QueueStats::new();
+        for i in 0..10 {
+            stats.record(timing(base, "canvas", i * 10, i * 15, i * 15 + 20));
+        }
+        let canvas = &stats.summary()[0];
+        assert!(canvas.littles_law_error() < 1e-9);
+    }
+}
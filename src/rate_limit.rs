@@ -0,0 +1,207 @@
+use crate::clock;
+use chrono::{DateTime, Duration, Utc};
+use std::{
+    collections::{HashMap, VecDeque},
+    str::FromStr,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RateKey {
+    Artist(usize),
+    ApiKey(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimited {
+    pub retry_after: Duration,
+}
+
+impl RateLimited {
+    // Status a request handler should answer with when it surfaces this error.
+    pub const HTTP_STATUS: u16 = 429;
+}
+
+#[derive(Debug, Clone)]
+struct TokenBucket {
+    tokens: f64,
+    last_refill: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+enum Limit {
+    Bucket {
+        capacity: f64,
+        refill_per_second: f64,
+        buckets: HashMap<RateKey, TokenBucket>,
+    },
+    // When each of the key's operations in the last `window` happened,
+    // oldest first.
+    Window {
+        max: usize,
+        window: Duration,
+        recent: HashMap<RateKey, VecDeque<DateTime<Utc>>>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    limit: Limit,
+}
+
+impl RateLimiter {
+    // `capacity` operations may burst at once; afterwards tokens trickle back
+    // at `refill_per_second`.
+    pub fn new(capacity: u32, refill_per_second: f64) -> Self {
+        Self {
+            limit: Limit::Bucket {
+                capacity: f64::from(capacity),
+                refill_per_second,
+                buckets: HashMap::new(),
+            },
+        }
+    }
+
+    // At most `max` operations per key in any `window`, going by whatever
+    // time the caller passes, so simulated hours count like real ones.
+    pub fn per_window(max: u32, window: Duration) -> Self {
+        Self {
+            limit: Limit::Window {
+                max: max as usize,
+                window,
+                recent: HashMap::new(),
+            },
+        }
+    }
+
+    pub fn check(&mut self, key: &RateKey, now: DateTime<Utc>) -> Result<(), RateLimited> {
+        match &mut self.limit {
+            Limit::Bucket {
+                capacity,
+                refill_per_second,
+                buckets,
+            } => check_bucket(buckets, *capacity, *refill_per_second, key, now),
+            Limit::Window {
+                max,
+                window,
+                recent,
+            } => {
+                let recent = recent.entry(key.clone()).or_default();
+                while recent.front().is_some_and(|&at| at + *window <= now) {
+                    recent.pop_front();
+                }
+                if recent.len() < *max {
+                    recent.push_back(now);
+                    return Ok(());
+                }
+                let retry_after = match recent.front() {
+                    Some(&oldest) => oldest + *window - now,
+                    None => Duration::max_value(),
+                };
+                Err(RateLimited { retry_after })
+            }
+        }
+    }
+}
+
+fn check_bucket(
+    buckets: &mut HashMap<RateKey, TokenBucket>,
+    capacity: f64,
+    refill_per_second: f64,
+    key: &RateKey,
+    now: DateTime<Utc>,
+) -> Result<(), RateLimited> {
+    let bucket = buckets.entry(key.clone()).or_insert(TokenBucket {
+        tokens: capacity,
+        last_refill: now,
+    });
+
+    let elapsed = (now - bucket.last_refill).num_milliseconds().max(0) as f64 / 1000.0;
+    bucket.tokens = (bucket.tokens + elapsed * refill_per_second).min(capacity);
+    bucket.last_refill = now;
+
+    if bucket.tokens >= 1.0 {
+        bucket.tokens -= 1.0;
+        return Ok(());
+    }
+
+    let missing = 1.0 - bucket.tokens;
+    let retry_after = if refill_per_second > 0.0 {
+        Duration::milliseconds((missing / refill_per_second * 1000.0).ceil() as i64)
+    } else {
+        Duration::max_value()
+    };
+    Err(RateLimited { retry_after })
+}
+
+// `3/hour`, `10/30m` or `1/90s`: that many operations per window. A window
+// without a number is one of its unit.
+impl FromStr for RateLimiter {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid rate limit '{}'", text);
+        let (max, window) = text.split_once('/').ok_or_else(invalid)?;
+        let max = max.trim().parse().map_err(|_| invalid())?;
+        let window = clock::parse_span(window.trim()).ok_or_else(invalid)?;
+        if window <= Duration::zero() {
+            return Err(invalid());
+        }
+        Ok(Self::per_window(max, window))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_allows_burst_then_limits() {
+        let mut limiter = RateLimiter::new(2, 1.0);
+        let now = Utc::now();
+        let key = RateKey::Artist(1);
+        assert!(limiter.check(&key, now).is_ok());
+        assert!(limiter.check(&key, now).is_ok());
+        let limited = limiter.check(&key, now).unwrap_err();
+        assert_eq!(limited.retry_after, Duration::seconds(1));
+    }
+
+    #[test]
+    fn test_bucket_refills_over_time_and_keys_are_independent() {
+        let mut limiter = RateLimiter::new(1, 2.0);
+        let now = Utc::now();
+        let artist = RateKey::Artist(1);
+        assert!(limiter.check(&artist, now).is_ok());
+        assert!(limiter.check(&artist, now).is_err());
+        assert!(limiter
+            .check(&RateKey::ApiKey("kiosk".to_string()), now)
+            .is_ok());
+        assert!(limiter
+            .check(&artist, now + Duration::milliseconds(500))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_window_counts_operations_within_the_hour() {
+        let mut limiter: RateLimiter = "3/hour".parse().unwrap();
+        let start = Utc::now();
+        let key = RateKey::Artist(1);
+        for minute in [0, 10, 20] {
+            assert!(limiter
+                .check(&key, start + Duration::minutes(minute))
+                .is_ok());
+        }
+        let limited = limiter
+            .check(&key, start + Duration::minutes(45))
+            .unwrap_err();
+        assert_eq!(limited.retry_after, Duration::minutes(15));
+        // The first checkout has left the window.
+        assert!(limiter.check(&key, start + Duration::minutes(60)).is_ok());
+        assert!(limiter.check(&key, start + Duration::minutes(61)).is_err());
+        assert!(limiter.check(&RateKey::Artist(2), start).is_ok());
+
+        assert!("10/30m".parse::<RateLimiter>().is_ok());
+        for invalid in ["3", "x/hour", "3/fortnight", "3/0h"] {
+            assert!(invalid.parse::<RateLimiter>().is_err(), "{}", invalid);
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use crate::{
+    error::RegistryError, i18n::Message, interner::Symbol, registry::HELD_STATES, units::Amount,
+    ArtistToolRegistry, SharedResources, State,
+};
+use std::{
+    collections::BTreeMap,
+    sync::{MutexGuard, PoisonError},
+};
+
+// An item whose count didn't match the registry's records when a poisoned
+// lock was recovered, and what it was put back to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Restored<T> {
+    pub item: String,
+    pub found: T,
+    pub restored: T,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PoisonRecovery {
+    // Shelf stock, from the last level the event log recorded.
+    pub stock: Vec<Restored<Amount>>,
+    // Loan counter, from the units the history says are out.
+    pub on_loan: Vec<Restored<usize>>,
+}
+
+impl PoisonRecovery {
+    pub fn is_empty(&self) -> bool {
+        self.stock.is_empty() && self.on_loan.is_empty()
+    }
+}
+
+impl ArtistToolRegistry {
+    // Puts `resources` back in line with what the registry recorded: each
+    // item's shelf stock to the level its last event left it at, and each
+    // tool's loan count to the units the history has out. Whatever a holder
+    // changed and never got to record is undone. Items the registry has never
+    // touched are left as they are.
+    pub fn reconcile(&self, resources: &mut SharedResources) -> PoisonRecovery {
+        let mut recovery = PoisonRecovery::default();
+        let mut logged: BTreeMap<Symbol, Amount> = BTreeMap::new();
+        for event in self.events.events() {
+            for (&item, &stock) in &event.stock {
+                logged.insert(item, stock);
+            }
+        }
+        for (item, stock) in logged
+            .into_iter()
+            .filter_map(|(symbol, stock)| Some((self.events.name(symbol)?, stock)))
+        {
+            let found = resources.amount_of(item);
+            if found != stock && resources.set_quantity(item, stock).is_ok() {
+                recovery.stock.push(Restored {
+                    item: item.to_string(),
+                    found,
+                    restored: stock,
+                });
+            }
+        }
+
+        let on_loan = |state: Option<State>| {
+            state.is_some_and(|state| HELD_STATES.contains(&state) && state != State::Lost)
+        };
+        let mut held: BTreeMap<String, usize> = resources
+            .loan_caps
+            .loans()
+            .into_iter()
+            .map(|(tool, _)| (tool, 0))
+            .collect();
+        for entry in &self.artist_tool_preferences {
+            let from = entry.source_state();
+            for &symbol in &entry.preferred_tools {
+                let Some(tool) = self.interner.resolve(symbol) else {
+                    continue;
+                };
+                let count = held.entry(tool.to_string()).or_insert(0);
+                if on_loan(from) {
+                    *count = count.saturating_sub(1);
+                }
+                if on_loan(entry.state) {
+                    *count += 1;
+                }
+            }
+        }
+        for (tool, count) in held {
+            let found = resources.loan_caps.on_loan(&tool);
+            if found != count {
+                resources.loan_caps.set_on_loan(&tool, count);
+                recovery.on_loan.push(Restored {
+                    item: tool,
+                    found,
+                    restored: count,
+                });
+            }
+        }
+        recovery
+    }
+
+    // For a lock on the shared resources that came back poisoned: reconciles
+    // what's behind it, clears the poison so the next caller gets sound
+    // stock, and hands back the error for this caller, whose own change
+    // never happened.
+    pub(crate) fn recover(
+        &self,
+        poisoned: PoisonError<MutexGuard<'_, SharedResources>>,
+    ) -> RegistryError {
+        drop(self.recovered(poisoned));
+        RegistryError::LockPoisoned
+    }
+
+    // The same reconciling, for callers that only read or log and have no
+    // error to hand back: they carry on with the reconciled stock.
+    pub(crate) fn recovered<'a>(
+        &self,
+        poisoned: PoisonError<MutexGuard<'a, SharedResources>>,
+    ) -> MutexGuard<'a, SharedResources> {
+        let mut resources = poisoned.into_inner();
+        let recovery = self.reconcile(&mut resources);
+        self.shared_resources.clear_poison();
+        tracing::warn!(
+            stock = recovery.stock.len(),
+            on_loan = recovery.on_loan.len(),
+            "{}",
+            Message::LockRecovered(&recovery)
+        );
+        resources
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chaos;
+    use std::{
+        panic,
+        sync::{Arc, Mutex},
+        thread,
+    };
+
+    #[test]
+    fn test_poisoned_checkout_is_refused_and_stock_put_back() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        registry
+            .tool_registry(1, vec!["brush".to_string()])
+            .unwrap();
+        let brushes = resources.lock().unwrap().stock("brush");
+
+        // A holder takes a brush and a loan slot, then panics before the
+        // registry records anything.
+        thread::scope(|scope| {
+            let _ = scope
+                .spawn(|| {
+                    let mut held = resources.lock().unwrap();
+                    held.take_out_all(&["brush".to_string()]).unwrap();
+                    panic::resume_unwind(Box::new("mid-checkout"));
+                })
+                .join();
+        });
+        assert!(matches!(
+            registry.tool_registry(2, vec!["brush".to_string()]),
+            Err(RegistryError::LockPoisoned)
+        ));
+        assert!(!resources.is_poisoned());
+        assert_eq!(resources.lock().unwrap().stock("brush"), brushes);
+        assert_eq!(resources.lock().unwrap().loan_caps.on_loan("brush"), 1);
+
+        registry
+            .tool_registry(2, vec!["brush".to_string()])
+            .unwrap();
+        assert_eq!(registry.audit(0).discrepancies().count(), 0);
+
+        // Poisoning with nothing half-done has nothing to put back.
+        chaos::poison(&resources);
+        let mut stock = resources
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        assert!(registry.reconcile(&mut stock).is_empty());
+        drop(stock);
+
+        // Callers with no error to hand back carry on with the lock cleared.
+        let stock = resources
+            .lock()
+            .unwrap_or_else(|poisoned| registry.recovered(poisoned));
+        assert_eq!(stock.loan_caps.on_loan("brush"), 2);
+        drop(stock);
+        assert!(!resources.is_poisoned());
+    }
+}
@@ -0,0 +1,2011 @@
+use crate::{
+    alerts::{LowStockAlert, Notifier},
+    artwork::Gallery,
+    budgets::{CostKind, DepartmentBudgets},
+    clock::{Clock, SystemClock},
+    costs::{CashFlow, CostBook, StudioBudget},
+    crdt::CrdtInventory,
+    deliveries::{Delivery, FillEntry},
+    deposits::Deposits,
+    drying::DryingRacks,
+    dump::StateDump,
+    error::{RegistryError, ResourceError, UnavailableTools},
+    events::{EventLog, EventRecord},
+    expiry::PaintBatch,
+    fairness::Starvation,
+    i18n::Message,
+    interner::{Interner, Symbol},
+    ledger::{AccountCodes, Ledger, LedgerEvent},
+    loan_caps::QueuedCheckout,
+    money::{Currency, ExchangeRates, Money, UnknownCurrency},
+    observer::RegistryEvent,
+    overdue::DEFAULT_LOAN_PERIOD,
+    profiles::{ArtistProfile, Profiles},
+    rate_limit::{RateKey, RateLimiter},
+    repairs::RepairQueue,
+    reservations::Reservations,
+    serials::{moves_units, SerialBook},
+    tool_limits::{ToolCountError, ToolCountRange, ToolLimits},
+    units::{Amount, Count, Kilograms},
+    wear::Wear,
+    SharedResources,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs, io,
+    path::Path,
+    sync::{mpsc, Arc, Mutex},
+};
+
+// What a checkout did with each requested tool. Capped tools are either
+// queued for the artist or refused, depending on the loan cap policy.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkout {
+    pub lent: Vec<String>,
+    pub queued: Vec<String>,
+    pub refused: Vec<String>,
+    // When the lent tools are due back.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due: Option<DateTime<Utc>>,
+}
+
+// One artist's share of a group checkout.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckoutRequest {
+    pub artist_id: usize,
+    pub tools: Vec<String>,
+}
+
+// An event taken while the resources were locked, and the alerts it
+// raised, waiting to be published.
+struct LoggedEvent {
+    event: EventRecord,
+    alerts: Vec<LowStockAlert>,
+}
+
+// How the units an event covers moved: the state they left, if they were
+// held, and their serials by tool.
+#[derive(Default)]
+struct Moved {
+    from: Option<State>,
+    serials: BTreeMap<String, Vec<usize>>,
+}
+
+// Groups the serials of units moved, listed alongside their tools, by tool.
+fn by_tool(tools: &[String], serials: &[usize]) -> BTreeMap<String, Vec<usize>> {
+    let mut grouped: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (tool, &serial) in tools.iter().zip(serials) {
+        grouped.entry(tool.clone()).or_default().push(serial);
+    }
+    grouped
+}
+
+pub const MIN_REQUIRED_TOOLS: usize = 2;
+pub const MAX_ALLOWED_TOOLS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum State {
+    TakeOut,
+    Return,
+    // Stock delivered onto the shelf.
+    Fill,
+    // Paint an artist draws into an artwork; it's used up, never returned.
+    Paint,
+    Change,
+    New,
+    Retire,
+    Damage,
+    Lost,
+    Audit,
+    Reserved,
+    Repair,
+    Expired,
+    Sold,
+    // Logged when a lent unit passes its due time; units never move into it.
+    Overdue,
+    // Logged when the rate limiter turns a checkout away; nothing moves.
+    RateLimited,
+    // Shelf stock sent to another studio, and stock received from one.
+    TransferOut,
+    TransferIn,
+}
+
+// Which states a tool unit may move to from `from`. A unit on the shelf can
+// be lent, reserved, counted, retired or sold; once lent it comes back, is
+// damaged or goes missing; damaged units are repaired or retired.
+pub fn next_states(from: State) -> &'static [State] {
+    match from {
+        State::New | State::Return | State::Audit | State::Expired => &[
+            State::TakeOut,
+            State::Reserved,
+            State::Audit,
+            State::Retire,
+            State::Sold,
+        ],
+        State::Reserved => &[State::TakeOut, State::Expired],
+        State::TakeOut => &[State::Return, State::Damage, State::Lost],
+        State::Damage => &[State::Repair, State::Retire],
+        State::Repair => &[State::Return],
+        State::Lost => &[State::Return, State::Retire],
+        State::Retire | State::Sold | State::Overdue | State::RateLimited => &[],
+        // Deliveries, paint draws, preference changes and stock moved between
+        // studios aren't steps in a tool's life.
+        State::Fill | State::Paint | State::Change | State::TransferOut | State::TransferIn => &[],
+    }
+}
+
+pub fn transition(from: State, to: State) -> Result<(), RegistryError> {
+    if next_states(from).contains(&to) {
+        Ok(())
+    } else {
+        Err(RegistryError::InvalidStateTransition { from, to })
+    }
+}
+
+// States in which a unit is still the artist's responsibility, in the order
+// a transition looks for a unit to move.
+pub const HELD_STATES: [State; 4] = [State::TakeOut, State::Damage, State::Repair, State::Lost];
+
+#[derive(Default)]
+pub struct ArtistToolPreferences {
+    pub artist_id: usize,
+    pub preferred_tools: Vec<Symbol>,
+    pub datetime: Option<DateTime<Utc>>,
+    pub state: Option<State>,
+    // The state the listed units left. None for checkouts off the shelf.
+    pub from: Option<State>,
+    // Kilograms per color, on `Fill` entries.
+    pub paints: Vec<(Symbol, Kilograms)>,
+    // When the units are due back, on `TakeOut` entries.
+    pub due: Option<DateTime<Utc>>,
+    // The serial of each unit in `preferred_tools`, on entries that move units.
+    pub serials: Vec<usize>,
+}
+
+impl ArtistToolPreferences {
+    // The state the units left. Returns recorded before transitions were
+    // tracked came from plain checkouts.
+    pub fn source_state(&self) -> Option<State> {
+        match (self.from, self.state) {
+            (None, Some(State::Return)) => Some(State::TakeOut),
+            (from, _) => from,
+        }
+    }
+}
+
+pub struct ArtistToolRegistry {
+    pub artist_tool_preferences: Vec<ArtistToolPreferences>,
+    pub shared_resources: Arc<Mutex<SharedResources>>,
+    rate_limiter: Option<RateLimiter>,
+    tool_limits: Option<ToolLimits>,
+    profiles: Profiles,
+    pub deposits: Deposits,
+    pub ledger: Ledger,
+    pub interner: Interner,
+    pub reservations: Reservations,
+    pub events: EventLog,
+    // Supplier deliveries, oldest first.
+    pub fills: Vec<FillEntry>,
+    pub repairs: RepairQueue,
+    pub gallery: Gallery,
+    // Where finished canvases dry before they're sold, if the studio has any.
+    pub racks: Option<DryingRacks>,
+    pub(crate) drying_time: Duration,
+    // Counters for merging stock changes back in, when this is an offline
+    // branch of another studio's inventory.
+    pub branch: Option<CrdtInventory>,
+    // Which numbered unit of each tool is where.
+    pub(crate) units: SerialBook,
+    pub budget: StudioBudget,
+    // Restock and repair spending by department, against monthly limits.
+    pub departments: DepartmentBudgets,
+    pub(crate) over_budget: bool,
+    pub starvation: Starvation,
+    notifiers: Vec<Box<dyn Notifier>>,
+    pub(crate) observers: Vec<mpsc::Sender<RegistryEvent>>,
+    clock: Arc<dyn Clock>,
+    loan_period: Duration,
+    // Overdue units already logged, by artist, tool and due time.
+    pub(crate) flagged_overdue: HashSet<(usize, Symbol, DateTime<Utc>)>,
+    pub(crate) wear: Option<Wear>,
+    pub(crate) costs: CostBook,
+    pub(crate) failed_checkouts: u64,
+    // Undone checkouts and returns, each with the entry that reversed it.
+    pub(crate) undone: Vec<(usize, usize)>,
+}
+
+impl ArtistToolRegistry {
+    pub fn new(resources: &Arc<Mutex<SharedResources>>) -> Self {
+        let rates = ExchangeRates::new(Currency::DEFAULT);
+        Self {
+            artist_tool_preferences: vec![],
+            shared_resources: Arc::clone(resources),
+            rate_limiter: None,
+            tool_limits: None,
+            profiles: Profiles::default(),
+            deposits: Deposits::new(),
+            ledger: Ledger::new(AccountCodes::default(), rates.clone()),
+            interner: Interner::new(),
+            reservations: Reservations::default(),
+            events: EventLog::default(),
+            fills: vec![],
+            repairs: RepairQueue::default(),
+            gallery: Gallery::default(),
+            racks: None,
+            drying_time: Duration::zero(),
+            branch: None,
+            units: SerialBook::default(),
+            budget: StudioBudget::new(Money::zero(rates.base())),
+            departments: DepartmentBudgets::new(rates),
+            over_budget: false,
+            starvation: Starvation::default(),
+            notifiers: vec![],
+            observers: vec![],
+            clock: Arc::new(SystemClock),
+            loan_period: DEFAULT_LOAN_PERIOD,
+            flagged_overdue: HashSet::new(),
+            wear: None,
+            costs: CostBook::default(),
+            failed_checkouts: 0,
+            undone: vec![],
+        }
+    }
+
+    // Writes the audit trail and remaining stock as a state dump. Deposits,
+    // the ledger, the repair queue, the gallery and configured limits aren't
+    // saved.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let temporary = path.with_extension("tmp");
+        fs::write(&temporary, StateDump::capture(self).to_json()?)?;
+        fs::rename(&temporary, path)
+    }
+
+    // Reads a file written by `save` (or any state dump) back into
+    // `resources` and rebuilds the registry around them.
+    pub fn load(path: &Path, resources: &Arc<Mutex<SharedResources>>) -> io::Result<Self> {
+        let dump: StateDump = serde_json::from_str(&fs::read_to_string(path)?)?;
+        Ok(dump.restore(resources))
+    }
+
+    pub fn set_rate_limiter(&mut self, limiter: RateLimiter) {
+        self.rate_limiter = Some(limiter);
+    }
+
+    pub fn set_tool_limits(&mut self, limits: ToolLimits) {
+        self.tool_limits = Some(limits);
+    }
+
+    pub fn tool_limits(&self) -> Option<&ToolLimits> {
+        self.tool_limits.as_ref()
+    }
+
+    pub fn set_profiles(&mut self, profiles: Profiles) {
+        self.profiles = profiles;
+    }
+
+    pub fn profile(&self, artist_id: usize) -> Option<&ArtistProfile> {
+        self.profiles.get(artist_id)
+    }
+
+    // Every entry and check is timed by this clock; the system clock until
+    // another is set.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    pub fn now(&self) -> DateTime<Utc> {
+        self.clock.now()
+    }
+
+    // How long checkouts from now on may be kept.
+    pub fn set_loan_period(&mut self, period: Duration) {
+        self.loan_period = period;
+    }
+
+    pub fn loan_period(&self) -> Duration {
+        self.loan_period
+    }
+
+    // Low-stock alerts go to every notifier added, in the order added.
+    pub fn add_notifier(&mut self, notifier: impl Notifier + 'static) {
+        self.notifiers.push(Box::new(notifier));
+    }
+
+    // Without configured limits the simulation falls back to the global range
+    // and checkouts of any size are accepted.
+    pub fn tool_count_range(&self, id: usize) -> ToolCountRange {
+        match &self.tool_limits {
+            Some(limits) => limits.range_for(id),
+            None => ToolCountRange {
+                min: MIN_REQUIRED_TOOLS,
+                max: MAX_ALLOWED_TOOLS,
+            },
+        }
+    }
+
+    pub fn check_tool_count(&self, id: usize, count: usize) -> Result<(), ToolCountError> {
+        match &self.tool_limits {
+            Some(limits) => limits.check(id, count),
+            None => Ok(()),
+        }
+    }
+
+    // Tool names are resolved first, so aliases and variants like
+    // "sculpting-tool" lend the tool they mean.
+    pub fn tool_registry(
+        &mut self,
+        id: usize,
+        tools: Vec<String>,
+    ) -> Result<Checkout, RegistryError> {
+        let checkout = self.lend(id, tools);
+        self.count_failure(&checkout);
+        match &checkout {
+            Ok(checkout) => self.track_wait(
+                id,
+                checkout.queued.is_empty() && checkout.refused.is_empty(),
+            ),
+            Err(error) if error.is_shortage() => self.track_wait(id, false),
+            Err(_) => {}
+        }
+        checkout
+    }
+
+    fn lend(&mut self, id: usize, tools: Vec<String>) -> Result<Checkout, RegistryError> {
+        let now = self.now();
+        let shared = Arc::clone(&self.shared_resources);
+        let mut resources = shared.lock().map_err(|poisoned| self.recover(poisoned))?;
+        let mut logged = vec![];
+        let checkout = self.lend_from(&mut resources, id, tools, now, &mut logged);
+        drop(resources);
+        for event in logged {
+            self.emit_event(event);
+        }
+        checkout
+    }
+
+    // Checks and lends under a resources guard the caller already holds, so
+    // stock can't move between the checks and the taking. Events are only
+    // snapshotted into `logged`, to be published once the guard is dropped.
+    fn lend_from(
+        &mut self,
+        resources: &mut SharedResources,
+        id: usize,
+        tools: Vec<String>,
+        now: DateTime<Utc>,
+        logged: &mut Vec<LoggedEvent>,
+    ) -> Result<Checkout, RegistryError> {
+        let tools = tools
+            .iter()
+            .map(|tool| resources.resolve_tool(tool).map_err(RegistryError::from))
+            .collect::<Result<Vec<_>, _>>()?;
+        // Requests the tool limits refuse don't use up the artist's rate,
+        // here or in `lend_all`.
+        self.check_tool_count(id, tools.len())?;
+        if let Err(error) = self.limit_rate(&RateKey::Artist(id), id, now) {
+            let items = tools.clone();
+            let event = Self::snapshot_event(
+                resources,
+                Some(id),
+                State::RateLimited,
+                items,
+                BTreeMap::new(),
+                now,
+            );
+            logged.push(event);
+            return Err(error);
+        }
+        self.check_holding(id, tools.len())?;
+        self.check_reserved(resources, id, &tools, now)?;
+
+        let tier = self
+            .tool_limits
+            .as_ref()
+            .map(|limits| limits.tier_for(id))
+            .unwrap_or_default();
+        let mut checkout = Checkout::default();
+        let capped = resources.take_out_resources(tools.clone())?;
+        for tool in &capped {
+            if resources.loan_caps.defer(id, tool, tier, now) {
+                checkout.queued.push(tool.clone());
+            } else {
+                checkout.refused.push(tool.clone());
+            }
+        }
+        checkout.lent = tools;
+        checkout.lent.retain(|tool| !capped.contains(tool));
+        if !checkout.lent.is_empty() {
+            checkout.due = Some(now + self.loan_period);
+        }
+
+        self.hold_deposits(id, &checkout.lent, now);
+        let items = checkout.lent.clone();
+        let serials = self.push_history(id, &checkout.lent, None, State::TakeOut, now);
+        let mut event = Self::snapshot_event(
+            resources,
+            Some(id),
+            State::TakeOut,
+            items,
+            BTreeMap::new(),
+            now,
+        );
+        event.event.serials = by_tool(&checkout.lent, &serials);
+        logged.push(event);
+        Ok(checkout)
+    }
+
+    // Like `tool_registry`, but the artist gets every requested tool or none:
+    // nothing is queued or partially lent.
+    pub fn checkout_all(&mut self, id: usize, tools: Vec<String>) -> Result<(), RegistryError> {
+        let checkout = self.lend_all(id, tools);
+        self.count_failure(&checkout);
+        match &checkout {
+            Ok(()) => self.track_wait(id, true),
+            Err(error) if error.is_shortage() => self.track_wait(id, false),
+            Err(_) => {}
+        }
+        checkout
+    }
+
+    // Checks out a whole class at once, e.g. when a workshop starts. The
+    // shared resources are locked once for the lot: each request is checked
+    // and lent in order, against the stock and holdings the ones before it
+    // left, and gets its own result. Nothing else can take stock in between.
+    pub fn checkout_batch(
+        &mut self,
+        requests: Vec<CheckoutRequest>,
+    ) -> Vec<Result<Checkout, RegistryError>> {
+        let now = self.now();
+        let shared = Arc::clone(&self.shared_resources);
+        let mut resources = shared
+            .lock()
+            .unwrap_or_else(|poisoned| self.recovered(poisoned));
+        let mut logged = vec![];
+        let checkouts: Vec<_> = requests
+            .into_iter()
+            .map(|request| {
+                let id = request.artist_id;
+                let tools = request.tools;
+                (
+                    id,
+                    self.lend_from(&mut resources, id, tools, now, &mut logged),
+                )
+            })
+            .collect();
+        drop(resources);
+        for event in logged {
+            self.emit_event(event);
+        }
+        checkouts
+            .into_iter()
+            .map(|(id, checkout)| {
+                self.count_failure(&checkout);
+                match &checkout {
+                    Ok(checkout) => self.track_wait(
+                        id,
+                        checkout.queued.is_empty() && checkout.refused.is_empty(),
+                    ),
+                    Err(error) if error.is_shortage() => self.track_wait(id, false),
+                    Err(_) => {}
+                }
+                checkout
+            })
+            .collect()
+    }
+
+    fn lend_all(&mut self, id: usize, tools: Vec<String>) -> Result<(), RegistryError> {
+        let now = self.now();
+        let tools = self.resolve_tools(tools)?;
+        self.check_tool_count(id, tools.len())?;
+        self.check_rate(&RateKey::Artist(id), id, &tools, now)?;
+        self.check_holding(id, tools.len())?;
+        {
+            let mut resources = self
+                .shared_resources
+                .lock()
+                .map_err(|poisoned| self.recover(poisoned))?;
+            self.check_reserved(&resources, id, &tools, now)?;
+            resources.take_out_all(&tools)?;
+        }
+        self.record_checkout(id, &tools, None, now);
+        Ok(())
+    }
+
+    // Applies a checkout exactly as it went before, e.g. from a journal:
+    // `lent` comes off the shelf and `queued` joins the queue, at `at`,
+    // whatever the limits, caps and rates say now. Fails, changing nothing,
+    // if a lent unit isn't on the shelf.
+    pub fn replay_checkout(
+        &mut self,
+        id: usize,
+        lent: &[String],
+        queued: &[String],
+        at: DateTime<Utc>,
+    ) -> Result<(), RegistryError> {
+        let tier = self
+            .tool_limits
+            .as_ref()
+            .map(|limits| limits.tier_for(id))
+            .unwrap_or_default();
+        {
+            let mut resources = self
+                .shared_resources
+                .lock()
+                .map_err(|poisoned| self.recover(poisoned))?;
+            resources.take_out_lent(lent)?;
+            for tool in queued {
+                resources.loan_caps.enqueue(QueuedCheckout {
+                    artist_id: id,
+                    tool: tool.clone(),
+                    priority: tier,
+                    queued_at: at,
+                });
+            }
+        }
+        self.record_checkout(id, lent, None, at);
+        Ok(())
+    }
+
+    fn count_failure<T>(&mut self, checkout: &Result<T, RegistryError>) {
+        if checkout.is_err() {
+            self.failed_checkouts += 1;
+        }
+    }
+
+    fn track_wait(&mut self, id: usize, served: bool) {
+        let now = self.now();
+        match served {
+            true => self.starvation.served(id, now),
+            false => self.starvation.kept_waiting(id, now),
+        }
+    }
+
+    // Checkouts refused since the registry was built.
+    pub fn failed_checkouts(&self) -> u64 {
+        self.failed_checkouts
+    }
+
+    // Books tools for `from..until` and returns the reservation number. Units
+    // out on loan count as available, since they may be back by then; units
+    // booked for an overlapping window don't.
+    pub fn reserve(
+        &mut self,
+        id: usize,
+        tools: Vec<String>,
+        from: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Result<usize, RegistryError> {
+        if from >= until {
+            return Err(RegistryError::EmptyWindow);
+        }
+        let tools = self.resolve_tools(tools)?;
+        self.check_tool_count(id, tools.len())?;
+        {
+            let resources = self
+                .shared_resources
+                .lock()
+                .map_err(|poisoned| self.recover(poisoned))?;
+            let mut unavailable = UnavailableTools::default();
+            for tool in &tools {
+                let wanted = tools.iter().filter(|name| *name == tool).count();
+                let owned = resources.stock(tool).get() + resources.loan_caps.on_loan(tool);
+                if owned < self.reservations.booked(tool, from, until) + wanted
+                    && !unavailable.missing.contains(tool)
+                {
+                    unavailable.missing.push(tool.clone());
+                }
+            }
+            if !unavailable.missing.is_empty() {
+                return Err(ResourceError::Unavailable(unavailable).into());
+            }
+        }
+        let reservation = self.reservations.add(id, tools.clone(), from, until);
+        self.push_entry(id, &tools, None, State::Reserved, self.now());
+        Ok(reservation)
+    }
+
+    // Turns a reservation into a checkout, all or nothing. It can be claimed
+    // early if the stock is there, but not once its window has ended.
+    pub fn claim_reservation(&mut self, reservation: usize) -> Result<(), RegistryError> {
+        let now = self.now();
+        let booking = self
+            .reservations
+            .get(reservation)
+            .cloned()
+            .ok_or(RegistryError::UnknownReservation(reservation))?;
+        if booking.until <= now {
+            self.expire_reservations(now);
+            return Err(RegistryError::ReservationExpired(reservation));
+        }
+        self.check_holding(booking.artist_id, booking.tools.len())?;
+        {
+            let mut resources = self
+                .shared_resources
+                .lock()
+                .map_err(|poisoned| self.recover(poisoned))?;
+            self.check_reserved(&resources, booking.artist_id, &booking.tools, now)?;
+            resources.take_out_all(&booking.tools)?;
+        }
+        self.reservations.remove(reservation);
+        self.record_checkout(
+            booking.artist_id,
+            &booking.tools,
+            Some(State::Reserved),
+            now,
+        );
+        Ok(())
+    }
+
+    pub fn cancel_reservation(&mut self, reservation: usize) -> Result<(), RegistryError> {
+        let booking = self
+            .reservations
+            .remove(reservation)
+            .ok_or(RegistryError::UnknownReservation(reservation))?;
+        let tools = booking.tools;
+        self.push_entry(
+            booking.artist_id,
+            &tools,
+            Some(State::Reserved),
+            State::Expired,
+            self.now(),
+        );
+        Ok(())
+    }
+
+    // Records every reservation whose window has ended as expired and
+    // returns their numbers.
+    pub fn expire_reservations(&mut self, now: DateTime<Utc>) -> Vec<usize> {
+        let expired = self.reservations.take_expired(now);
+        for booking in &expired {
+            self.push_entry(
+                booking.artist_id,
+                &booking.tools,
+                Some(State::Reserved),
+                State::Expired,
+                now,
+            );
+        }
+        expired.iter().map(|booking| booking.id).collect()
+    }
+
+    // Refuses a checkout that would dip into units booked for someone else.
+    // Shortfalls that aren't down to reservations are left to the stock check.
+    fn check_reserved(
+        &self,
+        resources: &SharedResources,
+        id: usize,
+        tools: &[String],
+        now: DateTime<Utc>,
+    ) -> Result<(), RegistryError> {
+        for tool in tools {
+            let wanted = tools.iter().filter(|name| *name == tool).count();
+            let stock = resources.stock(tool).get();
+            if stock >= wanted && stock < wanted + self.reservations.held_for_others(id, tool, now)
+            {
+                return Err(RegistryError::ReservedForOthers(tool.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    // An artist may hold at most their range's maximum across every checkout
+    // they haven't returned, damaged and lost units included. Units handed
+    // back for repair don't count.
+    pub(crate) fn check_holding(&self, id: usize, requested: usize) -> Result<(), RegistryError> {
+        let holding = self.outstanding(id);
+        let cap = self.tool_count_range(id).max;
+        if holding + requested > cap {
+            return Err(RegistryError::HoldingCap {
+                artist_id: id,
+                holding,
+                requested,
+                cap,
+            });
+        }
+        Ok(())
+    }
+
+    // Charges a caller many artists share, such as an HTTP API key, for
+    // artist `id`'s checkout, on top of the artist's own allowance.
+    pub fn check_api_key(
+        &mut self,
+        key: &str,
+        id: usize,
+        tools: &[String],
+    ) -> Result<(), RegistryError> {
+        let now = self.now();
+        self.check_rate(&RateKey::ApiKey(key.to_string()), id, tools, now)
+    }
+
+    // Turned-away checkouts go in the event log with the tools asked for.
+    fn check_rate(
+        &mut self,
+        key: &RateKey,
+        id: usize,
+        tools: &[String],
+        now: DateTime<Utc>,
+    ) -> Result<(), RegistryError> {
+        let limited = self.limit_rate(key, id, now);
+        if limited.is_err() {
+            self.record_event(Some(id), State::RateLimited, tools, now);
+        }
+        limited
+    }
+
+    fn limit_rate(
+        &mut self,
+        key: &RateKey,
+        id: usize,
+        now: DateTime<Utc>,
+    ) -> Result<(), RegistryError> {
+        let Some(limiter) = &mut self.rate_limiter else {
+            return Ok(());
+        };
+        limiter
+            .check(key, now)
+            .map_err(|limited| RegistryError::RateLimited {
+                artist_id: id,
+                retry_after: limited.retry_after,
+            })
+    }
+
+    // Gives back tools the artist is holding. Nothing is returned unless the
+    // artist holds every listed tool (counting repeats).
+    pub fn tool_return(&mut self, id: usize, tools: Vec<String>) -> Result<(), RegistryError> {
+        let tools = self.resolve_tools(tools)?;
+        let mut held = self.held_tools(id);
+        let mut not_held = vec![];
+        for tool in &tools {
+            match self
+                .interner
+                .get(tool)
+                .and_then(|symbol| held.get_mut(&symbol))
+            {
+                Some(count) if *count > 0 => *count -= 1,
+                _ => not_held.push(tool.clone()),
+            }
+        }
+        if !not_held.is_empty() {
+            return Err(RegistryError::NotHeld {
+                artist_id: id,
+                tools: not_held,
+            });
+        }
+
+        let returned = tools.len();
+        let tools = self.service_worn(id, tools)?;
+        // Every unit handed back was worn out, so none go on the shelf.
+        if tools.is_empty() && returned > 0 {
+            return Ok(());
+        }
+        self.put_back(id, &tools, State::TakeOut, self.now())
+    }
+
+    // Damaged units stay with the artist until they go to repair.
+    pub fn report_damage(&mut self, id: usize, tool: &str) -> Result<(), RegistryError> {
+        let tool = &self.resolve_tool(tool)?;
+        self.advance(id, tool, State::Damage)?;
+        self.book_loss(tool);
+        Ok(())
+    }
+
+    // A lost unit is written off the studio's stock straight away, freeing
+    // its loan slot; the entry records who lost it. Its deposit stays held
+    // until it is found or forfeited.
+    pub fn report_lost(&mut self, id: usize, tool: &str) -> Result<(), RegistryError> {
+        let tool = &self.resolve_tool(tool)?;
+        self.advance(id, tool, State::Lost)?;
+        self.book_loss(tool);
+        self.write_off(tool)
+    }
+
+    pub fn send_to_repair(&mut self, id: usize, tool: &str) -> Result<(), RegistryError> {
+        let tool = &self.resolve_tool(tool)?;
+        self.advance(id, tool, State::Repair)
+    }
+
+    pub fn finish_repair(&mut self, id: usize, tool: &str) -> Result<(), RegistryError> {
+        let tool = &self.resolve_tool(tool)?;
+        let from = self.source_state(id, tool, State::Return, &[State::Repair])?;
+        self.put_back(id, &[tool.to_string()], from, self.now())
+    }
+
+    // The artist hands back a damaged unit. It goes to the repair queue
+    // rather than the shelf and no longer counts toward their holding.
+    // Returns the repair ticket.
+    pub fn return_damaged(&mut self, id: usize, tool: &str) -> Result<usize, RegistryError> {
+        self.report_damage(id, tool)?;
+        Ok(self.repairs.push(tool, id, self.now()))
+    }
+
+    pub fn start_repair(
+        &mut self,
+        ticket: usize,
+        duration: chrono::Duration,
+        cost: Option<Money>,
+    ) -> Result<(), RegistryError> {
+        let job = self
+            .repairs
+            .get(ticket)
+            .ok_or(RegistryError::UnknownRepair(ticket))?;
+        if job.started_at.is_some() {
+            return Err(RegistryError::InvalidStateTransition {
+                from: State::Repair,
+                to: State::Repair,
+            });
+        }
+        let (id, tool) = (job.artist_id, job.tool.clone());
+        let charges = match cost {
+            Some(cost) => self.check_departments(&[(&tool, cost)])?,
+            None => vec![],
+        };
+        self.send_to_repair(id, &tool)?;
+        let now = self.now();
+        if let Some(job) = self.repairs.get_mut(ticket) {
+            job.started_at = Some(now);
+            job.duration = Some(duration);
+            job.cost = cost;
+        }
+        if let Some(cost) = cost {
+            self.book(CashFlow::Repair, cost, format!("repair of {}", tool));
+        }
+        self.charge_departments(CostKind::Repair, charges);
+        Ok(())
+    }
+
+    // Puts a repaired unit back in stock and closes its ticket.
+    pub fn complete_repair(&mut self, ticket: usize) -> Result<(), RegistryError> {
+        let job = self
+            .repairs
+            .get(ticket)
+            .ok_or(RegistryError::UnknownRepair(ticket))?;
+        let (id, tool) = (job.artist_id, job.tool.clone());
+        self.finish_repair(id, &tool)?;
+        self.repairs.remove(ticket);
+        Ok(())
+    }
+
+    // Completes every repair whose time has run out; returns their tickets.
+    pub fn complete_ready_repairs(&mut self, now: DateTime<Utc>) -> Vec<usize> {
+        self.repairs
+            .ready(now)
+            .into_iter()
+            .filter(|&ticket| self.complete_repair(ticket).is_ok())
+            .collect()
+    }
+
+    // A found unit counts as on loan again until it is back on the shelf.
+    pub fn recover_lost(&mut self, id: usize, tool: &str) -> Result<(), RegistryError> {
+        let tool = &self.resolve_tool(tool)?;
+        let from = self.source_state(id, tool, State::Return, &[State::Lost])?;
+        {
+            let mut resources = self
+                .shared_resources
+                .lock()
+                .map_err(|poisoned| self.recover(poisoned))?;
+            let on_loan = resources.loan_caps.on_loan(tool);
+            resources.loan_caps.set_on_loan(tool, on_loan + 1);
+        }
+        self.put_back(id, &[tool.to_string()], from, self.now())
+    }
+
+    // Writes off a damaged or lost unit. Its loan slot is freed, but any
+    // deposit stays held until it is forfeited with `forfeit_deposit`.
+    pub fn retire(&mut self, id: usize, tool: &str) -> Result<(), RegistryError> {
+        let tool = &self.resolve_tool(tool)?;
+        let from = self.source_state(id, tool, State::Retire, &HELD_STATES)?;
+        // Lost units were written off when they were reported.
+        if from != State::Lost {
+            self.write_off(tool)?;
+        }
+        self.push_entry(
+            id,
+            &[tool.to_string()],
+            Some(from),
+            State::Retire,
+            self.now(),
+        );
+        Ok(())
+    }
+
+    // Takes aging units off the shelf for good. Only units on the shelf and
+    // not reserved for anyone may go; checked-out ones must come back first.
+    pub fn retire_stock(
+        &mut self,
+        admin_id: usize,
+        tool: &str,
+        count: usize,
+    ) -> Result<(), RegistryError> {
+        let tool = &self.resolve_tool(tool)?;
+        self.take_off_shelf(admin_id, tool, count, State::Retire)
+    }
+
+    // Like `retire_stock`, and books `price` for the lot as a sale.
+    pub fn sell_stock(
+        &mut self,
+        admin_id: usize,
+        tool: &str,
+        count: usize,
+        price: Money,
+    ) -> Result<(), RegistryError> {
+        let tool = &self.resolve_tool(tool)?;
+        self.take_off_shelf(admin_id, tool, count, State::Sold)?;
+        let memo = format!("sold {} {}", count, tool);
+        self.book(CashFlow::Revenue, price, memo.clone());
+        self.record_ledger(LedgerEvent::Sale, price, self.now(), memo);
+        Ok(())
+    }
+
+    // `tool` is the resolved name, not an alias.
+    fn take_off_shelf(
+        &mut self,
+        admin_id: usize,
+        tool: &str,
+        count: usize,
+        to: State,
+    ) -> Result<(), RegistryError> {
+        let now = self.now();
+        {
+            let mut resources = self
+                .shared_resources
+                .lock()
+                .map_err(|poisoned| self.recover(poisoned))?;
+            let on_shelf = resources.stock(tool).get();
+            let reserved = self.reservations.held_for_others(admin_id, tool, now);
+            if on_shelf < count {
+                return Err(RegistryError::NotOnShelf {
+                    tool: tool.to_string(),
+                    on_shelf,
+                    requested: count,
+                    on_loan: resources.loan_caps.on_loan(tool),
+                });
+            }
+            if on_shelf < count + reserved {
+                return Err(RegistryError::ReservedForOthers(tool.to_string()));
+            }
+            resources.set_quantity(tool, Count::of(on_shelf - count))?;
+        }
+        // The studio, not an artist, disposed of these, so the event has no
+        // artist; the entry records who did it.
+        let tools = vec![tool.to_string(); count];
+        let serials = self
+            .units
+            .move_units(&tools, Some(admin_id), Some(State::Return), to);
+        let quantities = BTreeMap::from([(tool.to_string(), Count::of(count).into())]);
+        let by_tool = by_tool(&tools, &serials);
+        let moved = Moved {
+            from: Some(State::Return),
+            serials: by_tool,
+        };
+        self.log_event(None, to, vec![tool.to_string()], quantities, moved, now);
+        let symbol = self.interner.intern(tool);
+        self.artist_tool_preferences.push(ArtistToolPreferences {
+            artist_id: admin_id,
+            preferred_tools: vec![symbol; count],
+            datetime: Some(now),
+            state: Some(to),
+            from: Some(State::Return),
+            serials,
+            ..Default::default()
+        });
+        Ok(())
+    }
+
+    fn write_off(&mut self, tool: &str) -> Result<(), RegistryError> {
+        let mut resources = self
+            .shared_resources
+            .lock()
+            .map_err(|poisoned| self.recover(poisoned))?;
+        let counted = Count::of(resources.loan_caps.on_loan(tool));
+        let on_loan = counted
+            .checked_sub(Count(1))
+            .ok_or_else(|| ResourceError::Miscounted {
+                tool: tool.to_string(),
+                counted,
+                removed: Count(1),
+                on_loan: true,
+            })?;
+        resources.loan_caps.set_on_loan(tool, on_loan.get());
+        Ok(())
+    }
+
+    // Moves one held unit of `tool` to `to` without touching stock.
+    fn advance(&mut self, id: usize, tool: &str, to: State) -> Result<(), RegistryError> {
+        let from = self.source_state(id, tool, to, &HELD_STATES)?;
+        self.push_entry(id, &[tool.to_string()], Some(from), to, self.now());
+        Ok(())
+    }
+
+    // Finds a unit the artist holds in one of `candidates` that may become
+    // `to`, preferring the earlier candidates.
+    fn source_state(
+        &self,
+        id: usize,
+        tool: &str,
+        to: State,
+        candidates: &[State],
+    ) -> Result<State, RegistryError> {
+        let Some(symbol) = self.interner.get(tool) else {
+            return Err(RegistryError::NotHeld {
+                artist_id: id,
+                tools: vec![tool.to_string()],
+            });
+        };
+        let held: Vec<State> = HELD_STATES
+            .into_iter()
+            .filter(|&state| self.units_in(id, state).contains_key(&symbol))
+            .collect();
+        if let Some(&from) = held
+            .iter()
+            .find(|&&state| candidates.contains(&state) && transition(state, to).is_ok())
+        {
+            return Ok(from);
+        }
+        match held.first() {
+            Some(&from) => Err(RegistryError::InvalidStateTransition { from, to }),
+            None => Err(RegistryError::NotHeld {
+                artist_id: id,
+                tools: vec![tool.to_string()],
+            }),
+        }
+    }
+
+    pub(crate) fn push_entry(
+        &mut self,
+        id: usize,
+        tools: &[String],
+        from: Option<State>,
+        to: State,
+        now: DateTime<Utc>,
+    ) {
+        let serials = self.push_history(id, tools, from, to, now);
+        let serials = by_tool(tools, &serials);
+        let moved = Moved { from, serials };
+        self.log_event(Some(id), to, tools.to_vec(), BTreeMap::new(), moved, now);
+    }
+
+    // Appends the entry and returns the serials of the units it moved.
+    fn push_history(
+        &mut self,
+        id: usize,
+        tools: &[String],
+        from: Option<State>,
+        to: State,
+        now: DateTime<Utc>,
+    ) -> Vec<usize> {
+        let serials = if moves_units(to, from) {
+            self.units.move_units(tools, Some(id), from, to)
+        } else {
+            vec![]
+        };
+        self.artist_tool_preferences.push(ArtistToolPreferences {
+            artist_id: id,
+            datetime: Some(now),
+            state: Some(to),
+            from,
+            preferred_tools: tools
+                .iter()
+                .map(|tool| self.interner.intern(tool))
+                .collect(),
+            paints: vec![],
+            due: (to == State::TakeOut).then(|| now + self.loan_period),
+            serials: serials.clone(),
+        });
+        serials
+    }
+
+    // Adds units to the shelf, e.g. a delivery, and logs it as `New` stock.
+    // Spends what the cost book says they cost.
+    pub fn restock(
+        &mut self,
+        item: &str,
+        quantity: impl Into<Amount>,
+    ) -> Result<(), RegistryError> {
+        let quantity = quantity.into();
+        let cost = self.costs.cost_of(item, quantity);
+        let charges = match cost {
+            Some(cost) => self.check_departments(&[(item, cost)])?,
+            None => vec![],
+        };
+        self.shared_resources
+            .lock()
+            .map_err(|poisoned| self.recover(poisoned))?
+            .restock(item, quantity)?;
+        if let Some(cost) = cost {
+            let memo = format!("restocked {} {}", quantity, item);
+            self.book(CashFlow::Restock, cost, memo);
+        }
+        self.charge_departments(CostKind::Restock, charges);
+        self.record_amounts(
+            None,
+            State::New,
+            &[(item.to_string(), quantity)],
+            self.now(),
+        );
+        Ok(())
+    }
+
+    // Puts a supplier's delivery on the shelves and books its cost as a
+    // purchase, shared evenly between its lines' departments. Refused whole
+    // if any item would go over its storage capacity.
+    pub fn receive_delivery(&mut self, delivery: Delivery) -> Result<(), RegistryError> {
+        let now = self.now();
+        let shares: Vec<(&str, Money)> = delivery
+            .items
+            .iter()
+            .map(|(item, _)| item.as_str())
+            .zip(delivery.cost.split(delivery.items.len()))
+            .collect();
+        let charges = self.check_departments(&shares)?;
+        self.shared_resources
+            .lock()
+            .map_err(|poisoned| self.recover(poisoned))?
+            .receive(&delivery.items)?;
+        self.charge_departments(CostKind::Restock, charges);
+        self.record_amounts(None, State::Fill, &delivery.items, now);
+        let memo = format!("delivery from {}", delivery.supplier);
+        self.book(CashFlow::Restock, delivery.cost, memo.clone());
+        self.record_ledger(LedgerEvent::Purchase, delivery.cost, now, memo);
+        self.fills.push(FillEntry { at: now, delivery });
+        Ok(())
+    }
+
+    // Appends to the event log, capturing the stock left of each item.
+    pub fn record_event(
+        &mut self,
+        artist_id: Option<usize>,
+        kind: State,
+        items: &[String],
+        at: DateTime<Utc>,
+    ) {
+        self.log_event(
+            artist_id,
+            kind,
+            items.to_vec(),
+            BTreeMap::new(),
+            Moved::default(),
+            at,
+        );
+    }
+
+    // Like `record_event`, for changes of more than one unit per item such
+    // as restocks and paint, so that the log can be replayed. Tools arriving
+    // from outside are given serials here, and tools sent away give theirs up.
+    pub fn record_amounts<Q: Into<Amount> + Copy>(
+        &mut self,
+        artist_id: Option<usize>,
+        kind: State,
+        amounts: &[(String, Q)],
+        at: DateTime<Utc>,
+    ) {
+        let items = amounts.iter().map(|(item, _)| item.clone()).collect();
+        let mut quantities: BTreeMap<String, Amount> = BTreeMap::new();
+        for (item, amount) in amounts {
+            let amount = (*amount).into();
+            quantities
+                .entry(item.clone())
+                .and_modify(|total| *total = total.checked_add(amount).unwrap_or(*total))
+                .or_insert(amount);
+        }
+        let mut serials = BTreeMap::new();
+        for (item, amount) in &quantities {
+            let Some(count) = amount.count().map(Count::get) else {
+                continue;
+            };
+            let moved = match (kind, artist_id) {
+                (State::New | State::Fill | State::TransferIn, None) => {
+                    self.units.intake(item, count)
+                }
+                (State::TransferOut, None) => {
+                    let tools = vec![item.clone(); count];
+                    self.units.move_units(&tools, None, None, kind)
+                }
+                _ => continue,
+            };
+            serials.insert(item.clone(), moved);
+        }
+        let moved = Moved {
+            from: None,
+            serials,
+        };
+        self.log_event(artist_id, kind, items, quantities, moved, at);
+    }
+
+    // Every checkout passes through here, so this is also where low-stock
+    // alerts are raised.
+    fn log_event(
+        &mut self,
+        artist_id: Option<usize>,
+        kind: State,
+        items: Vec<String>,
+        quantities: BTreeMap<String, Amount>,
+        moved: Moved,
+        at: DateTime<Utc>,
+    ) {
+        let resources = self
+            .shared_resources
+            .lock()
+            .unwrap_or_else(|poisoned| self.recovered(poisoned));
+        let mut logged = Self::snapshot_event(&resources, artist_id, kind, items, quantities, at);
+        drop(resources);
+        logged.event.from = moved.from;
+        logged.event.serials = moved.serials;
+        self.emit_event(logged);
+    }
+
+    // Builds the event and any alerts it raises from the stock as it stands,
+    // without publishing anything, so it can run under a held guard.
+    fn snapshot_event(
+        resources: &SharedResources,
+        artist_id: Option<usize>,
+        kind: State,
+        items: Vec<String>,
+        quantities: BTreeMap<String, Amount>,
+        at: DateTime<Utc>,
+    ) -> LoggedEvent {
+        let mut event = EventRecord::new(at, artist_id, kind, items, resources);
+        event.quantities = quantities;
+        let alerts = match (kind, artist_id) {
+            (State::TakeOut, Some(id)) => {
+                let mut taken = BTreeMap::new();
+                for item in &event.items {
+                    *taken.entry(item.clone()).or_insert(Count(0)) += Count(1);
+                }
+                let taken = taken
+                    .into_iter()
+                    .map(|(item, count)| (item, Amount::from(count)))
+                    .collect();
+                resources.low_stock.crossed(&taken, &event.stock, id, at)
+            }
+            (State::Paint, Some(id)) => {
+                resources
+                    .low_stock
+                    .crossed(&event.quantities, &event.stock, id, at)
+            }
+            _ => vec![],
+        };
+        LoggedEvent { event, alerts }
+    }
+
+    // Publishes a snapshotted event, appends it to the log and sends out its
+    // alerts. Must not be called with the resources locked.
+    fn emit_event(&mut self, logged: LoggedEvent) {
+        let LoggedEvent { event, alerts } = logged;
+        self.publish(RegistryEvent::from(&event));
+        if let Some(branch) = &mut self.branch {
+            branch.record(&event);
+        }
+        self.events.append(event);
+        for alert in &alerts {
+            self.publish(RegistryEvent::LowStock(alert.clone()));
+            for notifier in &mut self.notifiers {
+                if let Err(error) = notifier.notify(alert) {
+                    tracing::warn!(
+                        item = %alert.item,
+                        "{}",
+                        Message::NotifyFailed(&alert.item, error.to_string())
+                    );
+                }
+            }
+        }
+    }
+
+    // Restocks units coming back from `from`, releases their deposits and
+    // serves any checkout that was queued for them.
+    pub(crate) fn put_back(
+        &mut self,
+        id: usize,
+        tools: &[String],
+        from: State,
+        now: DateTime<Utc>,
+    ) -> Result<(), RegistryError> {
+        let handed_off = self
+            .shared_resources
+            .lock()
+            .map_err(|poisoned| self.recover(poisoned))?
+            .return_resources(tools, now)?;
+        for tool in tools {
+            self.release_deposit(id, tool);
+        }
+        self.push_entry(id, tools, Some(from), State::Return, now);
+
+        for queued in handed_off {
+            tracing::info!(
+                artist_id = queued.artist_id,
+                tool = %queued.tool,
+                "{}",
+                Message::QueuedCheckoutServed(queued.artist_id, &queued.tool)
+            );
+            self.record_checkout(queued.artist_id, &[queued.tool], None, now);
+            self.starvation.served(queued.artist_id, now);
+        }
+        Ok(())
+    }
+
+    // How many of each tool the artist has checked out and may still return.
+    pub fn held_tools(&self, id: usize) -> HashMap<Symbol, usize> {
+        self.units_in(id, State::TakeOut)
+    }
+
+    // Units of any tool the artist still has to answer for.
+    pub fn outstanding(&self, id: usize) -> usize {
+        let held: usize = HELD_STATES
+            .iter()
+            .map(|&state| self.units_in(id, state).values().sum::<usize>())
+            .sum();
+        held.saturating_sub(self.repairs.for_artist(id).count())
+    }
+
+    // How many units of each tool the artist holds in `state`.
+    pub fn units_in(&self, id: usize, state: State) -> HashMap<Symbol, usize> {
+        let mut units: HashMap<Symbol, usize> = HashMap::new();
+        for entry in self.history_for_artist(id) {
+            let from = entry.source_state();
+            for &symbol in &entry.preferred_tools {
+                let count = units.entry(symbol).or_insert(0);
+                if from == Some(state) {
+                    *count = count.saturating_sub(1);
+                }
+                if entry.state == Some(state) {
+                    *count += 1;
+                }
+            }
+        }
+        units.retain(|_, count| *count > 0);
+        units
+    }
+
+    fn record_checkout(
+        &mut self,
+        id: usize,
+        tools: &[String],
+        from: Option<State>,
+        now: DateTime<Utc>,
+    ) {
+        self.hold_deposits(id, tools, now);
+        self.push_entry(id, tools, from, State::TakeOut, now);
+    }
+
+    // Takes the deposits on units going out and wears them.
+    fn hold_deposits(&mut self, id: usize, tools: &[String], now: DateTime<Utc>) {
+        for tool in tools {
+            if let Some(amount) = self.deposits.hold(id, tool) {
+                let memo = format!("artist {} {}", id, tool);
+                self.record_ledger(LedgerEvent::DepositHeld, amount, now, memo);
+            }
+        }
+        self.wear_tools(tools);
+    }
+
+    // Paint is used up rather than lent, so it is recorded as a `Paint` entry
+    // and never returned.
+    pub fn paint_checkout(
+        &mut self,
+        id: usize,
+        paints: Vec<(String, Kilograms)>,
+    ) -> Result<(), RegistryError> {
+        self.shared_resources
+            .lock()
+            .map_err(|poisoned| self.recover(poisoned))?
+            .take_paints(&paints)?;
+        let now = self.now();
+        self.record_amounts(Some(id), State::Paint, &paints, now);
+        self.artist_tool_preferences.push(ArtistToolPreferences {
+            artist_id: id,
+            datetime: Some(now),
+            state: Some(State::Paint),
+            from: None,
+            preferred_tools: vec![],
+            paints: paints
+                .iter()
+                .map(|(color, kg)| (self.interner.intern(color), *kg))
+                .collect(),
+            due: None,
+            serials: vec![],
+        });
+        Ok(())
+    }
+
+    // Takes every paint batch that has expired by `now` out of usable stock
+    // and records an `Expired` entry for `admin_id`. Returns the batches with
+    // the kilograms actually removed.
+    pub fn sweep_expired_paints(
+        &mut self,
+        admin_id: usize,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<PaintBatch>, RegistryError> {
+        let expired = self
+            .shared_resources
+            .lock()
+            .map_err(|poisoned| self.recover(poisoned))?
+            .expire_batches(now);
+        if expired.is_empty() {
+            return Ok(expired);
+        }
+        let amounts: Vec<(String, Kilograms)> = expired
+            .iter()
+            .map(|batch| (batch.color.clone(), batch.kg))
+            .collect();
+        self.record_amounts(None, State::Expired, &amounts, now);
+        self.artist_tool_preferences.push(ArtistToolPreferences {
+            artist_id: admin_id,
+            datetime: Some(now),
+            state: Some(State::Expired),
+            paints: amounts
+                .iter()
+                .map(|(color, kg)| (self.interner.intern(color), *kg))
+                .collect(),
+            ..Default::default()
+        });
+        Ok(expired)
+    }
+
+    // Total kilograms of each color the artist has taken, sorted by color.
+    pub fn paint_usage(&self, id: usize) -> Vec<(String, Kilograms)> {
+        let mut usage: HashMap<&str, Kilograms> = HashMap::new();
+        for entry in self
+            .history_for_artist(id)
+            .filter(|entry| entry.state == Some(State::Paint))
+        {
+            for &(symbol, kg) in &entry.paints {
+                if let Some(color) = self.interner.resolve(symbol) {
+                    *usage.entry(color).or_default() += kg;
+                }
+            }
+        }
+        let mut usage: Vec<_> = usage
+            .into_iter()
+            .map(|(color, kg)| (color.to_string(), kg))
+            .collect();
+        usage.sort_by(|(a, _), (b, _)| a.cmp(b));
+        usage
+    }
+
+    pub fn release_deposit(&mut self, id: usize, tool: &str) {
+        if let Some(amount) = self.deposits.release(id, tool) {
+            let memo = format!("artist {} {}", id, tool);
+            self.record_ledger(LedgerEvent::DepositReleased, amount, self.now(), memo);
+        }
+    }
+
+    // Fails, keeping the deposit held, if the deduction's currency can't be
+    // converted into the deposit's.
+    pub fn forfeit_deposit(
+        &mut self,
+        id: usize,
+        tool: &str,
+        deduction: Money,
+    ) -> Result<(), RegistryError> {
+        let forfeit = self
+            .deposits
+            .forfeit(id, tool, deduction, self.ledger.rates())
+            .map_err(|UnknownCurrency(currency)| RegistryError::UnknownCurrency(currency))?;
+        if let Some(forfeit) = forfeit {
+            let now = self.now();
+            let memo = format!("artist {} {}", id, tool);
+            self.record_ledger(LedgerEvent::Penalty, forfeit.deducted, now, memo.clone());
+            if forfeit.refunded.minor_units > 0 {
+                self.record_ledger(LedgerEvent::DepositReleased, forfeit.refunded, now, memo);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn record_ledger(
+        &mut self,
+        event: LedgerEvent,
+        amount: Money,
+        at: DateTime<Utc>,
+        memo: String,
+    ) {
+        if let Err(UnknownCurrency(currency)) = self.ledger.record(event, amount, at, memo) {
+            tracing::warn!(currency = %currency, "{}", Message::UnknownCurrency(currency));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::ResourceError,
+        loan_caps::CapPolicy,
+        resources::TOTAL_ITEMS,
+        tool_limits::{self, PriorityTier},
+    };
+
+    #[test]
+    fn test_tool_registry() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        let tools = vec!["brush".to_string(), "palette".to_string()];
+        registry.tool_registry(1, tools).unwrap();
+        assert_eq!(registry.artist_tool_preferences.len(), 1);
+    }
+
+    #[test]
+    fn test_tool_registry_refuses_unknown_tools_without_lending() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        let error = registry
+            .tool_registry(1, vec!["brush".to_string(), "easel".to_string()])
+            .unwrap_err();
+        assert_eq!(
+            error,
+            RegistryError::Resource(ResourceError::ToolNotFound("easel".to_string()))
+        );
+        assert!(registry.artist_tool_preferences.is_empty());
+        assert_eq!(resources.lock().unwrap().stock("brush"), TOTAL_ITEMS);
+    }
+
+    #[test]
+    fn test_tool_return_restores_stock_and_rejects_unheld_tools() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        registry
+            .tool_registry(1, vec!["brush".to_string(), "tape".to_string()])
+            .unwrap();
+
+        let error = registry
+            .tool_return(1, vec!["brush".to_string(), "brush".to_string()])
+            .unwrap_err();
+        assert_eq!(error.to_string(), "artist 1 did not check out brush");
+        assert!(registry.tool_return(2, vec!["tape".to_string()]).is_err());
+        assert_eq!(
+            resources.lock().unwrap().stock("brush"),
+            TOTAL_ITEMS - Count(1)
+        );
+
+        registry.tool_return(1, vec!["brush".to_string()]).unwrap();
+        assert_eq!(resources.lock().unwrap().stock("brush"), TOTAL_ITEMS);
+        let entry = registry.artist_tool_preferences.last().unwrap();
+        assert_eq!(entry.state, Some(State::Return));
+        assert!(entry.datetime.is_some());
+        let held = registry.held_tools(1);
+        assert_eq!(held.len(), 1);
+        assert!(registry.tool_return(1, vec!["brush".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_save_and_load_resume_a_run() {
+        let path = std::env::temp_dir().join(format!("registry-{}.json", std::process::id()));
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        registry
+            .tool_registry(1, vec!["brush".to_string(), "tape".to_string()])
+            .unwrap();
+        registry.report_damage(1, "tape").unwrap();
+        registry.save(&path).unwrap();
+
+        let fresh = Arc::new(Mutex::new(SharedResources::default()));
+        let mut loaded = ArtistToolRegistry::load(&path, &fresh).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(fresh.lock().unwrap().stock("brush"), TOTAL_ITEMS - Count(1));
+        assert_eq!(loaded.artist_tool_preferences.len(), 2);
+        loaded.send_to_repair(1, "tape").unwrap();
+        loaded.tool_return(1, vec!["brush".to_string()]).unwrap();
+        assert_eq!(fresh.lock().unwrap().stock("brush"), TOTAL_ITEMS);
+    }
+
+    #[test]
+    fn test_reservations_hold_stock_until_claimed_or_cancelled() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        resources
+            .lock()
+            .unwrap()
+            .restock("easel", Count(2))
+            .unwrap();
+        let mut registry = ArtistToolRegistry::new(&resources);
+        let easels = |count| vec!["easel".to_string(); count];
+        let now = Utc::now();
+        let hour = chrono::Duration::hours(1);
+
+        assert_eq!(
+            registry.reserve(1, easels(1), now, now),
+            Err(RegistryError::EmptyWindow)
+        );
+        let booked = registry
+            .reserve(1, easels(2), now - hour, now + hour)
+            .unwrap();
+        assert!(registry.reserve(3, easels(1), now, now + hour).is_err());
+        assert_eq!(
+            registry.tool_registry(2, easels(1)),
+            Err(RegistryError::ReservedForOthers("easel".to_string()))
+        );
+
+        registry.claim_reservation(booked).unwrap();
+        assert_eq!(registry.held_tools(1).values().sum::<usize>(), 2);
+        let entry = registry.artist_tool_preferences.last().unwrap();
+        assert_eq!(
+            (entry.from, entry.state),
+            (Some(State::Reserved), Some(State::TakeOut))
+        );
+        assert_eq!(
+            registry.claim_reservation(booked),
+            Err(RegistryError::UnknownReservation(booked))
+        );
+
+        let later = registry
+            .reserve(2, easels(1), now + hour, now + hour * 2)
+            .unwrap();
+        registry.cancel_reservation(later).unwrap();
+        assert!(registry.reservations.open().is_empty());
+        let entry = registry.artist_tool_preferences.last().unwrap();
+        assert_eq!(entry.state, Some(State::Expired));
+    }
+
+    #[test]
+    fn test_transition_rejects_illegal_moves() {
+        assert!(transition(State::TakeOut, State::Damage).is_ok());
+        assert!(transition(State::Repair, State::Return).is_ok());
+        assert_eq!(
+            transition(State::Sold, State::TakeOut),
+            Err(RegistryError::InvalidStateTransition {
+                from: State::Sold,
+                to: State::TakeOut
+            })
+        );
+        assert!(transition(State::Repair, State::TakeOut).is_err());
+    }
+
+    #[test]
+    fn test_damaged_and_lost_tools_follow_the_lifecycle() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        registry
+            .tool_registry(1, vec!["brush".to_string(), "tape".to_string()])
+            .unwrap();
+
+        registry.report_damage(1, "brush").unwrap();
+        assert!(registry.tool_return(1, vec!["brush".to_string()]).is_err());
+        registry.send_to_repair(1, "brush").unwrap();
+        assert_eq!(
+            registry.report_lost(1, "brush"),
+            Err(RegistryError::InvalidStateTransition {
+                from: State::Repair,
+                to: State::Lost
+            })
+        );
+        registry.finish_repair(1, "brush").unwrap();
+        assert_eq!(resources.lock().unwrap().stock("brush"), TOTAL_ITEMS);
+        assert!(matches!(
+            registry.send_to_repair(1, "brush"),
+            Err(RegistryError::NotHeld { .. })
+        ));
+
+        registry.report_lost(1, "tape").unwrap();
+        registry.retire(1, "tape").unwrap();
+        let resources = resources.lock().unwrap();
+        assert_eq!(resources.stock("tape"), TOTAL_ITEMS - Count(1));
+        assert_eq!(resources.loan_caps.on_loan("tape"), 0);
+        let entry = registry.artist_tool_preferences.last().unwrap();
+        assert_eq!(
+            (entry.from, entry.state),
+            (Some(State::Lost), Some(State::Retire))
+        );
+    }
+
+    #[test]
+    fn test_retire_and_sell_only_take_shelf_units() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        let brushes = vec!["brush".to_string(); 3];
+        registry.tool_registry(1, brushes).unwrap();
+
+        assert_eq!(
+            registry.retire_stock(99, "brush", 8),
+            Err(RegistryError::NotOnShelf {
+                tool: "brush".to_string(),
+                on_shelf: 7,
+                requested: 8,
+                on_loan: 3,
+            })
+        );
+        registry.retire_stock(99, "brush", 2).unwrap();
+        let price = Money::new(4_500, Currency::USD);
+        registry
+            .sell_stock(99, "tape", TOTAL_ITEMS.get(), price)
+            .unwrap();
+        assert_eq!(resources.lock().unwrap().stock("brush"), Count(5));
+        assert_eq!(
+            registry.tool_registry(2, vec!["tape".to_string()]),
+            Err(RegistryError::Resource(ResourceError::ToolNotFound(
+                "tape".to_string()
+            )))
+        );
+        let sale = registry.ledger.entries().last().unwrap();
+        assert_eq!((sale.event, sale.amount), (LedgerEvent::Sale, price));
+
+        let report = registry.audit(99);
+        assert_eq!(report.discrepancies().count(), 0, "{}", report);
+        let brush = &report.lines[0];
+        assert_eq!((brush.retired, brush.held), (2, 3));
+        let replayed = crate::events::replay(&registry.events, &mut SharedResources::default());
+        assert!(replayed.matches(), "{:?}", replayed);
+    }
+
+    #[test]
+    fn test_checkout_all_is_all_or_nothing() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        let refused = registry
+            .checkout_all(1, vec!["brush".to_string(), "easel".to_string()])
+            .unwrap_err();
+        assert_eq!(refused.to_string(), "not available: easel");
+        assert!(registry.artist_tool_preferences.is_empty());
+        assert_eq!(resources.lock().unwrap().stock("brush"), TOTAL_ITEMS);
+
+        registry
+            .checkout_all(1, vec!["brush".to_string(), "tape".to_string()])
+            .unwrap();
+        assert_eq!(registry.held_tools(1).len(), 2);
+    }
+
+    #[test]
+    fn test_paint_checkout_records_usage_per_artist() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        let paints = |list: &[(&str, f64)]| {
+            list.iter()
+                .map(|(color, kg)| (color.to_string(), Kilograms::new(*kg).unwrap()))
+                .collect::<Vec<_>>()
+        };
+        registry
+            .paint_checkout(1, paints(&[("red", 2.0), ("white", 0.75)]))
+            .unwrap();
+        registry.paint_checkout(1, paints(&[("red", 3.5)])).unwrap();
+        assert!(registry.paint_checkout(2, paints(&[("red", 6.0)])).is_err());
+
+        assert_eq!(
+            registry.paint_usage(1),
+            paints(&[("red", 5.5), ("white", 0.75)])
+        );
+        assert!(registry.paint_usage(2).is_empty());
+        assert_eq!(
+            registry.artist_tool_preferences[0].state,
+            Some(State::Paint)
+        );
+        assert_eq!(
+            resources.lock().unwrap().paints.quantity("red"),
+            Kilograms::grams(4_500)
+        );
+    }
+
+    #[test]
+    fn test_checkout_batch_serves_a_class_in_order() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        resources
+            .lock()
+            .unwrap()
+            .set_quantity("easel", Count(2))
+            .unwrap();
+        let mut registry = ArtistToolRegistry::new(&resources);
+        let mut class: Vec<CheckoutRequest> = (1..=3)
+            .map(|artist_id| CheckoutRequest {
+                artist_id,
+                tools: vec!["easel".to_string(), "brush".to_string()],
+            })
+            .collect();
+        // Artist 1 again: the two units from earlier in the batch count.
+        class.push(CheckoutRequest {
+            artist_id: 1,
+            tools: vec!["brush".to_string(); 4],
+        });
+        let results = registry.checkout_batch(class);
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[0].as_ref().unwrap().lent, vec!["easel", "brush"]);
+        assert!(results[1].is_ok());
+        // The first two took both easels.
+        assert!(results[2].as_ref().unwrap_err().is_shortage());
+        assert!(matches!(
+            results[3],
+            Err(RegistryError::HoldingCap { holding: 2, .. })
+        ));
+        assert!(registry.held_tools(3).is_empty());
+        assert_eq!(resources.lock().unwrap().stock("brush"), Count(8));
+        assert_eq!(registry.failed_checkouts(), 2);
+
+        // Each logged checkout saw the stock its predecessors left.
+        let easels: Vec<_> = registry
+            .events
+            .records()
+            .filter(|event| event.kind == State::TakeOut)
+            .map(|event| event.stock["easel"])
+            .collect();
+        assert_eq!(easels, vec![Amount::from(Count(1)), Amount::from(Count(0))]);
+    }
+
+    #[test]
+    fn test_tool_registry_respects_loan_caps() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        resources.lock().unwrap().loan_caps.set_cap("canvas", 1);
+        let mut registry = ArtistToolRegistry::new(&resources);
+        registry
+            .tool_registry(1, vec!["canvas".to_string(), "brush".to_string()])
+            .unwrap();
+        let checkout = registry
+            .tool_registry(2, vec!["canvas".to_string(), "brush".to_string()])
+            .unwrap();
+        assert_eq!(checkout.lent, vec!["brush".to_string()]);
+        assert_eq!(checkout.refused, vec!["canvas".to_string()]);
+
+        let second = &registry.artist_tool_preferences[1];
+        assert_eq!(
+            second.preferred_tools,
+            vec![registry.interner.intern("brush")]
+        );
+        let resources = resources.lock().unwrap();
+        assert_eq!(resources.stock("canvas"), TOTAL_ITEMS - Count(1));
+        assert_eq!(resources.loan_caps.on_loan("canvas"), 1);
+    }
+
+    #[test]
+    fn test_out_of_stock_checkouts_wait_in_priority_order() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        {
+            let mut resources = resources.lock().unwrap();
+            resources.loan_caps.policy = CapPolicy::Queue;
+            resources.set_quantity("canvas", Count(1)).unwrap();
+        }
+        let mut registry = ArtistToolRegistry::new(&resources);
+        let mut limits = ToolLimits::new(1, 5);
+        limits.set_artist(tool_limits::ArtistLimits {
+            id: 3,
+            tier: PriorityTier::High,
+            ..Default::default()
+        });
+        registry.set_tool_limits(limits);
+        let canvas = vec!["canvas".to_string()];
+        registry.tool_registry(0, canvas.clone()).unwrap();
+        for student in [1, 2] {
+            let checkout = registry.tool_registry(student, canvas.clone()).unwrap();
+            assert_eq!(checkout.queued, canvas);
+        }
+        // The instructor asks last but is served first; then the students in
+        // the order they asked.
+        assert!(registry.tool_registry(3, canvas.clone()).is_ok());
+        let mut served = vec![];
+        for holder in [0, 3, 1] {
+            registry.tool_return(holder, canvas.clone()).unwrap();
+            served.push(registry.artist_tool_preferences.last().unwrap().artist_id);
+        }
+        assert_eq!(served, vec![3, 1, 2]);
+        assert_eq!(resources.lock().unwrap().stock("canvas"), Count(0));
+
+        // Unknown tools still fail outright.
+        assert!(registry.tool_registry(4, vec!["kiln".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_tool_registry_holds_deposits() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        let deposit = Money::new(5_000, Currency::USD);
+        registry.deposits.require("sculpting tool", deposit);
+        registry
+            .tool_registry(1, vec!["sculpting tool".to_string(), "brush".to_string()])
+            .unwrap();
+        let rates = registry.ledger.rates();
+        assert_eq!(
+            registry.deposits.statement(1, rates).unwrap().held_total,
+            deposit
+        );
+        assert!(registry
+            .deposits
+            .statement(2, rates)
+            .unwrap()
+            .held
+            .is_empty());
+
+        registry
+            .forfeit_deposit(1, "sculpting tool", Money::new(1_000, Currency::USD))
+            .unwrap();
+        let events: Vec<LedgerEvent> = registry.ledger.entries().iter().map(|e| e.event).collect();
+        assert_eq!(
+            events,
+            vec![
+                LedgerEvent::DepositHeld,
+                LedgerEvent::Penalty,
+                LedgerEvent::DepositReleased
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tool_registry_rate_limited() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        registry.set_rate_limiter(RateLimiter::new(1, 0.0));
+        registry
+            .tool_registry(1, vec!["brush".to_string()])
+            .unwrap();
+        assert!(matches!(
+            registry.tool_registry(1, vec!["brush".to_string()]),
+            Err(RegistryError::RateLimited { artist_id: 1, .. })
+        ));
+        registry
+            .tool_registry(2, vec!["brush".to_string()])
+            .unwrap();
+        assert_eq!(registry.artist_tool_preferences.len(), 2);
+        assert_eq!(
+            resources.lock().unwrap().stock("brush"),
+            TOTAL_ITEMS - Count(2)
+        );
+        let refused: Vec<_> = registry
+            .events
+            .records()
+            .filter(|event| event.kind == State::RateLimited)
+            .map(|event| (event.artist_id, event.items))
+            .collect();
+        assert_eq!(refused, vec![(Some(1), vec!["brush".to_string()])]);
+
+        // Both checkouts check the tool count before the rate.
+        registry.set_tool_limits(ToolLimits::new(1, 1));
+        let two = vec!["brush".to_string(), "tape".to_string()];
+        assert!(matches!(
+            registry.tool_registry(3, two.clone()),
+            Err(RegistryError::ToolCount(_))
+        ));
+        assert!(matches!(
+            registry.checkout_all(3, two),
+            Err(RegistryError::ToolCount(_))
+        ));
+        registry.checkout_all(3, vec!["brush".to_string()]).unwrap();
+    }
+
+    #[test]
+    fn test_tool_registry_enforces_tool_limits() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        let mut limits = ToolLimits::new(2, 3);
+        limits.set_artist(tool_limits::ArtistLimits {
+            id: 9,
+            min: Some(1),
+            ..Default::default()
+        });
+        registry.set_tool_limits(limits);
+        assert_eq!(
+            registry.tool_count_range(9),
+            ToolCountRange { min: 1, max: 3 }
+        );
+
+        assert!(matches!(
+            registry.tool_registry(1, vec!["brush".to_string()]),
+            Err(RegistryError::ToolCount(_))
+        ));
+        registry
+            .tool_registry(9, vec!["brush".to_string()])
+            .unwrap();
+        assert_eq!(registry.artist_tool_preferences.len(), 1);
+        assert_eq!(registry.artist_tool_preferences[0].artist_id, 9);
+        assert_eq!(
+            resources.lock().unwrap().stock("brush"),
+            TOTAL_ITEMS - Count(1)
+        );
+    }
+
+    #[test]
+    fn test_artists_cannot_hold_more_than_their_maximum() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        let brushes = |count| vec!["brush".to_string(); count];
+        registry.tool_registry(1, brushes(3)).unwrap();
+        registry.report_damage(1, "brush").unwrap();
+        assert_eq!(registry.outstanding(1), 3);
+
+        assert_eq!(
+            registry.checkout_all(1, brushes(3)),
+            Err(RegistryError::HoldingCap {
+                artist_id: 1,
+                holding: 3,
+                requested: 3,
+                cap: MAX_ALLOWED_TOOLS,
+            })
+        );
+        registry.tool_registry(2, brushes(3)).unwrap();
+        registry.tool_return(1, brushes(2)).unwrap();
+        registry.checkout_all(1, brushes(4)).unwrap();
+        assert_eq!(registry.outstanding(1), MAX_ALLOWED_TOOLS);
+    }
+}
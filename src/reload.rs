@@ -0,0 +1,286 @@
+use crate::{
+    i18n::{self, Locale, Message},
+    logging,
+    registry::ArtistToolRegistry,
+    templates::{StockItem, StudioConfig},
+    units::Amount,
+};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+// How often a running daemon looks at its studio file for changes.
+pub const POLL_EVERY: Duration = Duration::from_secs(2);
+
+// Settings given by flags, which win over the file's, before and after a
+// reload. `--lang` is pinned in `i18n` instead.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Pinned {
+    pub loan_period: bool,
+    pub quotas: bool,
+}
+
+// Follows the studio file a daemon was started with, applying every change
+// to it live: thresholds, capacities, categories, tags and aliases, added
+// stock and batches, the locale, loan period, quotas and log level. Stock
+// is compared with the file as last loaded, so what the registry recorded
+// since stays; lowering it is left to `retire` and `sell`. Kits and the
+// studio's name are only checked, since nothing holds them while running.
+pub struct StudioWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    pinned: Pinned,
+    loaded: StudioConfig,
+}
+
+impl StudioWatcher {
+    pub fn open(path: PathBuf, pinned: Pinned) -> io::Result<Self> {
+        let modified = modified(&path);
+        let loaded = StudioConfig::load(&path)?;
+        Ok(StudioWatcher {
+            path,
+            modified,
+            pinned,
+            loaded,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Reloads the file if it changed since it was last looked at; None if it
+    // hasn't. A file that fails to parse or validate changes nothing.
+    pub fn poll(&mut self, registry: &Mutex<ArtistToolRegistry>) -> Option<Result<(), String>> {
+        let modified = modified(&self.path);
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+        let studio = StudioConfig::load(&self.path).map_err(|error| error.to_string());
+        Some(studio.and_then(|studio| {
+            let mut registry = registry.lock().map_err(|error| error.to_string())?;
+            apply(&self.loaded, &studio, self.pinned, &mut registry)?;
+            self.loaded = studio;
+            Ok(())
+        }))
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+// Moves the registry from the file as it was, `before`, to `after`, all or
+// nothing: refused if an item was dropped or lists less stock, or if the
+// storeroom would hold more of an item than its new capacity.
+pub fn apply(
+    before: &StudioConfig,
+    after: &StudioConfig,
+    pinned: Pinned,
+    registry: &mut ArtistToolRegistry,
+) -> Result<(), String> {
+    if let Some(item) = before
+        .tools
+        .iter()
+        .chain(&before.paints)
+        .find(|item| find(after, &item.name).is_none())
+    {
+        return Err(format!(
+            "'{}' was dropped from the file; retire or sell its stock instead",
+            item.name
+        ));
+    }
+    let mut added = vec![];
+    for item in after.tools.iter().chain(&after.paints) {
+        let was = find(before, &item.name).map(|item| item.quantity);
+        match raised_by(was, item.quantity) {
+            Some(amount) if amount.is_zero() => {}
+            Some(amount) => added.push((item.name.clone(), amount)),
+            None => {
+                return Err(format!(
+                    "'{}' lists less stock than before; retire or sell it instead",
+                    item.name
+                ))
+            }
+        }
+    }
+    let (capacity, low_stock) = after.limits();
+    let mut resources = registry
+        .shared_resources
+        .lock()
+        .map_err(|error| error.to_string())?;
+    for item in after.tools.iter().chain(&after.paints) {
+        let Some(max) = capacity.get(&item.name) else {
+            continue;
+        };
+        let holding = resources.holding(&item.name);
+        let incoming = added
+            .iter()
+            .find(|(name, _)| *name == item.name)
+            .and_then(|(_, amount)| holding.checked_add(*amount))
+            .unwrap_or(holding);
+        if incoming > max {
+            return Err(format!(
+                "'{}' would hold {}, more than its new capacity of {}",
+                item.name, incoming, max
+            ));
+        }
+    }
+    resources.capacity = capacity;
+    resources.low_stock = low_stock;
+    resources.set_catalog(after.catalog());
+    for batch in after
+        .batches
+        .iter()
+        .filter(|batch| !before.batches.contains(batch))
+    {
+        resources.add_batch(batch.clone());
+    }
+    drop(resources);
+    for (item, amount) in added {
+        registry
+            .restock(&item, amount)
+            .map_err(|error| error.to_string())?;
+    }
+    settle(after, pinned, registry);
+    Ok(())
+}
+
+// Applies the file's locale, log level, loan period and quotas, leaving
+// alone any a flag pinned or the file doesn't set.
+pub fn settle(studio: &StudioConfig, pinned: Pinned, registry: &mut ArtistToolRegistry) {
+    if let Some(locale) = studio.locale.as_deref().and_then(Locale::from_tag) {
+        i18n::set_locale(locale);
+    }
+    logging::set_level(studio.log_level());
+    if let Some(days) = studio.loan_days.filter(|_| !pinned.loan_period) {
+        registry.set_loan_period(chrono::Duration::days(days));
+    }
+    if let Some(quotas) = studio.quotas.clone().filter(|_| !pinned.quotas) {
+        registry.set_tool_limits(quotas);
+    }
+}
+
+fn find<'a>(studio: &'a StudioConfig, name: &str) -> Option<&'a StockItem> {
+    studio
+        .tools
+        .iter()
+        .chain(&studio.paints)
+        .find(|item| item.name == name)
+}
+
+// How much more `now` is than `was`, counting an item new to the file
+// from nothing; None if it is less.
+fn raised_by(was: Option<Amount>, now: Amount) -> Option<Amount> {
+    let Some(was) = was else {
+        return Some(now);
+    };
+    match (was, now) {
+        (Amount::Count(was), Amount::Count(now)) if now >= was => Some((now - was).into()),
+        (Amount::Kilograms(was), Amount::Kilograms(now)) if now >= was => Some((now - was).into()),
+        _ => None,
+    }
+}
+
+// Polls on its own thread for as long as the process runs.
+pub fn spawn(mut watcher: StudioWatcher, registry: Arc<Mutex<ArtistToolRegistry>>) {
+    thread::spawn(move || loop {
+        thread::sleep(POLL_EVERY);
+        let path = watcher.path().display().to_string();
+        match watcher.poll(&registry) {
+            Some(Ok(())) => tracing::info!(path = %path, "{}", Message::StudioReloaded(&path)),
+            Some(Err(error)) => tracing::warn!(
+                path = %path,
+                error = %error,
+                "{}",
+                Message::StudioReloadRefused(&path, error.clone())
+            ),
+            None => {}
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{units::Count, SharedResources};
+    use std::{env, fs::File, process};
+
+    #[test]
+    fn test_poll_applies_every_setting_and_refuses_what_breaks_stock() {
+        let path = env::temp_dir().join(format!("rustic-canvas-reload-{}.toml", process::id()));
+        let write = |text: &str| {
+            fs::write(&path, text).unwrap();
+            // Make sure the change shows, however coarse the file's clock.
+            let later = SystemTime::now() + Duration::from_secs(5);
+            File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(later)
+                .unwrap();
+        };
+        let studio = "name = \"test\"\n[[tools]]\nname = \"brush\"\nquantity = 4\n";
+        write(studio);
+        let resources = Arc::new(Mutex::new(StudioConfig::load(&path).unwrap().resources()));
+        let registry = Mutex::new(ArtistToolRegistry::new(&resources));
+        let mut watcher = StudioWatcher::open(path.clone(), Pinned::default()).unwrap();
+        assert_eq!(watcher.poll(&registry), None);
+
+        write(
+            "name = \"test\"\nloan_days = 3\n[quotas]\nmin = 1\nmax = 2\n\
+             [[tools]]\nname = \"brush\"\nquantity = 6\nreorder_below = 2\ncapacity = 8\n\
+             tags = [\"soft\"]\n",
+        );
+        assert_eq!(watcher.poll(&registry), Some(Ok(())));
+        assert_eq!(watcher.poll(&registry), None);
+        let limits = |resources: &Mutex<SharedResources>| {
+            let resources = resources.lock().unwrap();
+            (
+                resources.stock("brush"),
+                resources.low_stock.get("brush"),
+                resources.capacity.get("brush"),
+                resources.catalog.has_tag("brush", "soft"),
+            )
+        };
+        let applied = (Count(6), Some(Count(2).into()), Some(Count(8).into()), true);
+        assert_eq!(limits(&resources), applied);
+        {
+            let registry = registry.lock().unwrap();
+            assert_eq!(registry.loan_period(), chrono::Duration::days(3));
+            assert_eq!(registry.tool_limits().map(|limits| limits.max), Some(2));
+        }
+
+        // Less stock than the file listed before, or a capacity below what
+        // the storeroom holds now, changes nothing.
+        resources
+            .lock()
+            .unwrap()
+            .restock("brush", Count(2))
+            .unwrap();
+        let fewer = "name = \"test\"\n[[tools]]\nname = \"brush\"\nquantity = 5\n";
+        write(fewer);
+        assert!(watcher.poll(&registry).unwrap().is_err());
+        let cramped = "name = \"test\"\n[[tools]]\nname = \"brush\"\nquantity = 6\ncapacity = 7\n";
+        write(cramped);
+        assert!(watcher.poll(&registry).unwrap().is_err());
+        assert_eq!(limits(&resources), (Count(8), applied.1, applied.2, true));
+
+        // A loan period given by flag stays.
+        let mut registry = registry.lock().unwrap();
+        let mut longer = StudioConfig::load(&path).unwrap();
+        longer.loan_days = Some(10);
+        let pinned = Pinned {
+            loan_period: true,
+            quotas: false,
+        };
+        settle(&longer, pinned, &mut registry);
+        assert_eq!(registry.loan_period(), chrono::Duration::days(3));
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,148 @@
+use crate::money::Money;
+use chrono::{DateTime, Duration, Utc};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepairJob {
+    pub ticket: usize,
+    pub tool: String,
+    // Who handed the unit back damaged.
+    pub artist_id: usize,
+    pub damaged_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub duration: Option<Duration>,
+    pub cost: Option<Money>,
+}
+
+impl RepairJob {
+    // When the unit is expected back on the shelf, once work has started.
+    pub fn ready_at(&self) -> Option<DateTime<Utc>> {
+        Some(self.started_at? + self.duration?)
+    }
+}
+
+// Units handed back damaged, oldest first, until they are back in stock.
+// Their entries stay with the artist who returned them, moving from
+// `Damage` to `Repair` and then `Return`.
+#[derive(Debug, Clone, Default)]
+pub struct RepairQueue {
+    jobs: Vec<RepairJob>,
+    next_ticket: usize,
+}
+
+impl RepairQueue {
+    pub fn push(&mut self, tool: &str, artist_id: usize, at: DateTime<Utc>) -> usize {
+        self.next_ticket += 1;
+        self.jobs.push(RepairJob {
+            ticket: self.next_ticket,
+            tool: tool.to_string(),
+            artist_id,
+            damaged_at: at,
+            started_at: None,
+            duration: None,
+            cost: None,
+        });
+        self.next_ticket
+    }
+
+    pub fn get(&self, ticket: usize) -> Option<&RepairJob> {
+        self.jobs.iter().find(|job| job.ticket == ticket)
+    }
+
+    pub fn get_mut(&mut self, ticket: usize) -> Option<&mut RepairJob> {
+        self.jobs.iter_mut().find(|job| job.ticket == ticket)
+    }
+
+    pub fn remove(&mut self, ticket: usize) -> Option<RepairJob> {
+        let pos = self.jobs.iter().position(|job| job.ticket == ticket)?;
+        Some(self.jobs.remove(pos))
+    }
+
+    pub fn jobs(&self) -> &[RepairJob] {
+        &self.jobs
+    }
+
+    // Jobs no one has started work on yet.
+    pub fn waiting(&self) -> impl Iterator<Item = &RepairJob> {
+        self.jobs.iter().filter(|job| job.started_at.is_none())
+    }
+
+    pub fn for_artist(&self, artist_id: usize) -> impl Iterator<Item = &RepairJob> {
+        self.jobs
+            .iter()
+            .filter(move |job| job.artist_id == artist_id)
+    }
+
+    // Tickets whose repair time has run out by `now`.
+    pub fn ready(&self, now: DateTime<Utc>) -> Vec<usize> {
+        self.jobs
+            .iter()
+            .filter(|job| job.ready_at().is_some_and(|ready| ready <= now))
+            .map(|job| job.ticket)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::RegistryError, money::Currency, resources::TOTAL_ITEMS, units::Count,
+        ArtistToolRegistry, SharedResources, State,
+    };
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_damaged_return_goes_through_the_repair_queue() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        registry
+            .tool_registry(1, vec!["brush".to_string(), "tape".to_string()])
+            .unwrap();
+        let ticket = registry.return_damaged(1, "brush").unwrap();
+        assert_eq!(registry.outstanding(1), 1);
+        assert_eq!(registry.repairs.waiting().count(), 1);
+        assert_eq!(
+            resources.lock().unwrap().stock("brush"),
+            TOTAL_ITEMS - Count(1)
+        );
+        assert_eq!(
+            registry.complete_repair(ticket),
+            Err(RegistryError::InvalidStateTransition {
+                from: State::Damage,
+                to: State::Return,
+            })
+        );
+
+        let cost = Money::new(1_500, Currency::USD);
+        registry
+            .start_repair(ticket, Duration::hours(2), Some(cost))
+            .unwrap();
+        let job = registry.repairs.get(ticket).unwrap().clone();
+        assert_eq!(job.cost, Some(cost));
+        let ready = job.ready_at().unwrap();
+        assert!(registry
+            .complete_ready_repairs(ready - Duration::minutes(1))
+            .is_empty());
+        assert_eq!(registry.complete_ready_repairs(ready), vec![ticket]);
+
+        assert_eq!(resources.lock().unwrap().stock("brush"), TOTAL_ITEMS);
+        assert!(registry.repairs.jobs().is_empty());
+        let states: Vec<Option<State>> = registry
+            .artist_tool_preferences
+            .iter()
+            .map(|entry| entry.state)
+            .collect();
+        assert_eq!(
+            states[1..],
+            [
+                Some(State::Damage),
+                Some(State::Repair),
+                Some(State::Return)
+            ]
+        );
+        assert_eq!(
+            registry.start_repair(ticket, Duration::hours(1), None),
+            Err(RegistryError::UnknownRepair(ticket))
+        );
+    }
+}
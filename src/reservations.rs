@@ -0,0 +1,152 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reservation {
+    pub id: usize,
+    pub artist_id: usize,
+    pub tools: Vec<String>,
+    pub from: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+}
+
+impl Reservation {
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.from <= now && now < self.until
+    }
+
+    fn overlaps(&self, from: DateTime<Utc>, until: DateTime<Utc>) -> bool {
+        self.from < until && from < self.until
+    }
+
+    fn units_of(&self, tool: &str) -> usize {
+        self.tools.iter().filter(|name| *name == tool).count()
+    }
+}
+
+// Bookings that haven't been claimed, cancelled or expired yet.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reservations {
+    next_id: usize,
+    open: Vec<Reservation>,
+}
+
+impl Reservations {
+    // Bookings read back from storage. `next_id` is the last number handed
+    // out, so a new booking never takes the number of an old one.
+    pub fn restore(open: Vec<Reservation>, next_id: usize) -> Self {
+        let next_id = open
+            .iter()
+            .map(|booking| booking.id)
+            .fold(next_id, usize::max);
+        Self { next_id, open }
+    }
+
+    pub fn add(
+        &mut self,
+        artist_id: usize,
+        tools: Vec<String>,
+        from: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> usize {
+        self.next_id += 1;
+        self.open.push(Reservation {
+            id: self.next_id,
+            artist_id,
+            tools,
+            from,
+            until,
+        });
+        self.next_id
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Reservation> {
+        self.open.iter().find(|reservation| reservation.id == id)
+    }
+
+    pub fn remove(&mut self, id: usize) -> Option<Reservation> {
+        let pos = self
+            .open
+            .iter()
+            .position(|reservation| reservation.id == id)?;
+        Some(self.open.remove(pos))
+    }
+
+    pub fn open(&self) -> &[Reservation] {
+        &self.open
+    }
+
+    // Units of `tool` held back right now for anyone but `artist_id`.
+    pub fn held_for_others(&self, artist_id: usize, tool: &str, now: DateTime<Utc>) -> usize {
+        self.open
+            .iter()
+            .filter(|reservation| reservation.artist_id != artist_id && reservation.is_active(now))
+            .map(|reservation| reservation.units_of(tool))
+            .sum()
+    }
+
+    // Units of `tool` held back right now for anyone.
+    pub fn held(&self, tool: &str, now: DateTime<Utc>) -> usize {
+        self.open
+            .iter()
+            .filter(|reservation| reservation.is_active(now))
+            .map(|reservation| reservation.units_of(tool))
+            .sum()
+    }
+
+    // Units of `tool` booked at some point during `from..until`. Counts every
+    // overlapping booking, even ones that don't overlap each other.
+    pub fn booked(&self, tool: &str, from: DateTime<Utc>, until: DateTime<Utc>) -> usize {
+        self.open
+            .iter()
+            .filter(|reservation| reservation.overlaps(from, until))
+            .map(|reservation| reservation.units_of(tool))
+            .sum()
+    }
+
+    // Removes and returns the reservations whose window has ended.
+    pub fn take_expired(&mut self, now: DateTime<Utc>) -> Vec<Reservation> {
+        let (expired, open) = self
+            .open
+            .drain(..)
+            .partition(|reservation| reservation.until <= now);
+        self.open = open;
+        expired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_holds_stock_for_others_only_inside_the_window() {
+        let now = Utc::now();
+        let mut reservations = Reservations::default();
+        let easels = vec!["easel".to_string(), "easel".to_string()];
+        let id = reservations.add(
+            1,
+            easels,
+            now + Duration::hours(1),
+            now + Duration::hours(3),
+        );
+
+        assert_eq!(reservations.held_for_others(2, "easel", now), 0);
+        let during = now + Duration::hours(2);
+        assert_eq!(reservations.held_for_others(2, "easel", during), 2);
+        assert_eq!(reservations.held_for_others(1, "easel", during), 0);
+        assert_eq!(
+            reservations.booked("easel", now, now + Duration::hours(1)),
+            0
+        );
+        assert_eq!(reservations.booked("easel", now, during), 2);
+
+        assert!(reservations.take_expired(during).is_empty());
+        assert_eq!(
+            reservations.take_expired(now + Duration::hours(3))[0].id,
+            id
+        );
+        assert!(reservations.get(id).is_none());
+    }
+}
@@ -0,0 +1,605 @@
+use crate::{
+    alerts::LowStockThresholds,
+    catalog::Catalog,
+    deliveries::StorageCapacity,
+    error::{ResourceError, UnavailableTools},
+    expiry::{PaintBatch, PaintBatches},
+    loan_caps::{CapPolicy, LoanCaps, QueuedCheckout},
+    stock::{Paint, Stock, Stocked, Tool},
+    units::{Amount, Count, Kilograms, Quantity},
+};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+pub const TOTAL_ITEMS: Count = Count(10);
+pub const TOTAL_WEIGHT_KG: Kilograms = Kilograms::whole(10);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SharedResources {
+    pub tools: Stock<Tool>,
+    pub paints: Stock<Paint>,
+    pub loan_caps: LoanCaps,
+    pub capacity: StorageCapacity,
+    pub low_stock: LowStockThresholds,
+    pub batches: PaintBatches,
+    pub catalog: Catalog,
+}
+
+impl Default for SharedResources {
+    fn default() -> Self {
+        fn stock<T: Stocked>(names: [&str; 10], quantity: T::Quantity) -> Stock<T> {
+            names
+                .into_iter()
+                .map(|name| (name.to_string(), quantity))
+                .collect()
+        }
+        let mut resources = Self {
+            tools: stock(
+                [
+                    "brush",
+                    "palette",
+                    "canvas",
+                    "eraser",
+                    "sponges",
+                    "roller",
+                    "sculpting tool",
+                    "water container",
+                    "rags",
+                    "tape",
+                ],
+                TOTAL_ITEMS,
+            ),
+            paints: stock(
+                [
+                    "red", "blue", "green", "yellow", "black", "white", "purple", "orange", "pink",
+                    "brown",
+                ],
+                TOTAL_WEIGHT_KG,
+            ),
+            loan_caps: LoanCaps::new(CapPolicy::Fail),
+            capacity: StorageCapacity::default(),
+            low_stock: LowStockThresholds::default(),
+            batches: PaintBatches::default(),
+            catalog: Catalog::default(),
+        };
+        for (category, tools) in [
+            (
+                "painting",
+                &["brush", "palette", "canvas", "roller", "tape"][..],
+            ),
+            ("sculpting", &["sculpting tool"]),
+            ("cleanup", &["eraser", "sponges", "water container", "rags"]),
+        ] {
+            for tool in tools {
+                resources.categorize(tool, category);
+            }
+        }
+        for tool in ["brush", "sponges", "roller"] {
+            resources.tag(tool, "beginner-friendly");
+        }
+        for (alias, tool) in [
+            ("paintbrush", "brush"),
+            ("sponge", "sponges"),
+            ("rag", "rags"),
+            ("masking tape", "tape"),
+        ] {
+            resources.catalog.add_alias(alias, tool);
+        }
+        resources
+    }
+}
+
+impl SharedResources {
+    // Returns the tools that were held back because their loan cap is reached
+    // or, when the caps queue, because every unit is out and one has to come
+    // back first. Otherwise nothing is taken if any tool, counting repeats,
+    // has too few units left.
+    pub fn take_out_resources(&mut self, tools: Vec<String>) -> Result<Vec<String>, ResourceError> {
+        let queueing = self.loan_caps.policy == CapPolicy::Queue;
+        for (tool, count) in count_tools(&tools) {
+            let on_loan = self.loan_caps.on_loan(tool);
+            if self.stock(tool) < count && !(queueing && on_loan > 0) {
+                return Err(if on_loan > 0 {
+                    ResourceError::OutOfStock(tool.to_string())
+                } else {
+                    ResourceError::ToolNotFound(tool.to_string())
+                });
+            }
+        }
+
+        let mut held_back = vec![];
+        for tool in tools {
+            if self.stock(&tool).is_zero() || !self.loan_caps.try_lend(&tool) {
+                held_back.push(tool);
+                continue;
+            }
+            self.remove_one(&tool)?;
+        }
+        Ok(held_back)
+    }
+
+    // All-or-nothing checkout: either every requested tool (counting
+    // repeats) can be lent and all are taken, or nothing changes and the
+    // error lists each tool that couldn't be.
+    pub fn take_out_all(&mut self, tools: &[String]) -> Result<(), ResourceError> {
+        self.check_all(tools)?;
+        for tool in tools {
+            self.loan_caps.try_lend(tool);
+            self.remove_one(tool)?;
+        }
+        Ok(())
+    }
+
+    // Takes units already lent once, e.g. loans replayed from a journal, off
+    // the shelf without asking the loan caps. Nothing is taken unless every
+    // unit is on the shelf.
+    pub fn take_out_lent(&mut self, tools: &[String]) -> Result<(), ResourceError> {
+        if let Some((tool, _)) = count_tools(tools)
+            .into_iter()
+            .find(|(tool, count)| self.stock(tool) < *count)
+        {
+            return Err(ResourceError::OutOfStock(tool.to_string()));
+        }
+        for tool in tools {
+            let on_loan = self.loan_caps.on_loan(tool);
+            self.loan_caps.set_on_loan(tool, on_loan + 1);
+            self.remove_one(tool)?;
+        }
+        Ok(())
+    }
+
+    // Whether `take_out_all` would lend every tool right now.
+    pub fn check_all(&self, tools: &[String]) -> Result<(), ResourceError> {
+        let mut unavailable = UnavailableTools::default();
+        for (tool, count) in count_tools(tools) {
+            let on_loan = self.loan_caps.on_loan(tool);
+            if self.stock(tool) < count {
+                unavailable.missing.push(tool.to_string());
+            } else if self
+                .loan_caps
+                .cap(tool)
+                .is_some_and(|cap| on_loan + count.get() > cap)
+            {
+                unavailable.capped.push(tool.to_string());
+            }
+        }
+        if !unavailable.missing.is_empty() || !unavailable.capped.is_empty() {
+            return Err(ResourceError::Unavailable(unavailable));
+        }
+        Ok(())
+    }
+
+    // Puts returned tools back on the shelf. A unit whose loan slot was
+    // waited for goes straight to the next queued checkout for it; those
+    // hand-offs are returned so they can be recorded. Nothing is put back
+    // unless every unit is counted as out on loan.
+    pub fn return_resources(
+        &mut self,
+        tools: &[String],
+        now: DateTime<Utc>,
+    ) -> Result<Vec<QueuedCheckout>, ResourceError> {
+        for (tool, count) in count_tools(tools) {
+            let on_loan = Count::of(self.loan_caps.on_loan(tool));
+            if on_loan.checked_sub(count).is_none() {
+                return Err(ResourceError::Miscounted {
+                    tool: tool.to_string(),
+                    counted: on_loan,
+                    removed: count,
+                    on_loan: true,
+                });
+            }
+        }
+        let mut handed_off = vec![];
+        for tool in tools {
+            self.add_tools(tool, Count(1));
+            if let Some(next) = self.loan_caps.release(tool, now) {
+                self.remove_one(tool)?;
+                handed_off.push(next);
+            }
+        }
+        Ok(handed_off)
+    }
+
+    // Takes the requested kilograms of each color. Nothing is taken unless
+    // every color has enough left; a color listed twice counts both amounts.
+    pub fn take_paints(&mut self, requests: &[(String, Kilograms)]) -> Result<(), ResourceError> {
+        let mut wanted: HashMap<&str, Kilograms> = HashMap::new();
+        for (color, kg) in requests {
+            *wanted.entry(color.as_str()).or_default() += *kg;
+        }
+        for (color, _) in requests {
+            let available_kg = self
+                .paints
+                .get(color)
+                .map(|paint| paint.weight_kg)
+                .ok_or_else(|| ResourceError::UnknownPaint(color.clone()))?;
+            if available_kg < wanted[color.as_str()] {
+                return Err(ResourceError::PaintUnderStock {
+                    color: color.clone(),
+                    requested_kg: wanted[color.as_str()],
+                    available_kg,
+                });
+            }
+        }
+        for (color, kg) in wanted {
+            if let Some(paint) = self.paints.get_mut(color) {
+                paint.weight_kg -= kg;
+            }
+            self.batches.consume(color, kg);
+        }
+        self.refresh_batches();
+        Ok(())
+    }
+
+    pub fn stock(&self, tool: &str) -> Count {
+        self.tools.quantity(tool)
+    }
+
+    // Takes one unit of a tool off the shelf, delisting it once none are left.
+    fn remove_one(&mut self, tool: &str) -> Result<(), ResourceError> {
+        let counted = self.stock(tool);
+        let left = counted
+            .checked_sub(Count(1))
+            .ok_or_else(|| ResourceError::Miscounted {
+                tool: tool.to_string(),
+                counted,
+                removed: Count(1),
+                on_loan: false,
+            })?;
+        if let Some(item) = self.tools.get_mut(tool) {
+            item.quantity = left;
+            if item.quantity.is_zero() {
+                self.tools.remove(tool);
+            }
+        }
+        Ok(())
+    }
+
+    // Sets the stock of a tool or paint outright; a quantity of zero delists
+    // the item. Items not stocked yet are listed as tools given a count and
+    // as paints given kilograms.
+    pub fn set_quantity(
+        &mut self,
+        item: &str,
+        quantity: impl Into<Amount>,
+    ) -> Result<(), ResourceError> {
+        match self.measured(item, quantity.into())? {
+            Amount::Count(count) => {
+                self.tools.set(item, count);
+                self.label(item);
+            }
+            Amount::Kilograms(kg) => self.paints.set(item, kg),
+        }
+        Ok(())
+    }
+
+    // Adds stock of a tool or paint, listing it again if it had run out.
+    pub fn restock(
+        &mut self,
+        item: &str,
+        quantity: impl Into<Amount>,
+    ) -> Result<(), ResourceError> {
+        match self.measured(item, quantity.into())? {
+            Amount::Count(count) => self.add_tools(item, count),
+            Amount::Kilograms(kg) => {
+                self.paints.add(item, kg);
+            }
+        }
+        Ok(())
+    }
+
+    fn add_tools(&mut self, tool: &str, count: Count) {
+        self.tools.add(tool, count);
+        self.label(tool);
+    }
+
+    // What the storeroom has to find room for: the stock on the shelf, and
+    // for tools the units out on loan too.
+    pub fn holding(&self, item: &str) -> Amount {
+        match self.amount_of(item) {
+            Amount::Count(count) => Amount::Count(count + Count::of(self.loan_caps.on_loan(item))),
+            weight => weight,
+        }
+    }
+
+    // What the studio holds of an item, in whatever it is measured in.
+    pub fn amount_of(&self, item: &str) -> Amount {
+        match self.paints.get(item) {
+            Some(paint) => paint.weight_kg.into(),
+            None => self.stock(item).into(),
+        }
+    }
+
+    // Reads a quantity typed for `item` as a bare number: a whole number of
+    // a paint is that many kilograms, while a fraction of a tool is refused.
+    pub fn measure(&self, item: &str, quantity: Amount) -> Result<Amount, ResourceError> {
+        match quantity {
+            Amount::Count(count) if self.paints.contains(item) => {
+                Ok(Kilograms::whole(count.0).into())
+            }
+            _ => self.measured(item, quantity),
+        }
+    }
+
+    // `quantity` back if it is in the unit `item` goes by: kilograms for
+    // paint, units for tools on the shelf or out on loan. Anything else can
+    // be either.
+    fn measured(&self, item: &str, quantity: Amount) -> Result<Amount, ResourceError> {
+        let mismatched = match quantity {
+            Amount::Count(_) => self.paints.contains(item),
+            Amount::Kilograms(_) => self.tools.contains(item) || self.loan_caps.on_loan(item) > 0,
+        };
+        if mismatched {
+            return Err(ResourceError::WrongUnit {
+                item: item.to_string(),
+                quantity,
+            });
+        }
+        Ok(quantity)
+    }
+
+    // Restocks a delivery, all or nothing: nothing is added if any item,
+    // counting repeats, comes in the wrong unit or would go over its storage
+    // capacity.
+    pub fn receive(&mut self, items: &[(String, Amount)]) -> Result<(), ResourceError> {
+        let mut delivered: Vec<(&str, Amount)> = vec![];
+        for (item, quantity) in items {
+            let quantity = self.measured(item, *quantity)?;
+            match delivered.iter_mut().find(|(name, _)| name == item) {
+                Some((_, total)) => {
+                    *total =
+                        total
+                            .checked_add(quantity)
+                            .ok_or_else(|| ResourceError::WrongUnit {
+                                item: item.clone(),
+                                quantity,
+                            })?;
+                }
+                None => delivered.push((item, quantity)),
+            }
+        }
+        for &(item, delivered) in &delivered {
+            let Some(capacity) = self.capacity.get(item) else {
+                continue;
+            };
+            let holding = self.holding(item);
+            if holding
+                .checked_add(delivered)
+                .is_some_and(|total| total > capacity)
+            {
+                return Err(ResourceError::OverCapacity {
+                    item: item.to_string(),
+                    holding,
+                    delivered,
+                    capacity,
+                });
+            }
+        }
+        for (item, quantity) in delivered {
+            self.restock(item, quantity)?;
+        }
+        Ok(())
+    }
+
+    // Records a dated batch making up part of a color's stock.
+    pub fn add_batch(&mut self, batch: PaintBatch) {
+        self.batches.add(batch);
+        self.refresh_batches();
+    }
+
+    // Takes every batch that has expired by `now` out of usable stock and
+    // returns them with the kilograms actually removed.
+    pub fn expire_batches(&mut self, now: DateTime<Utc>) -> Vec<PaintBatch> {
+        let mut expired = self.batches.take_expired(now);
+        for batch in &mut expired {
+            match self.paints.get_mut(&batch.color) {
+                Some(paint) => {
+                    batch.kg = batch.kg.min(paint.weight_kg);
+                    paint.weight_kg -= batch.kg;
+                }
+                None => batch.kg = Kilograms::default(),
+            }
+        }
+        expired.retain(|batch| !batch.kg.is_zero());
+        self.refresh_batches();
+        expired
+    }
+
+    // Points each paint at the batch its next kilogram comes out of.
+    fn refresh_batches(&mut self) {
+        for paint in self.paints.iter_mut() {
+            let next = self
+                .batches
+                .batches()
+                .iter()
+                .find(|batch| batch.color == paint.color);
+            paint.batch = next.map(|batch| batch.batch.clone());
+            paint.expiry = next.map(|batch| batch.expires);
+        }
+    }
+}
+
+// Distinct tools in request order, with how many of each are wanted.
+fn count_tools(tools: &[String]) -> Vec<(&str, Count)> {
+    let mut wanted: Vec<(&str, Count)> = vec![];
+    for tool in tools {
+        match wanted.iter_mut().find(|(name, _)| name == tool) {
+            Some((_, count)) => *count += Count(1),
+            None => wanted.push((tool, Count(1))),
+        }
+    }
+    wanted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shared_resources_initialization() {
+        let resources = SharedResources::default();
+        assert_eq!(resources.tools.len(), 10);
+        assert_eq!(resources.paints.len(), 10);
+    }
+
+    #[test]
+    fn test_take_out_resources() {
+        let mut resources = SharedResources::default();
+        let initial_tool_count = resources.stock("brush");
+        resources
+            .take_out_resources(vec!["brush".to_string()])
+            .unwrap();
+        assert_eq!(resources.stock("brush"), initial_tool_count - Count(1));
+    }
+
+    #[test]
+    fn test_take_out_resources_reports_missing_tools() {
+        let mut resources = SharedResources::default();
+        assert_eq!(
+            resources.take_out_resources(vec!["brush".to_string(), "easel".to_string()]),
+            Err(ResourceError::ToolNotFound("easel".to_string()))
+        );
+        assert_eq!(resources.stock("brush"), TOTAL_ITEMS);
+
+        resources
+            .take_out_resources(vec!["tape".to_string(); TOTAL_ITEMS.get()])
+            .unwrap();
+        assert_eq!(
+            resources.take_out_resources(vec!["tape".to_string()]),
+            Err(ResourceError::OutOfStock("tape".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_take_out_all_leaves_stock_untouched_on_failure() {
+        let mut resources = SharedResources::default();
+        resources.loan_caps.set_cap("canvas", 1);
+        let tools = |names: &[&str]| {
+            names
+                .iter()
+                .map(|name| name.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        let Err(ResourceError::Unavailable(error)) =
+            resources.take_out_all(&tools(&["brush", "easel", "canvas", "canvas"]))
+        else {
+            panic!("checkout should have been refused");
+        };
+        assert_eq!(error.missing, tools(&["easel"]));
+        assert_eq!(error.capped, tools(&["canvas"]));
+        assert_eq!(
+            error.to_string(),
+            "not available: easel; loan cap reached: canvas"
+        );
+        assert_eq!(resources.stock("brush"), TOTAL_ITEMS);
+        assert_eq!(resources.loan_caps.on_loan("canvas"), 0);
+
+        resources
+            .take_out_all(&tools(&["brush", "brush", "canvas"]))
+            .unwrap();
+        assert_eq!(resources.stock("brush"), TOTAL_ITEMS - Count(2));
+        assert_eq!(resources.loan_caps.on_loan("canvas"), 1);
+    }
+
+    #[test]
+    fn test_take_paints_is_all_or_nothing() {
+        let mut resources = SharedResources::default();
+        let request = |color: &str, kg| (color.to_string(), Kilograms::new(kg).unwrap());
+        assert_eq!(
+            resources.take_paints(&[
+                request("red", 4.0),
+                request("blue", 6.0),
+                request("red", 7.0)
+            ]),
+            Err(ResourceError::PaintUnderStock {
+                color: "red".to_string(),
+                requested_kg: Kilograms::whole(11),
+                available_kg: TOTAL_WEIGHT_KG
+            })
+        );
+        assert_eq!(
+            resources.take_paints(&[request("teal", 1.0)]),
+            Err(ResourceError::UnknownPaint("teal".to_string()))
+        );
+        assert_eq!(resources.paints.quantity("blue"), TOTAL_WEIGHT_KG);
+
+        resources
+            .take_paints(&[
+                request("red", 4.0),
+                request("blue", 5.75),
+                request("blue", 0.25),
+            ])
+            .unwrap();
+        assert_eq!(resources.paints.quantity("red"), Kilograms::whole(6));
+        assert_eq!(resources.paints.quantity("blue"), Kilograms::whole(4));
+    }
+
+    #[test]
+    fn test_set_quantity_overrides_tools_and_paints() {
+        let mut resources = SharedResources::default();
+        resources.set_quantity("brush", Count(3)).unwrap();
+        resources
+            .set_quantity("red", Kilograms::grams(2_500))
+            .unwrap();
+        resources.set_quantity("easel", Count(2)).unwrap();
+        resources.set_quantity("tape", Count(0)).unwrap();
+        assert_eq!(resources.stock("brush"), Count(3));
+        assert_eq!(resources.paints.quantity("red"), Kilograms::grams(2_500));
+        assert_eq!(
+            resources.tools.amounts().last().unwrap(),
+            &("easel".to_string(), Count(2))
+        );
+        assert!(!resources.tools.contains("tape"));
+
+        // Neither brushes by the kilogram nor paint by the unit.
+        assert_eq!(
+            resources.restock("brush", Kilograms::whole(1)),
+            Err(ResourceError::WrongUnit {
+                item: "brush".to_string(),
+                quantity: Kilograms::whole(1).into(),
+            })
+        );
+        assert!(resources.set_quantity("red", Count(4)).is_err());
+        resources.restock("ochre", Kilograms::grams(500)).unwrap();
+        assert_eq!(resources.amount_of("ochre"), Kilograms::grams(500).into());
+    }
+
+    #[test]
+    fn test_return_resources_hands_capped_units_to_queue() {
+        let mut resources = SharedResources {
+            loan_caps: LoanCaps::new(CapPolicy::Queue),
+            ..SharedResources::default()
+        };
+        resources.loan_caps.set_cap("canvas", 1);
+        let now = Utc::now();
+        resources
+            .take_out_resources(vec!["canvas".to_string(), "tape".to_string()])
+            .unwrap();
+        let capped = resources
+            .take_out_resources(vec!["canvas".to_string()])
+            .unwrap();
+        resources
+            .loan_caps
+            .defer(2, &capped[0], Default::default(), now);
+
+        let handed_off = resources
+            .return_resources(&["canvas".to_string()], now)
+            .unwrap();
+        assert_eq!(handed_off[0].artist_id, 2);
+        assert_eq!(resources.stock("canvas"), TOTAL_ITEMS - Count(1));
+        assert_eq!(resources.loan_caps.on_loan("canvas"), 1);
+
+        assert!(resources
+            .return_resources(&["tape".to_string()], now)
+            .unwrap()
+            .is_empty());
+        assert_eq!(resources.stock("tape"), TOTAL_ITEMS);
+        // A unit that was never lent can't come back.
+        assert!(matches!(
+            resources.return_resources(&["tape".to_string()], now),
+            Err(ResourceError::Miscounted { on_loan: true, .. })
+        ));
+        assert_eq!(resources.stock("tape"), TOTAL_ITEMS);
+    }
+}
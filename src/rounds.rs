@@ -0,0 +1,286 @@
+use crate::{
+    artwork::Artwork,
+    error::RegistryError,
+    interrupt, logging,
+    profiles::SkillLevel,
+    simulation::{log_task, simulated_clock, use_paint, Artist, SimulationConfig},
+    stock::{Paint, Stock},
+    units::{Count, Kilograms},
+    ArtistToolRegistry,
+};
+use rand::{seq::SliceRandom, Rng};
+use std::sync::Mutex;
+
+// How likely an artist with nothing out is to check something out in a
+// round, and one who has worked to keep going rather than hand back.
+pub const CHECKOUT_CHANCE: f64 = 0.75;
+pub const KEEP_WORKING_CHANCE: f64 = 0.5;
+
+// What an artist does with a round.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    CheckOut,
+    Work,
+    Return,
+    Idle,
+}
+
+// How one round went, across every artist.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RoundStats {
+    pub round: usize,
+    pub checkouts: usize,
+    pub working: usize,
+    pub returns: usize,
+    pub idle: usize,
+    // Steps the registry refused.
+    pub failed: usize,
+    pub paint_kg: Kilograms,
+    // Units out with artists once the round is over.
+    pub on_loan: Count,
+}
+
+// An artist and the piece they're working on, if any.
+struct Seat {
+    artist: Artist,
+    sketch: Option<Artwork>,
+    worked: bool,
+}
+
+impl Seat {
+    // Artists with nothing out check out or idle; the rest work at least one
+    // round with what they have before they might hand it back.
+    fn next_step(&mut self, holding: bool) -> Step {
+        let rng = &mut self.artist.rng;
+        match (holding, self.worked) {
+            (false, _) if rng.gen_bool(CHECKOUT_CHANCE) => Step::CheckOut,
+            (false, _) => Step::Idle,
+            (true, false) => Step::Work,
+            (true, true) if rng.gen_bool(KEEP_WORKING_CHANCE) => Step::Work,
+            (true, true) => Step::Return,
+        }
+    }
+
+    // Artists with favorite colors paint with those; the rest with whatever
+    // is on the shelf.
+    fn pick_paint(&mut self, paints: &Stock<Paint>) -> Option<(String, Kilograms)> {
+        if self
+            .artist
+            .profile
+            .as_ref()
+            .is_some_and(|profile| !profile.favorite_colors.is_empty())
+        {
+            return self.artist.pick_paint(paints);
+        }
+        let kg = SkillLevel::default().paint_per_round();
+        let colors: Vec<&str> = paints
+            .iter()
+            .filter(|paint| paint.weight_kg >= kg)
+            .map(|paint| paint.color.as_str())
+            .collect();
+        colors
+            .choose(&mut self.artist.rng)
+            .map(|color| (color.to_string(), kg))
+    }
+}
+
+// Runs the simulation a round at a time: every artist takes one step per
+// round, in id order, and the registry's simulated clock (if any) moves on
+// once the round is done. Seeded runs repeat exactly. Returns each round's
+// stats and whatever the registry refused.
+pub fn run_rounds(
+    registry: &Mutex<ArtistToolRegistry>,
+    config: &SimulationConfig,
+) -> (Vec<RoundStats>, Vec<RegistryError>) {
+    let (mut seats, clock) = {
+        let mut registry = registry.lock().expect("Failed to lock registry");
+        let clock = simulated_clock(&mut registry, config);
+        let seats: Vec<Seat> = (0..config.artists)
+            .map(|id| Seat {
+                artist: Artist::new(id, &registry, config),
+                sketch: None,
+                worked: false,
+            })
+            .collect();
+        (seats, clock)
+    };
+    let mut rounds = vec![];
+    let mut errors = vec![];
+    for round in 0..config.rounds {
+        if interrupt::global().requested() {
+            break;
+        }
+        let mut stats = RoundStats {
+            round: round + 1,
+            ..RoundStats::default()
+        };
+        let mut registry = registry.lock().expect("Failed to lock registry");
+        for seat in &mut seats {
+            match take_step(&mut registry, seat, config, &mut stats) {
+                Ok(step) => match step {
+                    Step::CheckOut => stats.checkouts += 1,
+                    Step::Work => stats.working += 1,
+                    Step::Return => stats.returns += 1,
+                    Step::Idle => stats.idle += 1,
+                },
+                Err(error) => {
+                    stats.failed += 1;
+                    errors.push(error);
+                }
+            }
+        }
+        stats.on_loan = seats
+            .iter()
+            .map(|seat| Count::of(registry.held_tools(seat.artist.id).values().sum()))
+            .sum();
+        drop(registry);
+        if let Some(clock) = &clock {
+            clock.tick();
+        }
+        rounds.push(stats);
+    }
+    (rounds, errors)
+}
+
+// A checkout that lends nothing, with every tool queued or refused, leaves
+// the artist idle for the round.
+fn take_step(
+    registry: &mut ArtistToolRegistry,
+    seat: &mut Seat,
+    config: &SimulationConfig,
+    stats: &mut RoundStats,
+) -> Result<Step, RegistryError> {
+    let id = seat.artist.id;
+    let mut holding: Vec<String> = registry
+        .held_tools(id)
+        .into_iter()
+        .filter_map(|(symbol, count)| Some((registry.interner.resolve(symbol)?, count)))
+        .flat_map(|(tool, count)| vec![tool.to_string(); count])
+        .collect();
+    holding.sort();
+    match seat.next_step(!holding.is_empty()) {
+        Step::CheckOut => {
+            let range = match config.tools_per_artist {
+                Some(range) => range,
+                None => registry.tool_count_range(id),
+            };
+            let tools = seat
+                .artist
+                .pick_tools(&registry.shared_resources.lock()?.tools, range);
+            let checkout = registry.tool_registry(id, tools)?;
+            if checkout.lent.is_empty() {
+                return Ok(Step::Idle);
+            }
+            if !config.quiet {
+                logging::checked_out(id, &checkout.lent, &*registry.shared_resources.lock()?);
+            }
+            let mut sketch = registry.start_artwork(id, checkout.lent);
+            sketch.advance(registry.now());
+            seat.sketch = Some(sketch);
+            seat.worked = false;
+            Ok(Step::CheckOut)
+        }
+        // Units handed over from the queue come without a sketch, so one is
+        // started for them.
+        Step::Work => {
+            let mut sketch = match seat.sketch.take() {
+                Some(sketch) => sketch,
+                None => {
+                    let mut sketch = registry.start_artwork(id, holding.clone());
+                    sketch.advance(registry.now());
+                    sketch
+                }
+            };
+            let paint = seat.pick_paint(&registry.shared_resources.lock()?.paints);
+            let result = match paint {
+                Some(paint) => {
+                    let kg = paint.1;
+                    use_paint(registry, &mut sketch, paint, config).map(|()| kg)
+                }
+                None => Ok(Kilograms::default()),
+            };
+            let task = seat.artist.next_task(config.fatigue.as_ref());
+            sketch.work_with_tools(task.work_time);
+            seat.sketch = Some(sketch);
+            seat.worked = true;
+            let damaged = seat.artist.damaged_tool(&task, &mut holding);
+            if let Some(tool) = &damaged {
+                registry.return_damaged(id, tool)?;
+            }
+            log_task(id, &task, damaged.as_deref(), config);
+            stats.paint_kg += result?;
+            Ok(Step::Work)
+        }
+        Step::Return => {
+            registry.tool_return(id, holding.clone())?;
+            if !config.quiet {
+                logging::returned(id, &holding, &*registry.shared_resources.lock()?);
+            }
+            if let Some(sketch) = seat.sketch.take() {
+                registry.finish_artwork(sketch);
+            }
+            seat.worked = false;
+            Ok(Step::Return)
+        }
+        Step::Idle => Ok(Step::Idle),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tool_limits::ToolCountRange, units::Quantity, SharedResources, State};
+    use std::sync::Arc;
+
+    fn run(seed: u64) -> (Vec<RoundStats>, ArtistToolRegistry) {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let registry = Mutex::new(ArtistToolRegistry::new(&resources));
+        let config = SimulationConfig {
+            artists: 3,
+            tools_per_artist: Some(ToolCountRange { min: 1, max: 2 }),
+            rounds: 12,
+            seed: Some(seed),
+            quiet: true,
+            ..SimulationConfig::default()
+        };
+        let (rounds, errors) = run_rounds(&registry, &config);
+        assert!(errors.is_empty(), "{:?}", errors);
+        (rounds, registry.into_inner().unwrap())
+    }
+
+    #[test]
+    fn test_artists_take_one_step_a_round() {
+        let (rounds, registry) = run(3);
+        assert_eq!(rounds.len(), 12);
+        assert_eq!(rounds[0].round, 1);
+        for stats in &rounds {
+            assert_eq!(
+                stats.checkouts + stats.working + stats.returns + stats.idle,
+                3
+            );
+        }
+        // Nobody works or returns before checking out.
+        assert_eq!(rounds[0].working + rounds[0].returns, 0);
+        let total = |step: fn(&RoundStats) -> usize| rounds.iter().map(step).sum::<usize>();
+        assert!(total(|stats| stats.returns) > 0);
+        assert!(rounds.iter().any(|stats| !stats.paint_kg.is_zero()));
+
+        let entries = |state| {
+            registry
+                .history()
+                .filter(|entry| entry.state == Some(state))
+                .count()
+        };
+        assert_eq!(entries(State::TakeOut), total(|stats| stats.checkouts));
+        assert_eq!(entries(State::Return), total(|stats| stats.returns));
+        assert_eq!(entries(State::Paint), total(|stats| stats.working));
+        assert_eq!(registry.gallery.len(), total(|stats| stats.returns));
+        let on_loan: usize = (0..3)
+            .map(|id| registry.held_tools(id).values().sum::<usize>())
+            .sum();
+        assert_eq!(rounds.last().unwrap().on_loan, Count::of(on_loan));
+
+        assert_eq!(run(3).0, rounds);
+        assert_ne!(run(4).0, rounds);
+    }
+}
@@ -0,0 +1,336 @@
+use crate::{
+    costs::ProfitLoss,
+    drying::DryingStats,
+    stats::Stats,
+    units::{Count, Kilograms},
+    ArtistToolRegistry, State,
+};
+use chrono::{DateTime, Utc};
+use std::{fmt::Write as _, fs, io, path::Path, str::FromStr};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReportFormat {
+    #[default]
+    Markdown,
+    Html,
+}
+
+impl ReportFormat {
+    // HTML for `.html` and `.htm` files, Markdown for anything else.
+    pub fn for_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("html" | "htm") => ReportFormat::Html,
+            _ => ReportFormat::Markdown,
+        }
+    }
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "markdown" | "md" => Ok(ReportFormat::Markdown),
+            "html" => Ok(ReportFormat::Html),
+            _ => Err(format!("unknown report format '{}'", text)),
+        }
+    }
+}
+
+// A unit an artist damaged or lost during the run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Incident {
+    pub at: Option<DateTime<Utc>>,
+    pub artist_id: usize,
+    pub tool: String,
+    pub kind: State,
+}
+
+struct Table {
+    title: &'static str,
+    headers: &'static [&'static str],
+    rows: Vec<Vec<String>>,
+}
+
+// Everything worth knowing once a run is over, ready to be written out as a
+// document: what's left in stock, who did what, which tools were wanted
+// most, what got damaged or lost, and where the money went.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunReport {
+    pub at: DateTime<Utc>,
+    pub tools: Vec<(String, Count)>,
+    pub paints: Vec<(String, Kilograms)>,
+    pub stats: Stats,
+    pub incidents: Vec<Incident>,
+    // None when the run had no budget.
+    pub budget: Option<ProfitLoss>,
+    // None when the studio has no drying racks.
+    pub drying: Option<DryingStats>,
+}
+
+impl RunReport {
+    pub fn compute(registry: &ArtistToolRegistry, now: DateTime<Utc>) -> Self {
+        let (tools, paints) = {
+            let resources = registry
+                .shared_resources
+                .lock()
+                .expect("Failed to lock resources");
+            (resources.tools.amounts(), resources.paints.amounts())
+        };
+        let incidents = registry
+            .history()
+            .filter(|entry| matches!(entry.state, Some(State::Damage | State::Lost)))
+            .flat_map(|entry| {
+                entry.preferred_tools.iter().filter_map(|&symbol| {
+                    Some(Incident {
+                        at: entry.datetime,
+                        artist_id: entry.artist_id,
+                        tool: registry.interner.resolve(symbol)?.to_string(),
+                        kind: entry.state.unwrap_or(State::Damage),
+                    })
+                })
+            })
+            .collect();
+        Self {
+            at: now,
+            tools,
+            paints,
+            stats: Stats::compute(registry, now),
+            incidents,
+            // Left out if it can't be totalled; the CLI says why.
+            budget: (!registry.budget.is_empty())
+                .then(|| registry.budget.summary().ok())
+                .flatten(),
+            drying: registry.racks.as_ref().map(|racks| racks.stats()),
+        }
+    }
+
+    fn tables(&self) -> Vec<Table> {
+        let mut tables = vec![
+            Table {
+                title: "Final stock",
+                headers: &["item", "kind", "on shelf"],
+                rows: self
+                    .tools
+                    .iter()
+                    .map(|(tool, quantity)| (tool, "tool", quantity.to_string()))
+                    .chain(
+                        self.paints
+                            .iter()
+                            .map(|(paint, kg)| (paint, "paint (kg)", kg.to_string())),
+                    )
+                    .map(|(item, kind, quantity)| vec![item.clone(), kind.to_string(), quantity])
+                    .collect(),
+            },
+            Table {
+                title: "Artist activity",
+                headers: &["artist", "checkouts", "returns", "paint kg"],
+                rows: self
+                    .stats
+                    .artists
+                    .iter()
+                    .map(|artist| {
+                        vec![
+                            artist.artist_id.to_string(),
+                            artist.checkouts.to_string(),
+                            artist.returns.to_string(),
+                            artist.paint_kg.to_string(),
+                        ]
+                    })
+                    .collect(),
+            },
+            Table {
+                title: "Popular tools",
+                headers: &["tool", "checkouts", "utilization"],
+                rows: self
+                    .stats
+                    .tools
+                    .iter()
+                    .filter(|tool| tool.checkouts > 0)
+                    .map(|tool| {
+                        vec![
+                            tool.tool.clone(),
+                            tool.checkouts.to_string(),
+                            format!("{:.0}%", tool.utilization * 100.0),
+                        ]
+                    })
+                    .collect(),
+            },
+            Table {
+                title: "Incidents",
+                headers: &["time", "artist", "tool", "what"],
+                rows: self
+                    .incidents
+                    .iter()
+                    .map(|incident| {
+                        vec![
+                            incident
+                                .at
+                                .map(|at| at.format("%Y-%m-%d %H:%M:%S").to_string())
+                                .unwrap_or_default(),
+                            incident.artist_id.to_string(),
+                            incident.tool.clone(),
+                            match incident.kind {
+                                State::Lost => "lost",
+                                _ => "damaged",
+                            }
+                            .to_string(),
+                        ]
+                    })
+                    .collect(),
+            },
+        ];
+        if let Some(budget) = &self.budget {
+            tables.push(Table {
+                title: "Budget",
+                headers: &["", "amount"],
+                rows: [
+                    ("revenue", budget.revenue),
+                    ("restocks", budget.restocks),
+                    ("repairs", budget.repairs),
+                    ("losses", budget.losses),
+                    ("profit", budget.profit),
+                    ("balance", budget.balance),
+                ]
+                .into_iter()
+                .map(|(line, amount)| vec![line.to_string(), amount.to_string()])
+                .collect(),
+            });
+        }
+        if let Some(drying) = &self.drying {
+            tables.push(Table {
+                title: "Drying racks",
+                headers: &["", "value"],
+                rows: [
+                    ("racks", drying.capacity.to_string()),
+                    ("in use", drying.occupied.to_string()),
+                    ("waiting", drying.queued.to_string()),
+                    ("most waiting", drying.max_queued.to_string()),
+                    ("racked", drying.racked_total.to_string()),
+                    (
+                        "average wait (min)",
+                        drying.avg_wait_for_rack.num_minutes().to_string(),
+                    ),
+                ]
+                .into_iter()
+                .map(|(line, value)| vec![line.to_string(), value])
+                .collect(),
+            });
+        }
+        tables
+    }
+
+    pub fn render(&self, format: ReportFormat) -> String {
+        let generated = self.at.format("%Y-%m-%d %H:%M:%S UTC");
+        let mut text = String::new();
+        match format {
+            ReportFormat::Markdown => {
+                let _ = writeln!(text, "# Run report\n\nGenerated {}.", generated);
+                for table in self.tables() {
+                    let _ = writeln!(text, "\n## {}\n", table.title);
+                    if table.rows.is_empty() {
+                        let _ = writeln!(text, "None.");
+                        continue;
+                    }
+                    let cells = |cells: Vec<String>| {
+                        format!("| {} |", cells.join(" | ").replace('\n', " "))
+                    };
+                    let headers = table.headers.iter().map(|header| header.to_string());
+                    let _ = writeln!(text, "{}", cells(headers.collect()));
+                    let rule = table.headers.iter().map(|_| "---".to_string());
+                    let _ = writeln!(text, "{}", cells(rule.collect()));
+                    for row in table.rows {
+                        let row = row.iter().map(|cell| cell.replace('|', "\\|"));
+                        let _ = writeln!(text, "{}", cells(row.collect()));
+                    }
+                }
+            }
+            ReportFormat::Html => {
+                let _ = writeln!(
+                    text,
+                    "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>Run report</title></head>\n<body>\n<h1>Run report</h1>\n<p>Generated {}.</p>",
+                    generated
+                );
+                for table in self.tables() {
+                    let _ = writeln!(text, "<h2>{}</h2>", table.title);
+                    if table.rows.is_empty() {
+                        let _ = writeln!(text, "<p>None.</p>");
+                        continue;
+                    }
+                    let _ = writeln!(text, "<table>");
+                    let _ = write!(text, "<tr>");
+                    for header in table.headers {
+                        let _ = write!(text, "<th>{}</th>", escape(header));
+                    }
+                    let _ = writeln!(text, "</tr>");
+                    for row in &table.rows {
+                        let _ = write!(text, "<tr>");
+                        for cell in row {
+                            let _ = write!(text, "<td>{}</td>", escape(cell));
+                        }
+                        let _ = writeln!(text, "</tr>");
+                    }
+                    let _ = writeln!(text, "</table>");
+                }
+                let _ = writeln!(text, "</body>\n</html>");
+            }
+        }
+        text
+    }
+
+    pub fn write(&self, path: &Path, format: ReportFormat) -> io::Result<()> {
+        fs::write(path, self.render(format))
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SharedResources;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_report_covers_stock_activity_and_incidents() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        resources
+            .lock()
+            .unwrap()
+            .restock("<easel>", Count(1))
+            .unwrap();
+        let mut registry = ArtistToolRegistry::new(&resources);
+        registry
+            .tool_registry(1, vec!["brush".to_string(), "<easel>".to_string()])
+            .unwrap();
+        registry.report_lost(1, "brush").unwrap();
+        registry.report_damage(1, "<easel>").unwrap();
+        let report = RunReport::compute(&registry, Utc::now());
+        assert_eq!(report.incidents.len(), 2);
+        assert_eq!(report.incidents[0].kind, State::Lost);
+        assert!(report.budget.is_none());
+
+        let markdown = report.render(ReportFormat::Markdown);
+        assert!(markdown.contains("## Final stock"));
+        assert!(markdown.contains("| brush | tool | 9 |"));
+        assert!(markdown.contains("| 1 | 2 | 0 | 0 |"));
+        assert!(markdown.contains("| 1 | brush | lost |"));
+        assert!(!markdown.contains("## Budget"));
+        assert!(!markdown.contains("## Drying racks"));
+        let html = report.render(ReportFormat::Html);
+        assert!(html.contains("<td>&lt;easel&gt;</td><td>damaged</td>"));
+        assert!(!html.contains("<td><easel>"));
+
+        assert_eq!(
+            ReportFormat::for_path(Path::new("run.html")),
+            ReportFormat::Html
+        );
+        assert_eq!("md".parse(), Ok(ReportFormat::Markdown));
+        assert!("pdf".parse::<ReportFormat>().is_err());
+    }
+}
@@ -0,0 +1,227 @@
+use crate::{
+    artwork::{Artwork, Stage},
+    costs::CashFlow,
+    error::RegistryError,
+    ledger::LedgerEvent,
+    money::{Currency, ExchangeRates, Money, UnknownCurrency},
+    units::Kilograms,
+    ArtistToolRegistry, State,
+};
+use std::collections::BTreeMap;
+
+// What a finished piece is listed at: a base price plus a share for each
+// tool it took and each kilogram of paint on it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pricing {
+    pub base: Money,
+    pub per_tool: Money,
+    pub per_kg: Money,
+}
+
+impl Pricing {
+    // The studio's standard rates, in `currency`.
+    pub fn standard(currency: Currency) -> Self {
+        Self {
+            base: Money::new(4_000, currency),
+            per_tool: Money::new(500, currency),
+            per_kg: Money::new(250, currency),
+        }
+    }
+
+    // In the base currency of `rates`, whatever the parts are priced in.
+    pub fn price(
+        &self,
+        artwork: &Artwork,
+        rates: &ExchangeRates,
+    ) -> Result<Money, UnknownCurrency> {
+        let kg: Kilograms = artwork.paints.iter().map(|&(_, kg)| kg).sum();
+        rates.sum([
+            self.base,
+            Money::new(
+                self.per_tool.minor_units * artwork.tools.len() as i64,
+                self.per_tool.currency,
+            ),
+            Money::new(
+                (self.per_kg.minor_units as f64 * kg.get()).round() as i64,
+                self.per_kg.currency,
+            ),
+        ])
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArtistRevenue {
+    pub artist_id: usize,
+    pub sold: usize,
+    // In the ledger's base currency.
+    pub revenue: Money,
+}
+
+impl ArtistToolRegistry {
+    // Sets or changes the asking price of a piece still for sale.
+    pub fn price_artwork(&mut self, id: usize, price: Money) -> Result<(), RegistryError> {
+        self.ledger
+            .rates()
+            .to_base(price)
+            .map_err(|UnknownCurrency(currency)| RegistryError::UnknownCurrency(currency))?;
+        let artwork = self
+            .gallery
+            .get_mut(id)
+            .ok_or(RegistryError::UnknownArtwork(id))?;
+        if artwork.sold.is_some() {
+            return Err(RegistryError::AlreadySold(id));
+        }
+        artwork.price = Some(price);
+        Ok(())
+    }
+
+    // Sells a piece at its asking price. Like tools sold off the shelf it's
+    // logged as `Sold` and booked as a sale, but under the artist who made
+    // it.
+    pub fn sell_artwork(&mut self, id: usize) -> Result<Money, RegistryError> {
+        self.dry_artworks();
+        let now = self.now();
+        let artwork = self
+            .gallery
+            .get_mut(id)
+            .ok_or(RegistryError::UnknownArtwork(id))?;
+        if artwork.sold.is_some() {
+            return Err(RegistryError::AlreadySold(id));
+        }
+        if artwork.dried.is_none() {
+            return Err(RegistryError::StillDrying(id));
+        }
+        let price = artwork.price.ok_or(RegistryError::Unpriced(id))?;
+        debug_assert_eq!(artwork.stage, Stage::Finished);
+        artwork.sold = Some(now);
+        let artist_id = artwork.artist_id;
+        let item = format!("artwork {}", id);
+        self.record_event(
+            Some(artist_id),
+            State::Sold,
+            std::slice::from_ref(&item),
+            now,
+        );
+        let memo = format!("sold {} by artist {}", item, artist_id);
+        self.book(CashFlow::Revenue, price, memo.clone());
+        self.record_ledger(LedgerEvent::Sale, price, now, memo);
+        Ok(price)
+    }
+
+    // Prices every unpriced piece by `pricing`, then sells everything still
+    // listed, which is what has dried. Returns the ids sold.
+    pub fn sell_gallery(&mut self, pricing: &Pricing) -> Result<Vec<usize>, RegistryError> {
+        self.dry_artworks();
+        let listed: Vec<(usize, Option<Money>, Money)> = self
+            .gallery
+            .listed()
+            .map(|artwork| {
+                let suggested = pricing.price(artwork, self.ledger.rates())?;
+                Ok((artwork.id, artwork.price, suggested))
+            })
+            .collect::<Result<_, UnknownCurrency>>()
+            .map_err(|UnknownCurrency(currency)| RegistryError::UnknownCurrency(currency))?;
+        let mut sold = vec![];
+        for (id, price, suggested) in listed {
+            if price.is_none() {
+                self.price_artwork(id, suggested)?;
+            }
+            self.sell_artwork(id)?;
+            sold.push(id);
+        }
+        Ok(sold)
+    }
+
+    // Pieces sold and what they brought in, per artist, highest revenue
+    // first.
+    pub fn revenue_by_artist(&self) -> Vec<ArtistRevenue> {
+        let base = self.ledger.rates().base();
+        let mut by_artist: BTreeMap<usize, ArtistRevenue> = BTreeMap::new();
+        for artwork in self.gallery.iter().filter(|artwork| artwork.sold.is_some()) {
+            // Prices were checked against the rates when they were set.
+            let Some(price) = artwork
+                .price
+                .and_then(|price| self.ledger.rates().to_base(price).ok())
+            else {
+                continue;
+            };
+            let line = by_artist.entry(artwork.artist_id).or_insert(ArtistRevenue {
+                artist_id: artwork.artist_id,
+                sold: 0,
+                revenue: Money::zero(base),
+            });
+            line.sold += 1;
+            // Both are in the base currency, so this can't mix them.
+            if let Ok(revenue) = line.revenue.checked_add(price) {
+                line.revenue = revenue;
+            }
+        }
+        let mut revenue: Vec<ArtistRevenue> = by_artist.into_values().collect();
+        revenue.sort_by_key(|line| std::cmp::Reverse(line.revenue.minor_units));
+        revenue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SharedResources;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_gallery_sales_book_revenue_per_artist() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        let mut painted = registry.start_artwork(1, vec!["brush".to_string()]);
+        painted.advance(registry.now());
+        painted.add_paint("red", Kilograms::whole(2));
+        let painted = registry.finish_artwork(painted);
+        let sketch = registry.finish_artwork(registry.start_artwork(2, vec![]));
+        let third = registry.finish_artwork(registry.start_artwork(1, vec![]));
+
+        assert_eq!(
+            registry.sell_artwork(sketch),
+            Err(RegistryError::Unpriced(sketch))
+        );
+        assert_eq!(
+            registry.price_artwork(sketch, Money::new(100, Currency::EUR)),
+            Err(RegistryError::UnknownCurrency(Currency::EUR))
+        );
+        registry
+            .price_artwork(sketch, Money::new(10_000, Currency::USD))
+            .unwrap();
+        assert_eq!(
+            registry.sell_artwork(sketch),
+            Ok(Money::new(10_000, Currency::USD))
+        );
+        assert_eq!(
+            registry.sell_artwork(sketch),
+            Err(RegistryError::AlreadySold(sketch))
+        );
+        let event = registry.events.last().unwrap();
+        assert_eq!((event.kind, event.artist_id), (State::Sold, Some(2)));
+
+        assert_eq!(
+            registry.sell_gallery(&Pricing::standard(Currency::USD)),
+            Ok(vec![painted, third])
+        );
+        assert_eq!(registry.gallery.listed().count(), 0);
+        assert_eq!(registry.ledger.entries().len(), 3);
+        assert_eq!(
+            registry.revenue_by_artist(),
+            vec![
+                ArtistRevenue {
+                    artist_id: 2,
+                    sold: 1,
+                    revenue: Money::new(10_000, Currency::USD),
+                },
+                ArtistRevenue {
+                    artist_id: 1,
+                    sold: 2,
+                    // 45.00 + 2 × 2.50 for the painted one, 40.00 for the other.
+                    revenue: Money::new(9_000, Currency::USD),
+                },
+            ]
+        );
+    }
+}
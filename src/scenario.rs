@@ -0,0 +1,257 @@
+use crate::{
+    clock::{self, MockClock},
+    error::RegistryError,
+    units::Kilograms,
+    ArtistToolRegistry,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{de::Error as _, Deserialize, Deserializer};
+use std::{collections::BTreeMap, fmt, fs, io, path::Path, sync::Arc};
+
+fn span<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    let text = String::deserialize(deserializer)?;
+    clock::parse_span(&text).ok_or_else(|| {
+        D::Error::custom(format!(
+            "invalid duration '{}', expected e.g. 90s, 30m or 2h",
+            text
+        ))
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Checkout(Vec<String>),
+    // Kilograms per color.
+    Paint(BTreeMap<String, Kilograms>),
+    // Every tool the artist has checked out when the list is empty.
+    Return(Vec<String>),
+    Damage(String),
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::Checkout(tools) => write!(f, "checkout {}", tools.join(", ")),
+            Action::Paint(paints) => {
+                let paints: Vec<String> = paints
+                    .iter()
+                    .map(|(color, kg)| format!("{}={}", color, kg))
+                    .collect();
+                write!(f, "paint {}", paints.join(", "))
+            }
+            Action::Return(tools) if tools.is_empty() => write!(f, "return everything"),
+            Action::Return(tools) => write!(f, "return {}", tools.join(", ")),
+            Action::Damage(tool) => write!(f, "damage {}", tool),
+        }
+    }
+}
+
+// One thing an artist does, and how long it keeps them busy before their
+// next step.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Step {
+    #[serde(flatten)]
+    pub action: Action,
+    #[serde(default, rename = "for", deserialize_with = "span")]
+    pub takes: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ScenarioArtist {
+    pub id: usize,
+    // Offset from the start of the run.
+    #[serde(default, deserialize_with = "span")]
+    pub arrives: Duration,
+    #[serde(default)]
+    pub steps: Vec<Step>,
+}
+
+// A scripted workload: who arrives when and what each artist does, in
+// simulated time. `start` fixes the timestamps too; without it the run starts
+// now.
+//
+//   start = "2026-01-05T09:00:00Z"
+//
+//   [[artist]]
+//   id = 1
+//   arrives = "0s"
+//   steps = [
+//       { checkout = ["brush", "canvas"], for = "5m" },
+//       { paint = { red = 2 }, for = "45m" },
+//       { damage = "brush" },
+//       { return = [] },
+//   ]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct Scenario {
+    #[serde(default)]
+    pub start: Option<DateTime<Utc>>,
+    #[serde(default, rename = "artist")]
+    pub artists: Vec<ScenarioArtist>,
+}
+
+#[derive(Debug)]
+pub struct StepOutcome {
+    // Offset from the start of the run.
+    pub at: Duration,
+    pub artist_id: usize,
+    pub action: Action,
+    // Tools a checkout queued or refused under loan caps.
+    pub held_back: Vec<String>,
+    pub result: Result<(), RegistryError>,
+}
+
+impl Scenario {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        toml::from_str(text).map_err(|error| error.to_string())
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+
+    // Every step with when it happens, in the order the run takes them:
+    // by time, then by the artist's place in the file, then step order.
+    pub fn timeline(&self) -> Vec<(Duration, usize, &Action)> {
+        let mut timeline = vec![];
+        for artist in &self.artists {
+            let mut at = artist.arrives;
+            for step in &artist.steps {
+                timeline.push((at, artist.id, &step.action));
+                at += step.takes;
+            }
+        }
+        timeline.sort_by_key(|&(at, _, _)| at);
+        timeline
+    }
+
+    // Runs every step against `registry` on a clock moved to each step's
+    // time, so the same scenario always does the same thing. Failed steps are
+    // reported and the artist carries on.
+    pub fn run(&self, registry: &mut ArtistToolRegistry) -> Vec<StepOutcome> {
+        let start = self.start.unwrap_or_else(Utc::now);
+        let clock = MockClock::new(start);
+        registry.set_clock(Arc::new(clock.clone()));
+        self.timeline()
+            .into_iter()
+            .map(|(at, artist_id, action)| {
+                clock.set(start + at);
+                let mut held_back = vec![];
+                let result = match action {
+                    Action::Checkout(tools) => registry
+                        .tool_registry(artist_id, tools.clone())
+                        .map(|checkout| {
+                            held_back = checkout.queued;
+                            held_back.extend(checkout.refused);
+                        }),
+                    Action::Paint(paints) => {
+                        registry.paint_checkout(artist_id, paints.clone().into_iter().collect())
+                    }
+                    Action::Return(tools) if tools.is_empty() => {
+                        let held = held_by(registry, artist_id);
+                        registry.tool_return(artist_id, held)
+                    }
+                    Action::Return(tools) => registry.tool_return(artist_id, tools.clone()),
+                    Action::Damage(tool) => registry.report_damage(artist_id, tool),
+                };
+                StepOutcome {
+                    at,
+                    artist_id,
+                    action: action.clone(),
+                    held_back,
+                    result,
+                }
+            })
+            .collect()
+    }
+}
+
+// Every unit the artist has checked out, by name, in a fixed order.
+fn held_by(registry: &ArtistToolRegistry, artist_id: usize) -> Vec<String> {
+    let mut held: Vec<String> = registry
+        .held_tools(artist_id)
+        .into_iter()
+        .filter_map(|(symbol, count)| Some((registry.interner.resolve(symbol)?, count)))
+        .flat_map(|(tool, count)| std::iter::repeat_n(tool.to_string(), count))
+        .collect();
+    held.sort();
+    held
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SharedResources, State};
+    use std::sync::Mutex;
+
+    const SCENARIO: &str = r#"
+        start = "2026-01-05T09:00:00Z"
+
+        [[artist]]
+        id = 1
+        steps = [
+            { checkout = ["brush", "canvas"], for = "5m" },
+            { paint = { red = 2 }, for = "45m" },
+            { damage = "brush" },
+            { return = [] },
+        ]
+
+        [[artist]]
+        id = 2
+        arrives = "50m"
+        steps = [{ return = ["easel"] }, { checkout = ["canvas"] }]
+    "#;
+
+    #[test]
+    fn test_scenario_runs_the_same_every_time() {
+        let scenario = Scenario::parse(SCENARIO).unwrap();
+        let order: Vec<(i64, usize)> = scenario
+            .timeline()
+            .iter()
+            .map(|&(at, id, _)| (at.num_minutes(), id))
+            .collect();
+        assert_eq!(
+            order,
+            vec![(0, 1), (5, 1), (50, 1), (50, 1), (50, 2), (50, 2)]
+        );
+
+        let run = || {
+            let resources = Arc::new(Mutex::new(SharedResources::default()));
+            let mut registry = ArtistToolRegistry::new(&resources);
+            let outcomes = scenario.run(&mut registry);
+            let stock = resources.lock().unwrap().tools.amounts();
+            let events: Vec<_> = registry
+                .events
+                .records()
+                .map(|event| (event.at, event.kind, event.items))
+                .collect();
+            (outcomes, stock, events)
+        };
+        let (outcomes, stock, events) = run();
+        let failed: Vec<usize> = outcomes
+            .iter()
+            .enumerate()
+            .filter(|(_, outcome)| outcome.result.is_err())
+            .map(|(index, _)| index)
+            .collect();
+        // Artist 2 never had an easel.
+        assert_eq!(failed, vec![4]);
+        assert_eq!(outcomes[3].action.to_string(), "return everything");
+        let start = scenario.start.unwrap();
+        assert_eq!(
+            events[1],
+            (
+                start + Duration::minutes(5),
+                State::Paint,
+                vec!["red".to_string()]
+            )
+        );
+        assert_eq!(events.last().unwrap().2, vec!["canvas".to_string()]);
+        assert_eq!(run().1, stock);
+        assert_eq!(run().2, events);
+
+        assert!(Scenario::parse("[[artist]]\nid = 1\narrives = \"soon\"").is_err());
+        assert!(Scenario::parse("[[artist]]\nid = 1\nsteps = [{ juggle = 3 }]").is_err());
+    }
+}
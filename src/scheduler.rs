@@ -0,0 +1,404 @@
+use crate::{
+    daemon, deliveries::Delivery, dump::StateDump, error::ResourceError, i18n::Message,
+    lock_stats::REGISTRY_LOCK, money::Money, units::Amount, ArtistToolRegistry,
+};
+use chrono::{DateTime, Datelike, Duration, NaiveTime, TimeZone, Timelike, Utc, Weekday};
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduleError(pub String);
+
+impl fmt::Display for ScheduleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+// When a job fires, in UTC. Written as "hourly at :15", "daily at 02:00" or
+// "weekly Monday 08:00"; the "at" is optional.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Schedule {
+    Hourly { minute: u32 },
+    Daily { time: NaiveTime },
+    Weekly { weekday: Weekday, time: NaiveTime },
+}
+
+impl Schedule {
+    pub fn parse(spec: &str) -> Result<Self, ScheduleError> {
+        let words: Vec<&str> = spec
+            .split_whitespace()
+            .filter(|word| !word.eq_ignore_ascii_case("at"))
+            .collect();
+        let invalid = || ScheduleError(format!("invalid schedule '{}'", spec));
+        let time = |word: &str| NaiveTime::parse_from_str(word, "%H:%M").map_err(|_| invalid());
+
+        match words.as_slice() {
+            [every] if every.eq_ignore_ascii_case("hourly") => Ok(Schedule::Hourly { minute: 0 }),
+            [every, minute] if every.eq_ignore_ascii_case("hourly") => {
+                match minute.trim_start_matches(':').parse() {
+                    Ok(minute) if minute < 60 => Ok(Schedule::Hourly { minute }),
+                    _ => Err(invalid()),
+                }
+            }
+            [every, at] if every.eq_ignore_ascii_case("daily") => {
+                Ok(Schedule::Daily { time: time(at)? })
+            }
+            [every, day, at] if every.eq_ignore_ascii_case("weekly") => Ok(Schedule::Weekly {
+                weekday: day.parse().map_err(|_| invalid())?,
+                time: time(at)?,
+            }),
+            _ => Err(invalid()),
+        }
+    }
+
+    // The first firing strictly after `after`.
+    pub fn next_after(&self, after: DateTime<Utc>) -> DateTime<Utc> {
+        let at =
+            |date: chrono::NaiveDate, time: NaiveTime| Utc.from_utc_datetime(&date.and_time(time));
+        match *self {
+            Schedule::Hourly { minute } => {
+                let hour = NaiveTime::from_hms_opt(after.hour(), minute, 0).expect("valid minute");
+                let candidate = at(after.date_naive(), hour);
+                if candidate > after {
+                    candidate
+                } else {
+                    candidate + Duration::hours(1)
+                }
+            }
+            Schedule::Daily { time } => {
+                let candidate = at(after.date_naive(), time);
+                if candidate > after {
+                    candidate
+                } else {
+                    candidate + Duration::days(1)
+                }
+            }
+            Schedule::Weekly { weekday, time } => {
+                let days_ahead = (7 + weekday.num_days_from_monday()
+                    - after.weekday().num_days_from_monday())
+                    % 7;
+                let candidate =
+                    at(after.date_naive(), time) + Duration::days(i64::from(days_ahead));
+                if candidate > after {
+                    candidate
+                } else {
+                    candidate + Duration::weeks(1)
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Task {
+    // Prints the same inventory summary as the daemon's `status` command.
+    Report,
+    // Writes a full state dump to the given file.
+    Dump(PathBuf),
+    // Receives a standing order from a supplier.
+    Deliver(Delivery),
+    // Takes expired paint batches out of stock on behalf of the admin.
+    Sweep { admin_id: usize },
+    // Logs tools that have gone overdue since the last run and lists them.
+    Overdue,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Job {
+    pub name: String,
+    pub task: Task,
+    pub schedule: Schedule,
+}
+
+#[derive(Deserialize)]
+struct JobConfig {
+    name: Option<String>,
+    task: String,
+    schedule: String,
+    path: Option<PathBuf>,
+    admin_id: Option<usize>,
+    supplier: Option<String>,
+    cost: Option<String>,
+    #[serde(default)]
+    items: BTreeMap<String, Amount>,
+}
+
+#[derive(Deserialize)]
+struct SchedulerConfig {
+    #[serde(default)]
+    job: Vec<JobConfig>,
+}
+
+// Reads `[[job]]` tables with `task`, `schedule`, and optional `name` and
+// `path` keys. Deliver jobs also take a `supplier`, a `cost` such as
+// "120.00 USD" and an `items` table of quantities, measured in whatever
+// each item comes in once the delivery arrives; sweep jobs take an
+// `admin_id`.
+pub fn parse_jobs(config: &str) -> Result<Vec<Job>, ScheduleError> {
+    let config: SchedulerConfig =
+        toml::from_str(config).map_err(|error| ScheduleError(error.to_string()))?;
+    config
+        .job
+        .into_iter()
+        .map(|job| {
+            let task = match (job.task.as_str(), job.path) {
+                ("report", _) => Task::Report,
+                ("overdue", _) => Task::Overdue,
+                ("dump", Some(path)) => Task::Dump(path),
+                ("dump", None) => return Err(ScheduleError("dump jobs need a 'path'".to_string())),
+                ("deliver", _) => Task::Deliver(delivery(job.supplier, job.cost, job.items)?),
+                ("sweep", _) => match job.admin_id {
+                    Some(admin_id) => Task::Sweep { admin_id },
+                    None => return Err(ScheduleError("sweep jobs need an 'admin_id'".to_string())),
+                },
+                (other, _) => return Err(ScheduleError(format!("unknown task '{}'", other))),
+            };
+            Ok(Job {
+                name: job.name.unwrap_or_else(|| job.task.clone()),
+                task,
+                schedule: Schedule::parse(&job.schedule)?,
+            })
+        })
+        .collect()
+}
+
+fn delivery(
+    supplier: Option<String>,
+    cost: Option<String>,
+    items: BTreeMap<String, Amount>,
+) -> Result<Delivery, ScheduleError> {
+    let (Some(supplier), Some(cost)) = (supplier, cost) else {
+        return Err(ScheduleError(
+            "deliver jobs need a 'supplier' and a 'cost'".to_string(),
+        ));
+    };
+    let Some(cost) = Money::parse(&cost) else {
+        return Err(ScheduleError(format!("invalid cost '{}'", cost)));
+    };
+    if items.is_empty() {
+        return Err(ScheduleError("deliver jobs need 'items'".to_string()));
+    }
+    Ok(Delivery {
+        supplier,
+        items: items.into_iter().collect(),
+        cost,
+    })
+}
+
+pub fn load_jobs(path: &Path) -> Result<Vec<Job>, ScheduleError> {
+    let config = fs::read_to_string(path).map_err(|error| ScheduleError(error.to_string()))?;
+    parse_jobs(&config)
+}
+
+pub struct Scheduler {
+    jobs: Vec<(Job, DateTime<Utc>)>,
+}
+
+impl Scheduler {
+    pub fn new(jobs: Vec<Job>, now: DateTime<Utc>) -> Self {
+        let jobs = jobs
+            .into_iter()
+            .map(|job| {
+                let next = job.schedule.next_after(now);
+                (job, next)
+            })
+            .collect();
+        Self { jobs }
+    }
+
+    pub fn next_due(&self) -> Option<DateTime<Utc>> {
+        self.jobs.iter().map(|(_, next)| *next).min()
+    }
+
+    // Jobs due at or before `now`, each rescheduled for its next firing. A job
+    // that missed several firings runs once.
+    pub fn take_due(&mut self, now: DateTime<Utc>) -> Vec<Job> {
+        let mut due = vec![];
+        for (job, next) in &mut self.jobs {
+            if *next <= now {
+                due.push(job.clone());
+                *next = job.schedule.next_after(now);
+            }
+        }
+        due
+    }
+}
+
+pub fn run_job(job: &Job, registry: &Mutex<ArtistToolRegistry>) -> Result<String, ScheduleError> {
+    let mut registry = REGISTRY_LOCK.lock(registry);
+    match &job.task {
+        Task::Report => Ok(daemon::status(&registry)),
+        Task::Dump(path) => {
+            let json = StateDump::capture(&registry)
+                .to_json()
+                .map_err(|error| ScheduleError(error.to_string()))?;
+            fs::write(path, json).map_err(|error| ScheduleError(error.to_string()))?;
+            Ok(format!("state written to {}\n", path.display()))
+        }
+        Task::Deliver(delivery) => {
+            let items = {
+                let resources = registry
+                    .shared_resources
+                    .lock()
+                    .expect("Failed to lock resources");
+                delivery
+                    .items
+                    .iter()
+                    .map(|(item, quantity)| Ok((item.clone(), resources.measure(item, *quantity)?)))
+                    .collect::<Result<_, ResourceError>>()
+                    .map_err(|error| ScheduleError(error.to_string()))?
+            };
+            registry
+                .receive_delivery(Delivery {
+                    items,
+                    ..delivery.clone()
+                })
+                .map_err(|error| ScheduleError(error.to_string()))?;
+            Ok(format!("delivery from {} received\n", delivery.supplier))
+        }
+        Task::Sweep { admin_id } => {
+            let now = registry.now();
+            let expired = registry
+                .sweep_expired_paints(*admin_id, now)
+                .map_err(|error| ScheduleError(error.to_string()))?;
+            Ok(format!(
+                "{} expired paint batch(es) removed\n",
+                expired.len()
+            ))
+        }
+        Task::Overdue => Ok(daemon::overdue(&registry.sweep_overdue())),
+    }
+}
+
+// Runs the jobs on a background thread for as long as the process lives.
+pub fn spawn(mut scheduler: Scheduler, registry: Arc<Mutex<ArtistToolRegistry>>) {
+    thread::spawn(move || {
+        while let Some(next) = scheduler.next_due() {
+            if let Ok(wait) = (next - Utc::now()).to_std() {
+                thread::sleep(wait);
+            }
+            for job in scheduler.take_due(Utc::now()) {
+                match run_job(&job, &registry) {
+                    Ok(output) => tracing::info!(job = %job.name, "{}", output.trim_end()),
+                    Err(error) => tracing::warn!(
+                        job = %job.name,
+                        error = %error,
+                        "{}",
+                        Message::JobFailed(&job.name, error.to_string())
+                    ),
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        units::{Count, Kilograms},
+        SharedResources,
+    };
+
+    fn utc(text: &str) -> DateTime<Utc> {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn test_schedule_next_after() {
+        let monday_noon = utc("2024-05-06T12:00:00Z");
+        let hourly = Schedule::parse("hourly at :15").unwrap();
+        assert_eq!(hourly.next_after(monday_noon), utc("2024-05-06T12:15:00Z"));
+
+        let daily = Schedule::parse("daily at 02:00").unwrap();
+        assert_eq!(daily.next_after(monday_noon), utc("2024-05-07T02:00:00Z"));
+
+        let weekly = Schedule::parse("weekly Monday 08:00").unwrap();
+        assert_eq!(weekly.next_after(monday_noon), utc("2024-05-13T08:00:00Z"));
+        let friday = Schedule::parse("weekly fri 08:00").unwrap();
+        assert_eq!(friday.next_after(monday_noon), utc("2024-05-10T08:00:00Z"));
+
+        assert!(Schedule::parse("daily 25:00").is_err());
+        assert!(Schedule::parse("fortnightly").is_err());
+    }
+
+    #[test]
+    fn test_parse_jobs_from_toml() {
+        let jobs = parse_jobs(
+            r#"
+            [[job]]
+            name = "weekly report"
+            task = "report"
+            schedule = "weekly Monday 08:00"
+
+            [[job]]
+            task = "dump"
+            schedule = "hourly"
+            path = "state.json"
+
+            [[job]]
+            task = "deliver"
+            schedule = "weekly Thursday 09:00"
+            supplier = "Pigment House"
+            cost = "84.50 EUR"
+            items = { red = 4, brush = 6 }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(jobs.len(), 3);
+        assert_eq!(jobs[0].name, "weekly report");
+        assert_eq!(jobs[1].task, Task::Dump(PathBuf::from("state.json")));
+        let Task::Deliver(delivery) = &jobs[2].task else {
+            panic!("expected a delivery, got {:?}", jobs[2].task);
+        };
+        assert_eq!(
+            delivery.items,
+            vec![
+                ("brush".to_string(), Count(6).into()),
+                ("red".to_string(), Count(4).into())
+            ]
+        );
+        assert_eq!(delivery.cost.to_string(), "84.50 EUR");
+        // A whole number of paint arrives as that many kilograms.
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let registry = Mutex::new(ArtistToolRegistry::new(&resources));
+        run_job(&jobs[2], &registry).unwrap();
+        assert_eq!(
+            resources.lock().unwrap().paints.quantity("red"),
+            Kilograms::whole(14)
+        );
+        assert!(parse_jobs("[[job]]\ntask = \"deliver\"\nschedule = \"hourly\"\n").is_err());
+        assert!(parse_jobs("[[job]]\ntask = \"sweep\"\nschedule = \"hourly\"\n").is_err());
+        let overdue = parse_jobs("[[job]]\ntask = \"overdue\"\nschedule = \"daily 09:00\"\n");
+        assert_eq!(overdue.unwrap()[0].task, Task::Overdue);
+
+        let unknown = parse_jobs("[[job]]\ntask = \"audit\"\nschedule = \"daily 02:00\"\n");
+        assert_eq!(
+            unknown,
+            Err(ScheduleError("unknown task 'audit'".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_scheduler_runs_due_jobs_once() {
+        let jobs = parse_jobs("[[job]]\ntask = \"report\"\nschedule = \"hourly\"\n").unwrap();
+        let mut scheduler = Scheduler::new(jobs, utc("2024-05-06T12:30:00Z"));
+        assert_eq!(scheduler.next_due(), Some(utc("2024-05-06T13:00:00Z")));
+        assert!(scheduler.take_due(utc("2024-05-06T12:59:00Z")).is_empty());
+
+        let due = scheduler.take_due(utc("2024-05-06T15:10:00Z"));
+        assert_eq!(due.len(), 1);
+        assert_eq!(scheduler.next_due(), Some(utc("2024-05-06T16:00:00Z")));
+
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let registry = Mutex::new(ArtistToolRegistry::new(&resources));
+        assert!(run_job(&due[0], &registry).unwrap().contains("brush"));
+    }
+}
@@ -0,0 +1,357 @@
+use crate::{interrupt, units::Amount, ArtistToolRegistry, SharedResources, State};
+use std::{
+    fmt,
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+}
+
+impl Comparison {
+    // Never, for amounts in different units.
+    fn holds(self, left: Amount, right: Amount) -> bool {
+        match self {
+            Comparison::Less => left < right,
+            Comparison::LessOrEqual => left <= right,
+            Comparison::Greater => left > right,
+            Comparison::GreaterOrEqual => left >= right,
+            Comparison::Equal => left == right,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    Reorder(Amount),
+    Log(String),
+}
+
+// `when stock('<item>') <op> <n> then <action>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    pub line: usize,
+    pub item: String,
+    pub comparison: Comparison,
+    pub threshold: Amount,
+    pub action: Action,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FiredRule {
+    pub line: usize,
+    pub item: String,
+    pub stock: Amount,
+    pub action: Action,
+}
+
+// `at <offset> checkout <artist_id> <tool>, <tool>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimedCheckout {
+    pub offset: Duration,
+    pub artist_id: usize,
+    pub tools: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+// A scenario: checkouts at fixed offsets from the start, plus rules checked
+// after every checkout. `#` starts a comment.
+//
+//   at 0ms checkout 1 brush, canvas
+//   at 1.5s checkout 2 brush
+//   when stock('brush') < 3 then reorder 10
+//   when stock('red') <= 2 then log "order more red"
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Script {
+    pub rules: Vec<Rule>,
+    pub checkouts: Vec<TimedCheckout>,
+}
+
+impl Script {
+    pub fn parse(text: &str) -> Result<Self, ScriptError> {
+        let mut script = Script::default();
+        for (index, line) in text.lines().enumerate() {
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error = |message: &str| ScriptError {
+                line: index + 1,
+                message: message.to_string(),
+            };
+            if let Some(rest) = line.strip_prefix("when ") {
+                script
+                    .rules
+                    .push(parse_rule(index + 1, rest).map_err(|m| error(&m))?);
+            } else if let Some(rest) = line.strip_prefix("at ") {
+                script
+                    .checkouts
+                    .push(parse_checkout(rest).map_err(|m| error(&m))?);
+            } else {
+                return Err(error("expected 'when ...' or 'at ...'"));
+            }
+        }
+        script.checkouts.sort_by_key(|checkout| checkout.offset);
+        Ok(script)
+    }
+
+    // Applies every rule whose condition holds and returns what fired, with
+    // reorders in the unit the item goes by. A rule that gives a tool in
+    // kilograms never fires.
+    pub fn apply_rules(&self, resources: &mut SharedResources) -> Vec<FiredRule> {
+        let mut fired = vec![];
+        for rule in &self.rules {
+            let stock = resources.amount_of(&rule.item);
+            let Ok(threshold) = resources.measure(&rule.item, rule.threshold) else {
+                continue;
+            };
+            if !rule.comparison.holds(stock, threshold) {
+                continue;
+            }
+            let action = match &rule.action {
+                Action::Reorder(quantity) => {
+                    let Ok(quantity) = resources.measure(&rule.item, *quantity) else {
+                        continue;
+                    };
+                    if resources.restock(&rule.item, quantity).is_err() {
+                        continue;
+                    }
+                    Action::Reorder(quantity)
+                }
+                action => action.clone(),
+            };
+            fired.push(FiredRule {
+                line: rule.line,
+                item: rule.item.clone(),
+                stock,
+                action,
+            });
+        }
+        fired
+    }
+
+    // Runs the checkouts at `speed` times their scripted pace, checking the
+    // rules after each one. Returns the rule actions in the order they fired.
+    pub fn run(&self, registry: &Mutex<ArtistToolRegistry>, speed: f64) -> Vec<FiredRule> {
+        let speed = speed.max(f64::EPSILON);
+        let started = Instant::now();
+        let mut fired = vec![];
+        for checkout in &self.checkouts {
+            if interrupt::global().requested() {
+                break;
+            }
+            let due = started + checkout.offset.div_f64(speed);
+            if let Some(wait) = due.checked_duration_since(Instant::now()) {
+                thread::sleep(wait);
+            }
+            let mut registry = registry.lock().expect("Failed to lock registry");
+            // A refused checkout leaves the stock as it was; the rules still
+            // get to look at it.
+            let _ = registry.tool_registry(checkout.artist_id, checkout.tools.clone());
+            let mut resources = registry
+                .shared_resources
+                .lock()
+                .expect("Failed to lock resources");
+            let applied = self.apply_rules(&mut resources);
+            drop(resources);
+            for rule in &applied {
+                if let Action::Reorder(quantity) = rule.action {
+                    let amounts = [(rule.item.clone(), quantity)];
+                    let now = registry.now();
+                    registry.record_amounts(None, State::New, &amounts, now);
+                }
+            }
+            fired.extend(applied);
+        }
+        fired
+    }
+}
+
+fn strip_comment(line: &str) -> &str {
+    // A '#' inside a quoted log message isn't a comment.
+    let mut quoted = false;
+    for (pos, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..pos],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn parse_rule(line: usize, rest: &str) -> Result<Rule, String> {
+    let rest = rest
+        .trim()
+        .strip_prefix("stock(")
+        .ok_or("expected stock('<item>')")?;
+    let (item, rest) = rest.split_once(')').ok_or("missing ')'")?;
+    let item = item
+        .trim()
+        .trim_matches(|c| c == '\'' || c == '"')
+        .to_string();
+
+    let (condition, action) = rest.split_once(" then ").ok_or("missing 'then'")?;
+    let (operator, threshold) = condition
+        .trim()
+        .split_once(' ')
+        .ok_or("expected a comparison such as '< 3'")?;
+    let comparison = match operator {
+        "<" => Comparison::Less,
+        "<=" => Comparison::LessOrEqual,
+        ">" => Comparison::Greater,
+        ">=" => Comparison::GreaterOrEqual,
+        "==" => Comparison::Equal,
+        other => return Err(format!("unknown comparison '{}'", other)),
+    };
+    let threshold = threshold.trim().parse().map_err(|_| "invalid number")?;
+
+    let action = action.trim();
+    let action = if let Some(quantity) = action.strip_prefix("reorder ") {
+        Action::Reorder(
+            quantity
+                .trim()
+                .parse()
+                .map_err(|_| "invalid reorder quantity")?,
+        )
+    } else if let Some(text) = action.strip_prefix("log ") {
+        Action::Log(text.trim().trim_matches('"').to_string())
+    } else {
+        return Err(format!("unknown action '{}'", action));
+    };
+
+    Ok(Rule {
+        line,
+        item,
+        comparison,
+        threshold,
+        action,
+    })
+}
+
+fn parse_checkout(rest: &str) -> Result<TimedCheckout, String> {
+    let (offset, rest) = rest.trim().split_once(' ').ok_or("expected an offset")?;
+    let offset = parse_offset(offset)?;
+    let rest = rest
+        .trim()
+        .strip_prefix("checkout ")
+        .ok_or("expected 'checkout <artist_id> <tools>'")?;
+    let (id, tools) = rest.trim().split_once(' ').ok_or("expected tools")?;
+    Ok(TimedCheckout {
+        offset,
+        artist_id: id
+            .parse()
+            .map_err(|_| format!("invalid artist id '{}'", id))?,
+        tools: tools
+            .split(',')
+            .map(str::trim)
+            .filter(|tool| !tool.is_empty())
+            .map(str::to_string)
+            .collect(),
+    })
+}
+
+fn parse_offset(text: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid offset '{}', expected e.g. 500ms or 2s", text);
+    if let Some(ms) = text.strip_suffix("ms") {
+        return ms.parse().map(Duration::from_millis).map_err(|_| invalid());
+    }
+    let seconds: f64 = text
+        .strip_suffix('s')
+        .ok_or_else(invalid)?
+        .parse()
+        .map_err(|_| invalid())?;
+    Duration::try_from_secs_f64(seconds).map_err(|_| invalid())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        resources::TOTAL_WEIGHT_KG,
+        units::{Count, Kilograms},
+    };
+    use std::sync::Arc;
+
+    #[test]
+    fn test_parse_rules_and_checkouts() {
+        let script = Script::parse(
+            "# restock policy\n\
+             at 1.5s checkout 2 brush\n\
+             at 0ms checkout 1 brush, sculpting tool\n\
+             when stock('sculpting tool') < 3 then reorder 10\n\
+             when stock(\"red\") <= 2 then log \"order more #red\"\n",
+        )
+        .unwrap();
+        assert_eq!(script.checkouts[0].artist_id, 1);
+        assert_eq!(script.checkouts[1].offset, Duration::from_millis(1500));
+        assert_eq!(script.rules[0].item, "sculpting tool");
+        assert_eq!(script.rules[0].action, Action::Reorder(Count(10).into()));
+        assert_eq!(
+            script.rules[1].action,
+            Action::Log("order more #red".to_string())
+        );
+
+        let error =
+            Script::parse("at 0ms checkout 1 brush\nwhen stock('brush') ~ 3 then reorder 1")
+                .unwrap_err();
+        assert_eq!(error.to_string(), "line 2: unknown comparison '~'");
+    }
+
+    #[test]
+    fn test_run_fires_reorder_when_stock_drops() {
+        let script = Script::parse(
+            "at 0ms checkout 1 canvas\n\
+             at 1ms checkout 2 canvas\n\
+             when stock('canvas') < 9 then reorder 5\n\
+             when stock('red') <= 10 then reorder 0.5\n\
+             when stock('brush') > 1 then reorder 0.5\n",
+        )
+        .unwrap();
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let registry = Mutex::new(ArtistToolRegistry::new(&resources));
+
+        let fired = script.run(&registry, 100.0);
+        assert_eq!(
+            fired,
+            vec![
+                FiredRule {
+                    line: 4,
+                    item: "red".to_string(),
+                    stock: TOTAL_WEIGHT_KG.into(),
+                    action: Action::Reorder(Kilograms::grams(500).into())
+                },
+                FiredRule {
+                    line: 3,
+                    item: "canvas".to_string(),
+                    stock: Count(8).into(),
+                    action: Action::Reorder(Count(5).into())
+                }
+            ]
+        );
+        let resources = resources.lock().unwrap();
+        assert_eq!(resources.stock("canvas"), Count(13));
+        assert_eq!(
+            resources.paints.quantity("red"),
+            TOTAL_WEIGHT_KG + Kilograms::grams(500)
+        );
+    }
+}
@@ -0,0 +1,216 @@
+use crate::{i18n::Message, ArtistToolRegistry};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SearchKind {
+    Tool,
+    Paint,
+    Artist,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchHit {
+    pub kind: SearchKind,
+    pub name: String,
+    pub score: u32,
+}
+
+const EXACT_SCORE: u32 = 100;
+const PREFIX_SCORE: u32 = 90;
+const SUBSTRING_SCORE: u32 = 75;
+const FUZZY_SCORE: u32 = 50;
+const TYPO_SCORE: u32 = 40;
+// A tool also matches on its category and tags, just below a match of the
+// same kind on its name.
+const LABEL_PENALTY: u32 = 5;
+
+impl ArtistToolRegistry {
+    pub fn search(&self, query: &str) -> Vec<SearchHit> {
+        let query = query.trim().to_lowercase();
+        if query.is_empty() {
+            return vec![];
+        }
+
+        let mut hits = vec![];
+        if let Ok(resources) = self.shared_resources.lock() {
+            for tool in resources.tools.iter() {
+                let mut labels = resources.catalog.tags(&tool.name);
+                labels.push(resources.catalog.category(&tool.name));
+                push_hit(&mut hits, SearchKind::Tool, &tool.name, &labels, &query);
+            }
+            for paint in resources.paints.iter() {
+                push_hit(&mut hits, SearchKind::Paint, &paint.color, &[], &query);
+            }
+        } else {
+            tracing::warn!(query = %query, "{}", Message::LockFailed);
+        }
+
+        let mut artist_ids: Vec<usize> = self
+            .artist_tool_preferences
+            .iter()
+            .map(|preferences| preferences.artist_id)
+            .collect();
+        artist_ids.sort_unstable();
+        artist_ids.dedup();
+        for id in artist_ids {
+            push_hit(
+                &mut hits,
+                SearchKind::Artist,
+                &format!("artist {}", id),
+                &[],
+                &query,
+            );
+        }
+
+        hits.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.name.cmp(&b.name)));
+        hits
+    }
+}
+
+fn push_hit(hits: &mut Vec<SearchHit>, kind: SearchKind, name: &str, labels: &[&str], query: &str) {
+    let labelled = labels
+        .iter()
+        .filter_map(|label| match_score(query, label))
+        .map(|score| score.saturating_sub(LABEL_PENALTY));
+    if let Some(score) = match_score(query, name).into_iter().chain(labelled).max() {
+        hits.push(SearchHit {
+            kind,
+            name: name.to_string(),
+            score,
+        });
+    }
+}
+
+// `query` is expected to be lowercased already.
+fn match_score(query: &str, candidate: &str) -> Option<u32> {
+    let candidate = candidate.to_lowercase();
+    if candidate == query {
+        return Some(EXACT_SCORE);
+    }
+    if candidate.starts_with(query) {
+        return Some(PREFIX_SCORE);
+    }
+    if candidate.contains(query) {
+        return Some(SUBSTRING_SCORE);
+    }
+    if let Some(gaps) = subsequence_gaps(query, &candidate) {
+        return Some(FUZZY_SCORE.saturating_sub(gaps as u32).max(TYPO_SCORE + 1));
+    }
+
+    // Tolerate small typos against any word of the candidate ("bursh" -> "brush").
+    let allowed = (query.chars().count() / 4).max(1);
+    candidate
+        .split_whitespace()
+        .map(|word| edit_distance(query, word))
+        .min()
+        .filter(|&distance| distance <= allowed)
+        .map(|distance| TYPO_SCORE - distance as u32)
+}
+
+fn subsequence_gaps(query: &str, candidate: &str) -> Option<usize> {
+    let mut gaps = 0;
+    let mut chars = candidate.chars();
+    for wanted in query.chars() {
+        loop {
+            match chars.next() {
+                Some(c) if c == wanted => break,
+                Some(_) => gaps += 1,
+                None => return None,
+            }
+        }
+    }
+    Some(gaps)
+}
+
+// Optimal string alignment distance, so a swapped pair of letters counts as one edit.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SharedResources;
+    use std::sync::{Arc, Mutex};
+
+    fn registry() -> ArtistToolRegistry {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        ArtistToolRegistry::new(&resources)
+    }
+
+    #[test]
+    fn test_search_substring_is_case_insensitive() {
+        let hits = registry().search("SPON");
+        assert_eq!(hits[0].name, "sponges");
+        assert_eq!(hits[0].kind, SearchKind::Tool);
+    }
+
+    #[test]
+    fn test_search_fuzzy_and_typo_matches() {
+        let registry = registry();
+        assert!(registry
+            .search("wtr cnt")
+            .iter()
+            .any(|hit| hit.name == "water container"));
+        assert!(registry
+            .search("bursh")
+            .iter()
+            .any(|hit| hit.name == "brush"));
+    }
+
+    #[test]
+    fn test_search_ranks_exact_paint_first_and_includes_artists() {
+        let mut registry = registry();
+        registry
+            .tool_registry(3, vec!["brush".to_string()])
+            .unwrap();
+        assert_eq!(registry.search("red")[0].kind, SearchKind::Paint);
+
+        let hits = registry.search("artist 3");
+        assert_eq!(hits[0].kind, SearchKind::Artist);
+        assert_eq!(hits[0].score, EXACT_SCORE);
+    }
+
+    #[test]
+    fn test_search_matches_categories_and_tags() {
+        let registry = registry();
+        let names = |query| -> Vec<String> {
+            registry
+                .search(query)
+                .into_iter()
+                .filter(|hit| hit.score == PREFIX_SCORE - LABEL_PENALTY)
+                .map(|hit| hit.name)
+                .collect()
+        };
+        assert_eq!(names("beginner"), vec!["brush", "roller", "sponges"]);
+        assert!(names("clean").contains(&"water container".to_string()));
+        // A match on the name still comes first.
+        registry
+            .shared_resources
+            .lock()
+            .unwrap()
+            .catalog
+            .add_tag("easel", "brush-friendly");
+        assert_eq!(registry.search("brush")[0].name, "brush");
+    }
+}
@@ -0,0 +1,256 @@
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Read, Write},
+    marker::PhantomData,
+    path::{Path, PathBuf},
+};
+
+const INDEX_FILE: &str = "index.json";
+const COMPRESSION_LEVEL: i32 = 3;
+
+pub trait Timestamped {
+    fn at(&self) -> DateTime<Utc>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SegmentInfo {
+    pub file: String,
+    pub first: DateTime<Utc>,
+    pub last: DateTime<Utc>,
+    pub events: usize,
+    pub sealed: bool,
+}
+
+// Append-only event storage split into segments of roughly `max_segment_bytes`.
+// Full segments are compressed with zstd; the index records each segment's
+// time span so range queries only open the segments that can match.
+pub struct SegmentedLog<T> {
+    dir: PathBuf,
+    max_segment_bytes: u64,
+    index: Vec<SegmentInfo>,
+    active: Option<(File, u64)>,
+    events: PhantomData<T>,
+}
+
+impl<T: Serialize + DeserializeOwned + Timestamped> SegmentedLog<T> {
+    pub fn open(dir: &Path, max_segment_bytes: u64) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let index = match fs::read_to_string(dir.join(INDEX_FILE)) {
+            Ok(text) => serde_json::from_str(&text)?,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => vec![],
+            Err(error) => return Err(error),
+        };
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            max_segment_bytes: max_segment_bytes.max(1),
+            index,
+            active: None,
+            events: PhantomData,
+        })
+    }
+
+    // Like `open`, but drops whatever log `dir` already held.
+    pub fn create(dir: &Path, max_segment_bytes: u64) -> io::Result<Self> {
+        let mut log = Self::open(dir, max_segment_bytes)?;
+        for segment in log.index.drain(..) {
+            match fs::remove_file(dir.join(&segment.file)) {
+                Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(error),
+                _ => {}
+            }
+        }
+        log.save_index()?;
+        Ok(log)
+    }
+
+    pub fn segments(&self) -> &[SegmentInfo] {
+        &self.index
+    }
+
+    pub fn append(&mut self, event: &T) -> io::Result<()> {
+        let line = serde_json::to_string(event)? + "\n";
+        let at = event.at();
+
+        if self.index.last().is_none_or(|segment| segment.sealed) {
+            self.index.push(SegmentInfo {
+                file: format!("segment-{:06}.jsonl", self.index.len() + 1),
+                first: at,
+                last: at,
+                events: 0,
+                sealed: false,
+            });
+        }
+        let segment = self.index.last_mut().expect("active segment");
+        segment.first = segment.first.min(at);
+        segment.last = segment.last.max(at);
+        segment.events += 1;
+        let path = self.dir.join(&segment.file);
+
+        if self.active.is_none() {
+            let file = OpenOptions::new().create(true).append(true).open(&path)?;
+            let size = file.metadata()?.len();
+            self.active = Some((file, size));
+        }
+        let (file, size) = self.active.as_mut().expect("active segment file");
+        file.write_all(line.as_bytes())?;
+        *size += line.len() as u64;
+
+        if *size >= self.max_segment_bytes {
+            self.seal()?;
+        }
+        self.save_index()
+    }
+
+    // Events with `from <= at < to`, in storage order.
+    pub fn range(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> io::Result<Vec<T>> {
+        let mut events = vec![];
+        for segment in &self.index {
+            if segment.last < from || segment.first >= to {
+                continue;
+            }
+            self.read(segment, |event| {
+                if event.at() >= from && event.at() < to {
+                    events.push(event);
+                }
+            })?;
+        }
+        Ok(events)
+    }
+
+    // Every event, in storage order.
+    pub fn all(&self) -> io::Result<Vec<T>> {
+        let mut events = vec![];
+        for segment in &self.index {
+            self.read(segment, |event| events.push(event))?;
+        }
+        Ok(events)
+    }
+
+    fn read(&self, segment: &SegmentInfo, mut each: impl FnMut(T)) -> io::Result<()> {
+        let path = self.dir.join(&segment.file);
+        let reader: Box<dyn Read> = if segment.sealed {
+            Box::new(zstd::Decoder::new(File::open(path)?)?)
+        } else {
+            Box::new(File::open(path)?)
+        };
+        for line in BufReader::new(reader).lines() {
+            each(serde_json::from_str(&line?)?);
+        }
+        Ok(())
+    }
+
+    pub fn disk_bytes(&self) -> io::Result<u64> {
+        self.index
+            .iter()
+            .map(|segment| Ok(fs::metadata(self.dir.join(&segment.file))?.len()))
+            .sum()
+    }
+
+    fn seal(&mut self) -> io::Result<()> {
+        self.active = None;
+        let Some(segment) = self.index.last_mut() else {
+            return Ok(());
+        };
+        let plain = self.dir.join(&segment.file);
+        let compressed_name = format!("{}.zst", segment.file);
+        let compressed = zstd::encode_all(File::open(&plain)?, COMPRESSION_LEVEL)?;
+        fs::write(self.dir.join(&compressed_name), compressed)?;
+        segment.file = compressed_name;
+        segment.sealed = true;
+        self.save_index()?;
+        fs::remove_file(plain)
+    }
+
+    fn save_index(&self) -> io::Result<()> {
+        let path = self.dir.join(INDEX_FILE);
+        let temporary = path.with_extension("json.tmp");
+        fs::write(&temporary, serde_json::to_string(&self.index)?)?;
+        fs::rename(temporary, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use std::{env, process};
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Event {
+        at: DateTime<Utc>,
+        artist_id: usize,
+        tool: String,
+    }
+
+    impl Timestamped for Event {
+        fn at(&self) -> DateTime<Utc> {
+            self.at
+        }
+    }
+
+    fn log_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("rustic-canvas-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn events(start: DateTime<Utc>, count: usize) -> Vec<Event> {
+        (0..count)
+            .map(|i| Event {
+                at: start + Duration::minutes(i as i64),
+                artist_id: i % 4,
+                tool: "sculpting tool".to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_segments_are_bounded_and_compressed() {
+        let dir = log_dir("segments");
+        let start: DateTime<Utc> = "2024-01-01T00:00:00Z".parse().unwrap();
+        let all = events(start, 500);
+        let mut log = SegmentedLog::open(&dir, 4_096).unwrap();
+        let mut raw_bytes = 0;
+        for event in &all {
+            raw_bytes += serde_json::to_string(event).unwrap().len() + 1;
+            log.append(event).unwrap();
+        }
+
+        let segments = log.segments();
+        assert!(segments.len() > 2);
+        assert!(segments[..segments.len() - 1].iter().all(|s| s.sealed));
+        assert_eq!(segments.iter().map(|s| s.events).sum::<usize>(), 500);
+        assert!(log.disk_bytes().unwrap() < raw_bytes as u64 / 4);
+
+        let from = start + Duration::minutes(100);
+        let found = log.range(from, from + Duration::minutes(50)).unwrap();
+        assert_eq!(found, all[100..150].to_vec());
+        assert_eq!(log.all().unwrap(), all);
+
+        let log = SegmentedLog::<Event>::create(&dir, 4_096).unwrap();
+        assert!(log.segments().is_empty());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_reopen_continues_active_segment() {
+        let dir = log_dir("segments-reopen");
+        let start: DateTime<Utc> = "2024-01-01T00:00:00Z".parse().unwrap();
+        let all = events(start, 4);
+        {
+            let mut log = SegmentedLog::open(&dir, 1 << 20).unwrap();
+            log.append(&all[0]).unwrap();
+            log.append(&all[1]).unwrap();
+        }
+        let mut log = SegmentedLog::open(&dir, 1 << 20).unwrap();
+        log.append(&all[2]).unwrap();
+        log.append(&all[3]).unwrap();
+
+        assert_eq!(log.segments().len(), 1);
+        let found = log.range(start, start + Duration::days(1)).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(found, all);
+    }
+}
@@ -0,0 +1,206 @@
+use crate::{
+    stock::{Stock, Tool},
+    ArtistToolRegistry, State,
+};
+use rand::{seq::SliceRandom, RngCore};
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+// How a simulated artist picks `count` tools from what the studio stocks.
+pub trait SelectionStrategy: Send + Sync {
+    fn select(&self, tools: &Stock<Tool>, count: usize, rng: &mut dyn RngCore) -> Vec<String>;
+}
+
+// Any `count` tools, each as likely as any other.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Random;
+
+impl SelectionStrategy for Random {
+    fn select(&self, tools: &Stock<Tool>, count: usize, rng: &mut dyn RngCore) -> Vec<String> {
+        let tools: Vec<&Tool> = tools.iter().collect();
+        uniformly(&tools, count, rng)
+    }
+}
+
+fn uniformly(tools: &[&Tool], count: usize, rng: &mut dyn RngCore) -> Vec<String> {
+    tools
+        .choose_multiple(rng, count)
+        .map(|tool| tool.name.clone())
+        .collect()
+}
+
+// Favors tools by weight; tools without one weigh 1.
+#[derive(Debug, Clone, Default)]
+pub struct PreferenceWeighted {
+    pub weights: HashMap<String, f64>,
+}
+
+impl PreferenceWeighted {
+    // Weighs each tool by how many times the artist has checked it out, so
+    // artists keep going back to the tools they already like.
+    pub fn from_history(registry: &ArtistToolRegistry, artist_id: usize) -> Self {
+        let mut weights = HashMap::new();
+        for entry in registry.history_for_artist(artist_id) {
+            if entry.state != Some(State::TakeOut) {
+                continue;
+            }
+            for tool in entry
+                .preferred_tools
+                .iter()
+                .filter_map(|&symbol| registry.interner.resolve(symbol))
+            {
+                *weights.entry(tool.to_string()).or_insert(1.0) += 1.0;
+            }
+        }
+        Self { weights }
+    }
+
+    fn weight(&self, tool: &Tool) -> f64 {
+        self.weights.get(&tool.name).copied().unwrap_or(1.0)
+    }
+}
+
+impl SelectionStrategy for PreferenceWeighted {
+    fn select(&self, tools: &Stock<Tool>, count: usize, rng: &mut dyn RngCore) -> Vec<String> {
+        let tools: Vec<&Tool> = tools.iter().collect();
+        match tools.choose_multiple_weighted(rng, count, |tool| self.weight(tool)) {
+            Ok(chosen) => chosen.map(|tool| tool.name.clone()).collect(),
+            // Only when a weight is negative or not a number.
+            Err(_) => uniformly(&tools, count, rng),
+        }
+    }
+}
+
+// The tools with the most units on the shelf, ties broken at random.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LeastContended;
+
+impl SelectionStrategy for LeastContended {
+    fn select(&self, tools: &Stock<Tool>, count: usize, rng: &mut dyn RngCore) -> Vec<String> {
+        let mut tools: Vec<&Tool> = tools.iter().collect();
+        tools.shuffle(rng);
+        tools.sort_by_key(|tool| std::cmp::Reverse(tool.quantity));
+        tools
+            .into_iter()
+            .take(count)
+            .map(|tool| tool.name.clone())
+            .collect()
+    }
+}
+
+// Works through the tools in listing order, carrying on after the last one
+// picked the previous time.
+#[derive(Debug, Default)]
+pub struct RoundRobin {
+    next: AtomicUsize,
+}
+
+impl RoundRobin {
+    pub fn starting_at(offset: usize) -> Self {
+        Self {
+            next: AtomicUsize::new(offset),
+        }
+    }
+}
+
+impl SelectionStrategy for RoundRobin {
+    fn select(&self, tools: &Stock<Tool>, count: usize, _rng: &mut dyn RngCore) -> Vec<String> {
+        let tools: Vec<&Tool> = tools.iter().collect();
+        if tools.is_empty() {
+            return vec![];
+        }
+        let count = count.min(tools.len());
+        let start = self.next.fetch_add(count, Ordering::Relaxed);
+        (start..start + count)
+            .map(|index| tools[index % tools.len()].name.clone())
+            .collect()
+    }
+}
+
+// Which strategy simulated artists are built with, as named on the command
+// line.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Strategy {
+    #[default]
+    Random,
+    PreferenceWeighted,
+    LeastContended,
+    RoundRobin,
+}
+
+impl Strategy {
+    // Artists start round-robin at their own offset so they don't all reach
+    // for the same tools at once.
+    pub fn build(
+        self,
+        registry: &ArtistToolRegistry,
+        artist_id: usize,
+    ) -> Box<dyn SelectionStrategy> {
+        match self {
+            Strategy::Random => Box::new(Random),
+            Strategy::PreferenceWeighted => {
+                Box::new(PreferenceWeighted::from_history(registry, artist_id))
+            }
+            Strategy::LeastContended => Box::new(LeastContended),
+            Strategy::RoundRobin => Box::new(RoundRobin::starting_at(artist_id)),
+        }
+    }
+}
+
+impl std::str::FromStr for Strategy {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "random" => Ok(Strategy::Random),
+            "weighted" => Ok(Strategy::PreferenceWeighted),
+            "least-contended" => Ok(Strategy::LeastContended),
+            "round-robin" => Ok(Strategy::RoundRobin),
+            _ => Err(format!(
+                "expected random, weighted, least-contended or round-robin, got '{}'",
+                text
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::units::Count;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_strategies_pick_as_described() {
+        let mut tools: Stock<Tool> = [("brush", 5), ("tape", 1), ("easel", 9)]
+            .into_iter()
+            .map(|(name, quantity)| (name.to_string(), Count(quantity)))
+            .collect();
+        tools.set("palette", Count(3));
+        let rng = &mut StdRng::seed_from_u64(1);
+
+        let random = Random.select(&tools, 2, rng);
+        assert_eq!(random.len(), 2);
+        assert!(random.iter().all(|tool| tools.contains(tool)));
+        assert_eq!(
+            LeastContended.select(&tools, 2, rng),
+            vec!["easel", "brush"]
+        );
+
+        let round_robin = RoundRobin::starting_at(1);
+        assert_eq!(round_robin.select(&tools, 2, rng), vec!["tape", "easel"]);
+        assert_eq!(
+            round_robin.select(&tools, 3, rng),
+            vec!["palette", "brush", "tape"]
+        );
+
+        let weighted = PreferenceWeighted {
+            weights: HashMap::from([("brush".to_string(), 1e9)]),
+        };
+        assert!((0..10).all(|_| weighted.select(&tools, 1, rng) == vec!["brush"]));
+        assert_eq!("round-robin".parse(), Ok(Strategy::RoundRobin));
+        assert!("greedy".parse::<Strategy>().is_err());
+    }
+}
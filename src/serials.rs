@@ -0,0 +1,229 @@
+use crate::{registry::HELD_STATES, ArtistToolPreferences, ArtistToolRegistry, State};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt};
+
+// One physical unit of a tool, numbered from 1 per tool as it's taken into
+// stock. Units on the shelf have no artist; anywhere else the artist is
+// whoever last moved it there, if anyone did.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Item {
+    pub tool: String,
+    pub serial: usize,
+    pub state: State,
+    pub artist_id: Option<usize>,
+}
+
+impl Item {
+    pub fn on_shelf(&self) -> bool {
+        matches!(self.state, State::New | State::Return)
+    }
+}
+
+impl fmt::Display for Item {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} #{}", self.tool, self.serial)
+    }
+}
+
+// Entries that don't move a unit: reservations only book stock, audits only
+// count it, and expiring a reservation leaves the stock where it was.
+pub(crate) fn moves_units(to: State, from: Option<State>) -> bool {
+    match to {
+        State::Reserved | State::Audit | State::Fill | State::Paint => false,
+        State::Expired => from != Some(State::Reserved),
+        _ => true,
+    }
+}
+
+// Where every numbered unit is. Units get their serial when they're taken
+// into stock; stock that was already on the shelf before is numbered the
+// first time it moves.
+#[derive(Debug, Clone, Default)]
+pub struct SerialBook {
+    units: BTreeMap<String, Vec<Item>>,
+}
+
+impl SerialBook {
+    pub fn from_items(items: Vec<Item>) -> Self {
+        let mut book = Self::default();
+        for item in items {
+            book.units.entry(item.tool.clone()).or_default().push(item);
+        }
+        for units in book.units.values_mut() {
+            units.sort_by_key(|item| item.serial);
+        }
+        book
+    }
+
+    // Rebuilds the book from history recorded without serials, numbering
+    // each unit moved as `move_units` would have, and fills the serials in.
+    pub(crate) fn replay(
+        entries: &mut [ArtistToolPreferences],
+        resolve: impl Fn(&ArtistToolPreferences) -> Vec<String>,
+    ) -> Self {
+        let mut book = Self::default();
+        for entry in entries.iter_mut() {
+            let (Some(to), from) = (entry.state, entry.source_state()) else {
+                continue;
+            };
+            if moves_units(to, from) {
+                entry.serials = book.move_units(&resolve(entry), Some(entry.artist_id), from, to);
+            }
+        }
+        book
+    }
+
+    pub fn items(&self) -> impl Iterator<Item = &Item> {
+        self.units.values().flatten()
+    }
+
+    // Numbers `count` new units of `tool` arriving on the shelf.
+    pub fn intake(&mut self, tool: &str, count: usize) -> Vec<usize> {
+        (0..count).map(|_| self.add(tool)).collect()
+    }
+
+    // Moves one unit of each listed tool to `to` and returns their serials.
+    // Each move goes to the lowest-numbered unit that fits: off the shelf
+    // unless `from` is a held state, and then the artist's own unit in it.
+    pub fn move_units(
+        &mut self,
+        tools: &[String],
+        artist_id: Option<usize>,
+        from: Option<State>,
+        to: State,
+    ) -> Vec<usize> {
+        let held = from.filter(|from| HELD_STATES.contains(from));
+        tools
+            .iter()
+            .map(|tool| {
+                let found = self.units.get(tool.as_str()).and_then(|units| {
+                    units.iter().position(|item| match held {
+                        Some(from) => item.state == from && item.artist_id == artist_id,
+                        None => item.on_shelf(),
+                    })
+                });
+                let at = match found {
+                    Some(at) => at,
+                    None => self.add(tool) - 1,
+                };
+                let item = &mut self.units.get_mut(tool.as_str()).expect("numbered tool")[at];
+                item.state = to;
+                item.artist_id = artist_id.filter(|_| to != State::Return);
+                item.serial
+            })
+            .collect()
+    }
+
+    fn add(&mut self, tool: &str) -> usize {
+        let units = self.units.entry(tool.to_string()).or_default();
+        let serial = units.len() + 1;
+        units.push(Item {
+            tool: tool.to_string(),
+            serial,
+            state: State::New,
+            artist_id: None,
+        });
+        serial
+    }
+}
+
+impl ArtistToolRegistry {
+    // Every numbered unit, by tool and serial.
+    pub fn items(&self) -> Vec<Item> {
+        self.units.items().cloned().collect()
+    }
+
+    pub fn item(&self, tool: &str, serial: usize) -> Option<Item> {
+        self.items()
+            .into_iter()
+            .find(|item| item.tool == tool && item.serial == serial)
+    }
+
+    // The units an artist has out, damaged, in repair or lost.
+    pub fn items_held(&self, artist_id: usize) -> Vec<Item> {
+        self.items()
+            .into_iter()
+            .filter(|item| {
+                item.artist_id == Some(artist_id)
+                    && !matches!(item.state, State::Retire | State::Sold)
+            })
+            .collect()
+    }
+
+    // E.g. `State::Repair` for what's at the repairer, `State::Lost` for the
+    // serials gone missing.
+    pub fn items_in(&self, state: State) -> Vec<Item> {
+        self.items()
+            .into_iter()
+            .filter(|item| item.state == state)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dump::StateDump, units::Count, SharedResources};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_registry_knows_each_unit() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        let brushes = |count| vec!["brush".to_string(); count];
+        registry.tool_registry(1, brushes(2)).unwrap();
+        registry.tool_registry(2, brushes(1)).unwrap();
+        registry.tool_return(1, brushes(1)).unwrap();
+        registry.tool_registry(3, brushes(2)).unwrap();
+        registry.report_damage(2, "brush").unwrap();
+        registry.send_to_repair(2, "brush").unwrap();
+        registry.report_lost(3, "brush").unwrap();
+
+        let held: Vec<String> = registry
+            .items_held(3)
+            .iter()
+            .map(|item| format!("{} {:?}", item, item.state))
+            .collect();
+        // Brush #1 came back from artist 1 and went straight out to 3.
+        assert_eq!(held, vec!["brush #1 Lost", "brush #4 TakeOut"]);
+        assert_eq!(registry.items_held(1)[0].serial, 2);
+        assert_eq!(
+            registry.items_in(State::Repair),
+            vec![Item {
+                tool: "brush".to_string(),
+                serial: 3,
+                state: State::Repair,
+                artist_id: Some(2),
+            }]
+        );
+        assert_eq!(registry.items_in(State::Lost)[0].to_string(), "brush #1");
+
+        registry.tool_return(3, brushes(1)).unwrap();
+        assert!(registry.item("brush", 4).unwrap().on_shelf());
+        assert!(registry.item("brush", 5).is_none());
+    }
+
+    #[test]
+    fn test_serials_are_given_at_intake_and_kept() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        registry.restock("kiln", Count(2)).unwrap();
+        assert!(registry.item("kiln", 2).unwrap().on_shelf());
+        registry
+            .tool_registry(1, vec!["kiln".to_string(), "brush".to_string()])
+            .unwrap();
+        registry.report_damage(1, "kiln").unwrap();
+
+        let checkout = &registry.artist_tool_preferences[0];
+        assert_eq!(checkout.serials, vec![1, 1]);
+        let events: Vec<_> = registry.events.records().collect();
+        assert_eq!(events[0].serials["kiln"], vec![1, 2]);
+        assert_eq!(events[1].serials["kiln"], vec![1]);
+        assert_eq!(events[2].serials["kiln"], vec![1]);
+
+        let restored = StateDump::capture(&registry).restore(&resources);
+        assert_eq!(restored.items(), registry.items());
+        assert_eq!(restored.items_in(State::Damage)[0].to_string(), "kiln #1");
+        assert_eq!(restored.artist_tool_preferences[1].serials, vec![1]);
+    }
+}
@@ -0,0 +1,481 @@
+use crate::{
+    dump::{DumpEntry, StateDump},
+    error::{RegistryError, ResourceError},
+    events::EventRecord,
+    lock_stats::REGISTRY_LOCK,
+    registry::{Checkout, CheckoutRequest},
+    search::SearchHit,
+    units::Amount,
+    wear::WearLevel,
+    ArtistToolRegistry,
+};
+use axum::{
+    extract::{
+        ws::{self, WebSocket, WebSocketUpgrade},
+        Path, Query, State,
+    },
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    io,
+    sync::{mpsc, Arc, Mutex, MutexGuard},
+};
+
+pub const DEFAULT_ADDR: &str = "127.0.0.1:8080";
+
+type Registry = Arc<Mutex<ArtistToolRegistry>>;
+
+// The same registry the daemon serves, over HTTP and JSON:
+//
+//   GET  /tools                 stock of every tool
+//   GET  /tools/wear            durability of every tool, most worn first
+//   GET  /paints                kilograms left of every color
+//   POST /checkout              {"artist_id": 3, "tools": ["brush"]}
+//   POST /checkout/batch        [{"artist_id": 3, "tools": ["brush"]}, ...]; one
+//                               result per request, in order
+//   POST /return                {"artist_id": 3, "tools": ["brush"]}
+//   GET  /artists/{id}/history  every registry entry for the artist
+//   GET  /search?q=spon         tools, paints and artists matching, best first
+//   GET  /events                WebSocket; one JSON inventory event per message
+//   GET  /metrics               Prometheus metrics, with the `metrics` feature
+//
+// Checkouts sent with `Authorization: Bearer <key>` or `X-Api-Key: <key>`
+// are also charged to that key under the registry's rate limit, one token
+// per checkout, batched or not.
+pub fn router(registry: Registry) -> Router {
+    let router = Router::new()
+        .route("/tools", get(tools))
+        .route("/tools/wear", get(wear))
+        .route("/paints", get(paints))
+        .route("/checkout", post(checkout))
+        .route("/checkout/batch", post(checkout_batch))
+        .route("/return", post(tool_return))
+        .route("/artists/{id}/history", get(history))
+        .route("/search", get(search))
+        .route("/events", get(events));
+    #[cfg(feature = "metrics")]
+    let router = router.route("/metrics", get(metrics));
+    router.with_state(registry)
+}
+
+// Serves until the process is stopped.
+pub fn serve(addr: &str, registry: Registry) -> io::Result<()> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async {
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        axum::serve(listener, router(registry)).await
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Stock {
+    pub name: String,
+    // Units of a tool, or kilograms of a paint.
+    pub quantity: Amount,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolsRequest {
+    pub artist_id: usize,
+    pub tools: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchQuery {
+    pub q: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorBody {
+    pub error: String,
+}
+
+// What became of one request in a batch checkout.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum BatchOutcome {
+    Checkout(Checkout),
+    Refused(ErrorBody),
+}
+
+struct ApiError(RegistryError);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = match &self.0 {
+            RegistryError::Resource(ResourceError::ToolNotFound(_))
+            | RegistryError::Resource(ResourceError::UnknownPaint(_))
+            | RegistryError::UnknownReservation(_)
+            | RegistryError::UnknownRepair(_)
+            | RegistryError::UnknownArtwork(_) => StatusCode::NOT_FOUND,
+            RegistryError::Resource(_)
+            | RegistryError::ReservedForOthers(_)
+            | RegistryError::NotOnShelf { .. }
+            | RegistryError::ReservationExpired(_)
+            | RegistryError::AlreadySold(_) => StatusCode::CONFLICT,
+            RegistryError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            RegistryError::LockPoisoned => StatusCode::INTERNAL_SERVER_ERROR,
+            RegistryError::Timeout { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::UNPROCESSABLE_ENTITY,
+        };
+        let body = ErrorBody {
+            error: self.0.to_string(),
+        };
+        (status, Json(body)).into_response()
+    }
+}
+
+impl From<RegistryError> for ApiError {
+    fn from(error: RegistryError) -> Self {
+        ApiError(error)
+    }
+}
+
+fn lock(registry: &Registry) -> MutexGuard<'_, ArtistToolRegistry> {
+    REGISTRY_LOCK.lock(registry)
+}
+
+fn api_key(headers: &HeaderMap) -> Option<&str> {
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    bearer
+        .or_else(|| headers.get("x-api-key")?.to_str().ok())
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+}
+
+fn charge_key(
+    registry: &mut ArtistToolRegistry,
+    key: Option<&str>,
+    artist_id: usize,
+    tools: &[String],
+) -> Result<(), RegistryError> {
+    match key {
+        Some(key) => registry.check_api_key(key, artist_id, tools),
+        None => Ok(()),
+    }
+}
+
+fn stock<Q: Into<Amount> + Copy>(items: &[(String, Q)]) -> Json<Vec<Stock>> {
+    Json(
+        items
+            .iter()
+            .map(|(name, quantity)| Stock {
+                name: name.clone(),
+                quantity: (*quantity).into(),
+            })
+            .collect(),
+    )
+}
+
+async fn tools(State(registry): State<Registry>) -> Result<Json<Vec<Stock>>, ApiError> {
+    let registry = lock(&registry);
+    let resources = registry
+        .shared_resources
+        .lock()
+        .map_err(RegistryError::from)?;
+    Ok(stock(&resources.tools.amounts()))
+}
+
+async fn wear(State(registry): State<Registry>) -> Json<Vec<WearLevel>> {
+    Json(lock(&registry).wear_levels())
+}
+
+async fn paints(State(registry): State<Registry>) -> Result<Json<Vec<Stock>>, ApiError> {
+    let registry = lock(&registry);
+    let resources = registry
+        .shared_resources
+        .lock()
+        .map_err(RegistryError::from)?;
+    Ok(stock(&resources.paints.amounts()))
+}
+
+async fn checkout(
+    State(registry): State<Registry>,
+    headers: HeaderMap,
+    Json(request): Json<ToolsRequest>,
+) -> Result<Json<Checkout>, ApiError> {
+    let mut registry = lock(&registry);
+    let key = api_key(&headers);
+    charge_key(&mut registry, key, request.artist_id, &request.tools)?;
+    let checkout = registry.tool_registry(request.artist_id, request.tools)?;
+    Ok(Json(checkout))
+}
+
+// Refusals don't fail the batch; each shows up in its own place. Requests
+// the key can't pay for are refused before the rest go through together.
+async fn checkout_batch(
+    State(registry): State<Registry>,
+    headers: HeaderMap,
+    Json(requests): Json<Vec<CheckoutRequest>>,
+) -> Json<Vec<BatchOutcome>> {
+    let mut registry = lock(&registry);
+    let charged: Vec<_> = requests
+        .iter()
+        .map(|request| {
+            let key = api_key(&headers);
+            charge_key(&mut registry, key, request.artist_id, &request.tools)
+        })
+        .collect();
+    let paid = requests
+        .into_iter()
+        .zip(&charged)
+        .filter(|(_, charged)| charged.is_ok())
+        .map(|(request, _)| request)
+        .collect();
+    let mut checkouts = registry.checkout_batch(paid).into_iter();
+    Json(
+        charged
+            .into_iter()
+            .filter_map(|charged| match charged {
+                Ok(()) => checkouts.next(),
+                Err(error) => Some(Err(error)),
+            })
+            .map(|result| match result {
+                Ok(checkout) => BatchOutcome::Checkout(checkout),
+                Err(error) => BatchOutcome::Refused(ErrorBody {
+                    error: error.to_string(),
+                }),
+            })
+            .collect(),
+    )
+}
+
+async fn tool_return(
+    State(registry): State<Registry>,
+    Json(request): Json<ToolsRequest>,
+) -> Result<StatusCode, ApiError> {
+    lock(&registry).tool_return(request.artist_id, request.tools)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn history(State(registry): State<Registry>, Path(id): Path<usize>) -> Json<Vec<DumpEntry>> {
+    let dump = StateDump::capture(&lock(&registry));
+    Json(
+        dump.entries
+            .into_iter()
+            .filter(|entry| entry.artist_id == id)
+            .collect(),
+    )
+}
+
+async fn search(
+    State(registry): State<Registry>,
+    Query(query): Query<SearchQuery>,
+) -> Json<Vec<SearchHit>> {
+    Json(lock(&registry).search(&query.q))
+}
+
+async fn events(State(registry): State<Registry>, upgrade: WebSocketUpgrade) -> Response {
+    let receiver = lock(&registry).events.subscribe();
+    upgrade.on_upgrade(move |socket| stream_events(socket, receiver))
+}
+
+#[cfg(feature = "metrics")]
+async fn metrics(State(registry): State<Registry>) -> impl IntoResponse {
+    // Read before locking, so this scrape's own wait isn't in it.
+    let waits = REGISTRY_LOCK.snapshot();
+    let body = crate::metrics::render(&lock(&registry), waits);
+    (
+        [(
+            axum::http::header::CONTENT_TYPE,
+            crate::metrics::CONTENT_TYPE,
+        )],
+        body,
+    )
+}
+
+async fn stream_events(mut socket: WebSocket, receiver: mpsc::Receiver<EventRecord>) {
+    // The registry publishes on a std channel, so a blocking task forwards
+    // it. After the client leaves, that task ends with the next event.
+    let (sender, mut live) = tokio::sync::mpsc::unbounded_channel();
+    tokio::task::spawn_blocking(move || {
+        for event in receiver {
+            if sender.send(event).is_err() {
+                break;
+            }
+        }
+    });
+    while let Some(event) = live.recv().await {
+        let Ok(json) = serde_json::to_string(&event) else {
+            continue;
+        };
+        if socket.send(ws::Message::Text(json.into())).await.is_err() {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{rate_limit::RateLimiter, units::Count, SharedResources};
+    use std::{
+        io::{Read, Write},
+        net::TcpStream,
+        thread,
+    };
+
+    fn request(addr: &str, method: &str, path: &str, body: &str) -> (u16, String) {
+        request_with(addr, method, path, "", body)
+    }
+
+    // `headers` are extra header lines, each ending in CRLF.
+    fn request_with(
+        addr: &str,
+        method: &str,
+        path: &str,
+        headers: &str,
+        body: &str,
+    ) -> (u16, String) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: {}\r\n{}Content-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            method,
+            path,
+            addr,
+            headers,
+            body.len(),
+            body
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let status = response[9..12].parse().unwrap();
+        let body = response
+            .split_once("\r\n\r\n")
+            .map(|(_, body)| body.to_string())
+            .unwrap_or_default();
+        (status, body)
+    }
+
+    fn start() -> (String, Registry) {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let registry = Arc::new(Mutex::new(ArtistToolRegistry::new(&resources)));
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        drop(listener);
+        {
+            let (addr, registry) = (addr.clone(), Arc::clone(&registry));
+            thread::spawn(move || serve(&addr, registry));
+        }
+        while TcpStream::connect(&addr).is_err() {
+            thread::sleep(std::time::Duration::from_millis(10));
+        }
+        (addr, registry)
+    }
+
+    #[test]
+    fn test_endpoints_share_the_registry() {
+        let (addr, registry) = start();
+
+        let checkout = r#"{"artist_id": 3, "tools": ["brush", "tape"]}"#;
+        let (status, body) = request(&addr, "POST", "/checkout", checkout);
+        assert_eq!(status, 200);
+        let lent: Checkout = serde_json::from_str(&body).unwrap();
+        assert_eq!(lent.lent, vec!["brush", "tape"]);
+
+        let (_, body) = request(&addr, "GET", "/tools", "");
+        let tools: Vec<Stock> = serde_json::from_str(&body).unwrap();
+        assert!(tools.contains(&Stock {
+            name: "brush".to_string(),
+            quantity: Count(9).into()
+        }));
+
+        let unknown = r#"{"artist_id": 3, "tools": ["kiln"]}"#;
+        assert_eq!(request(&addr, "POST", "/checkout", unknown).0, 404);
+        let (status, _) = request(&addr, "POST", "/return", checkout);
+        assert_eq!(status, 204);
+        assert_eq!(request(&addr, "POST", "/return", checkout).0, 422);
+
+        let (_, body) = request(&addr, "GET", "/artists/3/history", "");
+        let history: Vec<DumpEntry> = serde_json::from_str(&body).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(registry.lock().unwrap().artist_tool_preferences.len(), 2);
+        assert!(request(&addr, "GET", "/paints", "").1.contains("red"));
+        let (status, body) = request(&addr, "GET", "/search?q=beginner", "");
+        assert_eq!(status, 200);
+        let hits: Vec<SearchHit> = serde_json::from_str(&body).unwrap();
+        assert!(hits.iter().any(|hit| hit.name == "sponges"));
+
+        let class = r#"[{"artist_id": 4, "tools": ["rags"]}, {"artist_id": 5, "tools": ["kiln"]}]"#;
+        let (status, body) = request(&addr, "POST", "/checkout/batch", class);
+        assert_eq!(status, 200);
+        let outcomes: Vec<BatchOutcome> = serde_json::from_str(&body).unwrap();
+        assert!(
+            matches!(&outcomes[0], BatchOutcome::Checkout(checkout) if checkout.lent == ["rags"])
+        );
+        assert!(matches!(&outcomes[1], BatchOutcome::Refused(body) if body.error.contains("kiln")));
+
+        // A key shared by a kiosk runs out however many artists use it.
+        registry
+            .lock()
+            .unwrap()
+            .set_rate_limiter(RateLimiter::new(2, 0.0));
+        let kiosk = "X-Api-Key: kiosk\r\n";
+        let checkout = |id| format!(r#"{{"artist_id": {}, "tools": ["rags"]}}"#, id);
+        assert_eq!(
+            request_with(&addr, "POST", "/checkout", kiosk, &checkout(6)).0,
+            200
+        );
+        let (status, body) = request_with(&addr, "POST", "/checkout/batch", kiosk, class);
+        assert_eq!(status, 200);
+        let outcomes: Vec<BatchOutcome> = serde_json::from_str(&body).unwrap();
+        assert!(matches!(&outcomes[0], BatchOutcome::Checkout(_)));
+        assert!(matches!(&outcomes[1], BatchOutcome::Refused(body) if body.error.contains("rate")));
+        let bearer = "Authorization: Bearer kiosk\r\n";
+        assert_eq!(
+            request_with(&addr, "POST", "/checkout", bearer, &checkout(7)).0,
+            429
+        );
+        assert_eq!(request(&addr, "POST", "/checkout", &checkout(7)).0, 200);
+    }
+
+    #[test]
+    fn test_events_stream_over_websocket() {
+        let (addr, registry) = start();
+        let mut stream = TcpStream::connect(&addr).unwrap();
+        write!(
+            stream,
+            "GET /events HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n",
+            addr
+        )
+        .unwrap();
+        let mut head = vec![];
+        let mut byte = [0; 1];
+        while !head.ends_with(b"\r\n\r\n") {
+            stream.read_exact(&mut byte).unwrap();
+            head.push(byte[0]);
+        }
+        assert!(String::from_utf8_lossy(&head).starts_with("HTTP/1.1 101"));
+
+        registry
+            .lock()
+            .unwrap()
+            .tool_registry(5, vec!["brush".to_string(), "rags".to_string()])
+            .unwrap();
+        let mut header = [0; 2];
+        stream.read_exact(&mut header).unwrap();
+        assert_eq!(header[0], 0x81);
+        let len = match header[1] & 0x7f {
+            126 => {
+                let mut len = [0; 2];
+                stream.read_exact(&mut len).unwrap();
+                u16::from_be_bytes(len) as usize
+            }
+            len => len as usize,
+        };
+        let mut payload = vec![0; len];
+        stream.read_exact(&mut payload).unwrap();
+        let event: EventRecord = serde_json::from_slice(&payload).unwrap();
+        assert_eq!(event.artist_id, Some(5));
+        assert_eq!(event.items, vec!["brush", "rags"]);
+    }
+}
@@ -0,0 +1,124 @@
+use crate::{
+    dump::StateDump,
+    i18n::Message,
+    lock_stats::{LockSnapshot, REGISTRY_LOCK},
+    ArtistToolRegistry,
+};
+use serde::Serialize;
+use signal_hook::{consts::SIGUSR1, iterator::Signals};
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+// Everything useful for debugging a live instance offline: the registry state
+// plus how deep the checkout queues are and how contended the registry lock is.
+#[derive(Debug, Serialize)]
+pub struct DebugDump {
+    #[serde(flatten)]
+    pub state: StateDump,
+    pub queue_depths: BTreeMap<String, usize>,
+    pub registry_lock: LockSnapshot,
+}
+
+impl DebugDump {
+    pub fn capture(registry: &ArtistToolRegistry) -> Self {
+        let state = StateDump::capture(registry);
+        let mut queue_depths = BTreeMap::new();
+        for queued in &state.queued {
+            *queue_depths.entry(queued.tool.clone()).or_insert(0) += 1;
+        }
+        Self {
+            state,
+            queue_depths,
+            registry_lock: REGISTRY_LOCK.snapshot(),
+        }
+    }
+}
+
+// Writes `rustic-canvas-dump-<timestamp>.json` into `dir` and returns its path.
+pub fn write_dump(dir: &Path, registry: &Mutex<ArtistToolRegistry>) -> io::Result<PathBuf> {
+    let dump = DebugDump::capture(&REGISTRY_LOCK.lock(registry));
+    let name = format!(
+        "rustic-canvas-dump-{}.json",
+        dump.state.captured_at.format("%Y%m%dT%H%M%S%.3fZ")
+    );
+    let path = dir.join(name);
+    fs::write(&path, serde_json::to_string_pretty(&dump)?)?;
+    Ok(path)
+}
+
+// Writes a dump into `dir` and logs where it went, or why it didn't. Both
+// SIGUSR1 and the dashboard's dump key come through here.
+pub fn dump_and_log(dir: &Path, registry: &Mutex<ArtistToolRegistry>) -> io::Result<PathBuf> {
+    let written = write_dump(dir, registry);
+    match &written {
+        Ok(path) => tracing::info!(
+            path = %path.display(),
+            "{}",
+            Message::StateDumped(&path.display().to_string())
+        ),
+        Err(error) => tracing::warn!(
+            dir = %dir.display(),
+            error = %error,
+            "{}",
+            Message::FileError(&dir.display().to_string(), error.to_string())
+        ),
+    }
+    written
+}
+
+// Dumps state into `dir` every time the process receives SIGUSR1.
+pub fn install(dir: PathBuf, registry: Arc<Mutex<ArtistToolRegistry>>) -> io::Result<()> {
+    let mut signals = Signals::new([SIGUSR1])?;
+    thread::spawn(move || {
+        for _ in signals.forever() {
+            let _ = dump_and_log(&dir, &registry);
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SharedResources;
+    use std::{env, process};
+
+    #[test]
+    fn test_write_dump_includes_queues_and_lock_stats() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        {
+            let mut resources = resources.lock().unwrap();
+            resources.loan_caps.policy = crate::loan_caps::CapPolicy::Queue;
+            resources.loan_caps.set_cap("brush", 1);
+        }
+        let registry = Mutex::new(ArtistToolRegistry::new(&resources));
+        registry
+            .lock()
+            .unwrap()
+            .tool_registry(1, vec!["brush".to_string()])
+            .unwrap();
+        registry
+            .lock()
+            .unwrap()
+            .tool_registry(2, vec!["brush".to_string()])
+            .unwrap();
+
+        let dir = env::temp_dir().join(format!("rustic-canvas-dump-test-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = write_dump(&dir, &registry).unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(json["queue_depths"]["brush"], 1);
+        assert_eq!(json["on_loan"][0], serde_json::json!(["brush", 1]));
+        assert_eq!(json["queued"][0]["artist_id"], 2);
+        assert!(json["registry_lock"]["acquisitions"].as_u64().unwrap() >= 1);
+        assert_eq!(json["entries"].as_array().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,764 @@
+use crate::{
+    artwork::Artwork,
+    chaos::{self, Chaos},
+    clock::SimulatedClock,
+    error::RegistryError,
+    fatigue::{ArtistFatigue, FatigueModel},
+    i18n::Message,
+    interrupt, logging,
+    profiles::{ArtistProfile, Favoring},
+    queueing::{QueueStats, RequestTiming},
+    selection::{SelectionStrategy, Strategy},
+    stock::{Paint, Stock, Tool},
+    studios::Studios,
+    tool_limits::ToolCountRange,
+    units::{Count, Kilograms},
+    worker_pool::WorkerPool,
+    ArtistToolRegistry, SharedResources,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+pub const TOTAL_ARTISTS: usize = 1;
+// How long a debug build's artist works between checkout and return, unless
+// their profile says they work faster or slower.
+pub const TASK_DELAY: Duration = Duration::from_millis(10);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SimulationConfig {
+    pub artists: usize,
+    // Overrides the registry's per-artist tool count range when set.
+    pub tools_per_artist: Option<ToolCountRange>,
+    pub rounds: usize,
+    // With a seed every artist's choices are reproducible, and artists run one
+    // after another so the checkout sequence is identical between runs.
+    pub seed: Option<u64>,
+    // Keeps artists from printing, e.g. while a dashboard owns the terminal.
+    pub quiet: bool,
+    // Threads the artists share; one per core when unset. Seeded runs use a
+    // single thread regardless.
+    pub pool_size: Option<usize>,
+    // How each artist picks the tools it asks for.
+    pub strategy: Strategy,
+    // Runs the registry on simulated time, moving this far each time an
+    // artist finishes a round, instead of on the system clock.
+    pub time_step: Option<chrono::Duration>,
+    // Random failures injected into the artists' rounds. Only threaded runs
+    // share a lock that chaos can poison.
+    pub chaos: Option<Chaos>,
+    // Tasks in a row slow artists down and make them likelier to damage a
+    // tool, until they take a break.
+    pub fatigue: Option<FatigueModel>,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            artists: TOTAL_ARTISTS,
+            tools_per_artist: None,
+            rounds: 1,
+            seed: None,
+            quiet: false,
+            pool_size: None,
+            strategy: Strategy::Random,
+            time_step: None,
+            chaos: None,
+            fatigue: None,
+        }
+    }
+}
+
+// One simulated artist, built with the strategy it picks tools by, the
+// profile the registry has for it, if any, and a random source of its own.
+pub struct Artist {
+    pub id: usize,
+    pub strategy: Box<dyn SelectionStrategy>,
+    pub profile: Option<ArtistProfile>,
+    pub rng: StdRng,
+    // Tools chaos kept back from an earlier round's return, and lost tools
+    // the supplier hasn't replaced yet.
+    pub kept: Vec<String>,
+    pub reorders: Vec<String>,
+    pub fatigue: ArtistFatigue,
+}
+
+// What one task costs an artist: how long they work at it, and whether a
+// tool they hold comes back damaged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Task {
+    pub work_time: Duration,
+    pub damages_tool: bool,
+    // The task was the last before a break.
+    pub rested: bool,
+}
+
+impl Artist {
+    // Seeded runs give each artist its own reproducible stream. A profile's
+    // favorite tools and categories are picked ahead of the configured
+    // strategy.
+    pub fn new(id: usize, registry: &ArtistToolRegistry, config: &SimulationConfig) -> Self {
+        let profile = registry.profile(id).cloned();
+        let mut strategy = config.strategy.build(registry, id);
+        if let Some(profile) = profile
+            .as_ref()
+            .filter(|p| !p.favorite_tools.is_empty() || !p.favorite_categories.is_empty())
+        {
+            strategy = Box::new(Favoring {
+                favorites: profile.favorite_tools.clone(),
+                categories: profile.favorite_categories.clone(),
+                inner: strategy,
+            });
+        }
+        Self {
+            id,
+            strategy,
+            profile,
+            rng: match config.seed {
+                Some(seed) => StdRng::seed_from_u64(seed.wrapping_add(id as u64)),
+                None => StdRng::from_entropy(),
+            },
+            kept: vec![],
+            reorders: vec![],
+            fatigue: ArtistFatigue::new(),
+        }
+    }
+
+    pub fn pick_tools(&mut self, tools: &Stock<Tool>, range: ToolCountRange) -> Vec<String> {
+        tools_usage(self.id, tools, range, self.strategy.as_ref(), &mut self.rng).1
+    }
+
+    // Only artists with favorite colors paint; the rest just use tools.
+    pub fn pick_paint(&mut self, paints: &Stock<Paint>) -> Option<(String, Kilograms)> {
+        self.profile.as_ref()?.pick_paint(paints, &mut self.rng)
+    }
+
+    // `TASK_DELAY` at the pace the artist's profile sets.
+    pub fn work_time(&self) -> Duration {
+        match &self.profile {
+            Some(profile) => profile.work_time(TASK_DELAY),
+            None => TASK_DELAY,
+        }
+    }
+
+    // The artist's next task at their own pace, slowed and made riskier by
+    // however many they've done in a row when the run models fatigue.
+    pub fn next_task(&mut self, fatigue: Option<&FatigueModel>) -> Task {
+        let Some(model) = fatigue else {
+            return Task {
+                work_time: self.work_time(),
+                damages_tool: false,
+                rested: false,
+            };
+        };
+        let effort = self.fatigue.record_task(model);
+        let slowdown = match model.base_duration.as_secs_f64() {
+            base if base > 0.0 => effort.duration.as_secs_f64() / base,
+            _ => 1.0,
+        };
+        Task {
+            work_time: self.work_time().mul_f64(slowdown),
+            damages_tool: effort.rolls_damage(&mut self.rng),
+            rested: effort.needs_break,
+        }
+    }
+
+    // Takes the tool a task damaged out of `held`, picked at random.
+    pub fn damaged_tool(&mut self, task: &Task, held: &mut Vec<String>) -> Option<String> {
+        if !task.damages_tool || held.is_empty() {
+            return None;
+        }
+        Some(held.swap_remove(self.rng.gen_range(0..held.len())))
+    }
+
+    // Decides the round's work on what was lent before anything is booked,
+    // so each runner only differs in how it reaches the registry.
+    pub fn plan_work(
+        &mut self,
+        lent: Vec<String>,
+        paints: &Stock<Paint>,
+        config: &SimulationConfig,
+    ) -> Work {
+        let task = self.next_task(config.fatigue.as_ref());
+        let paint = if lent.is_empty() {
+            None
+        } else {
+            self.pick_paint(paints)
+        };
+        let mut returns = lent.clone();
+        let damaged = self.damaged_tool(&task, &mut returns);
+        Work {
+            task,
+            tools: lent,
+            paint,
+            damaged,
+            returns,
+        }
+    }
+
+    // Which of this round's returns, and whatever was kept back before, make
+    // it to the shelf: none if chaos has the artist keep them all another
+    // round, less any it has them lose on the way.
+    pub fn hand_back(&mut self, returns: Vec<String>, config: &SimulationConfig) -> HandBack {
+        let chaos = config.chaos.unwrap_or_default();
+        let mut tools = std::mem::take(&mut self.kept);
+        tools.extend(returns);
+        let mut hand_back = HandBack::default();
+        if tools.is_empty() {
+            return hand_back;
+        }
+        if Chaos::strikes(chaos.delay, &mut self.rng) {
+            if !config.quiet {
+                tracing::warn!(
+                    artist_id = self.id,
+                    "{}",
+                    Message::ChaosReturnDelayed(self.id, &tools)
+                );
+            }
+            self.kept = tools;
+            return hand_back;
+        }
+        for tool in tools {
+            if Chaos::strikes(chaos.lose, &mut self.rng) {
+                self.reorders.push(tool.clone());
+                hand_back.lost.push(tool);
+            } else {
+                hand_back.returned.push(tool);
+            }
+        }
+        hand_back
+    }
+
+    // Lost tools the supplier replaces at the end of the round, unless chaos
+    // has it running late.
+    pub fn reorders_due(&mut self, config: &SimulationConfig) -> Vec<String> {
+        if self.reorders.is_empty() {
+            return vec![];
+        }
+        let chaos = config.chaos.unwrap_or_default();
+        if Chaos::strikes(chaos.supplier, &mut self.rng) {
+            if !config.quiet {
+                tracing::warn!("{}", Message::ChaosSupplierDelayed(&self.reorders));
+            }
+            return vec![];
+        }
+        std::mem::take(&mut self.reorders)
+    }
+}
+
+// A round's work, as `Artist::plan_work` decided it: the task, every tool
+// that goes into the sketch, the paint on it, the tool the task damaged and
+// what goes back on the shelf.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Work {
+    pub task: Task,
+    pub tools: Vec<String>,
+    pub paint: Option<(String, Kilograms)>,
+    pub damaged: Option<String>,
+    pub returns: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HandBack {
+    pub returned: Vec<String>,
+    pub lost: Vec<String>,
+}
+
+// Starts and paints the sketch `work` goes into, if anything was lent. The
+// caller waits out the work time, without the registry, before
+// `finish_work`.
+pub fn start_work(
+    registry: &mut ArtistToolRegistry,
+    id: usize,
+    work: &Work,
+    config: &SimulationConfig,
+) -> Result<Option<Artwork>, RegistryError> {
+    if work.tools.is_empty() {
+        return Ok(None);
+    }
+    let mut sketch = registry.start_artwork(id, work.tools.clone());
+    sketch.advance(registry.now());
+    if let Some(paint) = work.paint.clone() {
+        use_paint(registry, &mut sketch, paint, config)?;
+    }
+    sketch.work_with_tools(work.task.work_time);
+    Ok(Some(sketch))
+}
+
+// Hangs the finished sketch and sends the tool the task damaged to repair
+// rather than back on the shelf.
+pub fn finish_work(
+    registry: &mut ArtistToolRegistry,
+    id: usize,
+    work: &Work,
+    artwork: Option<Artwork>,
+    config: &SimulationConfig,
+) -> Result<(), RegistryError> {
+    if let Some(artwork) = artwork {
+        registry.finish_artwork(artwork);
+    }
+    if let Some(tool) = &work.damaged {
+        registry.return_damaged(id, tool)?;
+    }
+    log_task(id, &work.task, work.damaged.as_deref(), config);
+    Ok(())
+}
+
+// Books what `Artist::hand_back` decided, then restocks what the supplier
+// replaces.
+pub fn book_returns(
+    registry: &mut ArtistToolRegistry,
+    id: usize,
+    hand_back: &HandBack,
+    reorders: &[String],
+    config: &SimulationConfig,
+) -> Result<(), RegistryError> {
+    for tool in &hand_back.lost {
+        registry.report_lost(id, tool)?;
+        if !config.quiet {
+            tracing::warn!(artist_id = id, tool = %tool, "{}", Message::ChaosToolLost(id, tool));
+        }
+    }
+    if !hand_back.returned.is_empty() {
+        registry.tool_return(id, hand_back.returned.clone())?;
+        if !config.quiet {
+            logging::returned(id, &hand_back.returned, &*registry.shared_resources.lock()?);
+        }
+    }
+    for tool in reorders {
+        registry.restock(tool, Count(1))?;
+    }
+    Ok(())
+}
+
+// Logs what fatigue did to a task, unless the run is quiet.
+pub fn log_task(id: usize, task: &Task, damaged: Option<&str>, config: &SimulationConfig) {
+    if config.quiet {
+        return;
+    }
+    if let Some(tool) = damaged {
+        tracing::warn!(artist_id = id, tool = %tool, "{}", Message::TiredArtistDamaged(id, tool));
+    }
+    if task.rested {
+        tracing::info!(artist_id = id, "{}", Message::ArtistTookBreak(id));
+    }
+}
+
+// Runs every simulated artist as a job on a pool of worker threads and
+// returns their queue timings, along with the checkouts that were refused.
+pub fn run_artists(
+    resources: &Arc<Mutex<SharedResources>>,
+    registry: &Arc<Mutex<ArtistToolRegistry>>,
+    config: &SimulationConfig,
+) -> (Arc<Mutex<QueueStats>>, Vec<RegistryError>) {
+    let ids: Vec<usize> = (0..config.artists).collect();
+    run_assigned(resources, registry, &ids, config)
+}
+
+// Runs every studio's artists at once, each studio on its own threads, and
+// returns the checkouts each one refused, in studio order. Artists not
+// assigned a studio yet are spread over them first.
+pub fn run_studios(studios: &mut Studios, config: &SimulationConfig) -> Vec<Vec<RegistryError>> {
+    studios.assign_remaining(config.artists);
+    let studios = &*studios;
+    thread::scope(|scope| {
+        let runs: Vec<_> = studios
+            .iter()
+            .map(|studio| {
+                let ids = studios.artists(&studio.name);
+                scope.spawn(move || {
+                    run_assigned(&studio.resources, &studio.registry, &ids, config).1
+                })
+            })
+            .collect();
+        runs.into_iter()
+            .map(|run| run.join().expect("Thread panicked"))
+            .collect()
+    })
+}
+
+// Like `run_artists`, for the artists with the given ids.
+pub fn run_assigned(
+    resources: &Arc<Mutex<SharedResources>>,
+    registry: &Arc<Mutex<ArtistToolRegistry>>,
+    ids: &[usize],
+    config: &SimulationConfig,
+) -> (Arc<Mutex<QueueStats>>, Vec<RegistryError>) {
+    let queue_stats = Arc::new(Mutex::new(QueueStats::new()));
+    let errors = Arc::new(Mutex::new(vec![]));
+    let clock = simulated_clock(
+        &mut registry.lock().expect("Failed to lock registry"),
+        config,
+    );
+    // One worker takes the artists in order, so the checkout sequence is
+    // the same between seeded runs.
+    let pool = WorkerPool::new(match config.seed {
+        Some(_) => 1,
+        None => config.pool_size.unwrap_or_else(WorkerPool::default_size),
+    });
+
+    for &id in ids {
+        let resources_arc_clone = Arc::clone(resources);
+        let artist_tool_registry_arc_clone = Arc::clone(registry);
+        let queue_stats_arc_clone = Arc::clone(&queue_stats);
+        let errors_arc_clone = Arc::clone(&errors);
+        let clock = clock.clone();
+        let config = *config;
+        pool.submit(move || {
+            let mut artist = Artist::new(
+                id,
+                &artist_tool_registry_arc_clone
+                    .lock()
+                    .expect("Failed to lock registry"),
+                &config,
+            );
+            let mut errors = vec![];
+            for round in 0..config.rounds {
+                if interrupt::global().requested() {
+                    break;
+                }
+                // Tools are only handed back between rounds, so a single
+                // round leaves them checked out as before. An interrupted
+                // artist stops between rounds too, never mid-checkout.
+                let return_tools = round + 1 < config.rounds;
+                if let Err(error) = artis_task(
+                    Arc::clone(&artist_tool_registry_arc_clone),
+                    &mut artist,
+                    Arc::clone(&resources_arc_clone),
+                    Arc::clone(&queue_stats_arc_clone),
+                    &config,
+                    return_tools,
+                ) {
+                    // Chaos poisons the lock without changing anything under
+                    // it, so the artist can clear it and the run goes on.
+                    if config.chaos.is_some()
+                        && matches!(error, RegistryError::LockPoisoned)
+                        && chaos::recover(&artist_tool_registry_arc_clone)
+                        && !config.quiet
+                    {
+                        tracing::warn!(artist_id = id, "{}", Message::ChaosRecovered(id));
+                    }
+                    errors.push(error);
+                }
+                if let Some(clock) = &clock {
+                    clock.tick();
+                }
+            }
+            errors_arc_clone
+                .lock()
+                .expect("Failed to lock errors")
+                .extend(errors);
+        });
+    }
+    pool.wait();
+
+    let errors = std::mem::take(&mut *errors.lock().expect("Failed to lock errors"));
+    (queue_stats, errors)
+}
+
+// Puts the registry on simulated time when the config asks for it, starting
+// from the registry's current time. The caller ticks the returned clock.
+pub fn simulated_clock(
+    registry: &mut ArtistToolRegistry,
+    config: &SimulationConfig,
+) -> Option<Arc<SimulatedClock>> {
+    let clock = Arc::new(SimulatedClock::new(registry.now(), config.time_step?));
+    registry.set_clock(clock.clone());
+    Some(clock)
+}
+
+pub fn artis_task(
+    artist_tool_registry: Arc<Mutex<ArtistToolRegistry>>,
+    artist: &mut Artist,
+    resources: Arc<Mutex<SharedResources>>,
+    queue_stats: Arc<Mutex<QueueStats>>,
+    config: &SimulationConfig,
+    return_tools: bool,
+) -> Result<(), RegistryError> {
+    let id = artist.id;
+    let _task = tracing::info_span!("artist_task", artist_id = id).entered();
+    let chaos = config.chaos.unwrap_or_default();
+    if Chaos::strikes(chaos.poison, &mut artist.rng) {
+        chaos::poison(&artist_tool_registry);
+        if !config.quiet {
+            tracing::warn!(artist_id = id, "{}", Message::ChaosPoisoned(id));
+        }
+    }
+    let range = match config.tools_per_artist {
+        Some(range) => range,
+        None => artist_tool_registry.lock()?.tool_count_range(id),
+    };
+    let tools = artist.pick_tools(&resources.lock()?.tools, range);
+    if !config.quiet {
+        tracing::info!(
+            artist_id = id,
+            tools = %tools.join(","),
+            "{}",
+            Message::SelectedTools(id, &tools)
+        );
+    }
+
+    let arrival = Instant::now();
+    let mut registry = artist_tool_registry.lock()?;
+    let service_start = Instant::now();
+    let checkout = registry.tool_registry(id, tools.clone())?;
+    if !config.quiet && !checkout.lent.is_empty() {
+        logging::checked_out(id, &checkout.lent, &*registry.shared_resources.lock()?);
+    }
+    drop(registry);
+    let departure = Instant::now();
+    if !config.quiet {
+        for tool in &checkout.queued {
+            tracing::info!(artist_id = id, tool = %tool, "{}", Message::CheckoutQueued(id, tool));
+        }
+        for tool in &checkout.refused {
+            tracing::warn!(artist_id = id, tool = %tool, "{}", Message::LoanCapReached(tool));
+        }
+    }
+
+    let mut stats = queue_stats.lock()?;
+    for tool in tools {
+        stats.record(RequestTiming {
+            tool,
+            arrival,
+            service_start,
+            departure,
+        });
+    }
+    drop(stats);
+
+    // Whatever was lent goes into a sketch, which is painted over the work
+    // time and hung once finished.
+    let work = artist.plan_work(checkout.lent, &resources.lock()?.paints, config);
+    let artwork = start_work(&mut *artist_tool_registry.lock()?, id, &work, config)?;
+    #[cfg(debug_assertions)]
+    simulate_task_delay(work.task.work_time);
+    finish_work(
+        &mut *artist_tool_registry.lock()?,
+        id,
+        &work,
+        artwork,
+        config,
+    )?;
+    let hand_back = if return_tools {
+        artist.hand_back(work.returns, config)
+    } else {
+        HandBack::default()
+    };
+    let reorders = artist.reorders_due(config);
+    book_returns(
+        &mut *artist_tool_registry.lock()?,
+        id,
+        &hand_back,
+        &reorders,
+        config,
+    )
+}
+
+pub fn tools_usage(
+    id: usize,
+    tools: &Stock<Tool>,
+    range: ToolCountRange,
+    strategy: &dyn SelectionStrategy,
+    rng: &mut impl Rng,
+) -> (usize, Vec<String>) {
+    let tool_count = rng.gen_range(range.min..=range.max);
+    (id, strategy.select(tools, tool_count, rng))
+}
+
+// Paint goes straight from the shelf into the artwork, so it's never
+// returned.
+pub fn use_paint(
+    registry: &mut ArtistToolRegistry,
+    artwork: &mut Artwork,
+    (color, kg): (String, Kilograms),
+    config: &SimulationConfig,
+) -> Result<(), RegistryError> {
+    let id = artwork.artist_id;
+    registry.paint_checkout(id, vec![(color.clone(), kg)])?;
+    artwork.add_paint(&color, kg);
+    if !config.quiet {
+        tracing::info!(
+            artist_id = id,
+            color = %color,
+            kg = kg.get(),
+            "{}",
+            Message::UsedPaint(id, &color, kg)
+        );
+    }
+    Ok(())
+}
+
+pub fn simulate_task_delay(work_time: Duration) {
+    thread::sleep(work_time);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        registry::{MAX_ALLOWED_TOOLS, MIN_REQUIRED_TOOLS},
+        resources::TOTAL_ITEMS,
+        State,
+    };
+
+    #[test]
+    fn test_tools_usage() {
+        let tools = [
+            ("brush".to_string(), TOTAL_ITEMS),
+            ("palette".to_string(), TOTAL_ITEMS),
+        ]
+        .into_iter()
+        .collect();
+        let range = ToolCountRange {
+            min: MIN_REQUIRED_TOOLS,
+            max: MAX_ALLOWED_TOOLS,
+        };
+        let (id, selected_tools) = tools_usage(
+            1,
+            &tools,
+            range,
+            &crate::selection::Random,
+            &mut StdRng::seed_from_u64(1),
+        );
+        assert_eq!(id, 1);
+        assert!(
+            selected_tools.len() >= MIN_REQUIRED_TOOLS && selected_tools.len() <= MAX_ALLOWED_TOOLS
+        );
+    }
+
+    #[test]
+    fn test_run_artists_returns_tools_between_rounds() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let registry = Arc::new(Mutex::new(ArtistToolRegistry::new(&resources)));
+        let config = SimulationConfig {
+            artists: 3,
+            tools_per_artist: Some(ToolCountRange { min: 1, max: 2 }),
+            rounds: 4,
+            pool_size: Some(2),
+            ..SimulationConfig::default()
+        };
+        let (queue_stats, errors) = run_artists(&resources, &registry, &config);
+        assert!(errors.is_empty());
+
+        let registry = registry.lock().unwrap();
+        assert_eq!(registry.artist_tool_preferences.len(), 3 * (4 + 3));
+        for id in 0..3 {
+            let held: usize = registry.held_tools(id).values().sum();
+            assert!((1..=2).contains(&held));
+        }
+        assert!(!queue_stats.lock().unwrap().summary().is_empty());
+    }
+
+    #[test]
+    fn test_same_seed_gives_same_checkouts() {
+        let checkouts = |seed| {
+            let resources = Arc::new(Mutex::new(SharedResources::default()));
+            let registry = Arc::new(Mutex::new(ArtistToolRegistry::new(&resources)));
+            let config = SimulationConfig {
+                artists: 4,
+                rounds: 3,
+                seed: Some(seed),
+                ..SimulationConfig::default()
+            };
+            run_artists(&resources, &registry, &config);
+            let registry = registry.lock().unwrap();
+            registry
+                .artist_tool_preferences
+                .iter()
+                .map(|entry| {
+                    let tools: Vec<&str> = entry
+                        .preferred_tools
+                        .iter()
+                        .filter_map(|&symbol| registry.interner.resolve(symbol))
+                        .collect();
+                    format!("{} {:?} {}", entry.artist_id, entry.state, tools.join(","))
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(checkouts(7), checkouts(7));
+        assert_ne!(checkouts(7), checkouts(8));
+    }
+
+    #[test]
+    fn test_fatigue_slows_artists_and_damages_tools() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let registry = Arc::new(Mutex::new(ArtistToolRegistry::new(&resources)));
+        let config = SimulationConfig {
+            artists: 1,
+            tools_per_artist: Some(ToolCountRange { min: 1, max: 1 }),
+            rounds: 3,
+            seed: Some(1),
+            quiet: true,
+            fatigue: Some(FatigueModel {
+                base_damage_probability: 1.0,
+                tasks_before_break: 2,
+                ..FatigueModel::default()
+            }),
+            ..SimulationConfig::default()
+        };
+        let mut artist = Artist::new(0, &registry.lock().unwrap(), &config);
+        let first = artist.next_task(config.fatigue.as_ref());
+        let second = artist.next_task(config.fatigue.as_ref());
+        assert!(second.work_time > first.work_time);
+        assert!(!first.rested && second.rested && second.damages_tool);
+        assert_eq!(artist.next_task(config.fatigue.as_ref()), first);
+
+        let (_, errors) = run_artists(&resources, &registry, &config);
+        assert!(errors.is_empty(), "{:?}", errors);
+        // Every round's tool is damaged, so none is left to hand back.
+        let registry = registry.lock().unwrap();
+        assert_eq!(registry.repairs.jobs().len(), 3);
+        assert!(registry.held_tools(0).is_empty());
+    }
+
+    #[test]
+    fn test_chaos_loses_tools_and_the_run_recovers_from_poisoning() {
+        let run = |chaos: Chaos| {
+            let resources = Arc::new(Mutex::new(SharedResources::default()));
+            let registry = Arc::new(Mutex::new(ArtistToolRegistry::new(&resources)));
+            let config = SimulationConfig {
+                artists: 2,
+                tools_per_artist: Some(ToolCountRange { min: 1, max: 1 }),
+                rounds: 3,
+                seed: Some(1),
+                quiet: true,
+                chaos: Some(chaos),
+                ..SimulationConfig::default()
+            };
+            let (_, errors) = run_artists(&resources, &registry, &config);
+            (registry, errors)
+        };
+
+        let (registry, errors) = run(Chaos {
+            lose: 1.0,
+            ..Chaos::default()
+        });
+        assert!(errors.is_empty(), "{:?}", errors);
+        let kinds: Vec<State> = registry
+            .lock()
+            .unwrap()
+            .events
+            .events()
+            .iter()
+            .map(|event| event.kind)
+            .collect();
+        let count = |kind| kinds.iter().filter(|&&other| other == kind).count();
+        // The first two rounds' tools are lost and replaced at once.
+        assert_eq!((count(State::Lost), count(State::New)), (4, 4));
+        assert_eq!(count(State::Return), 0);
+
+        let (registry, errors) = run(Chaos {
+            poison: 1.0,
+            ..Chaos::default()
+        });
+        assert_eq!(errors.len(), 6);
+        assert!(errors
+            .iter()
+            .all(|error| matches!(error, RegistryError::LockPoisoned)));
+        assert_eq!(registry.lock().unwrap().failed_checkouts(), 0);
+    }
+}
@@ -0,0 +1,177 @@
+use crate::{
+    artwork::Gallery, costs::StudioBudget, crdt::CrdtInventory, deposits::Deposits,
+    drying::DryingRacks, fairness::Starvation, interner::Symbol, ledger::Ledger,
+    repairs::RepairQueue, reservations::Reservations, serials::SerialBook, wear::Wear,
+    ArtistToolRegistry, SharedResources,
+};
+use chrono::{DateTime, Utc};
+use std::collections::HashSet;
+
+// A copy of the inventory and of how far the registry had got when it was
+// taken. History, the event log, deliveries and department charges only ever
+// grow, so for those the snapshot keeps their length; everything else is
+// copied whole.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    taken_at: DateTime<Utc>,
+    resources: SharedResources,
+    entries: usize,
+    events: usize,
+    fills: usize,
+    charges: usize,
+    deposits: Deposits,
+    ledger: Ledger,
+    reservations: Reservations,
+    repairs: RepairQueue,
+    gallery: Gallery,
+    racks: Option<DryingRacks>,
+    branch: Option<CrdtInventory>,
+    units: SerialBook,
+    budget: StudioBudget,
+    starvation: Starvation,
+    flagged_overdue: HashSet<(usize, Symbol, DateTime<Utc>)>,
+    wear: Option<Wear>,
+    failed_checkouts: u64,
+}
+
+impl Snapshot {
+    pub fn taken_at(&self) -> DateTime<Utc> {
+        self.taken_at
+    }
+
+    pub fn resources(&self) -> &SharedResources {
+        &self.resources
+    }
+
+    // How many history entries the registry had.
+    pub fn entries(&self) -> usize {
+        self.entries
+    }
+}
+
+// The registry has fewer entries than the snapshot, so it wasn't taken from
+// this registry or anything it became.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SnapshotAhead {
+    pub entries: usize,
+    pub snapshot: usize,
+}
+
+impl ArtistToolRegistry {
+    pub fn snapshot(&self) -> Snapshot {
+        let resources = self
+            .shared_resources
+            .lock()
+            .expect("Failed to lock resources")
+            .clone();
+        Snapshot {
+            taken_at: self.now(),
+            resources,
+            entries: self.artist_tool_preferences.len(),
+            events: self.events.len(),
+            fills: self.fills.len(),
+            charges: self.departments.charges().len(),
+            deposits: self.deposits.clone(),
+            ledger: self.ledger.clone(),
+            reservations: self.reservations.clone(),
+            repairs: self.repairs.clone(),
+            gallery: self.gallery.clone(),
+            racks: self.racks.clone(),
+            branch: self.branch.clone(),
+            units: self.units.clone(),
+            budget: self.budget.clone(),
+            starvation: self.starvation.clone(),
+            flagged_overdue: self.flagged_overdue.clone(),
+            wear: self.wear.clone(),
+            failed_checkouts: self.failed_checkouts,
+        }
+    }
+
+    // Puts stock and the registry back as they were at `snapshot`, dropping
+    // everything recorded since. The snapshot can be rolled back to again.
+    // Configuration set since, like tool limits or costs, stays.
+    pub fn rollback(&mut self, snapshot: &Snapshot) -> Result<(), SnapshotAhead> {
+        if self.artist_tool_preferences.len() < snapshot.entries {
+            return Err(SnapshotAhead {
+                entries: self.artist_tool_preferences.len(),
+                snapshot: snapshot.entries,
+            });
+        }
+        *self
+            .shared_resources
+            .lock()
+            .expect("Failed to lock resources") = snapshot.resources.clone();
+        self.artist_tool_preferences.truncate(snapshot.entries);
+        self.events.truncate(snapshot.events);
+        self.fills.truncate(snapshot.fills);
+        self.departments.truncate(snapshot.charges);
+        self.deposits = snapshot.deposits.clone();
+        self.ledger = snapshot.ledger.clone();
+        self.reservations = snapshot.reservations.clone();
+        self.repairs = snapshot.repairs.clone();
+        self.gallery = snapshot.gallery.clone();
+        self.racks = snapshot.racks.clone();
+        self.branch = snapshot.branch.clone();
+        self.units = snapshot.units.clone();
+        self.budget = snapshot.budget.clone();
+        self.starvation = snapshot.starvation.clone();
+        self.flagged_overdue = snapshot.flagged_overdue.clone();
+        self.wear = snapshot.wear.clone();
+        self.failed_checkouts = snapshot.failed_checkouts;
+        self.undone
+            .retain(|&(_, reversal)| reversal < snapshot.entries);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::units::Count;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_rollback_undoes_a_what_if_run() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        registry
+            .tool_registry(0, vec!["brush".to_string()])
+            .unwrap();
+        let snapshot = registry.snapshot();
+        let canvases = snapshot.resources().stock("canvas");
+
+        // What if 20 artists all want a canvas?
+        let refused = (1..=20)
+            .filter(|&id| {
+                registry
+                    .tool_registry(id, vec!["canvas".to_string()])
+                    .is_err()
+            })
+            .count();
+        assert_eq!(refused, 20 - canvases.get());
+        assert_eq!(resources.lock().unwrap().stock("canvas"), Count(0));
+
+        for _ in 0..2 {
+            registry.rollback(&snapshot).unwrap();
+            assert_eq!(resources.lock().unwrap().stock("canvas"), canvases);
+            assert_eq!(registry.artist_tool_preferences.len(), 1);
+            assert_eq!(registry.events.len(), 1);
+            assert_eq!(registry.failed_checkouts(), 0);
+            registry
+                .tool_registry(1, vec!["canvas".to_string()])
+                .unwrap();
+        }
+
+        let fresh = ArtistToolRegistry::new(&resources).snapshot();
+        let later = registry.snapshot();
+        let mut other = ArtistToolRegistry::new(&Arc::new(Mutex::new(SharedResources::default())));
+        assert!(other.rollback(&fresh).is_ok());
+        assert_eq!(
+            other.rollback(&later),
+            Err(SnapshotAhead {
+                entries: 0,
+                snapshot: 2,
+            })
+        );
+    }
+}
@@ -0,0 +1,208 @@
+use crate::{
+    units::{Amount, Kilograms},
+    ArtistToolRegistry, SharedResources, State,
+};
+use std::{fs, io, path::Path};
+
+// Quotes a field holding a comma, quote or line break, doubling any quotes.
+pub fn field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// Splits one line into fields, undoing `field`'s quoting.
+pub fn split_row(line: &str) -> Vec<String> {
+    let mut fields = vec![];
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                current.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut current)),
+            c => current.push(c),
+        }
+    }
+    fields.push(current);
+    fields
+}
+
+impl ArtistToolRegistry {
+    // One row per checkout and return, oldest first. An entry's tools share
+    // a field, separated by `;`.
+    pub fn history_csv(&self) -> String {
+        let mut csv = String::from("datetime,artist_id,state,tools\n");
+        for entry in self
+            .history()
+            .filter(|entry| matches!(entry.state, Some(State::TakeOut | State::Return)))
+        {
+            let tools: Vec<&str> = entry
+                .preferred_tools
+                .iter()
+                .filter_map(|&symbol| self.interner.resolve(symbol))
+                .collect();
+            csv.push_str(&format!(
+                "{},{},{:?},{}\n",
+                entry
+                    .datetime
+                    .map(|datetime| datetime.to_rfc3339())
+                    .unwrap_or_default(),
+                entry.artist_id,
+                entry.state.unwrap_or(State::TakeOut),
+                field(&tools.join(";")),
+            ));
+        }
+        csv
+    }
+
+    pub fn export_csv(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.history_csv())
+    }
+}
+
+impl SharedResources {
+    // Every tool and paint in stock, tools first; paint quantities are in
+    // kilograms.
+    pub fn inventory_csv(&self) -> String {
+        let mut csv = String::from("kind,item,quantity\n");
+        for tool in self.tools.iter() {
+            csv.push_str(&format!("tool,{},{}\n", field(&tool.name), tool.quantity));
+        }
+        for paint in self.paints.iter() {
+            csv.push_str(&format!(
+                "paint,{},{}\n",
+                field(&paint.color),
+                paint.weight_kg
+            ));
+        }
+        csv
+    }
+
+    pub fn export_csv(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.inventory_csv())
+    }
+
+    // Sets stock from `kind,item,quantity` rows, as `inventory_csv` writes
+    // them, or the plainer `item,quantity`. Without a kind, items already
+    // stocked as paint stay paint and anything else is a tool. Paint may come
+    // in fractions of a kilogram, tools only in whole units. A header line
+    // and blank lines are skipped. Returns how many rows were applied.
+    pub fn import_rows(&mut self, csv: &str) -> Result<usize, String> {
+        let mut applied = 0;
+        for (number, line) in csv.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let fields = split_row(line);
+            let (kind, item, quantity) = match fields.as_slice() {
+                [kind, item, quantity] => (Some(kind.trim()), item.trim(), quantity),
+                [item, quantity] => (None, item.trim(), quantity),
+                _ => {
+                    return Err(format!(
+                        "line {}: expected 'kind,item,quantity'",
+                        number + 1
+                    ))
+                }
+            };
+            let quantity: Amount = match quantity.trim().parse() {
+                Ok(quantity) => quantity,
+                Err(_) if number == 0 => continue,
+                Err(_) => return Err(format!("line {}: invalid quantity", number + 1)),
+            };
+            let invalid = |error| format!("line {}: {}", number + 1, error);
+            match kind.map(str::to_lowercase).as_deref() {
+                Some("tool") => {
+                    let count = quantity
+                        .count()
+                        .ok_or_else(|| invalid("tools come in whole units".to_string()))?;
+                    self.tools.set(item, count);
+                    self.label(item);
+                }
+                Some("paint") => {
+                    let kg = match quantity {
+                        Amount::Count(count) => Kilograms::whole(count.0),
+                        Amount::Kilograms(kg) => kg,
+                    };
+                    self.paints.set(item, kg);
+                }
+                Some(kind) => return Err(format!("line {}: unknown kind '{}'", number + 1, kind)),
+                None => self
+                    .measure(item, quantity)
+                    .and_then(|quantity| self.set_quantity(item, quantity))
+                    .map_err(|error| invalid(error.to_string()))?,
+            }
+            applied += 1;
+        }
+        Ok(applied)
+    }
+
+    pub fn import_csv(&mut self, path: &Path) -> io::Result<usize> {
+        self.import_rows(&fs::read_to_string(path)?)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{resources::TOTAL_ITEMS, units::Count};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_history_csv_has_a_row_per_checkout_and_return() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        let tools = vec!["brush".to_string(), "palette".to_string()];
+        registry.tool_registry(3, tools.clone()).unwrap();
+        registry.tool_return(3, tools).unwrap();
+        registry.restock("tape", Count(1)).unwrap();
+
+        let csv = registry.history_csv();
+        let rows: Vec<Vec<String>> = csv.lines().skip(1).map(split_row).collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0][1..], ["3", "TakeOut", "brush;palette"]);
+        assert_eq!(rows[1][2], "Return");
+        assert_eq!(split_row(&field("a, \"b\"")), vec!["a, \"b\""]);
+    }
+
+    #[test]
+    fn test_inventory_round_trips_through_csv() {
+        let mut exported = SharedResources::default();
+        exported.tools.set("palette knife", Count(4));
+        exported.paints.set("ochre", Kilograms::grams(1_250));
+        let csv = exported.inventory_csv();
+
+        let mut imported = SharedResources::default();
+        imported.tools.set("palette knife", Count(1));
+        assert_eq!(
+            imported.import_rows(&csv),
+            Ok(exported.tools.iter().count() + exported.paints.iter().count())
+        );
+        assert_eq!(imported.inventory_csv(), csv);
+
+        let mut seeded = SharedResources::default();
+        seeded
+            .import_rows("item,quantity\nbrush,7\nred,2\nblue,0.5\nkiln,1\n")
+            .unwrap();
+        assert_eq!(seeded.stock("brush"), Count(7));
+        assert_eq!(seeded.paints.quantity("red"), Kilograms::whole(2));
+        assert_eq!(seeded.paints.quantity("blue"), Kilograms::grams(500));
+        assert_eq!(seeded.stock("kiln"), Count(1));
+        assert_eq!(seeded.stock("tape"), TOTAL_ITEMS);
+        assert_eq!(
+            seeded.import_rows("brush,1.5"),
+            Err("line 1: 'brush' isn't measured in kg, so 1.5 of it makes no sense".to_string())
+        );
+        assert_eq!(
+            seeded.import_rows("gem,ruby,1"),
+            Err("line 1: unknown kind 'gem'".to_string())
+        );
+    }
+}
@@ -0,0 +1,684 @@
+use crate::{
+    dump::{DumpEntry, StateDump},
+    events::EventRecord,
+    loan_caps::QueuedCheckout,
+    reservations::{Reservation, Reservations},
+    serials::Item,
+    units::{Count, Kilograms},
+    ArtistToolRegistry, SharedResources, State,
+};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    io,
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+// Items and tools are JSON arrays and objects, so SQLite's json functions
+// can look inside them; states are their plain names.
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS entries (
+        seq INTEGER PRIMARY KEY,
+        artist_id INTEGER NOT NULL,
+        tools TEXT NOT NULL,
+        datetime TEXT,
+        state TEXT,
+        from_state TEXT,
+        paints TEXT NOT NULL,
+        due TEXT,
+        serials TEXT NOT NULL DEFAULT '[]'
+    );
+    CREATE TABLE IF NOT EXISTS inventory (
+        kind TEXT NOT NULL,
+        item TEXT NOT NULL,
+        quantity INTEGER NOT NULL,
+        PRIMARY KEY (kind, item)
+    );
+    CREATE TABLE IF NOT EXISTS loans (
+        tool TEXT PRIMARY KEY,
+        count INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS queued (
+        seq INTEGER PRIMARY KEY,
+        checkout TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS events (
+        seq INTEGER PRIMARY KEY,
+        at TEXT NOT NULL,
+        artist_id INTEGER,
+        kind TEXT NOT NULL,
+        items TEXT NOT NULL,
+        stock TEXT NOT NULL,
+        quantities TEXT NOT NULL,
+        serials TEXT NOT NULL DEFAULT '{}',
+        from_state TEXT
+    );
+    CREATE TABLE IF NOT EXISTS units (
+        tool TEXT NOT NULL,
+        serial INTEGER NOT NULL,
+        state TEXT NOT NULL,
+        artist_id INTEGER,
+        PRIMARY KEY (tool, serial)
+    );
+    CREATE TABLE IF NOT EXISTS reservations (
+        id INTEGER PRIMARY KEY,
+        artist_id INTEGER NOT NULL,
+        tools TEXT NOT NULL,
+        from_at TEXT NOT NULL,
+        until_at TEXT NOT NULL
+    );
+";
+
+// Columns added since the first schema, with their types and defaults, for
+// databases created before them.
+const ADDED_COLUMNS: [(&str, &str, &str); 3] = [
+    ("entries", "serials", "TEXT NOT NULL DEFAULT '[]'"),
+    ("events", "serials", "TEXT NOT NULL DEFAULT '{}'"),
+    ("events", "from_state", "TEXT"),
+];
+
+fn sql(error: rusqlite::Error) -> io::Error {
+    io::Error::other(error.to_string())
+}
+
+fn state_name(state: State) -> String {
+    format!("{:?}", state)
+}
+
+fn parse_state(name: String) -> io::Result<State> {
+    Ok(serde_json::from_value(Value::String(name))?)
+}
+
+type EntryRow = (
+    usize,
+    String,
+    Option<DateTime<Utc>>,
+    Option<String>,
+    Option<String>,
+    String,
+    Option<DateTime<Utc>>,
+    String,
+);
+
+type EventRow = (
+    DateTime<Utc>,
+    Option<usize>,
+    String,
+    Option<String>,
+    String,
+    String,
+    String,
+    String,
+);
+
+// Registry history, inventory levels and the event log in one SQLite file.
+// Loan caps' counts and queue come along; like a state dump, deposits, the
+// ledger and repair tickets don't.
+pub struct SqliteStore {
+    connection: Connection,
+}
+
+impl SqliteStore {
+    // Opens the database at `path`, creating it and its tables if needed.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Self::with_connection(Connection::open(path).map_err(sql)?)
+    }
+
+    pub fn in_memory() -> io::Result<Self> {
+        Self::with_connection(Connection::open_in_memory().map_err(sql)?)
+    }
+
+    fn with_connection(connection: Connection) -> io::Result<Self> {
+        connection.execute_batch(SCHEMA).map_err(sql)?;
+        for (table, column, definition) in ADDED_COLUMNS {
+            let present: bool = connection
+                .query_row(
+                    "SELECT COUNT(*) > 0 FROM pragma_table_info(?1) WHERE name = ?2",
+                    params![table, column],
+                    |row| row.get(0),
+                )
+                .map_err(sql)?;
+            if !present {
+                let alter = format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition);
+                connection.execute_batch(&alter).map_err(sql)?;
+            }
+        }
+        Ok(Self { connection })
+    }
+
+    // Replaces whatever was stored with the registry as it stands, all in
+    // one transaction.
+    pub fn save(&mut self, registry: &ArtistToolRegistry) -> io::Result<()> {
+        let dump = StateDump::capture(registry);
+        let transaction = self.connection.transaction().map_err(sql)?;
+        transaction
+            .execute_batch(
+                "DELETE FROM entries; DELETE FROM inventory; DELETE FROM loans;
+                 DELETE FROM queued; DELETE FROM events; DELETE FROM units;
+                 DELETE FROM reservations;",
+            )
+            .map_err(sql)?;
+        for entry in &dump.entries {
+            transaction
+                .execute(
+                    "INSERT INTO entries
+                     (artist_id, tools, datetime, state, from_state, paints, due, serials)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                    params![
+                        entry.artist_id,
+                        serde_json::to_string(&entry.tools)?,
+                        entry.datetime,
+                        entry.state.map(state_name),
+                        entry.from.map(state_name),
+                        serde_json::to_string(&entry.paints)?,
+                        entry.due,
+                        serde_json::to_string(&entry.serials)?,
+                    ],
+                )
+                .map_err(sql)?;
+        }
+        // Tools as whole numbers and paint as weights; SQLite keeps either
+        // in the one column.
+        let tools = dump
+            .tools
+            .iter()
+            .map(|(tool, count)| ("tool", tool, count.0 as f64));
+        let paints = dump
+            .paints
+            .iter()
+            .map(|(paint, kg)| ("paint", paint, kg.get()));
+        for (kind, item, quantity) in tools.chain(paints) {
+            transaction
+                .execute(
+                    "INSERT INTO inventory (kind, item, quantity) VALUES (?1, ?2, ?3)",
+                    params![kind, item, quantity],
+                )
+                .map_err(sql)?;
+        }
+        for (tool, count) in &dump.on_loan {
+            transaction
+                .execute(
+                    "INSERT INTO loans (tool, count) VALUES (?1, ?2)",
+                    params![tool, count],
+                )
+                .map_err(sql)?;
+        }
+        for unit in &dump.units {
+            transaction
+                .execute(
+                    "INSERT INTO units (tool, serial, state, artist_id) VALUES (?1, ?2, ?3, ?4)",
+                    params![
+                        unit.tool,
+                        unit.serial,
+                        state_name(unit.state),
+                        unit.artist_id
+                    ],
+                )
+                .map_err(sql)?;
+        }
+        for booking in dump.reservations.open() {
+            transaction
+                .execute(
+                    "INSERT INTO reservations (id, artist_id, tools, from_at, until_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        booking.id,
+                        booking.artist_id,
+                        serde_json::to_string(&booking.tools)?,
+                        booking.from,
+                        booking.until,
+                    ],
+                )
+                .map_err(sql)?;
+        }
+        for queued in &dump.queued {
+            transaction
+                .execute(
+                    "INSERT INTO queued (checkout) VALUES (?1)",
+                    params![serde_json::to_string(queued)?],
+                )
+                .map_err(sql)?;
+        }
+        for event in registry.events.records() {
+            transaction
+                .execute(
+                    "INSERT INTO events
+                     (at, artist_id, kind, from_state, items, stock, quantities, serials)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                    params![
+                        event.at,
+                        event.artist_id,
+                        state_name(event.kind),
+                        event.from.map(state_name),
+                        serde_json::to_string(&event.items)?,
+                        serde_json::to_string(&event.stock)?,
+                        serde_json::to_string(&event.quantities)?,
+                        serde_json::to_string(&event.serials)?,
+                    ],
+                )
+                .map_err(sql)?;
+        }
+        transaction.commit().map_err(sql)
+    }
+
+    // Puts the stored inventory into `resources` and rebuilds the registry
+    // around it, event log included. None if nothing has been saved yet.
+    pub fn load(
+        &self,
+        resources: &Arc<Mutex<SharedResources>>,
+    ) -> io::Result<Option<ArtistToolRegistry>> {
+        let mut dump = StateDump {
+            captured_at: Utc::now(),
+            tools: vec![],
+            paints: vec![],
+            on_loan: vec![],
+            queued: vec![],
+            entries: vec![],
+            undone: vec![],
+            branch: None,
+            units: vec![],
+            reservations: Reservations::default(),
+        };
+        let mut statement = self
+            .connection
+            .prepare("SELECT kind, item, quantity FROM inventory ORDER BY rowid")
+            .map_err(sql)?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, f64>(2)?,
+                ))
+            })
+            .map_err(sql)?;
+        for row in rows {
+            let (kind, item, quantity) = row.map_err(sql)?;
+            match kind.as_str() {
+                "paint" => {
+                    let kg = Kilograms::new(quantity).map_err(io::Error::other)?;
+                    dump.paints.push((item, kg))
+                }
+                _ => dump.tools.push((item, Count(quantity as u32))),
+            }
+        }
+        if dump.tools.is_empty() && dump.paints.is_empty() {
+            return Ok(None);
+        }
+
+        let mut statement = self
+            .connection
+            .prepare("SELECT tool, count FROM loans ORDER BY rowid")
+            .map_err(sql)?;
+        let rows = statement
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(sql)?;
+        for row in rows {
+            dump.on_loan.push(row.map_err(sql)?);
+        }
+
+        let mut statement = self
+            .connection
+            .prepare("SELECT tool, serial, state, artist_id FROM units ORDER BY tool, serial")
+            .map_err(sql)?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })
+            .map_err(sql)?;
+        for row in rows {
+            let (tool, serial, state, artist_id) = row.map_err(sql)?;
+            dump.units.push(Item {
+                tool,
+                serial,
+                state: parse_state(state)?,
+                artist_id,
+            });
+        }
+
+        let mut statement = self
+            .connection
+            .prepare("SELECT checkout FROM queued ORDER BY seq")
+            .map_err(sql)?;
+        let rows = statement
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(sql)?;
+        for row in rows {
+            let queued: QueuedCheckout = serde_json::from_str(&row.map_err(sql)?)?;
+            dump.queued.push(queued);
+        }
+
+        let mut statement = self
+            .connection
+            .prepare(
+                "SELECT artist_id, tools, datetime, state, from_state, paints, due, serials
+                 FROM entries ORDER BY seq",
+            )
+            .map_err(sql)?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                    row.get(5)?,
+                    row.get(6)?,
+                    row.get(7)?,
+                ))
+            })
+            .map_err(sql)?;
+        for row in rows {
+            let (artist_id, tools, datetime, state, from, paints, due, serials): EntryRow =
+                row.map_err(sql)?;
+            dump.entries.push(DumpEntry {
+                artist_id,
+                tools: serde_json::from_str(&tools)?,
+                datetime,
+                state: state.map(parse_state).transpose()?,
+                from: from.map(parse_state).transpose()?,
+                paints: serde_json::from_str(&paints)?,
+                due,
+                serials: serde_json::from_str(&serials)?,
+            });
+        }
+
+        let mut statement = self
+            .connection
+            .prepare("SELECT id, artist_id, tools, from_at, until_at FROM reservations ORDER BY id")
+            .map_err(sql)?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                ))
+            })
+            .map_err(sql)?;
+        let mut open = vec![];
+        for row in rows {
+            let (id, artist_id, tools, from, until) = row.map_err(sql)?;
+            open.push(Reservation {
+                id,
+                artist_id,
+                tools: serde_json::from_str(&tools)?,
+                from,
+                until,
+            });
+        }
+        // Every booking leaves one `Reserved` entry, so that many numbers
+        // have been handed out.
+        let booked = dump
+            .entries
+            .iter()
+            .filter(|entry| entry.state == Some(State::Reserved))
+            .count();
+        dump.reservations = Reservations::restore(open, booked);
+        let mut registry = dump.restore(resources);
+
+        let mut statement = self
+            .connection
+            .prepare(
+                "SELECT at, artist_id, kind, from_state, items, stock, quantities, serials
+                 FROM events ORDER BY seq",
+            )
+            .map_err(sql)?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                    row.get(5)?,
+                    row.get(6)?,
+                    row.get(7)?,
+                ))
+            })
+            .map_err(sql)?;
+        for row in rows {
+            let (at, artist_id, kind, from, items, stock, quantities, serials): EventRow =
+                row.map_err(sql)?;
+            registry.events.append(EventRecord {
+                at,
+                artist_id,
+                kind: parse_state(kind)?,
+                from: from.map(parse_state).transpose()?,
+                items: serde_json::from_str(&items)?,
+                stock: serde_json::from_str(&stock)?,
+                quantities: serde_json::from_str(&quantities)?,
+                serials: serde_json::from_str(&serials)?,
+            });
+        }
+        Ok(Some(registry))
+    }
+
+    // Units of `tool` on the shelf as last saved; None if it isn't stocked.
+    pub fn stock(&self, tool: &str) -> io::Result<Option<Count>> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT quantity FROM inventory WHERE kind = 'tool' AND item = ?1")
+            .map_err(sql)?;
+        let mut rows = statement
+            .query_map(params![tool], |row| row.get::<_, f64>(0))
+            .map_err(sql)?;
+        rows.next()
+            .transpose()
+            .map(|quantity| quantity.map(|quantity| Count(quantity as u32)))
+            .map_err(sql)
+    }
+
+    // The artist's saved history, oldest first.
+    pub fn history_for(&self, artist_id: usize) -> io::Result<Vec<DumpEntry>> {
+        let mut statement = self
+            .connection
+            .prepare(
+                "SELECT artist_id, tools, datetime, state, from_state, paints, due, serials
+                 FROM entries WHERE artist_id = ?1 ORDER BY seq",
+            )
+            .map_err(sql)?;
+        let rows = statement
+            .query_map(params![artist_id], |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                    row.get(5)?,
+                    row.get(6)?,
+                    row.get(7)?,
+                ))
+            })
+            .map_err(sql)?;
+        let mut entries = vec![];
+        for row in rows {
+            let (artist_id, tools, datetime, state, from, paints, due, serials): EntryRow =
+                row.map_err(sql)?;
+            entries.push(DumpEntry {
+                artist_id,
+                tools: serde_json::from_str(&tools)?,
+                datetime,
+                state: state.map(parse_state).transpose()?,
+                from: from.map(parse_state).transpose()?,
+                paints: serde_json::from_str(&paints)?,
+                due,
+                serials: serde_json::from_str(&serials)?,
+            });
+        }
+        Ok(entries)
+    }
+}
+
+// How often `CachedStore` reads were answered without the database, across
+// every store in the process; `GET /metrics` reports them.
+#[derive(Debug, Default)]
+pub struct CacheStats {
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheSnapshot {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+pub static DB_CACHE: CacheStats = CacheStats::new();
+
+impl CacheStats {
+    pub const fn new() -> Self {
+        Self {
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub fn snapshot(&self) -> CacheSnapshot {
+        CacheSnapshot {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    fn count(&self, hit: bool) {
+        let counter = if hit { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+// A `SqliteStore` that keeps what it has read in memory, so repeated
+// availability and history lookups don't go back to the database. Saving
+// goes through to the database and drops everything cached.
+pub struct CachedStore {
+    store: SqliteStore,
+    stock: HashMap<String, Option<Count>>,
+    histories: HashMap<usize, Vec<DumpEntry>>,
+}
+
+impl CachedStore {
+    pub fn new(store: SqliteStore) -> Self {
+        Self {
+            store,
+            stock: HashMap::new(),
+            histories: HashMap::new(),
+        }
+    }
+
+    pub fn open(path: &Path) -> io::Result<Self> {
+        SqliteStore::open(path).map(Self::new)
+    }
+
+    pub fn save(&mut self, registry: &ArtistToolRegistry) -> io::Result<()> {
+        self.stock.clear();
+        self.histories.clear();
+        self.store.save(registry)
+    }
+
+    pub fn load(
+        &self,
+        resources: &Arc<Mutex<SharedResources>>,
+    ) -> io::Result<Option<ArtistToolRegistry>> {
+        self.store.load(resources)
+    }
+
+    pub fn stock(&mut self, tool: &str) -> io::Result<Option<Count>> {
+        if let Some(&stock) = self.stock.get(tool) {
+            DB_CACHE.count(true);
+            return Ok(stock);
+        }
+        DB_CACHE.count(false);
+        let stock = self.store.stock(tool)?;
+        self.stock.insert(tool.to_string(), stock);
+        Ok(stock)
+    }
+
+    pub fn history_for(&mut self, artist_id: usize) -> io::Result<&[DumpEntry]> {
+        DB_CACHE.count(self.histories.contains_key(&artist_id));
+        if !self.histories.contains_key(&artist_id) {
+            let history = self.store.history_for(artist_id)?;
+            self.histories.insert(artist_id, history);
+        }
+        Ok(&self.histories[&artist_id])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_round_trips_through_sqlite() {
+        let mut store = SqliteStore::in_memory().unwrap();
+        let fresh = Arc::new(Mutex::new(SharedResources::default()));
+        assert!(store.load(&fresh).unwrap().is_none());
+
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        let tools = vec!["brush".to_string(), "palette".to_string()];
+        registry.tool_registry(2, tools.clone()).unwrap();
+        registry.tool_return(2, vec!["brush".to_string()]).unwrap();
+        registry.restock("red", Kilograms::grams(2_500)).unwrap();
+        let now = registry.now();
+        registry
+            .reserve(3, tools.clone(), now, now + chrono::Duration::hours(1))
+            .unwrap();
+        store.save(&registry).unwrap();
+        // Saving again replaces rather than adds to what was stored.
+        store.save(&registry).unwrap();
+
+        let loaded = store.load(&fresh).unwrap().unwrap();
+        let (saved, restored) = (StateDump::capture(&registry), StateDump::capture(&loaded));
+        assert_eq!(restored.entries, saved.entries);
+        assert_eq!(restored.tools, saved.tools);
+        assert_eq!(restored.paints, saved.paints);
+        assert_eq!(restored.reservations, saved.reservations);
+        assert!(loaded.events.records().eq(registry.events.records()));
+
+        let take_outs: usize = store
+            .connection
+            .query_row(
+                "SELECT COUNT(*) FROM events WHERE kind = 'TakeOut' AND artist_id = 2",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(take_outs, 1);
+    }
+
+    #[test]
+    fn test_cached_store_reads_once_until_saved() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        let mut store = CachedStore::new(SqliteStore::in_memory().unwrap());
+        store.save(&registry).unwrap();
+        let before = DB_CACHE.snapshot();
+        let brushes = store.stock("brush").unwrap().unwrap();
+        assert_eq!(store.stock("brush").unwrap(), Some(brushes));
+        assert_eq!(store.stock("kiln").unwrap(), None);
+        assert!(store.history_for(2).unwrap().is_empty());
+        let after = DB_CACHE.snapshot();
+        // Other tests share the counters, so only a lower bound holds.
+        assert!(after.hits > before.hits);
+        assert!(after.misses >= before.misses + 3);
+
+        // A save invalidates what was cached.
+        registry
+            .tool_registry(2, vec!["brush".to_string()])
+            .unwrap();
+        store.save(&registry).unwrap();
+        assert_eq!(store.stock("brush").unwrap(), Some(brushes - Count(1)));
+        assert_eq!(store.history_for(2).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,186 @@
+use crate::{fairness::ArtistWait, interner::Symbol, units::Kilograms, ArtistToolRegistry, State};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, VecDeque};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolStats {
+    pub tool: String,
+    pub checkouts: usize,
+    // Share of the tool's unit-time spent checked out, from 0 to 1.
+    pub utilization: f64,
+    // Over units that have left the artist's hands; None until one has.
+    pub avg_hold: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ArtistActivity {
+    pub artist_id: usize,
+    pub checkouts: usize,
+    pub returns: usize,
+    pub paint_kg: Kilograms,
+}
+
+// How the registry's tools have been used, from its first entry up to the
+// time the stats were computed. Tools are sorted most checked out first and
+// artists most active first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stats {
+    pub window: Duration,
+    pub tools: Vec<ToolStats>,
+    pub artists: Vec<ArtistActivity>,
+    // Artists who have been kept waiting, longest wait first.
+    pub starvation: Vec<ArtistWait>,
+}
+
+#[derive(Default)]
+struct ToolTally {
+    checkouts: usize,
+    // Completed holds and their total length.
+    holds: usize,
+    hold_total: Duration,
+    // How long the units still out have been held so far, summed.
+    still_out: Duration,
+}
+
+impl Stats {
+    // Units still out count as held until `now`. A tool's units are what is
+    // on the shelf or on loan at `now`.
+    pub fn compute(registry: &ArtistToolRegistry, now: DateTime<Utc>) -> Self {
+        let start = registry
+            .history()
+            .filter_map(|entry| entry.datetime)
+            .min()
+            .unwrap_or(now);
+        let mut tallies: HashMap<Symbol, ToolTally> = HashMap::new();
+        let mut artists: HashMap<usize, ArtistActivity> = HashMap::new();
+        // When each unit an artist has out was taken, oldest first.
+        let mut out: HashMap<(usize, Symbol), VecDeque<DateTime<Utc>>> = HashMap::new();
+
+        for entry in registry.history() {
+            let at = entry.datetime.unwrap_or(start);
+            if entry.state == Some(State::Paint) {
+                activity(&mut artists, entry.artist_id).paint_kg +=
+                    entry.paints.iter().map(|&(_, kg)| kg).sum::<Kilograms>();
+            }
+            for &symbol in &entry.preferred_tools {
+                if entry.source_state() == Some(State::TakeOut) {
+                    if let Some(taken) = out
+                        .get_mut(&(entry.artist_id, symbol))
+                        .and_then(VecDeque::pop_front)
+                    {
+                        let tally = tallies.entry(symbol).or_default();
+                        tally.holds += 1;
+                        tally.hold_total += at - taken;
+                    }
+                    if entry.state == Some(State::Return) {
+                        activity(&mut artists, entry.artist_id).returns += 1;
+                    }
+                }
+                if entry.state == Some(State::TakeOut) {
+                    tallies.entry(symbol).or_default().checkouts += 1;
+                    out.entry((entry.artist_id, symbol))
+                        .or_default()
+                        .push_back(at);
+                    activity(&mut artists, entry.artist_id).checkouts += 1;
+                }
+            }
+        }
+        for ((_, symbol), taken) in out {
+            let tally = tallies.entry(symbol).or_default();
+            for taken in taken {
+                tally.still_out += now - taken;
+            }
+        }
+
+        let window = now - start;
+        let resources = registry
+            .shared_resources
+            .lock()
+            .expect("Failed to lock resources");
+        let mut tools: Vec<ToolStats> = tallies
+            .into_iter()
+            .filter_map(|(symbol, tally)| {
+                let tool = registry.interner.resolve(symbol)?;
+                let units = resources.stock(tool).get() + resources.loan_caps.on_loan(tool);
+                let capacity = window.num_milliseconds() as f64 * units as f64;
+                Some(ToolStats {
+                    tool: tool.to_string(),
+                    checkouts: tally.checkouts,
+                    utilization: if capacity > 0.0 {
+                        (tally.hold_total + tally.still_out).num_milliseconds() as f64 / capacity
+                    } else {
+                        0.0
+                    },
+                    avg_hold: (tally.holds > 0).then(|| tally.hold_total / tally.holds as i32),
+                })
+            })
+            .collect();
+        tools.sort_by(|a, b| {
+            b.checkouts
+                .cmp(&a.checkouts)
+                .then_with(|| a.tool.cmp(&b.tool))
+        });
+        let mut artists: Vec<ArtistActivity> = artists.into_values().collect();
+        artists.sort_by(|a, b| {
+            b.checkouts
+                .cmp(&a.checkouts)
+                .then_with(|| a.artist_id.cmp(&b.artist_id))
+        });
+        Self {
+            window,
+            tools,
+            artists,
+            starvation: registry.starvation.report(now),
+        }
+    }
+}
+
+fn activity(artists: &mut HashMap<usize, ArtistActivity>, artist_id: usize) -> &mut ArtistActivity {
+    artists.entry(artist_id).or_insert_with(|| ArtistActivity {
+        artist_id,
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SharedResources;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_stats_tally_holds_and_activity() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        registry
+            .tool_registry(1, vec!["brush".to_string(), "tape".to_string()])
+            .unwrap();
+        registry
+            .tool_registry(2, vec!["brush".to_string()])
+            .unwrap();
+        registry.tool_return(1, vec!["brush".to_string()]).unwrap();
+        let start = Utc::now();
+        for (hours, entry) in registry.artist_tool_preferences.iter_mut().enumerate() {
+            entry.datetime = Some(start + Duration::hours(hours as i64));
+        }
+
+        let stats = Stats::compute(&registry, start + Duration::hours(4));
+        assert_eq!(stats.window, Duration::hours(4));
+        let brush = &stats.tools[0];
+        assert_eq!((brush.tool.as_str(), brush.checkouts), ("brush", 2));
+        assert_eq!(brush.avg_hold, Some(Duration::hours(2)));
+        // Two hours returned plus three still out, over ten units for four hours.
+        assert!((brush.utilization - 0.125).abs() < 1e-9);
+        assert_eq!(stats.tools[1].avg_hold, None);
+        assert_eq!(
+            stats.artists[0],
+            ArtistActivity {
+                artist_id: 1,
+                checkouts: 2,
+                returns: 1,
+                paint_kg: Kilograms::default()
+            }
+        );
+        assert_eq!(stats.artists[1].checkouts, 1);
+    }
+}
@@ -0,0 +1,246 @@
+use crate::units::{Count, Kilograms, Quantity};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+
+pub const DEFAULT_CATEGORY: &str = "general";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub enum Condition {
+    #[default]
+    Good,
+    Worn,
+    Damaged,
+}
+
+// One line of tool stock: `quantity` interchangeable units on the shelf.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Tool {
+    pub id: usize,
+    pub name: String,
+    pub category: String,
+    pub quantity: Count,
+    pub condition: Condition,
+}
+
+// One color of paint. `batch` and `expiry` describe the batch the next
+// kilogram comes out of, when any of the color is batched.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Paint {
+    pub id: usize,
+    pub color: String,
+    pub weight_kg: Kilograms,
+    pub batch: Option<String>,
+    pub expiry: Option<DateTime<Utc>>,
+}
+
+// What `Stock` needs from the items it holds.
+pub trait Stocked {
+    type Quantity: Quantity;
+
+    fn new(id: usize, name: &str, quantity: Self::Quantity) -> Self;
+    fn name(&self) -> &str;
+    fn quantity_mut(&mut self) -> &mut Self::Quantity;
+    fn quantity(&self) -> Self::Quantity;
+}
+
+impl Stocked for Tool {
+    type Quantity = Count;
+
+    fn new(id: usize, name: &str, quantity: Count) -> Self {
+        Self {
+            id,
+            name: name.to_string(),
+            category: DEFAULT_CATEGORY.to_string(),
+            quantity,
+            condition: Condition::default(),
+        }
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn quantity_mut(&mut self) -> &mut Count {
+        &mut self.quantity
+    }
+
+    fn quantity(&self) -> Count {
+        self.quantity
+    }
+}
+
+impl Stocked for Paint {
+    type Quantity = Kilograms;
+
+    fn new(id: usize, name: &str, quantity: Kilograms) -> Self {
+        Self {
+            id,
+            color: name.to_string(),
+            weight_kg: quantity,
+            batch: None,
+            expiry: None,
+        }
+    }
+
+    fn name(&self) -> &str {
+        &self.color
+    }
+
+    fn quantity_mut(&mut self) -> &mut Kilograms {
+        &mut self.weight_kg
+    }
+
+    fn quantity(&self) -> Kilograms {
+        self.weight_kg
+    }
+}
+
+// Items keyed by ID, with a name index so lookups by name are O(1) too.
+// IDs are handed out in listing order and iteration follows them; an item
+// that is delisted and listed again gets a new ID.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stock<T> {
+    items: HashMap<usize, T>,
+    ids: HashMap<String, usize>,
+    next_id: usize,
+}
+
+impl<T> Default for Stock<T> {
+    fn default() -> Self {
+        Self {
+            items: HashMap::new(),
+            ids: HashMap::new(),
+            next_id: 1,
+        }
+    }
+}
+
+impl<T: Stocked> Stock<T> {
+    pub fn id(&self, name: &str) -> Option<usize> {
+        self.ids.get(name).copied()
+    }
+
+    pub fn by_id(&self, id: usize) -> Option<&T> {
+        self.items.get(&id)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&T> {
+        self.items.get(&self.id(name)?)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut T> {
+        let id = self.id(name)?;
+        self.items.get_mut(&id)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.ids.contains_key(name)
+    }
+
+    // Zero for items that aren't listed.
+    pub fn quantity(&self, name: &str) -> T::Quantity {
+        self.get(name).map(T::quantity).unwrap_or_default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    // In the order the items were listed.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        let mut ids: Vec<&usize> = self.items.keys().collect();
+        ids.sort();
+        ids.into_iter().map(|id| &self.items[id])
+    }
+
+    // In no particular order.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.items.values_mut()
+    }
+
+    // Adds to an item's quantity, listing it if it isn't. Returns its ID.
+    pub fn add(&mut self, name: &str, quantity: T::Quantity) -> usize {
+        if let Some(id) = self.id(name) {
+            *self.items.get_mut(&id).expect("indexed").quantity_mut() += quantity;
+            return id;
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        self.items.insert(id, T::new(id, name, quantity));
+        self.ids.insert(name.to_string(), id);
+        id
+    }
+
+    // Sets an item's quantity outright; zero delists it.
+    pub fn set(&mut self, name: &str, quantity: T::Quantity) {
+        if quantity.is_zero() {
+            self.remove(name);
+        } else if let Some(item) = self.get_mut(name) {
+            *item.quantity_mut() = quantity;
+        } else {
+            self.add(name, quantity);
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<T> {
+        let id = self.ids.remove(name)?;
+        self.items.remove(&id)
+    }
+
+    // Name and quantity of every item, in listing order.
+    pub fn amounts(&self) -> Vec<(String, T::Quantity)> {
+        self.iter()
+            .map(|item| (item.name().to_string(), item.quantity()))
+            .collect()
+    }
+}
+
+impl<T: Stocked> FromIterator<(String, T::Quantity)> for Stock<T> {
+    fn from_iter<I: IntoIterator<Item = (String, T::Quantity)>>(items: I) -> Self {
+        let mut stock = Self::default();
+        for (name, quantity) in items {
+            stock.add(&name, quantity);
+        }
+        stock
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stock_keeps_listing_order_by_id() {
+        let mut tools: Stock<Tool> = [("brush", 2), ("tape", 1), ("easel", 1)]
+            .into_iter()
+            .map(|(name, quantity)| (name.to_string(), Count(quantity)))
+            .collect();
+        assert_eq!(tools.id("tape"), Some(2));
+        assert_eq!(tools.by_id(2).unwrap().name, "tape");
+        assert_eq!(tools.get("brush").unwrap().category, DEFAULT_CATEGORY);
+
+        tools.set("brush", Count(0));
+        tools.add("tape", Count(2));
+        tools.add("brush", Count(4));
+        assert_eq!(
+            tools.amounts(),
+            vec![
+                ("tape".to_string(), Count(3)),
+                ("easel".to_string(), Count(1)),
+                ("brush".to_string(), Count(4))
+            ]
+        );
+        assert_eq!(tools.id("brush"), Some(4));
+        assert_eq!(tools.quantity("palette"), Count(0));
+
+        let mut paints: Stock<Paint> = Stock::default();
+        paints.add("red", Kilograms::grams(500));
+        paints.add("red", Kilograms::grams(250));
+        assert_eq!(paints.quantity("red"), Kilograms::grams(750));
+    }
+}
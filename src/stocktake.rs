@@ -0,0 +1,203 @@
+use crate::{
+    dump::StateDump,
+    error::ResourceError,
+    units::{round_to_gram, Amount, Kilograms, Quantity},
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::{
+    fs::OpenOptions,
+    io::{self, Write},
+    path::Path,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum StockKind {
+    Tool,
+    Paint,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum AdjustmentReason {
+    // Fewer on the shelf than recorded: lost, broken or taken without a slip.
+    Shrinkage,
+    // More on the shelf than recorded, or an item the registry didn't know:
+    // the earlier count or a return was recorded wrong.
+    Miscount,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Adjustment {
+    pub item: String,
+    pub kind: StockKind,
+    pub recorded: Amount,
+    pub counted: Amount,
+    pub reason: AdjustmentReason,
+}
+
+impl Adjustment {
+    pub fn delta(&self) -> f64 {
+        let value = |amount: Amount| match amount {
+            Amount::Count(count) => count.0 as f64,
+            Amount::Kilograms(kg) => kg.get(),
+        };
+        round_to_gram(value(self.counted) - value(self.recorded))
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct AuditRecord<'a> {
+    at: DateTime<Utc>,
+    adjustments: &'a [Adjustment],
+}
+
+// Reads `item,quantity` lines; a header line and blank lines are skipped.
+// Quantities may be fractional, for weighed paint.
+pub fn parse_counts(csv: &str) -> Result<Vec<(String, Amount)>, String> {
+    let mut counts = vec![];
+    for (number, line) in csv.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (item, quantity) = line
+            .rsplit_once(',')
+            .ok_or_else(|| format!("line {}: expected 'item,quantity'", number + 1))?;
+        match quantity.trim().parse() {
+            Ok(quantity) => counts.push((item.trim().to_string(), quantity)),
+            Err(_) if number == 0 => continue,
+            Err(_) => return Err(format!("line {}: invalid quantity", number + 1)),
+        }
+    }
+    Ok(counts)
+}
+
+// Compares physical counts to the recorded stock. Items that weren't counted
+// are left alone. A whole number of a paint is that many kilograms, while a
+// fraction of a tool is refused.
+pub fn propose(
+    state: &StateDump,
+    counts: &[(String, Amount)],
+) -> Result<Vec<Adjustment>, ResourceError> {
+    let mut adjustments = vec![];
+    for (item, counted) in counts {
+        let (kind, recorded) = lookup(state, item);
+        let counted = match (kind, *counted) {
+            (StockKind::Paint, Amount::Count(count)) => Kilograms::whole(count.0).into(),
+            (StockKind::Tool, Amount::Kilograms(_)) => {
+                return Err(ResourceError::WrongUnit {
+                    item: item.clone(),
+                    quantity: *counted,
+                })
+            }
+            (_, counted) => counted,
+        };
+        let reason = match counted.partial_cmp(&recorded) {
+            Some(std::cmp::Ordering::Less) => AdjustmentReason::Shrinkage,
+            Some(std::cmp::Ordering::Greater) => AdjustmentReason::Miscount,
+            _ => continue,
+        };
+        adjustments.push(Adjustment {
+            item: item.clone(),
+            kind,
+            recorded,
+            counted,
+            reason,
+        });
+    }
+    Ok(adjustments)
+}
+
+// Recorded quantity of a tool or paint. Unknown items count as tools with
+// nothing recorded, and tools that ran out no longer appear in the stock list.
+fn lookup(state: &StateDump, item: &str) -> (StockKind, Amount) {
+    if let Some((_, kg)) = state.paints.iter().find(|(name, _)| name == item) {
+        return (StockKind::Paint, (*kg).into());
+    }
+    let tools = state.tools.iter().find(|(name, _)| name == item);
+    (
+        StockKind::Tool,
+        tools.map(|(_, count)| *count).unwrap_or_default().into(),
+    )
+}
+
+pub fn apply(state: &mut StateDump, adjustments: &[Adjustment]) {
+    // Tools that ran out leave the list; paint stays listed at nothing.
+    fn set<Q: Quantity>(stock: &mut Vec<(String, Q)>, item: &str, counted: Q, unlist: bool) {
+        match stock.iter().position(|(name, _)| name == item) {
+            Some(pos) if counted.is_zero() && unlist => {
+                stock.remove(pos);
+            }
+            Some(pos) => stock[pos].1 = counted,
+            None if !counted.is_zero() => stock.push((item.to_string(), counted)),
+            None => {}
+        }
+    }
+    for adjustment in adjustments {
+        let item = &adjustment.item;
+        // `propose` measures every count in the unit its item goes by.
+        match (adjustment.kind, adjustment.counted) {
+            (StockKind::Tool, Amount::Count(count)) => set(&mut state.tools, item, count, true),
+            (StockKind::Paint, Amount::Kilograms(kg)) => set(&mut state.paints, item, kg, false),
+            _ => {}
+        }
+    }
+}
+
+// Appends one JSON line per applied stocktake.
+pub fn log_audit(path: &Path, adjustments: &[Adjustment], at: DateTime<Utc>) -> io::Result<()> {
+    let record = serde_json::to_string(&AuditRecord { at, adjustments })?;
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", record)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{units::Count, ArtistToolRegistry, SharedResources};
+    use std::sync::{Arc, Mutex};
+
+    fn state() -> StateDump {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        StateDump::capture(&ArtistToolRegistry::new(&resources))
+    }
+
+    #[test]
+    fn test_parse_counts_skips_header() {
+        let counts = parse_counts("item,quantity\nbrush,8\n\nwater container, 10\n").unwrap();
+        assert_eq!(
+            counts,
+            vec![
+                ("brush".to_string(), Count(8).into()),
+                ("water container".to_string(), Count(10).into())
+            ]
+        );
+        assert!(parse_counts("brush,8\ntape,lots\n").is_err());
+    }
+
+    #[test]
+    fn test_propose_and_apply_adjustments() {
+        let mut state = state();
+        let counts = parse_counts("brush,8\ncanvas,10\nred,12.5\nblue,10\neasel,1\n").unwrap();
+        let adjustments = propose(&state, &counts).unwrap();
+
+        assert_eq!(adjustments.len(), 3);
+        assert_eq!(adjustments[0].reason, AdjustmentReason::Shrinkage);
+        assert_eq!(adjustments[0].delta(), -2.0);
+        assert_eq!(adjustments[1].kind, StockKind::Paint);
+        assert_eq!(adjustments[1].reason, AdjustmentReason::Miscount);
+        assert_eq!(adjustments[1].delta(), 2.5);
+        assert_eq!(adjustments[2].recorded, Count(0).into());
+
+        apply(&mut state, &adjustments);
+        assert_eq!(propose(&state, &counts), Ok(vec![]));
+        assert_eq!(state.tools.last(), Some(&("easel".to_string(), Count(1))));
+        assert_eq!(
+            propose(&state, &parse_counts("brush,7.5\n").unwrap()),
+            Err(ResourceError::WrongUnit {
+                item: "brush".to_string(),
+                quantity: Kilograms::grams(7_500).into(),
+            })
+        );
+    }
+}
@@ -0,0 +1,326 @@
+use crate::{
+    error::{RegistryError, ResourceError},
+    templates::StudioConfig,
+    units::Count,
+    watch::RunSummary,
+    ArtistToolRegistry, SharedResources, State,
+};
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+// One studio: its own stock and the registry lending it out. Nothing is
+// shared between studios.
+pub struct Studio {
+    pub name: String,
+    pub resources: Arc<Mutex<SharedResources>>,
+    pub registry: Arc<Mutex<ArtistToolRegistry>>,
+}
+
+impl Studio {
+    pub fn new(name: &str, resources: SharedResources) -> Self {
+        let resources = Arc::new(Mutex::new(resources));
+        Self {
+            name: name.to_string(),
+            registry: Arc::new(Mutex::new(ArtistToolRegistry::new(&resources))),
+            resources,
+        }
+    }
+
+    pub fn from_config(config: &StudioConfig) -> Self {
+        Self::new(&config.name, config.resources())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StudioError {
+    Duplicate(String),
+    Unknown(String),
+    // A transfer from a studio to itself.
+    SameStudio(String),
+    // The source can't spare the units.
+    Registry(RegistryError),
+    // The destination has no room for them.
+    Resource(ResourceError),
+}
+
+// Named studios and which one each artist works in.
+#[derive(Default)]
+pub struct Studios {
+    studios: Vec<Studio>,
+    // Artist id to the index of their studio.
+    assignments: BTreeMap<usize, usize>,
+}
+
+impl Studios {
+    pub fn add(&mut self, studio: Studio) -> Result<(), StudioError> {
+        if self.get(&studio.name).is_some() {
+            return Err(StudioError::Duplicate(studio.name));
+        }
+        self.studios.push(studio);
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Studio> {
+        self.studios.iter().find(|studio| studio.name == name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Studio> {
+        self.studios.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.studios.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.studios.is_empty()
+    }
+
+    fn index(&self, name: &str) -> Result<usize, StudioError> {
+        self.studios
+            .iter()
+            .position(|studio| studio.name == name)
+            .ok_or_else(|| StudioError::Unknown(name.to_string()))
+    }
+
+    // Moves the artist to `name`, wherever they worked before.
+    pub fn assign(&mut self, artist_id: usize, name: &str) -> Result<(), StudioError> {
+        let index = self.index(name)?;
+        self.assignments.insert(artist_id, index);
+        Ok(())
+    }
+
+    // Moves `count` units of a tool from one studio's shelf to another's.
+    // Either both stocks change or neither does: the source must have the
+    // units on its shelf and not reserved, and the destination room for
+    // them. The source logs the move as `TransferOut`, the destination as
+    // `TransferIn`.
+    pub fn transfer(
+        &self,
+        tool: &str,
+        count: usize,
+        from: &str,
+        to: &str,
+    ) -> Result<(), StudioError> {
+        let (source, destination) = (self.index(from)?, self.index(to)?);
+        if source == destination {
+            return Err(StudioError::SameStudio(from.to_string()));
+        }
+        // Registries are locked in studio order, so two transfers going
+        // opposite ways can't deadlock.
+        let lock = |index: usize| {
+            self.studios[index]
+                .registry
+                .lock()
+                .expect("Failed to lock registry")
+        };
+        let (mut sender, mut receiver) = if source < destination {
+            let sender = lock(source);
+            (sender, lock(destination))
+        } else {
+            let receiver = lock(destination);
+            (lock(source), receiver)
+        };
+        let now = sender.now();
+        {
+            let mut sent = sender
+                .shared_resources
+                .lock()
+                .expect("Failed to lock resources");
+            let mut received = receiver
+                .shared_resources
+                .lock()
+                .expect("Failed to lock resources");
+            let on_shelf = sent.stock(tool).get();
+            if on_shelf < count {
+                return Err(StudioError::Registry(RegistryError::NotOnShelf {
+                    tool: tool.to_string(),
+                    on_shelf,
+                    requested: count,
+                    on_loan: sent.loan_caps.on_loan(tool),
+                }));
+            }
+            if on_shelf < count + sender.reservations.held(tool, now) {
+                return Err(StudioError::Registry(RegistryError::ReservedForOthers(
+                    tool.to_string(),
+                )));
+            }
+            received
+                .receive(&[(tool.to_string(), Count::of(count).into())])
+                .map_err(StudioError::Resource)?;
+            sent.set_quantity(tool, Count::of(on_shelf - count))
+                .map_err(StudioError::Resource)?;
+        }
+        let moved = [(tool.to_string(), Count::of(count))];
+        sender.record_amounts(None, State::TransferOut, &moved, now);
+        let arrived = receiver.now();
+        receiver.record_amounts(None, State::TransferIn, &moved, arrived);
+        Ok(())
+    }
+
+    // Spreads artists `0..artists` not yet assigned over the studios in turn.
+    pub fn assign_remaining(&mut self, artists: usize) {
+        if self.studios.is_empty() {
+            return;
+        }
+        let mut next = 0;
+        for id in 0..artists {
+            if !self.assignments.contains_key(&id) {
+                self.assignments.insert(id, next % self.studios.len());
+                next += 1;
+            }
+        }
+    }
+
+    pub fn studio_of(&self, artist_id: usize) -> Option<&Studio> {
+        self.studios.get(*self.assignments.get(&artist_id)?)
+    }
+
+    // The artists working in `name`, by id.
+    pub fn artists(&self, name: &str) -> Vec<usize> {
+        let Some(index) = self.studios.iter().position(|studio| studio.name == name) else {
+            return vec![];
+        };
+        self.assignments
+            .iter()
+            .filter(|&(_, &studio)| studio == index)
+            .map(|(&id, _)| id)
+            .collect()
+    }
+
+    // Each studio's run summary, in the order they were added.
+    pub fn summaries(&self) -> Vec<(String, RunSummary)> {
+        self.studios
+            .iter()
+            .map(|studio| {
+                let registry = studio.registry.lock().expect("Failed to lock registry");
+                (studio.name.clone(), RunSummary::capture(&registry, 0))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::{self, SimulationConfig};
+
+    #[test]
+    fn test_artists_are_assigned_to_separate_studios() {
+        let mut studios = Studios::default();
+        studios
+            .add(Studio::new("north", SharedResources::default()))
+            .unwrap();
+        studios
+            .add(Studio::new("south", SharedResources::default()))
+            .unwrap();
+        assert_eq!(
+            studios.add(Studio::new("north", SharedResources::default())),
+            Err(StudioError::Duplicate("north".to_string()))
+        );
+        assert_eq!(
+            studios.assign(1, "east"),
+            Err(StudioError::Unknown("east".to_string()))
+        );
+
+        studios.assign(4, "south").unwrap();
+        studios.assign(0, "south").unwrap();
+        studios.assign_remaining(4);
+        assert_eq!(studios.artists("north"), vec![1, 3]);
+        assert_eq!(studios.artists("south"), vec![0, 2, 4]);
+        assert_eq!(studios.studio_of(3).unwrap().name, "north");
+        assert!(studios.studio_of(9).is_none());
+
+        let north = studios.get("north").unwrap();
+        north
+            .registry
+            .lock()
+            .unwrap()
+            .tool_registry(1, vec!["brush".to_string()])
+            .unwrap();
+        let south = studios.get("south").unwrap();
+        assert_eq!(
+            south.resources.lock().unwrap().stock("brush"),
+            north.resources.lock().unwrap().stock("brush") + Count(1)
+        );
+        let summaries = studios.summaries();
+        assert_eq!((summaries[0].1.checkouts, summaries[1].1.checkouts), (1, 0));
+    }
+
+    #[test]
+    fn test_transfer_moves_stock_between_studios() {
+        let mut studios = Studios::default();
+        for name in ["north", "south"] {
+            studios
+                .add(Studio::new(name, SharedResources::default()))
+                .unwrap();
+        }
+        let stock = |name: &str| {
+            studios
+                .get(name)
+                .unwrap()
+                .resources
+                .lock()
+                .unwrap()
+                .stock("brush")
+                .get()
+        };
+        let before = stock("north");
+
+        studios.transfer("brush", 2, "north", "south").unwrap();
+        assert_eq!((stock("north"), stock("south")), (before - 2, before + 2));
+        for (name, kind) in [("north", State::TransferOut), ("south", State::TransferIn)] {
+            let registry = studios.get(name).unwrap().registry.lock().unwrap();
+            let event = registry.events.last().unwrap();
+            assert_eq!(
+                (event.kind, event.quantities["brush"]),
+                (kind, Count(2).into())
+            );
+        }
+
+        assert_eq!(
+            studios.transfer("brush", before, "north", "south"),
+            Err(StudioError::Registry(RegistryError::NotOnShelf {
+                tool: "brush".to_string(),
+                on_shelf: before - 2,
+                requested: before,
+                on_loan: 0,
+            }))
+        );
+        assert_eq!((stock("north"), stock("south")), (before - 2, before + 2));
+        assert_eq!(
+            studios.transfer("brush", 1, "south", "south"),
+            Err(StudioError::SameStudio("south".to_string()))
+        );
+        assert_eq!(
+            studios.transfer("brush", 1, "east", "south"),
+            Err(StudioError::Unknown("east".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_run_studios_keeps_each_studio_to_its_own_artists() {
+        let mut studios = Studios::default();
+        for name in ["north", "south"] {
+            studios
+                .add(Studio::new(name, SharedResources::default()))
+                .unwrap();
+        }
+        let config = SimulationConfig {
+            artists: 3,
+            seed: Some(7),
+            quiet: true,
+            ..SimulationConfig::default()
+        };
+        let errors = simulation::run_studios(&mut studios, &config);
+        assert!(errors.iter().all(Vec::is_empty));
+        let checkouts: Vec<usize> = studios
+            .summaries()
+            .iter()
+            .map(|(_, summary)| summary.checkouts)
+            .collect();
+        assert_eq!(checkouts, vec![2, 1]);
+    }
+}
@@ -0,0 +1,302 @@
+use crate::{
+    crdt::{AuditItem, CrdtInventory},
+    dump::{DumpEntry, StateDump},
+    loan_caps::QueuedCheckout,
+    units::{Count, Kilograms},
+};
+use std::{collections::BTreeMap, fmt, fs, io, path::Path};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncOp {
+    SetTool { name: String, quantity: Count },
+    RemoveTool { name: String },
+    SetPaint { name: String, quantity: Kilograms },
+    RemovePaint { name: String },
+    SetOnLoan { tool: String, count: usize },
+    // Drops history entries past `keep` where the two registries diverged.
+    TruncateEntries { keep: usize },
+    AppendEntry(DumpEntry),
+    ReplaceQueue(Vec<QueuedCheckout>),
+}
+
+impl fmt::Display for SyncOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyncOp::SetTool { name, quantity } => write!(f, "set tool '{}' to {}", name, quantity),
+            SyncOp::RemoveTool { name } => write!(f, "remove tool '{}'", name),
+            SyncOp::SetPaint { name, quantity } => {
+                write!(f, "set paint '{}' to {}", name, quantity)
+            }
+            SyncOp::RemovePaint { name } => write!(f, "remove paint '{}'", name),
+            SyncOp::SetOnLoan { tool, count } => write!(f, "set '{}' on loan to {}", tool, count),
+            SyncOp::TruncateEntries { keep } => write!(f, "truncate history to {} entries", keep),
+            SyncOp::AppendEntry(entry) => {
+                write!(f, "append artist {}", entry.artist_id)?;
+                if let Some(state) = entry.state {
+                    write!(f, " {:?}", state)?;
+                }
+                write!(f, " {}", entry.tools.join(", "))
+            }
+            SyncOp::ReplaceQueue(queue) => {
+                write!(f, "replace checkout queue ({} waiting)", queue.len())
+            }
+        }
+    }
+}
+
+// The smallest set of operations that turns `to` into `from`. History is
+// append-only, so only the entries after the longest common prefix are sent.
+pub fn diff(from: &StateDump, to: &StateDump) -> Vec<SyncOp> {
+    let mut ops = vec![];
+    diff_stock(
+        &from.tools,
+        &to.tools,
+        &mut ops,
+        |name, quantity| match quantity {
+            Some(quantity) => SyncOp::SetTool { name, quantity },
+            None => SyncOp::RemoveTool { name },
+        },
+    );
+    diff_stock(
+        &from.paints,
+        &to.paints,
+        &mut ops,
+        |name, quantity| match quantity {
+            Some(quantity) => SyncOp::SetPaint { name, quantity },
+            None => SyncOp::RemovePaint { name },
+        },
+    );
+    diff_stock(&from.on_loan, &to.on_loan, &mut ops, |tool, count| {
+        SyncOp::SetOnLoan {
+            tool,
+            count: count.unwrap_or(0),
+        }
+    });
+
+    let common = from
+        .entries
+        .iter()
+        .zip(&to.entries)
+        .take_while(|(a, b)| a == b)
+        .count();
+    if common < to.entries.len() {
+        ops.push(SyncOp::TruncateEntries { keep: common });
+    }
+    ops.extend(
+        from.entries[common..]
+            .iter()
+            .cloned()
+            .map(SyncOp::AppendEntry),
+    );
+
+    if from.queued != to.queued {
+        ops.push(SyncOp::ReplaceQueue(from.queued.clone()));
+    }
+    ops
+}
+
+fn diff_stock<Q: Copy + PartialEq>(
+    from: &[(String, Q)],
+    to: &[(String, Q)],
+    ops: &mut Vec<SyncOp>,
+    op: impl Fn(String, Option<Q>) -> SyncOp,
+) {
+    let from: BTreeMap<_, _> = from
+        .iter()
+        .map(|(name, quantity)| (name, *quantity))
+        .collect();
+    let to: BTreeMap<_, _> = to
+        .iter()
+        .map(|(name, quantity)| (name, *quantity))
+        .collect();
+    for (name, &quantity) in &from {
+        if to.get(name) != Some(&quantity) {
+            ops.push(op(name.to_string(), Some(quantity)));
+        }
+    }
+    for name in to.keys().filter(|name| !from.contains_key(*name)) {
+        ops.push(op(name.to_string(), None));
+    }
+}
+
+pub fn apply(state: &mut StateDump, ops: &[SyncOp]) {
+    for op in ops {
+        match op {
+            SyncOp::SetTool { name, quantity } => set(&mut state.tools, name, *quantity),
+            SyncOp::RemoveTool { name } => state.tools.retain(|(tool, _)| tool != name),
+            SyncOp::SetPaint { name, quantity } => set(&mut state.paints, name, *quantity),
+            SyncOp::RemovePaint { name } => state.paints.retain(|(paint, _)| paint != name),
+            SyncOp::SetOnLoan { tool, count: 0 } => state.on_loan.retain(|(name, _)| name != tool),
+            SyncOp::SetOnLoan { tool, count } => {
+                set(&mut state.on_loan, tool, *count);
+                state.on_loan.sort();
+            }
+            SyncOp::TruncateEntries { keep } => state.entries.truncate(*keep),
+            SyncOp::AppendEntry(entry) => state.entries.push(entry.clone()),
+            SyncOp::ReplaceQueue(queue) => state.queued = queue.clone(),
+        }
+    }
+}
+
+fn set<Q>(stock: &mut Vec<(String, Q)>, name: &str, quantity: Q) {
+    match stock.iter_mut().find(|(item, _)| item == name) {
+        Some((_, current)) => *current = quantity,
+        None => stock.push((name.to_string(), quantity)),
+    }
+}
+
+// Starts an offline branch of `state` named `replica`: the same inventory,
+// with counters that follow whatever the branch does to it from here on.
+pub fn fork(state: &StateDump, replica: &str) -> StateDump {
+    let counts = |stock: &[(String, Count)]| -> Vec<(String, usize)> {
+        stock
+            .iter()
+            .map(|(item, count)| (item.clone(), count.get()))
+            .collect()
+    };
+    let on_loan = state.on_loan.clone();
+    let mut branch = state.clone();
+    branch.branch = Some(CrdtInventory::seeded(
+        replica,
+        &counts(&state.tools),
+        &on_loan,
+    ));
+    branch
+}
+
+// Folds branch `other` into branch `into`: their counters merge, shelf
+// stock and loans are set from the merged counts, and history `into`
+// hasn't seen is appended. Returns an audit item for every tool the two
+// branches lent out more of than there was; None unless both are branches.
+pub fn merge(into: &mut StateDump, other: &StateDump) -> Option<Vec<AuditItem>> {
+    let theirs = other.branch.as_ref()?;
+    let ours = into.branch.as_mut()?;
+    let audit = ours.merge(theirs);
+    let stock: Vec<(String, i64)> = ours
+        .items()
+        .map(|(item, quantity)| (item.to_string(), quantity))
+        .collect();
+    let loans: Vec<(String, i64)> = ours
+        .loans()
+        .map(|(item, count)| (item.to_string(), count))
+        .collect();
+    for (item, quantity) in stock {
+        set(&mut into.tools, &item, Count::of(quantity.max(0) as usize));
+    }
+    into.on_loan = loans
+        .into_iter()
+        .filter(|(_, count)| *count > 0)
+        .map(|(item, count)| (item, count as usize))
+        .collect();
+    let common = into
+        .entries
+        .iter()
+        .zip(&other.entries)
+        .take_while(|(a, b)| a == b)
+        .count();
+    into.entries.extend(other.entries[common..].iter().cloned());
+    Some(audit)
+}
+
+// Reads a state file written by `ctl dump`, a SIGUSR1 dump, or a daemon
+// checkpoint (which wraps the state in a `state` field).
+pub fn load_state(path: &Path) -> io::Result<StateDump> {
+    let mut value: serde_json::Value = serde_json::from_str(&fs::read_to_string(path)?)?;
+    if let Some(state) = value.get_mut("state") {
+        value = state.take();
+    }
+    Ok(serde_json::from_value(value)?)
+}
+
+pub fn save_state(path: &Path, state: &StateDump) -> io::Result<()> {
+    fs::write(path, state.to_json()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArtistToolRegistry, SharedResources};
+    use std::sync::{Arc, Mutex};
+
+    fn registry() -> ArtistToolRegistry {
+        ArtistToolRegistry::new(&Arc::new(Mutex::new(SharedResources::default())))
+    }
+
+    #[test]
+    fn test_diff_sends_only_new_history() {
+        let mut source = registry();
+        source.tool_registry(1, vec!["brush".to_string()]).unwrap();
+        let mut backup = StateDump::capture(&source);
+        source.tool_registry(2, vec!["tape".to_string()]).unwrap();
+        let current = StateDump::capture(&source);
+
+        let ops = diff(&current, &backup);
+        assert_eq!(ops.len(), 3);
+        assert_eq!(
+            ops[0],
+            SyncOp::SetTool {
+                name: "tape".to_string(),
+                quantity: Count(9)
+            }
+        );
+        assert!(matches!(&ops[2], SyncOp::AppendEntry(entry) if entry.artist_id == 2));
+
+        apply(&mut backup, &ops);
+        assert!(diff(&current, &backup).is_empty());
+    }
+
+    #[test]
+    fn test_diff_truncates_diverged_history_and_removes_stock() {
+        let mut a = StateDump::capture(&registry());
+        let mut b = a.clone();
+        b.tools.push(("easel".to_string(), Count(1)));
+        let mut other = registry();
+        other.tool_registry(9, vec!["rags".to_string()]).unwrap();
+        b.entries = StateDump::capture(&other).entries;
+        a.paints.retain(|(paint, _)| paint != "pink");
+
+        let ops = diff(&a, &b);
+        assert!(ops.contains(&SyncOp::RemoveTool {
+            name: "easel".to_string()
+        }));
+        assert!(ops.contains(&SyncOp::RemovePaint {
+            name: "pink".to_string()
+        }));
+        assert!(ops.contains(&SyncOp::TruncateEntries { keep: 0 }));
+
+        apply(&mut b, &ops);
+        assert!(diff(&a, &b).is_empty());
+    }
+
+    #[test]
+    fn test_offline_branches_merge_and_report_the_oversold_unit() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        resources
+            .lock()
+            .unwrap()
+            .restock("<easel>", Count(1))
+            .unwrap();
+        let studio = StateDump::capture(&ArtistToolRegistry::new(&resources));
+
+        let branch = |replica: &str, artist_id: usize| {
+            let resources = Arc::new(Mutex::new(SharedResources::default()));
+            let mut registry = fork(&studio, replica).restore(&resources);
+            registry
+                .tool_registry(artist_id, vec!["brush".to_string(), "<easel>".to_string()])
+                .unwrap();
+            StateDump::capture(&registry)
+        };
+        let mut north = branch("north", 1);
+        let south = branch("south", 2);
+        assert!(merge(&mut north.clone(), &studio).is_none());
+
+        let audit = merge(&mut north, &south).unwrap();
+        assert_eq!(audit.len(), 1);
+        assert_eq!(audit[0].item, "<easel>");
+        assert_eq!(audit[0].shortfall, 1);
+        let brushes = north.tools.iter().find(|(tool, _)| tool == "brush");
+        assert_eq!(brushes, Some(&("brush".to_string(), Count(8))));
+        assert!(north.on_loan.contains(&("brush".to_string(), 2)));
+        assert_eq!(north.entries.len(), 2);
+    }
+}
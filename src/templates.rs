@@ -0,0 +1,519 @@
+use crate::{
+    alerts::LowStockThresholds,
+    catalog::Catalog,
+    deliveries::StorageCapacity,
+    expiry::{PaintBatch, PaintBatches},
+    i18n::Locale,
+    loan_caps::{CapPolicy, LoanCaps},
+    stock::{Stock, Stocked},
+    tool_limits::ToolLimits,
+    units::{Amount, Count, Kilograms},
+    SharedResources,
+};
+use serde::{Deserialize, Serialize};
+use std::{fs, io, path::Path};
+use tracing::level_filters::LevelFilter;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StockItem {
+    pub name: String,
+    pub quantity: Amount,
+    // Reorder once stock falls below this; checkouts that cross it alert.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reorder_below: Option<Amount>,
+    // Most the storeroom holds; deliveries beyond it are refused.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capacity: Option<Amount>,
+    // Tools only; unset means `stock::DEFAULT_CATEGORY`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    // Tools only.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    // Other names the tool is asked for by; tools only.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+}
+
+impl StockItem {
+    // Every amount it lists, in the order they appear.
+    fn amounts(&self) -> impl Iterator<Item = Amount> {
+        [Some(self.quantity), self.reorder_below, self.capacity]
+            .into_iter()
+            .flatten()
+    }
+
+    // Paint written in whole kilograms reads back as a count.
+    fn in_kilograms(&mut self) {
+        fn kg(amount: Amount) -> Amount {
+            match amount {
+                Amount::Count(count) => Kilograms::whole(count.0).into(),
+                amount => amount,
+            }
+        }
+        self.quantity = kg(self.quantity);
+        self.reorder_below = self.reorder_below.map(kg);
+        self.capacity = self.capacity.map(kg);
+    }
+}
+
+// A set of tools usually checked out together, e.g. a student's starter set.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Kit {
+    pub name: String,
+    pub tools: Vec<String>,
+}
+
+// What a studio stocks; written by `init --template` and readable by the
+// daemon with `--studio`. Paint quantities are in kilograms.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StudioConfig {
+    pub name: String,
+    // Overrides LANG for messages, e.g. `es`; `--lang` overrides this.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+    #[serde(default)]
+    pub tools: Vec<StockItem>,
+    #[serde(default)]
+    pub paints: Vec<StockItem>,
+    #[serde(default)]
+    pub kits: Vec<Kit>,
+    // Dated batches making up part of the paint stock.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub batches: Vec<PaintBatch>,
+    // Days a loan runs before it is overdue; `--loan-days` overrides this.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loan_days: Option<i64>,
+    // How many tools each artist may hold, laid out as in a `--tool-limits`
+    // file, which overrides this.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quotas: Option<ToolLimits>,
+    // The most detailed log lines written: `error`, `warn`, `info`, `debug`
+    // or `trace`; unset writes everything.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_level: Option<String>,
+}
+
+impl StudioConfig {
+    pub fn resources(&self) -> SharedResources {
+        fn stock<T: Stocked>(
+            items: &[StockItem],
+            measured: fn(Amount) -> Option<T::Quantity>,
+        ) -> Stock<T> {
+            items
+                .iter()
+                .filter_map(|item| Some((item.name.clone(), measured(item.quantity)?)))
+                .collect()
+        }
+        let (capacity, low_stock) = self.limits();
+        let mut resources = SharedResources {
+            tools: stock(&self.tools, Amount::count),
+            paints: stock(&self.paints, Amount::kilograms),
+            loan_caps: LoanCaps::new(CapPolicy::Fail),
+            capacity,
+            low_stock,
+            batches: PaintBatches::default(),
+            catalog: Catalog::default(),
+        };
+        resources.set_catalog(self.catalog());
+        for batch in &self.batches {
+            resources.add_batch(batch.clone());
+        }
+        resources
+    }
+
+    // The categories, tags and aliases the file gives its tools.
+    pub fn catalog(&self) -> Catalog {
+        let mut catalog = Catalog::default();
+        for item in &self.tools {
+            if let Some(category) = &item.category {
+                catalog.set_category(&item.name, category);
+            }
+            for tag in &item.tags {
+                catalog.add_tag(&item.name, tag);
+            }
+            for alias in &item.aliases {
+                catalog.add_alias(alias, &item.name);
+            }
+        }
+        catalog
+    }
+
+    // The level `log_level` names; everything if it is unset.
+    pub fn log_level(&self) -> LevelFilter {
+        self.log_level
+            .as_deref()
+            .and_then(|level| level.parse().ok())
+            .unwrap_or(LevelFilter::TRACE)
+    }
+
+    // The storage capacities and reorder thresholds the file sets.
+    pub fn limits(&self) -> (StorageCapacity, LowStockThresholds) {
+        let mut capacity = StorageCapacity::default();
+        let mut low_stock = LowStockThresholds::default();
+        for item in self.tools.iter().chain(&self.paints) {
+            if let Some(max) = item.capacity {
+                capacity.set(&item.name, max);
+            }
+            if let Some(threshold) = item.reorder_below {
+                low_stock.set(&item.name, threshold);
+            }
+        }
+        (capacity, low_stock)
+    }
+
+    pub fn to_toml(&self) -> Result<String, toml::ser::Error> {
+        toml::to_string_pretty(self)
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+
+    // Quantities are unsigned, so a negative one is refused while parsing.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut config: Self = toml::from_str(text).map_err(|error| error.to_string())?;
+        for paint in &mut config.paints {
+            paint.in_kilograms();
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    // Every tool and paint needs a distinct name, since stock is looked up by
+    // name alone, tools whole units and paints kilograms, no more stock than
+    // its capacity, kits may only list stocked tools, and batches may not add
+    // up to more than a paint's stock. Loans run at least a day, and quotas
+    // follow the `--tool-limits` rules.
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(locale) = &self.locale {
+            if Locale::from_tag(locale).is_none() {
+                return Err(format!("locale '{}' isn't supported", locale));
+            }
+        }
+        if let Some(level) = &self.log_level {
+            if level.parse::<LevelFilter>().is_err() {
+                return Err(format!(
+                    "log level '{}' isn't one of error, warn, info, debug or trace",
+                    level
+                ));
+            }
+        }
+        if self.loan_days.is_some_and(|days| days < 1) {
+            return Err("loans must run at least a day".to_string());
+        }
+        if let Some(quotas) = &self.quotas {
+            quotas
+                .validate()
+                .map_err(|error| format!("quotas: {}", error))?;
+        }
+        if let Some(tool) = self
+            .tools
+            .iter()
+            .find(|tool| tool.amounts().any(|amount| amount.count().is_none()))
+        {
+            return Err(format!(
+                "'{}' is a tool, so comes in whole units",
+                tool.name
+            ));
+        }
+        if let Some(paint) = self
+            .paints
+            .iter()
+            .find(|paint| paint.amounts().any(|amount| amount.kilograms().is_none()))
+        {
+            return Err(format!("'{}' is a paint, so comes in kg", paint.name));
+        }
+        let mut seen: Vec<&str> = vec![];
+        for item in self.tools.iter().chain(&self.paints) {
+            let name = item.name.trim();
+            if name.is_empty() {
+                return Err("an item has an empty name".to_string());
+            }
+            if seen.contains(&name) {
+                return Err(format!("'{}' is listed more than once", name));
+            }
+            seen.push(name);
+            if item
+                .capacity
+                .is_some_and(|capacity| item.quantity > capacity)
+            {
+                return Err(format!("'{}' has more stock than its capacity", name));
+            }
+        }
+        for kit in &self.kits {
+            if let Some(tool) = kit
+                .tools
+                .iter()
+                .find(|tool| !self.tools.iter().any(|item| item.name == **tool))
+            {
+                return Err(format!("kit '{}' uses unknown tool '{}'", kit.name, tool));
+            }
+        }
+        for paint in &self.paints {
+            let batched: Kilograms = self
+                .batches
+                .iter()
+                .filter(|batch| batch.color == paint.name)
+                .map(|batch| batch.kg)
+                .sum();
+            if Amount::from(batched) > paint.quantity {
+                return Err(format!(
+                    "'{}' has more paint in batches than in stock",
+                    paint.name
+                ));
+            }
+        }
+        if let Some(batch) = self
+            .batches
+            .iter()
+            .find(|batch| !self.paints.iter().any(|paint| paint.name == batch.color))
+        {
+            return Err(format!(
+                "batch '{}' is of unknown paint '{}'",
+                batch.batch, batch.color
+            ));
+        }
+        Ok(())
+    }
+}
+
+pub const DEFAULT_STUDIO: &str = "studio.toml";
+
+pub const TEMPLATES: [&str; 4] = [
+    "watercolor-classroom",
+    "oil-studio",
+    "sculpture-workshop",
+    "print-shop",
+];
+
+pub fn template(name: &str) -> Option<StudioConfig> {
+    let (tools, paints, kits): (&[_], &[_], &[(&str, &[&str])]) = match name {
+        "watercolor-classroom" => (
+            &[
+                ("round brush", 30, 10),
+                ("flat brush", 30, 10),
+                ("palette", 30, 5),
+                ("water container", 30, 5),
+                ("sponges", 20, 5),
+                ("tape", 15, 4),
+                ("rags", 20, 5),
+            ],
+            &[
+                ("red", 3, 1),
+                ("blue", 3, 1),
+                ("yellow", 3, 1),
+                ("green", 2, 1),
+                ("black", 1, 0),
+                ("white", 2, 1),
+            ],
+            &[
+                (
+                    "student set",
+                    &["round brush", "palette", "water container"],
+                ),
+                ("wash set", &["flat brush", "sponges", "tape"]),
+            ],
+        ),
+        "oil-studio" => (
+            &[
+                ("brush", 20, 6),
+                ("palette knife", 10, 3),
+                ("palette", 12, 3),
+                ("easel", 8, 0),
+                ("canvas", 40, 10),
+                ("rags", 30, 10),
+                ("solvent jar", 8, 2),
+            ],
+            &[
+                ("titanium white", 8, 2),
+                ("ivory black", 3, 1),
+                ("cadmium red", 3, 1),
+                ("ultramarine blue", 3, 1),
+                ("yellow ochre", 3, 1),
+                ("burnt umber", 3, 1),
+            ],
+            &[
+                ("oil basics", &["brush", "palette", "palette knife", "rags"]),
+                ("easel painting", &["easel", "canvas", "solvent jar"]),
+            ],
+        ),
+        "sculpture-workshop" => (
+            &[
+                ("sculpting tool", 25, 8),
+                ("loop tool", 15, 4),
+                ("wire cutter", 6, 2),
+                ("modeling stand", 8, 0),
+                ("spray bottle", 10, 3),
+                ("sponges", 15, 5),
+                ("rags", 20, 5),
+            ],
+            &[
+                ("grey clay", 50, 15),
+                ("terracotta clay", 30, 10),
+                ("slip", 5, 2),
+            ],
+            &[
+                ("hand building", &["sculpting tool", "loop tool", "sponges"]),
+                (
+                    "stand work",
+                    &["modeling stand", "wire cutter", "spray bottle"],
+                ),
+            ],
+        ),
+        "print-shop" => (
+            &[
+                ("brayer", 12, 4),
+                ("baren", 10, 3),
+                ("carving set", 12, 4),
+                ("inking slab", 8, 2),
+                ("press blanket", 4, 0),
+                ("rags", 25, 8),
+            ],
+            &[
+                ("black relief ink", 4, 1),
+                ("red relief ink", 2, 1),
+                ("blue relief ink", 2, 1),
+                ("extender", 2, 1),
+            ],
+            &[
+                (
+                    "linocut",
+                    &["carving set", "brayer", "inking slab", "baren"],
+                ),
+                ("press run", &["press blanket", "brayer", "rags"]),
+            ],
+        ),
+        _ => return None,
+    };
+
+    fn items<Q: Into<Amount>>(items: &[(&str, u32, u32)], unit: fn(u32) -> Q) -> Vec<StockItem> {
+        items
+            .iter()
+            .map(|&(name, quantity, reorder_below)| StockItem {
+                name: name.to_string(),
+                quantity: unit(quantity).into(),
+                reorder_below: (reorder_below > 0).then(|| unit(reorder_below).into()),
+                capacity: None,
+                category: None,
+                tags: vec![],
+                aliases: vec![],
+            })
+            .collect()
+    }
+    Some(StudioConfig {
+        name: name.to_string(),
+        locale: None,
+        tools: items(tools, Count),
+        paints: items(paints, Kilograms::whole),
+        kits: kits
+            .iter()
+            .map(|(name, tools)| Kit {
+                name: name.to_string(),
+                tools: tools.iter().map(|tool| tool.to_string()).collect(),
+            })
+            .collect(),
+        batches: vec![],
+        loan_days: None,
+        quotas: None,
+        log_level: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_template_is_consistent() {
+        for name in TEMPLATES {
+            let config = template(name).unwrap();
+            assert!(!config.tools.is_empty(), "{}", name);
+            assert_eq!(config.validate(), Ok(()), "{}", name);
+        }
+        assert_eq!(template("pottery"), None);
+    }
+
+    #[test]
+    fn test_parse_rejects_duplicate_and_negative_stock() {
+        let studio = |tools: &str| format!("name = \"test\"\n{}", tools);
+        let item = |name: &str, quantity: i64| {
+            format!("[[tools]]\nname = \"{}\"\nquantity = {}\n", name, quantity)
+        };
+        assert!(StudioConfig::parse(&studio(&(item("brush", 2) + &item("tape", 1)))).is_ok());
+        assert_eq!(
+            StudioConfig::parse(&studio(&(item("brush", 2) + &item("brush", 1)))),
+            Err("'brush' is listed more than once".to_string())
+        );
+        assert!(StudioConfig::parse(&studio(&item("brush", -1))).is_err());
+        assert_eq!(
+            StudioConfig::parse(&studio("locale = \"es_ES.UTF-8\"\n"))
+                .unwrap()
+                .locale
+                .as_deref()
+                .and_then(Locale::from_tag),
+            Some(Locale::Spanish)
+        );
+        assert_eq!(
+            StudioConfig::parse(&studio("locale = \"xx\"\n")),
+            Err("locale 'xx' isn't supported".to_string())
+        );
+        assert!(StudioConfig::parse(&studio("loan_days = 0\n")).is_err());
+        assert!(StudioConfig::parse(&studio("log_level = \"loud\"\n")).is_err());
+        assert!(StudioConfig::parse(&studio("[quotas]\nmin = 3\nmax = 1\n")).is_err());
+
+        let mut config = template("print-shop").unwrap();
+        config.paints.push(config.tools[0].clone());
+        assert!(config.validate().is_err());
+        let mut config = template("print-shop").unwrap();
+        config.tools[0].capacity =
+            Some(Count(config.tools[0].quantity.count().unwrap().0 - 1).into());
+        assert!(config.validate().is_err());
+        let mut config = template("print-shop").unwrap();
+        config.tools[0].quantity = Kilograms::grams(1_500).into();
+        assert_eq!(
+            config.validate(),
+            Err("'brayer' is a tool, so comes in whole units".to_string())
+        );
+        let mut config = template("print-shop").unwrap();
+        config.batches.push(PaintBatch {
+            color: config.paints[0].name.clone(),
+            batch: "A1".to_string(),
+            kg: config.paints[0].quantity.kilograms().unwrap() + Kilograms::whole(1),
+            expires: chrono::Utc::now(),
+        });
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_template_round_trips_through_toml() {
+        let mut config = template("oil-studio").unwrap();
+        config.paints[0].capacity = Some(Kilograms::whole(50).into());
+        config.loan_days = Some(7);
+        config.quotas = Some(ToolLimits::new(1, 4));
+        config.log_level = Some("warn".to_string());
+        let text = config.to_toml().unwrap();
+        assert!(text.contains("[[kits]]"));
+        let parsed: StudioConfig = toml::from_str(&text).unwrap();
+        assert_eq!(parsed, config);
+        assert_eq!(parsed.log_level(), LevelFilter::WARN);
+
+        let resources = parsed.resources();
+        assert_eq!(
+            resources.tools.amounts()[4],
+            ("canvas".to_string(), Count(40))
+        );
+        assert_eq!(resources.paints.len(), 6);
+        assert_eq!(
+            resources.capacity.get(&config.paints[0].name),
+            Some(Kilograms::whole(50).into())
+        );
+        assert_eq!(resources.low_stock.get("canvas"), Some(Count(10).into()));
+
+        // Paint written in whole kilograms is still paint.
+        let text = "name = \"test\"\n[[paints]]\nname = \"red\"\nquantity = 2\n";
+        let resources = StudioConfig::parse(text).unwrap().resources();
+        assert_eq!(resources.paints.quantity("red"), Kilograms::whole(2));
+    }
+}
@@ -0,0 +1,139 @@
+use crate::{ArtistToolRegistry, State};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::fmt::Write;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TimelineEntry {
+    pub datetime: Option<DateTime<Utc>>,
+    pub state: Option<State>,
+    // None when the studio took the unit in rather than anyone moving it.
+    pub artist_id: Option<usize>,
+}
+
+// Chronological history of one unit of a tool, from when it was taken into
+// stock to wherever it is now.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ToolTimeline {
+    pub tool: String,
+    pub serial: usize,
+    pub entries: Vec<TimelineEntry>,
+}
+
+impl ToolTimeline {
+    pub fn to_text(&self) -> String {
+        let mut text = format!("Timeline for '{}' #{}\n", self.tool, self.serial);
+        for entry in &self.entries {
+            let datetime = entry
+                .datetime
+                .map(|datetime| datetime.to_rfc3339())
+                .unwrap_or_else(|| "-".to_string());
+            let state = entry
+                .state
+                .map(|state| format!("{:?}", state))
+                .unwrap_or_else(|| "-".to_string());
+            let artist = entry
+                .artist_id
+                .map(|id| format!("artist {}", id))
+                .unwrap_or_else(|| "studio".to_string());
+            let _ = writeln!(text, "{}  {:<10} {}", datetime, state, artist);
+        }
+        text
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+impl ArtistToolRegistry {
+    // Unit `serial` of `tool`: its intake from the event log, then every
+    // history entry that moved it. None if no such unit was ever numbered.
+    pub fn tool_timeline(&self, tool: &str, serial: usize) -> Option<ToolTimeline> {
+        self.item(tool, serial)?;
+        let logged = self.events.symbol(tool);
+        let intake = self
+            .events
+            .events()
+            .iter()
+            .filter(|event| {
+                event.artist_id.is_none()
+                    && matches!(event.kind, State::New | State::Fill | State::TransferIn)
+                    && logged
+                        .and_then(|symbol| event.serials.get(&symbol))
+                        .is_some_and(|serials| serials.contains(&serial))
+            })
+            .map(|event| TimelineEntry {
+                datetime: Some(event.at),
+                state: Some(State::New),
+                artist_id: None,
+            });
+        let symbol = self.interner.get(tool);
+        let moves = self
+            .history_for_tool(tool)
+            .filter(|entry| {
+                entry
+                    .preferred_tools
+                    .iter()
+                    .zip(&entry.serials)
+                    .any(|(&moved, &unit)| Some(moved) == symbol && unit == serial)
+            })
+            .map(|entry| TimelineEntry {
+                datetime: entry.datetime,
+                state: entry.state,
+                artist_id: Some(entry.artist_id),
+            });
+        let mut entries: Vec<TimelineEntry> = intake.chain(moves).collect();
+        entries.sort_by_key(|entry| entry.datetime);
+        Some(ToolTimeline {
+            tool: tool.to_string(),
+            serial,
+            entries,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{units::Count, ArtistToolRegistry, SharedResources, State};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_tool_timeline_follows_one_unit() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        registry.restock("kiln", Count(2)).unwrap();
+        registry
+            .tool_registry(1, vec!["kiln".to_string(), "tape".to_string()])
+            .unwrap();
+        registry
+            .tool_registry(2, vec!["kiln".to_string(), "tape".to_string()])
+            .unwrap();
+        registry.report_damage(1, "kiln").unwrap();
+        registry.retire(1, "kiln").unwrap();
+
+        let timeline = registry.tool_timeline("kiln", 1).unwrap();
+        let steps: Vec<(Option<State>, Option<usize>)> = timeline
+            .entries
+            .iter()
+            .map(|entry| (entry.state, entry.artist_id))
+            .collect();
+        assert_eq!(
+            steps,
+            vec![
+                (Some(State::New), None),
+                (Some(State::TakeOut), Some(1)),
+                (Some(State::Damage), Some(1)),
+                (Some(State::Retire), Some(1)),
+            ]
+        );
+        assert!(timeline.to_text().starts_with("Timeline for 'kiln' #1"));
+        let second = registry.tool_timeline("kiln", 2).unwrap();
+        assert_eq!(second.entries.len(), 2);
+        assert!(registry.tool_timeline("kiln", 3).is_none());
+
+        let json: serde_json::Value = serde_json::from_str(&timeline.to_json().unwrap()).unwrap();
+        assert_eq!(json["serial"], 1);
+        assert_eq!(json["entries"][1]["artist_id"], 1);
+    }
+}
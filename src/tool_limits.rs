@@ -0,0 +1,250 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt, fs, io, path::Path};
+
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum PriorityTier {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+impl PriorityTier {
+    pub fn rank(self) -> u32 {
+        match self {
+            PriorityTier::Low => 0,
+            PriorityTier::Normal => 1,
+            PriorityTier::High => 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolCountRange {
+    pub min: usize,
+    pub max: usize,
+}
+
+impl ToolCountRange {
+    pub fn contains(&self, count: usize) -> bool {
+        (self.min..=self.max).contains(&count)
+    }
+}
+
+// Parses `2..5` (both ends included) or a single count like `3`.
+impl std::str::FromStr for ToolCountRange {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("expected a range like 2..5, got '{}'", text);
+        let (min, max) = match text.split_once("..") {
+            Some((min, max)) => (min, max.trim_start_matches('=')),
+            None => (text, text),
+        };
+        let min = min.trim().parse().map_err(|_| invalid())?;
+        let max = max.trim().parse().map_err(|_| invalid())?;
+        if min > max {
+            return Err(invalid());
+        }
+        Ok(Self { min, max })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtistLimits {
+    pub id: usize,
+    #[serde(default)]
+    pub tier: PriorityTier,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ToolCountError {
+    pub artist_id: usize,
+    pub requested: usize,
+    pub allowed: ToolCountRange,
+}
+
+impl fmt::Display for ToolCountError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "artist {} may check out {} to {} tools, not {}",
+            self.artist_id, self.allowed.min, self.allowed.max, self.requested
+        )
+    }
+}
+
+// How many tools one checkout may hold. The most specific setting wins: an
+// artist's own min/max, then their tier's range, then the global one.
+//
+//   min = 2
+//   max = 5
+//   [tiers.high]
+//   min = 1
+//   max = 8
+//   [[artists]]
+//   id = 3
+//   tier = "high"
+//   max = 10
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolLimits {
+    pub min: usize,
+    pub max: usize,
+    #[serde(default)]
+    pub tiers: HashMap<PriorityTier, ToolCountRange>,
+    #[serde(default)]
+    pub artists: Vec<ArtistLimits>,
+}
+
+impl ToolLimits {
+    pub fn new(min: usize, max: usize) -> Self {
+        Self {
+            min,
+            max,
+            tiers: HashMap::new(),
+            artists: vec![],
+        }
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let limits: Self = toml::from_str(&fs::read_to_string(path)?)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error.to_string()))?;
+        limits
+            .validate()
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        Ok(limits)
+    }
+
+    pub fn set_tier(&mut self, tier: PriorityTier, range: ToolCountRange) {
+        self.tiers.insert(tier, range);
+    }
+
+    pub fn set_artist(&mut self, artist: ArtistLimits) {
+        self.artists.retain(|known| known.id != artist.id);
+        self.artists.push(artist);
+    }
+
+    pub fn tier_for(&self, artist_id: usize) -> PriorityTier {
+        self.artists
+            .iter()
+            .find(|artist| artist.id == artist_id)
+            .map(|artist| artist.tier)
+            .unwrap_or_default()
+    }
+
+    pub fn range_for(&self, artist_id: usize) -> ToolCountRange {
+        let artist = self.artists.iter().find(|artist| artist.id == artist_id);
+        let tier = artist.map(|artist| artist.tier).unwrap_or_default();
+        let base = self.tiers.get(&tier).copied().unwrap_or(ToolCountRange {
+            min: self.min,
+            max: self.max,
+        });
+        ToolCountRange {
+            min: artist.and_then(|artist| artist.min).unwrap_or(base.min),
+            max: artist.and_then(|artist| artist.max).unwrap_or(base.max),
+        }
+    }
+
+    pub fn check(&self, artist_id: usize, requested: usize) -> Result<(), ToolCountError> {
+        let allowed = self.range_for(artist_id);
+        if allowed.contains(requested) {
+            Ok(())
+        } else {
+            Err(ToolCountError {
+                artist_id,
+                requested,
+                allowed,
+            })
+        }
+    }
+
+    // Rejects a configuration in which some artist could never check out.
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        let ids = self.artists.iter().map(|artist| artist.id);
+        for artist_id in ids.chain([usize::MAX]) {
+            let range = self.range_for(artist_id);
+            if range.min > range.max {
+                return Err(format!(
+                    "minimum {} is above maximum {}",
+                    range.min, range.max
+                ));
+            }
+        }
+        for (tier, range) in &self.tiers {
+            if range.min > range.max {
+                return Err(format!(
+                    "{:?} tier minimum {} is above maximum {}",
+                    tier, range.min, range.max
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tool_count_range() {
+        assert_eq!("2..5".parse(), Ok(ToolCountRange { min: 2, max: 5 }));
+        assert_eq!("1..=3".parse(), Ok(ToolCountRange { min: 1, max: 3 }));
+        assert_eq!("4".parse(), Ok(ToolCountRange { min: 4, max: 4 }));
+        assert!("5..2".parse::<ToolCountRange>().is_err());
+        assert!("two".parse::<ToolCountRange>().is_err());
+    }
+
+    #[test]
+    fn test_most_specific_limit_wins() {
+        let mut limits = ToolLimits::new(2, 5);
+        limits.set_tier(PriorityTier::High, ToolCountRange { min: 1, max: 8 });
+        limits.set_artist(ArtistLimits {
+            id: 3,
+            tier: PriorityTier::High,
+            max: Some(10),
+            ..ArtistLimits::default()
+        });
+        limits.set_artist(ArtistLimits {
+            id: 4,
+            tier: PriorityTier::High,
+            ..ArtistLimits::default()
+        });
+
+        assert_eq!(limits.range_for(1), ToolCountRange { min: 2, max: 5 });
+        assert_eq!(limits.range_for(3), ToolCountRange { min: 1, max: 10 });
+        assert_eq!(limits.range_for(4), ToolCountRange { min: 1, max: 8 });
+        assert!(limits.check(3, 9).is_ok());
+        assert_eq!(
+            limits.check(1, 6).unwrap_err().to_string(),
+            "artist 1 may check out 2 to 5 tools, not 6"
+        );
+    }
+
+    #[test]
+    fn test_parse_limits_file() {
+        let limits: ToolLimits = toml::from_str(
+            "min = 2\nmax = 5\n\
+             [tiers.low]\nmin = 1\nmax = 2\n\
+             [[artists]]\nid = 7\ntier = \"low\"\n",
+        )
+        .unwrap();
+        assert_eq!(limits.range_for(7), ToolCountRange { min: 1, max: 2 });
+        assert!(limits.validate().is_ok());
+
+        let mut broken = limits.clone();
+        broken.set_artist(ArtistLimits {
+            id: 8,
+            min: Some(6),
+            ..ArtistLimits::default()
+        });
+        assert!(broken.validate().is_err());
+    }
+}
@@ -0,0 +1,188 @@
+use crate::{ArtistToolRegistry, State};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs, io,
+    path::Path,
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceEvent {
+    // Milliseconds since the first event of the trace.
+    pub offset_ms: u64,
+    pub artist_id: usize,
+    pub tools: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Trace {
+    pub events: Vec<TraceEvent>,
+}
+
+impl Trace {
+    // Only checkouts are traced; returns and other history are skipped.
+    pub fn from_registry(registry: &ArtistToolRegistry) -> Self {
+        let mut entries: Vec<_> = registry
+            .artist_tool_preferences
+            .iter()
+            .filter(|preferences| matches!(preferences.state, Some(State::TakeOut) | None))
+            .filter_map(|preferences| preferences.datetime.map(|at| (at, preferences)))
+            .collect();
+        entries.sort_by_key(|(at, _)| *at);
+
+        let Some(&(first, _)) = entries.first() else {
+            return Self::default();
+        };
+        let events = entries
+            .into_iter()
+            .map(|(at, preferences)| TraceEvent {
+                offset_ms: (at - first).num_milliseconds().max(0) as u64,
+                artist_id: preferences.artist_id,
+                tools: preferences
+                    .preferred_tools
+                    .iter()
+                    .filter_map(|&symbol| registry.interner.resolve(symbol))
+                    .map(str::to_string)
+                    .collect(),
+            })
+            .collect();
+        Self { events }
+    }
+
+    pub fn to_json_lines(&self) -> serde_json::Result<String> {
+        let mut lines = String::new();
+        for event in &self.events {
+            lines.push_str(&serde_json::to_string(event)?);
+            lines.push('\n');
+        }
+        Ok(lines)
+    }
+
+    pub fn from_json_lines(lines: &str) -> serde_json::Result<Self> {
+        let events = lines
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<serde_json::Result<_>>()?;
+        Ok(Self { events })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_json_lines()?)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::from_json_lines(&fs::read_to_string(path)?)?)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayReport {
+    pub events: usize,
+    pub speed: f64,
+    pub scheduled: Duration,
+    pub actual: Duration,
+    pub avg_lag: Duration,
+    pub max_lag: Duration,
+    pub kept_up: bool,
+}
+
+// Re-issues the trace's checkouts at `speed` times their recorded pace. The
+// registry keeps up if no event finishes more than `tolerance` behind schedule.
+pub fn replay(
+    trace: &Trace,
+    registry: &Mutex<ArtistToolRegistry>,
+    speed: f64,
+    tolerance: Duration,
+) -> ReplayReport {
+    let speed = speed.max(f64::EPSILON);
+    let started = Instant::now();
+    let mut total_lag = Duration::ZERO;
+    let mut max_lag = Duration::ZERO;
+
+    for event in &trace.events {
+        let due = started + Duration::from_millis(event.offset_ms).div_f64(speed);
+        if let Some(wait) = due.checked_duration_since(Instant::now()) {
+            thread::sleep(wait);
+        }
+        // A refused checkout still counts towards the measured lag.
+        let _ = registry
+            .lock()
+            .expect("Failed to lock registry")
+            .tool_registry(event.artist_id, event.tools.clone());
+        let lag = Instant::now().saturating_duration_since(due);
+        total_lag += lag;
+        max_lag = max_lag.max(lag);
+    }
+
+    let scheduled = trace
+        .events
+        .last()
+        .map(|event| Duration::from_millis(event.offset_ms).div_f64(speed))
+        .unwrap_or_default();
+    ReplayReport {
+        events: trace.events.len(),
+        speed,
+        scheduled,
+        actual: started.elapsed(),
+        avg_lag: total_lag / trace.events.len().max(1) as u32,
+        max_lag,
+        kept_up: max_lag <= tolerance,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SharedResources;
+    use std::sync::Arc;
+
+    fn registry() -> ArtistToolRegistry {
+        ArtistToolRegistry::new(&Arc::new(Mutex::new(SharedResources::default())))
+    }
+
+    #[test]
+    fn test_trace_round_trips_through_json_lines() {
+        let mut source = registry();
+        source.tool_registry(1, vec!["brush".to_string()]).unwrap();
+        source
+            .tool_registry(2, vec!["tape".to_string(), "rags".to_string()])
+            .unwrap();
+
+        let trace = Trace::from_registry(&source);
+        assert_eq!(trace.events.len(), 2);
+        assert_eq!(trace.events[0].offset_ms, 0);
+        assert_eq!(trace.events[1].tools, vec!["tape", "rags"]);
+
+        let lines = trace.to_json_lines().unwrap();
+        assert_eq!(Trace::from_json_lines(&lines).unwrap(), trace);
+    }
+
+    #[test]
+    fn test_replay_applies_events_at_speed() {
+        let trace = Trace {
+            events: vec![
+                TraceEvent {
+                    offset_ms: 0,
+                    artist_id: 1,
+                    tools: vec!["brush".to_string()],
+                },
+                TraceEvent {
+                    offset_ms: 100,
+                    artist_id: 2,
+                    tools: vec!["canvas".to_string()],
+                },
+            ],
+        };
+        let target = Mutex::new(registry());
+        let report = replay(&trace, &target, 10.0, Duration::from_secs(1));
+
+        assert_eq!(report.events, 2);
+        assert_eq!(report.scheduled, Duration::from_millis(10));
+        assert!(report.actual >= report.scheduled);
+        assert!(report.kept_up);
+        assert_eq!(target.lock().unwrap().artist_tool_preferences.len(), 2);
+    }
+}
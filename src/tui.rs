@@ -0,0 +1,357 @@
+use crate::{
+    events::EventRecord,
+    i18n::Message,
+    signal_dump,
+    units::{Amount, Quantity},
+    ArtistToolRegistry, State,
+};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode},
+    layout::{Constraint, Direction, Layout, Rect},
+    text::Line,
+    widgets::{Bar, BarChart, BarGroup, Block, List, Row, Table},
+    DefaultTerminal, Frame,
+};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    io,
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+const FEED_LEN: usize = 200;
+const REFRESH: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArtistStatus {
+    pub last: State,
+    pub events: usize,
+}
+
+// What a key asks `follow` to do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyAction {
+    Nothing,
+    Quit,
+    Dump,
+}
+
+// What the dashboard has seen of the event stream so far.
+#[derive(Debug, Default)]
+pub struct Dashboard {
+    feed: VecDeque<EventRecord>,
+    artists: BTreeMap<usize, ArtistStatus>,
+    finished: bool,
+    // What's typed in the search box, while it's open.
+    search: Option<String>,
+    // How the last dump went, shown in the feed's title.
+    dumped: Option<String>,
+}
+
+impl Dashboard {
+    pub fn push(&mut self, event: EventRecord) {
+        if let Some(id) = event.artist_id {
+            let status = self.artists.entry(id).or_insert(ArtistStatus {
+                last: event.kind,
+                events: 0,
+            });
+            status.last = event.kind;
+            status.events += 1;
+        }
+        self.feed.push_front(event);
+        self.feed.truncate(FEED_LEN);
+    }
+
+    pub fn artists(&self) -> &BTreeMap<usize, ArtistStatus> {
+        &self.artists
+    }
+
+    // `/` opens the search box, which takes every key until Esc closes it.
+    // `d` asks for the same state dump SIGUSR1 writes.
+    pub fn key(&mut self, code: KeyCode) -> KeyAction {
+        match (&mut self.search, code) {
+            (Some(_), KeyCode::Esc) => self.search = None,
+            (Some(query), KeyCode::Backspace) => {
+                query.pop();
+            }
+            (Some(query), KeyCode::Char(c)) => query.push(c),
+            (Some(_), _) => {}
+            (None, KeyCode::Char('/')) => self.search = Some(String::new()),
+            (None, KeyCode::Char('d')) => return KeyAction::Dump,
+            (None, KeyCode::Char('q') | KeyCode::Esc) => return KeyAction::Quit,
+            (None, _) => {}
+        }
+        KeyAction::Nothing
+    }
+
+    // Keeps the outcome of a dump into `dir` for the feed's title.
+    pub fn dumped(&mut self, written: io::Result<PathBuf>, dir: &Path) {
+        self.dumped = Some(match written {
+            Ok(path) => Message::StateDumped(&path.display().to_string()).to_string(),
+            Err(error) => {
+                Message::FileError(&dir.display().to_string(), error.to_string()).to_string()
+            }
+        });
+    }
+
+    // Stock bars on top, the event feed and artist table below. An open
+    // search box takes the artist table's place.
+    pub fn draw(&self, frame: &mut Frame, registry: &ArtistToolRegistry) {
+        let [stock, activity] =
+            Layout::vertical([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .areas(frame.area());
+        let [tools, paints] =
+            Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .areas(stock);
+        let [feed, artists] =
+            Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)])
+                .areas(activity);
+
+        {
+            let resources = registry
+                .shared_resources
+                .lock()
+                .expect("Failed to lock resources");
+            draw_stock(frame, tools, "Tools", &resources.tools.amounts());
+            draw_stock(frame, paints, "Paint (kg)", &resources.paints.amounts());
+        }
+
+        let mut title = if self.finished {
+            "Events (finished, / to search, d to dump, q to quit)".to_string()
+        } else {
+            "Events (/ to search, d to dump, q to quit)".to_string()
+        };
+        if let Some(dumped) = &self.dumped {
+            title = format!("{} {}", title, dumped);
+        }
+        let lines: Vec<Line> = self
+            .feed
+            .iter()
+            .map(|event| {
+                let who = match event.artist_id {
+                    Some(id) => format!("artist {}", id),
+                    None => "studio".to_string(),
+                };
+                Line::from(format!(
+                    "{} {:<10} {:<8} {}",
+                    event.at.format("%H:%M:%S%.3f"),
+                    who,
+                    format!("{:?}", event.kind),
+                    event.items.join(", ")
+                ))
+            })
+            .collect();
+        frame.render_widget(List::new(lines).block(Block::bordered().title(title)), feed);
+
+        if let Some(query) = &self.search {
+            let hits: Vec<Line> = registry
+                .search(query)
+                .into_iter()
+                .map(|hit| Line::from(format!("{:<6} {}", format!("{:?}", hit.kind), hit.name)))
+                .collect();
+            let title = format!("Search: {}_ (Esc to close)", query);
+            frame.render_widget(
+                List::new(hits).block(Block::bordered().title(title)),
+                artists,
+            );
+            return;
+        }
+
+        let rows: Vec<Row> = self
+            .artists
+            .iter()
+            .map(|(&id, status)| {
+                Row::new(vec![
+                    id.to_string(),
+                    registry.outstanding(id).to_string(),
+                    format!("{:?}", status.last),
+                    status.events.to_string(),
+                ])
+            })
+            .collect();
+        let widths = [
+            Constraint::Length(6),
+            Constraint::Length(7),
+            Constraint::Length(9),
+            Constraint::Length(6),
+        ];
+        let table = Table::new(rows, widths)
+            .header(Row::new(vec!["artist", "holding", "last", "events"]))
+            .block(Block::bordered().title("Artists"));
+        frame.render_widget(table, artists);
+    }
+}
+
+// Bars only come in whole steps, so paint is drawn to the nearest kilogram
+// and labelled with its exact weight.
+fn draw_stock<Q: Quantity>(frame: &mut Frame, area: Rect, title: &str, items: &[(String, Q)]) {
+    let label_width = items.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    let bars: Vec<Bar> = items
+        .iter()
+        .map(|(name, quantity)| {
+            let value = match (*quantity).into() {
+                Amount::Count(count) => count.0 as u64,
+                Amount::Kilograms(kg) => kg.get().round() as u64,
+            };
+            Bar::default()
+                .label(Line::from(format!("{:>width$}", name, width = label_width)))
+                .value(value)
+                .text_value(quantity.to_string())
+        })
+        .collect();
+    let chart = BarChart::default()
+        .block(Block::bordered().title(title.to_string()))
+        .direction(Direction::Horizontal)
+        .bar_width(1)
+        .bar_gap(0)
+        .data(BarGroup::default().bars(&bars));
+    frame.render_widget(chart, area);
+}
+
+// Runs `work` on its own thread while the dashboard follows the registry's
+// events, until the user quits with q or Esc; then waits for `work` and
+// returns what it returned. Nothing runs if the terminal can't be set up.
+// The dump key writes into `dump_dir`.
+pub fn run<T: Send + 'static>(
+    registry: &Arc<Mutex<ArtistToolRegistry>>,
+    dump_dir: &Path,
+    work: impl FnOnce() -> T + Send + 'static,
+) -> io::Result<T> {
+    let events = registry
+        .lock()
+        .expect("Failed to lock registry")
+        .events
+        .subscribe();
+    let mut terminal = ratatui::try_init()?;
+    let handle = thread::spawn(work);
+    let followed = follow(&mut terminal, registry, dump_dir, &events, &handle);
+    let restored = ratatui::try_restore();
+    let result = handle.join().expect("Thread panicked");
+    followed.and(restored).map(|()| result)
+}
+
+fn follow<T>(
+    terminal: &mut DefaultTerminal,
+    registry: &Mutex<ArtistToolRegistry>,
+    dump_dir: &Path,
+    events: &mpsc::Receiver<EventRecord>,
+    work: &thread::JoinHandle<T>,
+) -> io::Result<()> {
+    let mut dashboard = Dashboard::default();
+    loop {
+        for event in events.try_iter() {
+            dashboard.push(event);
+        }
+        dashboard.finished = work.is_finished();
+        {
+            let registry = registry.lock().expect("Failed to lock registry");
+            terminal.draw(|frame| dashboard.draw(frame, &registry))?;
+        }
+        if event::poll(REFRESH)? {
+            if let Event::Key(key) = event::read()? {
+                match dashboard.key(key.code) {
+                    KeyAction::Nothing => {}
+                    KeyAction::Quit => return Ok(()),
+                    KeyAction::Dump => {
+                        dashboard.dumped(signal_dump::dump_and_log(dump_dir, registry), dump_dir)
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SharedResources;
+    use ratatui::{backend::TestBackend, Terminal};
+
+    #[test]
+    fn test_dashboard_shows_stock_feed_and_artists() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        let events = registry.events.subscribe();
+        registry
+            .tool_registry(4, vec!["brush".to_string(), "rags".to_string()])
+            .unwrap();
+        registry.tool_return(4, vec!["rags".to_string()]).unwrap();
+
+        let mut dashboard = Dashboard::default();
+        for event in events.try_iter() {
+            dashboard.push(event);
+        }
+        assert_eq!(
+            dashboard.artists()[&4],
+            ArtistStatus {
+                last: State::Return,
+                events: 2
+            }
+        );
+
+        let mut terminal = Terminal::new(TestBackend::new(120, 40)).unwrap();
+        terminal
+            .draw(|frame| dashboard.draw(frame, &registry))
+            .unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        for text in [
+            "Tools",
+            "sculpting tool",
+            "Paint (kg)",
+            "artist 4",
+            "TakeOut",
+        ] {
+            assert!(screen.contains(text), "missing {}", text);
+        }
+
+        // Typing in the search box doesn't quit or dump.
+        assert_eq!(dashboard.key(KeyCode::Char('d')), KeyAction::Dump);
+        for code in [
+            KeyCode::Char('/'),
+            KeyCode::Char('q'),
+            KeyCode::Char('d'),
+            KeyCode::Backspace,
+            KeyCode::Backspace,
+        ] {
+            assert_eq!(dashboard.key(code), KeyAction::Nothing);
+        }
+        for c in "spon".chars() {
+            dashboard.key(KeyCode::Char(c));
+        }
+        terminal
+            .draw(|frame| dashboard.draw(frame, &registry))
+            .unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        assert!(screen.contains("Search: spon_"));
+        assert!(screen.contains("sponges"));
+        assert!(!screen.contains("holding"));
+        assert_eq!(dashboard.key(KeyCode::Esc), KeyAction::Nothing);
+        dashboard.dumped(Ok(PathBuf::from("dump.json")), Path::new("."));
+        terminal
+            .draw(|frame| dashboard.draw(frame, &registry))
+            .unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        assert!(screen.contains("dump.json"));
+        assert_eq!(dashboard.key(KeyCode::Char('q')), KeyAction::Quit);
+    }
+}
@@ -0,0 +1,112 @@
+use crate::{error::RegistryError, ledger::LedgerEvent, ArtistToolRegistry, State};
+
+impl ArtistToolRegistry {
+    // Reverses the artist's most recent checkout or return that hasn't been
+    // undone yet, for fixing an operator's slip. Nothing is erased: the
+    // reversal is a new entry, a return for a checkout and a checkout for a
+    // return, with stock and deposits moved back to match. Undoing again
+    // reaches further back.
+    pub fn undo_last(&mut self, id: usize) -> Result<(), RegistryError> {
+        let undone = |index: &usize| {
+            self.undone
+                .iter()
+                .any(|&(entry, reversal)| entry == *index || reversal == *index)
+        };
+        let (index, state) = self
+            .artist_tool_preferences
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(index, entry)| entry.artist_id == id && !undone(index))
+            .find_map(|(index, entry)| match (entry.state, entry.source_state()) {
+                (Some(State::TakeOut), _) => Some((index, State::TakeOut)),
+                (Some(State::Return), Some(State::TakeOut)) => Some((index, State::Return)),
+                _ => None,
+            })
+            .ok_or(RegistryError::NothingToUndo(id))?;
+        let tools: Vec<String> = self.artist_tool_preferences[index]
+            .preferred_tools
+            .iter()
+            .filter_map(|&symbol| self.interner.resolve(symbol))
+            .map(str::to_string)
+            .collect();
+        let now = self.now();
+        let reversal = self.artist_tool_preferences.len();
+
+        if state == State::TakeOut {
+            let mut held = self.held_tools(id);
+            let not_held: Vec<String> = tools
+                .iter()
+                .filter(|tool| {
+                    match self
+                        .interner
+                        .get(tool)
+                        .and_then(|symbol| held.get_mut(&symbol))
+                    {
+                        Some(count) if *count > 0 => {
+                            *count -= 1;
+                            false
+                        }
+                        _ => true,
+                    }
+                })
+                .cloned()
+                .collect();
+            if !not_held.is_empty() {
+                return Err(RegistryError::NotHeld {
+                    artist_id: id,
+                    tools: not_held,
+                });
+            }
+            self.put_back(id, &tools, State::TakeOut, now)?;
+        } else {
+            // The units may have gone out again to someone else since.
+            self.shared_resources
+                .lock()
+                .map_err(|poisoned| self.recover(poisoned))?
+                .take_out_all(&tools)?;
+            for tool in &tools {
+                if let Some(amount) = self.deposits.hold(id, tool) {
+                    let memo = format!("artist {} {}", id, tool);
+                    self.record_ledger(LedgerEvent::DepositHeld, amount, now, memo);
+                }
+            }
+            self.push_entry(id, &tools, None, State::TakeOut, now);
+        }
+        self.undone.push((index, reversal));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{units::Count, SharedResources};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_undo_reverses_returns_then_checkouts() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        let on_shelf = |tool: &str| resources.lock().unwrap().tools.quantity(tool);
+        let brushes = on_shelf("brush");
+        registry
+            .tool_registry(1, vec!["brush".to_string(), "rags".to_string()])
+            .unwrap();
+        registry.tool_return(1, vec!["brush".to_string()]).unwrap();
+        assert_eq!(on_shelf("brush"), brushes);
+
+        // The brush goes back out to artist 1.
+        registry.undo_last(1).unwrap();
+        assert_eq!(on_shelf("brush"), brushes - Count(1));
+        assert_eq!(registry.held_tools(1).values().sum::<usize>(), 2);
+
+        // Then the checkout itself is reversed, brush and rags alike.
+        registry.undo_last(1).unwrap();
+        assert_eq!(on_shelf("brush"), brushes);
+        assert!(registry.held_tools(1).is_empty());
+        assert_eq!(registry.history_for_artist(1).count(), 4);
+        assert_eq!(registry.undo_last(1), Err(RegistryError::NothingToUndo(1)));
+        assert_eq!(registry.undo_last(2), Err(RegistryError::NothingToUndo(2)));
+    }
+}
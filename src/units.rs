@@ -0,0 +1,386 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Ordering,
+    fmt,
+    iter::Sum,
+    ops::{Add, AddAssign, Sub, SubAssign},
+    str::FromStr,
+};
+
+// What a stock is measured in: whole units for tools, kilograms for paint.
+pub trait Quantity:
+    Copy
+    + Default
+    + PartialOrd
+    + fmt::Display
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + AddAssign
+    + SubAssign
+    + Sum
+    + Into<Amount>
+{
+    fn is_zero(self) -> bool {
+        self == Self::default()
+    }
+
+    // Nothing rather than less than nothing.
+    fn saturating_sub(self, other: Self) -> Self {
+        if other > self {
+            Self::default()
+        } else {
+            self - other
+        }
+    }
+}
+
+// A number of interchangeable tool units.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct Count(pub u32);
+
+impl Count {
+    // For lengths and tallies that come as `usize`; past `u32::MAX` it stays
+    // there.
+    pub fn of(count: usize) -> Self {
+        Count(u32::try_from(count).unwrap_or(u32::MAX))
+    }
+
+    pub fn get(self) -> usize {
+        self.0 as usize
+    }
+
+    // None where `-` would stop at nothing, for stock that must never go
+    // below it.
+    pub fn checked_sub(self, other: Count) -> Option<Count> {
+        self.0.checked_sub(other.0).map(Count)
+    }
+}
+
+impl Quantity for Count {}
+
+// Stops at `u32::MAX` going up and at nothing coming down, so a miscounted
+// return can't wrap a stock round.
+impl Add for Count {
+    type Output = Count;
+
+    fn add(self, other: Count) -> Count {
+        Count(self.0.saturating_add(other.0))
+    }
+}
+
+impl Sub for Count {
+    type Output = Count;
+
+    fn sub(self, other: Count) -> Count {
+        Count(self.0.saturating_sub(other.0))
+    }
+}
+
+impl AddAssign for Count {
+    fn add_assign(&mut self, other: Count) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for Count {
+    fn sub_assign(&mut self, other: Count) {
+        *self = *self - other;
+    }
+}
+
+impl Sum for Count {
+    fn sum<I: Iterator<Item = Count>>(counts: I) -> Count {
+        counts.fold(Count(0), Add::add)
+    }
+}
+
+impl fmt::Display for Count {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl FromStr for Count {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        text.parse().map(Count)
+    }
+}
+
+// A weight of paint, kept to the gram so sums of fractions compare the way
+// they read: 0.1 kg and 0.2 kg make exactly 0.3 kg. Never NaN, infinite
+// or negative, which is what makes it `Eq`; the field is private so the
+// only way in from a bare `f64` is `new`, which checks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(try_from = "f64", into = "f64")]
+pub struct Kilograms(f64);
+
+impl Eq for Kilograms {}
+
+impl Kilograms {
+    pub fn new(kg: f64) -> Result<Self, String> {
+        if kg.is_finite() && kg >= 0.0 {
+            Ok(Kilograms::rounded(kg))
+        } else {
+            Err(format!("{} is not a weight in kilograms", kg))
+        }
+    }
+
+    // Weights that can't be wrong, for constants and whole counts.
+    pub const fn whole(kg: u32) -> Self {
+        Kilograms(kg as f64)
+    }
+
+    pub const fn grams(grams: u32) -> Self {
+        Kilograms(grams as f64 / 1000.0)
+    }
+
+    // For sums and differences of weights already known to be sound.
+    fn rounded(kg: f64) -> Self {
+        Kilograms(((kg * 1000.0).round() / 1000.0).max(0.0))
+    }
+
+    pub fn get(self) -> f64 {
+        self.0
+    }
+
+    pub fn min(self, other: Kilograms) -> Kilograms {
+        if other < self {
+            other
+        } else {
+            self
+        }
+    }
+}
+
+impl Quantity for Kilograms {}
+
+impl TryFrom<f64> for Kilograms {
+    type Error = String;
+
+    fn try_from(kg: f64) -> Result<Self, Self::Error> {
+        Kilograms::new(kg)
+    }
+}
+
+impl From<Kilograms> for f64 {
+    fn from(kg: Kilograms) -> f64 {
+        kg.0
+    }
+}
+
+impl Add for Kilograms {
+    type Output = Kilograms;
+
+    fn add(self, other: Kilograms) -> Kilograms {
+        Kilograms::rounded(self.0 + other.0)
+    }
+}
+
+// Taking more than there is leaves nothing, as with `Count`.
+impl Sub for Kilograms {
+    type Output = Kilograms;
+
+    fn sub(self, other: Kilograms) -> Kilograms {
+        Kilograms::rounded(self.0 - other.0)
+    }
+}
+
+impl AddAssign for Kilograms {
+    fn add_assign(&mut self, other: Kilograms) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for Kilograms {
+    fn sub_assign(&mut self, other: Kilograms) {
+        *self = *self - other;
+    }
+}
+
+impl Sum for Kilograms {
+    fn sum<I: Iterator<Item = Kilograms>>(weights: I) -> Kilograms {
+        weights.fold(Kilograms::default(), Add::add)
+    }
+}
+
+impl fmt::Display for Kilograms {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+// Accepts `2`, `0.25` and the like; negative and non-finite weights aren't
+// weights.
+impl FromStr for Kilograms {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.parse::<f64>() {
+            Ok(kg) => Kilograms::try_from(kg),
+            Err(_) => Err(format!("'{}' is not a weight in kilograms", text)),
+        }
+    }
+}
+
+// A difference of weights, which unlike a weight may be negative, to the
+// gram.
+pub fn round_to_gram(kg: f64) -> f64 {
+    (kg * 1000.0).round() / 1000.0
+}
+
+// A quantity of whatever an item is measured in, for places that handle
+// tools and paints alike by name. Written as a bare number; weights always
+// carry a decimal point, which is how they read back as weights.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Amount {
+    Count(Count),
+    Kilograms(Kilograms),
+}
+
+impl Amount {
+    pub fn count(self) -> Option<Count> {
+        match self {
+            Amount::Count(count) => Some(count),
+            Amount::Kilograms(_) => None,
+        }
+    }
+
+    pub fn kilograms(self) -> Option<Kilograms> {
+        match self {
+            Amount::Kilograms(kg) => Some(kg),
+            Amount::Count(_) => None,
+        }
+    }
+
+    pub fn unit(self) -> &'static str {
+        match self {
+            Amount::Count(_) => "units",
+            Amount::Kilograms(_) => "kg",
+        }
+    }
+
+    pub fn is_zero(self) -> bool {
+        match self {
+            Amount::Count(count) => count.is_zero(),
+            Amount::Kilograms(kg) => kg.is_zero(),
+        }
+    }
+
+    // None when the two are in different units.
+    pub fn checked_add(self, other: Amount) -> Option<Amount> {
+        match (self, other) {
+            (Amount::Count(a), Amount::Count(b)) => Some(Amount::Count(a + b)),
+            (Amount::Kilograms(a), Amount::Kilograms(b)) => Some(Amount::Kilograms(a + b)),
+            _ => None,
+        }
+    }
+}
+
+// Whole numbers read as counts and anything with a fraction as kilograms;
+// `SharedResources::measure` settles which an item really takes.
+impl FromStr for Amount {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.parse() {
+            Ok(count) => Ok(Amount::Count(count)),
+            Err(_) => text.parse().map(Amount::Kilograms),
+        }
+    }
+}
+
+impl From<Count> for Amount {
+    fn from(count: Count) -> Self {
+        Amount::Count(count)
+    }
+}
+
+impl From<Kilograms> for Amount {
+    fn from(kg: Kilograms) -> Self {
+        Amount::Kilograms(kg)
+    }
+}
+
+// Units and kilograms don't compare.
+impl PartialOrd for Amount {
+    fn partial_cmp(&self, other: &Amount) -> Option<Ordering> {
+        match (self, other) {
+            (Amount::Count(a), Amount::Count(b)) => a.partial_cmp(b),
+            (Amount::Kilograms(a), Amount::Kilograms(b)) => a.partial_cmp(b),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Amount::Count(count) => count.fmt(f),
+            Amount::Kilograms(kg) => kg.fmt(f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kilograms_add_up_to_the_gram() {
+        let tenth: Kilograms = "0.1".parse().unwrap();
+        assert_eq!(tenth + Kilograms::grams(200), Kilograms::grams(300));
+        assert_eq!(
+            [Kilograms::grams(2_500), Kilograms::grams(250)]
+                .into_iter()
+                .sum::<Kilograms>(),
+            Kilograms::grams(2_750)
+        );
+        assert_eq!(
+            Kilograms::whole(1).saturating_sub(Kilograms::grams(1_500)),
+            Kilograms::default()
+        );
+        assert_eq!("2".parse(), Ok(Kilograms::whole(2)));
+        assert!("-1".parse::<Kilograms>().is_err());
+        assert!("inf".parse::<Kilograms>().is_err());
+        assert!(Kilograms::new(-0.5).is_err());
+        assert_eq!(
+            Kilograms::grams(500) - Kilograms::whole(1),
+            Kilograms::default()
+        );
+        assert_eq!(Count(1) - Count(2), Count(0));
+        assert_eq!(Count(u32::MAX) + Count(1), Count(u32::MAX));
+        assert_eq!(Kilograms::whole(10).to_string(), "10");
+    }
+
+    #[test]
+    fn test_amounts_keep_their_unit() {
+        let brushes = Amount::from(Count(3));
+        let red = Amount::from(Kilograms::whole(3));
+        assert_ne!(brushes, red);
+        assert_eq!(brushes.checked_add(red), None);
+        assert_eq!(
+            red.checked_add(Kilograms::grams(500).into()),
+            Some(Amount::Kilograms(Kilograms::grams(3_500)))
+        );
+        assert!(brushes.partial_cmp(&red).is_none());
+        assert!(Amount::from(Count(2)) < brushes);
+
+        assert_eq!("3".parse(), Ok(brushes));
+        assert_eq!("0.5".parse(), Ok(Amount::Kilograms(Kilograms::grams(500))));
+        assert!("lots".parse::<Amount>().is_err());
+
+        let json = serde_json::to_string(&[brushes, red]).unwrap();
+        assert_eq!(json, "[3,3.0]");
+        assert_eq!(
+            serde_json::from_str::<Vec<Amount>>(&json).unwrap(),
+            vec![brushes, red]
+        );
+        assert!(serde_json::from_str::<Amount>("-1").is_err());
+    }
+}
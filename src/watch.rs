@@ -0,0 +1,178 @@
+use crate::{
+    units::{round_to_gram, Count, Kilograms},
+    ArtistToolRegistry, State,
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    Checkouts,
+    ItemsLent,
+    ToolsInStock,
+    PaintKg,
+    QueuedCheckouts,
+    RulesFired,
+    Artworks,
+}
+
+// The headline numbers of one simulation run, compared between runs in
+// watch mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RunSummary {
+    pub checkouts: usize,
+    pub items_lent: usize,
+    pub tools_in_stock: Count,
+    pub paint_kg: Kilograms,
+    pub queued_checkouts: usize,
+    pub rules_fired: usize,
+    pub artworks: usize,
+}
+
+// Paint comes in fractions of a kilogram, so every metric reads as a
+// fractional number here.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MetricChange {
+    pub metric: Metric,
+    pub before: f64,
+    pub after: f64,
+}
+
+impl MetricChange {
+    pub fn delta(&self) -> f64 {
+        round_to_gram(self.after - self.before)
+    }
+}
+
+impl RunSummary {
+    pub fn capture(registry: &ArtistToolRegistry, rules_fired: usize) -> Self {
+        let resources = registry
+            .shared_resources
+            .lock()
+            .expect("Failed to lock resources");
+        let checkouts: Vec<_> = registry
+            .artist_tool_preferences
+            .iter()
+            .filter(|entry| entry.state == Some(State::TakeOut))
+            .collect();
+        Self {
+            checkouts: checkouts.len(),
+            items_lent: checkouts
+                .iter()
+                .map(|entry| entry.preferred_tools.len())
+                .sum(),
+            tools_in_stock: resources.tools.iter().map(|tool| tool.quantity).sum(),
+            paint_kg: resources.paints.iter().map(|paint| paint.weight_kg).sum(),
+            queued_checkouts: resources.loan_caps.queued().count(),
+            rules_fired,
+            artworks: registry.gallery.len(),
+        }
+    }
+
+    pub fn metrics(&self) -> [(Metric, f64); 7] {
+        [
+            (Metric::Checkouts, self.checkouts as f64),
+            (Metric::ItemsLent, self.items_lent as f64),
+            (Metric::ToolsInStock, self.tools_in_stock.get() as f64),
+            (Metric::PaintKg, self.paint_kg.get()),
+            (Metric::QueuedCheckouts, self.queued_checkouts as f64),
+            (Metric::RulesFired, self.rules_fired as f64),
+            (Metric::Artworks, self.artworks as f64),
+        ]
+    }
+
+    // Metrics that differ from `previous`, in display order.
+    pub fn changes_since(&self, previous: &RunSummary) -> Vec<MetricChange> {
+        previous
+            .metrics()
+            .into_iter()
+            .zip(self.metrics())
+            .filter(|((_, before), (_, after))| before != after)
+            .map(|((metric, before), (_, after))| MetricChange {
+                metric,
+                before,
+                after,
+            })
+            .collect()
+    }
+}
+
+// Polls modification times; a file that disappears or reappears counts as a
+// change too.
+#[derive(Debug)]
+pub struct FileWatcher {
+    paths: Vec<PathBuf>,
+    seen: Vec<Option<SystemTime>>,
+}
+
+pub const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+impl FileWatcher {
+    pub fn new(paths: Vec<PathBuf>) -> Self {
+        let seen = paths.iter().map(|path| modified(path.as_path())).collect();
+        Self { paths, seen }
+    }
+
+    pub fn changed(&mut self) -> bool {
+        let current: Vec<_> = self
+            .paths
+            .iter()
+            .map(|path| modified(path.as_path()))
+            .collect();
+        let changed = current != self.seen;
+        self.seen = current;
+        changed
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SharedResources;
+    use std::{
+        env, process,
+        sync::{Arc, Mutex},
+    };
+
+    #[test]
+    fn test_changes_since_lists_only_changed_metrics() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        let before = RunSummary::capture(&registry, 0);
+        registry
+            .tool_registry(1, vec!["brush".to_string(), "tape".to_string()])
+            .unwrap();
+        let after = RunSummary::capture(&registry, 0);
+
+        let changes = after.changes_since(&before);
+        let metrics: Vec<_> = changes.iter().map(|change| change.metric).collect();
+        assert_eq!(
+            metrics,
+            vec![Metric::Checkouts, Metric::ItemsLent, Metric::ToolsInStock]
+        );
+        assert_eq!(changes[2].delta(), -2.0);
+        assert!(after.changes_since(&after).is_empty());
+    }
+
+    #[test]
+    fn test_watcher_notices_new_and_removed_files() {
+        let path = env::temp_dir().join(format!("rustic-canvas-watch-{}", process::id()));
+        let _ = fs::remove_file(&path);
+        let mut watcher = FileWatcher::new(vec![path.clone()]);
+        assert!(!watcher.changed());
+
+        fs::write(&path, "at 0ms checkout 1 brush\n").unwrap();
+        assert!(watcher.changed());
+        assert!(!watcher.changed());
+
+        fs::remove_file(&path).unwrap();
+        assert!(watcher.changed());
+    }
+}
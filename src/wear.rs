@@ -0,0 +1,213 @@
+use crate::{error::RegistryError, ArtistToolRegistry};
+use serde::Serialize;
+use std::collections::HashMap;
+
+pub const FULL_DURABILITY: u32 = 100;
+
+// What happens to a unit handed back from a worn-out tool line.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WornOut {
+    #[default]
+    Repair,
+    Retire,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WearPolicy {
+    // Durability a tool loses each time a unit is checked out.
+    pub per_checkout: u32,
+    // A tool below this is serviced the next time a unit comes back.
+    pub service_below: u32,
+    pub worn_out: WornOut,
+}
+
+impl Default for WearPolicy {
+    fn default() -> Self {
+        Self {
+            per_checkout: 5,
+            service_below: 20,
+            worn_out: WornOut::Repair,
+        }
+    }
+}
+
+// Durability of each tool, from `FULL_DURABILITY` down to 0. Units of a tool
+// are interchangeable, so they share one score: the next unit back from a
+// worn-out tool is the one serviced, and the tool is good as new again.
+#[derive(Debug, Clone, Default)]
+pub struct Wear {
+    pub policy: WearPolicy,
+    durability: HashMap<String, u32>,
+}
+
+impl Wear {
+    pub fn new(policy: WearPolicy) -> Self {
+        Self {
+            policy,
+            durability: HashMap::new(),
+        }
+    }
+
+    // Full for tools that haven't been checked out yet.
+    pub fn durability(&self, tool: &str) -> u32 {
+        self.durability
+            .get(tool)
+            .copied()
+            .unwrap_or(FULL_DURABILITY)
+    }
+
+    pub fn wear(&mut self, tool: &str) {
+        let durability = self
+            .durability(tool)
+            .saturating_sub(self.policy.per_checkout);
+        self.durability.insert(tool.to_string(), durability);
+    }
+
+    pub fn is_worn_out(&self, tool: &str) -> bool {
+        self.durability(tool) < self.policy.service_below
+    }
+
+    pub fn restore(&mut self, tool: &str) {
+        self.durability.remove(tool);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WearLevel {
+    pub tool: String,
+    pub durability: u32,
+    pub worn_out: bool,
+}
+
+impl ArtistToolRegistry {
+    // Tools wear only once a policy is set.
+    pub fn set_wear_policy(&mut self, policy: WearPolicy) {
+        self.wear = Some(Wear::new(policy));
+    }
+
+    // Every stocked tool with its durability, most worn first.
+    pub fn wear_levels(&self) -> Vec<WearLevel> {
+        let resources = self
+            .shared_resources
+            .lock()
+            .expect("Failed to lock resources");
+        let mut levels: Vec<WearLevel> = resources
+            .tools
+            .iter()
+            .map(|tool| WearLevel {
+                tool: tool.name.clone(),
+                durability: self
+                    .wear
+                    .as_ref()
+                    .map_or(FULL_DURABILITY, |wear| wear.durability(&tool.name)),
+                worn_out: self
+                    .wear
+                    .as_ref()
+                    .is_some_and(|wear| wear.is_worn_out(&tool.name)),
+            })
+            .collect();
+        levels.sort_by(|a, b| {
+            a.durability
+                .cmp(&b.durability)
+                .then_with(|| a.tool.cmp(&b.tool))
+        });
+        levels
+    }
+
+    pub(crate) fn wear_tools(&mut self, tools: &[String]) {
+        if let Some(wear) = &mut self.wear {
+            for tool in tools {
+                wear.wear(tool);
+            }
+        }
+    }
+
+    // Sends one returned unit of each worn-out tool to repair, or retires it,
+    // as the policy says. Returns the units left to go back on the shelf.
+    pub(crate) fn service_worn(
+        &mut self,
+        id: usize,
+        tools: Vec<String>,
+    ) -> Result<Vec<String>, RegistryError> {
+        let Some(wear) = &self.wear else {
+            return Ok(tools);
+        };
+        let action = wear.policy.worn_out;
+        let mut shelved = vec![];
+        for tool in tools {
+            if !self
+                .wear
+                .as_ref()
+                .is_some_and(|wear| wear.is_worn_out(&tool))
+            {
+                shelved.push(tool);
+                continue;
+            }
+            // A worn-out unit comes back damaged, whatever happens to it next.
+            match action {
+                WornOut::Repair => {
+                    self.return_damaged(id, &tool)?;
+                }
+                WornOut::Retire => {
+                    self.report_damage(id, &tool)?;
+                    self.retire(id, &tool)?;
+                }
+            }
+            if let Some(wear) = &mut self.wear {
+                wear.restore(&tool);
+            }
+        }
+        Ok(shelved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{resources::TOTAL_ITEMS, units::Count, SharedResources, State};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_worn_out_tools_are_serviced_on_return() {
+        let resources = Arc::new(Mutex::new(SharedResources::default()));
+        let mut registry = ArtistToolRegistry::new(&resources);
+        registry.set_wear_policy(WearPolicy {
+            per_checkout: 30,
+            service_below: 50,
+            worn_out: WornOut::Repair,
+        });
+        let brush = vec!["brush".to_string()];
+
+        registry.tool_registry(1, brush.clone()).unwrap();
+        registry.tool_return(1, brush.clone()).unwrap();
+        assert_eq!(registry.wear_levels()[0].durability, 70);
+        assert!(registry.repairs.jobs().is_empty());
+
+        registry.tool_registry(1, brush.clone()).unwrap();
+        assert!(registry.wear_levels()[0].worn_out);
+        registry.tool_return(1, brush.clone()).unwrap();
+        assert_eq!(registry.repairs.jobs()[0].tool, "brush");
+        assert_eq!(
+            resources.lock().unwrap().stock("brush"),
+            TOTAL_ITEMS - Count(1)
+        );
+        assert!(registry
+            .wear_levels()
+            .iter()
+            .all(|level| level.durability == FULL_DURABILITY));
+
+        registry.set_wear_policy(WearPolicy {
+            per_checkout: 60,
+            service_below: 50,
+            worn_out: WornOut::Retire,
+        });
+        registry.tool_registry(2, vec!["tape".to_string()]).unwrap();
+        registry.tool_return(2, vec!["tape".to_string()]).unwrap();
+        let last = registry.artist_tool_preferences.last().unwrap();
+        assert_eq!(last.state, Some(State::Retire));
+        assert_eq!(
+            resources.lock().unwrap().stock("tape"),
+            TOTAL_ITEMS - Count(1)
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        mpsc::{self, Sender},
+        Arc, Condvar, Mutex,
+    },
+    thread::{self, JoinHandle},
+};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+// Jobs submitted but not yet finished, and whether any of them panicked.
+#[derive(Default)]
+struct Pending {
+    jobs: usize,
+    panicked: bool,
+}
+
+// Lets `wait` block until every submitted job has finished.
+#[derive(Default)]
+struct Completion {
+    pending: Mutex<Pending>,
+    done: Condvar,
+}
+
+impl Completion {
+    fn finish(&self, panicked: bool) {
+        let mut pending = self.pending.lock().expect("Failed to lock pool");
+        pending.jobs -= 1;
+        pending.panicked |= panicked;
+        if pending.jobs == 0 {
+            self.done.notify_all();
+        }
+    }
+}
+
+// A fixed number of threads taking jobs off one queue in submission order.
+// With a single worker, jobs run one after another in that order.
+pub struct WorkerPool {
+    jobs: Option<Sender<Job>>,
+    workers: Vec<JoinHandle<()>>,
+    completion: Arc<Completion>,
+}
+
+impl WorkerPool {
+    // At least one worker, whatever `size` says.
+    pub fn new(size: usize) -> Self {
+        let (jobs, queue) = mpsc::channel::<Job>();
+        let queue = Arc::new(Mutex::new(queue));
+        let completion = Arc::new(Completion::default());
+        let workers = (0..size.max(1))
+            .map(|_| {
+                let queue = Arc::clone(&queue);
+                let completion = Arc::clone(&completion);
+                thread::spawn(move || loop {
+                    let job = queue.lock().expect("Failed to lock pool").recv();
+                    let Ok(job) = job else { break };
+                    // The worker outlives a job that panics; `wait` reports it.
+                    let result = panic::catch_unwind(AssertUnwindSafe(job));
+                    completion.finish(result.is_err());
+                })
+            })
+            .collect();
+        Self {
+            jobs: Some(jobs),
+            workers,
+            completion,
+        }
+    }
+
+    // One worker per available core.
+    pub fn default_size() -> usize {
+        thread::available_parallelism().map_or(1, |cores| cores.get())
+    }
+
+    pub fn size(&self) -> usize {
+        self.workers.len()
+    }
+
+    pub fn submit(&self, job: impl FnOnce() + Send + 'static) {
+        self.completion
+            .pending
+            .lock()
+            .expect("Failed to lock pool")
+            .jobs += 1;
+        self.jobs
+            .as_ref()
+            .expect("pool is running")
+            .send(Box::new(job))
+            .expect("Worker pool stopped");
+    }
+
+    // Blocks until every job submitted so far has finished. Panics if any
+    // of them did.
+    pub fn wait(&self) {
+        let mut pending = self.completion.pending.lock().expect("Failed to lock pool");
+        while pending.jobs > 0 {
+            pending = self
+                .completion
+                .done
+                .wait(pending)
+                .expect("Failed to lock pool");
+        }
+        if pending.panicked {
+            panic!("Thread panicked");
+        }
+    }
+}
+
+impl Drop for WorkerPool {
+    fn drop(&mut self) {
+        // Closing the queue lets each worker finish what it has and stop.
+        drop(self.jobs.take());
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_pool_runs_every_job_before_wait_returns() {
+        let pool = WorkerPool::new(3);
+        assert_eq!(pool.size(), 3);
+        let ran = Arc::new(AtomicUsize::new(0));
+        for _ in 0..100 {
+            let ran = Arc::clone(&ran);
+            pool.submit(move || {
+                ran.fetch_add(1, Ordering::SeqCst);
+            });
+        }
+        pool.wait();
+        assert_eq!(ran.load(Ordering::SeqCst), 100);
+
+        let order = Arc::new(Mutex::new(vec![]));
+        let single = WorkerPool::new(0);
+        for job in 0..5 {
+            let order = Arc::clone(&order);
+            single.submit(move || order.lock().unwrap().push(job));
+        }
+        single.wait();
+        assert_eq!(*order.lock().unwrap(), vec![0, 1, 2, 3, 4]);
+    }
+}